@@ -0,0 +1,41 @@
+// Compares reading runs of adjacent SNPs, which are coalesced into a few large reads,
+// with reading the same SNPs in reverse order, which takes one read per SNP.
+//
+// Run with `cargo run --release --example coalesced_reads`. To count the system calls,
+// run the built example under `strace -c -e trace=read,lseek`.
+use bed_reader::{Bed, BedErrorPlus, ReadOptions, WriteOptions};
+use ndarray as nd;
+use std::time::Instant;
+
+fn main() -> Result<(), Box<BedErrorPlus>> {
+    let (iid_count, sid_count) = (2_000, 20_000);
+    let output_folder = temp_testdir::TempDir::default();
+    let output_file = output_folder.join("coalesced_reads.bed");
+    let val = nd::Array2::<i8>::from_shape_fn((iid_count, sid_count), |(iid_i, sid_i)| {
+        ((iid_i + sid_i) % 3) as i8
+    });
+    WriteOptions::builder(&output_file).write(&val)?;
+    let mut bed = Bed::new(&output_file)?;
+
+    let in_order: Vec<isize> = (0..sid_count as isize).collect();
+    let reversed: Vec<isize> = in_order.iter().rev().copied().collect();
+    for (name, sid_index) in [("in order (coalesced)", in_order), ("reversed", reversed)] {
+        let start = Instant::now();
+        let repeat_count = 5;
+        for _ in 0..repeat_count {
+            ReadOptions::builder()
+                .sid_index(&sid_index)
+                .i8()
+                .read(&mut bed)?;
+        }
+        let seconds = start.elapsed().as_secs_f64() / f64::from(repeat_count);
+        let megabytes = (iid_count / 4 * sid_count) as f64 / 1_000_000.0;
+        println!(
+            "{name}: {:.1} ms per read, {:.0} MB/s",
+            seconds * 1000.0,
+            megabytes / seconds
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,71 @@
+#ifndef BED_READER_H
+#define BED_READER_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+/**
+ * Returned by every function on success.
+ */
+#define BED_READER_OK 0
+
+/**
+ * Returned by every function on failure. See `bed_reader_last_error`.
+ */
+#define BED_READER_ERROR 1
+
+/**
+ * An open .bed file, seen from C only through a pointer.
+ */
+typedef struct BedReader BedReader;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+int bed_reader_open(const char *path, BedReader **out);
+
+int bed_reader_iid_count(BedReader *bed, size_t *out);
+
+int bed_reader_sid_count(BedReader *bed, size_t *out);
+
+int bed_reader_read_f64(BedReader *bed,
+                        size_t iid_start,
+                        size_t iid_end,
+                        size_t sid_start,
+                        size_t sid_end,
+                        bool is_f,
+                        size_t num_threads,
+                        double *out,
+                        size_t out_len);
+
+int bed_reader_read_f32(BedReader *bed,
+                        size_t iid_start,
+                        size_t iid_end,
+                        size_t sid_start,
+                        size_t sid_end,
+                        bool is_f,
+                        size_t num_threads,
+                        float *out,
+                        size_t out_len);
+
+int bed_reader_read_i8(BedReader *bed,
+                       size_t iid_start,
+                       size_t iid_end,
+                       size_t sid_start,
+                       size_t sid_end,
+                       bool is_f,
+                       size_t num_threads,
+                       int8_t *out,
+                       size_t out_len);
+
+void bed_reader_close(BedReader *bed);
+
+size_t bed_reader_last_error(char *buffer, size_t buffer_len);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* BED_READER_H */
@@ -0,0 +1,151 @@
+use anyinput::anyinput;
+use ndarray as nd;
+use std::collections::HashMap;
+
+use crate::{Bed, BedError, BedErrorPlus, MissingPolicy, ReadOptions};
+
+// The most genotype values read at once when aggregating.
+const AGGREGATE_CHUNK_VALUES: usize = 8_000_000;
+
+/// How [`Bed::aggregate_by`](struct.Bed.html#method.aggregate_by) combines the genotypes of a group.
+///
+/// Missing values are ignored. To choose otherwise, see
+/// [`StatsOptions::aggregate_by`](struct.StatsOptions.html#method.aggregate_by).
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum Agg {
+    /// The mean count of allele 1 over the group's non-missing values (0.0 to 2.0), or NaN if all
+    /// are missing. Half of this is the group's allele 1 frequency.
+    MeanDosage,
+    /// The total count of allele 1 over the group's non-missing values.
+    AlleleCount,
+}
+
+impl Bed {
+    /// Aggregate genotypes over groups of individuals (samples), one SNP (variant) at a time.
+    ///
+    /// `group_labels` gives each individual's group, for example, a population or pool name.
+    /// Returns the groups, in the order first seen, and a (group count, SNP count) array of
+    /// aggregated values. SNPs are read in chunks, so the full genotype matrix is never held
+    /// in memory. This is useful for pool-seq style comparisons and per-cohort allele
+    /// frequency tables.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Agg, Bed, sample_bed_file};
+    ///
+    /// let mut bed = Bed::new(sample_bed_file("small.bed")?)?;
+    /// // Values are [[1, 0, missing, 0], [2, 0, missing, 2], [0, 1, 2, 0]]
+    /// let (groups, val) = bed.aggregate_by(["a", "a", "b"], Agg::AlleleCount)?;
+    /// assert_eq!(groups, nd::array!["a".to_string(), "b".to_string()]);
+    /// assert_eq!(val, nd::array![[3.0, 0.0, 0.0, 2.0], [0.0, 1.0, 2.0, 0.0]]);
+    ///
+    /// let (_, val) = bed.aggregate_by(["a", "a", "b"], Agg::MeanDosage)?;
+    /// println!("{val:?}"); // Outputs ndarray [[1.5, 0.0, NaN, 1.0], [0.0, 1.0, 2.0, 0.0]]
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[anyinput]
+    pub fn aggregate_by(
+        &mut self,
+        group_labels: AnyIter<AnyString>,
+        agg: Agg,
+    ) -> Result<(nd::Array1<String>, nd::Array2<f64>), Box<BedErrorPlus>> {
+        self.aggregate(group_labels, agg, MissingPolicy::Skip)
+    }
+
+    // Aggregate, handling missing values as missing_policy says.
+    pub(crate) fn aggregate<I, S>(
+        &mut self,
+        group_labels: I,
+        agg: Agg,
+        missing_policy: MissingPolicy,
+    ) -> Result<(nd::Array1<String>, nd::Array2<f64>), Box<BedErrorPlus>>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let iid_count = self.iid_count()?;
+        let sid_count = self.sid_count()?;
+
+        let mut groups: Vec<String> = Vec::new();
+        let mut group_of_label: HashMap<String, usize> = HashMap::new();
+        let group_index: Vec<usize> = group_labels
+            .into_iter()
+            .map(|label| {
+                let label = label.as_ref();
+                if let Some(&group_i) = group_of_label.get(label) {
+                    return group_i;
+                }
+                groups.push(label.to_string());
+                group_of_label.insert(label.to_string(), groups.len() - 1);
+                groups.len() - 1
+            })
+            .collect();
+        if group_index.len() != iid_count {
+            Err(BedError::InconsistentCount(
+                "iid".to_string(),
+                iid_count,
+                group_index.len(),
+            ))?;
+        }
+
+        let mut sum = nd::Array2::<f64>::zeros((groups.len(), sid_count));
+        let mut called_count = nd::Array2::<f64>::zeros((groups.len(), sid_count));
+        let chunk_len = (AGGREGATE_CHUNK_VALUES / iid_count.max(1)).max(1);
+        for chunk_start in (0..sid_count).step_by(chunk_len) {
+            let chunk_end = (chunk_start + chunk_len).min(sid_count);
+            let val = ReadOptions::builder()
+                .sid_index(chunk_start..chunk_end)
+                .f64()
+                .read(self)?;
+            let mut sum_chunk = sum.slice_mut(nd::s![.., chunk_start..chunk_end]);
+            let mut called_chunk = called_count.slice_mut(nd::s![.., chunk_start..chunk_end]);
+            let zip = nd::Zip::from(sum_chunk.columns_mut())
+                .and(called_chunk.columns_mut())
+                .and(val.columns());
+            let add_sid = |mut sum_col: nd::ArrayViewMut1<'_, f64>,
+                           mut called_col: nd::ArrayViewMut1<'_, f64>,
+                           val_col: nd::ArrayView1<'_, f64>| {
+                let mean = if missing_policy == MissingPolicy::MeanImpute {
+                    column_mean(val_col)
+                } else {
+                    f64::NAN
+                };
+                for (&group_i, &value) in group_index.iter().zip(val_col.iter()) {
+                    // A missing value is NaN
+                    let value = match (value.is_nan(), missing_policy) {
+                        (false, _) | (true, MissingPolicy::Propagate) => value,
+                        (true, MissingPolicy::MeanImpute) if !mean.is_nan() => mean,
+                        (true, _) => continue,
+                    };
+                    sum_col[group_i] += value;
+                    called_col[group_i] += 1.0;
+                }
+            };
+            #[cfg(feature = "parallel")]
+            zip.par_for_each(add_sid);
+            #[cfg(not(feature = "parallel"))]
+            zip.for_each(add_sid);
+        }
+
+        let val = match agg {
+            Agg::AlleleCount => sum,
+            Agg::MeanDosage => nd::Zip::from(&sum)
+                .and(&called_count)
+                .map_collect(|&sum, &count| if count > 0.0 { sum / count } else { f64::NAN }),
+        };
+        Ok((nd::Array1::from_vec(groups), val))
+    }
+}
+
+// The mean of a column's non-missing values, or NaN if all are missing.
+fn column_mean(column: nd::ArrayView1<'_, f64>) -> f64 {
+    let (sum, count) = column
+        .iter()
+        .filter(|value| !value.is_nan())
+        .fold((0.0, 0.0), |(sum, count), &value| {
+            (sum + value, count + 1.0)
+        });
+    sum / count
+}
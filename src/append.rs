@@ -0,0 +1,269 @@
+use anyinput::anyinput;
+use ndarray as nd;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::{Bed, BedError, BedErrorPlus, BedVal, Metadata, ReadOptions, WriteOptions};
+
+// The most genotype values held at once while appending.
+const APPEND_CHUNK_VALUES: usize = 8_000_000;
+
+// Append new values to an existing .fam field, using defaults if the new values aren't given.
+fn append_field<T, F>(
+    name: &str,
+    existing: &nd::Array1<T>,
+    new: Option<&Arc<nd::Array1<T>>>,
+    new_count: usize,
+    default: F,
+) -> Result<Option<Arc<nd::Array1<T>>>, Box<BedErrorPlus>>
+where
+    T: Clone,
+    F: Fn(usize) -> T,
+{
+    let mut values = existing.to_vec();
+    if let Some(new) = new {
+        if new.len() != new_count {
+            Err(BedError::InconsistentCount(
+                name.to_string(),
+                new_count,
+                new.len(),
+            ))?;
+        }
+        values.extend(new.iter().cloned());
+    } else {
+        values.extend((existing.len()..existing.len() + new_count).map(default));
+    }
+    Ok(Some(Arc::new(nd::Array1::from_vec(values))))
+}
+
+// Any .bim field given with the new samples must equal the existing .bim file's field.
+fn check_bim_field<T: PartialEq>(
+    name: &str,
+    existing: &nd::Array1<T>,
+    new: Option<&Arc<nd::Array1<T>>>,
+    bim_path: &Path,
+) -> Result<(), Box<BedErrorPlus>> {
+    if let Some(new) = new {
+        if **new != *existing {
+            Err(BedError::BimMismatch(
+                name.to_string(),
+                bim_path.to_path_buf(),
+            ))?;
+        }
+    }
+    Ok(())
+}
+
+// The path of a temporary file next to the given file.
+pub(crate) fn temp_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".append_tmp");
+    path.with_file_name(file_name)
+}
+
+// The path of a backup of the given file, next to it.
+fn backup_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".append_bak");
+    path.with_file_name(file_name)
+}
+
+// Rename the new .bed and .fam files over the originals. If the .fam file can't be
+// renamed, the original .bed file is put back, so the fileset stays consistent.
+fn rename_over(
+    temp_bed_path: &Path,
+    bed_path: &Path,
+    temp_fam_path: &Path,
+    fam_path: &Path,
+) -> Result<(), Box<BedErrorPlus>> {
+    let backup_bed_path = backup_path(bed_path);
+    fs::rename(bed_path, &backup_bed_path)?;
+    let result = fs::rename(temp_bed_path, bed_path).and_then(|()| {
+        fs::rename(temp_fam_path, fam_path).inspect_err(|_| {
+            let _ = fs::remove_file(bed_path);
+        })
+    });
+    if let Err(error) = result {
+        fs::rename(&backup_bed_path, bed_path)?;
+        Err(error)?;
+    }
+    fs::remove_file(&backup_bed_path)?;
+    Ok(())
+}
+
+// Write the existing individuals and then the new ones to temporary .bed and .fam files,
+// a block of SNPs at a time.
+fn write_appended<S, TVal>(
+    bed: &Bed,
+    val: &nd::ArrayBase<S, nd::Ix2>,
+    combined: &Metadata,
+    temp_bed_path: &Path,
+    temp_fam_path: &Path,
+) -> Result<(), Box<BedErrorPlus>>
+where
+    S: nd::Data<Elem = TVal>,
+    TVal: BedVal + Into<f64>,
+{
+    let (new_count, iid_count) = (val.nrows(), bed.iid_count()?);
+    let mut writer = WriteOptions::builder(temp_bed_path)
+        .fam_path(temp_fam_path)
+        .metadata(combined)
+        .skip_bim()
+        .bed_writer(iid_count + new_count)?;
+    let read_options = ReadOptions::<TVal>::builder().build()?;
+    let chunk_sids = (APPEND_CHUNK_VALUES / (iid_count + new_count).max(1)).max(1);
+    let mut sid_start = 0;
+    for chunk in bed.iter_sid_chunks(chunk_sids, &read_options)? {
+        let chunk = chunk?;
+        let sid_end = sid_start + chunk.ncols();
+        let mut block = nd::Array2::<TVal>::default(nd::ShapeBuilder::f((
+            iid_count + new_count,
+            chunk.ncols(),
+        )));
+        block.slice_mut(nd::s![..iid_count, ..]).assign(&chunk);
+        block
+            .slice_mut(nd::s![iid_count.., ..])
+            .assign(&val.slice(nd::s![.., sid_start..sid_end]));
+        writer.write_block(&block)?;
+        sid_start = sid_end;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+impl Bed {
+    /// Append individuals (samples) to an existing .bed/.fam/.bim fileset.
+    ///
+    /// `val` holds the new individuals' genotype data, one row per new individual and one column per
+    /// existing SNP (variant). `metadata` gives the new individuals' .fam fields; any field not
+    /// given gets default values, for example, iid "iid4" for the 4th individual. If `metadata`
+    /// also has .bim fields, they must equal the existing .bim file's, which guards against
+    /// appending data for different SNPs.
+    ///
+    /// Because each byte of a .bed file holds the values of four individuals, the whole .bed file is
+    /// read, repacked, and rewritten, a block of SNPs at a time. The new .bed and .fam files are
+    /// written to temporary files and then renamed over the originals, so a failure while writing
+    /// leaves the originals unchanged. If the .fam file can't be renamed, the original .bed file is
+    /// put back. The .bim file is not rewritten.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, Metadata, WriteOptions, sample_bed_file};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("small.bed");
+    /// let bed = Bed::new(sample_bed_file("small.bed")?)?;
+    /// WriteOptions::builder(&output_file)
+    ///     .metadata(&bed.metadata()?)
+    ///     .write(&bed.read::<i8>()?)?;
+    ///
+    /// let new_val = nd::array![[0i8, 2, 1, -127]];
+    /// let new_metadata = Metadata::builder().fid(["fid4"]).iid(["iid4"]).build()?;
+    /// Bed::append_samples(&output_file, &new_val, &new_metadata)?;
+    ///
+    /// let bed = Bed::new(&output_file)?;
+    /// println!("{:?}", bed.iid()?); // Outputs ndarray ["iid1", "iid2", "iid3", "iid4"]
+    /// println!("{:?}", bed.read::<i8>()?.row(3)); // Outputs ndarray [0, 2, 1, -127]
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[anyinput]
+    pub fn append_samples<S, TVal>(
+        path: AnyPath,
+        val: &nd::ArrayBase<S, nd::Ix2>,
+        metadata: &Metadata,
+    ) -> Result<(), Box<BedErrorPlus>>
+    where
+        S: nd::Data<Elem = TVal>,
+        TVal: BedVal + Into<f64>,
+    {
+        let bed = Bed::new(path)?;
+        let existing = bed.metadata()?;
+        let fam_path = bed.fam_path();
+        let bim_path = bed.bim_path();
+        let sid_count = bed.sid_count()?;
+        let new_count = val.nrows();
+        if val.ncols() != sid_count {
+            Err(BedError::InconsistentCount(
+                "sid".to_string(),
+                sid_count,
+                val.ncols(),
+            ))?;
+        }
+
+        check_bim_field(
+            "chromosome",
+            bed.chromosome()?,
+            metadata.chromosome.as_ref(),
+            &bim_path,
+        )?;
+        check_bim_field("sid", bed.sid()?, metadata.sid.as_ref(), &bim_path)?;
+        check_bim_field(
+            "cm_position",
+            bed.cm_position()?,
+            metadata.cm_position.as_ref(),
+            &bim_path,
+        )?;
+        check_bim_field(
+            "bp_position",
+            bed.bp_position()?,
+            metadata.bp_position.as_ref(),
+            &bim_path,
+        )?;
+        check_bim_field(
+            "allele_1",
+            bed.allele_1()?,
+            metadata.allele_1.as_ref(),
+            &bim_path,
+        )?;
+        check_bim_field(
+            "allele_2",
+            bed.allele_2()?,
+            metadata.allele_2.as_ref(),
+            &bim_path,
+        )?;
+
+        let mut combined = existing.clone();
+        combined.fid = append_field("fid", bed.fid()?, metadata.fid.as_ref(), new_count, |_| {
+            "0".to_string()
+        })?;
+        combined.iid = append_field("iid", bed.iid()?, metadata.iid.as_ref(), new_count, |i| {
+            format!("iid{}", i + 1)
+        })?;
+        combined.father = append_field(
+            "father",
+            bed.father()?,
+            metadata.father.as_ref(),
+            new_count,
+            |_| "0".to_string(),
+        )?;
+        combined.mother = append_field(
+            "mother",
+            bed.mother()?,
+            metadata.mother.as_ref(),
+            new_count,
+            |_| "0".to_string(),
+        )?;
+        combined.sex = append_field("sex", bed.sex()?, metadata.sex.as_ref(), new_count, |_| 0)?;
+        combined.pheno = append_field(
+            "pheno",
+            bed.pheno()?,
+            metadata.pheno.as_ref(),
+            new_count,
+            |_| "0".to_string(),
+        )?;
+
+        let temp_bed_path = temp_path(bed.path());
+        let temp_fam_path = temp_path(&fam_path);
+        let result = write_appended(&bed, val, &combined, &temp_bed_path, &temp_fam_path)
+            .and_then(|()| rename_over(&temp_bed_path, bed.path(), &temp_fam_path, &fam_path));
+        if result.is_err() {
+            // Clean up the files
+            let _ = fs::remove_file(&temp_bed_path);
+            let _ = fs::remove_file(&temp_fam_path);
+        }
+        result
+    }
+}
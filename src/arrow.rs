@@ -0,0 +1,245 @@
+use arrow_array::{ArrayRef, Int8Array, RecordBatch, StringArray};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use derive_builder::Builder;
+use ndarray as nd;
+use std::sync::Arc;
+
+use crate::{Bed, BedError, BedErrorPlus, Index, ReadOptions};
+
+/// The shape of the Arrow record batches made by [`Bed::to_record_batches`](struct.Bed.html#method.to_record_batches).
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash, Default)]
+pub enum ArrowLayout {
+    /// One row per individual (sample): an "iid" column, then one column per SNP (variant),
+    /// named by its sid (default).
+    #[default]
+    Wide,
+    /// One row per (SNP, individual) pair, SNP by SNP: "sid", "iid", and "value" columns.
+    Long,
+}
+
+/// Options for [`Bed::to_record_batches`](struct.Bed.html#method.to_record_batches).
+///
+/// Construct with [`ArrowOptions::builder`](struct.ArrowOptions.html#method.builder).
+#[derive(Debug, Clone, Builder)]
+#[builder(build_fn(error = "Box<BedErrorPlus>"))]
+pub struct ArrowOptions {
+    /// The shape of the record batches (defaults to [`ArrowLayout::Wide`](enum.ArrowLayout.html#variant.Wide)).
+    #[builder(default)]
+    layout: ArrowLayout,
+
+    /// Select which SNPs (variants) to include -- Defaults to all.
+    ///
+    /// See the [Table of Index Expressions](index.html#index-expressions)
+    /// for a list of the supported index expressions.
+    #[builder(default = "Index::All")]
+    #[builder(setter(into))]
+    sid_index: Index,
+}
+
+impl ArrowOptions {
+    /// Options for [`Bed::to_record_batches`](struct.Bed.html#method.to_record_batches).
+    ///
+    /// > See [`Bed::to_record_batches`](struct.Bed.html#method.to_record_batches) for an example.
+    #[must_use]
+    pub fn builder() -> ArrowOptionsBuilder {
+        ArrowOptionsBuilder::default()
+    }
+
+    /// The shape of the record batches.
+    #[must_use]
+    pub fn layout(&self) -> ArrowLayout {
+        self.layout
+    }
+
+    /// The SNPs (variants) to include.
+    #[must_use]
+    pub fn sid_index(&self) -> &Index {
+        &self.sid_index
+    }
+}
+
+/// An iterator of Arrow record batches of genotype data.
+///
+/// Created by [`Bed::to_record_batches`](struct.Bed.html#method.to_record_batches).
+/// Each genotype is an Int8 count of allele 1 (0, 1, or 2), or null if missing.
+/// Iteration stops after the first error.
+pub struct RecordBatches<'a> {
+    bed: &'a mut Bed,
+    schema: SchemaRef,
+    layout: ArrowLayout,
+    batch_rows: usize,
+    iid: nd::Array1<String>,
+    sid_index: Vec<isize>,
+    sid: Vec<String>,
+    row_count: usize,
+    next_row: usize,
+}
+
+fn value_array<'b>(values: impl Iterator<Item = &'b i8>) -> ArrayRef {
+    let values: Int8Array = values
+        .map(|&value| if value == -127 { None } else { Some(value) })
+        .collect();
+    Arc::new(values)
+}
+
+impl RecordBatches<'_> {
+    /// The Arrow schema shared by every record batch.
+    #[must_use]
+    pub fn schema(&self) -> SchemaRef {
+        self.schema.clone()
+    }
+
+    // Rows are individuals.
+    fn wide_batch(&mut self, start: usize, end: usize) -> Result<RecordBatch, Box<BedErrorPlus>> {
+        let val = ReadOptions::builder()
+            .iid_index(start..end)
+            .sid_index(self.sid_index.as_slice())
+            .i8()
+            .read(self.bed)?;
+        let mut columns: Vec<ArrayRef> = Vec::with_capacity(val.ncols() + 1);
+        columns.push(Arc::new(StringArray::from_iter_values(
+            self.iid.slice(nd::s![start..end]).iter(),
+        )));
+        columns.extend(
+            val.columns()
+                .into_iter()
+                .map(|column| value_array(column.iter())),
+        );
+        Ok(RecordBatch::try_new(self.schema.clone(), columns)?)
+    }
+
+    // Rows are (SNP, individual) pairs, SNP by SNP.
+    fn long_batch(&mut self, start: usize, end: usize) -> Result<RecordBatch, Box<BedErrorPlus>> {
+        let iid_count = self.iid.len();
+        let (sid_start, sid_end) = (start / iid_count, (end - 1) / iid_count + 1);
+        let val = ReadOptions::builder()
+            .sid_index(&self.sid_index[sid_start..sid_end])
+            .i8()
+            .f()
+            .read(self.bed)?;
+        let offset = sid_start * iid_count;
+        let rows = start - offset..end - offset;
+
+        let sid = rows
+            .clone()
+            .map(|row| self.sid[sid_start + row / iid_count].as_str());
+        let iid = rows.clone().map(|row| self.iid[row % iid_count].as_str());
+        // "unwrap" is safe because the array is in Fortran order
+        let values = &val.as_slice_memory_order().unwrap()[rows];
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(StringArray::from_iter_values(sid)),
+            Arc::new(StringArray::from_iter_values(iid)),
+            value_array(values.iter()),
+        ];
+        Ok(RecordBatch::try_new(self.schema.clone(), columns)?)
+    }
+}
+
+impl Iterator for RecordBatches<'_> {
+    type Item = Result<RecordBatch, Box<BedErrorPlus>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next_row >= self.row_count {
+            return None;
+        }
+        let start = self.next_row;
+        let end = (start + self.batch_rows).min(self.row_count);
+        let result = match self.layout {
+            ArrowLayout::Wide => self.wide_batch(start, end),
+            ArrowLayout::Long => self.long_batch(start, end),
+        };
+        self.next_row = if result.is_ok() { end } else { self.row_count };
+        Some(result)
+    }
+}
+
+impl Bed {
+    /// Stream genotype data as Arrow record batches, each with up to `batch_rows` rows.
+    ///
+    /// The batches can be handed, without copying, to Arrow-based tools such as `DataFusion`,
+    /// Polars, or a Parquet writer. With [`ArrowLayout::Wide`](enum.ArrowLayout.html#variant.Wide),
+    /// each row is an individual (sample). With [`ArrowLayout::Long`](enum.ArrowLayout.html#variant.Long),
+    /// each row is one genotype. Genotypes are read one batch at a time.
+    ///
+    /// Requires the `arrow` feature.
+    ///
+    /// # Example
+    /// ```
+    /// use bed_reader::{ArrowLayout, ArrowOptions, Bed, sample_bed_file};
+    ///
+    /// let mut bed = Bed::new(sample_bed_file("small.bed")?)?;
+    /// let arrow_options = ArrowOptions::builder().sid_index([0, 2]).build()?;
+    /// let batches = bed.to_record_batches(2, &arrow_options)?;
+    /// let field_names: Vec<String> = batches.schema().fields().iter().map(|f| f.name().clone()).collect();
+    /// assert_eq!(field_names, ["iid", "sid1", "sid3"]);
+    /// let batches = batches.collect::<Result<Vec<_>, _>>()?;
+    /// assert_eq!(batches.len(), 2); // 3 individuals, 2 per batch
+    ///
+    /// let arrow_options = ArrowOptions::builder().layout(ArrowLayout::Long).build()?;
+    /// for batch in bed.to_record_batches(1000, &arrow_options)? {
+    ///     let batch = batch?;
+    ///     assert_eq!(batch.num_rows(), 12); // 3 individuals x 4 SNPs
+    /// }
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn to_record_batches(
+        &mut self,
+        batch_rows: usize,
+        arrow_options: &ArrowOptions,
+    ) -> Result<RecordBatches<'_>, Box<BedErrorPlus>> {
+        if batch_rows == 0 {
+            Err(BedError::ZeroBatchRows)?;
+        }
+        let iid = self.iid()?.clone();
+        let all_sid = self.sid()?;
+        let sid_count = all_sid.len();
+        let sid_index = arrow_options.sid_index.to_vec(sid_count)?;
+        let sid = sid_index
+            .iter()
+            .map(|&sid_i| {
+                let in_sid_i = if sid_i < 0 {
+                    sid_i + sid_count as isize
+                } else {
+                    sid_i
+                };
+                if (0..sid_count as isize).contains(&in_sid_i) {
+                    Ok(all_sid[in_sid_i as usize].clone())
+                } else {
+                    Err(BedError::SidIndexTooBig(sid_i).into())
+                }
+            })
+            .collect::<Result<Vec<_>, Box<BedErrorPlus>>>()?;
+
+        let (fields, row_count) = match arrow_options.layout {
+            ArrowLayout::Wide => {
+                let mut fields = vec![Field::new("iid", DataType::Utf8, false)];
+                fields.extend(
+                    sid.iter()
+                        .map(|sid| Field::new(sid.as_str(), DataType::Int8, true)),
+                );
+                (fields, iid.len())
+            }
+            ArrowLayout::Long => (
+                vec![
+                    Field::new("sid", DataType::Utf8, false),
+                    Field::new("iid", DataType::Utf8, false),
+                    Field::new("value", DataType::Int8, true),
+                ],
+                iid.len() * sid.len(),
+            ),
+        };
+
+        Ok(RecordBatches {
+            bed: self,
+            schema: Arc::new(Schema::new(fields)),
+            layout: arrow_options.layout,
+            batch_rows,
+            iid,
+            sid_index,
+            sid,
+            row_count,
+            next_row: 0,
+        })
+    }
+}
@@ -0,0 +1,132 @@
+use anyinput::anyinput;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+
+use crate::{Bed, BedError, BedErrorPlus, ReadOptions};
+
+// The most genotype values read at once when writing Beagle likelihoods.
+const BEAGLE_CHUNK_VALUES: usize = 8_000_000;
+
+// The likelihood triplets, as text, for zero, one, and two copies of allele 1, then for missing.
+fn likelihood_triplets(error_rate: f64) -> [String; 4] {
+    let called = format!("{:.6}", 1.0 - error_rate);
+    let other = format!("{:.6}", error_rate / 2.0);
+    let uniform = format!("{:.6}", 1.0 / 3.0);
+    [
+        format!("{other}\t{other}\t{called}"),
+        format!("{other}\t{called}\t{other}"),
+        format!("{called}\t{other}\t{other}"),
+        format!("{uniform}\t{uniform}\t{uniform}"),
+    ]
+}
+
+impl Bed {
+    /// Write the hard calls as genotype likelihoods in the gzipped Beagle text format, for
+    /// genotype-likelihood tools such as ANGSD.
+    ///
+    /// The first line is `marker allele1 allele2` followed by each iid three times.
+    /// Each following line gives a SNP's (variant's) sid, its allele 1 and allele 2, and, for each
+    /// individual (sample), the likelihoods of the genotypes allele 1/allele 1, allele 1/allele 2,
+    /// and allele 2/allele 2. The called genotype gets `1 - error_rate` and the other two get
+    /// `error_rate / 2`. A missing call gets `1/3` for each genotype. Values are tab-separated
+    /// with six decimal places.
+    ///
+    /// SNPs are read and compressed in chunks, so neither the genotype matrix nor the output is
+    /// held in memory. Returns an error if `error_rate` is not at least 0.0 and less than 1.0.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, WriteOptions};
+    /// use std::io::Read;
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("small.bed");
+    /// WriteOptions::builder(&output_file)
+    ///     .iid(["i1", "i2"])
+    ///     .sid(["s1", "s2"])
+    ///     .allele_1(["A", "C"])
+    ///     .allele_2(["G", "T"])
+    ///     .write(&nd::array![[2, 0], [1, -127]])?;
+    ///
+    /// let mut bed = Bed::new(&output_file)?;
+    /// let beagle_file = output_folder.join("small.beagle.gz");
+    /// bed.to_beagle_gl(&beagle_file, 0.01)?;
+    ///
+    /// let mut text = String::new();
+    /// flate2::read::GzDecoder::new(std::fs::File::open(&beagle_file)?).read_to_string(&mut text)?;
+    /// let lines: Vec<&str> = text.lines().collect();
+    /// assert_eq!(lines[0], "marker\tallele1\tallele2\ti1\ti1\ti1\ti2\ti2\ti2");
+    /// assert_eq!(
+    ///     lines[1],
+    ///     "s1\tA\tG\t0.990000\t0.005000\t0.005000\t0.005000\t0.990000\t0.005000"
+    /// );
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[anyinput]
+    pub fn to_beagle_gl(
+        &mut self,
+        path: AnyPath,
+        error_rate: f64,
+    ) -> Result<(), Box<BedErrorPlus>> {
+        if !(0.0..1.0).contains(&error_rate) {
+            Err(BedError::BadErrorRate(error_rate))?;
+        }
+        let result = self.write_beagle_gl(path, error_rate);
+        if result.is_err() {
+            // Clean up the file
+            let _ = fs::remove_file(path);
+        }
+        result
+    }
+
+    #[anyinput]
+    fn write_beagle_gl(&mut self, path: AnyPath, error_rate: f64) -> Result<(), Box<BedErrorPlus>> {
+        let triplets = likelihood_triplets(error_rate);
+        let iid_count = self.iid_count()?;
+        let sid_count = self.sid_count()?;
+        let iid = self.iid()?.clone();
+        let sid = self.sid()?.clone();
+        let allele_1 = self.allele_1()?.clone();
+        let allele_2 = self.allele_2()?.clone();
+
+        let mut writer =
+            GzEncoder::new(BufWriter::new(File::create(path)?), Compression::default());
+        write!(writer, "marker\tallele1\tallele2")?;
+        for iid in &iid {
+            write!(writer, "\t{iid}\t{iid}\t{iid}")?;
+        }
+        writeln!(writer)?;
+
+        let chunk_len = (BEAGLE_CHUNK_VALUES / iid_count.max(1)).max(1);
+        for chunk_start in (0..sid_count).step_by(chunk_len) {
+            let chunk_end = (chunk_start + chunk_len).min(sid_count);
+            let val = ReadOptions::builder()
+                .sid_index(chunk_start..chunk_end)
+                .i8()
+                .read(self)?;
+            for (sid_i, column) in (chunk_start..chunk_end).zip(val.columns()) {
+                write!(
+                    writer,
+                    "{}\t{}\t{}",
+                    sid[sid_i], allele_1[sid_i], allele_2[sid_i]
+                )?;
+                for &value in column {
+                    // "as" is safe because non-missing values are 0, 1, or 2
+                    let triplet = if value < 0 {
+                        &triplets[3]
+                    } else {
+                        &triplets[value as usize]
+                    };
+                    write!(writer, "\t{triplet}")?;
+                }
+                writeln!(writer)?;
+            }
+        }
+        writer.finish()?.flush()?;
+        Ok(())
+    }
+}
@@ -15,9 +15,10 @@ use std::ops::Range;
 use std::path::PathBuf;
 
 use crate::{
-    check_and_precompute_iid_index, compute_max_chunk_bytes, compute_max_concurrent_requests,
-    set_up_two_bits_to_value, try_div_4, BedError, BedErrorPlus, BedVal, FromStringArray, Hold,
-    Metadata, ReadOptions, BED_FILE_MAGIC1, BED_FILE_MAGIC2, EMPTY_OPTIONS, STATIC_FETCH_DATA,
+    check_and_precompute_iid_index, check_count_a1_mask, compute_max_chunk_bytes,
+    compute_max_concurrent_requests, format, try_div_4, BedError, BedErrorPlus, BedVal,
+    FromStringArray, Hold, Metadata, ReadOptions, TwoBitsToValue, BED_FILE_MAGIC1, BED_FILE_MAGIC2,
+    EMPTY_OPTIONS, STATIC_FETCH_DATA,
 };
 use crate::{MetadataFields, CB_HEADER_U64};
 
@@ -153,10 +154,9 @@ async fn internal_read_no_alloc<TVal: BedVal>(
     size: usize,
     in_iid_count: usize,
     in_sid_count: usize,
-    is_a1_counted: bool,
+    two_bits_to_value: TwoBitsToValue<'_, TVal>,
     iid_index: &[isize],
     sid_index: &[isize],
-    missing_value: TVal,
     max_concurrent_requests: usize,
     max_chunk_bytes: usize,
     out_val: &mut nd::ArrayViewMut2<'_, TVal>,
@@ -169,7 +169,7 @@ async fn internal_read_no_alloc<TVal: BedVal>(
         return Ok(()); // we must return early because the chucks method doesn't work with size 0
     }
     let chunk_count = max(1, max_chunk_bytes / i_div_4_len as usize);
-    let from_two_bits_to_value = set_up_two_bits_to_value(is_a1_counted, missing_value);
+    let row_two_bits_to_value = two_bits_to_value.for_rows(iid_index, in_iid_count);
     let lower_sid_count = -(in_sid_count as isize);
     let upper_sid_count: isize = (in_sid_count as isize) - 1;
 
@@ -208,7 +208,8 @@ async fn internal_read_no_alloc<TVal: BedVal>(
             &i_div_4_less_start_array,
             &i_mod_4_times_2_array,
             out_val,
-            from_two_bits_to_value,
+            &two_bits_to_value,
+            row_two_bits_to_value.as_deref(),
         );
     }
 
@@ -218,7 +219,7 @@ async fn internal_read_no_alloc<TVal: BedVal>(
 #[inline]
 #[allow(clippy::type_complexity)]
 #[allow(clippy::too_many_arguments)]
-fn extract_ranges(
+pub(crate) fn extract_ranges(
     chunk_count: usize,
     chunk: itertools::Chunk<'_, std::slice::Iter<'_, isize>>,
     chunk_index: usize,
@@ -227,7 +228,7 @@ fn extract_ranges(
     in_iid_count_div4_u64: u64,
     i_div_4_start: u64,
     i_div_4_len: u64,
-) -> Result<(Vec<Range<usize>>, Vec<usize>), Box<BedErrorPlus>> {
+) -> Result<(Vec<Range<usize>>, Vec<(usize, u64)>), Box<BedErrorPlus>> {
     let mut ranges = Vec::with_capacity(chunk_count);
     let mut out_sid_i_vec = Vec::with_capacity(chunk_count);
     for (inner_index, in_sid_i_signed) in chunk.enumerate() {
@@ -239,15 +240,16 @@ fn extract_ranges(
         let range = pos..pos + i_div_4_len as usize;
         debug_assert!(range.end - range.start == i_div_4_len as usize); // real assert
         ranges.push(range);
-        out_sid_i_vec.push(out_sid_i);
+        out_sid_i_vec.push((out_sid_i, in_sid_i));
     }
     Ok((ranges, out_sid_i_vec))
 }
 
 #[inline]
-fn decode_bytes_into_columns<TVal: BedVal>(
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn decode_bytes_into_columns<TVal: BedVal>(
     bytes_slice: &[Bytes],
-    out_sid_i_vec: Vec<usize>,
+    out_sid_i_vec: Vec<(usize, u64)>,
     iid_index: &[isize],
     i_div_4_less_start_array: &nd::prelude::ArrayBase<
         nd::OwnedRepr<usize>,
@@ -255,17 +257,22 @@ fn decode_bytes_into_columns<TVal: BedVal>(
     >,
     i_mod_4_times_2_array: &nd::prelude::ArrayBase<nd::OwnedRepr<u8>, nd::prelude::Dim<[usize; 1]>>,
     out_val: &mut nd::prelude::ArrayBase<nd::ViewRepr<&mut TVal>, nd::prelude::Dim<[usize; 2]>>,
-    from_two_bits_to_value: [TVal; 4],
+    two_bits_to_value: &TwoBitsToValue<'_, TVal>,
+    row_two_bits_to_value: Option<&[[TVal; 4]]>,
 ) {
-    for (bytes, out_sid_i) in bytes_slice.iter().zip(out_sid_i_vec.into_iter()) {
+    for (bytes, (out_sid_i, in_sid_i)) in bytes_slice.iter().zip(out_sid_i_vec.into_iter()) {
         let mut col = out_val.column_mut(out_sid_i);
+        // "as" is safe because in_sid_i is less than in_sid_count
+        let from_two_bits_to_value = two_bits_to_value.for_column(in_sid_i as usize);
         // LATER: Consider doing this in parallel as in the non-cloud version.
         for out_iid_i in 0..iid_index.len() {
             let i_div_4_less_start = i_div_4_less_start_array[out_iid_i];
             let i_mod_4_times_2: u8 = i_mod_4_times_2_array[out_iid_i];
             let encoded: u8 = bytes[i_div_4_less_start];
             let genotype_byte: u8 = (encoded >> i_mod_4_times_2) & 0x03;
-            col[out_iid_i] = from_two_bits_to_value[genotype_byte as usize];
+            col[out_iid_i] = row_two_bits_to_value
+                .map_or(&from_two_bits_to_value, |tables| &tables[out_iid_i])
+                [genotype_byte as usize];
         }
     }
 }
@@ -281,7 +288,7 @@ fn check_file_length(
     let file_len = size as u64;
     let file_len2 = in_iid_count_div4_u64 * (in_sid_count as u64) + CB_HEADER_U64;
     if file_len != file_len2 {
-        Err(BedError::IllFormed(cloud_file.to_string()))?;
+        Err(BedError::IllFormed(PathBuf::from(cloud_file.to_string())))?;
     }
     Ok(in_iid_count_div4_u64)
 }
@@ -294,6 +301,7 @@ async fn read_no_alloc<TVal: BedVal>(
     iid_count: usize,
     sid_count: usize,
     is_a1_counted: bool,
+    count_a1_mask: Option<&nd::Array1<bool>>,
     iid_index: &[isize],
     sid_index: &[isize],
     missing_value: TVal,
@@ -303,6 +311,7 @@ async fn read_no_alloc<TVal: BedVal>(
     val: &mut nd::ArrayViewMut2<'_, TVal>, //mutable slices additionally allow to modify elements. But slices cannot grow - they are just a view into some vector.
 ) -> Result<(), Box<BedErrorPlus>> {
     let (size, bytes) = open_and_check(cloud_file).await?;
+    let two_bits_to_value = TwoBitsToValue::new(is_a1_counted, count_a1_mask, missing_value);
 
     match bytes[2] {
         0 => {
@@ -314,10 +323,9 @@ async fn read_no_alloc<TVal: BedVal>(
                 size,
                 sid_count,
                 iid_count,
-                is_a1_counted,
+                two_bits_to_value.individual_major(),
                 sid_index,
                 iid_index,
-                missing_value,
                 max_concurrent_requests,
                 max_chunk_bytes,
                 &mut val_t,
@@ -330,17 +338,16 @@ async fn read_no_alloc<TVal: BedVal>(
                 size,
                 iid_count,
                 sid_count,
-                is_a1_counted,
+                two_bits_to_value,
                 iid_index,
                 sid_index,
-                missing_value,
                 max_concurrent_requests,
                 max_chunk_bytes,
                 val,
             )
             .await?;
         }
-        _ => Err(BedError::BadMode(cloud_file.to_string()))?,
+        _ => Err(BedError::BadMode(PathBuf::from(cloud_file.to_string())))?,
     };
     Ok(())
 }
@@ -354,7 +361,9 @@ async fn open_and_check(cloud_file: &CloudFile) -> Result<(usize, Bytes), Box<Be
         || BED_FILE_MAGIC2 != bytes[1]
         || (0 != bytes[2] && 1 != bytes[2])
     {
-        Err(BedError::IllFormed(cloud_file.to_string()))?;
+        let path = PathBuf::from(cloud_file.to_string());
+        Err(format::FileFormat::from_header(&bytes)
+            .to_error(&path, BedError::IllFormed(path.clone())))?;
     }
     Ok((size, bytes))
 }
@@ -1999,6 +2008,14 @@ impl BedCloud {
         Ok(self.metadata.clone())
     }
 
+    /// The [`Metadata`](struct.Metadata.html) already known, without reading the .fam or .bim file.
+    ///
+    /// > See [`Bed::metadata_loaded`](struct.Bed.html#method.metadata_loaded) for details and an example.
+    #[must_use]
+    pub fn metadata_loaded(&self) -> &Metadata {
+        &self.metadata
+    }
+
     /// Return the `CloudFile` of the .bed file.
     #[must_use]
     pub fn cloud_file(&self) -> CloudFile {
@@ -2114,6 +2131,15 @@ impl BedCloud {
         val: &mut nd::ArrayViewMut2<'_, TVal>, //mutable slices additionally allow to modify elements. But slices cannot grow - they are just a view into some vector.,
         read_options: &ReadOptions<TVal>,
     ) -> Result<(), Box<BedErrorPlus>> {
+        // Scores can only be attached to a local Bed
+        if let Some((name, _)) = read_options.score_filters.first() {
+            Err(BedError::UnknownScore(name.clone()))?;
+        }
+        // The statistics SNP filters consult are only computed for a local Bed
+        if read_options.sid_filter.is_some() {
+            Err(BedError::UnsupportedSidFilter)?;
+        }
+
         // must do these one-at-a-time because they mutate self to cache the results
         let iid_count = self.iid_count().await?;
         let sid_count = self.sid_count().await?;
@@ -2124,34 +2150,44 @@ impl BedCloud {
         let max_chunk_bytes = compute_max_chunk_bytes(read_options.max_chunk_bytes)?;
 
         // If we already have a Vec<isize>, reference it. If we don't, create one and reference it.
-        let iid_hold = Hold::new(&read_options.iid_index, iid_count)?;
+        let iid_hold = self.filter_iids(iid_count, read_options).await?;
         let iid_index = iid_hold.as_ref();
-        let sid_hold = Hold::new(&read_options.sid_index, sid_count)?;
+        let sid_hold = self.filter_sids(sid_count, read_options).await?;
         let sid_index = sid_hold.as_ref();
+        check_count_a1_mask(read_options.count_a1_mask.as_ref(), sid_count)?;
 
         let dim = val.dim();
-        if dim != (iid_index.len(), sid_index.len()) {
+        let expected_dim = read_options.output_dim(iid_index.len(), sid_index.len());
+        if dim != expected_dim {
             Err(BedError::InvalidShape(
-                iid_index.len(),
-                sid_index.len(),
+                expected_dim.0,
+                expected_dim.1,
                 dim.0,
                 dim.1,
             ))?;
         }
 
+        // A transposed output is filled through a view with its axes reversed.
+        let mut val = if read_options.is_transposed {
+            val.view_mut().reversed_axes()
+        } else {
+            val.view_mut()
+        };
         read_no_alloc(
             &self.cloud_file,
             iid_count,
             sid_count,
             read_options.is_a1_counted,
+            read_options.count_a1_mask.as_ref(),
             iid_index,
             sid_index,
             read_options.missing_value,
             max_concurrent_requests,
             max_chunk_bytes,
-            &mut val.view_mut(),
+            &mut val,
         )
-        .await
+        .await?;
+        Ok(())
     }
 
     /// Read all genotype data into a preallocated array.
@@ -2226,9 +2262,26 @@ impl BedCloud {
     ) -> Result<nd::Array2<TVal>, Box<BedErrorPlus>> {
         let iid_count_in = self.iid_count().await?;
         let sid_count_in = self.sid_count().await?;
-        let iid_count_out = read_options.iid_index.len(iid_count_in)?;
-        let sid_count_out = read_options.sid_index.len(sid_count_in)?;
-        let shape = ShapeBuilder::set_f((iid_count_out, sid_count_out), read_options.is_f);
+        let iid_count_out = if read_options.iid_where.is_none() {
+            read_options.iid_index.len(iid_count_in)?
+        } else {
+            self.filter_iids(iid_count_in, read_options)
+                .await?
+                .as_ref()
+                .len()
+        };
+        let sid_count_out = if read_options.regions.is_none() {
+            read_options.sid_index.len(sid_count_in)?
+        } else {
+            self.filter_sids(sid_count_in, read_options)
+                .await?
+                .as_ref()
+                .len()
+        };
+        let shape = ShapeBuilder::set_f(
+            read_options.output_dim(iid_count_out, sid_count_out),
+            read_options.is_f,
+        );
         let mut val = nd::Array2::<TVal>::default(shape);
 
         self.read_and_fill_with_options(&mut val.view_mut(), read_options)
@@ -2237,6 +2290,26 @@ impl BedCloud {
         Ok(val)
     }
 
+    // Resolve the sid index, keeping only the SNPs in a region.
+    async fn filter_sids<'a, TVal: BedVal>(
+        &mut self,
+        sid_count: usize,
+        read_options: &'a ReadOptions<TVal>,
+    ) -> Result<Hold<'a>, Box<BedErrorPlus>> {
+        let sid_hold = Hold::new(&read_options.sid_index, sid_count)?;
+        let Some(regions) = &read_options.regions else {
+            return Ok(sid_hold);
+        };
+        let chromosome = self.chromosome().await?.clone();
+        let bp_position = self.bp_position().await?.clone();
+        Ok(Hold::Copy(regions.filter(
+            sid_hold.as_ref(),
+            sid_count,
+            &chromosome,
+            &bp_position,
+        )?))
+    }
+
     // LATER: Support writing to a BedCloud
 
     async fn unlazy_fam<T: FromStringArray<T>>(
@@ -0,0 +1,204 @@
+use derive_builder::Builder;
+use ndarray as nd;
+
+use crate::global_config::{resolve_missing_policy, resolve_num_threads};
+use crate::parallel::{create_pool, par_azip};
+use crate::{Bed, BedError, BedErrorPlus, Dist, Index, MissingPolicy, ReadOptions, StatsOptions};
+
+/// Options for [`bed_dot`](fn.bed_dot.html).
+///
+/// Construct with [`DotOptions::builder`](struct.DotOptions.html#method.builder).
+#[derive(Debug, Clone, Builder)]
+#[builder(build_fn(error = "Box<BedErrorPlus>"))]
+pub struct DotOptions {
+    /// If true, compute `Xᵀ B`, with one row of `B` per individual (sample), rather than
+    /// `X B`, with one row of `B` per SNP (variant) (defaults to false).
+    #[builder(default = "false")]
+    is_transposed: bool,
+
+    /// If true, the default, standardize each SNP (variant), as
+    /// [`standardize`](fn.standardize.html) does, before multiplying. If false, multiply the
+    /// allele counts, with missing values as NaN.
+    #[builder(default = "true")]
+    is_standardized: bool,
+
+    /// How each SNP (variant) is scaled after centering (defaults to
+    /// [`Dist::Unit`](enum.Dist.html#variant.Unit)).
+    #[builder(default = "Dist::Unit")]
+    dist: Dist,
+
+    /// What standardizing does with missing values (defaults to the missing policy of a new
+    /// [`StatsOptions`](struct.StatsOptions.html)).
+    ///
+    /// With [`MissingPolicy::Skip`](enum.MissingPolicy.html#variant.Skip) or
+    /// [`MissingPolicy::MeanImpute`](enum.MissingPolicy.html#variant.MeanImpute), a missing value
+    /// becomes its SNP's mean. With [`MissingPolicy::Propagate`](enum.MissingPolicy.html#variant.Propagate),
+    /// any SNP with a missing value gives NaN products.
+    #[builder(default = "resolve_missing_policy()")]
+    missing_policy: MissingPolicy,
+
+    /// Select which individuals (samples) make the rows of `X` -- Defaults to all.
+    ///
+    /// See the [Table of Index Expressions](index.html#index-expressions)
+    /// for a list of the supported index expressions.
+    #[builder(default = "Index::All")]
+    #[builder(setter(into))]
+    iid_index: Index,
+
+    /// Select which SNPs (variants) make the columns of `X` -- Defaults to all.
+    ///
+    /// See the [Table of Index Expressions](index.html#index-expressions)
+    /// for a list of the supported index expressions.
+    #[builder(default = "Index::All")]
+    #[builder(setter(into))]
+    sid_index: Index,
+
+    /// Number of threads to use (defaults to all processors)
+    #[builder(default, setter(strip_option))]
+    num_threads: Option<usize>,
+}
+
+impl DotOptions {
+    /// Options for [`bed_dot`](fn.bed_dot.html).
+    ///
+    /// > See [`bed_dot`](fn.bed_dot.html) for an example.
+    #[must_use]
+    pub fn builder() -> DotOptionsBuilder {
+        DotOptionsBuilder::default()
+    }
+
+    /// True if `Xᵀ B` is computed, rather than `X B`.
+    #[must_use]
+    pub fn is_transposed(&self) -> bool {
+        self.is_transposed
+    }
+
+    /// True if each SNP (variant) is standardized before multiplying.
+    #[must_use]
+    pub fn is_standardized(&self) -> bool {
+        self.is_standardized
+    }
+
+    /// How each SNP (variant) is scaled after centering.
+    #[must_use]
+    pub fn dist(&self) -> Dist {
+        self.dist
+    }
+
+    /// What standardizing does with missing values.
+    #[must_use]
+    pub fn missing_policy(&self) -> MissingPolicy {
+        self.missing_policy
+    }
+
+    /// The individuals (samples) that make the rows of `X`.
+    #[must_use]
+    pub fn iid_index(&self) -> &Index {
+        &self.iid_index
+    }
+
+    /// The SNPs (variants) that make the columns of `X`.
+    #[must_use]
+    pub fn sid_index(&self) -> &Index {
+        &self.sid_index
+    }
+
+    /// The number of threads, if set.
+    #[must_use]
+    pub fn num_threads(&self) -> Option<usize> {
+        self.num_threads
+    }
+}
+
+/// Multiply the genotype matrix `X` of a [`Bed`](struct.Bed.html), with one row per
+/// individual (sample) and one column per SNP (variant) selected, by `b`, giving `X b`, or,
+/// if [`is_transposed`](struct.DotOptionsBuilder.html#method.is_transposed), `Xᵀ b`.
+///
+/// SNPs are read, and, by default, standardized, a chunk at a time, through a
+/// [`Pipeline`](struct.Pipeline.html), so `X` is never all in memory. This gives, for example,
+/// the products that linear mixed models (LMMs) and principal component analysis (PCA) need.
+/// Returns a [`BedError::DotShapeMismatch`](enum.BedError.html#variant.DotShapeMismatch) error
+/// if `b` doesn't have one row per SNP selected, or, if transposed, one row per individual
+/// selected.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{bed_dot, Bed, DotOptions, WriteOptions};
+///
+/// let output_folder = temp_testdir::TempDir::default();
+/// let output_file = output_folder.join("dot.bed");
+/// WriteOptions::builder(&output_file)
+///     .write(&nd::array![[0, 2, 1], [2, 0, 1], [0, 2, -127], [1, 1, 1]])?;
+///
+/// let mut bed = Bed::new(&output_file)?;
+/// let dot_options = DotOptions::builder().is_standardized(false).build()?;
+/// let xb = bed_dot(&mut bed, &nd::array![[1.0], [10.0], [100.0]].view(), &dot_options)?;
+/// assert_eq!(xb.column(0).slice(nd::s![..2]), nd::array![120.0, 102.0]);
+/// assert!(xb[(2, 0)].is_nan()); // a missing value
+///
+/// let dot_options = DotOptions::builder().is_transposed(true).build()?;
+/// let xtb = bed_dot(&mut bed, &nd::Array2::ones((4, 1)).view(), &dot_options)?;
+/// assert!(xtb.iter().all(|v| v.abs() < 1e-12)); // standardized SNPs sum to 0
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+pub fn bed_dot(
+    bed: &mut Bed,
+    b: &nd::ArrayView2<'_, f64>,
+    dot_options: &DotOptions,
+) -> Result<nd::Array2<f64>, Box<BedErrorPlus>> {
+    let num_threads = resolve_num_threads(dot_options.num_threads)?;
+    let read_options = ReadOptions::builder()
+        .iid_index(dot_options.iid_index.clone())
+        .sid_index(dot_options.sid_index.clone())
+        .num_threads(num_threads)
+        .f64()
+        .build()?;
+    let stats_options = StatsOptions::new().with_missing_policy(dot_options.missing_policy);
+    let iid_count = dot_options.iid_index.len(bed.iid_count()?)?;
+    let mut pipeline = bed.pipeline(&read_options)?;
+    let sid_count = pipeline.sid_count();
+
+    let (b_row_count, out_row_count) = if dot_options.is_transposed {
+        (iid_count, sid_count)
+    } else {
+        (sid_count, iid_count)
+    };
+    if b.nrows() != b_row_count {
+        Err(BedError::DotShapeMismatch(b.nrows(), b_row_count))?;
+    }
+
+    if dot_options.is_standardized {
+        let dist = dot_options.dist;
+        pipeline = pipeline.map(move |_sid_start, block| {
+            stats_options.standardize(block, dist)?;
+            Ok(())
+        });
+    }
+    let mut product = nd::Array2::<f64>::zeros((out_row_count, b.ncols()));
+    let pool = create_pool(num_threads)?;
+    pipeline.for_each(|sid_start, block| {
+        let sid_range = sid_start..sid_start + block.ncols();
+        pool.install(|| {
+            if dot_options.is_transposed {
+                // Each SNP's row of the product is its column times b
+                par_azip!((mut product_row in product.slice_mut(nd::s![sid_range, ..]).axis_iter_mut(nd::Axis(0)),
+                           column in block.axis_iter(nd::Axis(1)))
+                {
+                    nd::linalg::general_mat_vec_mul(1.0, &b.t(), &column, 0.0, &mut product_row);
+                });
+            } else {
+                // Add each individual's row times the block's rows of b
+                let b_block = b.slice(nd::s![sid_range, ..]);
+                par_azip!((mut product_row in product.axis_iter_mut(nd::Axis(0)),
+                           row in block.axis_iter(nd::Axis(0)))
+                {
+                    nd::linalg::general_mat_vec_mul(1.0, &b_block.t(), &row, 1.0, &mut product_row);
+                });
+            }
+        });
+        Ok(())
+    })?;
+    Ok(product)
+}
@@ -0,0 +1,432 @@
+use anyinput::anyinput;
+use ndarray as nd;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::global_config::resolve_max_concurrent_requests;
+use crate::parallel::{create_pool, prelude::*};
+use crate::{
+    check_count_a1_mask, Bed, BedError, BedErrorPlus, BedVal, Index, Metadata, MetadataFields,
+    ReadOptions, ReadOptionsBuilder, SnpStats, StatsOptions,
+};
+
+// For a file, the output columns it fills and its SNPs that fill them.
+type FileJob = (Vec<usize>, Vec<isize>);
+
+/// A set of .bed files, for example, one per chromosome, read as one file with their SNPs
+/// (variants) concatenated.
+///
+/// The files must have the same individuals (samples): their .fam files must agree. The
+/// set's .bim metadata is the files' .bim metadata, in file order. Reads select SNPs by their
+/// index in the concatenation, and each file's SNPs are read in parallel.
+///
+/// Read with [`read`](struct.BedSet.html#method.read),
+/// [`read_with_options`](struct.BedSet.html#method.read_with_options), or
+/// [`ReadOptionsBuilder::read_set`](struct.ReadOptionsBuilder.html#method.read_set).
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{BedSet, ReadOptions, WriteOptions};
+///
+/// let output_folder = temp_testdir::TempDir::default();
+/// let mut paths = Vec::new();
+/// for (chromosome, val) in [
+///     ("1", nd::array![[0, 1], [2, 0], [1, 1]]),
+///     ("2", nd::array![[2, 2, 0], [-127, 1, 0], [0, 0, 1]]),
+/// ] {
+///     let path = output_folder.join(format!("chr{chromosome}.bed"));
+///     WriteOptions::builder(&path)
+///         .iid(["i1", "i2", "i3"])
+///         .chromosome(vec![chromosome; val.ncols()])
+///         .write(&val)?;
+///     paths.push(path);
+/// }
+///
+/// let bed_set = BedSet::new(&paths)?;
+/// assert_eq!(bed_set.dim(), (3, 5));
+/// assert_eq!(bed_set.chromosome()?, nd::array!["1", "1", "2", "2", "2"]);
+/// let val = ReadOptions::builder().sid_index([4, 0, -2]).i8().read_set(&bed_set)?;
+/// assert_eq!(val, nd::array![[0, 0, 2], [0, 2, 1], [1, 1, 0]]);
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+#[derive(Debug)]
+pub struct BedSet {
+    beds: Vec<Bed>,
+    iid_count: usize,
+    // The index of each file's first SNP in the concatenation, and then the SNP count
+    sid_starts: Vec<usize>,
+    metadata: Metadata,
+}
+
+impl BedSet {
+    /// Open the .bed files at `paths`, in order, each with its .fam and .bim files.
+    ///
+    /// > See [`BedSet`](struct.BedSet.html) for an example.
+    #[anyinput]
+    pub fn new(paths: AnyIter<AnyPath>) -> Result<BedSet, Box<BedErrorPlus>> {
+        let beds = paths
+            .map(|path| Bed::new(path))
+            .collect::<Result<Vec<Bed>, Box<BedErrorPlus>>>()?;
+        BedSet::from_beds(beds)
+    }
+
+    /// Make a set from [`Bed`](struct.Bed.html)s, for example, ones made with
+    /// [`Bed::builder`](struct.Bed.html#method.builder).
+    ///
+    /// Reads every file's .fam and .bim metadata. Returns a
+    /// [`BedError::BedSetFamMismatch`](enum.BedError.html#variant.BedSetFamMismatch) error if
+    /// a file's .fam metadata, or its number of individuals, differs from the first file's.
+    /// Metadata skipped by the first file, for example, with
+    /// [`BedBuilder::skip_iid`](struct.BedBuilder.html#method.skip_iid), isn't compared.
+    pub fn from_beds(mut beds: Vec<Bed>) -> Result<BedSet, Box<BedErrorPlus>> {
+        let Some(first) = beds.first_mut() else {
+            Err(BedError::EmptyBedSet)?
+        };
+        let first_metadata = first.metadata()?;
+        let iid_count = first.iid_count()?;
+        let first_path = first.path().to_path_buf();
+
+        let mut sid_starts = vec![0];
+        let mut metadata_list = Vec::with_capacity(beds.len());
+        for bed in &mut beds {
+            let metadata = bed.metadata()?;
+            if metadata.fid != first_metadata.fid
+                || metadata.iid != first_metadata.iid
+                || metadata.father != first_metadata.father
+                || metadata.mother != first_metadata.mother
+                || metadata.sex != first_metadata.sex
+                || metadata.pheno != first_metadata.pheno
+                || bed.iid_count()? != iid_count
+            {
+                Err(BedError::BedSetFamMismatch(
+                    first_path.clone(),
+                    bed.path().to_path_buf(),
+                ))?;
+            }
+            // unwrap always works because sid_starts starts with 0
+            sid_starts.push(sid_starts.last().unwrap() + bed.sid_count()?);
+            metadata_list.push(metadata);
+        }
+
+        let mut metadata = first_metadata;
+        metadata.chromosome = concat_field(&metadata_list, |m| &m.chromosome);
+        metadata.sid = concat_field(&metadata_list, |m| &m.sid);
+        metadata.cm_position = concat_field(&metadata_list, |m| &m.cm_position);
+        metadata.bp_position = concat_field(&metadata_list, |m| &m.bp_position);
+        metadata.allele_1 = concat_field(&metadata_list, |m| &m.allele_1);
+        metadata.allele_2 = concat_field(&metadata_list, |m| &m.allele_2);
+        // Annotations are kept per file, so aren't concatenated
+        metadata.sid_annotations = BTreeMap::new();
+
+        Ok(BedSet {
+            beds,
+            iid_count,
+            sid_starts,
+            metadata,
+        })
+    }
+
+    /// The [`Bed`](struct.Bed.html)s of the set, in order.
+    #[must_use]
+    pub fn beds(&self) -> &[Bed] {
+        &self.beds
+    }
+
+    /// The paths of the .bed files, in order.
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.beds.iter().map(Bed::path)
+    }
+
+    /// Number of individuals (samples)
+    #[must_use]
+    pub fn iid_count(&self) -> usize {
+        self.iid_count
+    }
+
+    /// Number of SNPs (variants) in all the files
+    #[must_use]
+    pub fn sid_count(&self) -> usize {
+        // unwrap always works because sid_starts starts with 0
+        *self.sid_starts.last().unwrap()
+    }
+
+    /// Number of individuals (samples) and SNPs (variants)
+    #[must_use]
+    pub fn dim(&self) -> (usize, usize) {
+        (self.iid_count(), self.sid_count())
+    }
+
+    /// The [`Metadata`](struct.Metadata.html): the first file's .fam metadata and all the
+    /// files' .bim metadata.
+    #[must_use]
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Individual id of each individual (sample)
+    ///
+    /// Returns a [`BedError::CannotUseSkippedMetadata`](enum.BedError.html#variant.CannotUseSkippedMetadata)
+    /// error if the first file skipped it.
+    pub fn iid(&self) -> Result<&nd::Array1<String>, Box<BedErrorPlus>> {
+        field(MetadataFields::Iid, self.metadata.iid())
+    }
+
+    /// SNP id of each SNP (variant), in all the files
+    ///
+    /// Returns a [`BedError::CannotUseSkippedMetadata`](enum.BedError.html#variant.CannotUseSkippedMetadata)
+    /// error if any file skipped it.
+    pub fn sid(&self) -> Result<&nd::Array1<String>, Box<BedErrorPlus>> {
+        field(MetadataFields::Sid, self.metadata.sid())
+    }
+
+    /// Chromosome of each SNP (variant), in all the files
+    ///
+    /// Returns a [`BedError::CannotUseSkippedMetadata`](enum.BedError.html#variant.CannotUseSkippedMetadata)
+    /// error if any file skipped it.
+    pub fn chromosome(&self) -> Result<&nd::Array1<String>, Box<BedErrorPlus>> {
+        field(MetadataFields::Chromosome, self.metadata.chromosome())
+    }
+
+    /// Base-pair position of each SNP (variant), in all the files
+    ///
+    /// Returns a [`BedError::CannotUseSkippedMetadata`](enum.BedError.html#variant.CannotUseSkippedMetadata)
+    /// error if any file skipped it.
+    pub fn bp_position(&self) -> Result<&nd::Array1<i32>, Box<BedErrorPlus>> {
+        field(MetadataFields::BpPosition, self.metadata.bp_position())
+    }
+
+    /// Read all genotype data.
+    ///
+    /// > See [`BedSet`](struct.BedSet.html) for an example.
+    pub fn read<TVal: BedVal>(&self) -> Result<nd::Array2<TVal>, Box<BedErrorPlus>> {
+        let read_options = ReadOptions::<TVal>::builder().build()?;
+        self.read_with_options(&read_options)
+    }
+
+    /// Read genotype data with options, selecting SNPs (variants) by their index in the
+    /// concatenation of all the files.
+    ///
+    /// Returns a [`BedError::UnsupportedBedSetOption`](enum.BedError.html#variant.UnsupportedBedSetOption)
+    /// error for options that select SNPs by metadata, such as regions and score filters.
+    /// Select those SNPs with the set's [`metadata`](struct.BedSet.html#method.metadata) instead.
+    /// At most [`max_concurrent_requests`](struct.ReadOptionsBuilder.html#method.max_concurrent_requests)
+    /// files (default 10) are read at once.
+    ///
+    /// > See [`BedSet`](struct.BedSet.html) for an example.
+    pub fn read_with_options<TVal: BedVal>(
+        &self,
+        read_options: &ReadOptions<TVal>,
+    ) -> Result<nd::Array2<TVal>, Box<BedErrorPlus>> {
+        let jobs = self.jobs(read_options)?;
+        let iid_count = self.iid_count();
+        let iid_count_out = self.beds[0]
+            .filter_iids(iid_count, read_options)?
+            .as_ref()
+            .len();
+        let out_sid_count = jobs.iter().map(|(out_list, _)| out_list.len()).sum();
+
+        // Each file's part is read in the default shape
+        let mut file_options = read_options.clone();
+        file_options.is_f = true;
+        file_options.is_transposed = false;
+        let parts = self.map_files(&file_options, &jobs, Bed::read_with_options)?;
+
+        let shape = nd::ShapeBuilder::set_f(
+            read_options.output_dim(iid_count_out, out_sid_count),
+            read_options.is_f,
+        );
+        let mut val = nd::Array2::<TVal>::default(shape);
+        let mut val_view = if read_options.is_transposed {
+            val.view_mut().reversed_axes()
+        } else {
+            val.view_mut()
+        };
+        for ((out_list, _), part) in jobs.iter().zip(parts) {
+            let Some(part) = part else { continue };
+            for (&out_sid_i, column) in out_list.iter().zip(part.axis_iter(nd::Axis(1))) {
+                val_view.column_mut(out_sid_i).assign(&column);
+            }
+        }
+        Ok(val)
+    }
+
+    /// Compute per-SNP (per-variant) statistics, as [`Bed::stats`](struct.Bed.html#method.stats)
+    /// does, over all the files, merged into one genome-wide [`SnpStats`](struct.SnpStats.html).
+    ///
+    /// > See [`BedSet::stats_with_options`](struct.BedSet.html#method.stats_with_options) for details and an example.
+    pub fn stats(&self) -> Result<SnpStats, Box<BedErrorPlus>> {
+        let read_options = ReadOptions::<f64>::builder().build()?;
+        self.stats_with_options(&read_options, StatsOptions::new())
+    }
+
+    /// Compute per-SNP (per-variant) statistics over the individuals (samples) and SNPs
+    /// selected by `read_options`, summing as `stats_options` says, and merge them into one
+    /// genome-wide [`SnpStats`](struct.SnpStats.html), in the order the SNPs were selected.
+    ///
+    /// Files are processed in parallel, each in one streaming pass, with at most
+    /// [`max_concurrent_requests`](struct.ReadOptionsBuilder.html#method.max_concurrent_requests)
+    /// files (default 10) open at once. Options are supported as by
+    /// [`read_with_options`](struct.BedSet.html#method.read_with_options).
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{BedSet, ReadOptions, StatsOptions, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let mut paths = Vec::new();
+    /// for (chromosome, val) in [
+    ///     ("1", nd::array![[0, 1], [2, 0], [1, 1]]),
+    ///     ("2", nd::array![[2, 2, 0], [-127, 1, 0], [0, 0, 1]]),
+    /// ] {
+    ///     let path = output_folder.join(format!("chr{chromosome}.bed"));
+    ///     WriteOptions::builder(&path).write(&val)?;
+    ///     paths.push(path);
+    /// }
+    ///
+    /// let bed_set = BedSet::new(&paths)?;
+    /// assert_eq!(bed_set.stats()?.call_count(), nd::array![3, 3, 2, 3, 3]);
+    ///
+    /// let read_options = ReadOptions::builder()
+    ///     .sid_index([2, 0])
+    ///     .max_concurrent_requests(1)
+    ///     .f64()
+    ///     .build()?;
+    /// let stats = bed_set.stats_with_options(&read_options, StatsOptions::deterministic())?;
+    /// assert_eq!(stats.mean(), nd::array![1.0, 1.0]);
+    /// assert_eq!(stats.missing_rate(), nd::array![1.0 / 3.0, 0.0]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn stats_with_options<TVal: BedVal + Into<f64>>(
+        &self,
+        read_options: &ReadOptions<TVal>,
+        stats_options: StatsOptions,
+    ) -> Result<SnpStats, Box<BedErrorPlus>> {
+        let jobs = self.jobs(read_options)?;
+        let parts = self.map_files(read_options, &jobs, |bed, part_options| {
+            bed.snp_stats(part_options, stats_options)
+        })?;
+        let sid_count = jobs.iter().map(|(out_list, _)| out_list.len()).sum();
+        Ok(SnpStats::gather(
+            jobs.iter()
+                .zip(parts)
+                .filter_map(|((out_list, _), part)| Some((out_list.as_slice(), part?))),
+            sid_count,
+        ))
+    }
+
+    fn jobs<TVal: BedVal>(
+        &self,
+        read_options: &ReadOptions<TVal>,
+    ) -> Result<Vec<FileJob>, Box<BedErrorPlus>> {
+        if read_options.regions.is_some() {
+            Err(BedError::UnsupportedBedSetOption("regions".to_string()))?;
+        }
+        if !read_options.score_filters.is_empty() {
+            Err(BedError::UnsupportedBedSetOption(
+                "score_filters".to_string(),
+            ))?;
+        }
+        if read_options.sid_filter.is_some() {
+            Err(BedError::UnsupportedBedSetOption("sid_filter".to_string()))?;
+        }
+        let sid_count = self.sid_count();
+        check_count_a1_mask(read_options.count_a1_mask.as_ref(), sid_count)?;
+
+        let mut jobs: Vec<FileJob> = vec![(Vec::new(), Vec::new()); self.beds.len()];
+        for (out_sid_i, in_sid_i_signed) in read_options
+            .sid_index
+            .to_vec(sid_count)?
+            .into_iter()
+            .enumerate()
+        {
+            let in_sid_i = if (0..sid_count as isize).contains(&in_sid_i_signed) {
+                in_sid_i_signed as usize
+            } else if (-(sid_count as isize)..0).contains(&in_sid_i_signed) {
+                (sid_count as isize + in_sid_i_signed) as usize
+            } else {
+                Err(BedError::SidIndexTooBig(in_sid_i_signed))?
+            };
+            let bed_i = self.sid_starts.partition_point(|&start| start <= in_sid_i) - 1;
+            jobs[bed_i].0.push(out_sid_i);
+            jobs[bed_i]
+                .1
+                .push((in_sid_i - self.sid_starts[bed_i]) as isize);
+        }
+        Ok(jobs)
+    }
+
+    // Run `f` on each file with SNPs to process, with options selecting its SNPs, with at most
+    // max_concurrent_requests files at once. Files with no SNPs to process give None.
+    fn map_files<TVal, T, F>(
+        &self,
+        read_options: &ReadOptions<TVal>,
+        jobs: &[FileJob],
+        f: F,
+    ) -> Result<Vec<Option<T>>, Box<BedErrorPlus>>
+    where
+        TVal: BedVal,
+        T: Send,
+        F: Fn(&Bed, &ReadOptions<TVal>) -> Result<T, Box<BedErrorPlus>> + Sync,
+    {
+        let max_concurrent_files =
+            resolve_max_concurrent_requests(read_options.max_concurrent_requests)?.max(1);
+        let sid_starts = &self.sid_starts;
+        create_pool(max_concurrent_files)?.install(|| {
+            self.beds
+                .par_iter()
+                .zip(jobs.par_iter())
+                .enumerate()
+                .map(|(bed_i, (bed, (out_list, in_list)))| {
+                    if out_list.is_empty() {
+                        return Ok(None);
+                    }
+                    let mut part_options = read_options.clone();
+                    part_options.sid_index = Index::Vec(in_list.clone());
+                    part_options.count_a1_mask = read_options.count_a1_mask.as_ref().map(|mask| {
+                        mask.slice(nd::s![sid_starts[bed_i]..sid_starts[bed_i + 1]])
+                            .to_owned()
+                    });
+                    Ok(Some(f(bed, &part_options)?))
+                })
+                .collect()
+        })
+    }
+}
+
+impl<TVal: BedVal> ReadOptionsBuilder<TVal> {
+    /// Read genotype data from a [`BedSet`](struct.BedSet.html).
+    ///
+    /// > See [`BedSet`](struct.BedSet.html) for an example.
+    pub fn read_set(&self, bed_set: &BedSet) -> Result<nd::Array2<TVal>, Box<BedErrorPlus>> {
+        let read_options = self.build()?;
+        bed_set.read_with_options(&read_options)
+    }
+}
+
+// A field of the set's metadata, which is missing only if skipped.
+fn field<T>(
+    field: MetadataFields,
+    values: Option<&nd::Array1<T>>,
+) -> Result<&nd::Array1<T>, Box<BedErrorPlus>> {
+    let Some(values) = values else {
+        Err(BedError::CannotUseSkippedMetadata(field.name().to_string()))?
+    };
+    Ok(values)
+}
+
+// Concatenate a .bim field across files.
+pub(crate) fn concat_field<T: Clone>(
+    metadata_list: &[Metadata],
+    field: impl Fn(&Metadata) -> &Option<Arc<nd::Array1<T>>>,
+) -> Option<Arc<nd::Array1<T>>> {
+    let mut values = Vec::new();
+    for metadata in metadata_list {
+        values.extend(field(metadata).as_ref()?.iter().cloned());
+    }
+    Some(Arc::new(nd::Array1::from_vec(values)))
+}
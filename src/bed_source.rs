@@ -0,0 +1,356 @@
+use bytes::Bytes;
+#[cfg(feature = "tokio")]
+use cloud_file::CloudFile;
+use itertools::Itertools;
+use ndarray as nd;
+use std::cmp::max;
+#[cfg(feature = "tokio")]
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::bed_cloud::{decode_bytes_into_columns, extract_ranges};
+use crate::{
+    check_and_precompute_iid_index, compute_max_chunk_bytes, try_div_4, Bed, BedBuilder, BedError,
+    BedErrorPlus, BedVal, ReadOptions, TwoBitsToValue, BED_FILE_MAGIC1, BED_FILE_MAGIC2,
+    CB_HEADER_U64, CB_HEADER_USIZE,
+};
+#[cfg(feature = "tokio")]
+use crate::{Metadata, MetadataFields, MetadataSource};
+
+/// Where a [`Bed`](struct.Bed.html) reads the bytes of its .bed file, in place of a local file.
+///
+/// Implement this trait to read .bed files kept somewhere else, for example, in an object
+/// store, and give the source to
+/// [`BedBuilder::bed_source`](struct.BedBuilder.html#method.bed_source). Reads then fetch the
+/// byte ranges of the selected SNPs (variants) from the source, with the same
+/// [`ReadOptions`](struct.ReadOptions.html) as local reads. With the `tokio` feature,
+/// [`Bed::builder_url`](struct.Bed.html#method.builder_url) reads from S3, GCS, Azure, HTTP,
+/// or local URLs.
+///
+/// Only reads, counts, and metadata go through the source. Operations that work on the
+/// local file itself, such as memory mapping or checksums, need a local `Bed`.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use std::ops::Range;
+/// use bytes::Bytes;
+/// use bed_reader::{Bed, BedErrorPlus, BedSource, WriteOptions};
+///
+/// // A stand-in for a .bed file kept in a remote store.
+/// #[derive(Debug)]
+/// struct InMemoryBed(Bytes);
+///
+/// impl BedSource for InMemoryBed {
+///     fn size(&self) -> Result<usize, Box<BedErrorPlus>> {
+///         Ok(self.0.len())
+///     }
+///
+///     fn read_ranges(&self, ranges: &[Range<usize>]) -> Result<Vec<Bytes>, Box<BedErrorPlus>> {
+///         Ok(ranges.iter().map(|range| self.0.slice(range.clone())).collect())
+///     }
+/// }
+///
+/// let output_folder = temp_testdir::TempDir::default();
+/// let output_file = output_folder.join("small.bed");
+/// WriteOptions::builder(&output_file).write(&nd::array![[0, 1], [2, 0], [1, 1]])?;
+///
+/// let source = InMemoryBed(std::fs::read(&output_file)?.into());
+/// let bed = Bed::builder(&output_file).bed_source(source).build()?;
+/// assert_eq!(bed.read::<i8>()?, nd::array![[0, 1], [2, 0], [1, 1]]);
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+pub trait BedSource: Debug + Send + Sync {
+    /// The size of the .bed file, in bytes.
+    fn size(&self) -> Result<usize, Box<BedErrorPlus>>;
+
+    /// Read the bytes of each range of the .bed file, in order.
+    ///
+    /// Sources that can fetch ranges concurrently, as object stores can, should do so here.
+    fn read_ranges(&self, ranges: &[Range<usize>]) -> Result<Vec<Bytes>, Box<BedErrorPlus>>;
+}
+
+// Check a source's .bed header and return the size of the file and its mode byte.
+pub(crate) fn check_source(
+    source: &dyn BedSource,
+    path: &Path,
+) -> Result<(usize, u8), Box<BedErrorPlus>> {
+    let size = source.size()?;
+    if size < CB_HEADER_USIZE {
+        Err(BedError::IllFormed(path.to_path_buf()))?;
+    }
+    let header_range = 0..CB_HEADER_USIZE;
+    let header = read_exact_ranges(source, std::slice::from_ref(&header_range), path)?;
+    let header = &header[0];
+    if BED_FILE_MAGIC1 != header[0] || BED_FILE_MAGIC2 != header[1] {
+        Err(BedError::IllFormed(path.to_path_buf()))?;
+    }
+    Ok((size, header[2]))
+}
+
+// Read ranges from a source, checking that it returns each in full.
+fn read_exact_ranges(
+    source: &dyn BedSource,
+    ranges: &[Range<usize>],
+    path: &Path,
+) -> Result<Vec<Bytes>, Box<BedErrorPlus>> {
+    let vec_bytes = source.read_ranges(ranges)?;
+    if vec_bytes.len() != ranges.len()
+        || vec_bytes
+            .iter()
+            .zip(ranges)
+            .any(|(bytes, range)| bytes.len() != range.len())
+    {
+        Err(BedError::IllFormed(path.to_path_buf()))?;
+    }
+    Ok(vec_bytes)
+}
+
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::similar_names)]
+fn internal_read_source<TVal: BedVal>(
+    source: &dyn BedSource,
+    path: &Path,
+    size: usize,
+    in_iid_count: usize,
+    in_sid_count: usize,
+    two_bits_to_value: TwoBitsToValue<'_, TVal>,
+    iid_index: &[isize],
+    sid_index: &[isize],
+    max_chunk_bytes: usize,
+    out_val: &mut nd::ArrayViewMut2<'_, TVal>,
+) -> Result<(), Box<BedErrorPlus>> {
+    let in_iid_count_div4_u64 = try_div_4(in_iid_count, in_sid_count)?;
+    if size as u64 != in_iid_count_div4_u64 * (in_sid_count as u64) + CB_HEADER_U64 {
+        Err(BedError::IllFormed(path.to_path_buf()))?;
+    }
+    let (i_div_4_less_start_array, i_mod_4_times_2_array, i_div_4_start, i_div_4_len) =
+        check_and_precompute_iid_index(in_iid_count, iid_index)?;
+    if i_div_4_len == 0 {
+        return Ok(()); // we must return early because the chunks method doesn't work with size 0
+    }
+    let chunk_count = max(1, max_chunk_bytes / i_div_4_len as usize);
+    let row_two_bits_to_value = two_bits_to_value.for_rows(iid_index, in_iid_count);
+    let lower_sid_count = -(in_sid_count as isize);
+    let upper_sid_count: isize = (in_sid_count as isize) - 1;
+
+    // Fetch the bytes of a chunk of columns at a time, then decode them.
+    for (chunk_index, chunk) in sid_index.iter().chunks(chunk_count).into_iter().enumerate() {
+        let (ranges, out_sid_i_vec) = extract_ranges(
+            chunk_count,
+            chunk,
+            chunk_index,
+            upper_sid_count,
+            lower_sid_count,
+            in_iid_count_div4_u64,
+            i_div_4_start,
+            i_div_4_len,
+        )?;
+        let vec_bytes = read_exact_ranges(source, &ranges, path)?;
+        decode_bytes_into_columns(
+            &vec_bytes,
+            out_sid_i_vec,
+            iid_index,
+            &i_div_4_less_start_array,
+            &i_mod_4_times_2_array,
+            out_val,
+            &two_bits_to_value,
+            row_two_bits_to_value.as_deref(),
+        );
+    }
+    Ok(())
+}
+
+impl BedBuilder {
+    /// Read the bytes of the .bed file from a [`BedSource`](trait.BedSource.html) instead of
+    /// from the local file.
+    ///
+    /// The path given to the builder still names the .bed file, for example, in errors, and,
+    /// unless a [`metadata_source`](struct.BedBuilder.html#method.metadata_source) is also
+    /// given, locates the .fam and .bim files.
+    ///
+    /// > See [`BedSource`](trait.BedSource.html) for an example.
+    #[must_use]
+    pub fn bed_source<S: BedSource + 'static>(mut self, source: S) -> Self {
+        self.byte_source = Some(Some(Arc::new(source)));
+        self
+    }
+}
+
+impl Bed {
+    /// Attempts to start building a [`Bed`](struct.Bed.html) that reads a .bed file, and its
+    /// .fam and .bim files, from a URL.
+    ///
+    /// The URL may name a file in S3 (`s3://`), Google Cloud Storage (`gs://`), Azure
+    /// (`az://`), on the web (`https://`), or on the local disk (`file://`). The .fam and
+    /// .bim files are found next to the .bed file. Reads fetch only the byte ranges of the
+    /// selected SNPs (variants) and take the same [`ReadOptions`](struct.ReadOptions.html) as
+    /// local reads. Each request blocks, so don't call this `Bed`'s methods from async code;
+    /// there, use [`BedCloud`](struct.BedCloud.html). Needs the `tokio` feature.
+    ///
+    /// > See [`BedSource`](trait.BedSource.html) to read from other stores.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, ReadOptions, WriteOptions};
+    /// use cloud_file::abs_path_to_url_string;
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("small.bed");
+    /// WriteOptions::builder(&output_file)
+    ///     .iid(["i1", "i2", "i3"])
+    ///     .write(&nd::array![[0, 1], [2, 0], [1, 1]])?;
+    ///
+    /// let url = abs_path_to_url_string(&output_file)?;
+    /// let bed = Bed::builder_url(&url)?.build()?;
+    /// assert_eq!(bed.iid()?, nd::array!["i1", "i2", "i3"]);
+    /// let val = ReadOptions::builder().sid_index(1).i8().read(&bed)?;
+    /// assert_eq!(val, nd::array![[1], [0], [1]]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub fn builder_url(url: impl AsRef<str>) -> Result<BedBuilder, Box<BedErrorPlus>> {
+        Bed::builder_url_with_options(url, Vec::<(&str, String)>::new())
+    }
+
+    /// Attempts to start building a [`Bed`](struct.Bed.html) that reads from a URL, with
+    /// options, for example, credentials, for the object store.
+    ///
+    /// > See [`Bed::builder_url`](struct.Bed.html#method.builder_url) for details and
+    /// > [`BedCloud::builder_with_options`](struct.BedCloud.html#method.builder_with_options)
+    /// > for the options.
+    #[cfg(feature = "tokio")]
+    pub fn builder_url_with_options<I, K, V>(
+        url: impl AsRef<str>,
+        options: I,
+    ) -> Result<BedBuilder, Box<BedErrorPlus>>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: Into<String>,
+    {
+        let url = url.as_ref();
+        let source = UrlSource::new(CloudFile::new_with_options(url, options)?)?;
+        let mut builder = Bed::builder(url);
+        builder.byte_source = Some(Some(source.clone()));
+        builder.metadata_source = Some(Some(source));
+        Ok(builder)
+    }
+
+    // Fill val from the .bed source. Like the cloud read, mode 0 files are read by swapping axes.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn read_source<TVal: BedVal>(
+        &self,
+        source: &dyn BedSource,
+        iid_count: usize,
+        sid_count: usize,
+        read_options: &ReadOptions<TVal>,
+        iid_index: &[isize],
+        sid_index: &[isize],
+        val: &mut nd::ArrayViewMut2<'_, TVal>,
+    ) -> Result<(), Box<BedErrorPlus>> {
+        let (size, mode) = check_source(source, &self.path)?;
+        let max_chunk_bytes = compute_max_chunk_bytes(read_options.max_chunk_bytes)?;
+        let two_bits_to_value = TwoBitsToValue::new(
+            read_options.is_a1_counted,
+            read_options.count_a1_mask.as_ref(),
+            read_options.missing_value,
+        );
+        match mode {
+            0 => internal_read_source(
+                source,
+                &self.path,
+                size,
+                sid_count,
+                iid_count,
+                two_bits_to_value.individual_major(),
+                sid_index,
+                iid_index,
+                max_chunk_bytes,
+                &mut val.view_mut().reversed_axes(),
+            ),
+            1 => internal_read_source(
+                source,
+                &self.path,
+                size,
+                iid_count,
+                sid_count,
+                two_bits_to_value,
+                iid_index,
+                sid_index,
+                max_chunk_bytes,
+                val,
+            ),
+            _ => Err(BedError::BadMode(self.path.clone()))?,
+        }
+    }
+}
+
+// Reads a .bed file and its .fam and .bim files from an object store, blocking on each request.
+#[cfg(feature = "tokio")]
+#[derive(Debug)]
+struct UrlSource {
+    cloud_file: CloudFile,
+    fam_cloud_file: CloudFile,
+    bim_cloud_file: CloudFile,
+    runtime: tokio::runtime::Runtime,
+}
+
+#[cfg(feature = "tokio")]
+impl UrlSource {
+    fn new(cloud_file: CloudFile) -> Result<Arc<UrlSource>, Box<BedErrorPlus>> {
+        let mut fam_cloud_file = cloud_file.clone();
+        fam_cloud_file.set_extension("fam")?;
+        let mut bim_cloud_file = cloud_file.clone();
+        bim_cloud_file.set_extension("bim")?;
+        Ok(Arc::new(UrlSource {
+            cloud_file,
+            fam_cloud_file,
+            bim_cloud_file,
+            runtime: tokio::runtime::Runtime::new()?,
+        }))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl BedSource for UrlSource {
+    fn size(&self) -> Result<usize, Box<BedErrorPlus>> {
+        Ok(self.runtime.block_on(self.cloud_file.read_file_size())?)
+    }
+
+    fn read_ranges(&self, ranges: &[Range<usize>]) -> Result<Vec<Bytes>, Box<BedErrorPlus>> {
+        Ok(self.runtime.block_on(self.cloud_file.read_ranges(ranges))?)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl MetadataSource for UrlSource {
+    fn read_fam(
+        &self,
+        skip_set: &HashSet<MetadataFields>,
+    ) -> Result<(Metadata, usize), Box<BedErrorPlus>> {
+        self.runtime
+            .block_on(Metadata::new().read_fam_cloud(&self.fam_cloud_file, skip_set))
+    }
+
+    fn read_bim(
+        &self,
+        skip_set: &HashSet<MetadataFields>,
+    ) -> Result<(Metadata, usize), Box<BedErrorPlus>> {
+        self.runtime
+            .block_on(Metadata::new().read_bim_cloud(&self.bim_cloud_file, skip_set))
+    }
+
+    fn iid_count(&self) -> Result<usize, Box<BedErrorPlus>> {
+        Ok(self.runtime.block_on(self.fam_cloud_file.count_lines())?)
+    }
+
+    fn sid_count(&self) -> Result<usize, Box<BedErrorPlus>> {
+        Ok(self.runtime.block_on(self.bim_cloud_file.count_lines())?)
+    }
+}
@@ -0,0 +1,251 @@
+use anyinput::anyinput;
+use ndarray as nd;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::time::Instant;
+
+use crate::{
+    compute_num_threads, format, missing_if::MissingIf, try_div_4, write_columns,
+    write_metadata_files, BedError, BedErrorPlus, BedVal, Metadata, WriteOptions,
+    WriteOptionsBuilder, WriteSummary, CB_HEADER_U64,
+};
+
+/// Writes a .bed file a block of SNPs (variants) at a time, and then its .fam and .bim files.
+///
+/// [`WriteOptions`](struct.WriteOptions.html) writes genotype data given as one 2-D array.
+/// A `BedWriter` instead appends blocks of SNP columns, each encoded and written when given,
+/// so a simulation can stream any number of SNPs to disk without holding them all in memory.
+/// [`finish`](struct.BedWriter.html#method.finish) writes the .fam and .bim files, now that
+/// the number of SNPs is known, and returns a [`WriteSummary`](struct.WriteSummary.html).
+///
+/// Create with [`BedWriter::new`](struct.BedWriter.html#method.new) or, to give metadata and
+/// other write options, with [`WriteOptionsBuilder::bed_writer`](struct.WriteOptionsBuilder.html#method.bed_writer).
+/// Any .bim fields given must cover all the SNPs written.
+///
+/// If a block has a bad value, nothing of it is written and the writer can still be used.
+/// If [`finish`](struct.BedWriter.html#method.finish) fails, the .bed file is removed. A `BedWriter`
+/// dropped without `finish` leaves a .bed file without its .fam and .bim files.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{Bed, WriteOptions};
+///
+/// let output_folder = temp_testdir::TempDir::default();
+/// let output_file = output_folder.join("stream.bed");
+/// let mut writer = WriteOptions::builder(&output_file)
+///     .iid(["i1", "i2", "i3"])
+///     .bed_writer(3)?;
+/// for block_i in 0..4 {
+///     let block = nd::Array2::<i8>::from_elem((3, 2), block_i % 3);
+///     writer.write_block(&block.view())?;
+/// }
+/// let summary = writer.finish()?;
+/// assert_eq!(summary.genotype_counts(), [12, 6, 6]);
+///
+/// let bed = Bed::new(&output_file)?;
+/// assert_eq!(bed.dim()?, (3, 8));
+/// assert_eq!(bed.sid()?[7], "sid8");
+/// assert_eq!(bed.read::<i8>()?.row(0), nd::array![0, 0, 1, 1, 2, 2, 0, 0]);
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+pub struct BedWriter<TVal: BedVal> {
+    write_options_builder: WriteOptionsBuilder<TVal>,
+    path: PathBuf,
+    writer: BufWriter<File>,
+    iid_count: usize,
+    sid_count: usize,
+    is_a1_counted: bool,
+    missing_value: TVal,
+    missing_if: Option<MissingIf>,
+    tolerance: Option<f64>,
+    validate: bool,
+    num_threads: usize,
+    // Per SNP written, the number of 0, 1, 2, and missing values, row after row
+    value_counts: Vec<usize>,
+    snapped_counts: Vec<usize>,
+    start: Instant,
+}
+
+impl<TVal: BedVal> BedWriter<TVal> {
+    /// Create a .bed file for `iid_count` individuals (samples), with default metadata
+    /// and write options.
+    ///
+    /// > See [`BedWriter`](struct.BedWriter.html) for an example.
+    #[anyinput]
+    pub fn new(path: AnyPath, iid_count: usize) -> Result<BedWriter<TVal>, Box<BedErrorPlus>> {
+        WriteOptions::builder(path).bed_writer(iid_count)
+    }
+
+    // The number of individuals (samples).
+    pub(crate) fn iid_count(&self) -> usize {
+        self.iid_count
+    }
+
+    /// The number of SNPs (variants) written so far.
+    #[must_use]
+    pub fn sid_count(&self) -> usize {
+        self.sid_count
+    }
+
+    /// Encode and append a block of SNP (variant) columns, one row per individual (sample).
+    ///
+    /// Values are checked and encoded as by [`WriteOptions`](struct.WriteOptions.html).
+    /// On error, nothing of the block is written.
+    ///
+    /// > See [`BedWriter`](struct.BedWriter.html) for an example.
+    pub fn write_block<S: nd::Data<Elem = TVal>>(
+        &mut self,
+        val: &nd::ArrayBase<S, nd::Ix2>,
+    ) -> Result<(), Box<BedErrorPlus>>
+    where
+        TVal: Into<f64>,
+    {
+        let (iid_count, sid_count) = val.dim();
+        if iid_count != self.iid_count {
+            Err(BedError::InconsistentCount(
+                "iid".to_string(),
+                self.iid_count,
+                iid_count,
+            ))?;
+        }
+        let iid_count_div4_u64 = try_div_4(iid_count, self.sid_count + sid_count)?;
+
+        // Encode the whole block before writing, so a bad value leaves the file unchanged.
+        let mut bytes = Vec::with_capacity(iid_count_div4_u64 as usize * sid_count);
+        let (value_counts, snapped_counts) = write_columns(
+            &mut bytes,
+            &self.path,
+            iid_count_div4_u64,
+            val,
+            self.is_a1_counted,
+            self.missing_value,
+            self.missing_if.as_ref(),
+            self.tolerance,
+            self.validate,
+            self.sid_count,
+            self.num_threads,
+            None,
+        )?;
+        self.writer.write_all(&bytes)?;
+
+        self.value_counts.extend(value_counts.iter());
+        self.snapped_counts.extend(snapped_counts.iter());
+        self.sid_count += sid_count;
+        Ok(())
+    }
+
+    // Give metadata fields not set in the write options, for example, from a file being
+    // converted. Fields set in the write options are kept.
+    pub(crate) fn fill_metadata(&mut self, metadata: &Metadata) -> Result<(), Box<BedErrorPlus>> {
+        // unwrap always works because WriteOptionsBuilder starts with some metadata
+        let given = self.write_options_builder.metadata.as_ref().unwrap();
+        self.write_options_builder.metadata = Some(
+            Metadata::builder()
+                .metadata(metadata)
+                .metadata(given)
+                .build_no_file_check()?,
+        );
+        Ok(())
+    }
+
+    /// Close the .bed file and write the .fam and .bim files.
+    ///
+    /// Metadata not given gets default values, for example, sid "sid8" for the 8th SNP (variant).
+    /// On error, the .bed file is removed.
+    ///
+    /// > See [`BedWriter`](struct.BedWriter.html) for an example.
+    pub fn finish(mut self) -> Result<WriteSummary, Box<BedErrorPlus>> {
+        let result = self.finish_internal();
+        if result.is_err() {
+            // Clean up the file
+            let _ = fs::remove_file(&self.path);
+        }
+        result
+    }
+
+    fn finish_internal(&mut self) -> Result<WriteSummary, Box<BedErrorPlus>> {
+        self.writer.flush()?;
+        let write_options = self
+            .write_options_builder
+            .build(self.iid_count, self.sid_count)?;
+        let result = self.write_metadata(&write_options);
+        if let Some(log) = write_options.log() {
+            log.log_write(&write_options, &result)?;
+        }
+        result
+    }
+
+    fn write_metadata(
+        &self,
+        write_options: &WriteOptions<TVal>,
+    ) -> Result<WriteSummary, Box<BedErrorPlus>> {
+        let policy_metadata = write_options
+            .id_policy
+            .map(|id_policy| write_options.metadata.with_id_policy(id_policy))
+            .transpose()?;
+        let metadata = policy_metadata.as_ref().unwrap_or(&write_options.metadata);
+        write_metadata_files(write_options, metadata)?;
+
+        let iid_count_div4_u64 = try_div_4(self.iid_count, self.sid_count)?;
+        // unwrap always works because there are 4 counts per SNP written
+        let value_counts =
+            nd::Array2::from_shape_vec((self.sid_count, 4), self.value_counts.clone()).unwrap();
+        Ok(WriteSummary::new(
+            value_counts,
+            nd::Array1::from_vec(self.snapped_counts.clone()),
+            CB_HEADER_U64 + iid_count_div4_u64 * self.sid_count as u64,
+            self.start.elapsed(),
+        ))
+    }
+}
+
+impl<TVal: BedVal> WriteOptionsBuilder<TVal> {
+    /// Creates a [`BedWriter`](struct.BedWriter.html) with the options given, to write a .bed
+    /// file for `iid_count` individuals (samples) a block of SNPs (variants) at a time.
+    ///
+    /// The .bed file is created now. The metadata is checked and the .fam and .bim files are
+    /// written by [`BedWriter::finish`](struct.BedWriter.html#method.finish). Returns a
+    /// [`BedError::StreamingDedupSids`](enum.BedError.html#variant.StreamingDedupSids) error
+    /// if [`dedup_sids`](struct.WriteOptionsBuilder.html#method.dedup_sids) is set, because
+    /// SNPs already written can't be dropped.
+    ///
+    /// > See [`BedWriter`](struct.BedWriter.html) for an example.
+    pub fn bed_writer(&self, iid_count: usize) -> Result<BedWriter<TVal>, Box<BedErrorPlus>> {
+        let Some(path) = self.path.as_ref() else {
+            Err(BedError::UninitializedField("path"))?
+        };
+        if self.dedup_sids.unwrap_or(None).is_some() {
+            Err(BedError::StreamingDedupSids)?;
+        }
+        let tolerance = self.tolerance.unwrap_or(None);
+        if let Some(tolerance) = tolerance {
+            if !(0.0..0.5).contains(&tolerance) {
+                Err(BedError::BadTolerance(tolerance))?;
+            }
+        }
+        let num_threads = compute_num_threads(self.num_threads.unwrap_or(None))?;
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(&format::Mode::SnpMajor.header())?;
+
+        Ok(BedWriter {
+            write_options_builder: self.clone(),
+            path: path.to_owned(),
+            writer,
+            iid_count,
+            sid_count: 0,
+            is_a1_counted: self.is_a1_counted.unwrap_or(true),
+            missing_value: self.missing_value.unwrap_or_else(|| TVal::missing()),
+            missing_if: self.missing_if.clone().unwrap_or(None),
+            tolerance,
+            validate: self.validate.unwrap_or(false),
+            num_threads,
+            value_counts: Vec::new(),
+            snapped_counts: Vec::new(),
+            start: Instant::now(),
+        })
+    }
+}
@@ -0,0 +1,402 @@
+use anyinput::anyinput;
+use byteorder::{LittleEndian, ReadBytesExt};
+#[cfg(feature = "gzip")]
+use flate2::read::ZlibDecoder;
+use ndarray as nd;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::oxford_gen::resolve_index;
+use crate::parallel::prelude::*;
+use crate::{
+    compute_num_threads, create_pool, Bed, BedError, BedErrorPlus, FromGenProbabilities, Hold,
+    Metadata, ReadOptions,
+};
+
+// How each SNP's probability data is compressed, from bits 0 and 1 of the header flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compression {
+    None,
+    Zlib,
+    Zstd,
+}
+
+// What the header and variant identifying blocks tell about a BGEN file.
+struct BgenIndex {
+    compression: Compression,
+    iid_count: usize,
+    iid: Option<Vec<String>>,
+    chromosome: Vec<String>,
+    sid: Vec<String>,
+    bp_position: Vec<i32>,
+    allele_1: Vec<String>,
+    allele_2: Vec<String>,
+    // The file position and length of each SNP's genotype data block.
+    blocks: Vec<(u64, usize)>,
+}
+
+fn ill_formed(message: impl Into<String>, path: &Path) -> BedError {
+    BedError::IllFormedBgen(message.into(), path.to_path_buf())
+}
+
+fn unsupported(message: impl Into<String>, path: &Path) -> BedError {
+    BedError::UnsupportedBgen(message.into(), path.to_path_buf())
+}
+
+fn read_string_u16(reader: &mut impl Read) -> Result<String, Box<BedErrorPlus>> {
+    let len = reader.read_u16::<LittleEndian>()?;
+    read_string(reader, len as usize)
+}
+
+fn read_string_u32(reader: &mut impl Read) -> Result<String, Box<BedErrorPlus>> {
+    let len = reader.read_u32::<LittleEndian>()?;
+    read_string(reader, len as usize)
+}
+
+fn read_string(reader: &mut impl Read, len: usize) -> Result<String, Box<BedErrorPlus>> {
+    let mut bytes = vec![0u8; len];
+    reader.read_exact(&mut bytes)?;
+    Ok(String::from_utf8(bytes).map_err(|e| e.utf8_error())?)
+}
+
+// Read the header, the sample identifiers, and every SNP's identifying data, skipping
+// over the genotype data.
+fn read_bgen_index(
+    reader: &mut BufReader<File>,
+    path: &Path,
+) -> Result<BgenIndex, Box<BedErrorPlus>> {
+    let offset = u64::from(reader.read_u32::<LittleEndian>()?);
+    let header_len = u64::from(reader.read_u32::<LittleEndian>()?);
+    let sid_count = reader.read_u32::<LittleEndian>()? as usize;
+    let iid_count = reader.read_u32::<LittleEndian>()? as usize;
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != *b"bgen" && magic != [0u8; 4] {
+        Err(ill_formed("the magic number isn't 'bgen'", path))?;
+    }
+    if header_len < 20 || offset < header_len {
+        Err(ill_formed("the header length is wrong", path))?;
+    }
+    // The flags are the last 4 bytes of the header block, after any free data.
+    reader.seek(SeekFrom::Start(header_len))?;
+    let flags = reader.read_u32::<LittleEndian>()?;
+    let compression = match flags & 3 {
+        0 => Compression::None,
+        1 => Compression::Zlib,
+        2 => Compression::Zstd,
+        _ => Err(ill_formed("the compression flag is 3", path))?,
+    };
+    let layout = (flags >> 2) & 0xF;
+    if layout != 2 {
+        Err(unsupported(
+            format!("layout {layout}; only layout 2 (BGEN v1.2 and v1.3) is supported"),
+            path,
+        ))?;
+    }
+
+    let iid = if flags >> 31 == 1 {
+        reader.read_u32::<LittleEndian>()?; // The sample identifier block's length
+        let block_iid_count = reader.read_u32::<LittleEndian>()? as usize;
+        if block_iid_count != iid_count {
+            Err(BedError::InconsistentCount(
+                "iid".to_string(),
+                iid_count,
+                block_iid_count,
+            ))?;
+        }
+        let iid = (0..iid_count)
+            .map(|_| read_string_u16(reader))
+            .collect::<Result<Vec<String>, _>>()?;
+        Some(iid)
+    } else {
+        None
+    };
+
+    reader.seek(SeekFrom::Start(offset + 4))?;
+    let mut index = BgenIndex {
+        compression,
+        iid_count,
+        iid,
+        chromosome: Vec::with_capacity(sid_count),
+        sid: Vec::with_capacity(sid_count),
+        bp_position: Vec::with_capacity(sid_count),
+        allele_1: Vec::with_capacity(sid_count),
+        allele_2: Vec::with_capacity(sid_count),
+        blocks: Vec::with_capacity(sid_count),
+    };
+    for _ in 0..sid_count {
+        read_string_u16(reader)?; // The variant id; the rsid becomes the sid
+        let sid = read_string_u16(reader)?;
+        index.chromosome.push(read_string_u16(reader)?);
+        let bp_position = reader.read_u32::<LittleEndian>()?;
+        index
+            .bp_position
+            .push(i32::try_from(bp_position).map_err(|_| {
+                ill_formed(format!("SNP '{sid}' has position {bp_position}"), path)
+            })?);
+        let allele_count = reader.read_u16::<LittleEndian>()?;
+        if allele_count != 2 {
+            Err(unsupported(
+                format!("SNP '{sid}' has {allele_count} alleles; only 2 are supported"),
+                path,
+            ))?;
+        }
+        index.allele_1.push(read_string_u32(reader)?);
+        index.allele_2.push(read_string_u32(reader)?);
+        index.sid.push(sid);
+        let block_len = reader.read_u32::<LittleEndian>()?;
+        index
+            .blocks
+            .push((reader.stream_position()?, block_len as usize));
+        reader.seek_relative(i64::from(block_len))?;
+    }
+    Ok(index)
+}
+
+// Decompress a SNP's genotype data block into its probability data.
+#[cfg_attr(
+    not(any(feature = "gzip", feature = "zstd")),
+    allow(unused_variables, unused_mut)
+)]
+fn decompress(
+    block: &[u8],
+    compression: Compression,
+    path: &Path,
+) -> Result<Vec<u8>, Box<BedErrorPlus>> {
+    if compression == Compression::None {
+        return Ok(block.to_vec());
+    }
+    if block.len() < 4 {
+        Err(ill_formed("a genotype data block is too short", path))?;
+    }
+    let (mut len_bytes, compressed) = block.split_at(4);
+    let len = len_bytes.read_u32::<LittleEndian>()? as usize;
+    let mut data = Vec::with_capacity(len);
+    match compression {
+        #[cfg(feature = "gzip")]
+        Compression::Zlib => {
+            ZlibDecoder::new(compressed).read_to_end(&mut data)?;
+        }
+        #[cfg(not(feature = "gzip"))]
+        Compression::Zlib => {
+            Err(unsupported(
+                "zlib compression needs the 'gzip' feature",
+                path,
+            ))?;
+        }
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => {
+            zstd::stream::read::Decoder::new(compressed)?.read_to_end(&mut data)?;
+        }
+        #[cfg(not(feature = "zstd"))]
+        Compression::Zstd => {
+            Err(unsupported(
+                "zstd compression needs the 'zstd' feature",
+                path,
+            ))?;
+        }
+        Compression::None => unreachable!(),
+    }
+    if data.len() != len {
+        Err(ill_formed(
+            "a genotype data block has the wrong uncompressed length",
+            path,
+        ))?;
+    }
+    Ok(data)
+}
+
+// Read `bits` bits, least significant first, starting at bit `bit_start`.
+fn read_bits(data: &[u8], bit_start: usize, bits: usize) -> u64 {
+    let shift = bit_start % 8;
+    let mut word = 0u64;
+    for (i, &byte) in data[bit_start / 8..]
+        .iter()
+        .take((shift + bits).div_ceil(8))
+        .enumerate()
+    {
+        word |= u64::from(byte) << (8 * i);
+    }
+    (word >> shift) & ((1u64 << bits) - 1)
+}
+
+// Decode one SNP's probability data into values for the selected individuals.
+fn decode_snp<TVal: FromGenProbabilities>(
+    data: &[u8],
+    iid_count: usize,
+    iid_index: &[usize],
+    read_options: &ReadOptions<TVal>,
+    mut out: nd::ArrayViewMut1<'_, TVal>,
+    path: &Path,
+) -> Result<(), Box<BedErrorPlus>> {
+    let mut reader = data;
+    if reader.read_u32::<LittleEndian>()? as usize != iid_count {
+        Err(ill_formed("a SNP's individual count doesn't match", path))?;
+    }
+    if reader.read_u16::<LittleEndian>()? != 2 {
+        Err(ill_formed("a SNP's allele count doesn't match", path))?;
+    }
+    reader.read_u16::<LittleEndian>()?; // The minimum and maximum ploidy
+    if reader.len() < iid_count + 2 {
+        Err(ill_formed("a genotype data block is too short", path))?;
+    }
+    let (ploidy, rest) = reader.split_at(iid_count);
+    let (is_phased, bits) = (rest[0] == 1, rest[1] as usize);
+    let probabilities = &rest[2..];
+    if !(1..=32).contains(&bits) {
+        Err(ill_formed(format!("{bits} bits per probability"), path))?;
+    }
+    // With two alleles and two copies, each individual has two probabilities.
+    if probabilities.len() < (iid_count * 2 * bits).div_ceil(8) {
+        Err(ill_formed("a genotype data block is too short", path))?;
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let max_value = ((1u64 << bits) - 1) as f64;
+
+    for (&iid_i, out) in iid_index.iter().zip(out.iter_mut()) {
+        // The top bit marks a missing value and the rest give the ploidy.
+        if ploidy[iid_i] & 0x80 != 0 {
+            *out = read_options.missing_value;
+            continue;
+        }
+        if ploidy[iid_i] & 0x3F != 2 {
+            Err(unsupported(
+                format!("ploidy {}; only 2 is supported", ploidy[iid_i] & 0x3F),
+                path,
+            ))?;
+        }
+        #[allow(clippy::cast_precision_loss)]
+        let first = read_bits(probabilities, iid_i * 2 * bits, bits) as f64 / max_value;
+        #[allow(clippy::cast_precision_loss)]
+        let second = read_bits(probabilities, (iid_i * 2 + 1) * bits, bits) as f64 / max_value;
+        // The probabilities of zero, one, and two copies of allele 1
+        let by_a1_count = if is_phased {
+            // Each haplotype's probability of allele 1
+            [
+                (1.0 - first) * (1.0 - second),
+                first * (1.0 - second) + (1.0 - first) * second,
+                first * second,
+            ]
+        } else {
+            // The probabilities of genotypes 11 and 12; 22 gets the rest
+            [(1.0 - first - second).max(0.0), second, first]
+        };
+        let by_count = if read_options.is_a1_counted {
+            by_a1_count
+        } else {
+            [by_a1_count[2], by_a1_count[1], by_a1_count[0]]
+        };
+        *out = TVal::from_gen_probabilities(by_count, read_options.missing_value);
+    }
+    Ok(())
+}
+
+impl Bed {
+    /// Read genotype data and metadata from a BGEN v1.2 or v1.3 file.
+    ///
+    /// This lets imputed data be used alongside .bed hard calls, with the same output shapes.
+    /// Individuals (samples) and SNPs (variants) are selected with the usual
+    /// [`ReadOptions`](struct.ReadOptions.html) index expressions, and the output's order and
+    /// counted allele follow it, too. With f32 or f64 values, the result is the expected count of
+    /// the counted allele (the dosage). With i8 values, it is the most likely count (a hard call).
+    /// Individuals marked missing get the missing value. SNPs are decompressed and decoded in
+    /// parallel.
+    ///
+    /// Only layout 2 files with two alleles per SNP and two copies per individual, phased or not,
+    /// are supported. Allele 1 is the first allele. Probability data compressed with zlib is
+    /// always supported; zstd needs the `zstd` feature. The returned
+    /// [`Metadata`](struct.Metadata.html) describes the whole file: iid comes from the sample
+    /// identifiers, if any, and sid from the rsids.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, ReadOptions, assert_eq_nan};
+    ///
+    /// // An uncompressed BGEN file with two individuals and one SNP
+    /// let mut bytes: Vec<u8> = vec![20, 0, 0, 0, 20, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0];
+    /// bytes.extend(b"bgen");
+    /// bytes.extend([8, 0, 0, 0]); // Layout 2, no compression, no sample identifiers
+    /// bytes.extend([4, 0, b's', b'n', b'p', b'1', 3, 0, b'r', b's', b'1', 1, 0, b'1']);
+    /// bytes.extend([100, 0, 0, 0, 2, 0, 1, 0, 0, 0, b'A', 1, 0, 0, 0, b'G']);
+    /// // The genotype data: 2 individuals, 2 alleles, ploidy 2 (the second missing),
+    /// // unphased, 8 bits, and P(AA), P(AG) for each
+    /// bytes.extend([16, 0, 0, 0, 2, 0, 0, 0, 2, 0, 2, 2, 2, 130, 0, 8, 0, 255, 0, 0]);
+    /// let temp_out = temp_testdir::TempDir::default();
+    /// let bgen_file = temp_out.join("small.bgen");
+    /// std::fs::write(&bgen_file, &bytes)?;
+    ///
+    /// let read_options = ReadOptions::builder().f64().build()?;
+    /// let (val, metadata) = Bed::read_dosage_from_bgen(&bgen_file, &read_options)?;
+    /// assert_eq_nan(&val, &nd::array![[1.0], [f64::NAN]]);
+    /// println!("{:?}", metadata.sid()); // Outputs optional ndarray Some(["rs1"]...)
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[anyinput]
+    pub fn read_dosage_from_bgen<TVal: FromGenProbabilities>(
+        bgen_path: AnyPath,
+        read_options: &ReadOptions<TVal>,
+    ) -> Result<(nd::Array2<TVal>, Metadata), Box<BedErrorPlus>> {
+        let mut reader = BufReader::new(File::open(bgen_path)?);
+        let index = read_bgen_index(&mut reader, bgen_path)?;
+        let iid_count = index.iid_count;
+        let sid_count = index.blocks.len();
+
+        let iid_hold = Hold::new(&read_options.iid_index, iid_count)?;
+        let iid_index = iid_hold
+            .as_ref()
+            .iter()
+            .map(|&iid_i_signed| {
+                resolve_index(iid_i_signed, iid_count).ok_or(BedError::IidIndexTooBig(iid_i_signed))
+            })
+            .collect::<Result<Vec<usize>, _>>()?;
+        let sid_hold = Hold::new(&read_options.sid_index, sid_count)?;
+        let sid_index = sid_hold.as_ref();
+
+        // Read the selected SNPs' blocks in order, then decode them in parallel
+        let mut blocks = Vec::with_capacity(sid_index.len());
+        for &sid_i_signed in sid_index {
+            let sid_i = resolve_index(sid_i_signed, sid_count)
+                .ok_or(BedError::SidIndexTooBig(sid_i_signed))?;
+            let (start, len) = index.blocks[sid_i];
+            let mut block = vec![0u8; len];
+            reader.seek(SeekFrom::Start(start))?;
+            reader.read_exact(&mut block)?;
+            blocks.push(block);
+        }
+
+        let shape = nd::ShapeBuilder::set_f(
+            read_options.output_dim(iid_index.len(), sid_index.len()),
+            read_options.is_f,
+        );
+        let mut val = nd::Array2::<TVal>::default(shape);
+        // A transposed output has one row, rather than one column, per SNP.
+        let sid_axis = nd::Axis(usize::from(!read_options.is_transposed));
+        let num_threads = compute_num_threads(read_options.num_threads)?;
+        create_pool(num_threads)?.install(|| {
+            val.axis_iter_mut(sid_axis)
+                .into_par_iter()
+                .zip(blocks.par_iter())
+                .try_for_each(|(col, block)| {
+                    let data = decompress(block, index.compression, bgen_path)?;
+                    decode_snp(&data, iid_count, &iid_index, read_options, col, bgen_path)
+                })
+        })?;
+
+        let mut metadata = Metadata::builder();
+        if let Some(iid) = index.iid {
+            metadata.iid(iid);
+        }
+        let metadata = metadata
+            .chromosome(index.chromosome)
+            .sid(index.sid)
+            .bp_position(index.bp_position)
+            .allele_1(index.allele_1)
+            .allele_2(index.allele_2)
+            .build()?;
+
+        Ok((val, metadata))
+    }
+}
@@ -0,0 +1,211 @@
+use std::fs;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+#[cfg(feature = "checksum")]
+use {
+    crate::{to_metadata_path, Bed},
+    std::fs::File,
+    std::io::Read,
+    std::path::PathBuf,
+    xxhash_rust::xxh3::Xxh3,
+};
+
+use crate::BedErrorPlus;
+
+// A .bedcache file starts with these bytes and then the format version.
+#[cfg(feature = "checksum")]
+const CACHE_MAGIC: [u8; 8] = *b"BEDCACHE";
+#[cfg(feature = "checksum")]
+const CACHE_VERSION: u32 = 1;
+// The magic bytes, the version, a stamp for each of the .fam and .bim files, and then
+// the iid count, sid count, .fam digest, and .bim digest.
+#[cfg(feature = "checksum")]
+const CACHE_LEN: usize = 8 + 4 + 2 * FILE_STAMP_LEN + 4 * 8;
+#[cfg(feature = "checksum")]
+const FILE_STAMP_LEN: usize = 8 + 8 + 4;
+// Bytes read at a time when counting lines.
+#[cfg(feature = "checksum")]
+const COUNT_BLOCK_BYTES: usize = 1 << 20;
+
+/// The counts and digests of a fileset's .fam and .bim files, as saved in a `.bedcache` sidecar file.
+///
+/// Get with [`Bed::cache`](struct.Bed.html#method.cache). Also see [`BedBuilder::use_cache`](struct.BedBuilder.html#method.use_cache).
+#[cfg(feature = "checksum")]
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub struct BedCache {
+    iid_count: usize,
+    sid_count: usize,
+    fam_digest: u64,
+    bim_digest: u64,
+}
+
+// The size and modification time (seconds and nanoseconds since the Unix epoch) of a file.
+pub(crate) type FileStamp = (u64, u64, u32);
+
+pub(crate) fn file_stamp(path: &Path) -> Result<FileStamp, Box<BedErrorPlus>> {
+    let metadata = fs::metadata(path)?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    Ok((metadata.len(), modified.as_secs(), modified.subsec_nanos()))
+}
+
+// Count a file's lines, the way BufRead::lines would, and digest its bytes, in one pass.
+#[cfg(feature = "checksum")]
+fn count_and_digest(path: &Path) -> Result<(usize, u64), Box<BedErrorPlus>> {
+    let mut file = File::open(path)?;
+    let mut hasher = Xxh3::new();
+    let mut block = vec![0u8; COUNT_BLOCK_BYTES];
+    let mut line_count = 0;
+    let mut last_byte = b'\n';
+    loop {
+        let len = file.read(&mut block)?;
+        if len == 0 {
+            break;
+        }
+        hasher.update(&block[..len]);
+        line_count += bytecount::count(&block[..len], b'\n');
+        last_byte = block[len - 1];
+    }
+    if last_byte != b'\n' {
+        line_count += 1;
+    }
+    Ok((line_count, hasher.digest()))
+}
+
+// The magic bytes, the version, and the stamps of the .fam and .bim files.
+#[cfg(feature = "checksum")]
+fn cache_header(stamps: [FileStamp; 2]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(CACHE_LEN);
+    bytes.extend_from_slice(&CACHE_MAGIC);
+    bytes.extend_from_slice(&CACHE_VERSION.to_le_bytes());
+    for (len, secs, nanos) in stamps {
+        bytes.extend_from_slice(&len.to_le_bytes());
+        bytes.extend_from_slice(&secs.to_le_bytes());
+        bytes.extend_from_slice(&nanos.to_le_bytes());
+    }
+    bytes
+}
+
+#[cfg(feature = "checksum")]
+impl BedCache {
+    fn to_bytes(self, stamps: [FileStamp; 2]) -> Vec<u8> {
+        let mut bytes = cache_header(stamps);
+        // "as" is safe because the crate requires a 64-bit target
+        bytes.extend_from_slice(&(self.iid_count as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.sid_count as u64).to_le_bytes());
+        bytes.extend_from_slice(&self.fam_digest.to_le_bytes());
+        bytes.extend_from_slice(&self.bim_digest.to_le_bytes());
+        bytes
+    }
+
+    // Returns None if the bytes aren't a cache, of this version, for files with these stamps.
+    fn from_bytes(bytes: &[u8], stamps: [FileStamp; 2]) -> Option<BedCache> {
+        let header = cache_header(stamps);
+        if bytes.len() != CACHE_LEN || bytes[..header.len()] != header {
+            return None;
+        }
+        let u64_at = |i: usize| {
+            let start = header.len() + 8 * i;
+            u64::from_le_bytes(bytes[start..start + 8].try_into().unwrap_or_default())
+        };
+        Some(BedCache {
+            // "as" is safe because the crate requires a 64-bit target
+            iid_count: u64_at(0) as usize,
+            sid_count: u64_at(1) as usize,
+            fam_digest: u64_at(2),
+            bim_digest: u64_at(3),
+        })
+    }
+
+    /// Number of individuals (samples), that is, lines in the .fam file.
+    #[must_use]
+    pub fn iid_count(&self) -> usize {
+        self.iid_count
+    }
+
+    /// Number of SNPs (variants), that is, lines in the .bim file.
+    #[must_use]
+    pub fn sid_count(&self) -> usize {
+        self.sid_count
+    }
+
+    /// An xxHash (XXH3, 64-bit) digest of the .fam file's bytes.
+    #[must_use]
+    pub fn fam_digest(&self) -> u64 {
+        self.fam_digest
+    }
+
+    /// An xxHash (XXH3, 64-bit) digest of the .bim file's bytes.
+    #[must_use]
+    pub fn bim_digest(&self) -> u64 {
+        self.bim_digest
+    }
+}
+
+#[cfg(feature = "checksum")]
+impl Bed {
+    /// The path of the `.bedcache` sidecar file, next to the .bed file.
+    #[must_use]
+    pub fn cache_path(&self) -> PathBuf {
+        to_metadata_path(&self.path, &None, "bedcache")
+    }
+
+    /// Return the counts and digests of the .fam and .bim files, using the `.bedcache` sidecar file.
+    ///
+    /// The sidecar file is used if its version is current and the sizes and modification times
+    /// it records match the .fam and .bim files. Otherwise, the .fam and .bim files are read
+    /// and the sidecar file is rebuilt. Because the sidecar file is only an optimization,
+    /// a failure to save it, for example, in a read-only folder, is ignored.
+    ///
+    /// # Example
+    /// ```
+    /// use bed_reader::{Bed, WriteOptions, sample_bed_file};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("small.bed");
+    /// let bed = Bed::new(sample_bed_file("small.bed")?)?;
+    /// WriteOptions::builder(&output_file)
+    ///     .metadata(&bed.metadata()?)
+    ///     .write(&bed.read::<i8>()?)?;
+    ///
+    /// let bed = Bed::new(&output_file)?;
+    /// let cache = bed.cache()?;
+    /// assert_eq!((cache.iid_count(), cache.sid_count()), (3, 4));
+    /// assert!(bed.cache_path().exists());
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn cache(&self) -> Result<BedCache, Box<BedErrorPlus>> {
+        let fam_path = self.fam_path();
+        let bim_path = self.bim_path();
+        let stamps = [file_stamp(&fam_path)?, file_stamp(&bim_path)?];
+        let cache_path = self.cache_path();
+        if let Ok(bytes) = fs::read(&cache_path) {
+            if let Some(cache) = BedCache::from_bytes(&bytes, stamps) {
+                return Ok(cache);
+            }
+        }
+
+        let (iid_count, fam_digest) = count_and_digest(&fam_path)?;
+        let (sid_count, bim_digest) = count_and_digest(&bim_path)?;
+        let cache = BedCache {
+            iid_count,
+            sid_count,
+            fam_digest,
+            bim_digest,
+        };
+        let _ = fs::write(&cache_path, cache.to_bytes(stamps));
+        Ok(cache)
+    }
+
+    // Fill in any unknown counts from the cache.
+    pub(crate) fn counts_from_cache(&self) -> Result<(), Box<BedErrorPlus>> {
+        let cache = self.cache()?;
+        self.check_max_dims(Some(cache.iid_count), Some(cache.sid_count))?;
+        self.iid_count.get_or_init(|| cache.iid_count);
+        self.sid_count.get_or_init(|| cache.sid_count);
+        Ok(())
+    }
+}
@@ -0,0 +1,330 @@
+use anyinput::anyinput;
+use ndarray as nd;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use xxhash_rust::xxh3::{xxh3_128, xxh3_64};
+
+use crate::{Bed, BedError, BedErrorPlus};
+
+/// A checksum of the packed genotype bytes of each SNP (variant) in a .bed file.
+///
+/// Create with [`Bed::column_checksums`](struct.Bed.html#method.column_checksums).
+/// Save to (and load from) a sidecar file with [`ColumnChecksums::to_file`](struct.ColumnChecksums.html#method.to_file)
+/// and [`ColumnChecksums::from_file`](struct.ColumnChecksums.html#method.from_file).
+/// Compare two versions of a dataset with [`ColumnChecksums::diff`](struct.ColumnChecksums.html#method.diff).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColumnChecksums {
+    sid: nd::Array1<String>,
+    checksum: nd::Array1<u64>,
+}
+
+/// The SNPs (variants) that differ between two [`ColumnChecksums`](struct.ColumnChecksums.html).
+///
+/// Created by [`ColumnChecksums::diff`](struct.ColumnChecksums.html#method.diff).
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+pub struct ChecksumDiff {
+    added: Vec<String>,
+    removed: Vec<String>,
+    changed: Vec<String>,
+}
+
+/// Merkle-style hashes of a .bed file's packed genotype bytes, one per chunk of SNPs (variants), plus a root hash.
+///
+/// Create with [`Bed::chunk_hashes`](struct.Bed.html#method.chunk_hashes).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChunkHashes {
+    chunk_sids: usize,
+    hashes: Vec<u128>,
+    root: u128,
+}
+
+// Domain-separation prefixes, so that a chunk's hash can never equal a combined hash.
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+fn merkle_root(chunk_hashes: &[u128]) -> u128 {
+    if chunk_hashes.is_empty() {
+        return xxh3_128(&[LEAF_PREFIX]);
+    }
+    let mut level = chunk_hashes.to_vec();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => {
+                    let mut bytes = [0u8; 33];
+                    bytes[0] = NODE_PREFIX;
+                    bytes[1..17].copy_from_slice(&left.to_le_bytes());
+                    bytes[17..].copy_from_slice(&right.to_le_bytes());
+                    xxh3_128(&bytes)
+                }
+                // An odd node moves up a level unchanged.
+                _ => pair[0],
+            })
+            .collect();
+    }
+    level[0]
+}
+
+impl Bed {
+    /// Compute an xxHash (XXH3, 64-bit) checksum of each SNP's (variant's) packed bytes.
+    ///
+    /// The checksums are keyed by sid, so they can be saved to a sidecar file and later
+    /// compared, SNP by SNP, against another version of the dataset. This supports
+    /// incremental transfers and detecting which variants changed.
+    ///
+    /// The .bed file must be in the usual SNP-major mode.
+    ///
+    /// # Example
+    /// ```
+    /// use bed_reader::{Bed, ColumnChecksums, sample_bed_file};
+    ///
+    /// let bed = Bed::new(sample_bed_file("small.bed")?)?;
+    /// let checksums = bed.column_checksums()?;
+    /// println!("{:?}", checksums.sid()); // Outputs ndarray ["sid1", "sid2", "sid3", "sid4"]
+    ///
+    /// let temp_out = temp_testdir::TempDir::default();
+    /// let checksum_file = temp_out.join("small.bed.xxh3");
+    /// checksums.to_file(&checksum_file)?;
+    /// let saved = ColumnChecksums::from_file(&checksum_file)?;
+    /// assert!(checksums.diff(&saved).is_empty());
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn column_checksums(&self) -> Result<ColumnChecksums, Box<BedErrorPlus>> {
+        let sid = self.sid()?.clone();
+        let (mut buf_reader, column_bytes, _) = self.open_packed_columns()?;
+
+        let mut bytes = vec![0u8; column_bytes];
+        let checksum = sid
+            .iter()
+            .map(|_| {
+                buf_reader.read_exact(&mut bytes)?;
+                Ok(xxh3_64(&bytes))
+            })
+            .collect::<Result<nd::Array1<u64>, Box<BedErrorPlus>>>()?;
+
+        Ok(ColumnChecksums { sid, checksum })
+    }
+
+    /// Compute Merkle-style hashes of the packed genotype bytes, one per chunk of `chunk_sids`
+    /// SNPs (variants), plus a root hash over all chunks.
+    ///
+    /// Hashes are 128-bit xxHash (XXH3) values. They depend only on the packed bytes and the chunk
+    /// size, so they are the same on every machine and run. A changed genotype changes only its
+    /// chunk's hash and the root. This supports content-addressed stores, such as DVC- or IPFS-like
+    /// systems, that deduplicate unchanged chunks between versions of a dataset.
+    ///
+    /// The root combines pairs of hashes, level by level, until one is left; an odd hash moves up
+    /// unchanged. Chunk and combined hashes are domain-separated. The last chunk may hold fewer SNPs.
+    /// The .bed file must be in the usual SNP-major mode. Returns an error if `chunk_sids` is 0.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("v1.bed");
+    /// let mut val = nd::array![[0, 1, 2, 0, 1], [2, 1, 0, -127, 0]];
+    /// WriteOptions::builder(&output_file).write(&val)?;
+    /// let v1 = Bed::new(&output_file)?.chunk_hashes(2)?;
+    /// assert_eq!(v1.chunk_hashes().len(), 3);
+    ///
+    /// val[[0, 4]] = 2;
+    /// let output_file = output_folder.join("v2.bed");
+    /// WriteOptions::builder(&output_file).write(&val)?;
+    /// let v2 = Bed::new(&output_file)?.chunk_hashes(2)?;
+    /// assert_eq!(v1.chunk_hashes()[..2], v2.chunk_hashes()[..2]);
+    /// assert_ne!(v1.root(), v2.root());
+    /// println!("{:032x}", v2.root());
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn chunk_hashes(&self, chunk_sids: usize) -> Result<ChunkHashes, Box<BedErrorPlus>> {
+        if chunk_sids == 0 {
+            Err(BedError::ZeroChunkSids)?;
+        }
+        let (mut buf_reader, column_bytes, sid_count) = self.open_packed_columns()?;
+
+        let mut bytes = Vec::with_capacity(1 + column_bytes * chunk_sids.min(sid_count));
+        let mut chunk_hashes = Vec::with_capacity(sid_count.div_ceil(chunk_sids));
+        for chunk_start in (0..sid_count).step_by(chunk_sids) {
+            let chunk_len = chunk_sids.min(sid_count - chunk_start);
+            bytes.clear();
+            bytes.push(LEAF_PREFIX);
+            bytes.resize(1 + column_bytes * chunk_len, 0);
+            buf_reader.read_exact(&mut bytes[1..])?;
+            chunk_hashes.push(xxh3_128(&bytes));
+        }
+
+        let root = merkle_root(&chunk_hashes);
+        Ok(ChunkHashes {
+            chunk_sids,
+            hashes: chunk_hashes,
+            root,
+        })
+    }
+}
+
+impl ChunkHashes {
+    /// The number of SNPs (variants) per chunk. The last chunk may hold fewer.
+    #[must_use]
+    pub fn chunk_sids(&self) -> usize {
+        self.chunk_sids
+    }
+
+    /// The hash of each chunk's packed bytes, in file order.
+    #[must_use]
+    pub fn chunk_hashes(&self) -> &[u128] {
+        &self.hashes
+    }
+
+    /// The root hash, over all chunk hashes.
+    #[must_use]
+    pub fn root(&self) -> u128 {
+        self.root
+    }
+}
+
+impl ColumnChecksums {
+    /// SNP (variant) ids, one per checksum.
+    #[must_use]
+    pub fn sid(&self) -> &nd::Array1<String> {
+        &self.sid
+    }
+
+    /// The checksum of each SNP's (variant's) packed bytes.
+    #[must_use]
+    pub fn checksum(&self) -> &nd::Array1<u64> {
+        &self.checksum
+    }
+
+    /// Write the checksums to a sidecar file.
+    ///
+    /// Each line holds a sid and its checksum (as 16 hex digits), separated by a tab.
+    ///
+    /// > See [`Bed::column_checksums`](struct.Bed.html#method.column_checksums) for an example.
+    #[anyinput]
+    pub fn to_file(&self, path: AnyPath) -> Result<(), Box<BedErrorPlus>> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        for (sid, checksum) in self.sid.iter().zip(self.checksum.iter()) {
+            writeln!(writer, "{sid}\t{checksum:016x}")?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Read checksums from a sidecar file written by [`ColumnChecksums::to_file`](struct.ColumnChecksums.html#method.to_file).
+    ///
+    /// > See [`Bed::column_checksums`](struct.Bed.html#method.column_checksums) for an example.
+    #[anyinput]
+    pub fn from_file(path: AnyPath) -> Result<ColumnChecksums, Box<BedErrorPlus>> {
+        let mut sid = Vec::new();
+        let mut checksum = Vec::new();
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() != 2 {
+                Err(BedError::MetadataFieldCount(
+                    2,
+                    fields.len(),
+                    path.to_path_buf(),
+                ))?;
+            }
+            sid.push(fields[0].to_string());
+            checksum.push(u64::from_str_radix(fields[1], 16)?);
+        }
+        Ok(ColumnChecksums {
+            sid: nd::Array1::from_vec(sid),
+            checksum: nd::Array1::from_vec(checksum),
+        })
+    }
+
+    /// Compare these (older) checksums with newer ones, SNP by SNP.
+    ///
+    /// SNPs are matched by sid. The result lists the sids only in the newer checksums (added),
+    /// only in these checksums (removed), and in both but with different checksums (changed).
+    ///
+    /// # Example
+    /// ```
+    /// use bed_reader::{Bed, WriteOptions, sample_bed_file};
+    ///
+    /// let bed = Bed::new(sample_bed_file("small.bed")?)?;
+    /// let older = bed.column_checksums()?;
+    ///
+    /// // Create a new version of the dataset with one genotype changed.
+    /// let mut val = bed.read::<i8>()?;
+    /// val[[0, 2]] = 2;
+    /// let temp_out = temp_testdir::TempDir::default();
+    /// let output_file = temp_out.join("small2.bed");
+    /// WriteOptions::builder(&output_file)
+    ///     .metadata(&bed.metadata()?)
+    ///     .write(&val)?;
+    ///
+    /// let newer = Bed::new(&output_file)?.column_checksums()?;
+    /// let diff = older.diff(&newer);
+    /// println!("{:?}", diff.changed()); // Outputs ["sid3"]
+    /// assert!(diff.added().is_empty() && diff.removed().is_empty());
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[must_use]
+    pub fn diff(&self, newer: &ColumnChecksums) -> ChecksumDiff {
+        let older: HashMap<&str, u64> = self
+            .sid
+            .iter()
+            .map(String::as_str)
+            .zip(self.checksum.iter().copied())
+            .collect();
+        let newer_map: HashMap<&str, u64> = newer
+            .sid
+            .iter()
+            .map(String::as_str)
+            .zip(newer.checksum.iter().copied())
+            .collect();
+
+        let mut diff = ChecksumDiff::default();
+        for (sid, checksum) in newer.sid.iter().zip(newer.checksum.iter()) {
+            match older.get(sid.as_str()) {
+                None => diff.added.push(sid.clone()),
+                Some(old_checksum) if old_checksum != checksum => diff.changed.push(sid.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.removed = self
+            .sid
+            .iter()
+            .filter(|sid| !newer_map.contains_key(sid.as_str()))
+            .cloned()
+            .collect();
+        diff
+    }
+}
+
+impl ChecksumDiff {
+    /// Sids found only in the newer checksums.
+    #[must_use]
+    pub fn added(&self) -> &[String] {
+        &self.added
+    }
+
+    /// Sids found only in the older checksums.
+    #[must_use]
+    pub fn removed(&self) -> &[String] {
+        &self.removed
+    }
+
+    /// Sids found in both, but with different checksums.
+    #[must_use]
+    pub fn changed(&self) -> &[String] {
+        &self.changed
+    }
+
+    /// True if no SNPs (variants) were added, removed, or changed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
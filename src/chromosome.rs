@@ -0,0 +1,153 @@
+use ndarray as nd;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::Metadata;
+
+/// Chromosome naming schemes supported by [`Metadata::normalize_chromosomes`](struct.Metadata.html#method.normalize_chromosomes).
+///
+/// Both schemes understand names with or without a "chr" prefix (in any case), with leading zeros
+/// ("01"), and PLINK's numeric codes for the non-autosomes (23 is X, 24 is Y, 25 is XY, and 26 is MT).
+/// Names that are not chromosomes, for example, "0" (unknown) or unplaced contigs, are left unchanged.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum ChromosomeScheme {
+    /// "1", ..., "22", "X", "Y", "XY", "MT"
+    EnsemblNumeric,
+    /// "chr1", ..., "chr22", "chrX", "chrY", "chrXY", "chrM"
+    UcscChrPrefix,
+}
+
+// Return the Ensembl-style name of a chromosome, or None if the name isn't a chromosome.
+fn canonical_chromosome(chromosome: &str) -> Option<String> {
+    let name = match chromosome.get(..3) {
+        Some(prefix) if chromosome.len() > 3 && prefix.eq_ignore_ascii_case("chr") => {
+            &chromosome[3..]
+        }
+        _ => chromosome,
+    };
+
+    if let Ok(number) = name.parse::<u32>() {
+        return match number {
+            1..=22 => Some(number.to_string()),
+            23 => Some("X".to_string()),
+            24 => Some("Y".to_string()),
+            25 => Some("XY".to_string()),
+            26 => Some("MT".to_string()),
+            _ => None,
+        };
+    }
+
+    match name.to_ascii_uppercase().as_str() {
+        "X" => Some("X".to_string()),
+        "Y" => Some("Y".to_string()),
+        "XY" => Some("XY".to_string()),
+        "M" | "MT" => Some("MT".to_string()),
+        _ => None,
+    }
+}
+
+// A key that sorts chromosomes 1 to 22, X, Y, XY, and MT first, in that order, and then
+// other names alphabetically.
+pub(crate) fn chromosome_sort_key(chromosome: &str) -> (u32, String) {
+    match canonical_chromosome(chromosome).as_deref() {
+        Some("X") => (23, String::new()),
+        Some("Y") => (24, String::new()),
+        Some("XY") => (25, String::new()),
+        Some("MT") => (26, String::new()),
+        Some(number) => (number.parse().unwrap_or(u32::MAX), String::new()),
+        None => (u32::MAX, chromosome.to_string()),
+    }
+}
+
+impl ChromosomeScheme {
+    /// Return a chromosome's name in this scheme.
+    ///
+    /// # Example
+    /// ```
+    /// use bed_reader::ChromosomeScheme;
+    ///
+    /// assert_eq!(ChromosomeScheme::EnsemblNumeric.normalize("chr01"), "1");
+    /// assert_eq!(ChromosomeScheme::EnsemblNumeric.normalize("23"), "X");
+    /// assert_eq!(ChromosomeScheme::UcscChrPrefix.normalize("MT"), "chrM");
+    /// assert_eq!(ChromosomeScheme::UcscChrPrefix.normalize("0"), "0");
+    /// ```
+    #[must_use]
+    pub fn normalize(self, chromosome: &str) -> String {
+        let Some(canonical) = canonical_chromosome(chromosome) else {
+            return chromosome.to_string();
+        };
+        match self {
+            ChromosomeScheme::EnsemblNumeric => canonical,
+            ChromosomeScheme::UcscChrPrefix if canonical == "MT" => "chrM".to_string(),
+            ChromosomeScheme::UcscChrPrefix => format!("chr{canonical}"),
+        }
+    }
+
+    /// True if two chromosome names refer to the same chromosome, whatever their schemes.
+    ///
+    /// Use this when matching, for example, a region's chromosome against a .bim file's chromosomes.
+    ///
+    /// # Example
+    /// ```
+    /// use bed_reader::ChromosomeScheme;
+    ///
+    /// assert!(ChromosomeScheme::same_chromosome("chrX", "23"));
+    /// assert!(ChromosomeScheme::same_chromosome("01", "chr1"));
+    /// assert!(!ChromosomeScheme::same_chromosome("1", "chr2"));
+    /// ```
+    #[must_use]
+    pub fn same_chromosome(chromosome_a: &str, chromosome_b: &str) -> bool {
+        ChromosomeScheme::EnsemblNumeric.normalize(chromosome_a)
+            == ChromosomeScheme::EnsemblNumeric.normalize(chromosome_b)
+    }
+}
+
+impl Metadata {
+    /// Create a new [`Metadata`](struct.Metadata.html) with chromosome names converted to the given scheme.
+    ///
+    /// Also returns a report of each distinct name that changed, as (old name, new name) pairs
+    /// in the order first seen. If the chromosome field is `None`, nothing changes.
+    ///
+    /// > See [`ChromosomeScheme`](enum.ChromosomeScheme.html) for the names understood.
+    ///
+    /// # Example
+    /// ```
+    /// use bed_reader::{Metadata, ChromosomeScheme};
+    ///
+    /// let metadata = Metadata::builder()
+    ///     .chromosome(["chr1", "01", "chrX", "23", "0"])
+    ///     .build()?;
+    /// let (metadata, mapping) = metadata.normalize_chromosomes(ChromosomeScheme::EnsemblNumeric);
+    /// println!("{:?}", metadata.chromosome()); // Outputs optional ndarray Some(["1", "1", "X", "X", "0"]...)
+    /// println!("{mapping:?}"); // Outputs [("chr1", "1"), ("01", "1"), ("chrX", "X"), ("23", "X")]
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[must_use]
+    pub fn normalize_chromosomes(
+        &self,
+        scheme: ChromosomeScheme,
+    ) -> (Metadata, Vec<(String, String)>) {
+        let mut metadata = self.clone();
+        let mut mapping = Vec::new();
+        if let Some(chromosome) = &self.chromosome {
+            let mut seen: HashMap<&str, String> = HashMap::new();
+            let normalized = chromosome
+                .iter()
+                .map(|name| {
+                    seen.entry(name.as_str())
+                        .or_insert_with(|| {
+                            let new_name = scheme.normalize(name);
+                            if new_name != *name {
+                                mapping.push((name.clone(), new_name.clone()));
+                            }
+                            new_name
+                        })
+                        .clone()
+                })
+                .collect::<nd::Array1<String>>();
+            metadata.chromosome = Some(Arc::new(normalized));
+        }
+        (metadata, mapping)
+    }
+}
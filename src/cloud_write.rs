@@ -0,0 +1,288 @@
+use bytes::Bytes;
+use cloud_file::{CloudFile, CloudFileError};
+use ndarray as nd;
+use std::future::poll_fn;
+use std::path::Path;
+use std::pin::Pin;
+use std::time::Instant;
+
+use crate::{
+    compute_num_threads, format, sid_annotation, try_div_4, write_columns, BedError, BedErrorPlus,
+    BedVal, WriteOptions, WriteOptionsBuilder, WriteSummary, CB_HEADER_U64, EMPTY_OPTIONS,
+};
+
+// About how many .bed bytes are encoded before being sent to the multipart upload.
+const CLOUD_WRITE_CHUNK_BYTES: usize = 8 << 20;
+// How many times a .fam, .bim, or sidecar upload is tried.
+const CLOUD_PUT_ATTEMPTS: usize = 3;
+
+impl<TVal> WriteOptionsBuilder<TVal>
+where
+    TVal: BedVal,
+{
+    /// Like [`write_with_summary`](struct.WriteOptionsBuilder.html#method.write_with_summary),
+    /// but writes the .bed (and .fam and .bim) files to the cloud. The path given to
+    /// [`WriteOptions::builder`](struct.WriteOptions.html#method.builder) is a URL.
+    ///
+    /// See ["Cloud URLs and `CloudFile` Examples"](supplemental_document_cloud_urls/index.html) for
+    /// details on specifying a file.
+    ///
+    /// The .bed file is streamed into a multipart upload, a chunk of SNPs (variants) at a time,
+    /// so its bytes are never all in memory. If anything fails, the upload is aborted. The .fam
+    /// and .bim files (and any sid annotations) are formatted in memory and each uploaded with
+    /// one request, tried up to three times. The cloud service also retries its requests. After
+    /// each upload, the size of the object stored is checked, returning a
+    /// [`BedError::CloudUploadSizeMismatch`](enum.BedError.html#variant.CloudUploadSizeMismatch)
+    /// error if it is not the size written.
+    ///
+    /// > Also see [`write_cloud_with_options`](struct.WriteOptionsBuilder.html#method.write_cloud_with_options),
+    /// > which supports cloud options.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{BedCloud, WriteOptions};
+    /// use cloud_file::abs_path_to_url_string;
+    ///
+    /// # #[cfg(feature = "tokio")] Runtime::new().unwrap().block_on(async {
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let url = abs_path_to_url_string(output_folder.join("cloud.bed"))?;
+    /// let val = nd::array![[1, 0, -127, 0], [2, 0, -127, 2], [0, 1, 2, 0]];
+    /// let summary = WriteOptions::builder(&url)
+    ///     .iid(["i1", "i2", "i3"])
+    ///     .write_cloud(&val)
+    ///     .await?;
+    /// assert_eq!(summary.bytes_written(), 7);
+    ///
+    /// let mut bed_cloud = BedCloud::new(&url).await?;
+    /// assert_eq!(bed_cloud.read::<i8>().await?, val);
+    /// assert_eq!(bed_cloud.iid().await?, nd::array!["i1", "i2", "i3"]);
+    /// # Ok::<(), Box<BedErrorPlus>>(())}).unwrap();
+    /// # #[cfg(feature = "tokio")] use {tokio::runtime::Runtime, bed_reader::BedErrorPlus};
+    /// ```
+    pub async fn write_cloud<S: nd::Data<Elem = TVal>>(
+        &mut self,
+        val: &nd::ArrayBase<S, nd::Ix2>,
+    ) -> Result<WriteSummary, Box<BedErrorPlus>>
+    where
+        TVal: Into<f64>,
+    {
+        self.write_cloud_with_options(val, EMPTY_OPTIONS).await
+    }
+
+    #[allow(clippy::doc_link_with_quotes)]
+    /// Like [`write_cloud`](struct.WriteOptionsBuilder.html#method.write_cloud), but with cloud
+    /// options, for example, credentials.
+    ///
+    /// See ["Options, Options, Options"](supplemental_document_options/index.html) for details
+    /// of the different option types.
+    pub async fn write_cloud_with_options<S, I, K, V>(
+        &mut self,
+        val: &nd::ArrayBase<S, nd::Ix2>,
+        cloud_options: I,
+    ) -> Result<WriteSummary, Box<BedErrorPlus>>
+    where
+        S: nd::Data<Elem = TVal>,
+        TVal: Into<f64>,
+        I: IntoIterator<Item = (K, V)> + Clone,
+        K: AsRef<str>,
+        V: Into<String>,
+    {
+        let (iid_count, sid_count) = val.dim();
+        let write_options = self.build(iid_count, sid_count)?;
+        if let Some((sid_index, write_options)) = write_options.dedup_for_write()? {
+            if sid_index.len() != sid_count {
+                let val = val.select(nd::Axis(1), &sid_index);
+                return write_fileset_cloud(&val, &write_options, cloud_options).await;
+            }
+            return write_fileset_cloud(val, &write_options, cloud_options).await;
+        }
+        write_fileset_cloud(val, &write_options, cloud_options).await
+    }
+}
+
+// Upload the .bed file, and then its .fam, .bim, and sid annotation files.
+async fn write_fileset_cloud<S, TVal, I, K, V>(
+    val: &nd::ArrayBase<S, nd::Ix2>,
+    write_options: &WriteOptions<TVal>,
+    cloud_options: I,
+) -> Result<WriteSummary, Box<BedErrorPlus>>
+where
+    S: nd::Data<Elem = TVal>,
+    TVal: BedVal + Into<f64>,
+    I: IntoIterator<Item = (K, V)> + Clone,
+    K: AsRef<str>,
+    V: Into<String>,
+{
+    let start = Instant::now();
+
+    // Format the small files first, so that bad metadata fails before any upload.
+    let policy_metadata = write_options
+        .id_policy
+        .map(|id_policy| write_options.metadata.with_id_policy(id_policy))
+        .transpose()?;
+    let metadata = policy_metadata.as_ref().unwrap_or(&write_options.metadata);
+    let mut uploads = Vec::new();
+    if !write_options.skip_fam() {
+        let mut bytes = Vec::new();
+        metadata.write_fam_to(&mut bytes)?;
+        uploads.push((write_options.fam_path().to_owned(), bytes));
+    }
+    if !write_options.skip_bim() {
+        let mut bytes = Vec::new();
+        if write_options.lazy_bim.is_empty() {
+            metadata.write_bim_to(&mut bytes)?;
+        } else {
+            write_options.lazy_bim.write_bim_to(
+                &mut bytes,
+                write_options.dedup_sids,
+                write_options.id_policy,
+            )?;
+        }
+        uploads.push((write_options.bim_path().to_owned(), bytes));
+    }
+    if !write_options.metadata.sid_annotations.is_empty() {
+        let mut bytes = Vec::new();
+        write_options
+            .metadata
+            .write_sid_annotations_to(&mut bytes)?;
+        let path = write_options
+            .path
+            .with_extension(sid_annotation::SID_ANNOTATIONS_EXTENSION);
+        uploads.push((path, bytes));
+    }
+
+    let cloud_file = to_cloud_file(write_options.path(), cloud_options.clone())?;
+    let (value_counts, snapped_counts, bytes_written) =
+        upload_bed(&cloud_file, val, write_options).await?;
+    for (path, bytes) in uploads {
+        let cloud_file = to_cloud_file(&path, cloud_options.clone())?;
+        put_checked(&cloud_file, Bytes::from(bytes)).await?;
+    }
+
+    Ok(WriteSummary::new(
+        value_counts,
+        snapped_counts,
+        bytes_written,
+        start.elapsed(),
+    ))
+}
+
+fn to_cloud_file<I, K, V>(path: &Path, cloud_options: I) -> Result<CloudFile, Box<BedErrorPlus>>
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    V: Into<String>,
+{
+    let Some(url) = path.to_str() else {
+        Err(BedError::NonUtf8Url(path.to_path_buf()))?
+    };
+    Ok(CloudFile::new_with_options(url, cloud_options)?)
+}
+
+// Stream the encoded .bed bytes into a multipart upload, aborting it on error.
+async fn upload_bed<S, TVal>(
+    cloud_file: &CloudFile,
+    val: &nd::ArrayBase<S, nd::Ix2>,
+    write_options: &WriteOptions<TVal>,
+) -> Result<(nd::Array2<usize>, nd::Array1<usize>, u64), Box<BedErrorPlus>>
+where
+    S: nd::Data<Elem = TVal>,
+    TVal: BedVal + Into<f64>,
+{
+    let (iid_count, sid_count) = val.dim();
+    let iid_count_div4_u64 = try_div_4(iid_count, sid_count)?;
+    let bytes_written = CB_HEADER_U64 + iid_count_div4_u64 * sid_count as u64;
+    let num_threads = compute_num_threads(write_options.num_threads)?;
+    let store = &cloud_file.cloud_service;
+    let store_path = &cloud_file.store_path;
+
+    let (multipart_id, mut upload) = store
+        .put_multipart(store_path)
+        .await
+        .map_err(CloudFileError::from)?;
+    let result = async {
+        let mut value_counts = nd::Array2::<usize>::zeros((sid_count, 4));
+        let mut snapped_counts = Vec::with_capacity(sid_count);
+        let mut bytes = format::Mode::SnpMajor.header().to_vec();
+        let chunk_len = (CLOUD_WRITE_CHUNK_BYTES / (iid_count_div4_u64 as usize).max(1)).max(1);
+        let mut sid_start = 0;
+        if let Some(progress) = &write_options.progress {
+            progress.report(0, sid_count);
+        }
+        while sid_start < sid_count || !bytes.is_empty() {
+            let sid_end = (sid_start + chunk_len).min(sid_count);
+            let (chunk_value_counts, chunk_snapped_counts) = write_columns(
+                &mut bytes,
+                write_options.path(),
+                iid_count_div4_u64,
+                &val.slice(nd::s![.., sid_start..sid_end]),
+                write_options.is_a1_counted,
+                write_options.missing_value,
+                write_options.missing_if.as_ref(),
+                write_options.tolerance,
+                write_options.validate,
+                sid_start,
+                num_threads,
+                None,
+            )?;
+            value_counts
+                .slice_mut(nd::s![sid_start..sid_end, ..])
+                .assign(&chunk_value_counts);
+            snapped_counts.extend(chunk_snapped_counts);
+            sid_start = sid_end;
+
+            let mut rest = bytes.as_slice();
+            while !rest.is_empty() {
+                let count = poll_fn(|cx| Pin::new(&mut *upload).poll_write(cx, rest)).await?;
+                rest = &rest[count..];
+            }
+            bytes.clear();
+            if let Some(progress) = &write_options.progress {
+                progress.report(sid_start, sid_count);
+            }
+        }
+        // Shutting down completes the multipart upload
+        poll_fn(|cx| Pin::new(&mut *upload).poll_shutdown(cx)).await?;
+        check_size(cloud_file, bytes_written as usize).await?;
+        Ok::<_, Box<BedErrorPlus>>((value_counts, nd::Array1::from_vec(snapped_counts)))
+    }
+    .await;
+
+    match result {
+        Ok((value_counts, snapped_counts)) => Ok((value_counts, snapped_counts, bytes_written)),
+        Err(e) => {
+            // Clean up the upload
+            let _ = store.abort_multipart(store_path, &multipart_id).await;
+            Err(e)
+        }
+    }
+}
+
+// Upload a small file with one request, trying again on failure, and then check its size.
+async fn put_checked(cloud_file: &CloudFile, bytes: Bytes) -> Result<(), Box<BedErrorPlus>> {
+    let mut attempt = 1;
+    while let Err(e) = cloud_file
+        .cloud_service
+        .put(&cloud_file.store_path, bytes.clone())
+        .await
+    {
+        if attempt == CLOUD_PUT_ATTEMPTS {
+            Err(CloudFileError::from(e))?;
+        }
+        attempt += 1;
+    }
+    check_size(cloud_file, bytes.len()).await
+}
+
+async fn check_size(cloud_file: &CloudFile, expected: usize) -> Result<(), Box<BedErrorPlus>> {
+    let size = cloud_file.read_file_size().await?;
+    if size != expected {
+        Err(BedError::CloudUploadSizeMismatch(
+            cloud_file.store_path.to_string(),
+            expected,
+            size,
+        ))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,251 @@
+use ndarray as nd;
+use std::fs;
+use std::path::Path;
+
+use crate::bed_set::concat_field;
+use crate::{
+    Bed, BedError, BedErrorPlus, BedVal, BedWriter, Metadata, ReadOptions, WriteOptionsBuilder,
+    WriteSummary,
+};
+
+// The most genotype values held at once when concatenating.
+const CONCAT_CHUNK_VALUES: usize = 8_000_000;
+
+// Check that there is at least one .bed file and that the output isn't one of them.
+fn check_inputs(beds: &[Bed], output: Option<&Path>) -> Result<(), Box<BedErrorPlus>> {
+    if beds.is_empty() {
+        Err(BedError::EmptyConcat)?;
+    }
+    let Some(output) = output.and_then(|output| fs::canonicalize(output).ok()) else {
+        return Ok(());
+    };
+    for bed in beds {
+        if fs::canonicalize(&bed.path).is_ok_and(|input| input == output) {
+            Err(BedError::ConcatOverwritesInput(bed.path.clone()))?;
+        }
+    }
+    Ok(())
+}
+
+fn same_fam(metadata: &Metadata, other: &Metadata) -> bool {
+    metadata.fid == other.fid
+        && metadata.iid == other.iid
+        && metadata.father == other.father
+        && metadata.mother == other.mother
+        && metadata.sex == other.sex
+        && metadata.pheno == other.pheno
+}
+
+fn same_bim(metadata: &Metadata, other: &Metadata) -> bool {
+    metadata.chromosome == other.chromosome
+        && metadata.sid == other.sid
+        && metadata.cm_position == other.cm_position
+        && metadata.bp_position == other.bp_position
+        && metadata.allele_1 == other.allele_1
+        && metadata.allele_2 == other.allele_2
+}
+
+// Read options that give values as the writer expects them, so genotypes keep their meaning.
+fn matching_read_options<TVal: BedVal>(
+    write_options: &WriteOptionsBuilder<TVal>,
+) -> Result<ReadOptions<TVal>, Box<BedErrorPlus>> {
+    ReadOptions::<TVal>::builder()
+        .is_a1_counted(write_options.is_a1_counted.unwrap_or(true))
+        .missing_value(
+            write_options
+                .missing_value
+                .unwrap_or_else(|| TVal::missing()),
+        )
+        .build()
+}
+
+// Stream through a writer, removing the .bed file if anything fails.
+fn write_streamed<TVal: BedVal>(
+    write_options: &WriteOptionsBuilder<TVal>,
+    iid_count: usize,
+    metadata: &Metadata,
+    write_blocks: impl FnOnce(&mut BedWriter<TVal>) -> Result<(), Box<BedErrorPlus>>,
+) -> Result<WriteSummary, Box<BedErrorPlus>> {
+    let mut writer = write_options.bed_writer(iid_count)?;
+    let result = writer
+        .fill_metadata(metadata)
+        .and_then(|()| write_blocks(&mut writer));
+    if result.is_err() {
+        // Clean up the file
+        if let Some(path) = &write_options.path {
+            let _ = fs::remove_file(path);
+        }
+    }
+    result?;
+    writer.finish()
+}
+
+impl Bed {
+    /// Write the SNPs (variants) of several .bed files, in order, to one new .bed/.fam/.bim
+    /// fileset, for example, to merge files split by chromosome.
+    ///
+    /// The files must have the same individuals (samples): their .fam metadata must agree, or a
+    /// [`BedError::BedSetFamMismatch`](enum.BedError.html#variant.BedSetFamMismatch) error is
+    /// returned before anything is written. The output's .bim metadata is the files' .bim
+    /// metadata, in order, for any fields not set in `write_options`. Genotypes are streamed
+    /// through a [`BedWriter`](struct.BedWriter.html) a chunk of SNPs at a time, so no file is
+    /// all in memory.
+    ///
+    /// Returns a [`BedError::EmptyConcat`](enum.BedError.html#variant.EmptyConcat) error if
+    /// `beds` is empty and a
+    /// [`BedError::ConcatOverwritesInput`](enum.BedError.html#variant.ConcatOverwritesInput)
+    /// error if the output .bed file is one of them. On error, the output .bed file is removed.
+    /// To read the files as one without writing, see [`BedSet`](struct.BedSet.html).
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let mut beds = Vec::new();
+    /// for (chromosome, val) in [
+    ///     ("1", nd::array![[0, 1], [2, 0]]),
+    ///     ("2", nd::array![[2, 2, 0], [-127, 1, 0]]),
+    /// ] {
+    ///     let path = output_folder.join(format!("chr{chromosome}.bed"));
+    ///     WriteOptions::builder(&path)
+    ///         .chromosome(vec![chromosome; val.ncols()])
+    ///         .write(&val)?;
+    ///     beds.push(Bed::new(&path)?);
+    /// }
+    ///
+    /// let output_file = output_folder.join("all.bed");
+    /// Bed::concat_sid(&mut beds, &WriteOptions::<i8>::builder(&output_file))?;
+    ///
+    /// let bed = Bed::new(&output_file)?;
+    /// assert_eq!(bed.chromosome()?, nd::array!["1", "1", "2", "2", "2"]);
+    /// assert_eq!(bed.read::<i8>()?, nd::array![[0, 1, 2, 2, 0], [2, 0, -127, 1, 0]]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn concat_sid<TVal: BedVal + Into<f64>>(
+        beds: &mut [Bed],
+        write_options: &WriteOptionsBuilder<TVal>,
+    ) -> Result<WriteSummary, Box<BedErrorPlus>> {
+        check_inputs(beds, write_options.path.as_deref())?;
+        let metadata_list = beds
+            .iter()
+            .map(Bed::metadata)
+            .collect::<Result<Vec<Metadata>, Box<BedErrorPlus>>>()?;
+        for (bed, metadata) in beds.iter().zip(&metadata_list).skip(1) {
+            if !same_fam(metadata, &metadata_list[0]) {
+                Err(BedError::BedSetFamMismatch(
+                    beds[0].path.clone(),
+                    bed.path.clone(),
+                ))?;
+            }
+        }
+        let mut metadata = metadata_list[0].clone();
+        metadata.chromosome = concat_field(&metadata_list, |m| &m.chromosome);
+        metadata.sid = concat_field(&metadata_list, |m| &m.sid);
+        metadata.cm_position = concat_field(&metadata_list, |m| &m.cm_position);
+        metadata.bp_position = concat_field(&metadata_list, |m| &m.bp_position);
+        metadata.allele_1 = concat_field(&metadata_list, |m| &m.allele_1);
+        metadata.allele_2 = concat_field(&metadata_list, |m| &m.allele_2);
+
+        let iid_count = beds[0].iid_count()?;
+        let read_options = matching_read_options(write_options)?;
+        let chunk_sids = (CONCAT_CHUNK_VALUES / iid_count.max(1)).max(1);
+        write_streamed(write_options, iid_count, &metadata, |writer| {
+            for bed in beds.iter_mut() {
+                for chunk in bed.iter_sid_chunks(chunk_sids, &read_options)? {
+                    writer.write_block(&chunk?)?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Write the individuals (samples) of several .bed files, in order, to one new
+    /// .bed/.fam/.bim fileset, for example, to merge batches of samples genotyped on the same
+    /// array.
+    ///
+    /// The files must have the same SNPs (variants): their .bim metadata must agree, or a
+    /// [`BedError::BimMetadataMismatch`](enum.BedError.html#variant.BimMetadataMismatch) error
+    /// is returned before anything is written. The output's .fam metadata is the files' .fam
+    /// metadata, in order, for any fields not set in `write_options`. Genotypes are streamed a
+    /// chunk of SNPs at a time, with each chunk read from every file and stacked.
+    ///
+    /// Returns errors as [`Bed::concat_sid`](struct.Bed.html#method.concat_sid) does.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let mut beds = Vec::new();
+    /// for (batch, iid, val) in [
+    ///     ("a", vec!["i1"], nd::array![[0, 1, 2]]),
+    ///     ("b", vec!["i2", "i3"], nd::array![[2, -127, 0], [1, 1, 0]]),
+    /// ] {
+    ///     let path = output_folder.join(format!("batch_{batch}.bed"));
+    ///     WriteOptions::builder(&path).iid(iid).write(&val)?;
+    ///     beds.push(Bed::new(&path)?);
+    /// }
+    ///
+    /// let output_file = output_folder.join("all.bed");
+    /// Bed::concat_iid(&mut beds, &WriteOptions::<i8>::builder(&output_file))?;
+    ///
+    /// let bed = Bed::new(&output_file)?;
+    /// assert_eq!(bed.iid()?, nd::array!["i1", "i2", "i3"]);
+    /// assert_eq!(bed.read::<i8>()?, nd::array![[0, 1, 2], [2, -127, 0], [1, 1, 0]]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn concat_iid<TVal: BedVal + Into<f64>>(
+        beds: &mut [Bed],
+        write_options: &WriteOptionsBuilder<TVal>,
+    ) -> Result<WriteSummary, Box<BedErrorPlus>> {
+        check_inputs(beds, write_options.path.as_deref())?;
+        let metadata_list = beds
+            .iter()
+            .map(Bed::metadata)
+            .collect::<Result<Vec<Metadata>, Box<BedErrorPlus>>>()?;
+        for (bed, metadata) in beds.iter().zip(&metadata_list).skip(1) {
+            if !same_bim(metadata, &metadata_list[0]) {
+                Err(BedError::BimMetadataMismatch(
+                    beds[0].path.clone(),
+                    bed.path.clone(),
+                ))?;
+            }
+        }
+        let mut metadata = metadata_list[0].clone();
+        metadata.fid = concat_field(&metadata_list, |m| &m.fid);
+        metadata.iid = concat_field(&metadata_list, |m| &m.iid);
+        metadata.father = concat_field(&metadata_list, |m| &m.father);
+        metadata.mother = concat_field(&metadata_list, |m| &m.mother);
+        metadata.sex = concat_field(&metadata_list, |m| &m.sex);
+        metadata.pheno = concat_field(&metadata_list, |m| &m.pheno);
+
+        let iid_count = beds
+            .iter()
+            .map(Bed::iid_count)
+            .sum::<Result<usize, Box<BedErrorPlus>>>()?;
+        let sid_count = beds[0].sid_count()?;
+        let read_options = matching_read_options(write_options)?;
+        let chunk_sids = (CONCAT_CHUNK_VALUES / iid_count.max(1)).max(1);
+        write_streamed(write_options, iid_count, &metadata, |writer| {
+            for sid_start in (0..sid_count).step_by(chunk_sids) {
+                let sid_end = (sid_start + chunk_sids).min(sid_count);
+                let mut read_options = read_options.clone();
+                read_options.sid_index = (sid_start..sid_end).into();
+                let chunks = beds
+                    .iter()
+                    .map(|bed| bed.read_with_options(&read_options))
+                    .collect::<Result<Vec<nd::Array2<TVal>>, Box<BedErrorPlus>>>()?;
+                let views: Vec<_> = chunks.iter().map(nd::Array2::view).collect();
+                // unwrap always works because every chunk has the same SNP columns
+                let block = nd::concatenate(nd::Axis(0), &views).unwrap();
+                writer.write_block(&block)?;
+            }
+            Ok(())
+        })
+    }
+}
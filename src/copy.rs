@@ -0,0 +1,316 @@
+use anyinput::anyinput;
+use derive_builder::Builder;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use xxhash_rust::xxh3::Xxh3;
+
+use crate::append::temp_path;
+use crate::{
+    bed_path_from_prefix, open_and_check, try_div_4, Bed, BedError, BedErrorPlus, CB_HEADER_U64,
+};
+
+// The bytes read and written at a time.
+const COPY_BUFFER_BYTES: usize = 1 << 20;
+
+// A user callback given the bytes copied so far and the total bytes to copy.
+#[derive(Clone)]
+pub(crate) struct CopyProgress(Arc<dyn Fn(u64, u64) + Send + Sync>);
+
+impl fmt::Debug for CopyProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CopyProgress(..)")
+    }
+}
+
+/// Options for [`copy_fileset`](fn.copy_fileset.html).
+///
+/// Construct with [`CopyOptions::builder`](struct.CopyOptions.html#method.builder).
+#[derive(Debug, Clone, Builder)]
+#[builder(build_fn(error = "Box<BedErrorPlus>"))]
+pub struct CopyOptions {
+    /// Read the copies back and check their checksums against the originals' (defaults to true).
+    #[builder(default = "true")]
+    verify: bool,
+
+    #[builder(default, setter(custom))]
+    progress: Option<CopyProgress>,
+}
+
+impl CopyOptions {
+    /// Options for [`copy_fileset`](fn.copy_fileset.html).
+    ///
+    /// > See [`copy_fileset`](fn.copy_fileset.html) for an example.
+    #[must_use]
+    pub fn builder() -> CopyOptionsBuilder {
+        CopyOptionsBuilder::default()
+    }
+
+    /// Are the copies read back and checked?
+    #[must_use]
+    pub fn verify(&self) -> bool {
+        self.verify
+    }
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        CopyOptions {
+            verify: true,
+            progress: None,
+        }
+    }
+}
+
+impl CopyOptionsBuilder {
+    /// Call `progress` with the bytes copied so far and the total bytes to copy, across the
+    /// .bed, .fam, and .bim files, after each block of up to 1 MiB is written.
+    ///
+    /// The files are copied in parallel, so `progress` may be called from several threads.
+    ///
+    /// > See [`copy_fileset`](fn.copy_fileset.html) for an example.
+    pub fn progress<F>(&mut self, progress: F) -> &mut Self
+    where
+        F: Fn(u64, u64) + Send + Sync + 'static,
+    {
+        self.progress = Some(Some(CopyProgress(Arc::new(progress))));
+        self
+    }
+}
+
+/// What [`copy_fileset`](fn.copy_fileset.html) copied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CopySummary {
+    bytes: u64,
+    // xxHash (XXH3, 128-bit) checksums of the .bed, .fam, and .bim files
+    checksums: [u128; 3],
+    is_verified: bool,
+}
+
+impl CopySummary {
+    /// The total bytes copied, across the .bed, .fam, and .bim files.
+    #[must_use]
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// The xxHash (XXH3, 128-bit) checksum of the .bed file.
+    #[must_use]
+    pub fn bed_checksum(&self) -> u128 {
+        self.checksums[0]
+    }
+
+    /// The xxHash (XXH3, 128-bit) checksum of the .fam file.
+    #[must_use]
+    pub fn fam_checksum(&self) -> u128 {
+        self.checksums[1]
+    }
+
+    /// The xxHash (XXH3, 128-bit) checksum of the .bim file.
+    #[must_use]
+    pub fn bim_checksum(&self) -> u128 {
+        self.checksums[2]
+    }
+
+    /// Were the copies read back and found to match?
+    #[must_use]
+    pub fn is_verified(&self) -> bool {
+        self.is_verified
+    }
+}
+
+// Check that the .bed file's header is good and its length fits its .fam and .bim files.
+fn validate_source(bed_path: &Path) -> Result<(), Box<BedErrorPlus>> {
+    let bed = Bed::new(bed_path)?;
+    let (iid_count, sid_count) = bed.dim()?;
+    let (buf_reader, bytes_array) = open_and_check(bed_path)?;
+    let expected_len = if bytes_array[2] == 1 {
+        try_div_4(iid_count, sid_count)? * sid_count as u64
+    } else {
+        try_div_4(sid_count, iid_count)? * iid_count as u64
+    } + CB_HEADER_U64;
+    if buf_reader.get_ref().metadata()?.len() != expected_len {
+        Err(BedError::IllFormed(bed_path.to_path_buf()))?;
+    }
+    Ok(())
+}
+
+// Copy a file, returning the checksum of the bytes read and reporting progress as it goes.
+fn copy_file(
+    source: &Path,
+    target: &Path,
+    copied: &AtomicU64,
+    total: u64,
+    progress: Option<&CopyProgress>,
+) -> Result<u128, Box<BedErrorPlus>> {
+    let mut reader = File::open(source)?;
+    let mut writer = BufWriter::new(File::create(target)?);
+    let mut hasher = Xxh3::new();
+    let mut buffer = vec![0u8; COPY_BUFFER_BYTES];
+    loop {
+        let len = reader.read(&mut buffer)?;
+        if len == 0 {
+            break;
+        }
+        hasher.update(&buffer[..len]);
+        writer.write_all(&buffer[..len])?;
+        let copied = copied.fetch_add(len as u64, Ordering::Relaxed) + len as u64;
+        if let Some(progress) = progress {
+            (progress.0)(copied, total);
+        }
+    }
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+    Ok(hasher.digest128())
+}
+
+fn checksum_file(path: &Path) -> Result<u128, Box<BedErrorPlus>> {
+    let mut reader = File::open(path)?;
+    let mut hasher = Xxh3::new();
+    let mut buffer = vec![0u8; COPY_BUFFER_BYTES];
+    loop {
+        let len = reader.read(&mut buffer)?;
+        if len == 0 {
+            return Ok(hasher.digest128());
+        }
+        hasher.update(&buffer[..len]);
+    }
+}
+
+/// Copy a .bed/.fam/.bim fileset, checking it before and, by default, after, for moving
+/// large datasets more safely than `cp`.
+///
+/// Filesets are given by their .bed path, with or without the `.bed` extension. Before
+/// copying, the source's .bed header is checked and its length checked against the
+/// individual (sample) and SNP (variant) counts of its .fam and .bim files. The three files are
+/// then copied in parallel, with each file's xxHash (XXH3, 128-bit) checksum computed as it is
+/// read. If [`verify`](struct.CopyOptionsBuilder.html#method.verify) is set, as by default,
+/// the copies are read back and their checksums compared. Copies are written to temporary
+/// files and renamed into place only when every step has passed, so on error, the destination
+/// is left as it was.
+///
+/// Returns a [`BedError::CopyOntoSource`](enum.BedError.html#variant.CopyOntoSource) error if
+/// the destination is the source and a
+/// [`BedError::CopyVerifyFailed`](enum.BedError.html#variant.CopyVerifyFailed) error if a copy
+/// doesn't match its original.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{copy_fileset, Bed, CopyOptions, WriteOptions};
+/// use std::sync::atomic::{AtomicU64, Ordering};
+/// use std::sync::Arc;
+///
+/// let output_folder = temp_testdir::TempDir::default();
+/// let source = output_folder.join("source.bed");
+/// WriteOptions::builder(&source).write(&nd::array![[0, 1, 2], [2, -127, 0]])?;
+///
+/// let copied = Arc::new(AtomicU64::new(0));
+/// let copied_clone = Arc::clone(&copied);
+/// let copy_options = CopyOptions::builder()
+///     .progress(move |bytes, _total| {
+///         copied_clone.fetch_max(bytes, Ordering::Relaxed);
+///     })
+///     .build()?;
+/// let summary = copy_fileset(&source, output_folder.join("backup"), &copy_options)?;
+/// assert!(summary.is_verified());
+/// assert_eq!(copied.load(Ordering::Relaxed), summary.bytes());
+///
+/// let bed = Bed::new(output_folder.join("backup.bed"))?;
+/// assert_eq!(bed.read::<i8>()?, nd::array![[0, 1, 2], [2, -127, 0]]);
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+#[anyinput]
+pub fn copy_fileset(
+    src_prefix: AnyPath,
+    dst_prefix: AnyPath,
+    copy_options: &CopyOptions,
+) -> Result<CopySummary, Box<BedErrorPlus>> {
+    let source_bed_path = bed_path_from_prefix(src_prefix);
+    let target_bed_path = bed_path_from_prefix(dst_prefix);
+    let sources = [
+        source_bed_path.clone(),
+        source_bed_path.with_extension("fam"),
+        source_bed_path.with_extension("bim"),
+    ];
+    let targets = [
+        target_bed_path.clone(),
+        target_bed_path.with_extension("fam"),
+        target_bed_path.with_extension("bim"),
+    ];
+    if let (Ok(source), Ok(target)) = (
+        fs::canonicalize(&source_bed_path),
+        fs::canonicalize(&target_bed_path),
+    ) {
+        if source == target {
+            Err(BedError::CopyOntoSource(target_bed_path.clone()))?;
+        }
+    }
+    validate_source(&source_bed_path)?;
+
+    let temps = targets.each_ref().map(|target| temp_path(target));
+    let result = copy_to_temps(&sources, &temps, &targets, copy_options);
+    match result {
+        Ok(summary) => {
+            for (temp, target) in temps.iter().zip(&targets) {
+                fs::rename(temp, target)?;
+            }
+            Ok(summary)
+        }
+        Err(error) => {
+            // Clean up the files
+            for temp in &temps {
+                let _ = fs::remove_file(temp);
+            }
+            Err(error)
+        }
+    }
+}
+
+fn copy_to_temps(
+    sources: &[PathBuf; 3],
+    temps: &[PathBuf; 3],
+    targets: &[PathBuf; 3],
+    copy_options: &CopyOptions,
+) -> Result<CopySummary, Box<BedErrorPlus>> {
+    let mut total = 0;
+    for source in sources {
+        total += fs::metadata(source)?.len();
+    }
+    let copied = AtomicU64::new(0);
+    let checksums = std::thread::scope(|scope| {
+        let handles: Vec<_> = sources
+            .iter()
+            .zip(temps)
+            .map(|(source, temp)| {
+                let copied = &copied;
+                scope.spawn(move || {
+                    copy_file(source, temp, copied, total, copy_options.progress.as_ref())
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().map_err(|_e| BedError::PanickedThread())?)
+            .collect::<Result<Vec<u128>, Box<BedErrorPlus>>>()
+    })?;
+    // unwrap always works because there is one checksum per file
+    let checksums: [u128; 3] = checksums.try_into().unwrap();
+
+    if copy_options.verify {
+        for ((temp, target), &checksum) in temps.iter().zip(targets).zip(&checksums) {
+            if checksum_file(temp)? != checksum {
+                Err(BedError::CopyVerifyFailed(target.clone()))?;
+            }
+        }
+    }
+    Ok(CopySummary {
+        bytes: copied.into_inner(),
+        checksums,
+        is_verified: copy_options.verify,
+    })
+}
@@ -0,0 +1,161 @@
+use ndarray as nd;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::{BedError, BedErrorPlus, BedVal, WriteOptions, WriteOptionsBuilder};
+
+/// What to do with repeated SNP ids (sid) when writing.
+///
+/// Set with [`WriteOptionsBuilder::dedup_sids`](struct.WriteOptionsBuilder.html#method.dedup_sids).
+/// Without a policy, repeated sids are written as given.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum DuplicateSidPolicy {
+    /// Return a [`BedError::DuplicateSid`](enum.BedError.html#variant.DuplicateSid) error.
+    ///
+    /// Nothing is written, unless the .bim fields are set from iterators. Then the error
+    /// is found while writing the .bim file, and the .bim file is removed.
+    Error,
+    /// Write only the first SNP with each sid, leaving later SNPs with that sid out of
+    /// both the .bed and .bim files. Can't be used when .bim fields are set from iterators.
+    KeepFirst,
+    /// Add a suffix, `_2`, `_3`, ..., to each repeated sid. For example, `rs1`, `rs1`, `rs1`
+    /// become `rs1`, `rs1_2`, `rs1_3`. A suffix is skipped if the result is already in use.
+    RenameWithSuffix,
+}
+
+// Applies a policy to sids, one at a time, in file order.
+pub(crate) struct SidDeduper {
+    policy: DuplicateSidPolicy,
+    seen: HashSet<String>,
+    next_suffix: HashMap<String, usize>,
+}
+
+impl SidDeduper {
+    pub(crate) fn new(policy: DuplicateSidPolicy) -> Self {
+        Self {
+            policy,
+            seen: HashSet::new(),
+            next_suffix: HashMap::new(),
+        }
+    }
+
+    // Returns the sid to write, or None if the SNP should be left out.
+    pub(crate) fn dedup(&mut self, sid: String) -> Result<Option<String>, Box<BedErrorPlus>> {
+        if self.seen.insert(sid.clone()) {
+            return Ok(Some(sid));
+        }
+        match self.policy {
+            DuplicateSidPolicy::Error => Err(BedError::DuplicateSid(sid))?,
+            DuplicateSidPolicy::KeepFirst => Ok(None),
+            DuplicateSidPolicy::RenameWithSuffix => {
+                let suffix = self.next_suffix.entry(sid.clone()).or_insert(2);
+                loop {
+                    let renamed = format!("{sid}_{suffix}");
+                    *suffix += 1;
+                    if self.seen.insert(renamed.clone()) {
+                        return Ok(Some(renamed));
+                    }
+                }
+            }
+        }
+    }
+}
+
+// The SNPs (by index) to write, and the options to write them with.
+pub(crate) type DedupedWrite<TVal> = (Vec<usize>, WriteOptions<TVal>);
+
+pub(crate) fn select_field<T: Clone>(
+    field: Option<&Arc<nd::Array1<T>>>,
+    sid_index: &[usize],
+) -> Option<Arc<nd::Array1<T>>> {
+    field.map(|field| Arc::new(field.select(nd::Axis(0), sid_index)))
+}
+
+impl<TVal> WriteOptions<TVal>
+where
+    TVal: BedVal,
+{
+    // If the sids need deduplicating, return the SNPs to write and the options to write them
+    // with. Streamed .bim fields are instead deduplicated while the .bim file is written.
+    pub(crate) fn dedup_for_write(&self) -> Result<Option<DedupedWrite<TVal>>, Box<BedErrorPlus>> {
+        let Some(policy) = self.dedup_sids else {
+            return Ok(None);
+        };
+        if !self.lazy_bim.is_empty() {
+            return Ok(None);
+        }
+
+        let mut deduper = SidDeduper::new(policy);
+        let mut sid_index = Vec::with_capacity(self.sid_count());
+        let mut new_sid = Vec::with_capacity(self.sid_count());
+        let mut is_changed = false;
+        for (sid_i, sid) in self.sid().iter().enumerate() {
+            if let Some(deduped) = deduper.dedup(sid.clone())? {
+                is_changed |= deduped != *sid;
+                sid_index.push(sid_i);
+                new_sid.push(deduped);
+            } else {
+                is_changed = true;
+            }
+        }
+        if !is_changed {
+            return Ok(None);
+        }
+
+        let mut metadata = self.metadata.clone();
+        metadata.chromosome = select_field(metadata.chromosome.as_ref(), &sid_index);
+        metadata.sid = Some(Arc::new(nd::Array1::from_vec(new_sid)));
+        metadata.cm_position = select_field(metadata.cm_position.as_ref(), &sid_index);
+        metadata.bp_position = select_field(metadata.bp_position.as_ref(), &sid_index);
+        metadata.allele_1 = select_field(metadata.allele_1.as_ref(), &sid_index);
+        metadata.allele_2 = select_field(metadata.allele_2.as_ref(), &sid_index);
+        metadata.sid_annotations = metadata.select_sid_annotations(&sid_index);
+        let write_options = WriteOptions {
+            metadata,
+            dedup_sids: None,
+            ..self.clone()
+        };
+        Ok(Some((sid_index, write_options)))
+    }
+}
+
+impl<TVal> WriteOptionsBuilder<TVal>
+where
+    TVal: BedVal,
+{
+    /// Check for repeated SNP ids (sid) and handle them with the given policy when writing.
+    ///
+    /// Many tools reject, or silently mishandle, a .bim file with repeated sids.
+    /// See [`DuplicateSidPolicy`](enum.DuplicateSidPolicy.html) for the choices.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, DuplicateSidPolicy, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("dedup.bed");
+    /// let val = nd::array![[1, 0, -127, 0], [2, 0, -127, 2], [0, 1, 2, 0]];
+    /// WriteOptions::builder(&output_file)
+    ///     .sid(["rs1", "rs2", "rs1", "rs3"])
+    ///     .dedup_sids(DuplicateSidPolicy::KeepFirst)
+    ///     .write(&val)?;
+    ///
+    /// let bed = Bed::new(&output_file)?;
+    /// println!("{:?}", bed.sid()?); // Outputs ndarray ["rs1", "rs2", "rs3"]
+    /// println!("{:?}", bed.read::<i8>()?); // Outputs ndarray [[1, 0, 0], [2, 0, 2], [0, 1, 0]]
+    ///
+    /// WriteOptions::builder(&output_file)
+    ///     .sid(["rs1", "rs2", "rs1", "rs3"])
+    ///     .dedup_sids(DuplicateSidPolicy::RenameWithSuffix)
+    ///     .write(&val)?;
+    /// let bed = Bed::new(&output_file)?;
+    /// println!("{:?}", bed.sid()?); // Outputs ndarray ["rs1", "rs2", "rs1_2", "rs3"]
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn dedup_sids(&mut self, policy: DuplicateSidPolicy) -> &mut Self {
+        self.dedup_sids = Some(Some(policy));
+        self
+    }
+}
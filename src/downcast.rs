@@ -0,0 +1,117 @@
+use ndarray as nd;
+
+use crate::{BedError, BedErrorPlus, Missing};
+
+// The most out-of-range examples kept in a report.
+const MAX_OUT_OF_RANGE_EXAMPLES: usize = 5;
+
+/// What [`to_i8_checked`](fn.to_i8_checked.html) does with values that, after rounding,
+/// are not 0, 1, or 2.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash, Default)]
+pub enum OutOfRangePolicy {
+    /// Return a [`BedError::OutOfRangeValue`](enum.BedError.html#variant.OutOfRangeValue) error (default).
+    #[default]
+    Error,
+    /// Make the value missing (-127).
+    Missing,
+    /// Saturate: negative values become 0 and values above 2 become 2.
+    Saturate,
+}
+
+/// What [`to_i8_checked`](fn.to_i8_checked.html) did while converting.
+///
+/// > See [`to_i8_checked`](fn.to_i8_checked.html) for an example.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DowncastReport {
+    missing_count: usize,
+    rounded_count: usize,
+    out_of_range_count: usize,
+    out_of_range_examples: Vec<(usize, usize, f64)>,
+}
+
+impl DowncastReport {
+    /// The number of NaN values, converted to missing (-127).
+    #[must_use]
+    pub fn missing_count(&self) -> usize {
+        self.missing_count
+    }
+
+    /// The number of values that were not whole numbers and so were rounded.
+    #[must_use]
+    pub fn rounded_count(&self) -> usize {
+        self.rounded_count
+    }
+
+    /// The number of values that, after rounding, were not 0, 1, or 2.
+    #[must_use]
+    pub fn out_of_range_count(&self) -> usize {
+        self.out_of_range_count
+    }
+
+    /// Up to five out-of-range values, each with its (individual index, SNP index).
+    #[must_use]
+    pub fn out_of_range_examples(&self) -> &[(usize, usize, f64)] {
+        &self.out_of_range_examples
+    }
+}
+
+/// Convert floating-point genotypes to i8, ready to write, checking every value.
+///
+/// NaN becomes missing (-127). Other values are rounded to the nearest whole number.
+/// Values that are then not 0, 1, or 2, including infinities, are handled by the
+/// [`OutOfRangePolicy`](enum.OutOfRangePolicy.html). The returned
+/// [`DowncastReport`](struct.DowncastReport.html) counts missing, rounded, and out-of-range values.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{to_i8_checked, OutOfRangePolicy, WriteOptions};
+///
+/// let val = nd::array![[0.0, 1.2, f64::NAN], [2.0, 3.0, -0.4]];
+/// let (val_i8, report) = to_i8_checked(&val.view(), OutOfRangePolicy::Saturate)?;
+/// assert_eq!(val_i8, nd::array![[0, 1, -127], [2, 2, 0]]);
+/// assert_eq!(report.missing_count(), 1);
+/// assert_eq!(report.rounded_count(), 2);
+/// assert_eq!(report.out_of_range_examples(), [(1, 1, 3.0)]);
+///
+/// let output_folder = temp_testdir::TempDir::default();
+/// WriteOptions::builder(output_folder.join("converted.bed")).write(&val_i8)?;
+///
+/// assert!(to_i8_checked(&val.view(), OutOfRangePolicy::Error).is_err());
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+pub fn to_i8_checked(
+    val: &nd::ArrayView2<'_, f64>,
+    policy: OutOfRangePolicy,
+) -> Result<(nd::Array2<i8>, DowncastReport), Box<BedErrorPlus>> {
+    let mut report = DowncastReport::default();
+    let mut val_i8 = nd::Array2::<i8>::zeros(val.raw_dim());
+    for ((iid_i, sid_i), &value) in val.indexed_iter() {
+        val_i8[(iid_i, sid_i)] = if value.is_nan() {
+            report.missing_count += 1;
+            i8::missing()
+        } else {
+            let rounded = value.round();
+            if value.is_finite() && value.fract() != 0.0 {
+                report.rounded_count += 1;
+            }
+            if (0.0..=2.0).contains(&rounded) {
+                // "as" is safe because the value is 0.0, 1.0, or 2.0
+                rounded as i8
+            } else {
+                report.out_of_range_count += 1;
+                if report.out_of_range_examples.len() < MAX_OUT_OF_RANGE_EXAMPLES {
+                    report.out_of_range_examples.push((iid_i, sid_i, value));
+                }
+                match policy {
+                    OutOfRangePolicy::Error => Err(BedError::OutOfRangeValue(value, iid_i, sid_i))?,
+                    OutOfRangePolicy::Missing => i8::missing(),
+                    OutOfRangePolicy::Saturate if rounded < 0.0 => 0,
+                    OutOfRangePolicy::Saturate => 2,
+                }
+            }
+        };
+    }
+    Ok((val_i8, report))
+}
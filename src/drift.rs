@@ -0,0 +1,170 @@
+use ndarray as nd;
+
+use crate::{Bed, BedError, BedErrorPlus};
+
+/// Rolling missingness and minor allele frequency (MAF) along the SNPs (variants), in file order.
+///
+/// Batch effects, for example, a bad plate or array version, often show as stretches of SNPs,
+/// in array manifest order, whose missing rate or MAF drifts from the rest. Window `w` covers
+/// SNPs `w..w + window`. Its missing rate is the mean of its SNPs' missing rates and its MAF is
+/// the mean MAF of its SNPs with any calls (NaN if none). Created by
+/// [`Bed::positional_drift_report`](struct.Bed.html#method.positional_drift_report).
+///
+/// > See [`Bed::positional_drift_report`](struct.Bed.html#method.positional_drift_report) for an example.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DriftReport {
+    window: usize,
+    missing_rate: nd::Array1<f64>,
+    maf: nd::Array1<f64>,
+    overall_missing_rate: f64,
+    overall_maf: f64,
+}
+
+impl DriftReport {
+    /// The number of SNPs (variants) in each window.
+    #[must_use]
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// The number of windows, one per starting SNP (variant).
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.missing_rate.len()
+    }
+
+    /// True if there are no windows.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.missing_rate.is_empty()
+    }
+
+    /// Each window's mean missing rate.
+    #[must_use]
+    pub fn missing_rate(&self) -> &nd::Array1<f64> {
+        &self.missing_rate
+    }
+
+    /// Each window's mean MAF, over its SNPs (variants) with any calls.
+    #[must_use]
+    pub fn maf(&self) -> &nd::Array1<f64> {
+        &self.maf
+    }
+
+    /// The mean missing rate over all SNPs (variants).
+    #[must_use]
+    pub fn overall_missing_rate(&self) -> f64 {
+        self.overall_missing_rate
+    }
+
+    /// The mean MAF over all SNPs (variants) with any calls.
+    #[must_use]
+    pub fn overall_maf(&self) -> f64 {
+        self.overall_maf
+    }
+
+    /// The starting SNP (variant) index of each window whose missing rate differs from the
+    /// overall missing rate by more than `missing_threshold`, or whose MAF differs from the
+    /// overall MAF by more than `maf_threshold`.
+    ///
+    /// Overlapping windows that drift are all returned, so a drifting stretch of SNPs shows as
+    /// a run of consecutive indexes.
+    ///
+    /// > See [`Bed::positional_drift_report`](struct.Bed.html#method.positional_drift_report) for an example.
+    #[must_use]
+    pub fn flagged(&self, missing_threshold: f64, maf_threshold: f64) -> Vec<usize> {
+        self.missing_rate
+            .iter()
+            .zip(&self.maf)
+            .enumerate()
+            .filter(|(_, (&missing_rate, &maf))| {
+                (missing_rate - self.overall_missing_rate).abs() > missing_threshold
+                    || (maf - self.overall_maf).abs() > maf_threshold
+            })
+            .map(|(window_start, _)| window_start)
+            .collect()
+    }
+}
+
+// The mean of values[start..start + window] for each start, skipping NaN, using prefix sums.
+#[allow(clippy::cast_precision_loss)]
+fn rolling_mean(values: &nd::Array1<f64>, window: usize) -> (nd::Array1<f64>, f64) {
+    let mut sums = Vec::with_capacity(values.len() + 1);
+    let mut counts = Vec::with_capacity(values.len() + 1);
+    let (mut sum, mut count) = (0.0, 0usize);
+    sums.push(sum);
+    counts.push(count);
+    for &value in values {
+        if !value.is_nan() {
+            sum += value;
+            count += 1;
+        }
+        sums.push(sum);
+        counts.push(count);
+    }
+    // 0.0 / 0.0 is NaN, as wanted for a window with no values
+    let rolling = (0..=values.len() - window)
+        .map(|start| {
+            let end = start + window;
+            (sums[end] - sums[start]) / (counts[end] - counts[start]) as f64
+        })
+        .collect();
+    (rolling, sum / count as f64)
+}
+
+impl Bed {
+    /// Find each window's missing rate and minor allele frequency (MAF), over `window`
+    /// consecutive SNPs (variants) in file order, to find positional drift from batch effects.
+    ///
+    /// The per-SNP statistics are found in one streaming pass, as with
+    /// [`Bed::stats`](struct.Bed.html#method.stats). The windows slide by one SNP, so there are
+    /// `sid_count - window + 1` of them. Use
+    /// [`DriftReport::flagged`](struct.DriftReport.html#method.flagged) to find the windows that
+    /// drift further than thresholds from the file as a whole.
+    ///
+    /// Returns a [`BedError::BadDriftWindow`](enum.BedError.html#variant.BadDriftWindow) error
+    /// if `window` is 0 or more than the number of SNPs.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("drift.bed");
+    /// // The last two SNPs have many missing values
+    /// WriteOptions::builder(&output_file).write(&nd::array![
+    ///     [0, 1, 0, 1, -127, -127],
+    ///     [1, 0, 1, 0, -127, 1],
+    ///     [0, 1, 0, 1, 0, -127],
+    ///     [1, 0, 1, 0, -127, -127],
+    /// ])?;
+    ///
+    /// let mut bed = Bed::new(&output_file)?;
+    /// let report = bed.positional_drift_report(2)?;
+    /// assert_eq!(report.len(), 5);
+    /// assert_eq!(report.missing_rate(), nd::array![0.0, 0.0, 0.0, 0.375, 0.75]);
+    /// assert_eq!(report.flagged(0.25, 1.0), vec![4]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn positional_drift_report(
+        &mut self,
+        window: usize,
+    ) -> Result<DriftReport, Box<BedErrorPlus>> {
+        let sid_count = self.sid_count()?;
+        if window == 0 || window > sid_count {
+            Err(BedError::BadDriftWindow(window, sid_count))?;
+        }
+        let stats = self.stats()?;
+        let (missing_rate, overall_missing_rate) = rolling_mean(stats.missing_rate(), window);
+        let (maf, overall_maf) = rolling_mean(stats.maf(), window);
+        Ok(DriftReport {
+            window,
+            missing_rate,
+            maf,
+            overall_missing_rate,
+            overall_maf,
+        })
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::{Bed, BedBuilder, BedError, BedErrorPlus};
+
+/// What to do with repeated (family id, individual id) pairs in the .fam file.
+///
+/// Set with [`BedBuilder::on_duplicate_iid`](struct.BedBuilder.html#method.on_duplicate_iid).
+/// Without a policy, the pairs aren't checked. Any repeated pairs found are listed by
+/// [`Bed::duplicate_iids`](struct.Bed.html#method.duplicate_iids).
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum DuplicateIidPolicy {
+    /// Return a [`BedError::DuplicateIid`](enum.BedError.html#variant.DuplicateIid) error from
+    /// [`BedBuilder::build`](struct.BedBuilder.html#method.build).
+    Error,
+    /// Keep the ids as they are. The repeated pairs are listed by
+    /// [`Bed::duplicate_iids`](struct.Bed.html#method.duplicate_iids) for the caller to report.
+    Warn,
+    /// Add a suffix, `_2`, `_3`, ..., to the iid of each repeat, so that every (fid, iid) pair
+    /// is unique. A suffix is skipped if the result is already in use.
+    MakeUnique,
+}
+
+impl BedBuilder {
+    /// Check the .fam file for repeated (family id, individual id) pairs when building, and
+    /// handle them with the given policy.
+    ///
+    /// Repeated pairs silently break joins with other data, such as phenotypes. Checking
+    /// reads the .fam file at build time. See [`DuplicateIidPolicy`](enum.DuplicateIidPolicy.html)
+    /// for the choices.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, DuplicateIidPolicy, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("dup_iid.bed");
+    /// WriteOptions::builder(&output_file)
+    ///     .fid(["f1", "f1", "f2", "f1"])
+    ///     .iid(["i1", "i2", "i1", "i1"])
+    ///     .write(&nd::array![[0i8], [1], [2], [0]])?;
+    ///
+    /// let result = Bed::builder(&output_file)
+    ///     .on_duplicate_iid(DuplicateIidPolicy::Error)
+    ///     .build();
+    /// assert!(result.is_err());
+    ///
+    /// let bed = Bed::builder(&output_file)
+    ///     .on_duplicate_iid(DuplicateIidPolicy::MakeUnique)
+    ///     .build()?;
+    /// assert_eq!(bed.duplicate_iids(), [("f1".to_string(), "i1".to_string())]);
+    /// println!("{:?}", bed.iid()?); // Outputs ndarray ["i1", "i2", "i1", "i1_2"]
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[must_use]
+    pub fn on_duplicate_iid(mut self, policy: DuplicateIidPolicy) -> Self {
+        self.on_duplicate_iid = Some(Some(policy));
+        self
+    }
+}
+
+impl Bed {
+    /// The (family id, individual id) pairs found more than once in the .fam file, in the
+    /// order first seen.
+    ///
+    /// Empty unless a policy was set with [`BedBuilder::on_duplicate_iid`](struct.BedBuilder.html#method.on_duplicate_iid).
+    /// With [`DuplicateIidPolicy::MakeUnique`](enum.DuplicateIidPolicy.html#variant.MakeUnique),
+    /// these are the pairs before renaming.
+    ///
+    /// > See [`BedBuilder::on_duplicate_iid`](struct.BedBuilder.html#method.on_duplicate_iid) for an example.
+    #[must_use]
+    pub fn duplicate_iids(&self) -> &[(String, String)] {
+        &self.duplicate_iids
+    }
+
+    // Find repeated (fid, iid) pairs and apply the policy.
+    pub(crate) fn check_duplicate_iids(
+        &mut self,
+        policy: DuplicateIidPolicy,
+    ) -> Result<(), Box<BedErrorPlus>> {
+        let fid = self.fid()?.clone();
+        let iid = self.iid()?.clone();
+
+        let mut seen_count: HashMap<(&str, &str), usize> = HashMap::new();
+        let mut duplicate_iids = Vec::new();
+        for (fid, iid) in fid.iter().zip(iid.iter()) {
+            let count = seen_count.entry((fid, iid)).or_insert(0);
+            *count += 1;
+            if *count == 2 {
+                duplicate_iids.push((fid.clone(), iid.clone()));
+            }
+        }
+        let Some((first_fid, first_iid)) = duplicate_iids.first().cloned() else {
+            return Ok(());
+        };
+
+        match policy {
+            DuplicateIidPolicy::Error => Err(BedError::DuplicateIid(first_fid, first_iid))?,
+            DuplicateIidPolicy::Warn => {}
+            DuplicateIidPolicy::MakeUnique => {
+                let mut in_use: HashSet<(String, String)> =
+                    fid.iter().cloned().zip(iid.iter().cloned()).collect();
+                let mut kept: HashSet<(&str, &str)> = HashSet::new();
+                let mut next_suffix: HashMap<(&str, &str), usize> = HashMap::new();
+                let new_iid = fid
+                    .iter()
+                    .zip(iid.iter())
+                    .map(|(fid, iid)| {
+                        if kept.insert((fid, iid)) {
+                            return iid.clone();
+                        }
+                        let suffix = next_suffix.entry((fid, iid)).or_insert(2);
+                        loop {
+                            let renamed = format!("{iid}_{suffix}");
+                            *suffix += 1;
+                            if in_use.insert((fid.clone(), renamed.clone())) {
+                                return renamed;
+                            }
+                        }
+                    })
+                    .collect();
+                self.metadata.iid = Some(Arc::new(new_iid));
+            }
+        }
+        self.duplicate_iids = duplicate_iids;
+        Ok(())
+    }
+}
@@ -0,0 +1,65 @@
+use std::fs;
+
+use crate::{Bed, BedError, BedErrorPlus, BedVal, ReadOptions, WriteOptionsBuilder, WriteSummary};
+
+impl Bed {
+    /// Write the individuals (samples) and SNPs (variants) selected by `read_options` to a new
+    /// .bed/.fam/.bim fileset, given by `write_options`, like `plink --keep --extract --make-bed`.
+    ///
+    /// Genotypes are streamed through a [`Pipeline`](struct.Pipeline.html) with no stages, a
+    /// chunk of SNPs at a time, so the selection is never all in memory. Every way of selecting that
+    /// [`ReadOptions`](struct.ReadOptions.html) supports, including
+    /// [`iid_where`](struct.ReadOptionsBuilder.html#method.iid_where), score filters, and regions,
+    /// is respected. The .fam and .bim rows of the selection are carried along, in the selection's
+    /// order, for any metadata fields not set in `write_options`.
+    ///
+    /// `read_options` and `write_options` should agree on
+    /// [`is_a1_counted`](struct.ReadOptionsBuilder.html#method.is_a1_counted) and the missing
+    /// value, as they do by default, so that the genotypes keep their meaning. Returns a
+    /// [`BedError::ExtractOverwritesInput`](enum.BedError.html#variant.ExtractOverwritesInput)
+    /// error if the output .bed file is this one. On error, the output .bed file is removed.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, ReadOptions, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let input_file = output_folder.join("all.bed");
+    /// WriteOptions::builder(&input_file)
+    ///     .iid(["i1", "i2", "i3"])
+    ///     .sid(["s1", "s2", "s3", "s4"])
+    ///     .write(&nd::array![[1, 0, -127, 0], [2, 0, -127, 2], [0, 1, 2, 0]])?;
+    ///
+    /// let mut bed = Bed::new(&input_file)?;
+    /// let read_options = ReadOptions::builder()
+    ///     .iid_index([2, 0])
+    ///     .sid_index(1..)
+    ///     .i8()
+    ///     .build()?;
+    /// let output_file = output_folder.join("subset.bed");
+    /// bed.extract(&read_options, &WriteOptions::builder(&output_file))?;
+    ///
+    /// let subset = Bed::new(&output_file)?;
+    /// assert_eq!(subset.iid()?, nd::array!["i3", "i1"]);
+    /// assert_eq!(subset.sid()?, nd::array!["s2", "s3", "s4"]);
+    /// assert_eq!(subset.read::<i8>()?, nd::array![[1, 2, 0], [0, -127, 0]]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn extract<TVal: BedVal + Into<f64>>(
+        &mut self,
+        read_options: &ReadOptions<TVal>,
+        write_options: &WriteOptionsBuilder<TVal>,
+    ) -> Result<WriteSummary, Box<BedErrorPlus>> {
+        if let Some(path) = &write_options.path {
+            if let (Ok(input), Ok(output)) = (fs::canonicalize(&self.path), fs::canonicalize(path))
+            {
+                if input == output {
+                    Err(BedError::ExtractOverwritesInput(path.clone()))?;
+                }
+            }
+        }
+        self.pipeline(read_options)?.write(write_options)
+    }
+}
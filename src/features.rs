@@ -0,0 +1,222 @@
+use derive_builder::Builder;
+use ndarray as nd;
+
+use crate::{Bed, BedError, BedErrorPlus, BedVal, Index, ReadOptions};
+
+// The most genotype values read at once when making features.
+const FEATURE_CHUNK_VALUES: usize = 8_000_000;
+
+/// How [`Bed::read_features`](struct.Bed.html#method.read_features) encodes each genotype.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash, Default)]
+pub enum FeatureEncoding {
+    /// Three columns per SNP (variant), indicators of zero, one, and two copies of the
+    /// counted allele (default).
+    #[default]
+    OneHot3,
+    /// Two columns per SNP (variant): the additive count of the counted allele (0, 1, or 2) and
+    /// a dominance indicator that is 1 for heterozygotes and 0 otherwise.
+    AddDom,
+}
+
+impl FeatureEncoding {
+    /// The number of feature columns per SNP (variant).
+    #[must_use]
+    pub fn width(&self) -> usize {
+        match self {
+            FeatureEncoding::OneHot3 => 3,
+            FeatureEncoding::AddDom => 2,
+        }
+    }
+}
+
+/// What [`Bed::read_features`](struct.Bed.html#method.read_features) does with a missing genotype.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash, Default)]
+pub enum FeatureMissing {
+    /// Set each of the genotype's feature columns to the missing value, NaN for floats and -127
+    /// for i8 (default).
+    #[default]
+    Missing,
+    /// Set each of the genotype's feature columns to 0.
+    Zero,
+    /// Return a [`BedError::MissingFeatureGenotype`](enum.BedError.html#variant.MissingFeatureGenotype) error.
+    Error,
+}
+
+/// Options for [`Bed::read_features`](struct.Bed.html#method.read_features).
+///
+/// Construct with [`FeatureOptions::builder`](struct.FeatureOptions.html#method.builder).
+#[derive(Debug, Clone, Builder)]
+#[builder(build_fn(error = "Box<BedErrorPlus>"))]
+pub struct FeatureOptions {
+    /// How each genotype is encoded (defaults to [`FeatureEncoding::OneHot3`](enum.FeatureEncoding.html#variant.OneHot3)).
+    #[builder(default)]
+    encoding: FeatureEncoding,
+
+    /// What to do with missing genotypes (defaults to [`FeatureMissing::Missing`](enum.FeatureMissing.html#variant.Missing)).
+    #[builder(default)]
+    missing: FeatureMissing,
+
+    /// Select which individual (sample) values to read -- Defaults to all.
+    ///
+    /// See the [Table of Index Expressions](index.html#index-expressions)
+    /// for a list of the supported index expressions.
+    #[builder(default = "Index::All")]
+    #[builder(setter(into))]
+    iid_index: Index,
+
+    /// Select which SNPs (variants) to read -- Defaults to all.
+    ///
+    /// See the [Table of Index Expressions](index.html#index-expressions)
+    /// for a list of the supported index expressions.
+    #[builder(default = "Index::All")]
+    #[builder(setter(into))]
+    sid_index: Index,
+
+    /// Is allele 1 the counted allele? (defaults to true)
+    #[builder(default = "true")]
+    is_a1_counted: bool,
+}
+
+impl FeatureOptions {
+    /// Options for [`Bed::read_features`](struct.Bed.html#method.read_features).
+    ///
+    /// > See [`Bed::read_features`](struct.Bed.html#method.read_features) for an example.
+    #[must_use]
+    pub fn builder() -> FeatureOptionsBuilder {
+        FeatureOptionsBuilder::default()
+    }
+
+    /// How each genotype is encoded.
+    #[must_use]
+    pub fn encoding(&self) -> FeatureEncoding {
+        self.encoding
+    }
+
+    /// What to do with missing genotypes.
+    #[must_use]
+    pub fn missing(&self) -> FeatureMissing {
+        self.missing
+    }
+
+    /// The individuals (samples) to read.
+    #[must_use]
+    pub fn iid_index(&self) -> &Index {
+        &self.iid_index
+    }
+
+    /// The SNPs (variants) to read.
+    #[must_use]
+    pub fn sid_index(&self) -> &Index {
+        &self.sid_index
+    }
+
+    /// Is allele 1 the counted allele?
+    #[must_use]
+    pub fn is_a1_counted(&self) -> bool {
+        self.is_a1_counted
+    }
+}
+
+impl Bed {
+    /// Read genotype data as machine-learning features, with one row per individual (sample) and
+    /// [`width`](enum.FeatureEncoding.html#method.width) columns per SNP (variant).
+    ///
+    /// The columns of SNP `j` are `j * width` up to, but not including, `(j + 1) * width`.
+    /// See [`FeatureEncoding`](enum.FeatureEncoding.html) for the encodings and
+    /// [`FeatureMissing`](enum.FeatureMissing.html) for the handling of missing genotypes.
+    /// The output's type, `TVal`, is i8, f32, or f64.
+    ///
+    /// SNPs are read in chunks and encoded straight into the output, so no full-size
+    /// genotype matrix is made along the way.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, FeatureEncoding, FeatureMissing, FeatureOptions, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("features.bed");
+    /// WriteOptions::builder(&output_file).write(&nd::array![[0, 2], [1, -127]])?;
+    ///
+    /// let mut bed = Bed::new(&output_file)?;
+    /// let options = FeatureOptions::builder().build()?;
+    /// let features = bed.read_features::<f32>(&options)?;
+    /// assert_eq!(features.row(0), nd::array![1.0, 0.0, 0.0, 0.0, 0.0, 1.0]);
+    ///
+    /// let options = FeatureOptions::builder()
+    ///     .encoding(FeatureEncoding::AddDom)
+    ///     .missing(FeatureMissing::Zero)
+    ///     .build()?;
+    /// let features = bed.read_features::<i8>(&options)?;
+    /// assert_eq!(features, nd::array![[0, 0, 2, 0], [1, 1, 0, 0]]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn read_features<TVal: BedVal>(
+        &mut self,
+        options: &FeatureOptions,
+    ) -> Result<nd::Array2<TVal>, Box<BedErrorPlus>> {
+        let iid_index = options.iid_index.to_vec(self.iid_count()?)?;
+        let sid_index = options.sid_index.to_vec(self.sid_count()?)?;
+        let width = options.encoding.width();
+        let (zero, one, two): (TVal, TVal, TVal) = (0.into(), 1.into(), 2.into());
+        let missing = match options.missing {
+            FeatureMissing::Zero => zero,
+            FeatureMissing::Missing | FeatureMissing::Error => TVal::missing(),
+        };
+        // The features of zero, one, and two copies of the counted allele, and then of missing.
+        let codes: [[TVal; 3]; 4] = match options.encoding {
+            FeatureEncoding::OneHot3 => [
+                [one, zero, zero],
+                [zero, one, zero],
+                [zero, zero, one],
+                [missing; 3],
+            ],
+            FeatureEncoding::AddDom => [
+                [zero, zero, zero],
+                [one, one, zero],
+                [two, zero, zero],
+                [missing; 3],
+            ],
+        };
+
+        let mut features = nd::Array2::<TVal>::default(nd::ShapeBuilder::f((
+            iid_index.len(),
+            width * sid_index.len(),
+        )));
+        let chunk_len = (FEATURE_CHUNK_VALUES / iid_index.len().max(1)).max(1);
+        for (chunk_i, chunk) in sid_index.chunks(chunk_len).enumerate() {
+            let val = ReadOptions::builder()
+                .iid_index(&iid_index)
+                .sid_index(chunk)
+                .is_a1_counted(options.is_a1_counted)
+                .i8()
+                .read(self)?;
+            for (column_i, column) in val.columns().into_iter().enumerate() {
+                let sid_out = chunk_i * chunk_len + column_i;
+                let mut snp_features =
+                    features.slice_mut(nd::s![.., sid_out * width..(sid_out + 1) * width]);
+                for (iid_out, (&value, mut row)) in
+                    column.iter().zip(snp_features.rows_mut()).enumerate()
+                {
+                    // "as" is safe because non-missing values are 0, 1, or 2
+                    let code = if value < 0 {
+                        if options.missing == FeatureMissing::Error {
+                            Err(BedError::MissingFeatureGenotype(
+                                iid_index[iid_out],
+                                chunk[column_i],
+                            ))?;
+                        }
+                        &codes[3]
+                    } else {
+                        &codes[value as usize]
+                    };
+                    for (feature, &code_value) in row.iter_mut().zip(code) {
+                        *feature = code_value;
+                    }
+                }
+            }
+        }
+        Ok(features)
+    }
+}
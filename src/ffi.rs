@@ -0,0 +1,293 @@
+//! A C interface, so that tools written in C, C++, R, and other languages can open .bed files
+//! and read genotypes with this crate.
+//!
+//! Every function returns [`BED_READER_OK`](constant.BED_READER_OK.html) on success or
+//! [`BED_READER_ERROR`](constant.BED_READER_ERROR.html) on failure, with results written
+//! through pointer arguments. After a failure,
+//! [`bed_reader_last_error`](fn.bed_reader_last_error.html) gives the error's message. A panic
+//! inside the crate is caught and reported as an error, rather than unwinding into C.
+//!
+//! The C declarations are in `include/bed_reader.h`, which can be regenerated with
+//! `cbindgen --config cbindgen.toml --output include/bed_reader.h`. Link against the crate's
+//! `cdylib`.
+//!
+//! ```c
+//! BedReader *bed;
+//! size_t iid_count, sid_count;
+//! if (bed_reader_open("small.bed", &bed) != BED_READER_OK) { /* ... */ }
+//! bed_reader_iid_count(bed, &iid_count);
+//! bed_reader_sid_count(bed, &sid_count);
+//! double *val = malloc(iid_count * 2 * sizeof(double));
+//! // SNPs (variants) 0 and 1 of every individual, in row-major (C) order
+//! bed_reader_read_f64(bed, 0, iid_count, 0, 2, false, 0, val, iid_count * 2);
+//! bed_reader_close(bed);
+//! ```
+use ndarray as nd;
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::{Bed, BedError, BedErrorPlus, BedVal, ReadOptions};
+
+/// Returned by every function on success.
+pub const BED_READER_OK: c_int = 0;
+
+/// Returned by every function on failure. See [`bed_reader_last_error`](fn.bed_reader_last_error.html).
+pub const BED_READER_ERROR: c_int = 1;
+
+/// An open .bed file, seen from C only through a pointer.
+///
+/// Create one with [`bed_reader_open`](fn.bed_reader_open.html) and free it with
+/// [`bed_reader_close`](fn.bed_reader_close.html).
+pub struct BedReader {
+    bed: Bed,
+}
+
+thread_local! {
+    // The message of this thread's last failure.
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+// Run a call, recording any error or panic as this thread's last error.
+fn status(call: impl FnOnce() -> Result<(), Box<BedErrorPlus>>) -> c_int {
+    let message = match catch_unwind(AssertUnwindSafe(call)) {
+        Ok(Ok(())) => return BED_READER_OK,
+        Ok(Err(error)) => error.to_string(),
+        Err(_) => BedError::FfiPanic.to_string(),
+    };
+    // Messages never hold a nul, but a replacement keeps CString::new from failing
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+    BED_READER_ERROR
+}
+
+// The value behind a pointer from C, or an error if the pointer is null.
+unsafe fn non_null<'a, T>(pointer: *mut T, name: &str) -> Result<&'a mut T, Box<BedErrorPlus>> {
+    let Some(value) = pointer.as_mut() else {
+        Err(BedError::NullPointer(name.to_string()))?
+    };
+    Ok(value)
+}
+
+/// Open a .bed file, with its .fam and .bim files beside it, and write a new
+/// [`BedReader`](struct.BedReader.html) to `*out`.
+///
+/// Like [`Bed::new`](../struct.Bed.html#method.new), nothing is read until needed.
+///
+/// # Safety
+/// `path` must be a nul-terminated UTF-8 string and `out` must point to writable memory.
+/// Free the `BedReader` with [`bed_reader_close`](fn.bed_reader_close.html).
+#[no_mangle]
+pub unsafe extern "C" fn bed_reader_open(path: *const c_char, out: *mut *mut BedReader) -> c_int {
+    status(|| {
+        let out = non_null(out, "out")?;
+        if path.is_null() {
+            Err(BedError::NullPointer("path".to_string()))?;
+        }
+        let path = CStr::from_ptr(path).to_str()?;
+        let bed = Bed::new(path)?;
+        *out = Box::into_raw(Box::new(BedReader { bed }));
+        Ok(())
+    })
+}
+
+/// Write the number of individuals (samples) to `*out`.
+///
+/// # Safety
+/// `bed` must come from [`bed_reader_open`](fn.bed_reader_open.html) and not be closed, and
+/// `out` must point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn bed_reader_iid_count(bed: *mut BedReader, out: *mut usize) -> c_int {
+    status(|| {
+        *non_null(out, "out")? = non_null(bed, "bed")?.bed.iid_count()?;
+        Ok(())
+    })
+}
+
+/// Write the number of SNPs (variants) to `*out`.
+///
+/// # Safety
+/// `bed` must come from [`bed_reader_open`](fn.bed_reader_open.html) and not be closed, and
+/// `out` must point to writable memory.
+#[no_mangle]
+pub unsafe extern "C" fn bed_reader_sid_count(bed: *mut BedReader, out: *mut usize) -> c_int {
+    status(|| {
+        *non_null(out, "out")? = non_null(bed, "bed")?.bed.sid_count()?;
+        Ok(())
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+unsafe fn read_range<TVal: BedVal>(
+    bed: *mut BedReader,
+    iid_start: usize,
+    iid_end: usize,
+    sid_start: usize,
+    sid_end: usize,
+    is_f: bool,
+    num_threads: usize,
+    out: *mut TVal,
+    out_len: usize,
+) -> c_int {
+    status(|| {
+        let bed = &mut non_null(bed, "bed")?.bed;
+        if out.is_null() {
+            Err(BedError::NullPointer("out".to_string()))?;
+        }
+        let shape = (
+            iid_end.saturating_sub(iid_start),
+            sid_end.saturating_sub(sid_start),
+        );
+        let Some(len) = shape.0.checked_mul(shape.1) else {
+            Err(BedError::BufferTooLarge(shape.0, shape.1))?
+        };
+        if out_len != len {
+            Err(BedError::BufferLength(len, out_len))?;
+        }
+        let mut builder = ReadOptions::<TVal>::builder();
+        builder
+            .iid_index(iid_start..iid_end)
+            .sid_index(sid_start..sid_end)
+            .is_f(is_f);
+        if num_threads > 0 {
+            builder.num_threads(num_threads);
+        }
+        let read_options = builder.build()?;
+        let mut val = nd::ArrayViewMut2::from_shape_ptr(nd::ShapeBuilder::set_f(shape, is_f), out);
+        bed.read_and_fill_with_options(&mut val, &read_options)
+    })
+}
+
+/// Read individuals (samples) `iid_start..iid_end` and SNPs (variants) `sid_start..sid_end`
+/// into the caller's buffer of `out_len` doubles, with missing values as NaN.
+///
+/// The buffer must hold exactly `(iid_end - iid_start) * (sid_end - sid_start)` values, in
+/// column-major (Fortran) order if `is_f`, otherwise row-major (C) order. A `num_threads` of 0
+/// uses the default, as in [`ReadOptionsBuilder::num_threads`](../struct.ReadOptionsBuilder.html#method.num_threads).
+///
+/// # Safety
+/// `bed` must come from [`bed_reader_open`](fn.bed_reader_open.html) and not be closed, and
+/// `out` must point to `out_len` writable values.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn bed_reader_read_f64(
+    bed: *mut BedReader,
+    iid_start: usize,
+    iid_end: usize,
+    sid_start: usize,
+    sid_end: usize,
+    is_f: bool,
+    num_threads: usize,
+    out: *mut f64,
+    out_len: usize,
+) -> c_int {
+    read_range(
+        bed,
+        iid_start,
+        iid_end,
+        sid_start,
+        sid_end,
+        is_f,
+        num_threads,
+        out,
+        out_len,
+    )
+}
+
+/// Like [`bed_reader_read_f64`](fn.bed_reader_read_f64.html), but into floats.
+///
+/// # Safety
+/// As for [`bed_reader_read_f64`](fn.bed_reader_read_f64.html).
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn bed_reader_read_f32(
+    bed: *mut BedReader,
+    iid_start: usize,
+    iid_end: usize,
+    sid_start: usize,
+    sid_end: usize,
+    is_f: bool,
+    num_threads: usize,
+    out: *mut f32,
+    out_len: usize,
+) -> c_int {
+    read_range(
+        bed,
+        iid_start,
+        iid_end,
+        sid_start,
+        sid_end,
+        is_f,
+        num_threads,
+        out,
+        out_len,
+    )
+}
+
+/// Like [`bed_reader_read_f64`](fn.bed_reader_read_f64.html), but into 8-bit integers, with
+/// missing values as -127.
+///
+/// # Safety
+/// As for [`bed_reader_read_f64`](fn.bed_reader_read_f64.html).
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn bed_reader_read_i8(
+    bed: *mut BedReader,
+    iid_start: usize,
+    iid_end: usize,
+    sid_start: usize,
+    sid_end: usize,
+    is_f: bool,
+    num_threads: usize,
+    out: *mut i8,
+    out_len: usize,
+) -> c_int {
+    read_range(
+        bed,
+        iid_start,
+        iid_end,
+        sid_start,
+        sid_end,
+        is_f,
+        num_threads,
+        out,
+        out_len,
+    )
+}
+
+/// Free a [`BedReader`](struct.BedReader.html). A null `bed` is ignored.
+///
+/// # Safety
+/// `bed` must be null or come from [`bed_reader_open`](fn.bed_reader_open.html), and must not
+/// be used again.
+#[no_mangle]
+pub unsafe extern "C" fn bed_reader_close(bed: *mut BedReader) {
+    if !bed.is_null() {
+        drop(Box::from_raw(bed));
+    }
+}
+
+/// Copy the message of this thread's last error, nul-terminated and truncated to fit, into
+/// `buffer`, and return the buffer length needed for the whole message, including the nul.
+///
+/// Returns 0, writing nothing, if no call on this thread has failed. Pass a null `buffer`
+/// with `buffer_len` 0 to find the length needed.
+///
+/// # Safety
+/// `buffer` must be null or point to `buffer_len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn bed_reader_last_error(buffer: *mut c_char, buffer_len: usize) -> usize {
+    LAST_ERROR.with(|last_error| {
+        let last_error = last_error.borrow();
+        let Some(message) = last_error.as_ref() else {
+            return 0;
+        };
+        let bytes = message.as_bytes_with_nul();
+        if !buffer.is_null() && buffer_len > 0 {
+            let copy_len = bytes.len().min(buffer_len) - 1;
+            std::ptr::copy_nonoverlapping(bytes.as_ptr().cast::<c_char>(), buffer, copy_len);
+            *buffer.add(copy_len) = 0;
+        }
+        bytes.len()
+    })
+}
@@ -0,0 +1,147 @@
+use ndarray as nd;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::{Bed, BedBuilder, BedError, BedErrorPlus, MetadataFields};
+
+/// How a .fam or .bim field is read.
+///
+/// Set with [`BedBuilder::field_mode`](struct.BedBuilder.html#method.field_mode).
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash, Default)]
+pub enum FieldMode {
+    /// Record the field as its typed array (default).
+    #[default]
+    Typed,
+    /// Record the field as text. The numeric fields, `sex`, `cm_position`, and `bp_position`,
+    /// aren't parsed when the file is read, but only when their typed array is first asked for.
+    /// Get the text with [`Bed::raw_field`](struct.Bed.html#method.raw_field).
+    Raw,
+    /// Don't record the field, like [`BedBuilder::skip_sex`](struct.BedBuilder.html#method.skip_sex)
+    /// and the other `skip_*` methods.
+    Skip,
+}
+
+impl BedBuilder {
+    /// Set how a .fam or .bim field is read: as its typed array (the default), as text, or not at all.
+    ///
+    /// Parsing the `sex`, `cm_position`, and `bp_position` fields of a huge .fam or .bim file takes time.
+    /// With [`FieldMode::Raw`](enum.FieldMode.html#variant.Raw), their text is kept and parsed only if
+    /// their typed array is asked for. [`FieldMode::Skip`](enum.FieldMode.html#variant.Skip) is the
+    /// same as the `skip_*` methods. The last mode set for a field wins.
+    ///
+    /// Raw fields left unparsed are `None` in [`Bed::metadata`](struct.Bed.html#method.metadata).
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, FieldMode, MetadataFields, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("raw.bed");
+    /// WriteOptions::builder(&output_file)
+    ///     .bp_position([100, 2000])
+    ///     .write(&nd::array![[0, 1], [2, 0]])?;
+    ///
+    /// let mut bed = Bed::builder(&output_file)
+    ///     .field_mode(MetadataFields::BpPosition, FieldMode::Raw)
+    ///     .field_mode(MetadataFields::Sex, FieldMode::Skip)
+    ///     .build()?;
+    /// assert_eq!(bed.raw_field(MetadataFields::BpPosition)?, nd::array!["100", "2000"]);
+    /// assert_eq!(bed.bp_position()?, nd::array![100, 2000]);
+    /// assert!(bed.sex().is_err());
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[must_use]
+    pub fn field_mode(mut self, field: MetadataFields, mode: FieldMode) -> Self {
+        // Unwraps will always work because BedBuilder starts with some skip_set and raw_set
+        let skip_set = self.skip_set.as_mut().unwrap();
+        let raw_set = self.raw_set.as_mut().unwrap();
+        match mode {
+            FieldMode::Typed => {
+                skip_set.remove(&field);
+                raw_set.remove(&field);
+            }
+            FieldMode::Raw => {
+                skip_set.remove(&field);
+                raw_set.insert(field);
+            }
+            FieldMode::Skip => {
+                skip_set.insert(field);
+                raw_set.remove(&field);
+            }
+        }
+        self
+    }
+}
+
+impl Bed {
+    /// The text of a field read with [`FieldMode::Raw`](enum.FieldMode.html#variant.Raw).
+    ///
+    /// If needed, the field is read from the .fam or .bim file. Returns a
+    /// [`BedError::FieldNotRaw`](enum.BedError.html#variant.FieldNotRaw) error
+    /// for a field not in raw mode.
+    ///
+    /// > See [`BedBuilder::field_mode`](struct.BedBuilder.html#method.field_mode) for an example.
+    pub fn raw_field(
+        &mut self,
+        field: MetadataFields,
+    ) -> Result<&nd::Array1<String>, Box<BedErrorPlus>> {
+        if !self.raw_set.contains(&field) {
+            Err(BedError::FieldNotRaw(field.name().to_string()))?;
+        }
+        match field {
+            MetadataFields::Fid => self.fid(),
+            MetadataFields::Iid => self.iid(),
+            MetadataFields::Father => self.father(),
+            MetadataFields::Mother => self.mother(),
+            MetadataFields::Pheno => self.pheno(),
+            MetadataFields::Chromosome => self.chromosome(),
+            MetadataFields::Sid => self.sid(),
+            MetadataFields::Allele1 => self.allele_1(),
+            MetadataFields::Allele2 => self.allele_2(),
+            MetadataFields::Sex | MetadataFields::CmPosition | MetadataFields::BpPosition => {
+                if self.loaded_raw_field(field)?.is_none() && !self.raw_fields.contains_key(&field)
+                {
+                    self.make_raw_field(field)?;
+                }
+                match self.loaded_raw_field(field)? {
+                    Some(raw) => Ok(raw),
+                    // unwrap always works because the raw text was just made
+                    None => Ok(self.raw_fields.get(&field).unwrap()),
+                }
+            }
+        }
+    }
+
+    // The fields not to read from the .fam or .bim file: the skipped ones and the raw ones already made.
+    pub(crate) fn read_skip_set(&self) -> HashSet<MetadataFields> {
+        let mut skip_set = self.skip_set.clone();
+        skip_set.extend(self.raw_fields.keys().copied());
+        skip_set
+    }
+
+    // A raw numeric field's text, as read from the .fam or .bim file, if it was.
+    fn loaded_raw_field(
+        &self,
+        field: MetadataFields,
+    ) -> Result<Option<&Arc<nd::Array1<String>>>, Box<BedErrorPlus>> {
+        let loaded = if field == MetadataFields::Sex {
+            self.fam()?
+        } else {
+            self.bim()?
+        };
+        Ok(loaded.raw_field(field))
+    }
+
+    // Make a raw numeric field's text from the typed array it was given as.
+    fn make_raw_field(&mut self, field: MetadataFields) -> Result<(), Box<BedErrorPlus>> {
+        let text = match field {
+            MetadataFields::Sex => self.sex()?.map(ToString::to_string),
+            MetadataFields::CmPosition => self.cm_position()?.map(ToString::to_string),
+            _ => self.bp_position()?.map(ToString::to_string),
+        };
+        self.raw_fields.insert(field, Arc::new(text));
+        Ok(())
+    }
+}
@@ -0,0 +1,216 @@
+//! Reproducible cross-validation folds for machine learning on genotype data.
+//!
+//! Each function takes a seed and always returns the same folds for the same seed and inputs.
+//! Each [`Fold`](struct.Fold.html) holds individual (sample) indexes that can be given to
+//! [`ReadOptionsBuilder::iid_index`](../struct.ReadOptionsBuilder.html#method.iid_index) to read
+//! its training or test genotypes. Labels and family ids line up with the crate's metadata
+//! arrays, for example, [`Metadata::pheno`](../struct.Metadata.html#method.pheno) and
+//! [`Metadata::fid`](../struct.Metadata.html#method.fid).
+use ndarray as nd;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::{BedError, BedErrorPlus};
+
+/// One cross-validation fold: the individuals (samples) held out for testing and the rest,
+/// used for training.
+///
+/// Both are individual indexes in increasing order.
+///
+/// > See [`stratified_kfold`](fn.stratified_kfold.html) for an example.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fold {
+    train: Vec<isize>,
+    test: Vec<isize>,
+}
+
+impl Fold {
+    /// The indexes of the individuals (samples) used for training.
+    #[must_use]
+    pub fn train(&self) -> &[isize] {
+        &self.train
+    }
+
+    /// The indexes of the individuals (samples) held out for testing.
+    #[must_use]
+    pub fn test(&self) -> &[isize] {
+        &self.test
+    }
+}
+
+/// Split individuals (samples) into `k` folds, keeping each label's share about the same in every fold.
+///
+/// Each individual is in the test set of exactly one fold. The individuals with each label are
+/// shuffled and dealt to the folds in turn, so fold sizes differ by at most one and each label's
+/// count per fold differs by at most one.
+///
+/// Returns a [`BedError::BadFoldCount`](../enum.BedError.html#variant.BadFoldCount) error if `k`
+/// is less than 2 or more than the number of individuals.
+///
+/// > Also see [`stratified_kfold_by_family`](fn.stratified_kfold_by_family.html), which keeps families together.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{Bed, ReadOptions, folds, sample_bed_file};
+///
+/// let mut bed = Bed::new(sample_bed_file("small.bed")?)?;
+/// let labels = nd::array!["case", "control", "case"];
+/// let fold_list = folds::stratified_kfold(2, &labels, 0)?;
+/// assert_eq!(fold_list.len(), 2);
+/// for fold in &fold_list {
+///     let train = ReadOptions::builder()
+///         .iid_index(fold.train())
+///         .f64()
+///         .read(&mut bed)?;
+///     let test = ReadOptions::builder()
+///         .iid_index(fold.test())
+///         .f64()
+///         .read(&mut bed)?;
+///     assert_eq!(train.nrows() + test.nrows(), 3);
+/// }
+/// assert_eq!(fold_list, folds::stratified_kfold(2, &labels, 0)?); // same seed, same folds
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+pub fn stratified_kfold<T: Eq + Hash>(
+    k: usize,
+    labels: &nd::Array1<T>,
+    seed: u64,
+) -> Result<Vec<Fold>, Box<BedErrorPlus>> {
+    let iid_count = labels.len();
+    if k < 2 || k > iid_count {
+        Err(BedError::BadFoldCount(k, iid_count))?;
+    }
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+
+    let mut fold_of = vec![0usize; iid_count];
+    let mut next_fold = 0;
+    for mut group in groups(labels.iter()) {
+        group.shuffle(&mut rng);
+        // Continue dealing where the last label stopped, so fold sizes stay balanced
+        for iid_i in group {
+            fold_of[iid_i] = next_fold;
+            next_fold = (next_fold + 1) % k;
+        }
+    }
+    Ok(to_folds(&fold_of, k))
+}
+
+/// Like [`stratified_kfold`](fn.stratified_kfold.html), but keeps every family in a single fold,
+/// so relatives never appear in both the training and test sets.
+///
+/// Individuals (samples) are grouped by family id (`fid`). Families are shuffled, then, from
+/// largest to smallest, each is put in the fold that has the fewest individuals with the
+/// family's labels, breaking ties by the fold's size. Fold sizes and label shares are balanced
+/// as well as whole families allow.
+///
+/// Returns a [`BedError::BadFoldCount`](../enum.BedError.html#variant.BadFoldCount) error if `k`
+/// is less than 2 or more than the number of families.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::folds;
+///
+/// let labels = nd::array![1, 0, 1, 0, 1, 0];
+/// let fid = nd::array!["f1", "f1", "f2", "f2", "f3", "f4"].map(|s| s.to_string());
+/// let fold_list = folds::stratified_kfold_by_family(2, &labels, &fid, 3)?;
+/// for fold in &fold_list {
+///     for &test_i in fold.test() {
+///         for &train_i in fold.train() {
+///             assert_ne!(fid[test_i as usize], fid[train_i as usize]);
+///         }
+///     }
+/// }
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+pub fn stratified_kfold_by_family<T: Eq + Hash>(
+    k: usize,
+    labels: &nd::Array1<T>,
+    fid: &nd::Array1<String>,
+    seed: u64,
+) -> Result<Vec<Fold>, Box<BedErrorPlus>> {
+    if labels.len() != fid.len() {
+        Err(BedError::InconsistentCount(
+            "fid".to_string(),
+            labels.len(),
+            fid.len(),
+        ))?;
+    }
+    let mut families = groups(fid.iter());
+    if k < 2 || k > families.len() {
+        Err(BedError::BadFoldCount(k, families.len()))?;
+    }
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    families.shuffle(&mut rng);
+    // Stable, so families of the same size stay shuffled
+    families.sort_by_key(|family| std::cmp::Reverse(family.len()));
+
+    // Number labels in the order first seen
+    let mut label_ids: HashMap<&T, usize> = HashMap::new();
+    let label_of: Vec<usize> = labels
+        .iter()
+        .map(|label| {
+            let next_id = label_ids.len();
+            *label_ids.entry(label).or_insert(next_id)
+        })
+        .collect();
+
+    let mut label_counts = nd::Array2::<usize>::zeros((k, label_ids.len()));
+    let mut fold_sizes = vec![0usize; k];
+    let mut fold_of = vec![0usize; labels.len()];
+    for family in families {
+        let fold_i = (0..k)
+            .min_by_key(|&fold_i| {
+                let label_count: usize = family
+                    .iter()
+                    .map(|&iid_i| label_counts[(fold_i, label_of[iid_i])])
+                    .sum();
+                (label_count, fold_sizes[fold_i])
+            })
+            .unwrap_or(0); // unwrap_or never used because k >= 2
+        for &iid_i in &family {
+            fold_of[iid_i] = fold_i;
+            label_counts[(fold_i, label_of[iid_i])] += 1;
+        }
+        fold_sizes[fold_i] += family.len();
+    }
+    Ok(to_folds(&fold_of, k))
+}
+
+// Group indexes by value, with groups in the order their values are first seen.
+fn groups<'a, T: Eq + Hash + 'a>(values: impl Iterator<Item = &'a T>) -> Vec<Vec<usize>> {
+    let mut group_of: HashMap<&T, usize> = HashMap::new();
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for (i, value) in values.enumerate() {
+        let group_i = *group_of.entry(value).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[group_i].push(i);
+    }
+    groups
+}
+
+// Make the folds, given each individual's test fold.
+fn to_folds(fold_of: &[usize], k: usize) -> Vec<Fold> {
+    (0..k)
+        .map(|fold_i| {
+            let (mut test, mut train) = (Vec::new(), Vec::new());
+            for (iid_i, &iid_fold_i) in fold_of.iter().enumerate() {
+                // "as" is safe because counts fit in isize
+                if iid_fold_i == fold_i {
+                    test.push(iid_i as isize);
+                } else {
+                    train.push(iid_i as isize);
+                }
+            }
+            Fold { train, test }
+        })
+        .collect()
+}
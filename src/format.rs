@@ -0,0 +1,255 @@
+//! Constants and functions that describe the on-disk layout of a PLINK .bed file.
+//!
+//! A .bed file starts with a [`HEADER_LEN`]-byte header: the two [`MAGIC`] bytes, then one
+//! [`Mode`] byte. The genotype data follows as a series of columns. In the usual
+//! [`Mode::SnpMajor`] layout, each column holds one SNP (variant) and each individual (sample)
+//! takes two bits, four individuals per byte with the first individual in the lowest bits.
+//! Each column is padded to a whole number of bytes. The two-bit codes are [`HOM_ALLELE_1`],
+//! [`MISSING`], [`HETEROZYGOUS`], and [`HOM_ALLELE_2`].
+//!
+//! # Example
+//! ```
+//! use bed_reader::{Bed, format, sample_bed_file};
+//!
+//! let file_name = sample_bed_file("small.bed")?;
+//! let bed = Bed::new(&file_name)?;
+//! let mode = format::read_mode(&file_name)?;
+//! assert_eq!(mode, format::Mode::SnpMajor);
+//! let file_len = format::expected_file_len(bed.dim()?, mode)?;
+//! assert_eq!(file_len, std::fs::metadata(&file_name)?.len()); // 3 + 1 byte * 4 SNPs
+//! # use bed_reader::BedErrorPlus;
+//! # Ok::<(), Box<BedErrorPlus>>(())
+//! ```
+use anyinput::anyinput;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+
+use crate::{open_and_check, BedError, BedErrorPlus};
+
+/// The two bytes that start every .bed file.
+pub const MAGIC: [u8; 2] = [0x6C, 0x1B]; // 0b01101100 or 'l' (lowercase 'L'), then 0b00011011 or <esc>
+
+/// The length, in bytes, of a .bed file's header: the [`MAGIC`] bytes and the [`Mode`] byte.
+pub const HEADER_LEN: u64 = 3;
+
+/// The number of individuals (or, in [`Mode::IndividualMajor`], SNPs) packed into each byte.
+pub const VALUES_PER_BYTE: u64 = 4;
+
+/// Two-bit code for homozygous allele 1 (2 copies of allele 1).
+pub const HOM_ALLELE_1: u8 = 0b00;
+/// Two-bit code for a missing value.
+pub const MISSING: u8 = 0b01;
+/// Two-bit code for heterozygous (1 copy of allele 1).
+pub const HETEROZYGOUS: u8 = 0b10;
+/// Two-bit code for homozygous allele 2 (0 copies of allele 1).
+pub const HOM_ALLELE_2: u8 = 0b11;
+
+/// The order of the genotype data in a .bed file, given by the third header byte.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum Mode {
+    /// Mode byte 0. Each column holds one individual (sample). Rarely used.
+    IndividualMajor,
+    /// Mode byte 1. Each column holds one SNP (variant). The usual mode and the mode written by this crate,
+    /// except by [`Bed::rewrite_as_individual_major`](../struct.Bed.html#method.rewrite_as_individual_major).
+    SnpMajor,
+}
+
+impl Mode {
+    /// Return the mode for a header's mode byte, or `None` if the byte isn't a valid mode.
+    #[must_use]
+    pub fn from_byte(byte: u8) -> Option<Mode> {
+        match byte {
+            0 => Some(Mode::IndividualMajor),
+            1 => Some(Mode::SnpMajor),
+            _ => None,
+        }
+    }
+
+    /// Return the header's mode byte for this mode.
+    #[must_use]
+    pub fn to_byte(self) -> u8 {
+        match self {
+            Mode::IndividualMajor => 0,
+            Mode::SnpMajor => 1,
+        }
+    }
+
+    /// Return the whole header of a .bed file with this mode.
+    ///
+    /// # Example
+    /// ```
+    /// use bed_reader::format::Mode;
+    ///
+    /// assert_eq!(Mode::SnpMajor.header(), [0x6C, 0x1B, 0x01]);
+    /// ```
+    #[must_use]
+    pub fn header(self) -> [u8; 3] {
+        [MAGIC[0], MAGIC[1], self.to_byte()]
+    }
+}
+
+/// The number of bytes in each column of a .bed file whose columns hold `count` values.
+///
+/// In [`Mode::SnpMajor`], `count` is the number of individuals (samples).
+///
+/// # Example
+/// ```
+/// use bed_reader::format;
+///
+/// assert_eq!(format::bytes_per_column(0), 0);
+/// assert_eq!(format::bytes_per_column(4), 1);
+/// assert_eq!(format::bytes_per_column(5), 2);
+/// ```
+#[must_use]
+pub fn bytes_per_column(count: usize) -> u64 {
+    // "as" is safe because the crate requires a 64-bit target
+    (count as u64).div_ceil(VALUES_PER_BYTE)
+}
+
+/// The length, in bytes, of a .bed file with the given (`iid_count`, `sid_count`) dimensions and mode.
+///
+/// Returns an error if the length would not fit in a `u64`.
+///
+/// > See the [module documentation](index.html) for an example.
+pub fn expected_file_len(dim: (usize, usize), mode: Mode) -> Result<u64, Box<BedErrorPlus>> {
+    let (iid_count, sid_count) = dim;
+    let (column_len, column_count) = match mode {
+        Mode::SnpMajor => (bytes_per_column(iid_count), sid_count as u64),
+        Mode::IndividualMajor => (bytes_per_column(sid_count), iid_count as u64),
+    };
+    column_len
+        .checked_mul(column_count)
+        .and_then(|len| len.checked_add(HEADER_LEN))
+        .ok_or_else(|| BedError::IndexesTooBigForFiles(iid_count, sid_count).into())
+}
+
+/// The format of a genotype file, as detected from its first bytes by [`detect_format`].
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum FileFormat {
+    /// A PLINK .bed file with the given mode.
+    Bed(Mode),
+    /// A PLINK 2 .pgen file. It starts with the .bed magic bytes, but with a mode byte of 2 or more.
+    Pgen,
+    /// An Oxford BGEN file.
+    Bgen,
+    /// An uncompressed, text VCF file.
+    Vcf,
+    /// A BCF (binary VCF) file.
+    Bcf,
+    /// A gzip- or bgzip-compressed file, for example, a .vcf.gz file.
+    Gzip,
+    /// None of the above.
+    Unknown,
+}
+
+impl FileFormat {
+    /// Detect a format from a file's first bytes. 20 bytes are enough to detect every format.
+    ///
+    /// # Example
+    /// ```
+    /// use bed_reader::format::{FileFormat, Mode};
+    ///
+    /// assert_eq!(FileFormat::from_header(&[0x6C, 0x1B, 0x01]), FileFormat::Bed(Mode::SnpMajor));
+    /// assert_eq!(FileFormat::from_header(&[0x6C, 0x1B, 0x10]), FileFormat::Pgen);
+    /// assert_eq!(FileFormat::from_header(b"##fileformat=VCFv4.2"), FileFormat::Vcf);
+    /// ```
+    #[must_use]
+    pub fn from_header(bytes: &[u8]) -> FileFormat {
+        if bytes.len() >= 3 && bytes[..2] == MAGIC {
+            return match (Mode::from_byte(bytes[2]), bytes[2]) {
+                (Some(mode), _) => FileFormat::Bed(mode),
+                // PLINK 2's fixed-width and variable-width storage modes
+                (None, 0x02..=0x04 | 0x10 | 0x11) => FileFormat::Pgen,
+                (None, _) => FileFormat::Unknown,
+            };
+        }
+        if bytes.starts_with(&[0x1F, 0x8B]) {
+            FileFormat::Gzip
+        } else if bytes.starts_with(b"BCF\x02") {
+            FileFormat::Bcf
+        } else if bytes.starts_with(b"##fileformat=VCF") {
+            FileFormat::Vcf
+        } else if bytes.get(16..20) == Some(b"bgen") {
+            FileFormat::Bgen
+        } else {
+            FileFormat::Unknown
+        }
+    }
+
+    /// How to read a file in this format, if this crate can't.
+    #[must_use]
+    pub fn suggestion(self) -> Option<&'static str> {
+        match self {
+            FileFormat::Bed(_) | FileFormat::Unknown => None,
+            FileFormat::Pgen => Some("Convert it with `plink2 --pfile <prefix> --make-bed` or read it with a PLINK 2 reader, such as pgenlib."),
+            FileFormat::Bgen => Some("Convert it with `plink2 --bgen <file> --make-bed` or read it with a BGEN reader."),
+            FileFormat::Vcf => Some("Convert it with `plink2 --vcf <file> --make-bed`."),
+            FileFormat::Bcf => Some("Convert it with `plink2 --bcf <file> --make-bed`."),
+            FileFormat::Gzip => Some("Decompress it first or, if it is a compressed VCF, convert it with `plink2 --vcf <file> --make-bed`."),
+        }
+    }
+
+    // The error for a file that isn't a readable .bed file: specific for known formats, else `default`.
+    pub(crate) fn to_error(self, path: &std::path::Path, default: BedError) -> BedError {
+        match self {
+            FileFormat::Bed(_) | FileFormat::Unknown => default,
+            _ => BedError::UnsupportedFormat(self, path.to_path_buf()),
+        }
+    }
+}
+
+impl fmt::Display for FileFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileFormat::Bed(mode) => write!(f, "a PLINK .bed file (mode {})", mode.to_byte()),
+            FileFormat::Pgen => write!(f, "a PLINK 2 .pgen file"),
+            FileFormat::Bgen => write!(f, "a BGEN file"),
+            FileFormat::Vcf => write!(f, "a VCF file"),
+            FileFormat::Bcf => write!(f, "a BCF file"),
+            FileFormat::Gzip => write!(f, "a gzip-compressed file"),
+            FileFormat::Unknown => write!(f, "a file of unknown format"),
+        }
+    }
+}
+
+/// Detect the format of a genotype file from its first bytes.
+///
+/// This tells, for example, a PLINK 2 .pgen file, which starts with the same two bytes as a
+/// .bed file, from a .bed file. Reading a file in a detected, unsupported format returns a
+/// [`BedError::UnsupportedFormat`](../enum.BedError.html#variant.UnsupportedFormat) error
+/// that suggests how to read it.
+///
+/// # Example
+/// ```
+/// use bed_reader::format::{detect_format, read_mode, FileFormat};
+///
+/// let output_folder = temp_testdir::TempDir::default();
+/// let pgen_file = output_folder.join("plink2.bed");
+/// std::fs::write(&pgen_file, [0x6C, 0x1B, 0x02, 0x00, 0x00])?;
+/// assert_eq!(detect_format(&pgen_file)?, FileFormat::Pgen);
+///
+/// let result = read_mode(&pgen_file);
+/// println!("{}", result.unwrap_err()); // Outputs "'.../plink2.bed' is a PLINK 2 .pgen file, not a PLINK .bed file. Convert it with ..."
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+#[anyinput]
+pub fn detect_format(path: AnyPath) -> Result<FileFormat, Box<BedErrorPlus>> {
+    let mut bytes = Vec::with_capacity(20);
+    File::open(path)?.take(20).read_to_end(&mut bytes)?;
+    Ok(FileFormat::from_header(&bytes))
+}
+
+/// Read and check the header of a .bed file, returning its mode.
+///
+/// > See the [module documentation](index.html) for an example.
+#[anyinput]
+pub fn read_mode(path: AnyPath) -> Result<Mode, Box<BedErrorPlus>> {
+    let (_, bytes_array) = open_and_check(path)?;
+    Mode::from_byte(bytes_array[2]).ok_or_else(|| {
+        FileFormat::from_header(&bytes_array)
+            .to_error(path, BedError::BadMode(path.to_path_buf()))
+            .into()
+    })
+}
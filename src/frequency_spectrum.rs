@@ -0,0 +1,154 @@
+use ndarray as nd;
+use std::collections::HashMap;
+
+use crate::parallel::prelude::*;
+use crate::{Bed, BedErrorPlus, ReadOptions};
+
+// The most genotype values read at once when computing a spectrum.
+const SPECTRUM_CHUNK_VALUES: usize = 8_000_000;
+
+// The chromosomes, their spectra, and their SNPs left out for missing genotypes.
+type ChromosomeSpectra = (nd::Array1<String>, nd::Array2<usize>, nd::Array1<usize>);
+
+impl Bed {
+    /// Compute the site frequency spectrum (SFS) over all SNPs (variants), in one streaming pass.
+    ///
+    /// Entry `k` is the number of SNPs where allele 1 appears `k` times over all individuals
+    /// (samples), counting two alleles per individual. The unfolded spectrum has
+    /// `2 * iid_count + 1` entries and treats allele 1 as the derived allele. If `folded` is
+    /// true, entry `k` counts the SNPs whose minor allele appears `k` times, giving
+    /// `iid_count + 1` entries.
+    ///
+    /// SNPs with any missing genotype are left out of the spectrum, because their allele 1
+    /// counts are out of fewer than `2 * iid_count` alleles. The number of SNPs left out is
+    /// returned with the spectrum. If many are left out, consider imputing, or filtering
+    /// individuals, first.
+    ///
+    /// SNPs are read in chunks, so the full genotype matrix is never held in memory.
+    /// For one spectrum per chromosome, see
+    /// [`site_frequency_spectrum_by_chromosome`](struct.Bed.html#method.site_frequency_spectrum_by_chromosome).
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, sample_bed_file};
+    ///
+    /// let bed = Bed::new(sample_bed_file("small.bed")?)?;
+    /// // Allele 1 counts are [3, 1, missing, 2]
+    /// let (sfs, skipped) = bed.site_frequency_spectrum(false)?;
+    /// assert_eq!(sfs, nd::array![0, 1, 1, 1, 0, 0, 0]);
+    /// assert_eq!(skipped, 1);
+    /// let (sfs, _) = bed.site_frequency_spectrum(true)?;
+    /// assert_eq!(sfs, nd::array![0, 1, 1, 1]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn site_frequency_spectrum(
+        &self,
+        folded: bool,
+    ) -> Result<(nd::Array1<usize>, usize), Box<BedErrorPlus>> {
+        let sid_count = self.sid_count()?;
+        let (spectra, skipped) = self.spectra(&vec![0; sid_count], 1, folded)?;
+        Ok((spectra.row(0).to_owned(), skipped[0]))
+    }
+
+    /// Compute one site frequency spectrum (SFS) per chromosome, in one streaming pass.
+    ///
+    /// Returns the chromosomes, in the order first seen in the .bim file, a
+    /// (chromosome count, spectrum length) array with one spectrum per row, and, for each
+    /// chromosome, the number of SNPs left out for missing genotypes. Each spectrum is as
+    /// described in [`site_frequency_spectrum`](struct.Bed.html#method.site_frequency_spectrum).
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, sample_bed_file};
+    ///
+    /// let bed = Bed::new(sample_bed_file("small.bed")?)?;
+    /// // Chromosomes are ["1", "1", "5", "Y"], and allele 1 counts are [3, 1, missing, 2]
+    /// let (chromosomes, sfs, skipped) = bed.site_frequency_spectrum_by_chromosome(true)?;
+    /// assert_eq!(chromosomes, nd::array!["1".to_string(), "5".to_string(), "Y".to_string()]);
+    /// assert_eq!(sfs, nd::array![[0, 1, 0, 1], [0, 0, 0, 0], [0, 0, 1, 0]]);
+    /// assert_eq!(skipped, nd::array![0, 1, 0]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn site_frequency_spectrum_by_chromosome(
+        &self,
+        folded: bool,
+    ) -> Result<ChromosomeSpectra, Box<BedErrorPlus>> {
+        let mut chromosomes: Vec<String> = Vec::new();
+        let mut group_of_chromosome: HashMap<&str, usize> = HashMap::new();
+        let chromosome = self.chromosome()?.clone();
+        let group_index: Vec<usize> = chromosome
+            .iter()
+            .map(|chromosome| {
+                *group_of_chromosome
+                    .entry(chromosome.as_str())
+                    .or_insert_with(|| {
+                        chromosomes.push(chromosome.clone());
+                        chromosomes.len() - 1
+                    })
+            })
+            .collect();
+
+        let (spectra, skipped) = self.spectra(&group_index, chromosomes.len(), folded)?;
+        Ok((nd::Array1::from_vec(chromosomes), spectra, skipped))
+    }
+
+    // Compute one spectrum per group of SNPs, given each SNP's group, and count each group's
+    // SNPs left out for missing genotypes.
+    fn spectra(
+        &self,
+        group_index: &[usize],
+        group_count: usize,
+        folded: bool,
+    ) -> Result<(nd::Array2<usize>, nd::Array1<usize>), Box<BedErrorPlus>> {
+        let iid_count = self.iid_count()?;
+        let sid_count = self.sid_count()?;
+        let allele_total = 2 * iid_count;
+        let bin_count = if folded {
+            iid_count + 1
+        } else {
+            allele_total + 1
+        };
+
+        let mut spectra = nd::Array2::<usize>::zeros((group_count, bin_count));
+        let mut skipped = nd::Array1::<usize>::zeros(group_count);
+        let chunk_len = (SPECTRUM_CHUNK_VALUES / iid_count.max(1)).max(1);
+        for chunk_start in (0..sid_count).step_by(chunk_len) {
+            let chunk_end = (chunk_start + chunk_len).min(sid_count);
+            let val = ReadOptions::builder()
+                .sid_index(chunk_start..chunk_end)
+                .i8()
+                .read(self)?;
+            // None if the SNP has a missing genotype
+            let allele_counts: Vec<Option<usize>> = val
+                .axis_iter(nd::Axis(1))
+                .into_par_iter()
+                .map(|col| {
+                    col.iter().try_fold(0usize, |count, &value| {
+                        // "as" is safe because non-missing values are 0, 1, or 2
+                        (value >= 0).then(|| count + value as usize)
+                    })
+                })
+                .collect();
+            for (&group_i, allele_count) in group_index[chunk_start..chunk_end]
+                .iter()
+                .zip(allele_counts)
+            {
+                let Some(allele_count) = allele_count else {
+                    skipped[group_i] += 1;
+                    continue;
+                };
+                let bin = if folded {
+                    allele_count.min(allele_total - allele_count)
+                } else {
+                    allele_count
+                };
+                spectra[(group_i, bin)] += 1;
+            }
+        }
+        Ok((spectra, skipped))
+    }
+}
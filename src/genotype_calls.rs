@@ -0,0 +1,86 @@
+use ndarray as nd;
+
+use crate::{Bed, BedErrorPlus, Index, ReadOptions};
+
+impl Bed {
+    /// Read genotypes as allele strings, such as `"AG"` or `"TT"`, with `"--"` for missing, for
+    /// the SNPs (variants) selected by `sid_index`, over all individuals (samples).
+    ///
+    /// Each genotype's count of allele 1 is combined with the SNP's
+    /// [`allele_1`](struct.Bed.html#method.allele_1) and
+    /// [`allele_2`](struct.Bed.html#method.allele_2): two copies give allele 1 twice, one copy
+    /// gives allele 1 then allele 2, and none gives allele 2 twice. If either allele is longer
+    /// than one character, for example, an indel, the two alleles are joined with `/`, as in
+    /// `"AT/A"`. Returns an (individual count, selected SNP count) array.
+    ///
+    /// `sid_index` can be any [`Index`](enum.Index.html) expression that
+    /// [`ReadOptionsBuilder::sid_index`](struct.ReadOptionsBuilder.html#method.sid_index) accepts.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("calls.bed");
+    /// WriteOptions::builder(&output_file)
+    ///     .allele_1(["A", "T", "AT"])
+    ///     .allele_2(["G", "C", "A"])
+    ///     .write(&nd::array![[2, 0, 1], [1, -127, 0]])?;
+    ///
+    /// let bed = Bed::new(&output_file)?;
+    /// let calls = bed.genotype_calls(..)?;
+    /// assert_eq!(calls, nd::array![["AA", "CC", "AT/A"], ["AG", "--", "A/A"]]);
+    /// let calls = bed.genotype_calls([1])?;
+    /// assert_eq!(calls, nd::array![["CC"], ["--"]]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn genotype_calls(
+        &self,
+        sid_index: impl Into<Index>,
+    ) -> Result<nd::Array2<String>, Box<BedErrorPlus>> {
+        let sid_count = self.sid_count()?;
+        let sid_index = sid_index.into().to_vec(sid_count)?;
+        let val = ReadOptions::builder()
+            .sid_index(&sid_index)
+            .is_a1_counted(true)
+            .i8()
+            .read(self)?;
+        // The read checked the indexes, so negative ones, counted from the end, can be resolved
+        let sid_index: Vec<usize> = sid_index
+            .iter()
+            .map(|&sid_i| {
+                if sid_i < 0 {
+                    sid_count - sid_i.unsigned_abs()
+                } else {
+                    sid_i.unsigned_abs()
+                }
+            })
+            .collect();
+        let allele_1 = self.allele_1()?.select(nd::Axis(0), &sid_index);
+        let allele_2 = self.allele_2()?.select(nd::Axis(0), &sid_index);
+
+        let mut calls = nd::Array2::<String>::default(val.dim());
+        nd::Zip::from(calls.columns_mut())
+            .and(val.columns())
+            .and(&allele_1)
+            .and(&allele_2)
+            .for_each(|mut call_column, val_column, allele_1, allele_2| {
+                let separator = if allele_1.chars().count() > 1 || allele_2.chars().count() > 1 {
+                    "/"
+                } else {
+                    ""
+                };
+                for (call, &count) in call_column.iter_mut().zip(val_column) {
+                    *call = match count {
+                        2 => format!("{allele_1}{separator}{allele_1}"),
+                        1 => format!("{allele_1}{separator}{allele_2}"),
+                        0 => format!("{allele_2}{separator}{allele_2}"),
+                        _ => "--".to_string(),
+                    };
+                }
+            });
+        Ok(calls)
+    }
+}
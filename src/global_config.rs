@@ -0,0 +1,212 @@
+use std::env;
+use std::sync::{PoisonError, RwLock};
+
+use crate::{BedErrorPlus, MissingPolicy};
+
+// The defaults installed with GlobalConfig::install.
+static GLOBAL_CONFIG: RwLock<GlobalConfig> = RwLock::new(GlobalConfig::new());
+
+/// Process-wide defaults for options not given to [`ReadOptions`](struct.ReadOptions.html),
+/// [`WriteOptions`](struct.WriteOptions.html), and [`StatsOptions`](struct.StatsOptions.html).
+///
+/// Each setting is found, in order of priority, from:
+///
+/// 1. the option given to the read, write, or statistics call, for example,
+///    [`ReadOptionsBuilder::num_threads`](struct.ReadOptionsBuilder.html#method.num_threads);
+/// 2. the `GlobalConfig` installed with [`GlobalConfig::install`](struct.GlobalConfig.html#method.install);
+/// 3. the environment variables, see [`GlobalConfig::from_env`](struct.GlobalConfig.html#method.from_env);
+/// 4. the built-in default: all processors, 10 concurrent requests, 8,000,000-byte chunks, and
+///    [`MissingPolicy::Skip`](enum.MissingPolicy.html#variant.Skip).
+///
+/// An application can so override environment lookups programmatically, and
+/// [`GlobalConfig::effective`](struct.GlobalConfig.html#method.effective) shows the settings
+/// that calls would use. Settings are read when a call starts, or, for the missing policy,
+/// when a [`StatsOptions`](struct.StatsOptions.html) is made.
+///
+/// # Example
+/// ```
+/// use bed_reader::GlobalConfig;
+///
+/// let previous = GlobalConfig::new().with_max_chunk_bytes(1_000_000).install();
+/// let effective = GlobalConfig::effective()?;
+/// assert_eq!(effective.max_chunk_bytes(), Some(1_000_000));
+/// assert_eq!(effective.max_concurrent_requests(), Some(10)); // the built-in default
+/// previous.install();
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GlobalConfig {
+    num_threads: Option<usize>,
+    max_concurrent_requests: Option<usize>,
+    max_chunk_bytes: Option<usize>,
+    missing_policy: Option<MissingPolicy>,
+}
+
+// The first of the environment variables that is set, parsed.
+fn env_usize(names: &[&str]) -> Result<Option<usize>, Box<BedErrorPlus>> {
+    for name in names {
+        if let Ok(value) = env::var(name) {
+            return Ok(Some(value.parse::<usize>()?));
+        }
+    }
+    Ok(None)
+}
+
+impl GlobalConfig {
+    /// A configuration with no settings, so that every setting falls through to the
+    /// environment variables and then the built-in defaults.
+    #[must_use]
+    pub const fn new() -> GlobalConfig {
+        GlobalConfig {
+            num_threads: None,
+            max_concurrent_requests: None,
+            max_chunk_bytes: None,
+            missing_policy: None,
+        }
+    }
+
+    /// The settings given by the environment variables, in order of priority:
+    ///
+    /// * number of threads: `BED_READER_NUM_THREADS`, then `NUM_THREADS`
+    /// * maximum concurrent requests: `BED_READER_MAX_CONCURRENT_REQUESTS`
+    /// * maximum chunk bytes: `BED_READER_MAX_CHUNK_BYTES`
+    ///
+    /// Returns an error if a variable that is set isn't a number.
+    pub fn from_env() -> Result<GlobalConfig, Box<BedErrorPlus>> {
+        Ok(GlobalConfig {
+            num_threads: env_usize(&["BED_READER_NUM_THREADS", "NUM_THREADS"])?,
+            max_concurrent_requests: env_usize(&["BED_READER_MAX_CONCURRENT_REQUESTS"])?,
+            max_chunk_bytes: env_usize(&["BED_READER_MAX_CHUNK_BYTES"])?,
+            missing_policy: None,
+        })
+    }
+
+    /// The configuration installed with
+    /// [`GlobalConfig::install`](struct.GlobalConfig.html#method.install), without the
+    /// environment variables or built-in defaults.
+    #[must_use]
+    pub fn current() -> GlobalConfig {
+        *GLOBAL_CONFIG.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Make this configuration the process-wide defaults, returning the configuration it
+    /// replaces, so that it can be restored.
+    ///
+    /// > See [`GlobalConfig`](struct.GlobalConfig.html) for an example.
+    #[allow(clippy::must_use_candidate, clippy::return_self_not_must_use)]
+    pub fn install(self) -> GlobalConfig {
+        let mut global_config = GLOBAL_CONFIG
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+        std::mem::replace(&mut *global_config, self)
+    }
+
+    /// The settings that calls given no options would use, with every setting filled in from
+    /// the installed configuration, the environment variables, or the built-in defaults.
+    ///
+    /// A number of threads of 0 means all processors. Returns an error if an environment
+    /// variable that is needed isn't a number.
+    ///
+    /// > See [`GlobalConfig`](struct.GlobalConfig.html) for an example.
+    pub fn effective() -> Result<GlobalConfig, Box<BedErrorPlus>> {
+        Ok(GlobalConfig {
+            num_threads: Some(resolve_num_threads(None)?),
+            max_concurrent_requests: Some(resolve_max_concurrent_requests(None)?),
+            max_chunk_bytes: Some(resolve_max_chunk_bytes(None)?),
+            missing_policy: Some(resolve_missing_policy()),
+        })
+    }
+
+    /// This configuration, but with `num_threads` threads, where 0 means all processors.
+    #[must_use]
+    pub fn with_num_threads(self, num_threads: usize) -> GlobalConfig {
+        GlobalConfig {
+            num_threads: Some(num_threads),
+            ..self
+        }
+    }
+
+    /// This configuration, but with at most `max_concurrent_requests` reads in flight, as in
+    /// [`ReadOptionsBuilder::max_concurrent_requests`](struct.ReadOptionsBuilder.html#method.max_concurrent_requests).
+    #[must_use]
+    pub fn with_max_concurrent_requests(self, max_concurrent_requests: usize) -> GlobalConfig {
+        GlobalConfig {
+            max_concurrent_requests: Some(max_concurrent_requests),
+            ..self
+        }
+    }
+
+    /// This configuration, but with chunks of at most `max_chunk_bytes` bytes, as in
+    /// [`ReadOptionsBuilder::max_chunk_bytes`](struct.ReadOptionsBuilder.html#method.max_chunk_bytes).
+    #[must_use]
+    pub fn with_max_chunk_bytes(self, max_chunk_bytes: usize) -> GlobalConfig {
+        GlobalConfig {
+            max_chunk_bytes: Some(max_chunk_bytes),
+            ..self
+        }
+    }
+
+    /// This configuration, but with new [`StatsOptions`](struct.StatsOptions.html) handling
+    /// missing values as `missing_policy` says.
+    #[must_use]
+    pub fn with_missing_policy(self, missing_policy: MissingPolicy) -> GlobalConfig {
+        GlobalConfig {
+            missing_policy: Some(missing_policy),
+            ..self
+        }
+    }
+
+    /// The number of threads, if set.
+    #[must_use]
+    pub fn num_threads(&self) -> Option<usize> {
+        self.num_threads
+    }
+
+    /// The maximum number of concurrent requests, if set.
+    #[must_use]
+    pub fn max_concurrent_requests(&self) -> Option<usize> {
+        self.max_concurrent_requests
+    }
+
+    /// The maximum chunk bytes, if set.
+    #[must_use]
+    pub fn max_chunk_bytes(&self) -> Option<usize> {
+        self.max_chunk_bytes
+    }
+
+    /// The missing policy of new [`StatsOptions`](struct.StatsOptions.html), if set.
+    #[must_use]
+    pub fn missing_policy(&self) -> Option<MissingPolicy> {
+        self.missing_policy
+    }
+}
+
+pub(crate) fn resolve_num_threads(option: Option<usize>) -> Result<usize, Box<BedErrorPlus>> {
+    if let Some(num_threads) = option.or(GlobalConfig::current().num_threads) {
+        return Ok(num_threads);
+    }
+    Ok(env_usize(&["BED_READER_NUM_THREADS", "NUM_THREADS"])?.unwrap_or(0))
+}
+
+pub(crate) fn resolve_max_concurrent_requests(
+    option: Option<usize>,
+) -> Result<usize, Box<BedErrorPlus>> {
+    if let Some(max_concurrent_requests) =
+        option.or(GlobalConfig::current().max_concurrent_requests)
+    {
+        return Ok(max_concurrent_requests);
+    }
+    Ok(env_usize(&["BED_READER_MAX_CONCURRENT_REQUESTS"])?.unwrap_or(10))
+}
+
+pub(crate) fn resolve_max_chunk_bytes(option: Option<usize>) -> Result<usize, Box<BedErrorPlus>> {
+    if let Some(max_chunk_bytes) = option.or(GlobalConfig::current().max_chunk_bytes) {
+        return Ok(max_chunk_bytes);
+    }
+    Ok(env_usize(&["BED_READER_MAX_CHUNK_BYTES"])?.unwrap_or(8_000_000))
+}
+
+pub(crate) fn resolve_missing_policy() -> MissingPolicy {
+    GlobalConfig::current().missing_policy.unwrap_or_default()
+}
@@ -0,0 +1,266 @@
+use anyinput::anyinput;
+use derive_builder::Builder;
+use ndarray as nd;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::ops::Range;
+use std::path::Path;
+
+use crate::global_config::{resolve_missing_policy, resolve_num_threads};
+use crate::parallel::{create_pool, par_azip};
+use crate::{Bed, BedError, BedErrorPlus, Dist, Index, MissingPolicy, ReadOptions, StatsOptions};
+
+// The most GRM values held at once when writing a GRM to a file.
+const GRM_BAND_VALUES: usize = 8_000_000;
+
+/// Options for [`Bed::grm`](struct.Bed.html#method.grm) and
+/// [`Bed::grm_to_file`](struct.Bed.html#method.grm_to_file).
+///
+/// Construct with [`GrmOptions::builder`](struct.GrmOptions.html#method.builder).
+#[derive(Debug, Clone, Builder)]
+#[builder(build_fn(error = "Box<BedErrorPlus>"))]
+pub struct GrmOptions {
+    /// How each SNP (variant) is scaled after centering (defaults to
+    /// [`Dist::Unit`](enum.Dist.html#variant.Unit)).
+    #[builder(default = "Dist::Unit")]
+    dist: Dist,
+
+    /// What is done with missing values (defaults to the missing policy of a new
+    /// [`StatsOptions`](struct.StatsOptions.html)).
+    ///
+    /// With [`MissingPolicy::Skip`](enum.MissingPolicy.html#variant.Skip) or
+    /// [`MissingPolicy::MeanImpute`](enum.MissingPolicy.html#variant.MeanImpute), a missing value
+    /// becomes its SNP's mean. With [`MissingPolicy::Propagate`](enum.MissingPolicy.html#variant.Propagate),
+    /// any SNP with a missing value makes the whole GRM NaN.
+    #[builder(default = "resolve_missing_policy()")]
+    missing_policy: MissingPolicy,
+
+    /// Select which individuals (samples) to relate -- Defaults to all.
+    ///
+    /// See the [Table of Index Expressions](index.html#index-expressions)
+    /// for a list of the supported index expressions.
+    #[builder(default = "Index::All")]
+    #[builder(setter(into))]
+    iid_index: Index,
+
+    /// Select which SNPs (variants) to use -- Defaults to all.
+    ///
+    /// See the [Table of Index Expressions](index.html#index-expressions)
+    /// for a list of the supported index expressions.
+    #[builder(default = "Index::All")]
+    #[builder(setter(into))]
+    sid_index: Index,
+
+    /// Number of threads to use (defaults to all processors)
+    #[builder(default, setter(strip_option))]
+    num_threads: Option<usize>,
+
+    /// For [`Bed::grm_to_file`](struct.Bed.html#method.grm_to_file), the number of rows
+    /// (individuals) computed per pass through the .bed file (defaults to as many as fit in
+    /// 8,000,000 values).
+    #[builder(default, setter(strip_option))]
+    band_iids: Option<usize>,
+}
+
+impl GrmOptions {
+    /// Options for [`Bed::grm`](struct.Bed.html#method.grm) and
+    /// [`Bed::grm_to_file`](struct.Bed.html#method.grm_to_file).
+    ///
+    /// > See [`Bed::grm`](struct.Bed.html#method.grm) for an example.
+    #[must_use]
+    pub fn builder() -> GrmOptionsBuilder {
+        GrmOptionsBuilder::default()
+    }
+
+    /// How each SNP (variant) is scaled after centering.
+    #[must_use]
+    pub fn dist(&self) -> Dist {
+        self.dist
+    }
+
+    /// What is done with missing values.
+    #[must_use]
+    pub fn missing_policy(&self) -> MissingPolicy {
+        self.missing_policy
+    }
+
+    /// The individuals (samples) to relate.
+    #[must_use]
+    pub fn iid_index(&self) -> &Index {
+        &self.iid_index
+    }
+
+    /// The SNPs (variants) to use.
+    #[must_use]
+    pub fn sid_index(&self) -> &Index {
+        &self.sid_index
+    }
+
+    /// The number of threads, if set.
+    #[must_use]
+    pub fn num_threads(&self) -> Option<usize> {
+        self.num_threads
+    }
+
+    /// The number of rows computed per pass by [`Bed::grm_to_file`](struct.Bed.html#method.grm_to_file), if set.
+    #[must_use]
+    pub fn band_iids(&self) -> Option<usize> {
+        self.band_iids
+    }
+}
+
+impl Bed {
+    /// Compute the genetic relationship matrix (GRM), or kinship matrix, of the selected
+    /// individuals (samples).
+    ///
+    /// Each selected SNP (variant) is standardized, as by
+    /// [`standardize`](fn.standardize.html), with the options'
+    /// [`Dist`](enum.Dist.html), to give a matrix `Z` with one row per individual. The GRM is
+    /// `Z Zᵀ / M`, where `M` is the number of SNPs. SNPs are read and standardized a chunk at a
+    /// time, through a [`Pipeline`](struct.Pipeline.html), and their products added to the
+    /// GRM, so the genotypes are never all in memory. For more individuals than fit in memory
+    /// squared, see [`Bed::grm_to_file`](struct.Bed.html#method.grm_to_file).
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, GrmOptions, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("grm.bed");
+    /// WriteOptions::builder(&output_file)
+    ///     .write(&nd::array![[0, 2, 1], [2, 0, 1], [0, 2, -127], [1, 1, 1]])?;
+    ///
+    /// let bed = Bed::new(&output_file)?;
+    /// let grm = bed.grm(&GrmOptions::builder().sid_index(..2).build()?)?;
+    /// assert_eq!(grm.dim(), (4, 4));
+    /// assert!((grm.diag().mean().unwrap() - 1.0).abs() < 1e-12);
+    /// assert_eq!(grm[(0, 2)], grm[(0, 0)]); // the same genotypes
+    /// assert!(grm[(0, 1)] < 0.0); // the opposite genotypes
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn grm(&self, grm_options: &GrmOptions) -> Result<nd::Array2<f64>, Box<BedErrorPlus>> {
+        let iid_count = grm_options.iid_index.len(self.iid_count()?)?;
+        self.grm_band(grm_options, 0..iid_count)
+    }
+
+    /// Compute the genetic relationship matrix (GRM), as [`Bed::grm`](struct.Bed.html#method.grm)
+    /// does, and write it to `path`, a band of rows at a time, so that the whole GRM is never in
+    /// memory.
+    ///
+    /// Each band of [`band_iids`](struct.GrmOptionsBuilder.html#method.band_iids) rows takes one
+    /// pass through the .bed file. The file holds the GRM's values, row after row, as
+    /// little-endian f64s, with no header. Returns a
+    /// [`BedError::ZeroBandIids`](enum.BedError.html#variant.ZeroBandIids) error if `band_iids`
+    /// is 0. On error, the file is removed.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, GrmOptions, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("grm.bed");
+    /// WriteOptions::builder(&output_file)
+    ///     .write(&nd::array![[0, 2, 1], [2, 0, 1], [0, 2, -127], [1, 1, 1]])?;
+    ///
+    /// let bed = Bed::new(&output_file)?;
+    /// let grm_options = GrmOptions::builder().band_iids(3).build()?;
+    /// let grm_file = output_folder.join("grm.bin");
+    /// bed.grm_to_file(&grm_file, &grm_options)?;
+    ///
+    /// let bytes = std::fs::read(&grm_file)?;
+    /// let values: Vec<f64> = bytes
+    ///     .chunks_exact(8)
+    ///     .map(|b| f64::from_le_bytes(b.try_into().unwrap()))
+    ///     .collect();
+    /// assert_eq!(nd::Array2::from_shape_vec((4, 4), values).unwrap(), bed.grm(&grm_options)?);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[anyinput]
+    pub fn grm_to_file(
+        &self,
+        path: AnyPath,
+        grm_options: &GrmOptions,
+    ) -> Result<(), Box<BedErrorPlus>> {
+        let iid_count = grm_options.iid_index.len(self.iid_count()?)?;
+        let band_iids = grm_options
+            .band_iids
+            .unwrap_or((GRM_BAND_VALUES / iid_count.max(1)).max(1));
+        if band_iids == 0 {
+            Err(BedError::ZeroBandIids)?;
+        }
+        let result = self.write_grm_bands(path, grm_options, iid_count, band_iids);
+        if result.is_err() {
+            // Clean up the file
+            let _ = fs::remove_file(path);
+        }
+        result
+    }
+
+    fn write_grm_bands(
+        &self,
+        path: &Path,
+        grm_options: &GrmOptions,
+        iid_count: usize,
+        band_iids: usize,
+    ) -> Result<(), Box<BedErrorPlus>> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        for band_start in (0..iid_count).step_by(band_iids) {
+            let band_end = (band_start + band_iids).min(iid_count);
+            let band = self.grm_band(grm_options, band_start..band_end)?;
+            for value in &band {
+                writer.write_all(&value.to_le_bytes())?;
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    // The rows of the GRM for the selected individuals in band, in one pass through the file.
+    fn grm_band(
+        &self,
+        grm_options: &GrmOptions,
+        band: Range<usize>,
+    ) -> Result<nd::Array2<f64>, Box<BedErrorPlus>> {
+        let num_threads = resolve_num_threads(grm_options.num_threads)?;
+        let read_options = ReadOptions::builder()
+            .iid_index(grm_options.iid_index.clone())
+            .sid_index(grm_options.sid_index.clone())
+            .num_threads(num_threads)
+            .f64()
+            .build()?;
+        let stats_options = StatsOptions::new().with_missing_policy(grm_options.missing_policy);
+        let dist = grm_options.dist;
+        let iid_count = grm_options.iid_index.len(self.iid_count()?)?;
+        let pipeline = self.pipeline(&read_options)?;
+        let sid_count = pipeline.sid_count();
+
+        let mut grm = nd::Array2::<f64>::zeros((band.len(), iid_count));
+        let pool = create_pool(num_threads)?;
+        pipeline
+            .map(|_sid_start, block| {
+                stats_options.standardize(block, dist)?;
+                Ok(())
+            })
+            .for_each(|_sid_start, block| {
+                // Add each band row's products with every row.
+                let band_block = block.slice(nd::s![band.clone(), ..]);
+                pool.install(|| {
+                    par_azip!((mut grm_row in grm.axis_iter_mut(nd::Axis(0)),
+                               z_row in band_block.axis_iter(nd::Axis(0)))
+                    {
+                        nd::linalg::general_mat_vec_mul(1.0, &block, &z_row, 1.0, &mut grm_row);
+                    });
+                });
+                Ok(())
+            })?;
+        // "as" is safe because the number of SNPs fits in f64's exact integers
+        #[allow(clippy::cast_precision_loss)]
+        let sid_count = sid_count as f64;
+        grm /= sid_count;
+        Ok(grm)
+    }
+}
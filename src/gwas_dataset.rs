@@ -0,0 +1,342 @@
+use anyinput::anyinput;
+use ndarray as nd;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+#[cfg(feature = "rand")]
+use crate::permute;
+use crate::{bed_path_from_prefix, Bed, BedError, BedErrorPlus, BedVal, Index, ReadOptions};
+
+// Phenotype and covariate values read as missing, as in PLINK.
+const MISSING_VALUES: [&str; 4] = ["NA", "na", "nan", "-9"];
+
+/// A .bed fileset with its phenotypes and, optionally, covariates, aligned by individual (sample).
+///
+/// Phenotype and covariate files are whitespace-separated, in PLINK's format: each line gives a
+/// family id (fid), an individual id (iid), and then one or more values. An optional first line
+/// starting `FID IID` names the value columns. `NA` and `-9` are missing.
+///
+/// The dataset keeps the individuals that are in the .fam file and in every other file and that
+/// have no missing phenotype or covariate values, in .fam file order. Phenotypes, covariates, and
+/// genotype reads all have one row per kept individual.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{GwasDataset, WriteOptions};
+///
+/// let output_folder = temp_testdir::TempDir::default();
+/// let prefix = output_folder.join("study");
+/// let val = nd::array![[0, 1, 2], [2, 1, 0], [1, 1, 1], [0, 0, 2]];
+/// WriteOptions::builder(prefix.with_extension("bed"))
+///     .iid(["i1", "i2", "i3", "i4"])
+///     .write(&val)?;
+/// let pheno_path = output_folder.join("study.pheno");
+/// std::fs::write(&pheno_path, "FID IID height\n0 i4 1.7\n0 i1 1.6\n0 i2 NA\n0 i3 1.8\n")?;
+/// let covar_path = output_folder.join("study.covar");
+/// std::fs::write(&covar_path, "0 i1 35 1\n0 i2 40 0\n0 i3 29 1\n")?;
+///
+/// let gwas = GwasDataset::open(&prefix, &pheno_path, &covar_path)?;
+/// assert_eq!(gwas.iid(), nd::array!["i1", "i3"]); // i2 lacks height; i4 lacks covariates
+/// assert_eq!(gwas.pheno("height"), Some(nd::array![1.6, 1.8].view()));
+/// assert_eq!(gwas.covariates(), nd::array![[35.0, 1.0], [29.0, 1.0]]);
+/// let genotypes = gwas.read::<f64>(..)?;
+/// assert_eq!(genotypes, nd::array![[0.0, 1.0, 2.0], [1.0, 1.0, 1.0]]);
+///
+/// let (train, test) = gwas.train_test_split(0.5, 0)?;
+/// assert_eq!((train.len(), test.len()), (1, 1));
+/// let test_genotypes = gwas.read_subset::<f64>(&test, ..)?;
+/// assert_eq!(test_genotypes.dim(), (1, 3));
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+pub struct GwasDataset {
+    bed: Bed,
+    iid_index: Vec<isize>,
+    fid: nd::Array1<String>,
+    iid: nd::Array1<String>,
+    pheno_names: Vec<String>,
+    pheno: nd::Array2<f64>,
+    covar_names: Vec<String>,
+    covariates: nd::Array2<f64>,
+}
+
+// The value column names and, by (fid, iid), the values of a phenotype or covariate file.
+type ValueTable = (Vec<String>, HashMap<(String, String), Vec<f64>>);
+
+fn read_value_table(path: &Path, default_prefix: &str) -> Result<ValueTable, Box<BedErrorPlus>> {
+    let mut names = Vec::new();
+    let mut rows = HashMap::new();
+    for (line_i, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        if fields.is_empty() {
+            continue;
+        }
+        if line_i == 0
+            && fields.len() >= 2
+            && fields[0].eq_ignore_ascii_case("FID")
+            && fields[1].eq_ignore_ascii_case("IID")
+        {
+            names = fields[2..].iter().map(|name| (*name).to_string()).collect();
+            continue;
+        }
+        if names.is_empty() {
+            names = (1..=fields.len().saturating_sub(2))
+                .map(|i| format!("{default_prefix}{i}"))
+                .collect();
+        }
+        if fields.len() != names.len() + 2 || names.is_empty() {
+            Err(BedError::MetadataFieldCount(
+                names.len().max(1) + 2,
+                fields.len(),
+                path.to_path_buf(),
+            ))?;
+        }
+        let values = fields[2..]
+            .iter()
+            .map(|value| {
+                if MISSING_VALUES.contains(value) {
+                    Ok(f64::NAN)
+                } else {
+                    value.parse::<f64>()
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        rows.insert((fields[0].to_string(), fields[1].to_string()), values);
+    }
+    Ok((names, rows))
+}
+
+impl GwasDataset {
+    /// Open a .bed fileset, given its path with or without the `.bed` extension, with a
+    /// phenotype file and a covariate file.
+    ///
+    /// > See [`GwasDataset`](struct.GwasDataset.html) for an example.
+    #[anyinput]
+    pub fn open(
+        prefix: AnyPath,
+        pheno_path: AnyPath,
+        covar_path: AnyPath,
+    ) -> Result<GwasDataset, Box<BedErrorPlus>> {
+        GwasDataset::open_internal(prefix, pheno_path, Some(covar_path))
+    }
+
+    /// Like [`GwasDataset::open`](struct.GwasDataset.html#method.open), but without covariates.
+    #[anyinput]
+    pub fn open_without_covariates(
+        prefix: AnyPath,
+        pheno_path: AnyPath,
+    ) -> Result<GwasDataset, Box<BedErrorPlus>> {
+        GwasDataset::open_internal(prefix, pheno_path, None)
+    }
+
+    fn open_internal(
+        prefix: &Path,
+        pheno_path: &Path,
+        covar_path: Option<&Path>,
+    ) -> Result<GwasDataset, Box<BedErrorPlus>> {
+        let bed = Bed::new(bed_path_from_prefix(prefix))?;
+        let (pheno_names, pheno_rows) = read_value_table(pheno_path, "pheno")?;
+        let (covar_names, covar_rows) = match covar_path {
+            Some(covar_path) => read_value_table(covar_path, "covar")?,
+            None => (Vec::new(), HashMap::new()),
+        };
+
+        let mut iid_index = Vec::new();
+        let mut fid = Vec::new();
+        let mut iid = Vec::new();
+        let mut pheno = Vec::new();
+        let mut covariates = Vec::new();
+        let bed_fid = bed.fid()?.clone();
+        let bed_iid = bed.iid()?;
+        for (iid_i, (fid_value, iid_value)) in bed_fid.iter().zip(bed_iid.iter()).enumerate() {
+            let key = (fid_value.clone(), iid_value.clone());
+            let Some(pheno_row) = pheno_rows.get(&key) else {
+                continue;
+            };
+            let covar_row = if covar_path.is_some() {
+                match covar_rows.get(&key) {
+                    Some(covar_row) => covar_row.as_slice(),
+                    None => continue,
+                }
+            } else {
+                &[]
+            };
+            if pheno_row
+                .iter()
+                .chain(covar_row)
+                .any(|value| value.is_nan())
+            {
+                continue;
+            }
+            // "as" is safe because counts fit in isize
+            iid_index.push(iid_i as isize);
+            fid.push(key.0);
+            iid.push(key.1);
+            pheno.extend_from_slice(pheno_row);
+            covariates.extend_from_slice(covar_row);
+        }
+
+        let iid_count = iid_index.len();
+        // unwraps always work because each row has one value per name
+        let pheno = nd::Array2::from_shape_vec((iid_count, pheno_names.len()), pheno).unwrap();
+        let covariates =
+            nd::Array2::from_shape_vec((iid_count, covar_names.len()), covariates).unwrap();
+        Ok(GwasDataset {
+            bed,
+            iid_index,
+            fid: nd::Array1::from_vec(fid),
+            iid: nd::Array1::from_vec(iid),
+            pheno_names,
+            pheno,
+            covar_names,
+            covariates,
+        })
+    }
+
+    /// Number of individuals (samples) kept.
+    #[must_use]
+    pub fn iid_count(&self) -> usize {
+        self.iid_index.len()
+    }
+
+    /// Number of SNPs (variants).
+    pub fn sid_count(&self) -> Result<usize, Box<BedErrorPlus>> {
+        self.bed.sid_count()
+    }
+
+    /// The underlying [`Bed`](struct.Bed.html), for example, to read SNP metadata.
+    pub fn bed(&mut self) -> &mut Bed {
+        &mut self.bed
+    }
+
+    /// For each individual kept, its index in the .bed file.
+    ///
+    /// Give this to [`ReadOptionsBuilder::iid_index`](struct.ReadOptionsBuilder.html#method.iid_index)
+    /// to read with other options.
+    #[must_use]
+    pub fn iid_index(&self) -> &[isize] {
+        &self.iid_index
+    }
+
+    /// Family id of each individual kept.
+    #[must_use]
+    pub fn fid(&self) -> &nd::Array1<String> {
+        &self.fid
+    }
+
+    /// Individual id of each individual kept.
+    #[must_use]
+    pub fn iid(&self) -> &nd::Array1<String> {
+        &self.iid
+    }
+
+    /// Names of the phenotypes, from the header or, if none, `pheno1`, `pheno2`, ...
+    #[must_use]
+    pub fn pheno_names(&self) -> &[String] {
+        &self.pheno_names
+    }
+
+    /// The values of the named phenotype, one per individual kept.
+    #[must_use]
+    pub fn pheno(&self, name: &str) -> Option<nd::ArrayView1<'_, f64>> {
+        let pheno_i = self.pheno_names.iter().position(|n| n == name)?;
+        Some(self.pheno.column(pheno_i))
+    }
+
+    /// All phenotypes, with one row per individual kept and one column per phenotype.
+    #[must_use]
+    pub fn phenos(&self) -> &nd::Array2<f64> {
+        &self.pheno
+    }
+
+    /// Names of the covariates, from the header or, if none, `covar1`, `covar2`, ...
+    #[must_use]
+    pub fn covar_names(&self) -> &[String] {
+        &self.covar_names
+    }
+
+    /// The covariates, with one row per individual kept and one column per covariate.
+    #[must_use]
+    pub fn covariates(&self) -> &nd::Array2<f64> {
+        &self.covariates
+    }
+
+    /// Read genotypes for every individual kept and the selected SNPs (variants).
+    ///
+    /// > See [`GwasDataset`](struct.GwasDataset.html) for an example.
+    pub fn read<TVal: BedVal>(
+        &self,
+        sid_index: impl Into<Index>,
+    ) -> Result<nd::Array2<TVal>, Box<BedErrorPlus>> {
+        ReadOptions::builder()
+            .iid_index(&self.iid_index)
+            .sid_index(sid_index)
+            .read(&self.bed)
+    }
+
+    /// Read genotypes for some of the individuals kept, given by their positions in this
+    /// dataset, for example, a split from
+    /// [`train_test_split`](struct.GwasDataset.html#method.train_test_split).
+    ///
+    /// > See [`GwasDataset`](struct.GwasDataset.html) for an example.
+    pub fn read_subset<TVal: BedVal>(
+        &self,
+        rows: &[usize],
+        sid_index: impl Into<Index>,
+    ) -> Result<nd::Array2<TVal>, Box<BedErrorPlus>> {
+        let iid_index = rows
+            .iter()
+            .map(|&row| {
+                self.iid_index.get(row).copied().ok_or_else(|| {
+                    // "as" is safe because counts fit in isize
+                    Box::new(BedError::IidIndexTooBig(row as isize).into())
+                })
+            })
+            .collect::<Result<Vec<_>, Box<BedErrorPlus>>>()?;
+        ReadOptions::builder()
+            .iid_index(iid_index)
+            .sid_index(sid_index)
+            .read(&self.bed)
+    }
+
+    /// Randomly split the individuals kept into training and test sets, returning their
+    /// positions in this dataset, each in increasing order.
+    ///
+    /// The test set has `test_fraction` of the individuals, rounded. The split is reproducible:
+    /// the same seed always gives the same split. Use the positions with
+    /// [`read_subset`](struct.GwasDataset.html#method.read_subset) and, for phenotypes and
+    /// covariates, `select(nd::Axis(0), &positions)`.
+    ///
+    /// > See [`GwasDataset`](struct.GwasDataset.html) for an example.
+    #[cfg(feature = "rand")]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn train_test_split(
+        &self,
+        test_fraction: f64,
+        seed: u64,
+    ) -> Result<(Vec<usize>, Vec<usize>), Box<BedErrorPlus>> {
+        if !(0.0..=1.0).contains(&test_fraction) {
+            Err(BedError::BadTestFraction(test_fraction))?;
+        }
+        let iid_count = self.iid_count();
+        // "as" is safe because the fraction is between 0 and 1
+        let test_count = (iid_count as f64 * test_fraction).round() as usize;
+        let shuffled = permute::iid_index(iid_count, seed);
+        // "as" is safe because permuted indexes are non-negative
+        let mut test = shuffled[..test_count]
+            .iter()
+            .map(|&i| i as usize)
+            .collect::<Vec<_>>();
+        let mut train = shuffled[test_count..]
+            .iter()
+            .map(|&i| i as usize)
+            .collect::<Vec<_>>();
+        test.sort_unstable();
+        train.sort_unstable();
+        Ok((train, test))
+    }
+}
@@ -0,0 +1,133 @@
+use ndarray as nd;
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::sync::Arc;
+
+use crate::{BedError, BedErrorPlus, BedVal, Metadata, WriteOptionsBuilder};
+
+/// What to do, when writing, with ids that some tools can't read.
+///
+/// Applies to the family (fid), individual (iid), father, and mother ids in the .fam file
+/// and to the SNP ids (sid) in the .bim file. An id is safe if it is not empty and uses only
+/// printable ASCII other than space, `#`, `"`, and `'`.
+///
+/// Set with [`WriteOptionsBuilder::id_policy`](struct.WriteOptionsBuilder.html#method.id_policy).
+/// Without a policy, ids are written as given.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum IdPolicy {
+    /// Percent-encode each byte of each unsafe character, and of `%`, as `%XX`.
+    /// For example, `"smith jr"` becomes `%22smith%20jr%22`. Distinct ids stay distinct.
+    Escape,
+    /// Return a [`BedError::UnsafeId`](enum.BedError.html#variant.UnsafeId) error for any
+    /// id that isn't safe, before anything is written.
+    Strict,
+}
+
+fn is_safe_char(c: char) -> bool {
+    c.is_ascii_graphic() && !matches!(c, '#' | '"' | '\'')
+}
+
+impl IdPolicy {
+    // Return the id to write.
+    pub(crate) fn apply<'a>(
+        self,
+        field: &str,
+        id: &'a str,
+    ) -> Result<Cow<'a, str>, Box<BedErrorPlus>> {
+        match self {
+            IdPolicy::Strict => {
+                if id.is_empty() || !id.chars().all(is_safe_char) {
+                    Err(BedError::UnsafeId(field.to_string(), id.to_string()))?;
+                }
+                Ok(Cow::Borrowed(id))
+            }
+            IdPolicy::Escape => {
+                if id.chars().all(|c| is_safe_char(c) && c != '%') {
+                    return Ok(Cow::Borrowed(id));
+                }
+                let mut escaped = String::with_capacity(id.len() * 3);
+                for c in id.chars() {
+                    if is_safe_char(c) && c != '%' {
+                        escaped.push(c);
+                    } else {
+                        let mut bytes = [0u8; 4];
+                        for byte in c.encode_utf8(&mut bytes).bytes() {
+                            // unwrap always works because writing to a String can't fail
+                            write!(escaped, "%{byte:02X}").unwrap();
+                        }
+                    }
+                }
+                Ok(Cow::Owned(escaped))
+            }
+        }
+    }
+
+    fn apply_field(
+        self,
+        field: &str,
+        values: Option<&Arc<nd::Array1<String>>>,
+    ) -> Result<Option<Arc<nd::Array1<String>>>, Box<BedErrorPlus>> {
+        let Some(values) = values else {
+            return Ok(None);
+        };
+        let applied = values
+            .iter()
+            .map(|id| Ok(self.apply(field, id)?.into_owned()))
+            .collect::<Result<nd::Array1<String>, Box<BedErrorPlus>>>()?;
+        Ok(Some(Arc::new(applied)))
+    }
+}
+
+impl Metadata {
+    // A copy of this metadata with the policy applied to its ids.
+    pub(crate) fn with_id_policy(&self, policy: IdPolicy) -> Result<Metadata, Box<BedErrorPlus>> {
+        let mut metadata = self.clone();
+        metadata.fid = policy.apply_field("fid", self.fid.as_ref())?;
+        metadata.iid = policy.apply_field("iid", self.iid.as_ref())?;
+        metadata.father = policy.apply_field("father", self.father.as_ref())?;
+        metadata.mother = policy.apply_field("mother", self.mother.as_ref())?;
+        metadata.sid = policy.apply_field("sid", self.sid.as_ref())?;
+        Ok(metadata)
+    }
+}
+
+impl<TVal> WriteOptionsBuilder<TVal>
+where
+    TVal: BedVal,
+{
+    /// Escape, or reject, ids that some tools can't read, such as ids with spaces, `#`,
+    /// quotes, or non-ASCII characters.
+    ///
+    /// See [`IdPolicy`](enum.IdPolicy.html) for the ids checked and the choices.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, IdPolicy, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("escaped.bed");
+    /// let val = nd::array![[1, 0], [2, 0], [0, 1]];
+    /// WriteOptions::builder(&output_file)
+    ///     .iid(["ann", "bob smith", "zoë"])
+    ///     .sid(["rs1", "#rs2"])
+    ///     .id_policy(IdPolicy::Escape)
+    ///     .write(&val)?;
+    ///
+    /// let bed = Bed::new(&output_file)?;
+    /// assert_eq!(bed.iid()?, nd::array!["ann", "bob%20smith", "zo%C3%AB"]);
+    /// assert_eq!(bed.sid()?, nd::array!["rs1", "%23rs2"]);
+    ///
+    /// let result = WriteOptions::builder(&output_file)
+    ///     .iid(["ann", "bob smith", "zoë"])
+    ///     .id_policy(IdPolicy::Strict)
+    ///     .write(&val);
+    /// assert!(result.is_err());
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn id_policy(&mut self, policy: IdPolicy) -> &mut Self {
+        self.id_policy = Some(Some(policy));
+        self
+    }
+}
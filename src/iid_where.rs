@@ -0,0 +1,159 @@
+use ndarray as nd;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::{Bed, BedCloud, BedError, BedErrorPlus, BedVal, Hold, ReadOptions, ReadOptionsBuilder};
+
+/// One individual's (sample's) .fam metadata, given to a
+/// [`ReadOptionsBuilder::iid_where`](struct.ReadOptionsBuilder.html#method.iid_where) predicate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FamRow<'a> {
+    /// Family id
+    pub fid: &'a str,
+    /// Individual id
+    pub iid: &'a str,
+    /// Father id
+    pub father: &'a str,
+    /// Mother id
+    pub mother: &'a str,
+    /// Sex (0 is unknown, 1 is male, 2 is female)
+    pub sex: i32,
+    /// Phenotype value
+    pub pheno: &'a str,
+}
+
+// A user predicate that selects individuals by their .fam metadata.
+#[derive(Clone)]
+pub(crate) struct IidWhere(Arc<dyn Fn(&FamRow<'_>) -> bool + Send + Sync>);
+
+impl fmt::Debug for IidWhere {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("IidWhere(..)")
+    }
+}
+
+impl IidWhere {
+    // Of the selected individuals, keep, in order, those whose .fam row passes the predicate.
+    fn filter(
+        &self,
+        iid_index: &[isize],
+        fam: [&nd::Array1<String>; 5],
+        sex: &nd::Array1<i32>,
+    ) -> Result<Vec<isize>, Box<BedErrorPlus>> {
+        let [fid, iid, father, mother, pheno] = fam;
+        let iid_count = iid.len();
+        let lower_iid_count = -(iid_count as isize);
+        let upper_iid_count: isize = (iid_count as isize) - 1;
+        let mut kept = Vec::new();
+        for &iid_i_signed in iid_index {
+            let iid_i = if (0..=upper_iid_count).contains(&iid_i_signed) {
+                iid_i_signed as usize
+            } else if (lower_iid_count..=-1).contains(&iid_i_signed) {
+                iid_count - ((-iid_i_signed) as usize)
+            } else {
+                Err(BedError::IidIndexTooBig(iid_i_signed))?
+            };
+            let row = FamRow {
+                fid: &fid[iid_i],
+                iid: &iid[iid_i],
+                father: &father[iid_i],
+                mother: &mother[iid_i],
+                sex: sex[iid_i],
+                pheno: &pheno[iid_i],
+            };
+            if (self.0)(&row) {
+                kept.push(iid_i_signed);
+            }
+        }
+        Ok(kept)
+    }
+}
+
+impl Bed {
+    // Resolve the iid index, keeping only the individuals that pass the iid_where predicate.
+    pub(crate) fn filter_iids<'a, TVal: BedVal>(
+        &self,
+        iid_count: usize,
+        read_options: &'a ReadOptions<TVal>,
+    ) -> Result<Hold<'a>, Box<BedErrorPlus>> {
+        let iid_hold = Hold::new(&read_options.iid_index, iid_count)?;
+        let Some(iid_where) = &read_options.iid_where else {
+            return Ok(iid_hold);
+        };
+        Ok(Hold::Copy(iid_where.filter(
+            iid_hold.as_ref(),
+            [
+                self.fid()?,
+                self.iid()?,
+                self.father()?,
+                self.mother()?,
+                self.pheno()?,
+            ],
+            self.sex()?,
+        )?))
+    }
+}
+
+impl BedCloud {
+    // Resolve the iid index, keeping only the individuals that pass the iid_where predicate.
+    pub(crate) async fn filter_iids<'a, TVal: BedVal>(
+        &mut self,
+        iid_count: usize,
+        read_options: &'a ReadOptions<TVal>,
+    ) -> Result<Hold<'a>, Box<BedErrorPlus>> {
+        let iid_hold = Hold::new(&read_options.iid_index, iid_count)?;
+        let Some(iid_where) = &read_options.iid_where else {
+            return Ok(iid_hold);
+        };
+        let fam = [
+            self.fid().await?.clone(),
+            self.iid().await?.clone(),
+            self.father().await?.clone(),
+            self.mother().await?.clone(),
+            self.pheno().await?.clone(),
+        ];
+        let sex = self.sex().await?.clone();
+        Ok(Hold::Copy(iid_where.filter(
+            iid_hold.as_ref(),
+            [&fam[0], &fam[1], &fam[2], &fam[3], &fam[4]],
+            &sex,
+        )?))
+    }
+}
+
+impl<TVal: BedVal> ReadOptionsBuilder<TVal> {
+    /// Of the individuals (samples) selected by [`iid_index`](struct.ReadOptionsBuilder.html#method.iid_index),
+    /// keep only those whose .fam metadata passes `predicate`.
+    ///
+    /// The predicate is given a [`FamRow`](struct.FamRow.html) for each selected individual. Individuals
+    /// keep their selected order. The .fam file is read, if needed, to evaluate the predicate.
+    ///
+    /// # Example:
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, ReadOptions, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("cases.bed");
+    /// WriteOptions::builder(&output_file)
+    ///     .fid(["CASE1", "CTRL1", "CASE2", "CASE3"])
+    ///     .sex([2, 2, 1, 2])
+    ///     .write(&nd::array![[0, 1], [2, 1], [1, 0], [0, -127]])?;
+    ///
+    /// let mut bed = Bed::new(&output_file)?;
+    /// let val = ReadOptions::builder()
+    ///     .iid_where(|row| row.sex == 2 && row.fid.starts_with("CASE"))
+    ///     .i8()
+    ///     .read(&mut bed)?;
+    /// assert_eq!(val, nd::array![[0, 1], [0, -127]]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn iid_where<F>(&mut self, predicate: F) -> &mut Self
+    where
+        F: Fn(&FamRow<'_>) -> bool + Send + Sync + 'static,
+    {
+        self.iid_where = Some(Some(IidWhere(Arc::new(predicate))));
+        self
+    }
+}
@@ -0,0 +1,318 @@
+use anyinput::anyinput;
+use ndarray as nd;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::chromosome::chromosome_sort_key;
+use crate::{format, try_div_4, BedError, BedErrorPlus, BedVal, Metadata};
+
+// Chromosome rank, chromosome name (for non-chromosomes), bp position, and push order.
+type SortKey = (u32, String, i32, u64);
+
+// One SNP (variant), with its genotypes already packed as .bed bytes.
+struct Snp {
+    chromosome: String,
+    bp_position: i32,
+    seq: u64,
+    sid: String,
+    allele_1: String,
+    allele_2: String,
+    bytes: Vec<u8>,
+}
+
+impl Snp {
+    fn sort_key(&self) -> SortKey {
+        let (rank, name) = chromosome_sort_key(&self.chromosome);
+        (rank, name, self.bp_position, self.seq)
+    }
+
+    fn write_to(&self, writer: &mut impl Write) -> Result<(), Box<BedErrorPlus>> {
+        writer.write_all(&self.seq.to_le_bytes())?;
+        writer.write_all(&self.bp_position.to_le_bytes())?;
+        for field in [&self.chromosome, &self.sid, &self.allele_1, &self.allele_2] {
+            // "as" is safe because the crate requires a 64-bit target
+            writer.write_all(&(field.len() as u64).to_le_bytes())?;
+            writer.write_all(field.as_bytes())?;
+        }
+        writer.write_all(&self.bytes)?;
+        Ok(())
+    }
+
+    // Returns None at the end of the run.
+    fn read_from(
+        reader: &mut impl Read,
+        bytes_per_snp: usize,
+    ) -> Result<Option<Snp>, Box<BedErrorPlus>> {
+        let mut seq = [0u8; 8];
+        match reader.read_exact(&mut seq) {
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            result => result?,
+        }
+        let mut bp_position = [0u8; 4];
+        reader.read_exact(&mut bp_position)?;
+        let mut read_field = || -> Result<String, Box<BedErrorPlus>> {
+            let mut len = [0u8; 8];
+            reader.read_exact(&mut len)?;
+            // "as" is safe because the crate requires a 64-bit target
+            let mut field = vec![0u8; u64::from_le_bytes(len) as usize];
+            reader.read_exact(&mut field)?;
+            Ok(String::from_utf8(field).map_err(|e| e.utf8_error())?)
+        };
+        let chromosome = read_field()?;
+        let sid = read_field()?;
+        let allele_1 = read_field()?;
+        let allele_2 = read_field()?;
+        let mut bytes = vec![0u8; bytes_per_snp];
+        reader.read_exact(&mut bytes)?;
+        Ok(Some(Snp {
+            chromosome,
+            bp_position: i32::from_le_bytes(bp_position),
+            seq: u64::from_le_bytes(seq),
+            sid,
+            allele_1,
+            allele_2,
+            bytes,
+        }))
+    }
+}
+
+// The path of a sorted run next to the output .bed file.
+fn run_path(path: &Path, run_i: usize) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(format!(".sort_run{run_i}"));
+    path.with_file_name(file_name)
+}
+
+/// Builds a .bed/.bim/.fam fileset, sorted by chromosome and bp position, from SNPs (variants)
+/// given in any order, for example, from unsorted VCF shards.
+///
+/// Each SNP is packed into .bed bytes as it is pushed. When `max_sids_in_memory` SNPs are held,
+/// they are sorted and spilled to a temporary run file next to the output. [`finish`](struct.ImportSorter.html#method.finish)
+/// merges the runs into the fileset, so imports far larger than memory need only about
+/// `max_sids_in_memory` SNPs, plus one SNP per run, in memory.
+///
+/// Chromosomes 1 to 22, X, Y, XY, and MT come first, in that order, whatever their naming
+/// scheme, followed by other chromosome names alphabetically. SNPs at the same position keep
+/// the order in which they were pushed. Run files are removed when the sorter finishes or is dropped.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{Bed, ImportSorter, Metadata};
+///
+/// let output_folder = temp_testdir::TempDir::default();
+/// let output_file = output_folder.join("sorted.bed");
+/// let mut sorter = ImportSorter::new(&output_file, 3, 2)?;
+/// sorter.push("2", 500, "rs3", "A", "G", &nd::array![0, 1, 2])?;
+/// sorter.push("1", 900, "rs2", "C", "T", &nd::array![1, 1, -127])?;
+/// sorter.push("X", 100, "rs4", "A", "C", &nd::array![2, 2, 2])?;
+/// sorter.push("1", 300, "rs1", "G", "T", &nd::array![0, 0, 1])?;
+/// sorter.finish(&Metadata::new())?;
+///
+/// let bed = Bed::new(&output_file)?;
+/// assert_eq!(bed.sid()?, nd::array!["rs1", "rs2", "rs3", "rs4"]);
+/// assert_eq!(bed.bp_position()?, nd::array![300, 900, 500, 100]);
+/// assert_eq!(
+///     bed.read::<i8>()?,
+///     nd::array![[0, 1, 0, 2], [0, 1, 1, 2], [1, -127, 2, 2]]
+/// );
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+pub struct ImportSorter {
+    path: PathBuf,
+    iid_count: usize,
+    bytes_per_snp: usize,
+    max_sids_in_memory: usize,
+    buffer: Vec<Snp>,
+    run_paths: Vec<PathBuf>,
+    // The number of SNPs pushed so far
+    sid_count: u64,
+}
+
+impl ImportSorter {
+    /// Create a sorter that will write a .bed file (and .bim and .fam files next to it) for
+    /// `iid_count` individuals (samples), holding at most `max_sids_in_memory` SNPs (variants)
+    /// in memory (at least 1).
+    ///
+    /// > See [`ImportSorter`](struct.ImportSorter.html) for an example.
+    #[anyinput]
+    pub fn new(
+        path: AnyPath,
+        iid_count: usize,
+        max_sids_in_memory: usize,
+    ) -> Result<ImportSorter, Box<BedErrorPlus>> {
+        // "as" is safe because the crate requires a 64-bit target
+        let bytes_per_snp = try_div_4(iid_count, 1)? as usize;
+        let max_sids_in_memory = max_sids_in_memory.max(1);
+        Ok(ImportSorter {
+            path: path.to_path_buf(),
+            iid_count,
+            bytes_per_snp,
+            max_sids_in_memory,
+            buffer: Vec::with_capacity(max_sids_in_memory),
+            run_paths: Vec::new(),
+            sid_count: 0,
+        })
+    }
+
+    /// Give one SNP (variant): its .bim fields and its genotype values, one per individual.
+    ///
+    /// Values must be 0, 1, 2, or missing (-127 for i8, NaN for floats), counting allele 1.
+    /// The .bim file gets a cM position of 0.
+    pub fn push<S, TVal>(
+        &mut self,
+        chromosome: &str,
+        bp_position: i32,
+        sid: &str,
+        allele_1: &str,
+        allele_2: &str,
+        val: &nd::ArrayBase<S, nd::Ix1>,
+    ) -> Result<(), Box<BedErrorPlus>>
+    where
+        S: nd::Data<Elem = TVal>,
+        TVal: BedVal,
+    {
+        if val.len() != self.iid_count {
+            Err(BedError::InconsistentCount(
+                "iid".to_string(),
+                self.iid_count,
+                val.len(),
+            ))?;
+        }
+
+        let missing = TVal::missing();
+        #[allow(clippy::eq_op)]
+        let use_nan = missing != missing; // generic NAN test
+        let mut bytes = vec![0u8; self.bytes_per_snp];
+        for (iid_i, &value) in val.iter().enumerate() {
+            #[allow(clippy::eq_op)]
+            let genotype_byte = if value == TVal::from(0) {
+                3
+            } else if value == TVal::from(1) {
+                2
+            } else if value == TVal::from(2) {
+                0
+            } else if (use_nan && value != value) || (!use_nan && value == missing) {
+                1
+            } else {
+                Err(BedError::BadValue(self.path.clone()))?
+            };
+            bytes[iid_i / 4] |= genotype_byte << (iid_i % 4 * 2);
+        }
+
+        self.buffer.push(Snp {
+            chromosome: chromosome.to_string(),
+            bp_position,
+            seq: self.sid_count,
+            sid: sid.to_string(),
+            allele_1: allele_1.to_string(),
+            allele_2: allele_2.to_string(),
+            bytes,
+        });
+        self.sid_count += 1;
+        if self.buffer.len() >= self.max_sids_in_memory {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    // Sort the SNPs in memory and write them to a new run file.
+    fn spill(&mut self) -> Result<(), Box<BedErrorPlus>> {
+        self.buffer.sort_by_cached_key(Snp::sort_key);
+        let run_path = run_path(&self.path, self.run_paths.len());
+        self.run_paths.push(run_path.clone());
+        let mut writer = BufWriter::new(File::create(run_path)?);
+        for snp in self.buffer.drain(..) {
+            snp.write_to(&mut writer)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Merge the SNPs (variants) into the sorted .bed, .bim, and .fam files.
+    ///
+    /// The .fam file gets the .fam fields of `metadata`, with defaults for any not given,
+    /// for example, iid "iid1" for the first individual.
+    pub fn finish(mut self, metadata: &Metadata) -> Result<(), Box<BedErrorPlus>> {
+        let mut fam_metadata = metadata.clone();
+        fam_metadata.chromosome = None;
+        fam_metadata.sid = None;
+        fam_metadata.cm_position = None;
+        fam_metadata.bp_position = None;
+        fam_metadata.allele_1 = None;
+        fam_metadata.allele_2 = None;
+        let fam_metadata = fam_metadata.fill(self.iid_count, 0)?;
+
+        let result = self.merge();
+        if result.is_err() {
+            // Clean up the files
+            let _ = fs::remove_file(&self.path);
+            let _ = fs::remove_file(self.path.with_extension("bim"));
+            return result;
+        }
+        fam_metadata.write_fam(self.path.with_extension("fam"))
+    }
+
+    // Write the .bed and .bim files from the sorted runs and the SNPs still in memory.
+    fn merge(&mut self) -> Result<(), Box<BedErrorPlus>> {
+        self.buffer.sort_by_cached_key(Snp::sort_key);
+        let mut readers = self
+            .run_paths
+            .iter()
+            .map(|run_path| Ok(BufReader::new(File::open(run_path)?)))
+            .collect::<Result<Vec<_>, Box<BedErrorPlus>>>()?;
+        let mut buffer = std::mem::take(&mut self.buffer).into_iter();
+
+        // The next SNP of each run, with the SNPs in memory as the last "run"
+        let mut heads: Vec<Option<Snp>> = Vec::with_capacity(readers.len() + 1);
+        let mut heap = BinaryHeap::with_capacity(readers.len() + 1);
+        for reader in &mut readers {
+            heads.push(Snp::read_from(reader, self.bytes_per_snp)?);
+        }
+        heads.push(buffer.next());
+        for (run_i, head) in heads.iter().enumerate() {
+            if let Some(snp) = head {
+                heap.push(Reverse((snp.sort_key(), run_i)));
+            }
+        }
+
+        let mut bed_writer = BufWriter::new(File::create(&self.path)?);
+        bed_writer.write_all(&format::Mode::SnpMajor.header())?;
+        let mut bim_writer = BufWriter::new(File::create(self.path.with_extension("bim"))?);
+        while let Some(Reverse((_, run_i))) = heap.pop() {
+            let Some(snp) = heads[run_i].take() else {
+                continue;
+            };
+            bed_writer.write_all(&snp.bytes)?;
+            writeln!(
+                bim_writer,
+                "{}\t{}\t0\t{}\t{}\t{}",
+                snp.chromosome, snp.sid, snp.bp_position, snp.allele_1, snp.allele_2
+            )?;
+
+            heads[run_i] = if run_i < readers.len() {
+                Snp::read_from(&mut readers[run_i], self.bytes_per_snp)?
+            } else {
+                buffer.next()
+            };
+            if let Some(snp) = &heads[run_i] {
+                heap.push(Reverse((snp.sort_key(), run_i)));
+            }
+        }
+        bed_writer.flush()?;
+        bim_writer.flush()?;
+        Ok(())
+    }
+}
+
+impl Drop for ImportSorter {
+    fn drop(&mut self) {
+        for run_path in &self.run_paths {
+            let _ = fs::remove_file(run_path);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use anyinput::anyinput;
+use ndarray as nd;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+
+use crate::parallel::prelude::*;
+use crate::{
+    format, write_metadata_files, Bed, BedErrorPlus, ReadOptions, ReadStrategy, WriteOptions,
+};
+
+// The most genotype values decoded at once when rewriting.
+const REWRITE_CHUNK_VALUES: usize = 8_000_000;
+
+impl Bed {
+    /// Write a copy of this .bed file, and its .fam and .bim files, in individual-major
+    /// ([`format::Mode::IndividualMajor`](format/enum.Mode.html#variant.IndividualMajor), mode 0) order.
+    ///
+    /// Each column of the new .bed file holds one individual's (sample's) values for all SNPs
+    /// (variants), so reading all SNPs for a few individuals, for example, to score each sample,
+    /// reads contiguous bytes. This crate reads both modes. The copy is made a block of
+    /// individuals at a time, each read in one sequential pass with
+    /// [`ReadStrategy::Scan`](enum.ReadStrategy.html#variant.Scan).
+    ///
+    /// The .fam and .bim files are written next to `path`, with the metadata of this
+    /// [`Bed`](struct.Bed.html). On error, the new .bed file is removed.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{format, Bed, ReadOptions, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("snp_major.bed");
+    /// let val = nd::array![[0, 1, 2], [2, -127, 0], [1, 1, 1]];
+    /// WriteOptions::builder(&output_file).iid(["a", "b", "c"]).write(&val)?;
+    ///
+    /// let transposed_file = output_folder.join("iid_major.bed");
+    /// Bed::new(&output_file)?.rewrite_as_individual_major(&transposed_file)?;
+    /// assert_eq!(format::read_mode(&transposed_file)?, format::Mode::IndividualMajor);
+    ///
+    /// let mut bed = Bed::new(&transposed_file)?;
+    /// assert_eq!(bed.iid()?, nd::array!["a", "b", "c"]);
+    /// assert_eq!(ReadOptions::builder().iid_index(1).i8().read(&mut bed)?, nd::array![[2, -127, 0]]);
+    /// assert_eq!(bed.read::<i8>()?, val);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[anyinput]
+    pub fn rewrite_as_individual_major(&self, path: AnyPath) -> Result<(), Box<BedErrorPlus>> {
+        let result = self.rewrite_as_individual_major_internal(path);
+        if result.is_err() {
+            // Clean up the file
+            let _ = fs::remove_file(path);
+        }
+        result
+    }
+
+    fn rewrite_as_individual_major_internal(
+        &self,
+        path: &std::path::Path,
+    ) -> Result<(), Box<BedErrorPlus>> {
+        let (iid_count, sid_count) = self.dim()?;
+        let metadata = self.metadata()?;
+        let write_options = WriteOptions::builder(path)
+            .metadata(&metadata)
+            .i8()
+            .build(iid_count, sid_count)?;
+        let column_len = format::bytes_per_column(sid_count) as usize;
+        // Check that the new file's length fits
+        format::expected_file_len((iid_count, sid_count), format::Mode::IndividualMajor)?;
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(&format::Mode::IndividualMajor.header())?;
+        let chunk_iid_count = (REWRITE_CHUNK_VALUES / sid_count.max(1)).max(1);
+        let mut start = 0;
+        while start < iid_count {
+            let end = (start + chunk_iid_count).min(iid_count);
+            let val = ReadOptions::builder()
+                .iid_index(start..end)
+                .read_strategy(ReadStrategy::Scan)
+                .c()
+                .i8()
+                .read(self)?;
+            let columns = val
+                .axis_iter(nd::Axis(0))
+                .into_par_iter()
+                .map(|row| encode_individual(row, column_len))
+                .collect::<Vec<Vec<u8>>>();
+            for column in columns {
+                writer.write_all(&column)?;
+            }
+            start = end;
+        }
+        writer.flush()?;
+
+        write_metadata_files(&write_options, &metadata)
+    }
+}
+
+// Pack one individual's values for all SNPs into an individual-major column.
+fn encode_individual(row: nd::ArrayView1<'_, i8>, column_len: usize) -> Vec<u8> {
+    let mut column = vec![0u8; column_len];
+    for (sid_i, &value) in row.iter().enumerate() {
+        let code = match value {
+            0 => format::HOM_ALLELE_2,
+            1 => format::HETEROZYGOUS,
+            2 => format::HOM_ALLELE_1,
+            _ => format::MISSING,
+        };
+        column[sid_i / 4] |= code << (sid_i % 4 * 2);
+    }
+    column
+}
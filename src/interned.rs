@@ -0,0 +1,112 @@
+use ndarray as nd;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::{BedError, BedErrorPlus};
+
+/// A column of strings, such as chromosomes or alleles, with each distinct string stored once.
+///
+/// Each value is kept as a 4-byte code into the distinct strings, rather than as its own
+/// `String`, so a column with few distinct values, for example, the chromosomes or alleles
+/// of tens of millions of SNPs (variants), takes a fraction of the memory of an `Array1<String>`.
+/// Create one from `&str` values without allocating a `String` per value and write it with, for example,
+/// [`WriteOptionsBuilder::chromosome_interned`](struct.WriteOptionsBuilder.html#method.chromosome_interned).
+/// Cloning is cheap; clones share their storage.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::InternedStrings;
+///
+/// let chromosome = InternedStrings::new(["1", "1", "2", "1", "X"])?;
+/// assert_eq!(chromosome.len(), 5);
+/// assert_eq!(chromosome.distinct_count(), 3);
+/// assert_eq!(chromosome.get(3), Some("1"));
+/// assert_eq!(chromosome.to_array(), nd::array!["1", "1", "2", "1", "X"]);
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InternedStrings {
+    distinct: Arc<Vec<Box<str>>>,
+    codes: Arc<Vec<u32>>,
+}
+
+impl InternedStrings {
+    /// Create a column from values, storing each distinct value once.
+    ///
+    /// Returns a [`BedError::TooManyDistinctStrings`](enum.BedError.html#variant.TooManyDistinctStrings)
+    /// error if there are more than `u32::MAX` distinct values.
+    ///
+    /// > See [`InternedStrings`](struct.InternedStrings.html) for an example.
+    pub fn new<I, S>(values: I) -> Result<InternedStrings, Box<BedErrorPlus>>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut distinct: Vec<Box<str>> = Vec::new();
+        let mut code_of: HashMap<Box<str>, u32> = HashMap::new();
+        let mut codes = Vec::new();
+        for value in values {
+            let value = value.as_ref();
+            let code = if let Some(&code) = code_of.get(value) {
+                code
+            } else {
+                let Ok(code) = u32::try_from(distinct.len()) else {
+                    Err(BedError::TooManyDistinctStrings(distinct.len()))?
+                };
+                distinct.push(value.into());
+                code_of.insert(value.into(), code);
+                code
+            };
+            codes.push(code);
+        }
+        Ok(InternedStrings {
+            distinct: Arc::new(distinct),
+            codes: Arc::new(codes),
+        })
+    }
+
+    /// The number of values.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.codes.len()
+    }
+
+    /// True if there are no values.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.codes.is_empty()
+    }
+
+    /// The number of distinct values.
+    #[must_use]
+    pub fn distinct_count(&self) -> usize {
+        self.distinct.len()
+    }
+
+    /// The value at an index, if the index is in range.
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<&str> {
+        let code = *self.codes.get(index)?;
+        Some(&self.distinct[code as usize])
+    }
+
+    /// Iterate over the values, in order.
+    pub fn iter(&self) -> impl Iterator<Item = &str> + '_ {
+        self.codes
+            .iter()
+            .map(|&code| &*self.distinct[code as usize])
+    }
+
+    /// The values as an `Array1<String>`, allocating a `String` per value.
+    #[must_use]
+    pub fn to_array(&self) -> nd::Array1<String> {
+        self.iter().map(str::to_string).collect()
+    }
+
+    // The values, one `String` at a time, for streaming to a .bim file.
+    pub(crate) fn into_lazy_iter(self) -> impl Iterator<Item = String> + 'static {
+        (0..self.len()).map(move |i| self.distinct[self.codes[i] as usize].to_string())
+    }
+}
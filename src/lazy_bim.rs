@@ -0,0 +1,402 @@
+use anyinput::anyinput;
+use ndarray as nd;
+use std::cell::RefCell;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::dedup::SidDeduper;
+use crate::{
+    BedError, BedErrorPlus, BedVal, DuplicateSidPolicy, IdPolicy, InternedStrings, Metadata,
+    WriteOptionsBuilder,
+};
+
+// A one-pass source of .bim values, already formatted for writing.
+type LazyColumn = Rc<RefCell<Box<dyn Iterator<Item = String>>>>;
+
+/// .bim values that are streamed to the .bim file, rather than held in memory, when writing.
+///
+/// Set with, for example, [`WriteOptionsBuilder::sid_iter`](struct.WriteOptionsBuilder.html#method.sid_iter).
+#[derive(Clone, Default)]
+pub(crate) struct LazyBim {
+    chromosome: Option<LazyColumn>,
+    sid: Option<LazyColumn>,
+    cm_position: Option<LazyColumn>,
+    bp_position: Option<LazyColumn>,
+    allele_1: Option<LazyColumn>,
+    allele_2: Option<LazyColumn>,
+    // Set when completed
+    sid_count: usize,
+}
+
+impl fmt::Debug for LazyBim {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazyBim")
+            .field("chromosome", &self.chromosome.is_some())
+            .field("sid", &self.sid.is_some())
+            .field("cm_position", &self.cm_position.is_some())
+            .field("bp_position", &self.bp_position.is_some())
+            .field("allele_1", &self.allele_1.is_some())
+            .field("allele_2", &self.allele_2.is_some())
+            .field("sid_count", &self.sid_count)
+            .finish()
+    }
+}
+
+fn lazy_column<I>(iter: I) -> LazyColumn
+where
+    I: Iterator<Item = String> + 'static,
+{
+    Rc::new(RefCell::new(Box::new(iter)))
+}
+
+// Use the lazy column if given, else the metadata's array, else default values.
+fn complete_column<T, F>(
+    column: Option<&LazyColumn>,
+    array: Option<&Arc<nd::Array1<T>>>,
+    sid_count: usize,
+    default: F,
+) -> LazyColumn
+where
+    T: ToString + 'static,
+    F: Fn(usize) -> String + 'static,
+{
+    if let Some(column) = column {
+        return column.clone();
+    }
+    let iter: Box<dyn Iterator<Item = String>> = if let Some(array) = array {
+        let array = array.clone();
+        Box::new((0..array.len()).map(move |i| array[i].to_string()))
+    } else {
+        Box::new((0..sid_count).map(default))
+    };
+    Rc::new(RefCell::new(iter))
+}
+
+impl LazyBim {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.chromosome.is_none()
+            && self.sid.is_none()
+            && self.cm_position.is_none()
+            && self.bp_position.is_none()
+            && self.allele_1.is_none()
+            && self.allele_2.is_none()
+    }
+
+    // Fill in every column not given lazily, using the metadata's arrays or the default values.
+    // Also returns the metadata with its .bim fields emptied, so that they are never materialized.
+    pub(crate) fn complete(
+        &self,
+        metadata: &Metadata,
+        sid_count: usize,
+    ) -> Result<(LazyBim, Metadata), Box<BedErrorPlus>> {
+        metadata.check_counts(None, Some(sid_count))?;
+
+        let lazy_bim = LazyBim {
+            chromosome: Some(complete_column(
+                self.chromosome.as_ref(),
+                metadata.chromosome.as_ref(),
+                sid_count,
+                |_| "0".to_string(),
+            )),
+            sid: Some(complete_column(
+                self.sid.as_ref(),
+                metadata.sid.as_ref(),
+                sid_count,
+                |i| format!("sid{}", i + 1),
+            )),
+            cm_position: Some(complete_column(
+                self.cm_position.as_ref(),
+                metadata.cm_position.as_ref(),
+                sid_count,
+                |_| "0".to_string(),
+            )),
+            bp_position: Some(complete_column(
+                self.bp_position.as_ref(),
+                metadata.bp_position.as_ref(),
+                sid_count,
+                |_| "0".to_string(),
+            )),
+            allele_1: Some(complete_column(
+                self.allele_1.as_ref(),
+                metadata.allele_1.as_ref(),
+                sid_count,
+                |_| "A1".to_string(),
+            )),
+            allele_2: Some(complete_column(
+                self.allele_2.as_ref(),
+                metadata.allele_2.as_ref(),
+                sid_count,
+                |_| "A2".to_string(),
+            )),
+            sid_count,
+        };
+
+        let mut metadata = metadata.clone();
+        metadata.chromosome = Some(Arc::new(nd::Array1::default(0)));
+        metadata.sid = Some(Arc::new(nd::Array1::default(0)));
+        metadata.cm_position = Some(Arc::new(nd::Array1::default(0)));
+        metadata.bp_position = Some(Arc::new(nd::Array1::default(0)));
+        metadata.allele_1 = Some(Arc::new(nd::Array1::default(0)));
+        metadata.allele_2 = Some(Arc::new(nd::Array1::default(0)));
+
+        Ok((lazy_bim, metadata))
+    }
+
+    pub(crate) fn sid_count(&self) -> usize {
+        self.sid_count
+    }
+
+    // Stream the (completed) columns to a .bim file, checking that each has sid_count values.
+    // Repeated sids are handled, one line at a time, with the policy, if given.
+    // Then, sids are escaped or checked with the id policy, if given.
+    #[anyinput]
+    pub(crate) fn write_bim(
+        &self,
+        path: AnyPath,
+        dedup_sids: Option<DuplicateSidPolicy>,
+        id_policy: Option<IdPolicy>,
+    ) -> Result<(), Box<BedErrorPlus>> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_bim_to(&mut writer, dedup_sids, id_policy)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    // Like write_bim, but streaming the lines to writer.
+    pub(crate) fn write_bim_to<W: Write>(
+        &self,
+        writer: &mut W,
+        dedup_sids: Option<DuplicateSidPolicy>,
+        id_policy: Option<IdPolicy>,
+    ) -> Result<(), Box<BedErrorPlus>> {
+        let sid_count = self.sid_count;
+        let mut deduper = dedup_sids.map(SidDeduper::new);
+        let named_columns = [
+            ("chromosome", &self.chromosome),
+            ("sid", &self.sid),
+            ("cm_position", &self.cm_position),
+            ("bp_position", &self.bp_position),
+            ("allele_1", &self.allele_1),
+            ("allele_2", &self.allele_2),
+        ];
+        let mut columns = Vec::with_capacity(named_columns.len());
+        for (name, column) in named_columns {
+            let Some(column) = column else {
+                Err(BedError::MetadataMissingForWrite(name.to_string()))?
+            };
+            columns.push((name, column.borrow_mut()));
+        }
+
+        let mut line = Vec::with_capacity(columns.len());
+        for sid_i in 0..sid_count {
+            line.clear();
+            for (name, column) in &mut columns {
+                let Some(value) = column.next() else {
+                    Err(BedError::InconsistentCount(
+                        (*name).to_string(),
+                        sid_count,
+                        sid_i,
+                    ))?
+                };
+                let value = match (&mut deduper, *name) {
+                    (Some(deduper), "sid") => {
+                        let Some(value) = deduper.dedup(value)? else {
+                            Err(BedError::KeepFirstWithIter)?
+                        };
+                        value
+                    }
+                    _ => value,
+                };
+                let value = match (id_policy, *name) {
+                    (Some(id_policy), "sid") => id_policy.apply("sid", &value)?.into_owned(),
+                    _ => value,
+                };
+                line.push(value);
+            }
+            writeln!(writer, "{}", line.join("\t"))?;
+        }
+        for (name, column) in &mut columns {
+            let extra_count = column.by_ref().count();
+            if extra_count > 0 {
+                Err(BedError::InconsistentCount(
+                    (*name).to_string(),
+                    sid_count,
+                    sid_count + extra_count,
+                ))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<TVal> WriteOptionsBuilder<TVal>
+where
+    TVal: BedVal,
+{
+    fn lazy_bim_mut(&mut self) -> &mut LazyBim {
+        self.lazy_bim.get_or_insert_with(LazyBim::default)
+    }
+
+    /// Set the SNP id (sid) for each SNP (variant) from an iterator that is only run when writing.
+    ///
+    /// Unlike [`sid`](struct.WriteOptionsBuilder.html#method.sid), the values are never collected in memory.
+    /// Instead, they are streamed, one line at a time, to the .bim file. This is useful for
+    /// very large, for example, synthetic, datasets.
+    ///
+    /// When any .bim field is set from an iterator, every .bim field is streamed and the .bim
+    /// accessors of the resulting [`WriteOptions`](struct.WriteOptions.html) return empty arrays.
+    /// An iterator is used up by the first write. The iterator must produce exactly one value per SNP.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, WriteOptions};
+    ///
+    /// let val = nd::array![[1, 0, -127, 0], [2, 0, -127, 2], [0, 1, 2, 0]];
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("lazy.bed");
+    /// WriteOptions::builder(&output_file)
+    ///     .sid_iter((0..4).map(|i| format!("rs{}", 1000 + i)))
+    ///     .bp_position_iter((0..4).map(|i| 100 * i + 1))
+    ///     .write(&val)?;
+    ///
+    /// let bed = Bed::new(&output_file)?;
+    /// println!("{:?}", bed.sid()?); // Outputs ndarray ["rs1000", "rs1001", "rs1002", "rs1003"]
+    /// println!("{:?}", bed.bp_position()?); // Outputs ndarray [1, 101, 201, 301]
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[must_use]
+    pub fn sid_iter<I>(mut self, sid: I) -> Self
+    where
+        I: IntoIterator<Item = String>,
+        I::IntoIter: 'static,
+    {
+        self.lazy_bim_mut().sid = Some(lazy_column(sid.into_iter()));
+        self
+    }
+
+    /// Set the chromosome for each SNP (variant) from an iterator that is only run when writing.
+    ///
+    /// > See [`sid_iter`](struct.WriteOptionsBuilder.html#method.sid_iter) for details and an example.
+    #[must_use]
+    pub fn chromosome_iter<I>(mut self, chromosome: I) -> Self
+    where
+        I: IntoIterator<Item = String>,
+        I::IntoIter: 'static,
+    {
+        self.lazy_bim_mut().chromosome = Some(lazy_column(chromosome.into_iter()));
+        self
+    }
+
+    /// Set the centimorgan position for each SNP (variant) from an iterator that is only run when writing.
+    ///
+    /// > See [`sid_iter`](struct.WriteOptionsBuilder.html#method.sid_iter) for details and an example.
+    #[must_use]
+    pub fn cm_position_iter<I>(mut self, cm_position: I) -> Self
+    where
+        I: IntoIterator<Item = f32>,
+        I::IntoIter: 'static,
+    {
+        self.lazy_bim_mut().cm_position = Some(lazy_column(
+            cm_position.into_iter().map(|value| value.to_string()),
+        ));
+        self
+    }
+
+    /// Set the base-pair position for each SNP (variant) from an iterator that is only run when writing.
+    ///
+    /// > See [`sid_iter`](struct.WriteOptionsBuilder.html#method.sid_iter) for details and an example.
+    #[must_use]
+    pub fn bp_position_iter<I>(mut self, bp_position: I) -> Self
+    where
+        I: IntoIterator<Item = i32>,
+        I::IntoIter: 'static,
+    {
+        self.lazy_bim_mut().bp_position = Some(lazy_column(
+            bp_position.into_iter().map(|value| value.to_string()),
+        ));
+        self
+    }
+
+    /// Set the first allele for each SNP (variant) from an iterator that is only run when writing.
+    ///
+    /// > See [`sid_iter`](struct.WriteOptionsBuilder.html#method.sid_iter) for details and an example.
+    #[must_use]
+    pub fn allele_1_iter<I>(mut self, allele_1: I) -> Self
+    where
+        I: IntoIterator<Item = String>,
+        I::IntoIter: 'static,
+    {
+        self.lazy_bim_mut().allele_1 = Some(lazy_column(allele_1.into_iter()));
+        self
+    }
+
+    /// Set the second allele for each SNP (variant) from an iterator that is only run when writing.
+    ///
+    /// > See [`sid_iter`](struct.WriteOptionsBuilder.html#method.sid_iter) for details and an example.
+    #[must_use]
+    pub fn allele_2_iter<I>(mut self, allele_2: I) -> Self
+    where
+        I: IntoIterator<Item = String>,
+        I::IntoIter: 'static,
+    {
+        self.lazy_bim_mut().allele_2 = Some(lazy_column(allele_2.into_iter()));
+        self
+    }
+
+    /// Set the chromosome for each SNP (variant) from [`InternedStrings`](struct.InternedStrings.html).
+    ///
+    /// The values are streamed to the .bim file, one line at a time, as with
+    /// [`chromosome_iter`](struct.WriteOptionsBuilder.html#method.chromosome_iter), so no
+    /// `Array1<String>` is ever made. See [`sid_iter`](struct.WriteOptionsBuilder.html#method.sid_iter)
+    /// for how this affects the other .bim fields.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, InternedStrings, WriteOptions};
+    ///
+    /// let val = nd::array![[1, 0, -127, 0], [2, 0, -127, 2], [0, 1, 2, 0]];
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("interned.bed");
+    /// WriteOptions::builder(&output_file)
+    ///     .chromosome_interned(&InternedStrings::new(["1", "1", "1", "2"])?)
+    ///     .allele_1_interned(&InternedStrings::new(["A", "C", "A", "A"])?)
+    ///     .allele_2_interned(&InternedStrings::new(["G", "G", "T", "G"])?)
+    ///     .write(&val)?;
+    ///
+    /// let bed = Bed::new(&output_file)?;
+    /// assert_eq!(bed.chromosome()?, nd::array!["1", "1", "1", "2"]);
+    /// assert_eq!(bed.allele_2()?, nd::array!["G", "G", "T", "G"]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[must_use]
+    pub fn chromosome_interned(mut self, chromosome: &InternedStrings) -> Self {
+        self.lazy_bim_mut().chromosome = Some(lazy_column(chromosome.clone().into_lazy_iter()));
+        self
+    }
+
+    /// Set the first allele for each SNP (variant) from [`InternedStrings`](struct.InternedStrings.html).
+    ///
+    /// > See [`chromosome_interned`](struct.WriteOptionsBuilder.html#method.chromosome_interned) for details and an example.
+    #[must_use]
+    pub fn allele_1_interned(mut self, allele_1: &InternedStrings) -> Self {
+        self.lazy_bim_mut().allele_1 = Some(lazy_column(allele_1.clone().into_lazy_iter()));
+        self
+    }
+
+    /// Set the second allele for each SNP (variant) from [`InternedStrings`](struct.InternedStrings.html).
+    ///
+    /// > See [`chromosome_interned`](struct.WriteOptionsBuilder.html#method.chromosome_interned) for details and an example.
+    #[must_use]
+    pub fn allele_2_interned(mut self, allele_2: &InternedStrings) -> Self {
+        self.lazy_bim_mut().allele_2 = Some(lazy_column(allele_2.clone().into_lazy_iter()));
+        self
+    }
+}
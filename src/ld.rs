@@ -0,0 +1,118 @@
+use ndarray as nd;
+
+use crate::parallel::prelude::*;
+use crate::{Bed, BedError, BedErrorPlus, ReadOptions};
+
+// The most genotype values read at once when computing LD.
+const LD_CHUNK_VALUES: usize = 8_000_000;
+
+impl Bed {
+    /// Compute the linkage disequilibrium (LD), r², of each SNP (variant) near a target SNP with
+    /// that target, for example, to find proxies for a SNP.
+    ///
+    /// The window is the `window` SNPs on each side of `target_sid` that are on the target's
+    /// chromosome. Returns the window's SNP indexes, in order and including the target, and
+    /// each one's r² with the target. r² is the squared Pearson correlation of allele 1 counts
+    /// over the individuals (samples) with values for both SNPs. It is NaN if either SNP has no
+    /// variation over those individuals.
+    ///
+    /// The target is read once and the window is streamed in chunks, each SNP computed in
+    /// parallel. Returns a [`BedError::SidIndexTooBig`](enum.BedError.html#variant.SidIndexTooBig)
+    /// error if `target_sid` isn't a SNP index.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("ld.bed");
+    /// WriteOptions::builder(&output_file)
+    ///     .chromosome(["1", "1", "1", "1", "2"])
+    ///     .write(&nd::array![
+    ///         [0, 2, 0, 0, 0],
+    ///         [1, 1, 1, 1, 1],
+    ///         [2, 0, 2, -127, 2],
+    ///         [2, 0, 0, 1, 2]
+    ///     ])?;
+    ///
+    /// let mut bed = Bed::new(&output_file)?;
+    /// let (sid_index, r2) = bed.ld_with(1, 10)?;
+    /// assert_eq!(sid_index, nd::array![0, 1, 2, 3]);
+    /// assert_eq!(r2.mapv(|r2| (r2 * 100.0).round() / 100.0), nd::array![1.0, 1.0, 0.21, 0.75]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn ld_with(
+        &mut self,
+        target_sid: usize,
+        window: usize,
+    ) -> Result<(nd::Array1<usize>, nd::Array1<f64>), Box<BedErrorPlus>> {
+        let iid_count = self.iid_count()?;
+        let sid_count = self.sid_count()?;
+        if target_sid >= sid_count {
+            Err(BedError::SidIndexTooBig(target_sid as isize))?;
+        }
+
+        // The window stops at the ends of the target's chromosome
+        let chromosome = self.chromosome()?;
+        let target_chromosome = &chromosome[target_sid];
+        let mut start = target_sid.saturating_sub(window);
+        while chromosome[start] != *target_chromosome {
+            start += 1;
+        }
+        let mut end = (target_sid + window + 1).min(sid_count);
+        while chromosome[end - 1] != *target_chromosome {
+            end -= 1;
+        }
+
+        let target = ReadOptions::builder()
+            .sid_index(target_sid as isize)
+            .i8()
+            .read(self)?;
+        let target = target.column(0);
+
+        let mut r2 = Vec::with_capacity(end - start);
+        let chunk_len = (LD_CHUNK_VALUES / iid_count.max(1)).max(1);
+        for chunk_start in (start..end).step_by(chunk_len) {
+            let chunk_end = (chunk_start + chunk_len).min(end);
+            let val = ReadOptions::builder()
+                .sid_index(chunk_start..chunk_end)
+                .i8()
+                .read(self)?;
+            let chunk_r2: Vec<f64> = val
+                .axis_iter(nd::Axis(1))
+                .into_par_iter()
+                .map(|column| r_squared(target, column))
+                .collect();
+            r2.extend(chunk_r2);
+        }
+
+        Ok((nd::Array1::from_iter(start..end), nd::Array1::from_vec(r2)))
+    }
+}
+
+// The squared correlation of two SNPs over the individuals with values for both.
+fn r_squared(a: nd::ArrayView1<'_, i8>, b: nd::ArrayView1<'_, i8>) -> f64 {
+    let (mut n, mut sum_a, mut sum_b, mut sum_aa, mut sum_bb, mut sum_ab) =
+        (0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+    for (&a, &b) in a.iter().zip(b) {
+        // Missing values are negative
+        if a >= 0 && b >= 0 {
+            let (a, b) = (f64::from(a), f64::from(b));
+            n += 1.0;
+            sum_a += a;
+            sum_b += b;
+            sum_aa += a * a;
+            sum_bb += b * b;
+            sum_ab += a * b;
+        }
+    }
+    let var_a = n * sum_aa - sum_a * sum_a;
+    let var_b = n * sum_bb - sum_b * sum_b;
+    if var_a <= 0.0 || var_b <= 0.0 {
+        return f64::NAN;
+    }
+    let cov = n * sum_ab - sum_a * sum_b;
+    cov * cov / (var_a * var_b)
+}
@@ -35,6 +35,7 @@
 //! | [`iid_count`](struct.Bed.html#method.iid_count) | Number of individuals (samples) |
 //! | [`sid_count`](struct.Bed.html#method.sid_count) | Number of SNPs (variants) |
 //! | [`dim`](struct.Bed.html#method.dim) | Number of individuals and SNPs |
+//! | [`peek_dims`](struct.Bed.html#method.peek_dims) | Number of individuals and SNPs, with SNPs inferred from the .bed file length |
 //! | [`fid`](struct.Bed.html#method.fid) | Family id of each of individual (sample) |
 //! | [`iid`](struct.Bed.html#method.iid) | Individual id of each of individual (sample) |
 //! | [`father`](struct.Bed.html#method.father) | Father id of each of individual (sample) |
@@ -47,7 +48,9 @@
 //! | [`bp_position`](struct.Bed.html#method.bp_position) | Base-pair position of each SNP (variant) |
 //! | [`allele_1`](struct.Bed.html#method.allele_1) | First allele of each SNP (variant) |
 //! | [`allele_2`](struct.Bed.html#method.allele_2) | Second allele of each SNP (variant) |
+//! | [`sid_annotation`](struct.Bed.html#method.sid_annotation) | A named, per-SNP annotation, such as gene symbols, from a sidecar file |
 //! | [`metadata`](struct.Bed.html#method.metadata) | All the metadata returned as a [`struct.Metadata`](struct.Metadata.html) |
+//! | [`metadata_loaded`](struct.Bed.html#method.metadata_loaded) | The metadata already known, without reading any files |
 //!
 //! ### `ReadOptions`
 //!
@@ -62,16 +65,24 @@
 //! | [`f64`](struct.ReadOptionsBuilder.html#method.f64) | Read values as f64 |
 //! | [`iid_index`](struct.ReadOptionsBuilder.html#method.iid_index) | Index of individuals (samples) to read (defaults to all)|
 //! | [`sid_index`](struct.ReadOptionsBuilder.html#method.sid_index) | Index of SNPs (variants) to read (defaults to all) |
+//! | [`typed_iid_index`](struct.ReadOptionsBuilder.html#method.typed_iid_index), [`typed_sid_index`](struct.ReadOptionsBuilder.html#method.typed_sid_index) | Like `iid_index` and `sid_index`, but with an [`IidIndex`](struct.IidIndex.html) or [`SidIndex`](struct.SidIndex.html), which can't be mixed up |
+//! | [`iid_where`](struct.ReadOptionsBuilder.html#method.iid_where) | Keep only the selected individuals (samples) whose .fam metadata passes a predicate |
+//! | [`regions`](struct.ReadOptionsBuilder.html#method.regions) | Keep only the selected SNPs (variants) in a [`RegionSet`](struct.RegionSet.html) of genomic regions |
+//! | [`region`](struct.ReadOptionsBuilder.html#method.region) | Keep only the selected SNPs (variants) in a [`Region`](struct.Region.html), such as "2:100000-250000" |
 //! | [`f`](struct.ReadOptionsBuilder.html#method.f) | Order of the output array, Fortran-style (default) |
 //! | [`c`](struct.ReadOptionsBuilder.html#method.c) | Order of the output array, C-style |
 //! | [`is_f`](struct.ReadOptionsBuilder.html#method.is_f) | Is order of the output array Fortran-style? (defaults to true)|
+//! | [`transpose_output`](struct.ReadOptionsBuilder.html#method.transpose_output) | Output one row per SNP (variant) and one column per individual (sample) |
 //! | [`missing_value`](struct.ReadOptionsBuilder.html#method.missing_value) | Value to use for missing values (defaults to -127 or NaN) |
 //! | [`count_a1`](struct.ReadOptionsBuilder.html#method.count_a1) | Count the number allele 1 (default) |
 //! | [`count_a2`](struct.ReadOptionsBuilder.html#method.count_a2) | Count the number allele 2 |
 //! | [`is_a1_counted`](struct.ReadOptionsBuilder.html#method.is_a1_counted) | Is allele 1 counted? (defaults to true) |
+//! | [`count_a1_mask`](struct.ReadOptionsBuilder.html#method.count_a1_mask) | Per SNP, is allele 1 counted? Overrides `is_a1_counted` |
 //! | [`num_threads`](struct.ReadOptionsBuilder.html#method.num_threads) | Number of threads to use (defaults to all processors) |
-//! | [`max_concurrent_requests`](struct.ReadOptionsBuilder.html#method.max_concurrent_requests) | Maximum number of concurrent async requests (defaults to 10) -- Used by [`BedCloud`](struct.BedCloud.html). |
+//! | [`max_concurrent_requests`](struct.ReadOptionsBuilder.html#method.max_concurrent_requests) | Maximum number of concurrent async requests (defaults to 10) -- Used by [`BedCloud`](struct.BedCloud.html), [`ReadStrategy::BatchedPread`](enum.ReadStrategy.html#variant.BatchedPread), and [`BedSet`](struct.BedSet.html). |
 //! | [`max_chunk_bytes`](struct.ReadOptionsBuilder.html#method.max_chunk_bytes) | Maximum chunk size of async requests (defaults to 8_000_000 bytes) -- Used by [`BedCloud`](struct.BedCloud.html). |
+//! | [`read_strategy`](struct.ReadOptionsBuilder.html#method.read_strategy) | How to read SNPs from a local file, sequentially (default), with concurrent positional reads, or through a memory mapping |
+//! | [`progress`](struct.ReadOptionsBuilder.html#method.progress) | Call back with the SNPs (variants) read so far, for example, for a progress bar |
 //!
 //! ### [`Index`](enum.Index.html) Expressions
 //!
@@ -103,9 +114,19 @@
 //!
 //! If [`ReadOptionsBuilder::num_threads`](struct.ReadOptionsBuilder.html#method.num_threads)
 //! or [`WriteOptionsBuilder::num_threads`](struct.WriteOptionsBuilder.html#method.num_threads) is not specified,
+//! and no [`GlobalConfig`](struct.GlobalConfig.html) sets it,
 //! the number of threads to use is determined by these environment variable (in order of priority):
 //! If neither of these environment variables are set, all processors are used.
 //!
+//! * `BED_READER_MAX_CONCURRENT_REQUESTS`
+//! * `BED_READER_MAX_CHUNK_BYTES`
+//!
+//! Likewise, these give the defaults of
+//! [`ReadOptionsBuilder::max_concurrent_requests`](struct.ReadOptionsBuilder.html#method.max_concurrent_requests)
+//! and [`ReadOptionsBuilder::max_chunk_bytes`](struct.ReadOptionsBuilder.html#method.max_chunk_bytes).
+//! See [`GlobalConfig`](struct.GlobalConfig.html) for the order of priority and for overriding
+//! environment variables programmatically.
+//!
 //! * `BED_READER_DATA_DIR`
 //!
 //! Any requested sample file will be downloaded to this directory. If the environment variable is not set,
@@ -113,23 +134,87 @@
 
 mod python_module;
 mod tests;
+pub use aggregate::Agg;
 use anyinput::anyinput;
+#[cfg(feature = "arrow")]
+pub use arrow::{ArrowLayout, ArrowOptions, ArrowOptionsBuilder, RecordBatches};
 pub use bed_cloud::{sample_bed_url, sample_url, sample_urls, BedCloud, BedCloudBuilder};
+pub use bed_dot::{bed_dot, DotOptions, DotOptionsBuilder};
+pub use bed_set::BedSet;
+pub use bed_source::BedSource;
+pub use bed_writer::BedWriter;
 use byteorder::{LittleEndian, ReadBytesExt};
+#[cfg(feature = "checksum")]
+pub use cache::BedCache;
+#[cfg(feature = "checksum")]
+pub use checksum::{ChecksumDiff, ChunkHashes, ColumnChecksums};
+pub use chromosome::ChromosomeScheme;
 pub use cloud_file::{CloudFile, CloudFileError};
+#[cfg(feature = "checksum")]
+pub use copy::{copy_fileset, CopyOptions, CopyOptionsBuilder, CopySummary};
 use core::fmt::Debug;
+pub use dedup::DuplicateSidPolicy;
 use derive_builder::Builder;
+pub use downcast::{to_i8_checked, DowncastReport, OutOfRangePolicy};
+#[cfg(feature = "parallel")]
 use dpc_pariter::{scope, IteratorExt};
+pub use drift::DriftReport;
+pub use duplicate_iid::DuplicateIidPolicy;
+pub use features::{FeatureEncoding, FeatureMissing, FeatureOptions, FeatureOptionsBuilder};
 use fetch_data::FetchData;
+pub use field_mode::FieldMode;
 use futures_util::StreamExt;
+pub use global_config::GlobalConfig;
+pub use grm::{GrmOptions, GrmOptionsBuilder};
+pub use gwas_dataset::GwasDataset;
+pub use id_policy::IdPolicy;
+pub use iid_where::FamRow;
+use iid_where::IidWhere;
+pub use import_sorter::ImportSorter;
+pub use interned::InternedStrings;
+use lazy_bim::LazyBim;
+#[cfg(feature = "mmap")]
+use memmap2::Mmap;
+pub use metadata_array::MetadataArray;
+pub use metadata_diff::{DiffExample, FieldDiff, MetadataDiff};
+use metadata_source::FAM_FIELDS;
+pub use metadata_source::{FileMetadataSource, MetadataSource};
+use missing_if::MissingIf;
 use nd::ShapeBuilder;
 use ndarray as nd;
 use num_traits::{abs, Float, FromPrimitive, Signed, ToPrimitive};
-use rayon::iter::{IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator};
-use rayon::{iter::ParallelBridge, ThreadPoolBuildError};
+pub use operation_log::OperationLog;
+pub use oxford_gen::FromGenProbabilities;
+pub use packed::PackedLayout;
+pub use panel::PanelReader;
+use parallel::prelude::*;
+use parallel::{create_pool, par_azip};
+pub use pipeline::{BlockStage, Pipeline};
+pub use pool::BedPool;
+use progress::{Progress, PROGRESS_CHUNK_VALUES};
+#[cfg(feature = "parallel")]
+use rayon::ThreadPoolBuildError;
+pub use read_cost::ReadCost;
+pub use read_options_snapshot::{IndexSnapshot, ReadOptionsSnapshot};
+pub use read_strategy::ReadStrategy;
+pub use reencode::reencode_allele_orientation;
+pub use region::{Region, RegionSet};
+pub use report::ReportFormat;
+pub use shared_index::SharedIndex;
+pub use sid_annotation::SidAnnotation;
+pub use sid_chunks::SidChunks;
+pub use sid_filter::SidFilter;
+use sid_filter::{filter_by_mask, SidFilterStats};
+pub use sidecar::{SidecarFormat, SidecarWriter};
+#[cfg(feature = "checksum")]
+pub use sketch::{match_samples, SampleMatch, SampleSketches};
+pub use snp_stats::SnpStats;
+pub use standardize::{standardize, standardize_with_stats};
+#[cfg(feature = "beta")]
 use statrs::distribution::{Beta, Continuous};
+pub use stats_options::{MissingPolicy, StatsOptions};
 use std::cmp::Ordering;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::{self};
 use std::io::Read;
 use std::io::Seek;
@@ -138,22 +223,114 @@ use std::io::Write;
 use std::num::{ParseFloatError, ParseIntError};
 use std::ops::AddAssign;
 use std::ops::{Bound, Range, RangeBounds, RangeFrom, RangeInclusive, RangeTo, RangeToInclusive};
-use std::rc::Rc;
 use std::str::Utf8Error;
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
 use std::{
-    env,
     fs::File,
     io::{BufRead, BufReader, BufWriter},
     ops::RangeFull,
     path::{Path, PathBuf},
 };
 use thiserror::Error;
+pub use typed_index::{IidIndex, SidIndex};
+pub use watch::StalePolicy;
+use watch::Watch;
+pub use write_summary::WriteSummary;
+mod aggregate;
+mod append;
+#[cfg(feature = "arrow")]
+mod arrow;
+#[cfg(feature = "gzip")]
+mod beagle;
 mod bed_cloud;
-
-const BED_FILE_MAGIC1: u8 = 0x6C; // 0b01101100 or 'l' (lowercase 'L')
-const BED_FILE_MAGIC2: u8 = 0x1B; // 0b00011011 or <esc>
-const CB_HEADER_U64: u64 = 3;
-const CB_HEADER_USIZE: usize = 3;
+mod bed_dot;
+mod bed_set;
+mod bed_source;
+mod bed_writer;
+mod bgen;
+mod cache;
+#[cfg(feature = "checksum")]
+mod checksum;
+mod chromosome;
+mod cloud_write;
+mod concat;
+#[cfg(feature = "checksum")]
+mod copy;
+mod dedup;
+mod downcast;
+mod drift;
+mod duplicate_iid;
+mod extract;
+mod features;
+pub mod ffi;
+mod field_mode;
+#[cfg(feature = "rand")]
+pub mod folds;
+pub mod format;
+mod frequency_spectrum;
+mod genotype_calls;
+mod global_config;
+mod grm;
+mod gwas_dataset;
+mod id_policy;
+mod iid_where;
+mod import_sorter;
+mod individual_major;
+mod interned;
+mod lazy_bim;
+mod ld;
+mod metadata_array;
+mod metadata_diff;
+mod metadata_filter;
+mod metadata_source;
+mod missing_if;
+#[cfg(feature = "mmap")]
+mod mmap;
+mod operation_log;
+mod oxford_gen;
+mod packed;
+mod panel;
+mod parallel;
+#[cfg(feature = "rand")]
+pub mod pca;
+#[cfg(feature = "rand")]
+pub mod permute;
+mod pipeline;
+mod plink2;
+mod pool;
+mod progress;
+pub mod qc;
+mod read_cost;
+mod read_options_snapshot;
+mod read_strategy;
+mod reencode;
+mod region;
+mod report;
+mod shard;
+mod shared_index;
+mod sid_annotation;
+mod sid_chunks;
+mod sid_counts;
+mod sid_filter;
+mod sidecar;
+#[cfg(feature = "checksum")]
+mod sketch;
+mod snp_stats;
+mod standardize;
+mod stats_options;
+mod tolerance;
+mod typed_index;
+mod u8_read;
+mod validate;
+mod vcf;
+mod watch;
+mod write_summary;
+
+const BED_FILE_MAGIC1: u8 = format::MAGIC[0];
+const BED_FILE_MAGIC2: u8 = format::MAGIC[1];
+const CB_HEADER_U64: u64 = format::HEADER_LEN;
+const CB_HEADER_USIZE: usize = format::HEADER_LEN as usize;
 
 // About ndarray
 //  https://docs.rs/ndarray/0.14.0/ndarray/parallel/index.html
@@ -177,6 +354,7 @@ pub enum BedErrorPlus {
     IOError(#[from] std::io::Error),
 
     #[allow(missing_docs)]
+    #[cfg(feature = "parallel")]
     #[error(transparent)]
     ThreadPoolError(#[from] ThreadPoolBuildError),
 
@@ -195,6 +373,16 @@ pub enum BedErrorPlus {
     #[allow(missing_docs)]
     #[error(transparent)]
     Utf8Error(#[from] Utf8Error),
+
+    #[cfg(feature = "arrow")]
+    #[allow(missing_docs)]
+    #[error(transparent)]
+    ArrowError(#[from] arrow_schema::ArrowError),
+
+    #[cfg(feature = "parquet")]
+    #[allow(missing_docs)]
+    #[error(transparent)]
+    ParquetError(#[from] parquet::errors::ParquetError),
 }
 // https://docs.rs/thiserror/1.0.23/thiserror/
 
@@ -202,18 +390,34 @@ pub enum BedErrorPlus {
 #[derive(Error, Debug, Clone)]
 pub enum BedError {
     #[allow(missing_docs)]
-    #[error("Ill-formed BED file. BED file header is incorrect or length is wrong. '{0}'")]
-    IllFormed(String),
+    #[error(
+        "Ill-formed BED file. BED file header is incorrect or length is wrong. '{}'",
+        .0.display()
+    )]
+    IllFormed(PathBuf),
+
+    #[allow(missing_docs)]
+    #[error(
+        "Ill-formed BED file. BED file header is incorrect. Expected mode to be 0 or 1. '{}'",
+        .0.display()
+    )]
+    BadMode(PathBuf),
 
     #[allow(missing_docs)]
     #[error(
-        "Ill-formed BED file. BED file header is incorrect. Expected mode to be 0 or 1. '{0}'"
+        "'{}' is {}, not a PLINK .bed file. {}",
+        .1.display(),
+        .0,
+        .0.suggestion().unwrap_or_default()
     )]
-    BadMode(String),
+    UnsupportedFormat(format::FileFormat, PathBuf),
 
     #[allow(missing_docs)]
-    #[error("Attempt to write illegal value to BED file. Only 0,1,2,missing allowed. '{0}'")]
-    BadValue(String),
+    #[error(
+        "Attempt to write illegal value to BED file. Only 0,1,2,missing allowed. '{}'",
+        .0.display()
+    )]
+    BadValue(PathBuf),
 
     #[allow(missing_docs)]
     #[error("Multithreading resulted in panic(s)")]
@@ -259,6 +463,10 @@ pub enum BedError {
     #[error("Cannot use skipped metadata '{0}'")]
     CannotUseSkippedMetadata(String),
 
+    #[allow(missing_docs)]
+    #[error("Metadata field '{0}' is not read with FieldMode::Raw")]
+    FieldNotRaw(String),
+
     #[allow(missing_docs)]
     #[error("Index starts at {0} but ends at {1}")]
     StartGreaterThanEnd(usize, usize),
@@ -284,8 +492,8 @@ pub enum BedError {
     NdSliceInfoNot1D,
 
     #[allow(missing_docs)]
-    #[error("Expect {0} fields but find only {1} in '{2}'")]
-    MetadataFieldCount(usize, usize, String),
+    #[error("Expect {0} fields but find only {1} in '{}'", .2.display())]
+    MetadataFieldCount(usize, usize, PathBuf),
 
     #[allow(missing_docs)]
     #[error("{0}_count values of {1} and {2} are inconsistent")]
@@ -338,6 +546,257 @@ pub enum BedError {
     #[allow(missing_docs)]
     #[error("Sample fetch error: {0}")]
     SampleFetch(String),
+
+    #[allow(missing_docs)]
+    #[error("Cannot write report: {0}")]
+    ReportWrite(String),
+
+    #[allow(missing_docs)]
+    #[error("This operation requires a SNP-major (mode 1) BED file. '{}'", .0.display())]
+    NotSnpMajor(PathBuf),
+
+    #[allow(missing_docs)]
+    #[error("No scores attached with name '{0}'")]
+    UnknownScore(String),
+
+    #[allow(missing_docs)]
+    #[error("The {0} values given differ from those in the .bim file '{}'", .1.display())]
+    BimMismatch(String, PathBuf),
+
+    #[allow(missing_docs)]
+    #[error("Duplicate SNP id (sid) '{0}'")]
+    DuplicateSid(String),
+
+    #[allow(missing_docs)]
+    #[error("DuplicateSidPolicy::KeepFirst can't be used when .bim fields are set from iterators")]
+    KeepFirstWithIter,
+
+    #[allow(missing_docs)]
+    #[error("The number of rows per batch must be at least 1")]
+    ZeroBatchRows,
+
+    #[allow(missing_docs)]
+    #[error("No SNP has id (sid) '{0}'")]
+    UnknownSid(String),
+
+    #[allow(missing_docs)]
+    #[error("A record was already given for SNP '{0}'")]
+    SidAlreadyRecorded(String),
+
+    #[allow(missing_docs)]
+    #[error("Tolerance must be at least 0.0 and less than 0.5, not {0}")]
+    BadTolerance(f64),
+
+    #[allow(missing_docs)]
+    #[error("Individual with fid '{0}' and iid '{1}' appears more than once in the .fam file")]
+    DuplicateIid(String, String),
+
+    #[allow(missing_docs)]
+    #[error("Expect SNP annotation header fields like 'name:string' or 'name:float', not '{0}' in '{}'", .1.display())]
+    BadSidAnnotationHeader(String, PathBuf),
+
+    #[allow(missing_docs)]
+    #[error("Value {0} at individual index {1}, SNP index {2} does not round to 0, 1, or 2")]
+    OutOfRangeValue(f64, usize, usize),
+
+    #[allow(missing_docs)]
+    #[error("Test fraction must be between 0.0 and 1.0, not {0}")]
+    BadTestFraction(f64),
+
+    #[allow(missing_docs)]
+    #[error("The number of SNPs per chunk must be at least 1")]
+    ZeroChunkSids,
+
+    #[allow(missing_docs)]
+    #[error("Error rate must be at least 0.0 and less than 1.0, not {0}")]
+    BadErrorRate(f64),
+
+    #[allow(missing_docs)]
+    #[error("Ill-formed region on line {0} of '{1}'")]
+    BadRegion(usize, PathBuf),
+
+    #[allow(missing_docs)]
+    #[error("The {0} '{1}' is empty or has characters other than printable ASCII without space, #, or quotes")]
+    UnsafeId(String, String),
+
+    #[allow(missing_docs)]
+    #[error("Cannot split {1} SNPs into {0} shards")]
+    BadShardCount(usize, usize),
+
+    #[allow(missing_docs)]
+    #[error("Shard path template '{0}' must contain '{{}}'")]
+    BadShardTemplate(String),
+
+    #[allow(missing_docs)]
+    #[error("Missing genotype at individual index {0}, SNP index {1}")]
+    MissingFeatureGenotype(isize, isize),
+
+    #[allow(missing_docs)]
+    #[error("File '{0}' changed since it was opened")]
+    StaleFile(PathBuf),
+
+    #[allow(missing_docs)]
+    #[error("Found {1} {0} values, more than the maximum of {2}")]
+    TooManyForMaxDims(String, usize, usize),
+
+    #[allow(missing_docs)]
+    #[error("Duplicate sids can't be removed while writing SNP blocks with BedWriter")]
+    StreamingDedupSids,
+
+    #[allow(missing_docs)]
+    #[error("A BedSet needs at least one .bed file")]
+    EmptyBedSet,
+
+    #[allow(missing_docs)]
+    #[error("The .fam metadata of '{1}' doesn't match the .fam metadata of '{0}'")]
+    BedSetFamMismatch(PathBuf, PathBuf),
+
+    #[allow(missing_docs)]
+    #[error("The '{0}' read option isn't supported when reading a BedSet")]
+    UnsupportedBedSetOption(String),
+
+    #[allow(missing_docs)]
+    #[error("Ill-formed BGEN file '{}': {0}", .1.display())]
+    IllFormedBgen(String, PathBuf),
+
+    #[allow(missing_docs)]
+    #[error("Unsupported BGEN file '{}': {0}", .1.display())]
+    UnsupportedBgen(String, PathBuf),
+
+    #[allow(missing_docs)]
+    #[error("Expect a '{0}' column in the header of '{}'", .1.display())]
+    MissingPlink2Column(String, PathBuf),
+
+    #[allow(missing_docs)]
+    #[error("Ill-formed VCF line {0} of '{}'", .1.display())]
+    IllFormedVcf(usize, PathBuf),
+
+    #[allow(missing_docs)]
+    #[error(
+        "Attempt to write illegal value {2} at iid index {0}, sid index {1} to BED file. Only 0,1,2,missing allowed. '{}'",
+        .3.display()
+    )]
+    BadValueAt(usize, usize, String, PathBuf),
+
+    #[allow(missing_docs)]
+    #[error("Cloud URL '{}' is not valid UTF-8", .0.display())]
+    NonUtf8Url(PathBuf),
+
+    #[allow(missing_docs)]
+    #[error("Uploaded '{0}' should have {1} bytes, but the cloud has {2}")]
+    CloudUploadSizeMismatch(String, usize, usize),
+
+    #[allow(missing_docs)]
+    #[error("Metadata field '{0}' was given values of another type")]
+    MetadataFieldType(String),
+
+    #[allow(missing_docs)]
+    #[error("Ill-formed region '{0}', expected 'chromosome:start-end' with 1-based positions, start at least 1, and end at least start")]
+    BadRegionString(String),
+
+    #[allow(missing_docs)]
+    #[error("Strings are limited to u32::MAX distinct values, but more than {0} were given")]
+    TooManyDistinctStrings(usize),
+
+    #[allow(missing_docs)]
+    #[error("The number of folds must be at least 2 and at most {1}, the number of individuals (or families), not {0}")]
+    BadFoldCount(usize, usize),
+
+    #[allow(missing_docs)]
+    #[error("The drift window must be at least 1 and at most {1}, the number of SNPs, not {0}")]
+    BadDriftWindow(usize, usize),
+
+    #[allow(missing_docs)]
+    #[error("The C argument '{0}' is a null pointer")]
+    NullPointer(String),
+
+    #[allow(missing_docs)]
+    #[error("The buffer must hold {0} values, but holds {1}")]
+    BufferLength(usize, usize),
+
+    #[allow(missing_docs)]
+    #[error("A call through the C interface panicked")]
+    FfiPanic,
+
+    #[allow(missing_docs)]
+    #[error("Shared index '{0}' is missing, unreadable, or out of date; rewrite it with Bed::write_shared_index")]
+    StaleSharedIndex(PathBuf),
+
+    #[allow(missing_docs)]
+    #[error("Cannot extract into '{0}' because it is the input .bed file")]
+    ExtractOverwritesInput(PathBuf),
+
+    #[allow(missing_docs)]
+    #[error("Concatenating needs at least one .bed file")]
+    EmptyConcat,
+
+    #[allow(missing_docs)]
+    #[error("Cannot concatenate into '{0}' because it is one of the input .bed files")]
+    ConcatOverwritesInput(PathBuf),
+
+    #[allow(missing_docs)]
+    #[error("The .bim metadata of '{1}' doesn't match the .bim metadata of '{0}'")]
+    BimMetadataMismatch(PathBuf, PathBuf),
+
+    #[allow(missing_docs)]
+    #[error("Cannot copy the fileset of '{0}' onto itself")]
+    CopyOntoSource(PathBuf),
+
+    #[allow(missing_docs)]
+    #[error("The copy '{0}' doesn't match its original's checksum")]
+    CopyVerifyFailed(PathBuf),
+
+    #[allow(missing_docs)]
+    #[error("Throughput and seek time must be finite, with throughput positive, not {0}")]
+    BadThroughput(f64),
+
+    #[allow(missing_docs)]
+    #[error("Cannot write a pipeline's output to '{0}' because it is the input .bed file")]
+    PipelineOverwritesInput(PathBuf),
+
+    #[allow(missing_docs)]
+    #[error("The number of individuals per GRM band must be at least 1")]
+    ZeroBandIids,
+
+    #[allow(missing_docs)]
+    #[error("Cannot replay read options recorded for '{0}' as '{1}'")]
+    SnapshotDtypeMismatch(String, String),
+
+    #[allow(missing_docs)]
+    #[error("Cannot replay read options that recorded an iid_where predicate")]
+    SnapshotHasIidWhere,
+
+    #[allow(missing_docs)]
+    #[error("The matrix to multiply has {0} rows, but {1} are needed")]
+    DotShapeMismatch(usize, usize),
+
+    #[allow(missing_docs)]
+    #[error("The number of sketch bins must be at least 1")]
+    ZeroSketchBins,
+
+    #[allow(missing_docs)]
+    #[error("Cannot match sketches of {0} bins with sketches of {1} bins")]
+    SketchBinsMismatch(usize, usize),
+
+    #[allow(missing_docs)]
+    #[error("The number of principal components must be at least 1 and at most {1}, the smaller of the numbers of individuals and SNPs, not {0}")]
+    BadPcaComponentCount(usize, usize),
+
+    #[allow(missing_docs)]
+    #[error("SNP (variant) filters are only supported when reading a local Bed")]
+    UnsupportedSidFilter,
+
+    #[allow(missing_docs)]
+    #[error("SNP {0} is selected more than once, but flipped in only some of its selections")]
+    InconsistentFlip(usize),
+
+    #[allow(missing_docs)]
+    #[error("Reading gzipped file '{}' needs the 'gzip' feature", .0.display())]
+    GzipFeatureNeeded(PathBuf),
+
+    #[allow(missing_docs)]
+    #[error("A buffer of {0} by {1} values is too large")]
+    BufferTooLarge(usize, usize),
 }
 
 // Trait alias
@@ -352,16 +811,6 @@ impl<T> BedVal for T where
 {
 }
 
-fn create_pool(num_threads: usize) -> Result<rayon::ThreadPool, Box<BedErrorPlus>> {
-    match rayon::ThreadPoolBuilder::new()
-        .num_threads(num_threads)
-        .build()
-    {
-        Err(e) => Err(Box::new(e.into())),
-        Ok(pool) => Ok(pool),
-    }
-}
-
 #[allow(clippy::too_many_arguments)]
 #[anyinput]
 fn read_no_alloc<TVal: BedVal>(
@@ -369,12 +818,14 @@ fn read_no_alloc<TVal: BedVal>(
     iid_count: usize,
     sid_count: usize,
     is_a1_counted: bool,
+    count_a1_mask: Option<&nd::Array1<bool>>,
     iid_index: &[isize],
     sid_index: &[isize],
     missing_value: TVal,
     num_threads: usize,
     val: &mut nd::ArrayViewMut2<'_, TVal>, //mutable slices additionally allow to modify elements. But slices cannot grow - they are just a view into some vector.
 ) -> Result<(), Box<BedErrorPlus>> {
+    let two_bits_to_value = TwoBitsToValue::new(is_a1_counted, count_a1_mask, missing_value);
     create_pool(num_threads)?.install(|| {
         let (buf_reader, bytes_vector) = open_and_check(path)?;
 
@@ -387,10 +838,9 @@ fn read_no_alloc<TVal: BedVal>(
                     path,
                     sid_count,
                     iid_count,
-                    is_a1_counted,
+                    two_bits_to_value.individual_major(),
                     sid_index,
                     iid_index,
-                    missing_value,
                     &mut val_t,
                 )
             }
@@ -399,23 +849,21 @@ fn read_no_alloc<TVal: BedVal>(
                 path,
                 iid_count,
                 sid_count,
-                is_a1_counted,
+                two_bits_to_value,
                 iid_index,
                 sid_index,
-                missing_value,
                 val,
             ),
-            _ => Err(Box::new(BedError::BadMode(path_ref_to_string(path)).into())),
+            _ => Err(Box::new(
+                format::FileFormat::from_header(&bytes_vector)
+                    .to_error(path, BedError::BadMode(path.to_path_buf()))
+                    .into(),
+            )),
         }
     })?;
     Ok(())
 }
 
-#[anyinput]
-fn path_ref_to_string(path: AnyPath) -> String {
-    PathBuf::from(path).display().to_string()
-}
-
 impl From<BedError> for Box<BedErrorPlus> {
     fn from(err: BedError) -> Self {
         Box::new(BedErrorPlus::BedError(err))
@@ -426,6 +874,7 @@ impl From<std::io::Error> for Box<BedErrorPlus> {
         Box::new(BedErrorPlus::IOError(err))
     }
 }
+#[cfg(feature = "parallel")]
 impl From<ThreadPoolBuildError> for Box<BedErrorPlus> {
     fn from(err: ThreadPoolBuildError) -> Self {
         Box::new(BedErrorPlus::ThreadPoolError(err))
@@ -461,6 +910,20 @@ impl From<Utf8Error> for Box<BedErrorPlus> {
     }
 }
 
+#[cfg(feature = "arrow")]
+impl From<arrow_schema::ArrowError> for Box<BedErrorPlus> {
+    fn from(err: arrow_schema::ArrowError) -> Self {
+        Box::new(BedErrorPlus::ArrowError(err))
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl From<parquet::errors::ParquetError> for Box<BedErrorPlus> {
+    fn from(err: parquet::errors::ParquetError) -> Self {
+        Box::new(BedErrorPlus::ParquetError(err))
+    }
+}
+
 #[anyinput]
 fn open_and_check(
     path: AnyPath,
@@ -469,7 +932,8 @@ fn open_and_check(
     let mut bytes_array: [u8; CB_HEADER_USIZE] = [0; CB_HEADER_USIZE];
     buf_reader.read_exact(&mut bytes_array)?;
     if (BED_FILE_MAGIC1 != bytes_array[0]) || (BED_FILE_MAGIC2 != bytes_array[1]) {
-        Err(BedError::IllFormed(path_ref_to_string(path)))?;
+        let file_format = format::detect_format(path)?;
+        Err(file_format.to_error(path, BedError::IllFormed(path.to_path_buf())))?;
     }
     Ok((buf_reader, bytes_array))
 }
@@ -538,10 +1002,9 @@ fn internal_read_no_alloc<TVal: BedVal>(
     path: AnyPath,
     in_iid_count: usize,
     in_sid_count: usize,
-    is_a1_counted: bool,
+    two_bits_to_value: TwoBitsToValue<'_, TVal>,
     iid_index: &[isize],
     sid_index: &[isize],
-    missing_value: TVal,
     out_val: &mut nd::ArrayViewMut2<'_, TVal>, //mutable slices additionally allow to modify elements. But slices cannot grow - they are just a view into some vector.
 ) -> Result<(), Box<BedErrorPlus>> {
     // Check the file length
@@ -551,7 +1014,7 @@ fn internal_read_no_alloc<TVal: BedVal>(
     let file_len = buf_reader.get_ref().metadata()?.len();
     let file_len2 = in_iid_count_div4_u64 * (in_sid_count as u64) + CB_HEADER_U64;
     if file_len != file_len2 {
-        Err(BedError::IllFormed(path_ref_to_string(path)))?;
+        Err(BedError::IllFormed(path.to_path_buf()))?;
     }
 
     // Check and precompute for each iid_index
@@ -559,51 +1022,102 @@ fn internal_read_no_alloc<TVal: BedVal>(
         check_and_precompute_iid_index(in_iid_count, iid_index)?;
 
     // Check and compute work for each sid_index
-    let from_two_bits_to_value = set_up_two_bits_to_value(is_a1_counted, missing_value);
+    let row_two_bits_to_value = two_bits_to_value.for_rows(iid_index, in_iid_count);
     let lower_sid_count = -(in_sid_count as isize);
     let upper_sid_count: isize = (in_sid_count as isize) - 1;
-    // See https://morestina.net/blog/1432/parallel-stream-processing-with-rayon
-    // Possible optimization: We could read snp in their input order instead of their output order
-    sid_index
+    // Turn signed sid_index into unsigned sid_index (or error)
+    let in_sid_list = sid_index
         .iter()
         .map(|in_sid_i_signed| {
-            // Turn signed sid_index into unsigned sid_index (or error)
-            let in_sid_i = if (0..=upper_sid_count).contains(in_sid_i_signed) {
-                *in_sid_i_signed as u64
+            if (0..=upper_sid_count).contains(in_sid_i_signed) {
+                Ok(*in_sid_i_signed as u64)
             } else if (lower_sid_count..=-1).contains(in_sid_i_signed) {
-                (in_sid_count - ((-in_sid_i_signed) as usize)) as u64
+                Ok((in_sid_count - ((-in_sid_i_signed) as usize)) as u64)
             } else {
-                Err(BedError::SidIndexTooBig(*in_sid_i_signed))?
-            };
-
-            // Read the iid info for one snp from the disk
-            let mut bytes_vector: Vec<u8> = vec![0; i_div_4_len as usize];
-            let pos: u64 = in_sid_i * in_iid_count_div4_u64 + i_div_4_start + CB_HEADER_U64; // "as" and math is safe because of early checks
-            buf_reader.seek(SeekFrom::Start(pos))?;
-            buf_reader.read_exact(&mut bytes_vector)?;
-            Ok::<_, Box<BedErrorPlus>>(bytes_vector)
+                Err(BedError::SidIndexTooBig(*in_sid_i_signed))
+            }
+        })
+        .collect::<Result<Vec<u64>, BedError>>()?;
+    let i_div_4_len_usize = i_div_4_len as usize;
+    let column_len = in_iid_count_div4_u64 as usize;
+    // See https://morestina.net/blog/1432/parallel-stream-processing-with-rayon
+    // Possible optimization: We could read snp in their input order instead of their output order
+    coalesce_sid_runs(&in_sid_list, in_iid_count_div4_u64, i_div_4_len)
+        .into_iter()
+        .flat_map(|(first, count)| {
+            // Read the iid info for a run of adjacent snps from the disk with one read
+            let pos: u64 =
+                in_sid_list[first] * in_iid_count_div4_u64 + i_div_4_start + CB_HEADER_U64; // "as" and math is safe because of early checks
+            let mut run_bytes: Vec<u8> = vec![0; (count - 1) * column_len + i_div_4_len_usize];
+            let result = buf_reader
+                .seek(SeekFrom::Start(pos))
+                .and_then(|_| buf_reader.read_exact(&mut run_bytes));
+            // Split the run into the bytes of each snp
+            match result {
+                Ok(()) => (0..count)
+                    .map(|run_i| {
+                        let start = run_i * column_len;
+                        Ok(run_bytes[start..start + i_div_4_len_usize].to_vec())
+                    })
+                    .collect::<Vec<_>>(),
+                Err(e) => vec![Err(Box::<BedErrorPlus>::from(e))],
+            }
         })
         // Zip in the column of the output array
         .zip(out_val.axis_iter_mut(nd::Axis(1)))
+        .zip(&in_sid_list)
         // In parallel, decompress the iid info and put it in its column
         .par_bridge() // This seems faster that parallel zip
-        .try_for_each(|(bytes_vector_result, mut col)| match bytes_vector_result {
-            Err(e) => Err(e),
-            Ok(bytes_vector) => {
-                for out_iid_i in 0..iid_index.len() {
-                    let i_div_4_less_start = i_div_4_less_start_array[out_iid_i];
-                    let i_mod_4_times_2 = i_mod_4_times_2_array[out_iid_i];
-                    let genotype_byte: u8 =
-                        (bytes_vector[i_div_4_less_start] >> i_mod_4_times_2) & 0x03;
-                    col[out_iid_i] = from_two_bits_to_value[genotype_byte as usize];
+        .try_for_each(
+            |((bytes_vector_result, mut col), &in_sid_i)| match bytes_vector_result {
+                Err(e) => Err(e),
+                Ok(bytes_vector) => {
+                    // "as" is safe because in_sid_i is less than in_sid_count
+                    let from_two_bits_to_value = two_bits_to_value.for_column(in_sid_i as usize);
+                    for out_iid_i in 0..iid_index.len() {
+                        let i_div_4_less_start = i_div_4_less_start_array[out_iid_i];
+                        let i_mod_4_times_2 = i_mod_4_times_2_array[out_iid_i];
+                        let genotype_byte: u8 =
+                            (bytes_vector[i_div_4_less_start] >> i_mod_4_times_2) & 0x03;
+                        col[out_iid_i] = row_two_bits_to_value
+                            .as_ref()
+                            .map_or(&from_two_bits_to_value, |tables| &tables[out_iid_i])
+                            [genotype_byte as usize];
+                    }
+                    Ok(())
                 }
-                Ok(())
-            }
-        })?;
+            },
+        )?;
 
     Ok(())
 }
 
+// The most bytes between the needed bytes of adjacent SNPs for them to be read with one read.
+const COALESCE_MAX_GAP: u64 = 4096;
+// The most bytes read at once for a run of adjacent SNPs.
+const COALESCE_MAX_BYTES: u64 = 8 * 1024 * 1024;
+
+// Split the SNPs to read, in output order, into runs that each can be read with one read,
+// returned as (first position, count). A run is SNPs adjacent in the file and in increasing
+// order, whose needed bytes are at most COALESCE_MAX_GAP apart.
+fn coalesce_sid_runs(in_sid_list: &[u64], column_len: u64, read_len: u64) -> Vec<(usize, usize)> {
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    let can_join = column_len - read_len <= COALESCE_MAX_GAP;
+    for (sid_i, &in_sid_i) in in_sid_list.iter().enumerate() {
+        if let Some((first, count)) = runs.last_mut() {
+            if can_join
+                && in_sid_i == in_sid_list[*first] + *count as u64
+                && (*count as u64) * column_len + read_len <= COALESCE_MAX_BYTES
+            {
+                *count += 1;
+                continue;
+            }
+        }
+        runs.push((sid_i, 1));
+    }
+    runs
+}
+
 type Array1Usize = nd::ArrayBase<nd::OwnedRepr<usize>, nd::Dim<[usize; 1]>>;
 type Array1U8 = nd::ArrayBase<nd::OwnedRepr<u8>, nd::Dim<[usize; 1]>>;
 
@@ -618,7 +1132,7 @@ fn check_and_precompute_iid_index(
     let mut i_div_4_less_start_array = nd::Array1::<usize>::zeros(iid_index.len());
     let mut i_mod_4_times_2_array = nd::Array1::<u8>::zeros(iid_index.len());
     let mut result_list: Vec<Result<(), BedError>> = vec![Ok(()); iid_index.len()];
-    nd::par_azip!((in_iid_i_signed in iid_index,
+    par_azip!((in_iid_i_signed in iid_index,
         i_div_4_less_start in &mut i_div_4_less_start_array,
         i_mod_4_times_2 in &mut i_mod_4_times_2_array,
         result in &mut result_list
@@ -666,6 +1180,22 @@ fn check_and_precompute_iid_index(
     ))
 }
 
+// A count_a1_mask must have one entry per SNP in the file.
+fn check_count_a1_mask(
+    count_a1_mask: Option<&nd::Array1<bool>>,
+    sid_count: usize,
+) -> Result<(), Box<BedErrorPlus>> {
+    if let Some(count_a1_mask) = count_a1_mask {
+        if count_a1_mask.len() != sid_count {
+            Err(BedError::BoolArrayVectorWrongLength(
+                sid_count,
+                count_a1_mask.len(),
+            ))?;
+        }
+    }
+    Ok(())
+}
+
 fn set_up_two_bits_to_value<TVal: From<i8>>(count_a1: bool, missing_value: TVal) -> [TVal; 4] {
     let homozygous_primary_allele = TVal::from(0); // Major Allele
     let heterozygous_allele = TVal::from(1);
@@ -688,45 +1218,194 @@ fn set_up_two_bits_to_value<TVal: From<i8>>(count_a1: bool, missing_value: TVal)
     }
 }
 
+// The look-up table from two bits to value for each SNP in the file. A count_a1_mask, if
+// any, says per SNP whether allele 1 is counted, overriding is_a1_counted. Because each SNP
+// is decoded with its own table, missing values stay missing, whatever the missing value.
+#[derive(Clone, Copy)]
+struct TwoBitsToValue<'a, TVal> {
+    // Indexed by whether allele 1 is counted
+    tables: [[TVal; 4]; 2],
+    is_a1_counted: bool,
+    count_a1_mask: Option<&'a nd::Array1<bool>>,
+    // In individual-major (mode 0) files, the SNPs run along the rows, not the columns.
+    is_individual_major: bool,
+}
+
+impl<'a, TVal: BedVal> TwoBitsToValue<'a, TVal> {
+    fn new(
+        is_a1_counted: bool,
+        count_a1_mask: Option<&'a nd::Array1<bool>>,
+        missing_value: TVal,
+    ) -> Self {
+        TwoBitsToValue {
+            tables: [
+                set_up_two_bits_to_value(false, missing_value),
+                set_up_two_bits_to_value(true, missing_value),
+            ],
+            is_a1_counted,
+            count_a1_mask,
+            is_individual_major: false,
+        }
+    }
+
+    // The same look-up, for a file whose columns are individuals and rows are SNPs.
+    fn individual_major(self) -> Self {
+        TwoBitsToValue {
+            is_individual_major: true,
+            ..self
+        }
+    }
+
+    // The table for a SNP in the file.
+    fn for_sid(&self, in_sid_i: usize) -> [TVal; 4] {
+        let count_a1 = self
+            .count_a1_mask
+            .map_or(self.is_a1_counted, |count_a1_mask| count_a1_mask[in_sid_i]);
+        self.tables[usize::from(count_a1)]
+    }
+
+    // The table for a column of the file.
+    fn for_column(&self, in_column_i: usize) -> [TVal; 4] {
+        if self.is_individual_major {
+            self.tables[usize::from(self.is_a1_counted)]
+        } else {
+            self.for_sid(in_column_i)
+        }
+    }
+
+    // If the table depends on the row, rather than the column, the table for each row read.
+    // The row index must already be checked against row_count.
+    fn for_rows(&self, row_index: &[isize], row_count: usize) -> Option<Vec<[TVal; 4]>> {
+        if !self.is_individual_major || self.count_a1_mask.is_none() {
+            return None;
+        }
+        Some(
+            row_index
+                .iter()
+                .map(|&in_row_i| {
+                    // "as" is safe because the index was already checked against row_count
+                    if in_row_i >= 0 {
+                        self.for_sid(in_row_i as usize)
+                    } else {
+                        self.for_sid(row_count - ((-in_row_i) as usize))
+                    }
+                })
+                .collect(),
+        )
+    }
+}
+
 // Thanks to Dawid for his dpc-pariter library that makes this function scale.
 // https://dpc.pw/adding-parallelism-to-your-rust-iterators
 #[anyinput]
+#[allow(clippy::too_many_arguments)]
 fn write_val<S, TVal>(
     path: AnyPath,
     val: &nd::ArrayBase<S, nd::Ix2>,
     is_a1_counted: bool,
     missing: TVal,
+    missing_if: Option<&MissingIf>,
+    tolerance: Option<f64>,
+    validate: bool,
     num_threads: usize,
-) -> Result<(), Box<BedErrorPlus>>
+    progress: Option<&Progress>,
+) -> Result<WriteSummary, Box<BedErrorPlus>>
 where
     S: nd::Data<Elem = TVal>,
-    TVal: BedVal,
+    TVal: BedVal + Into<f64>,
 {
+    let start = Instant::now();
     let (iid_count, sid_count) = val.dim();
 
     // 4 genotypes per byte so round up
     let iid_count_div4_u64 = try_div_4(iid_count, sid_count)?;
 
+    // Report, after each SNP, the SNPs written so far
+    if let Some(progress) = progress {
+        progress.report(0, sid_count);
+    }
+    let report = progress.map(|progress| move |done| progress.report(done, sid_count));
+
     // We create and write to a file.
     // If there is an error, we will delete it.
-    if let Err(e) = write_internal(
+    let (value_counts, snapped_counts) = write_internal(
         path,
         iid_count_div4_u64,
         val,
         is_a1_counted,
         missing,
+        missing_if,
+        tolerance,
+        validate,
         num_threads,
-    ) {
+        report
+            .as_ref()
+            .map(|report| report as &(dyn Fn(usize) + Sync)),
+    )
+    .inspect_err(|_| {
         // Clean up the file
         let _ = fs::remove_file(path);
-        Err(e)
-    } else {
-        Ok(())
+    })?;
+    let bytes_written = CB_HEADER_U64 + iid_count_div4_u64 * sid_count as u64;
+    Ok(WriteSummary::new(
+        value_counts,
+        snapped_counts,
+        bytes_written,
+        start.elapsed(),
+    ))
+}
+
+// Write the .fam, .bim, and any sid annotations of a .bed file just written.
+// On error, the file that failed is removed.
+fn write_metadata_files<TVal: BedVal>(
+    write_options: &WriteOptions<TVal>,
+    metadata: &Metadata,
+) -> Result<(), Box<BedErrorPlus>> {
+    if !write_options.skip_fam() {
+        if let Err(e) = metadata.write_fam(write_options.fam_path()) {
+            // Clean up the file
+            let _ = fs::remove_file(&write_options.fam_path);
+            Err(e)?;
+        }
+    }
+
+    if !write_options.skip_bim() {
+        let result = if write_options.lazy_bim.is_empty() {
+            metadata.write_bim(write_options.bim_path())
+        } else {
+            write_options.lazy_bim.write_bim(
+                write_options.bim_path(),
+                write_options.dedup_sids,
+                write_options.id_policy,
+            )
+        };
+        if let Err(e) = result {
+            // Clean up the file
+            let _ = fs::remove_file(&write_options.bim_path);
+            Err(e)?;
+        }
+    }
+
+    if !write_options.metadata.sid_annotations.is_empty() {
+        let sid_annotations_path = write_options
+            .path
+            .with_extension(sid_annotation::SID_ANNOTATIONS_EXTENSION);
+        if let Err(e) = write_options
+            .metadata
+            .write_sid_annotations(&sid_annotations_path)
+        {
+            // Clean up the file
+            let _ = fs::remove_file(&sid_annotations_path);
+            Err(e)?;
+        }
     }
+
+    Ok(())
 }
 
 // https://www.reddit.com/r/rust/comments/mo4s8e/difference_between_reference_and_view_in_ndarray/
 #[anyinput]
+#[allow(clippy::too_many_arguments)]
 fn write_internal<S, TVal>(
     path: AnyPath,
     iid_count_div4_u64: u64,
@@ -734,14 +1413,61 @@ fn write_internal<S, TVal>(
     val: &nd::ArrayBase<S, nd::Ix2>,
     is_a1_counted: bool,
     missing: TVal,
+    missing_if: Option<&MissingIf>,
+    tolerance: Option<f64>,
+    validate: bool,
     num_threads: usize,
-) -> Result<(), Box<BedErrorPlus>>
+    progress: Option<&(dyn Fn(usize) + Sync)>,
+) -> Result<(nd::Array2<usize>, nd::Array1<usize>), Box<BedErrorPlus>>
 where
     S: nd::Data<Elem = TVal>,
-    TVal: BedVal,
+    TVal: BedVal + Into<f64>,
 {
     let mut writer = BufWriter::new(File::create(path)?);
-    writer.write_all(&[BED_FILE_MAGIC1, BED_FILE_MAGIC2, 0x01])?;
+    writer.write_all(&format::Mode::SnpMajor.header())?;
+    write_columns(
+        &mut writer,
+        path,
+        iid_count_div4_u64,
+        val,
+        is_a1_counted,
+        missing,
+        missing_if,
+        tolerance,
+        validate,
+        0,
+        num_threads,
+        progress,
+    )
+}
+
+// Encode each column (SNP) of val and write its bytes, in order, to writer.
+// Bad values are reported against path and, if validating, with their location,
+// counting SNPs from sid_start. If given, progress is called with the columns written so far.
+#[allow(clippy::too_many_arguments)]
+#[cfg_attr(not(feature = "parallel"), allow(unused_variables))]
+fn write_columns<W, S, TVal>(
+    writer: &mut W,
+    path: &Path,
+    iid_count_div4_u64: u64,
+    val: &nd::ArrayBase<S, nd::Ix2>,
+    is_a1_counted: bool,
+    missing: TVal,
+    missing_if: Option<&MissingIf>,
+    tolerance: Option<f64>,
+    validate: bool,
+    sid_start: usize,
+    num_threads: usize,
+    progress: Option<&(dyn Fn(usize) + Sync)>,
+) -> Result<(nd::Array2<usize>, nd::Array1<usize>), Box<BedErrorPlus>>
+where
+    W: Write,
+    S: nd::Data<Elem = TVal>,
+    TVal: BedVal + Into<f64>,
+{
+    // Per SNP, the number of 0, 1, 2, and missing values
+    let mut value_counts = nd::Array2::<usize>::zeros((val.ncols(), 4));
+    let mut snapped_counts = Vec::with_capacity(val.ncols());
 
     #[allow(clippy::eq_op)]
     let use_nan = missing != missing; // generic NAN test
@@ -752,42 +1478,83 @@ where
     let heterozygous_allele = TVal::from(1);
     let homozygous_secondary_allele = TVal::from(2); // Minor Allele
 
+    let encode_column = move |(sid_i, column): (usize, nd::ArrayView1<'_, TVal>)| {
+        // Convert each column into a bytes_vector
+        let mut bytes_vector: Vec<u8> = vec![0; iid_count_div4_u64 as usize]; // inits to 0
+        let mut snapped_count = 0usize;
+        let mut column_counts = [0usize; 4];
+        for (iid_i, &v0) in column.iter().enumerate() {
+            let is_missing_if =
+                missing_if.is_some_and(|missing_if| missing_if.is_missing(v0.into()));
+            #[allow(clippy::eq_op)]
+            let (genotype_byte, count_i) = if is_missing_if {
+                (1, 3)
+            } else if v0 == homozygous_primary_allele {
+                (zero_code, 0)
+            } else if v0 == heterozygous_allele {
+                (2, 1)
+            } else if v0 == homozygous_secondary_allele {
+                (two_code, 2)
+            //                    v0 !=v0 is generic NAN check
+            } else if (use_nan && v0 != v0) || (!use_nan && v0 == missing) {
+                (1, 3)
+            } else if let Some(genotype) =
+                tolerance.and_then(|tolerance| tolerance::snap(v0.into(), tolerance))
+            {
+                snapped_count += 1;
+                match genotype {
+                    0 => (zero_code, 0),
+                    1 => (2, 1),
+                    _ => (two_code, 2),
+                }
+            } else if validate {
+                Err(BedError::BadValueAt(
+                    iid_i,
+                    sid_start + sid_i,
+                    format!("{v0:?}"),
+                    path.to_path_buf(),
+                ))?
+            } else {
+                Err(BedError::BadValue(path.to_path_buf()))?
+            };
+            column_counts[count_i] += 1;
+            // Possible optimization: We could pre-compute the conversion, the division, the mod, and the multiply*2
+            let i_div_4 = iid_i / 4;
+            let i_mod_4 = iid_i % 4;
+            bytes_vector[i_div_4] |= genotype_byte << (i_mod_4 * 2);
+        }
+        Ok::<_, Box<BedErrorPlus>>((bytes_vector, column_counts, snapped_count))
+    };
+    let write_column = |result: Result<(Vec<u8>, [usize; 4], usize), Box<BedErrorPlus>>| {
+        // Write the bytes vector, they must be in order.
+        let (bytes_vector, column_counts, snapped_count) = result?;
+        writer.write_all(&bytes_vector)?;
+        value_counts
+            .row_mut(snapped_counts.len())
+            .assign(&nd::aview1(&column_counts));
+        snapped_counts.push(snapped_count);
+        if let Some(progress) = progress {
+            progress(snapped_counts.len());
+        }
+        Ok::<_, Box<BedErrorPlus>>(())
+    };
+
+    #[cfg(feature = "parallel")]
     scope(|scope| {
         val.axis_iter(nd::Axis(1))
-            .parallel_map_scoped(scope, {
-                move |column| {
-                    // Convert each column into a bytes_vector
-                    let mut bytes_vector: Vec<u8> = vec![0; iid_count_div4_u64 as usize]; // inits to 0
-                    for (iid_i, &v0) in column.iter().enumerate() {
-                        #[allow(clippy::eq_op)]
-                        let genotype_byte = if v0 == homozygous_primary_allele {
-                            zero_code
-                        } else if v0 == heterozygous_allele {
-                            2
-                        } else if v0 == homozygous_secondary_allele {
-                            two_code
-                        //                    v0 !=v0 is generic NAN check
-                        } else if (use_nan && v0 != v0) || (!use_nan && v0 == missing) {
-                            1
-                        } else {
-                            Err(BedError::BadValue(path_ref_to_string(path)))?
-                        };
-                        // Possible optimization: We could pre-compute the conversion, the division, the mod, and the multiply*2
-                        let i_div_4 = iid_i / 4;
-                        let i_mod_4 = iid_i % 4;
-                        bytes_vector[i_div_4] |= genotype_byte << (i_mod_4 * 2);
-                    }
-                    Ok::<_, Box<BedErrorPlus>>(bytes_vector)
-                }
-            })
+            .enumerate()
+            .parallel_map_scoped(scope, encode_column)
             .threads(num_threads)
-            .try_for_each(|bytes_vector| {
-                // Write the bytes vector, they must be in order.
-                writer.write_all(&bytes_vector?)?;
-                Ok(())
-            })
+            .try_for_each(write_column)
     })
-    .map_err(|_e| BedError::PanickedThread())?
+    .map_err(|_e| BedError::PanickedThread())??;
+    #[cfg(not(feature = "parallel"))]
+    val.axis_iter(nd::Axis(1))
+        .enumerate()
+        .map(encode_column)
+        .try_for_each(write_column)?;
+
+    Ok((value_counts, nd::Array1::from_vec(snapped_counts)))
 }
 
 #[anyinput]
@@ -798,13 +1565,105 @@ fn count_lines(path: AnyPath) -> Result<usize, Box<BedErrorPlus>> {
     Ok(count)
 }
 
-#[allow(dead_code)]
-enum Dist {
+// Bytes of a .fam or .bim file parsed by each parallel task.
+const METADATA_CHUNK_BYTES: usize = 1 << 20;
+// Bytes of a .fam or .bim file read at a time, bounding memory beyond the parsed values.
+const METADATA_BLOCK_BYTES: usize = 64 << 20;
+
+// Split bytes into chunks of about chunk_bytes, each ending at the end of a line.
+fn split_at_lines(bytes: &[u8], chunk_bytes: usize) -> Vec<&[u8]> {
+    let mut chunks = Vec::new();
+    let mut rest = bytes;
+    while rest.len() > chunk_bytes {
+        let end = rest[chunk_bytes..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(rest.len(), |newline_i| chunk_bytes + newline_i + 1);
+        let (chunk, tail) = rest.split_at(end);
+        chunks.push(chunk);
+        rest = tail;
+    }
+    if !rest.is_empty() {
+        chunks.push(rest);
+    }
+    chunks
+}
+
+// Parse whole lines of a .fam or .bim file, keeping the fields in field_vec.
+// Lines are split exactly as BufRead::lines splits them.
+#[anyinput]
+fn parse_fam_or_bim(
+    bytes: &[u8],
+    field_vec: &[usize],
+    is_split_whitespace: bool,
+    path: AnyPath,
+) -> Result<(Vec<Vec<String>>, usize), Box<BedErrorPlus>> {
+    let mut vec_of_vec = vec![vec![]; field_vec.len()];
+    let mut count = 0;
+    for line in bytes.split_inclusive(|&b| b == b'\n') {
+        let line = match line.strip_suffix(b"\n") {
+            Some(line) => line.strip_suffix(b"\r").unwrap_or(line),
+            None => line,
+        };
+        let line = std::str::from_utf8(line).map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            )
+        })?;
+        count += 1;
+
+        let fields: Vec<&str> = if is_split_whitespace {
+            line.split_whitespace().collect()
+        } else {
+            line.split('\t').collect()
+        };
+
+        if fields.len() != 6 {
+            Err(BedError::MetadataFieldCount(
+                6,
+                fields.len(),
+                path.to_path_buf(),
+            ))?;
+        }
+
+        let mut of_interest_count = 0;
+        for (field_index, field) in fields.iter().enumerate() {
+            if field_vec.contains(&field_index) {
+                vec_of_vec[of_interest_count].push((*field).to_string());
+                of_interest_count += 1;
+            }
+        }
+    }
+    Ok((vec_of_vec, count))
+}
+
+/// How [`standardize`](fn.standardize.html) scales each SNP (variant) after centering it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dist {
+    /// Divide by the SNP's standard deviation, giving it unit variance.
     Unit,
-    Beta { a: f64, b: f64 },
+    /// Multiply by the density, at the SNP's minor allele frequency, of the Beta(`a`, `b`)
+    /// distribution. For example, `Beta { a: 1.0, b: 25.0 }` up-weights rare variants.
+    #[cfg(feature = "beta")]
+    Beta {
+        /// The first shape parameter.
+        a: f64,
+        /// The second shape parameter.
+        b: f64,
+    },
+}
+
+impl Dist {
+    #[cfg_attr(not(feature = "beta"), allow(clippy::unused_self))]
+    fn is_beta(&self) -> bool {
+        #[cfg(feature = "beta")]
+        return matches!(self, Dist::Beta { .. });
+        #[cfg(not(feature = "beta"))]
+        false
+    }
 }
 
-#[allow(dead_code)]
 fn impute_and_zero_mean_snps<
     T: Default + Copy + Debug + Sync + Send + Sync + Float + ToPrimitive + FromPrimitive,
 >(
@@ -818,18 +1677,23 @@ fn impute_and_zero_mean_snps<
 
     // If output is F-order (or in general if iid stride is no more than sid_stride)
     if val.stride_of(nd::Axis(0)) <= val.stride_of(nd::Axis(1)) {
-        let result_list = nd::Zip::from(val.axis_iter_mut(nd::Axis(1)))
-            .and(stats.axis_iter_mut(nd::Axis(0)))
-            .par_map_collect(|mut col, mut stats_row| {
-                _process_sid(
-                    &mut col,
-                    apply_in_place,
-                    use_stats,
-                    &mut stats_row,
-                    dist,
-                    two,
-                )
-            });
+        let zip =
+            nd::Zip::from(val.axis_iter_mut(nd::Axis(1))).and(stats.axis_iter_mut(nd::Axis(0)));
+        let process_sid = |mut col: nd::ArrayViewMut1<'_, T>,
+                           mut stats_row: nd::ArrayViewMut1<'_, T>| {
+            _process_sid(
+                &mut col,
+                apply_in_place,
+                use_stats,
+                &mut stats_row,
+                dist,
+                two,
+            )
+        };
+        #[cfg(feature = "parallel")]
+        let result_list = zip.par_map_collect(process_sid);
+        #[cfg(not(feature = "parallel"))]
+        let result_list = zip.map_collect(process_sid);
 
         // Check the result list for errors
         result_list
@@ -845,7 +1709,10 @@ fn impute_and_zero_mean_snps<
 }
 
 // Later move the other fast-lmm functions into their own package
-#[allow(dead_code)]
+#[cfg_attr(
+    not(feature = "beta"),
+    allow(unused_variables, clippy::unnecessary_wraps)
+)]
 fn find_factor<
     T: Default + Copy + Debug + Sync + Send + Sync + Float + ToPrimitive + FromPrimitive,
 >(
@@ -853,6 +1720,7 @@ fn find_factor<
     mean_s: T,
     std: T,
 ) -> Result<T, BedError> {
+    #[cfg(feature = "beta")]
     if let Dist::Beta { a, b } = dist {
         // Try to create a beta dist
         let Ok(beta_dist) = Beta::new(*a, *b) else {
@@ -870,17 +1738,15 @@ fn find_factor<
         }
 
         // Try to put the maf in the beta dist
-        if let Some(b) = T::from_f64(beta_dist.pdf(maf)) {
+        return if let Some(b) = T::from_f64(beta_dist.pdf(maf)) {
             Ok(b)
         } else {
             Err(BedError::CannotConvertBetaToFromF64)
-        }
-    } else {
-        Ok(T::one() / std)
+        };
     }
+    Ok(T::one() / std)
 }
 
-#[allow(dead_code)]
 fn _process_sid<
     T: Default + Copy + Debug + Sync + Send + Sync + Float + ToPrimitive + FromPrimitive,
 >(
@@ -911,10 +1777,7 @@ fn _process_sid<
         let mean_s = sum_s / n_observed; //compute the mean over observed individuals for the current SNP
         let mean2_s: T = sum2_s / n_observed; //compute the mean of the squared SNP
 
-        if mean_s.is_nan()
-            || (matches!(dist, Dist::Beta { a: _, b: _ })
-                && ((mean_s > two) || (mean_s < T::zero())))
-        {
+        if mean_s.is_nan() || (dist.is_beta() && ((mean_s > two) || (mean_s < T::zero()))) {
             Err(BedError::IllegalSnpMean)?;
         }
 
@@ -951,7 +1814,6 @@ fn _process_sid<
     Ok(())
 }
 
-#[allow(dead_code)]
 fn _process_all_iids<
     T: Default + Copy + Debug + Sync + Send + Sync + Float + ToPrimitive + FromPrimitive,
 >(
@@ -972,7 +1834,7 @@ fn _process_all_iids<
         let mut sum_s_array = nd::Array1::<T>::zeros(sid_count); //the sum of a SNP over all observed individuals
         let mut sum2_s_array = nd::Array1::<T>::zeros(sid_count); //the sum of the squares of the SNP over all observed individuals
         for row in val.axis_iter(nd::Axis(0)) {
-            nd::par_azip!((&v in row,
+            par_azip!((&v in row,
                 n_observed_ptr in &mut n_observed_array,
                 sum_s_ptr in &mut sum_s_array,
                 sum2_s_ptr in &mut sum2_s_array
@@ -987,7 +1849,7 @@ fn _process_all_iids<
 
         // O(sid_count)
         let mut result_list: Vec<Result<(), BedError>> = vec![Ok(()); sid_count];
-        nd::par_azip!((mut stats_row in stats.axis_iter_mut(nd::Axis(0)),
+        par_azip!((mut stats_row in stats.axis_iter_mut(nd::Axis(0)),
                 &n_observed in &n_observed_array,
                 &sum_s in &sum_s_array,
                 &sum2_s in &sum2_s_array,
@@ -1001,7 +1863,7 @@ fn _process_all_iids<
             let mean2_s: T = sum2_s / n_observed; //compute the mean of the squared SNP
 
             if mean_s.is_nan()
-                || (matches!(dist, Dist::Beta { a:_, b:_ }) && ((mean_s > two) || (mean_s < T::zero())))
+                || (dist.is_beta() && ((mean_s > two) || (mean_s < T::zero())))
             {
                 *result_ptr = Err(BedError::IllegalSnpMean);
                 return;
@@ -1039,7 +1901,7 @@ fn _process_all_iids<
             })?;
 
         // O(iid_count * sid_count)
-        nd::par_azip!((mut row in val.axis_iter_mut(nd::Axis(0)))
+        par_azip!((mut row in val.axis_iter_mut(nd::Axis(0)))
         {
             for sid_i in 0..row.len() {
                 //check for Missing (NAN) or SNC
@@ -1063,17 +1925,14 @@ fn file_b_less_aatbx(
     b1: &mut nd::ArrayViewMut2<'_, f64>,
     aatb: &mut nd::ArrayViewMut2<'_, f64>,
     atb: &mut nd::ArrayViewMut2<'_, f64>,
-    log_frequency: usize,
+    progress: &dyn Fn(usize, usize),
 ) -> Result<(), Box<BedErrorPlus>> {
     //speed idea from C++:
     //Are copies really needed?
     //is F, vc C order the best?
     //would bigger snp blocks be better
 
-    let (a_sid_count, b_sid_count) = atb.dim();
-    if log_frequency > 0 {
-        println!("file_b_less_aatbx: iid_count={iid_count}, {a_sid_count}x{b_sid_count} output");
-    };
+    let a_sid_count = atb.nrows();
 
     // Open the file and move to the starting sid
     let mut buf_reader = BufReader::new(File::open(a_filename)?);
@@ -1081,15 +1940,11 @@ fn file_b_less_aatbx(
 
     let mut sid_reuse = vec![f64::NAN; iid_count];
     for (a_sid_index, mut atb_row) in atb.axis_iter_mut(nd::Axis(0)).enumerate() {
-        if log_frequency > 0 && a_sid_index % log_frequency == 0 {
-            println!(
-                "   working on train_sid_index={a_sid_index} of {a_sid_count} (iid_count={iid_count}, b_sid_count={b_sid_count})"
-            );
-        }
+        progress(a_sid_index, a_sid_count);
 
         buf_reader.read_f64_into::<LittleEndian>(&mut sid_reuse)?;
 
-        nd::par_azip!(
+        par_azip!(
             (mut atb_element in atb_row.axis_iter_mut(nd::Axis(0)),
             b1_col in b1.axis_iter(nd::Axis(1)),
             mut aatb_col in aatb.axis_iter_mut(nd::Axis(1)))
@@ -1185,7 +2040,7 @@ fn file_ata_piece<T: Float + Send + Sync + Sync + AddAssign>(
     col_count: usize,
     col_start: usize,
     ata_piece: &mut nd::ArrayViewMut2<'_, T>,
-    log_frequency: usize,
+    progress: &dyn Fn(usize, usize),
     read_into: fn(&mut BufReader<File>, &mut [T]) -> std::io::Result<()>,
 ) -> Result<(), Box<BedErrorPlus>> {
     let (nrows, ncols) = ata_piece.dim();
@@ -1197,13 +2052,7 @@ fn file_ata_piece<T: Float + Send + Sync + Sync + AddAssign>(
     }
 
     _file_ata_piece_internal(
-        path,
-        offset,
-        row_count,
-        col_start,
-        ata_piece,
-        log_frequency,
-        read_into,
+        path, offset, row_count, col_start, ata_piece, progress, read_into,
     )
 }
 
@@ -1215,13 +2064,10 @@ fn _file_ata_piece_internal<T: Float + Send + Sync + Sync + AddAssign>(
     row_count: usize,
     col_start: usize,
     ata_piece: &mut nd::ArrayViewMut2<'_, T>,
-    log_frequency: usize,
+    progress: &dyn Fn(usize, usize),
     read_into: fn(&mut BufReader<File>, &mut [T]) -> std::io::Result<()>,
 ) -> Result<(), Box<BedErrorPlus>> {
     let (nrows, ncols) = ata_piece.dim();
-    if log_frequency > 0 {
-        println!("file_ata_piece: col_start={col_start}, {nrows}x{ncols} output");
-    };
 
     // Open the file and move to the starting col
     let mut buf_reader = BufReader::new(File::open(path)?);
@@ -1233,9 +2079,7 @@ fn _file_ata_piece_internal<T: Float + Send + Sync + Sync + AddAssign>(
     let mut col_reuse = vec![T::nan(); row_count];
 
     for (col_rel_index, mut ata_row) in ata_piece.axis_iter_mut(nd::Axis(0)).enumerate() {
-        if log_frequency > 0 && col_rel_index % log_frequency == 0 {
-            println!("   working on {col_rel_index} of {nrows}");
-        }
+        progress(col_rel_index, nrows);
 
         // Read next col and save if in range
         let col = if col_save_list.len() < ncols {
@@ -1250,7 +2094,7 @@ fn _file_ata_piece_internal<T: Float + Send + Sync + Sync + AddAssign>(
 
         // Multiple saved sids with new sid
         let mut ata_row_trimmed = ata_row.slice_mut(nd::s![..col_save_list.len()]);
-        nd::par_azip!((
+        par_azip!((
             col_in_range in &col_save_list,
             mut ata_val in ata_row_trimmed.axis_iter_mut(nd::Axis(0))
         )
@@ -1296,15 +2140,11 @@ fn file_aat_piece<T: Float + Sync + Send + Sync + AddAssign>(
     col_count: usize,
     row_start: usize,
     aat_piece: &mut nd::ArrayViewMut2<'_, T>,
-    log_frequency: usize,
+    progress: &dyn Fn(usize, usize),
     read_into: fn(&mut BufReader<File>, &mut [T]) -> std::io::Result<()>,
 ) -> Result<(), Box<BedErrorPlus>> {
     let (nrows, ncols) = aat_piece.dim();
 
-    if log_frequency > 0 {
-        println!("file_aat_piece: row_start={row_start}, {nrows}x{ncols} output");
-    };
-
     if (row_start >= row_count)
         || (row_start + nrows != row_count)
         || (row_start + ncols > row_count)
@@ -1320,9 +2160,7 @@ fn file_aat_piece<T: Float + Sync + Send + Sync + AddAssign>(
     let mut col = vec![T::nan(); row_count - row_start];
 
     for col_index in 0..col_count {
-        if log_frequency > 0 && col_index % log_frequency == 0 {
-            println!("   working on {col_index} of {col_count}");
-        }
+        progress(col_index, col_count);
 
         // Read next col
         buf_reader.seek(SeekFrom::Start(
@@ -1330,7 +2168,7 @@ fn file_aat_piece<T: Float + Sync + Send + Sync + AddAssign>(
         ))?;
         read_into(&mut buf_reader, &mut col)?;
 
-        nd::par_azip!(
+        par_azip!(
             (index row_index1,
             mut aat_col in aat_piece.axis_iter_mut(nd::Axis(1))
         )
@@ -1365,7 +2203,7 @@ fn file_aat_piece<T: Float + Sync + Send + Sync + AddAssign>(
 /// use bed_reader::{Bed, WriteOptions, sample_bed_file};
 /// use ndarray_rand::{rand::prelude::StdRng, rand::SeedableRng, rand_distr::Uniform, RandomExt};
 ///
-/// let mut bed = Bed::new(sample_bed_file("small.bed")?)?;
+/// let bed = Bed::new(sample_bed_file("small.bed")?)?;
 /// let metadata = bed.metadata()?;
 /// let shape = bed.dim()?;
 ///
@@ -1386,46 +2224,50 @@ fn file_aat_piece<T: Float + Sync + Send + Sync + AddAssign>(
 pub struct Metadata {
     #[builder(setter(custom))]
     #[builder(default = "None")]
-    fid: Option<Rc<nd::Array1<String>>>,
+    fid: Option<Arc<nd::Array1<String>>>,
     #[builder(setter(custom))]
     #[builder(default = "None")]
-    iid: Option<Rc<nd::Array1<String>>>,
+    iid: Option<Arc<nd::Array1<String>>>,
     #[builder(setter(custom))]
     #[builder(default = "None")]
-    father: Option<Rc<nd::Array1<String>>>,
+    father: Option<Arc<nd::Array1<String>>>,
     #[builder(setter(custom))]
     #[builder(default = "None")]
-    mother: Option<Rc<nd::Array1<String>>>,
+    mother: Option<Arc<nd::Array1<String>>>,
 
     // i32 based on https://www.cog-genomics.org/plink2/formats#bim
     #[builder(setter(custom))]
     #[builder(default = "None")]
-    sex: Option<Rc<nd::Array1<i32>>>,
+    sex: Option<Arc<nd::Array1<i32>>>,
     #[builder(setter(custom))]
     #[builder(default = "None")]
-    pheno: Option<Rc<nd::Array1<String>>>,
+    pheno: Option<Arc<nd::Array1<String>>>,
 
     #[builder(setter(custom))]
     #[builder(default = "None")]
-    chromosome: Option<Rc<nd::Array1<String>>>,
+    chromosome: Option<Arc<nd::Array1<String>>>,
     #[builder(setter(custom))]
     #[builder(default = "None")]
-    sid: Option<Rc<nd::Array1<String>>>,
+    sid: Option<Arc<nd::Array1<String>>>,
     #[builder(setter(custom))]
     #[builder(default = "None")]
-    cm_position: Option<Rc<nd::Array1<f32>>>,
+    cm_position: Option<Arc<nd::Array1<f32>>>,
     #[builder(setter(custom))]
     #[builder(default = "None")]
-    bp_position: Option<Rc<nd::Array1<i32>>>,
+    bp_position: Option<Arc<nd::Array1<i32>>>,
     #[builder(setter(custom))]
     #[builder(default = "None")]
-    allele_1: Option<Rc<nd::Array1<String>>>,
+    allele_1: Option<Arc<nd::Array1<String>>>,
     #[builder(setter(custom))]
     #[builder(default = "None")]
-    allele_2: Option<Rc<nd::Array1<String>>>,
+    allele_2: Option<Arc<nd::Array1<String>>>,
+
+    #[builder(setter(custom))]
+    #[builder(default = "BTreeMap::new()")]
+    sid_annotations: BTreeMap<String, Arc<SidAnnotation>>,
 }
 
-fn lazy_or_skip_count<T>(array: &Option<Rc<nd::Array1<T>>>) -> Option<usize> {
+fn lazy_or_skip_count<T>(array: &Option<Arc<nd::Array1<T>>>) -> Option<usize> {
     array.as_ref().map(|array| array.len())
 }
 
@@ -1462,6 +2304,7 @@ fn lazy_or_skip_count<T>(array: &Option<Rc<nd::Array1<T>>>) -> Option<usize> {
 /// ```
 #[derive(Clone, Debug, Builder)]
 #[builder(build_fn(private, name = "build_no_file_check", error = "BedErrorPlus"))]
+#[allow(clippy::struct_excessive_bools)]
 pub struct Bed {
     // https://stackoverflow.com/questions/32730714/what-is-the-right-way-to-store-an-immutable-path-in-a-struct
     // don't emit a setter, but keep the field declaration on the builder
@@ -1482,18 +2325,123 @@ pub struct Bed {
     is_checked_early: bool,
 
     #[builder(setter(custom))]
-    #[builder(default = "None")]
-    iid_count: Option<usize>,
+    #[builder(default = "OnceLock::new()")]
+    iid_count: OnceLock<usize>,
 
     #[builder(setter(custom))]
-    #[builder(default = "None")]
-    sid_count: Option<usize>,
+    #[builder(default = "OnceLock::new()")]
+    sid_count: OnceLock<usize>,
 
     #[builder(setter(custom))]
     metadata: Metadata,
 
+    // What reading the .fam and .bim files found, once read.
+    #[builder(setter(custom))]
+    #[builder(default = "OnceLock::new()")]
+    fam_loaded: OnceLock<Loaded>,
+
+    #[builder(setter(custom))]
+    #[builder(default = "OnceLock::new()")]
+    bim_loaded: OnceLock<Loaded>,
+
     #[builder(setter(custom))]
     skip_set: HashSet<MetadataFields>,
+
+    #[builder(setter(custom))]
+    raw_set: HashSet<MetadataFields>,
+
+    // The text of raw sex, cm_position, and bp_position fields given as typed arrays, once made.
+    #[builder(setter(custom))]
+    #[builder(default = "HashMap::new()")]
+    raw_fields: HashMap<MetadataFields, Arc<nd::Array1<String>>>,
+
+    #[builder(setter(custom))]
+    #[builder(default = "CountMismatchPolicy::Error")]
+    count_mismatch_policy: CountMismatchPolicy,
+
+    #[builder(setter(custom))]
+    #[builder(default = "HashMap::new()")]
+    scores: HashMap<String, nd::Array1<f64>>,
+
+    // The statistics that SNP filters consult, once computed.
+    #[builder(setter(custom))]
+    #[builder(default = "OnceLock::new()")]
+    sid_filter_stats: OnceLock<Arc<SidFilterStats>>,
+
+    #[builder(setter(custom))]
+    #[builder(default = "false")]
+    #[cfg_attr(not(feature = "checksum"), allow(dead_code))]
+    use_cache: bool,
+
+    #[builder(setter(custom))]
+    #[builder(default = "None")]
+    on_duplicate_iid: Option<DuplicateIidPolicy>,
+
+    #[builder(setter(custom))]
+    #[builder(default = "Vec::new()")]
+    duplicate_iids: Vec<(String, String)>,
+
+    // The metadata with the annotations of the SNP annotations sidecar file, once read.
+    #[builder(setter(custom))]
+    #[builder(default = "OnceLock::new()")]
+    sid_annotated: OnceLock<Metadata>,
+
+    #[builder(setter(custom))]
+    #[builder(default = "None")]
+    max_dims: Option<(usize, usize)>,
+
+    #[builder(setter(custom))]
+    #[builder(default = "false")]
+    is_watched: bool,
+
+    #[builder(setter(custom))]
+    #[builder(default = "StalePolicy::Refresh")]
+    stale_policy: StalePolicy,
+
+    #[builder(setter(custom))]
+    #[builder(default = "None")]
+    watch: Option<Watch>,
+
+    #[builder(setter(custom))]
+    #[builder(default = "false")]
+    #[cfg_attr(not(feature = "mmap"), allow(dead_code))]
+    is_mmap: bool,
+
+    // The memory mapping of the .bed file, once made.
+    #[cfg(feature = "mmap")]
+    #[builder(setter(custom))]
+    #[builder(default = "OnceLock::new()")]
+    mapping: OnceLock<Arc<Mmap>>,
+
+    #[builder(setter(custom))]
+    #[builder(default = "None")]
+    metadata_source: Option<Arc<dyn MetadataSource>>,
+
+    #[builder(setter(custom))]
+    #[builder(default = "None")]
+    byte_source: Option<Arc<dyn BedSource>>,
+}
+
+/// What to do when the number of lines in a .fam or .bim file disagrees with the
+/// number of individuals (samples) or SNPs (variants) expected.
+///
+/// The expected number comes from [`BedBuilder::iid_count`](struct.BedBuilder.html#method.iid_count),
+/// [`BedBuilder::sid_count`](struct.BedBuilder.html#method.sid_count), or overridden metadata
+/// such as [`BedBuilder::iid`](struct.BedBuilder.html#method.iid).
+///
+/// Set with [`BedBuilder::count_mismatch_policy`](struct.BedBuilder.html#method.count_mismatch_policy).
+/// Any decision made is recorded in [`Bed::count_mismatch_notes`](struct.Bed.html#method.count_mismatch_notes).
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash, Default)]
+pub enum CountMismatchPolicy {
+    /// Return a [`BedError::InconsistentCount`](enum.BedError.html#variant.InconsistentCount) error (default).
+    #[default]
+    Error,
+    /// Use the smaller of the two numbers. Extra metadata lines are ignored and
+    /// genotype data beyond the smaller number can not be read.
+    TruncateToMin,
+    /// Use the expected number. Missing metadata is filled with default values and
+    /// extra metadata lines are ignored.
+    PadMetadata,
 }
 
 /// All Metadata fields.
@@ -1542,7 +2490,27 @@ impl BedBuilder {
             sid_count: None,
 
             metadata: Some(Metadata::new()),
+            fam_loaded: None,
+            bim_loaded: None,
             skip_set: Some(HashSet::new()),
+            raw_set: Some(HashSet::new()),
+            raw_fields: None,
+            count_mismatch_policy: None,
+            scores: None,
+            sid_filter_stats: None,
+            use_cache: None,
+            on_duplicate_iid: None,
+            duplicate_iids: None,
+            sid_annotated: None,
+            max_dims: None,
+            is_watched: None,
+            stale_policy: None,
+            watch: None,
+            is_mmap: None,
+            #[cfg(feature = "mmap")]
+            mapping: None,
+            metadata_source: None,
+            byte_source: None,
         }
     }
 
@@ -1553,10 +2521,30 @@ impl BedBuilder {
         let mut bed = self.build_no_file_check()?;
 
         if bed.is_checked_early {
-            open_and_check(&bed.path)?;
+            match &bed.byte_source {
+                Some(source) => {
+                    bed_source::check_source(source.as_ref(), &bed.path)?;
+                }
+                None => {
+                    open_and_check(&bed.path)?;
+                }
+            }
+        }
+
+        let (iid_count, sid_count) = bed
+            .metadata
+            .check_counts(bed.iid_count.get().copied(), bed.sid_count.get().copied())?;
+        bed.check_max_dims(iid_count, sid_count)?;
+        bed.iid_count = iid_count.map(OnceLock::from).unwrap_or_default();
+        bed.sid_count = sid_count.map(OnceLock::from).unwrap_or_default();
+
+        if let Some(policy) = bed.on_duplicate_iid {
+            bed.check_duplicate_iids(policy)?;
         }
 
-        (bed.iid_count, bed.sid_count) = bed.metadata.check_counts(bed.iid_count, bed.sid_count)?;
+        if bed.is_watched {
+            bed.watch = Some(Watch::new(&bed)?);
+        }
 
         Ok(bed)
     }
@@ -1588,7 +2576,7 @@ impl BedBuilder {
     /// let file_name = sample_bed_file("small.bed")?;
     /// use bed_reader::ReadOptions;
     ///
-    /// let mut bed = Bed::builder(file_name)
+    /// let bed = Bed::builder(file_name)
     ///    .iid(["sample1", "sample2", "sample3"])
     ///    .build()?;
     /// println!("{:?}", bed.iid()?); // Outputs ndarray ["sample1", "sample2", "sample3"]
@@ -1679,7 +2667,7 @@ impl BedBuilder {
     /// use bed_reader::{Bed, ReadOptions, assert_eq_nan, sample_bed_file};
     /// let file_name = sample_bed_file("small.bed")?;
     ///
-    /// let mut bed = Bed::builder(file_name)
+    /// let bed = Bed::builder(file_name)
     ///    .sid(["SNP1", "SNP2", "SNP3", "SNP4"])
     ///    .build()?;
     /// println!("{:?}", bed.sid()?); // Outputs ndarray ["SNP1", "SNP2", "SNP3", "SNP4"]
@@ -1753,7 +2741,7 @@ impl BedBuilder {
     /// of lines. Providing the number thus avoids a file read.
     #[must_use]
     pub fn iid_count(mut self, count: usize) -> Self {
-        self.iid_count = Some(Some(count));
+        self.iid_count = Some(OnceLock::from(count));
         self
     }
 
@@ -1765,7 +2753,7 @@ impl BedBuilder {
     /// of lines. Providing the number thus avoids a file read.
     #[must_use]
     pub fn sid_count(mut self, count: usize) -> Self {
-        self.sid_count = Some(Some(count));
+        self.sid_count = Some(OnceLock::from(count));
         self
     }
 
@@ -1789,7 +2777,7 @@ impl BedBuilder {
     /// ```
     /// use bed_reader::{Bed, ReadOptions, sample_files};
     /// let deb_maf_mib = sample_files(["small.deb", "small.maf", "small.mib"])?;
-    /// let mut bed = Bed::builder(&deb_maf_mib[0])
+    /// let bed = Bed::builder(&deb_maf_mib[0])
     ///    .fam_path(&deb_maf_mib[1])
     ///    .bim_path(&deb_maf_mib[2])
     ///    .build()?;
@@ -1815,7 +2803,7 @@ impl BedBuilder {
     /// ```
     /// use bed_reader::{Bed, ReadOptions, sample_files};
     /// let deb_maf_mib = sample_files(["small.deb", "small.maf", "small.mib"])?;
-    /// let mut bed = Bed::builder(&deb_maf_mib[0])
+    /// let bed = Bed::builder(&deb_maf_mib[0])
     ///    .fam_path(&deb_maf_mib[1])
     ///    .bim_path(&deb_maf_mib[2])
     ///    .build()?;
@@ -2003,6 +2991,95 @@ impl BedBuilder {
         self
     }
 
+    /// Set what to do when the number of lines in the .fam or .bim file disagrees with the
+    /// expected number of individuals (samples) or SNPs (variants).
+    ///
+    /// By default, [`CountMismatchPolicy::Error`](enum.CountMismatchPolicy.html#variant.Error)
+    /// returns an error. See [`CountMismatchPolicy`](enum.CountMismatchPolicy.html) for the alternatives.
+    ///
+    /// # Example
+    /// The .bed file has 3 individuals, but we provide only two individual ids.
+    /// The rest of the .fam metadata is truncated to match.
+    /// ```
+    /// use bed_reader::{Bed, CountMismatchPolicy, sample_bed_file};
+    ///
+    /// let file_name = sample_bed_file("small.bed")?;
+    /// let bed = Bed::builder(file_name)
+    ///     .iid(["iid1", "iid2"])
+    ///     .count_mismatch_policy(CountMismatchPolicy::TruncateToMin)
+    ///     .build()?;
+    /// println!("{:?}", bed.fid()?); // Outputs ndarray ["fid1", "fid1"]
+    /// assert_eq!(bed.dim()?, (2, 4));
+    /// println!("{:?}", bed.count_mismatch_notes()); // Outputs ["iid: expected 2, found 3 in .fam file; using 2"]
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[must_use]
+    pub fn count_mismatch_policy(mut self, policy: CountMismatchPolicy) -> Self {
+        self.count_mismatch_policy = Some(policy);
+        self
+    }
+
+    /// Find the number of individuals (samples) and SNPs (variants) with a `.bedcache` sidecar file.
+    ///
+    /// Counting the lines of a huge .fam or .bim file can be slow. With the cache, the counts
+    /// are read from a small sidecar file next to the .bed file. The sidecar file is rebuilt when
+    /// missing, from an older version, or out of date with the .fam and .bim files' sizes and
+    /// modification times. Defaults to `false`. See [`Bed::cache`](struct.Bed.html#method.cache).
+    ///
+    /// # Example
+    /// ```
+    /// use bed_reader::{Bed, WriteOptions, sample_bed_file};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("small.bed");
+    /// let bed = Bed::new(sample_bed_file("small.bed")?)?;
+    /// WriteOptions::builder(&output_file)
+    ///     .metadata(&bed.metadata()?)
+    ///     .write(&bed.read::<i8>()?)?;
+    ///
+    /// let bed = Bed::builder(&output_file).use_cache(true).build()?;
+    /// assert_eq!(bed.dim()?, (3, 4)); // Counts lines and saves the cache
+    /// let bed = Bed::builder(&output_file).use_cache(true).build()?;
+    /// assert_eq!(bed.dim()?, (3, 4)); // Reads the cache
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[cfg(feature = "checksum")]
+    #[must_use]
+    pub fn use_cache(mut self, use_cache: bool) -> Self {
+        self.use_cache = Some(use_cache);
+        self
+    }
+
+    /// Set the most individuals (samples) and SNPs (variants) allowed.
+    ///
+    /// A corrupted .fam or .bim file with billions of lines can lead to huge allocations.
+    /// With maximums, any larger count, whether given or found by counting lines,
+    /// returns a [`BedError::TooManyForMaxDims`](enum.BedError.html#variant.TooManyForMaxDims)
+    /// error before metadata is read or genotype data is allocated.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("small.bed");
+    /// WriteOptions::builder(&output_file).write(&nd::array![[0, 1, 2], [2, 0, 1]])?;
+    ///
+    /// let bed = Bed::builder(&output_file).max_dims(1_000, 2).build()?;
+    /// assert_eq!(bed.iid_count()?, 2);
+    /// assert!(bed.sid_count().is_err()); // 3 SNPs, but at most 2 allowed
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[must_use]
+    pub fn max_dims(mut self, iid_max: usize, sid_max: usize) -> Self {
+        self.max_dims = Some(Some((iid_max, sid_max)));
+        self
+    }
+
     /// Override the metadata in the .fam and .bim files with info merged in from a [`Metadata`](struct.Metadata.html).
     ///
     /// # Example
@@ -2022,7 +3099,7 @@ impl BedBuilder {
     ///     .iid(["i1", "i2", "i3"])
     ///     .sid(["s1", "s2", "s3", "s4"])
     ///     .build()?;
-    /// let mut bed = Bed::builder(file_name)
+    /// let bed = Bed::builder(file_name)
     ///     .fid(["f1", "f2", "f3"])
     ///     .iid(["x1", "x2", "x3"])
     ///     .metadata(&metadata)
@@ -2048,6 +3125,20 @@ impl BedBuilder {
     }
 }
 
+// The .bed path for a fileset given with or without the .bed extension.
+fn bed_path_from_prefix(prefix: &Path) -> PathBuf {
+    if prefix
+        .extension()
+        .is_some_and(|extension| extension == "bed")
+    {
+        prefix.to_path_buf()
+    } else {
+        let mut path = prefix.as_os_str().to_owned();
+        path.push(".bed");
+        PathBuf::from(path)
+    }
+}
+
 #[anyinput]
 fn to_metadata_path(
     bed_path: AnyPath,
@@ -2093,7 +3184,7 @@ impl Bed {
     /// use bed_reader::{Bed, assert_eq_nan, sample_bed_file};
     ///
     /// let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::builder(file_name).build()?;
+    /// let bed = Bed::builder(file_name).build()?;
     /// println!("{:?}", bed.iid()?); // Outputs ndarray ["iid1", "iid2", "iid3"]
     /// println!("{:?}", bed.sid()?); // Outputs ndarray ["snp1", "snp2", "snp3", "snp4"]
     /// let val = bed.read::<f64>()?;
@@ -2115,7 +3206,7 @@ impl Bed {
     /// # use ndarray as nd;
     /// # use bed_reader::{Bed, ReadOptions, assert_eq_nan, sample_bed_file};
     /// # let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::builder(file_name)
+    /// let bed = Bed::builder(file_name)
     ///    .iid(["sample1", "sample2", "sample3"])
     ///    .build()?;
     /// println!("{:?}", bed.iid()?); // Outputs ndarray ["sample1", "sample2", "sample3"]
@@ -2128,7 +3219,7 @@ impl Bed {
     /// # use ndarray as nd;
     /// # use bed_reader::{Bed, ReadOptions, assert_eq_nan, sample_bed_file};
     /// # let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::builder(file_name).iid_count(3).sid_count(4).build()?;
+    /// let bed = Bed::builder(file_name).iid_count(3).sid_count(4).build()?;
     /// let val = bed.read::<f64>()?;
     ///
     /// assert_eq_nan(
@@ -2147,7 +3238,7 @@ impl Bed {
     /// # use ndarray as nd;
     /// # use bed_reader::{Bed, ReadOptions, assert_eq_nan, sample_bed_file};
     /// # let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::builder(file_name)
+    /// let bed = Bed::builder(file_name)
     ///     .skip_father()
     ///     .skip_mother()
     ///     .skip_sex()
@@ -2189,7 +3280,7 @@ impl Bed {
     /// use bed_reader::{Bed, assert_eq_nan, sample_bed_file};
     ///
     /// let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::new(file_name)?;
+    /// let bed = Bed::new(file_name)?;
     /// println!("{:?}", bed.iid()?); // Outputs ndarray: ["iid1", "iid2", "iid3"]
     /// println!("{:?}", bed.sid()?); // Outputs ndarray: ["sid1", "sid2", "sid3", "sid4"]
     /// let val = bed.read::<f64>()?;
@@ -2233,6 +3324,8 @@ impl Bed {
     /// The file read can be avoided by setting the
     /// number with [`BedBuilder::iid_count`](struct.BedBuilder.html#method.iid_count)
     /// or, for example, [`BedBuilder::iid`](struct.BedBuilder.html#method.iid).
+    /// With [`BedBuilder::use_cache`](struct.BedBuilder.html#method.use_cache),
+    /// the number is read from a `.bedcache` sidecar file, when up to date.
     ///
     /// # Example:
     /// ```
@@ -2240,20 +3333,34 @@ impl Bed {
     /// use bed_reader::{Bed, ReadOptions, assert_eq_nan, sample_bed_file};
     ///
     /// let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::new(file_name)?;
+    /// let bed = Bed::new(file_name)?;
     /// let iid_count = bed.iid_count()?;
     ///
     /// assert!(iid_count == 3);
     /// # use bed_reader::BedErrorPlus;
     /// # Ok::<(), Box<BedErrorPlus>>(())
-    pub fn iid_count(&mut self) -> Result<usize, Box<BedErrorPlus>> {
-        if let Some(iid_count) = self.iid_count {
+    pub fn iid_count(&self) -> Result<usize, Box<BedErrorPlus>> {
+        if let Some(latest) = self.latest() {
+            return latest.iid_count();
+        }
+        if let Some(fam) = self.fam_loaded.get() {
+            Ok(fam.count)
+        } else if let Some(&iid_count) = self.iid_count.get() {
             Ok(iid_count)
+        } else if let Some(iid_count) = self.source_iid_count() {
+            let iid_count = iid_count?;
+            self.check_max_dims(Some(iid_count), None)?;
+            Ok(*self.iid_count.get_or_init(|| iid_count))
         } else {
+            #[cfg(feature = "checksum")]
+            if self.use_cache {
+                self.counts_from_cache()?;
+                return self.iid_count();
+            }
             let fam_path = self.fam_path();
             let iid_count = count_lines(fam_path)?;
-            self.iid_count = Some(iid_count);
-            Ok(iid_count)
+            self.check_max_dims(Some(iid_count), None)?;
+            Ok(*self.iid_count.get_or_init(|| iid_count))
         }
     }
 
@@ -2265,6 +3372,8 @@ impl Bed {
     /// The file read can be avoided by setting the
     /// number with [`BedBuilder::sid_count`](struct.BedBuilder.html#method.sid_count)
     /// or, for example, [`BedBuilder::sid`](struct.BedBuilder.html#method.sid).
+    /// With [`BedBuilder::use_cache`](struct.BedBuilder.html#method.use_cache),
+    /// the number is read from a `.bedcache` sidecar file, when up to date.
     ///
     /// # Example:
     /// ```
@@ -2272,20 +3381,34 @@ impl Bed {
     /// use bed_reader::{Bed, ReadOptions, assert_eq_nan, sample_bed_file};
     ///
     /// let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::new(file_name)?;
+    /// let bed = Bed::new(file_name)?;
     /// let sid_count = bed.sid_count()?;
     ///
     /// assert!(sid_count == 4);
     /// # use bed_reader::BedErrorPlus;
     /// # Ok::<(), Box<BedErrorPlus>>(())
-    pub fn sid_count(&mut self) -> Result<usize, Box<BedErrorPlus>> {
-        if let Some(sid_count) = self.sid_count {
+    pub fn sid_count(&self) -> Result<usize, Box<BedErrorPlus>> {
+        if let Some(latest) = self.latest() {
+            return latest.sid_count();
+        }
+        if let Some(bim) = self.bim_loaded.get() {
+            Ok(bim.count)
+        } else if let Some(&sid_count) = self.sid_count.get() {
             Ok(sid_count)
+        } else if let Some(sid_count) = self.source_sid_count() {
+            let sid_count = sid_count?;
+            self.check_max_dims(None, Some(sid_count))?;
+            Ok(*self.sid_count.get_or_init(|| sid_count))
         } else {
+            #[cfg(feature = "checksum")]
+            if self.use_cache {
+                self.counts_from_cache()?;
+                return self.sid_count();
+            }
             let bim_path = self.bim_path();
             let sid_count = count_lines(bim_path)?;
-            self.sid_count = Some(sid_count);
-            Ok(sid_count)
+            self.check_max_dims(None, Some(sid_count))?;
+            Ok(*self.sid_count.get_or_init(|| sid_count))
         }
     }
 
@@ -2305,16 +3428,85 @@ impl Bed {
     /// use bed_reader::assert_eq_nan;
     ///
     /// let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::new(file_name)?;
+    /// let bed = Bed::new(file_name)?;
     /// let dim = bed.dim()?;
     ///
     /// assert!(dim == (3,4));
     /// # use bed_reader::BedErrorPlus;
     /// # Ok::<(), Box<BedErrorPlus>>(())
-    pub fn dim(&mut self) -> Result<(usize, usize), Box<BedErrorPlus>> {
+    pub fn dim(&self) -> Result<(usize, usize), Box<BedErrorPlus>> {
         Ok((self.iid_count()?, self.sid_count()?))
     }
 
+    /// Number of individuals (samples) and SNPs (variants), with the number of SNPs
+    /// inferred from the length of the .bed file rather than by counting .bim lines.
+    ///
+    /// The number of individuals is found as with [`iid_count`](struct.Bed.html#method.iid_count),
+    /// so the .fam file is only read if the number isn't already known. The number of SNPs
+    /// is not remembered, so the .bim file is still read, and checked, when it is needed.
+    /// If the number of SNPs is already known, it is checked against the inferred number.
+    /// Useful for quick sanity checks and progress estimates on very large files.
+    ///
+    /// Returns an error if the .bed file is not SNP-major or its length is not
+    /// a whole number of SNP columns.
+    ///
+    /// # Example:
+    /// ```
+    /// use bed_reader::{Bed, sample_bed_file};
+    ///
+    /// let file_name = sample_bed_file("small.bed")?;
+    /// let bed = Bed::builder(file_name).iid_count(3).build()?;
+    /// let dim = bed.peek_dims()?;
+    ///
+    /// assert!(dim == (3, 4));
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    pub fn peek_dims(&self) -> Result<(usize, usize), Box<BedErrorPlus>> {
+        if let Some(latest) = self.latest() {
+            return latest.peek_dims();
+        }
+        let iid_count = self.iid_count()?;
+        let layout_iid_count = self.layout_iid_count().unwrap_or(iid_count);
+
+        let (buf_reader, bytes_array) = open_and_check(&self.path)?;
+        if bytes_array[2] != 1 {
+            Err(BedError::NotSnpMajor(self.path.clone()))?;
+        }
+        let data_len = buf_reader.get_ref().metadata()?.len() - CB_HEADER_U64;
+
+        let column_len = format::bytes_per_column(layout_iid_count);
+        let layout_sid_count = if column_len == 0 {
+            // With no individuals, the columns take no space, so only the .bim file can tell.
+            if data_len != 0 {
+                Err(BedError::IllFormed(self.path.clone()))?;
+            }
+            return Ok((iid_count, self.sid_count()?));
+        } else if !data_len.is_multiple_of(column_len) {
+            Err(BedError::IllFormed(self.path.clone()))?
+        } else {
+            // "as" is safe because the crate requires a 64-bit target
+            (data_len / column_len) as usize
+        };
+
+        if let Some(sid_count) = self
+            .bim_loaded
+            .get()
+            .map(|bim| bim.count)
+            .or(self.sid_count.get().copied())
+        {
+            let known_layout_sid_count = self.layout_sid_count().unwrap_or(sid_count);
+            if known_layout_sid_count != layout_sid_count {
+                Err(BedError::InconsistentCount(
+                    "sid".to_string(),
+                    known_layout_sid_count,
+                    layout_sid_count,
+                ))?;
+            }
+            return Ok((iid_count, sid_count));
+        }
+        Ok((iid_count, layout_sid_count))
+    }
+
     /// Family id of each of individual (sample)
     ///
     /// If this ndarray is needed, it will be found
@@ -2330,14 +3522,13 @@ impl Bed {
     /// use bed_reader::assert_eq_nan;
     ///
     /// let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::new(file_name)?;
+    /// let bed = Bed::new(file_name)?;
     /// let fid = bed.fid()?;
     /// println!("{fid:?}"); // Outputs ndarray ["fid1", "fid1", "fid2"]
     /// # use bed_reader::BedErrorPlus;
     /// # Ok::<(), Box<BedErrorPlus>>(())
-    pub fn fid(&mut self) -> Result<&nd::Array1<String>, Box<BedErrorPlus>> {
-        self.unlazy_fam::<String>(self.metadata.fid.is_none(), MetadataFields::Fid, "fid")?;
-        Ok(self.metadata.fid.as_ref().unwrap()) //unwrap always works because of lazy_fam
+    pub fn fid(&self) -> Result<&nd::Array1<String>, Box<BedErrorPlus>> {
+        self.field(MetadataFields::Fid, |metadata| metadata.fid.as_ref())
     }
 
     /// Individual id of each of individual (sample)
@@ -2355,14 +3546,13 @@ impl Bed {
     /// use bed_reader::assert_eq_nan;
     ///
     /// let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::new(file_name)?;
+    /// let bed = Bed::new(file_name)?;
     /// let iid = bed.iid()?;    ///
     /// println!("{iid:?}"); // Outputs ndarray ["iid1", "iid2", "iid3"]
     /// # use bed_reader::BedErrorPlus;
     /// # Ok::<(), Box<BedErrorPlus>>(())
-    pub fn iid(&mut self) -> Result<&nd::Array1<String>, Box<BedErrorPlus>> {
-        self.unlazy_fam::<String>(self.metadata.iid.is_none(), MetadataFields::Iid, "iid")?;
-        Ok(self.metadata.iid.as_ref().unwrap()) //unwrap always works because of lazy_fam
+    pub fn iid(&self) -> Result<&nd::Array1<String>, Box<BedErrorPlus>> {
+        self.field(MetadataFields::Iid, |metadata| metadata.iid.as_ref())
     }
 
     /// Father id of each of individual (sample)
@@ -2380,18 +3570,13 @@ impl Bed {
     /// use bed_reader::assert_eq_nan;
     ///
     /// let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::new(file_name)?;
+    /// let bed = Bed::new(file_name)?;
     /// let father = bed.father()?;
     /// println!("{father:?}"); // Outputs ndarray ["iid23", "iid23", "iid22"]
     /// # use bed_reader::BedErrorPlus;
     /// # Ok::<(), Box<BedErrorPlus>>(())    
-    pub fn father(&mut self) -> Result<&nd::Array1<String>, Box<BedErrorPlus>> {
-        self.unlazy_fam::<String>(
-            self.metadata.father.is_none(),
-            MetadataFields::Father,
-            "father",
-        )?;
-        Ok(self.metadata.father.as_ref().unwrap()) //unwrap always works because of lazy_fam
+    pub fn father(&self) -> Result<&nd::Array1<String>, Box<BedErrorPlus>> {
+        self.field(MetadataFields::Father, |metadata| metadata.father.as_ref())
     }
 
     /// Mother id of each of individual (sample)
@@ -2409,18 +3594,13 @@ impl Bed {
     /// use bed_reader::assert_eq_nan;
     ///
     /// let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::new(file_name)?;
+    /// let bed = Bed::new(file_name)?;
     /// let mother = bed.mother()?;
     /// println!("{mother:?}"); // Outputs ndarray ["iid34", "iid34", "iid33"]
     /// # use bed_reader::BedErrorPlus;
     /// # Ok::<(), Box<BedErrorPlus>>(())
-    pub fn mother(&mut self) -> Result<&nd::Array1<String>, Box<BedErrorPlus>> {
-        self.unlazy_fam::<String>(
-            self.metadata.mother.is_none(),
-            MetadataFields::Mother,
-            "mother",
-        )?;
-        Ok(self.metadata.mother.as_ref().unwrap()) //unwrap always works because of lazy_fam
+    pub fn mother(&self) -> Result<&nd::Array1<String>, Box<BedErrorPlus>> {
+        self.field(MetadataFields::Mother, |metadata| metadata.mother.as_ref())
     }
 
     /// Sex each of individual (sample)
@@ -2440,14 +3620,13 @@ impl Bed {
     /// use bed_reader::assert_eq_nan;
     ///
     /// let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::new(file_name)?;
+    /// let bed = Bed::new(file_name)?;
     /// let sex = bed.sex()?;
     /// println!("{sex:?}"); // Outputs ndarray [1, 2, 0]
     /// # use bed_reader::BedErrorPlus;
     /// # Ok::<(), Box<BedErrorPlus>>(())
-    pub fn sex(&mut self) -> Result<&nd::Array1<i32>, Box<BedErrorPlus>> {
-        self.unlazy_fam::<String>(self.metadata.sex.is_none(), MetadataFields::Sex, "sex")?;
-        Ok(self.metadata.sex.as_ref().unwrap()) //unwrap always works because of lazy_fam
+    pub fn sex(&self) -> Result<&nd::Array1<i32>, Box<BedErrorPlus>> {
+        self.field(MetadataFields::Sex, |metadata| metadata.sex.as_ref())
     }
 
     /// A phenotype for each individual (seldom used)
@@ -2465,18 +3644,13 @@ impl Bed {
     /// use bed_reader::assert_eq_nan;
     ///
     /// let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::new(file_name)?;
+    /// let bed = Bed::new(file_name)?;
     /// let pheno = bed.pheno()?;
     /// println!("{pheno:?}"); // Outputs ndarray ["red", "red", "blue"]
     /// # use bed_reader::BedErrorPlus;
     /// # Ok::<(), Box<BedErrorPlus>>(())
-    pub fn pheno(&mut self) -> Result<&nd::Array1<String>, Box<BedErrorPlus>> {
-        self.unlazy_fam::<String>(
-            self.metadata.pheno.is_none(),
-            MetadataFields::Pheno,
-            "pheno",
-        )?;
-        Ok(self.metadata.pheno.as_ref().unwrap()) //unwrap always works because of lazy_fam
+    pub fn pheno(&self) -> Result<&nd::Array1<String>, Box<BedErrorPlus>> {
+        self.field(MetadataFields::Pheno, |metadata| metadata.pheno.as_ref())
     }
 
     /// Chromosome of each SNP (variant)
@@ -2494,18 +3668,15 @@ impl Bed {
     /// use bed_reader::assert_eq_nan;
     ///
     /// let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::new(file_name)?;
+    /// let bed = Bed::new(file_name)?;
     /// let chromosome = bed.chromosome()?;
     /// println!("{chromosome:?}"); // Outputs ndarray ["1", "1", "5", "Y"]
     /// # use bed_reader::BedErrorPlus;
     /// # Ok::<(), Box<BedErrorPlus>>(())
-    pub fn chromosome(&mut self) -> Result<&nd::Array1<String>, Box<BedErrorPlus>> {
-        self.unlazy_bim::<String>(
-            self.metadata.chromosome.is_none(),
-            MetadataFields::Chromosome,
-            "chromosome",
-        )?;
-        Ok(self.metadata.chromosome.as_ref().unwrap()) //unwrap always works because of lazy_bim
+    pub fn chromosome(&self) -> Result<&nd::Array1<String>, Box<BedErrorPlus>> {
+        self.field(MetadataFields::Chromosome, |metadata| {
+            metadata.chromosome.as_ref()
+        })
     }
 
     /// SNP id of each SNP (variant)
@@ -2523,14 +3694,13 @@ impl Bed {
     /// use bed_reader::assert_eq_nan;
     ///
     /// let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::new(file_name)?;
+    /// let bed = Bed::new(file_name)?;
     /// let sid = bed.sid()?;
     /// println!("{sid:?}"); // Outputs ndarray "sid1", "sid2", "sid3", "sid4"]
     /// # use bed_reader::BedErrorPlus;
     /// # Ok::<(), Box<BedErrorPlus>>(())
-    pub fn sid(&mut self) -> Result<&nd::Array1<String>, Box<BedErrorPlus>> {
-        self.unlazy_bim::<String>(self.metadata.sid.is_none(), MetadataFields::Sid, "sid")?;
-        Ok(self.metadata.sid.as_ref().unwrap()) //unwrap always works because of lazy_bim
+    pub fn sid(&self) -> Result<&nd::Array1<String>, Box<BedErrorPlus>> {
+        self.field(MetadataFields::Sid, |metadata| metadata.sid.as_ref())
     }
 
     /// Centimorgan position of each SNP (variant)
@@ -2548,18 +3718,15 @@ impl Bed {
     /// use bed_reader::assert_eq_nan;
     ///
     /// let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::new(file_name)?;
+    /// let bed = Bed::new(file_name)?;
     /// let cm_position = bed.cm_position()?;
     /// println!("{cm_position:?}"); // Outputs ndarray [100.4, 2000.5, 4000.7, 7000.9]
     /// # use bed_reader::BedErrorPlus;
     /// # Ok::<(), Box<BedErrorPlus>>(())
-    pub fn cm_position(&mut self) -> Result<&nd::Array1<f32>, Box<BedErrorPlus>> {
-        self.unlazy_bim::<String>(
-            self.metadata.cm_position.is_none(),
-            MetadataFields::CmPosition,
-            "cm_position",
-        )?;
-        Ok(self.metadata.cm_position.as_ref().unwrap()) //unwrap always works because of lazy_bim
+    pub fn cm_position(&self) -> Result<&nd::Array1<f32>, Box<BedErrorPlus>> {
+        self.field(MetadataFields::CmPosition, |metadata| {
+            metadata.cm_position.as_ref()
+        })
     }
 
     /// Base-pair position of each SNP (variant)
@@ -2577,18 +3744,15 @@ impl Bed {
     /// use bed_reader::assert_eq_nan;
     ///
     /// let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::new(file_name)?;
+    /// let bed = Bed::new(file_name)?;
     /// let bp_position = bed.bp_position()?;
     /// println!("{bp_position:?}"); // Outputs ndarray [1, 100, 1000, 1004]
     /// # use bed_reader::BedErrorPlus;
     /// # Ok::<(), Box<BedErrorPlus>>(())
-    pub fn bp_position(&mut self) -> Result<&nd::Array1<i32>, Box<BedErrorPlus>> {
-        self.unlazy_bim::<String>(
-            self.metadata.bp_position.is_none(),
-            MetadataFields::BpPosition,
-            "bp_position",
-        )?;
-        Ok(self.metadata.bp_position.as_ref().unwrap()) //unwrap always works because of lazy_bim
+    pub fn bp_position(&self) -> Result<&nd::Array1<i32>, Box<BedErrorPlus>> {
+        self.field(MetadataFields::BpPosition, |metadata| {
+            metadata.bp_position.as_ref()
+        })
     }
 
     /// First allele of each SNP (variant)
@@ -2606,18 +3770,15 @@ impl Bed {
     /// use bed_reader::assert_eq_nan;
     ///
     /// let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::new(file_name)?;
+    /// let bed = Bed::new(file_name)?;
     /// let allele_1 = bed.allele_1()?;
     /// println!("{allele_1:?}"); // Outputs ndarray ["A", "T", "A", "T"]
     /// # use bed_reader::BedErrorPlus;
     /// # Ok::<(), Box<BedErrorPlus>>(())
-    pub fn allele_1(&mut self) -> Result<&nd::Array1<String>, Box<BedErrorPlus>> {
-        self.unlazy_bim::<String>(
-            self.metadata.allele_1.is_none(),
-            MetadataFields::Allele1,
-            "allele_1",
-        )?;
-        Ok(self.metadata.allele_1.as_ref().unwrap()) //unwrap always works because of lazy_bim
+    pub fn allele_1(&self) -> Result<&nd::Array1<String>, Box<BedErrorPlus>> {
+        self.field(MetadataFields::Allele1, |metadata| {
+            metadata.allele_1.as_ref()
+        })
     }
 
     /// Second allele of each SNP (variant)
@@ -2635,18 +3796,15 @@ impl Bed {
     /// use bed_reader::assert_eq_nan;
     ///
     /// let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::new(file_name)?;
+    /// let bed = Bed::new(file_name)?;
     /// let allele_2 = bed.allele_2()?;
     /// println!("{allele_2:?}"); // Outputs ndarray ["A", "C", "C", "G"]
     /// # use bed_reader::BedErrorPlus;
     /// # Ok::<(), Box<BedErrorPlus>>(())
-    pub fn allele_2(&mut self) -> Result<&nd::Array1<String>, Box<BedErrorPlus>> {
-        self.unlazy_bim::<String>(
-            self.metadata.allele_2.is_none(),
-            MetadataFields::Allele2,
-            "allele_2",
-        )?;
-        Ok(self.metadata.allele_2.as_ref().unwrap()) //unwrap always works because of lazy_bim
+    pub fn allele_2(&self) -> Result<&nd::Array1<String>, Box<BedErrorPlus>> {
+        self.field(MetadataFields::Allele2, |metadata| {
+            metadata.allele_2.as_ref()
+        })
     }
 
     /// [`Metadata`](struct.Metadata.html) for this dataset, for example, the individual (sample) Ids.
@@ -2661,16 +3819,71 @@ impl Bed {
     /// use bed_reader::{Bed, sample_bed_file};
     ///
     /// let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::new(file_name)?;
+    /// let bed = Bed::new(file_name)?;
     /// let metadata = bed.metadata()?;
     /// println!("{0:?}", metadata.iid()); // Outputs Some(["iid1", "iid2", "iid3"] ...)
     /// println!("{0:?}", metadata.sid()); // Outputs Some(["sid1", "sid2", "sid3", "sid4"] ...)
     /// # use bed_reader::BedErrorPlus;
     /// # Ok::<(), Box<BedErrorPlus>>(())
-    pub fn metadata(&mut self) -> Result<Metadata, Box<BedErrorPlus>> {
+    pub fn metadata(&self) -> Result<Metadata, Box<BedErrorPlus>> {
+        if let Some(latest) = self.latest() {
+            return latest.metadata();
+        }
         self.fam()?;
         self.bim()?;
-        Ok(self.metadata.clone())
+        self.sid_annotated()?;
+        Ok(self.metadata_loaded())
+    }
+
+    /// The [`Metadata`](struct.Metadata.html) already known, without reading the .fam or .bim file.
+    ///
+    /// Fields given to the [`BedBuilder`](struct.BedBuilder.html) or already read are `Some`;
+    /// the others are `None`. Unlike [`metadata`](struct.Bed.html#method.metadata), this never
+    /// does file IO, so it lets callers see what is known before deciding to pay for reading.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("loaded.bed");
+    /// WriteOptions::builder(&output_file)
+    ///     .iid(["i1", "i2"])
+    ///     .write(&nd::array![[0, 1], [2, 0]])?;
+    ///
+    /// let bed = Bed::builder(&output_file).sid(["s1", "s2"]).build()?;
+    /// assert!(bed.metadata_loaded().iid().is_none());
+    /// assert!(bed.metadata_loaded().sid().is_some());
+    ///
+    /// bed.iid()?; // reads the .fam file
+    /// assert!(bed.metadata_loaded().iid().is_some());
+    /// assert!(bed.metadata_loaded().chromosome().is_none());
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[must_use]
+    pub fn metadata_loaded(&self) -> Metadata {
+        if let Some(latest) = self.latest() {
+            return latest.metadata_loaded();
+        }
+        let mut metadata_builder = Metadata::builder();
+        for loaded in [self.fam_loaded.get(), self.bim_loaded.get()]
+            .into_iter()
+            .flatten()
+        {
+            metadata_builder.metadata(&loaded.metadata);
+            for parsed in loaded.parsed.values().filter_map(OnceLock::get) {
+                metadata_builder.metadata(parsed);
+            }
+        }
+        if let Some(sid_annotated) = self.sid_annotated.get() {
+            metadata_builder.metadata(sid_annotated);
+        }
+        metadata_builder
+            .metadata(&self.metadata)
+            .build_no_file_check()
+            .unwrap() // unwrap is ok because nothing can go wrong
     }
 
     /// Return the path of the .bed file.
@@ -2679,28 +3892,157 @@ impl Bed {
         &self.path
     }
 
+    /// Notes on any count mismatches resolved by a [`CountMismatchPolicy`](enum.CountMismatchPolicy.html).
+    ///
+    /// > See [`BedBuilder::count_mismatch_policy`](struct.BedBuilder.html#method.count_mismatch_policy) for an example.
+    #[must_use]
+    pub fn count_mismatch_notes(&self) -> Vec<String> {
+        if let Some(latest) = self.latest() {
+            return latest.count_mismatch_notes();
+        }
+        let mut notes: Vec<_> = [self.fam_loaded.get(), self.bim_loaded.get()]
+            .into_iter()
+            .flatten()
+            .filter_map(|loaded| loaded.note.clone())
+            .collect();
+        // In the order the mismatches were found
+        notes.sort_by_key(|(order, _)| *order);
+        notes.into_iter().map(|(_, note)| note).collect()
+    }
+
+    /// Attach a named per-SNP (variant) score array, for example, imputation INFO scores.
+    ///
+    /// The array must have one value per SNP. Attaching a second array with the same name replaces the first.
+    /// Select SNPs by score with [`ReadOptionsBuilder::score_at_least`](struct.ReadOptionsBuilder.html#method.score_at_least).
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, ReadOptions, sample_bed_file};
+    ///
+    /// let mut bed = Bed::new(sample_bed_file("small.bed")?)?;
+    /// bed.attach_scores("info", nd::array![0.95, 0.4, 0.81, f64::NAN])?;
+    /// let val = ReadOptions::builder()
+    ///     .score_at_least("info", 0.8)
+    ///     .i8()
+    ///     .read(&mut bed)?;
+    /// assert_eq!(val, nd::array![[1, -127], [2, -127], [0, 2]]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[anyinput]
+    pub fn attach_scores(
+        &mut self,
+        name: AnyString,
+        scores: nd::Array1<f64>,
+    ) -> Result<(), Box<BedErrorPlus>> {
+        self.settle();
+        let sid_count = self.sid_count()?;
+        if scores.len() != sid_count {
+            Err(BedError::InconsistentCount(
+                name.to_string(),
+                sid_count,
+                scores.len(),
+            ))?;
+        }
+        self.scores.insert(name.to_string(), scores);
+        Ok(())
+    }
+
+    /// Return the per-SNP (variant) score array attached with the given name, if any.
+    ///
+    /// > See [`Bed::attach_scores`](struct.Bed.html#method.attach_scores) for an example.
+    #[must_use]
+    pub fn scores(&self, name: &str) -> Option<&nd::Array1<f64>> {
+        if let Some(latest) = self.latest() {
+            return latest.scores(name);
+        }
+        self.scores.get(name)
+    }
+
+    // Resolve the sid index, keeping only the SNPs that pass every score filter and the SNP
+    // filter, and are in a region.
+    fn filter_sids<'a, TVal: BedVal>(
+        &self,
+        sid_count: usize,
+        read_options: &'a ReadOptions<TVal>,
+    ) -> Result<Hold<'a>, Box<BedErrorPlus>> {
+        let sid_hold = self.filter_by_scores(
+            Hold::new(&read_options.sid_index, sid_count)?,
+            sid_count,
+            &read_options.score_filters,
+        )?;
+        let sid_hold = match &read_options.sid_filter {
+            Some(sid_filter) => {
+                let mask = sid_filter.mask(self.sid_filter_stats()?);
+                Hold::Copy(filter_by_mask(sid_hold.as_ref(), sid_count, &mask)?)
+            }
+            None => sid_hold,
+        };
+        let Some(regions) = &read_options.regions else {
+            return Ok(sid_hold);
+        };
+        Ok(Hold::Copy(regions.filter(
+            sid_hold.as_ref(),
+            sid_count,
+            self.chromosome()?,
+            self.bp_position()?,
+        )?))
+    }
+
+    // Keep only the SNPs whose attached scores pass every score filter.
+    fn filter_by_scores<'a>(
+        &self,
+        sid_hold: Hold<'a>,
+        sid_count: usize,
+        score_filters: &[(String, f64)],
+    ) -> Result<Hold<'a>, Box<BedErrorPlus>> {
+        if score_filters.is_empty() {
+            return Ok(sid_hold);
+        }
+        let filters = score_filters
+            .iter()
+            .map(|(name, min)| match self.scores.get(name) {
+                // The SNP count may have changed since, if the files were refreshed
+                Some(scores) if scores.len() != sid_count => Err(BedError::InconsistentCount(
+                    name.clone(),
+                    sid_count,
+                    scores.len(),
+                )),
+                Some(scores) => Ok((scores, *min)),
+                None => Err(BedError::UnknownScore(name.clone())),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let lower_sid_count = -(sid_count as isize);
+        let upper_sid_count: isize = (sid_count as isize) - 1;
+        let mut sid_index = Vec::new();
+        for &sid_i_signed in sid_hold.as_ref() {
+            let sid_i = if (0..=upper_sid_count).contains(&sid_i_signed) {
+                sid_i_signed as usize
+            } else if (lower_sid_count..=-1).contains(&sid_i_signed) {
+                sid_count - ((-sid_i_signed) as usize)
+            } else {
+                Err(BedError::SidIndexTooBig(sid_i_signed))?
+            };
+            // NaN scores never pass
+            if filters.iter().all(|(scores, min)| scores[sid_i] >= *min) {
+                sid_index.push(sid_i_signed);
+            }
+        }
+        Ok(Hold::Copy(sid_index))
+    }
+
     /// Return the path of the .fam file.
-    pub fn fam_path(&mut self) -> PathBuf {
-        // We need to clone the path because self might mutate later
-        if let Some(path) = &self.fam_path {
-            path.clone()
-        } else {
-            let path = to_metadata_path(&self.path, &self.fam_path, "fam");
-            self.fam_path = Some(path.clone());
-            path
-        }
+    #[must_use]
+    pub fn fam_path(&self) -> PathBuf {
+        to_metadata_path(&self.path, &self.fam_path, "fam")
     }
 
     /// Return the path of the .bim file.
-    pub fn bim_path(&mut self) -> PathBuf {
-        // We need to clone the path because self might mutate later
-        if let Some(path) = &self.bim_path {
-            path.clone()
-        } else {
-            let path = to_metadata_path(&self.path, &self.bim_path, "bim");
-            self.bim_path = Some(path.clone());
-            path
-        }
+    #[must_use]
+    pub fn bim_path(&self) -> PathBuf {
+        to_metadata_path(&self.path, &self.bim_path, "bim")
     }
 
     /// Read genotype data.
@@ -2720,7 +4062,7 @@ impl Bed {
     /// use bed_reader::assert_eq_nan;
     ///
     /// let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::new(file_name)?;
+    /// let bed = Bed::new(file_name)?;
     /// let val = bed.read::<f64>()?;
     ///
     /// assert_eq_nan(
@@ -2745,7 +4087,7 @@ impl Bed {
     /// # use bed_reader::BedErrorPlus;
     /// # Ok::<(), Box<BedErrorPlus>>(())
     /// ```    
-    pub fn read<TVal: BedVal>(&mut self) -> Result<nd::Array2<TVal>, Box<BedErrorPlus>> {
+    pub fn read<TVal: BedVal>(&self) -> Result<nd::Array2<TVal>, Box<BedErrorPlus>> {
         let read_options = ReadOptions::<TVal>::builder().build()?;
         self.read_with_options(&read_options)
     }
@@ -2771,7 +4113,7 @@ impl Bed {
     ///
     /// // Read the SNPs indexed by 2.
     /// let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::new(file_name)?;
+    /// let bed = Bed::new(file_name)?;
     /// let read_options = ReadOptions::builder().sid_index(2).build()?;
     /// let mut val = nd::Array2::<f64>::default((3, 1));
     /// bed.read_and_fill_with_options(&mut val.view_mut(), &read_options)?;
@@ -2781,43 +4123,194 @@ impl Bed {
     /// # Ok::<(), Box<BedErrorPlus>>(())
     /// ```  
     pub fn read_and_fill_with_options<TVal: BedVal>(
-        &mut self,
+        &self,
         val: &mut nd::ArrayViewMut2<'_, TVal>, //mutable slices additionally allow to modify elements. But slices cannot grow - they are just a view into some vector.,
         read_options: &ReadOptions<TVal>,
     ) -> Result<(), Box<BedErrorPlus>> {
+        if let Some(fresh) = self.check_watch()? {
+            return fresh.read_and_fill_with_options(val, read_options);
+        }
         let iid_count = self.iid_count()?;
         let sid_count = self.sid_count()?;
 
         let num_threads = compute_num_threads(read_options.num_threads)?;
+        #[cfg(feature = "mmap")]
+        let mapping = match read_options.read_strategy {
+            ReadStrategy::Sequential if self.is_mmap => Some(self.mapping()?),
+            ReadStrategy::Mmap => Some(self.mapping()?),
+            _ => None,
+        };
+        #[cfg(feature = "mmap")]
+        let mapping = mapping.as_deref().map(|mapping| &mapping[..]);
+        #[cfg(not(feature = "mmap"))]
+        let mapping = None;
 
         // If we already have a Vec<isize>, reference it. If we don't, create one and reference it.
-        let iid_hold = Hold::new(&read_options.iid_index, iid_count)?;
+        let iid_hold = self.filter_iids(iid_count, read_options)?;
         let iid_index = iid_hold.as_ref();
-        let sid_hold = Hold::new(&read_options.sid_index, sid_count)?;
+        let sid_hold = self.filter_sids(sid_count, read_options)?;
         let sid_index = sid_hold.as_ref();
+        self.fill_resolved(
+            val,
+            read_options,
+            iid_count,
+            sid_count,
+            mapping,
+            iid_index,
+            sid_index,
+            num_threads,
+        )
+    }
+
+    // Fill val, given the counts, the memory mapping, if any, and the resolved indexes.
+    // Needs only &self, so shared reads use it, too.
+    #[allow(clippy::too_many_arguments)]
+    fn fill_resolved<TVal: BedVal>(
+        &self,
+        val: &mut nd::ArrayViewMut2<'_, TVal>,
+        read_options: &ReadOptions<TVal>,
+        iid_count: usize,
+        sid_count: usize,
+        mapping: Option<&[u8]>,
+        iid_index: &[isize],
+        sid_index: &[isize],
+        num_threads: usize,
+    ) -> Result<(), Box<BedErrorPlus>> {
+        check_count_a1_mask(read_options.count_a1_mask.as_ref(), sid_count)?;
 
         let dim = val.dim();
-        if dim != (iid_index.len(), sid_index.len()) {
+        let expected_dim = read_options.output_dim(iid_index.len(), sid_index.len());
+        if dim != expected_dim {
             Err(BedError::InvalidShape(
-                iid_index.len(),
-                sid_index.len(),
+                expected_dim.0,
+                expected_dim.1,
                 dim.0,
                 dim.1,
             ))?;
         }
 
-        read_no_alloc(
-            &self.path,
-            iid_count,
-            sid_count,
-            read_options.is_a1_counted,
-            iid_index,
-            sid_index,
-            read_options.missing_value,
-            num_threads,
-            &mut val.view_mut(),
-        )?;
+        // A transposed output is filled through a view with its axes reversed.
+        let mut val = if read_options.is_transposed {
+            val.view_mut().reversed_axes()
+        } else {
+            val.view_mut()
+        };
+        if let Some(progress) = &read_options.progress {
+            // Read a chunk of SNPs at a time, reporting after each.
+            let total = sid_index.len();
+            let chunk_sids = (PROGRESS_CHUNK_VALUES / iid_index.len().max(1)).max(1);
+            progress.report(0, total);
+            for start in (0..total).step_by(chunk_sids) {
+                let end = (start + chunk_sids).min(total);
+                self.fill_columns(
+                    &mut val.slice_mut(nd::s![.., start..end]),
+                    read_options,
+                    iid_count,
+                    sid_count,
+                    mapping,
+                    iid_index,
+                    &sid_index[start..end],
+                    num_threads,
+                )?;
+                progress.report(end, total);
+            }
+        } else {
+            self.fill_columns(
+                &mut val,
+                read_options,
+                iid_count,
+                sid_count,
+                mapping,
+                iid_index,
+                sid_index,
+                num_threads,
+            )?;
+        }
+        Ok(())
+    }
 
+    // Fill val, in (iid, sid) order, with the selected SNPs, using the read strategy.
+    #[allow(clippy::too_many_arguments)]
+    #[cfg_attr(not(feature = "mmap"), allow(unused_variables))]
+    fn fill_columns<TVal: BedVal>(
+        &self,
+        val: &mut nd::ArrayViewMut2<'_, TVal>,
+        read_options: &ReadOptions<TVal>,
+        iid_count: usize,
+        sid_count: usize,
+        mapping: Option<&[u8]>,
+        iid_index: &[isize],
+        sid_index: &[isize],
+        num_threads: usize,
+    ) -> Result<(), Box<BedErrorPlus>> {
+        if let Some(source) = &self.byte_source {
+            return self.read_source(
+                source.as_ref(),
+                self.layout_iid_count().unwrap_or(iid_count),
+                self.layout_sid_count().unwrap_or(sid_count),
+                read_options,
+                iid_index,
+                sid_index,
+                val,
+            );
+        }
+        #[cfg(feature = "mmap")]
+        if let Some(mapping) = mapping {
+            return mmap::read_mmap(
+                &self.path,
+                mapping,
+                self.layout_iid_count().unwrap_or(iid_count),
+                self.layout_sid_count().unwrap_or(sid_count),
+                read_options.is_a1_counted,
+                read_options.count_a1_mask.as_ref(),
+                iid_index,
+                sid_index,
+                read_options.missing_value,
+                num_threads,
+                val,
+            );
+        }
+        if read_options.read_strategy == ReadStrategy::Scan {
+            read_strategy::read_scan(
+                &self.path,
+                self.layout_iid_count().unwrap_or(iid_count),
+                self.layout_sid_count().unwrap_or(sid_count),
+                read_options.is_a1_counted,
+                read_options.count_a1_mask.as_ref(),
+                iid_index,
+                sid_index,
+                read_options.missing_value,
+                num_threads,
+                val,
+            )?;
+        } else if read_options.read_strategy == ReadStrategy::BatchedPread {
+            read_strategy::read_batched_pread(
+                &self.path,
+                self.layout_iid_count().unwrap_or(iid_count),
+                self.layout_sid_count().unwrap_or(sid_count),
+                read_options.is_a1_counted,
+                read_options.count_a1_mask.as_ref(),
+                iid_index,
+                sid_index,
+                read_options.missing_value,
+                num_threads,
+                compute_max_concurrent_requests(read_options.max_concurrent_requests)?,
+                val,
+            )?;
+        } else {
+            read_no_alloc(
+                &self.path,
+                self.layout_iid_count().unwrap_or(iid_count),
+                self.layout_sid_count().unwrap_or(sid_count),
+                read_options.is_a1_counted,
+                read_options.count_a1_mask.as_ref(),
+                iid_index,
+                sid_index,
+                read_options.missing_value,
+                num_threads,
+                val,
+            )?;
+        }
         Ok(())
     }
 
@@ -2837,7 +4330,7 @@ impl Bed {
     /// use bed_reader::assert_eq_nan;
     ///
     /// let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::new(file_name)?;
+    /// let bed = Bed::new(file_name)?;
     /// let mut val = nd::Array2::<i8>::default(bed.dim()?);
     /// bed.read_and_fill(&mut val.view_mut())?;
     ///
@@ -2853,7 +4346,7 @@ impl Bed {
     /// # Ok::<(), Box<BedErrorPlus>>(())
     /// ```
     pub fn read_and_fill<TVal: BedVal>(
-        &mut self,
+        &self,
         val: &mut nd::ArrayViewMut2<'_, TVal>, //mutable slices additionally allow to modify elements. But slices cannot grow - they are just a view into some vector.,
     ) -> Result<(), Box<BedErrorPlus>> {
         let read_options = ReadOptions::<TVal>::builder().build()?;
@@ -2877,7 +4370,7 @@ impl Bed {
     ///
     /// // Read the SNPs indexed by 2.
     /// let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::new(file_name)?;
+    /// let bed = Bed::new(file_name)?;
     /// let read_options = ReadOptions::builder().sid_index(2).f64().build()?;
     /// let val = bed.read_with_options(&read_options)?;
     ///
@@ -2886,14 +4379,31 @@ impl Bed {
     /// # Ok::<(), Box<BedErrorPlus>>(())
     /// ```  
     pub fn read_with_options<TVal: BedVal>(
-        &mut self,
+        &self,
         read_options: &ReadOptions<TVal>,
     ) -> Result<nd::Array2<TVal>, Box<BedErrorPlus>> {
+        if let Some(fresh) = self.check_watch()? {
+            return fresh.read_with_options(read_options);
+        }
         let iid_count_in = self.iid_count()?;
         let sid_count_in = self.sid_count()?;
-        let iid_count_out = read_options.iid_index.len(iid_count_in)?;
-        let sid_count_out = read_options.sid_index.len(sid_count_in)?;
-        let shape = ShapeBuilder::set_f((iid_count_out, sid_count_out), read_options.is_f);
+        let iid_count_out = if read_options.iid_where.is_none() {
+            read_options.iid_index.len(iid_count_in)?
+        } else {
+            self.filter_iids(iid_count_in, read_options)?.as_ref().len()
+        };
+        let sid_count_out = if read_options.score_filters.is_empty()
+            && read_options.sid_filter.is_none()
+            && read_options.regions.is_none()
+        {
+            read_options.sid_index.len(sid_count_in)?
+        } else {
+            self.filter_sids(sid_count_in, read_options)?.as_ref().len()
+        };
+        let shape = ShapeBuilder::set_f(
+            read_options.output_dim(iid_count_out, sid_count_out),
+            read_options.is_f,
+        );
         let mut val = nd::Array2::<TVal>::default(shape);
 
         self.read_and_fill_with_options(&mut val.view_mut(), read_options)?;
@@ -2922,12 +4432,12 @@ impl Bed {
     ///
     /// // If we then read the new file and list the chromosome property,
     /// // it is an array of zeros, the default chromosome value.
-    /// let mut bed2 = Bed::new(&output_file)?;
+    /// let bed2 = Bed::new(&output_file)?;
     /// println!("{:?}", bed2.chromosome()?); // Outputs ndarray ["0", "0", "0", "0"]
     /// # use bed_reader::BedErrorPlus;
     /// # Ok::<(), Box<BedErrorPlus>>(())
     /// ```
-    pub fn write<S: nd::Data<Elem = TVal>, TVal: BedVal>(
+    pub fn write<S: nd::Data<Elem = TVal>, TVal: BedVal + Into<f64>>(
         val: &nd::ArrayBase<S, nd::Ix2>,
         path: &Path,
     ) -> Result<(), Box<BedErrorPlus>> {
@@ -2967,7 +4477,40 @@ impl Bed {
     ) -> Result<(), Box<BedErrorPlus>>
     where
         S: nd::Data<Elem = TVal>,
-        TVal: BedVal,
+        TVal: BedVal + Into<f64>,
+    {
+        Bed::write_with_summary(val, write_options)?;
+        Ok(())
+    }
+
+    /// Like [`Bed::write_with_options`](struct.Bed.html#method.write_with_options), but also
+    /// returns a [`WriteSummary`](struct.WriteSummary.html) with genotype and missing counts,
+    /// bytes written, timing, and the number of values snapped by
+    /// [`WriteOptionsBuilder::tolerance`](struct.WriteOptionsBuilder.html#method.tolerance).
+    ///
+    /// > See [`WriteSummary`](struct.WriteSummary.html) for an example.
+    pub fn write_with_summary<S, TVal>(
+        val: &nd::ArrayBase<S, nd::Ix2>,
+        write_options: &WriteOptions<TVal>,
+    ) -> Result<WriteSummary, Box<BedErrorPlus>>
+    where
+        S: nd::Data<Elem = TVal>,
+        TVal: BedVal + Into<f64>,
+    {
+        let result = Bed::write_unlogged(val, write_options);
+        if let Some(log) = &write_options.log {
+            log.log_write(write_options, &result)?;
+        }
+        result
+    }
+
+    fn write_unlogged<S, TVal>(
+        val: &nd::ArrayBase<S, nd::Ix2>,
+        write_options: &WriteOptions<TVal>,
+    ) -> Result<WriteSummary, Box<BedErrorPlus>>
+    where
+        S: nd::Data<Elem = TVal>,
+        TVal: BedVal + Into<f64>,
     {
         let (iid_count, sid_count) = val.dim();
         if iid_count != write_options.iid_count() {
@@ -2985,72 +4528,81 @@ impl Bed {
             ))?;
         }
 
+        if let Some((sid_index, write_options)) = write_options.dedup_for_write()? {
+            return if sid_index.len() == sid_count {
+                Bed::write_unlogged(val, &write_options)
+            } else {
+                Bed::write_unlogged(&val.select(nd::Axis(1), &sid_index), &write_options)
+            };
+        }
+
+        // Apply the id policy before writing anything, so that strict checks fail early.
+        let policy_metadata = write_options
+            .id_policy
+            .map(|id_policy| write_options.metadata.with_id_policy(id_policy))
+            .transpose()?;
+        let metadata = policy_metadata.as_ref().unwrap_or(&write_options.metadata);
+
         let num_threads = compute_num_threads(write_options.num_threads)?;
-        write_val(
+        let summary = write_val(
             &write_options.path,
             val,
             write_options.is_a1_counted,
             write_options.missing_value,
+            write_options.missing_if.as_ref(),
+            write_options.tolerance,
+            write_options.validate,
             num_threads,
+            write_options.progress.as_ref(),
         )?;
 
-        if !write_options.skip_fam() {
-            if let Err(e) = write_options.metadata.write_fam(write_options.fam_path()) {
-                // Clean up the file
-                let _ = fs::remove_file(&write_options.fam_path);
-                Err(e)?;
-            }
-        }
-
-        if !write_options.skip_bim() {
-            if let Err(e) = write_options.metadata.write_bim(write_options.bim_path()) {
-                // Clean up the file
-                let _ = fs::remove_file(&write_options.bim_path);
-                Err(e)?;
-            }
-        }
+        write_metadata_files(write_options, metadata)?;
 
-        Ok(())
+        Ok(summary)
     }
 
-    fn unlazy_fam<T: FromStringArray<T>>(
-        &mut self,
-        is_none: bool,
-        field_index: MetadataFields,
-        name: &str,
-    ) -> Result<(), Box<BedErrorPlus>> {
-        if self.skip_set.contains(&field_index) {
-            Err(BedError::CannotUseSkippedMetadata(name.to_string()))?;
+    // A metadata field: the one given to the builder, or else the one read, once, from the
+    // .fam or .bim file.
+    fn field<T>(
+        &self,
+        field: MetadataFields,
+        get: fn(&Metadata) -> Option<&Arc<nd::Array1<T>>>,
+    ) -> Result<&nd::Array1<T>, Box<BedErrorPlus>> {
+        if let Some(latest) = self.latest() {
+            return latest.field(field, get);
         }
-        if is_none {
-            self.fam()?;
+        if self.skip_set.contains(&field) {
+            Err(BedError::CannotUseSkippedMetadata(field.name().to_string()))?;
         }
-        Ok(())
-    }
-
-    fn unlazy_bim<T: FromStringArray<T>>(
-        &mut self,
-        is_none: bool,
-        field_index: MetadataFields,
-        name: &str,
-    ) -> Result<(), Box<BedErrorPlus>> {
-        if self.skip_set.contains(&field_index) {
-            Err(BedError::CannotUseSkippedMetadata(name.to_string()))?;
+        if let Some(values) = get(&self.metadata) {
+            return Ok(values);
         }
-        if is_none {
-            self.bim()?;
+        let loaded = if FAM_FIELDS.contains(&field) {
+            self.fam()?
+        } else {
+            self.bim()?
+        };
+        if let Some(values) = get(&loaded.metadata) {
+            return Ok(values);
         }
-        Ok(())
+        // unwrap always works because every field read is either typed or, if raw, parsed here
+        Ok(get(loaded.parse_raw_field(field)?).unwrap())
     }
 
-    fn fam(&mut self) -> Result<(), Box<BedErrorPlus>> {
-        let fam_path = self.fam_path();
-
-        let (metadata, count) = self.metadata.read_fam(fam_path, &self.skip_set)?;
-        self.metadata = metadata;
+    // What reading the .fam file found, read on first use.
+    pub(crate) fn fam(&self) -> Result<&Loaded, Box<BedErrorPlus>> {
+        if let Some(fam) = self.fam_loaded.get() {
+            return Ok(fam);
+        }
+        if self.max_dims.is_some() {
+            // Check the line count before reading the lines
+            self.iid_count()?;
+        }
 
-        match self.iid_count {
-            Some(iid_count) => {
+        let (metadata, count, raw_fields) = self.read_fam_fields()?;
+        let fam = if self.count_mismatch_policy == CountMismatchPolicy::Error {
+            metadata.check_counts(Some(count), None)?;
+            if let Some(&iid_count) = self.iid_count.get() {
                 if iid_count != count {
                     Err(BedError::InconsistentCount(
                         "iid".to_string(),
@@ -3059,21 +4611,37 @@ impl Bed {
                     ))?;
                 }
             }
-            None => {
-                self.iid_count = Some(count);
+            Loaded::new(metadata, count, raw_fields)
+        } else {
+            match self.iid_count.get() {
+                Some(&iid_count) if iid_count != count => {
+                    let (count, layout_count, note) =
+                        self.resolve_count_mismatch("iid", iid_count, count);
+                    let order = usize::from(self.bim_loaded.get().is_some_and(Loaded::has_note));
+                    let mut fam = Loaded::new(metadata.resize_fam(count), count, raw_fields);
+                    (fam.layout_count, fam.note) = (Some(layout_count), Some((order, note)));
+                    fam
+                }
+                _ => Loaded::new(metadata.resize_fam(count), count, raw_fields),
             }
-        }
-        Ok(())
+        };
+        Ok(self.fam_loaded.get_or_init(|| fam))
     }
 
-    fn bim(&mut self) -> Result<(), Box<BedErrorPlus>> {
-        let bim_path = self.bim_path();
-
-        let (metadata, count) = self.metadata.read_bim(bim_path, &self.skip_set)?;
-        self.metadata = metadata;
+    // What reading the .bim file found, read on first use.
+    pub(crate) fn bim(&self) -> Result<&Loaded, Box<BedErrorPlus>> {
+        if let Some(bim) = self.bim_loaded.get() {
+            return Ok(bim);
+        }
+        if self.max_dims.is_some() {
+            // Check the line count before reading the lines
+            self.sid_count()?;
+        }
 
-        match self.sid_count {
-            Some(sid_count) => {
+        let (metadata, count, raw_fields) = self.read_bim_fields()?;
+        let bim = if self.count_mismatch_policy == CountMismatchPolicy::Error {
+            metadata.check_counts(None, Some(count))?;
+            if let Some(&sid_count) = self.sid_count.get() {
                 if sid_count != count {
                     Err(BedError::InconsistentCount(
                         "sid".to_string(),
@@ -3082,12 +4650,180 @@ impl Bed {
                     ))?;
                 }
             }
-            None => {
-                self.sid_count = Some(count);
+            Loaded::new(metadata, count, raw_fields)
+        } else {
+            match self.sid_count.get() {
+                Some(&sid_count) if sid_count != count => {
+                    let (count, layout_count, note) =
+                        self.resolve_count_mismatch("sid", sid_count, count);
+                    let order = usize::from(self.fam_loaded.get().is_some_and(Loaded::has_note));
+                    let mut bim = Loaded::new(metadata.resize_bim(count), count, raw_fields);
+                    (bim.layout_count, bim.note) = (Some(layout_count), Some((order, note)));
+                    bim
+                }
+                _ => Loaded::new(metadata.resize_bim(count), count, raw_fields),
             }
+        };
+        Ok(self.bim_loaded.get_or_init(|| bim))
+    }
+
+    // The number of individuals (samples) in the layout of the .bed file, if it differs from
+    // iid_count because of a CountMismatchPolicy.
+    pub(crate) fn layout_iid_count(&self) -> Option<usize> {
+        self.fam_loaded.get().and_then(|fam| fam.layout_count)
+    }
+
+    // The number of SNPs (variants) in the layout of the .bed file, if it differs from
+    // sid_count because of a CountMismatchPolicy.
+    pub(crate) fn layout_sid_count(&self) -> Option<usize> {
+        self.bim_loaded.get().and_then(|bim| bim.layout_count)
+    }
+
+    // Open the .bed file, check it, and return a reader positioned at the first SNP,
+    // with the bytes per SNP and the number of SNPs.
+    pub(crate) fn open_packed_columns(
+        &self,
+    ) -> Result<(BufReader<File>, usize, usize), Box<BedErrorPlus>> {
+        let iid_count = self.iid_count()?;
+        let sid_count = self.sid_count()?;
+        let iid_count = self.layout_iid_count().unwrap_or(iid_count);
+        let sid_count = self.layout_sid_count().unwrap_or(sid_count);
+
+        let (buf_reader, bytes_array) = open_and_check(&self.path)?;
+        if bytes_array[2] != 1 {
+            Err(BedError::NotSnpMajor(self.path.clone()))?;
+        }
+
+        let iid_count_div4_u64 = try_div_4(iid_count, sid_count)?;
+        let file_len = buf_reader.get_ref().metadata()?.len();
+        if file_len != iid_count_div4_u64 * (sid_count as u64) + CB_HEADER_U64 {
+            Err(BedError::IllFormed(self.path.clone()))?;
+        }
+        // "as" is safe because of the file-length check
+        Ok((buf_reader, iid_count_div4_u64 as usize, sid_count))
+    }
+
+    // Return an error if a count is more than its maximum from BedBuilder::max_dims.
+    fn check_max_dims(
+        &self,
+        iid_count: Option<usize>,
+        sid_count: Option<usize>,
+    ) -> Result<(), Box<BedErrorPlus>> {
+        let Some((iid_max, sid_max)) = self.max_dims else {
+            return Ok(());
+        };
+        if let Some(iid_count) = iid_count.filter(|&count| count > iid_max) {
+            Err(BedError::TooManyForMaxDims(
+                "iid".to_string(),
+                iid_count,
+                iid_max,
+            ))?;
+        }
+        if let Some(sid_count) = sid_count.filter(|&count| count > sid_max) {
+            Err(BedError::TooManyForMaxDims(
+                "sid".to_string(),
+                sid_count,
+                sid_max,
+            ))?;
         }
         Ok(())
     }
+
+    // Returns the count to use, the count that describes the layout of the .bed file, and a note.
+    fn resolve_count_mismatch(
+        &self,
+        prefix: &str,
+        expected: usize,
+        found: usize,
+    ) -> (usize, usize, String) {
+        let count = match self.count_mismatch_policy {
+            CountMismatchPolicy::TruncateToMin => expected.min(found),
+            CountMismatchPolicy::Error | CountMismatchPolicy::PadMetadata => expected,
+        };
+        let extension = if prefix == "iid" { "fam" } else { "bim" };
+        let note = format!(
+            "{prefix}: expected {expected}, found {found} in .{extension} file; using {count}"
+        );
+        (count, expected, note)
+    }
+}
+
+// What reading a .fam or .bim file found.
+#[derive(Clone, Debug)]
+pub(crate) struct Loaded {
+    // The known metadata, with the fields read from the file filled in.
+    metadata: Metadata,
+    count: usize,
+    // The count that describes the layout of the .bed file, when it differs from count
+    // because of a CountMismatchPolicy, and a note on the decision, after the number of
+    // notes made before it.
+    layout_count: Option<usize>,
+    note: Option<(usize, String)>,
+    // The text of raw fields and, for the numeric ones, their values, once parsed.
+    raw_fields: HashMap<MetadataFields, Arc<nd::Array1<String>>>,
+    parsed: HashMap<MetadataFields, OnceLock<Metadata>>,
+}
+
+impl Loaded {
+    // Raw text is truncated or padded to count.
+    fn new(
+        metadata: Metadata,
+        count: usize,
+        raw_fields: HashMap<MetadataFields, nd::Array1<String>>,
+    ) -> Loaded {
+        let raw_fields: HashMap<_, _> = raw_fields
+            .into_iter()
+            .map(|(field, values)| {
+                let values = (0..count)
+                    .map(|i| values.get(i).cloned().unwrap_or_else(|| "0".to_string()))
+                    .collect::<nd::Array1<String>>();
+                (field, Arc::new(values))
+            })
+            .collect();
+        let parsed = raw_fields
+            .keys()
+            .map(|&field| (field, OnceLock::new()))
+            .collect();
+        Loaded {
+            metadata,
+            count,
+            layout_count: None,
+            note: None,
+            raw_fields,
+            parsed,
+        }
+    }
+
+    pub(crate) fn raw_field(&self, field: MetadataFields) -> Option<&Arc<nd::Array1<String>>> {
+        self.raw_fields.get(&field)
+    }
+
+    fn has_note(&self) -> bool {
+        self.note.is_some()
+    }
+
+    // A raw numeric field, parsed from its text on first use, as the only field of a Metadata.
+    fn parse_raw_field(&self, field: MetadataFields) -> Result<&Metadata, Box<BedErrorPlus>> {
+        let Some(parsed) = self.parsed.get(&field) else {
+            Err(BedError::CannotUseSkippedMetadata(field.name().to_string()))?
+        };
+        if let Some(metadata) = parsed.get() {
+            return Ok(metadata);
+        }
+        let raw = self.raw_fields[&field].as_ref().clone();
+        let mut metadata = Metadata::new();
+        match field {
+            MetadataFields::Sex => metadata.sex = Some(Arc::new(i32::from_string_array(raw)?)),
+            MetadataFields::CmPosition => {
+                metadata.cm_position = Some(Arc::new(f32::from_string_array(raw)?));
+            }
+            MetadataFields::BpPosition => {
+                metadata.bp_position = Some(Arc::new(i32::from_string_array(raw)?));
+            }
+            _ => {}
+        }
+        Ok(parsed.get_or_init(|| metadata))
+    }
 }
 
 /// If we already have a Vec<isize> remember a reference to it.
@@ -3116,49 +4852,19 @@ impl Hold<'_> {
 }
 
 fn compute_num_threads(option_num_threads: Option<usize>) -> Result<usize, Box<BedErrorPlus>> {
-    let num_threads = if let Some(num_threads) = option_num_threads {
-        num_threads
-    } else if let Ok(num_threads) = env::var("BED_READER_NUM_THREADS") {
-        num_threads.parse::<usize>()?
-    } else if let Ok(num_threads) = env::var("NUM_THREADS") {
-        num_threads.parse::<usize>()?
-    } else {
-        0
-    };
-    Ok(num_threads)
+    global_config::resolve_num_threads(option_num_threads)
 }
 
-#[allow(clippy::unnecessary_wraps)]
 fn compute_max_concurrent_requests(
     option_max_concurrent_requests: Option<usize>,
 ) -> Result<usize, Box<BedErrorPlus>> {
-    let max_concurrent_requests =
-        if let Some(max_concurrent_requests) = option_max_concurrent_requests {
-            max_concurrent_requests
-        // } else if let Ok(num_threads) = env::var("BED_READER_NUM_THREADS") {
-        //     num_threads.parse::<usize>()?
-        // } else if let Ok(num_threads) = env::var("NUM_THREADS") {
-        //     num_threads.parse::<usize>()?
-        } else {
-            10
-        };
-    Ok(max_concurrent_requests)
+    global_config::resolve_max_concurrent_requests(option_max_concurrent_requests)
 }
 
-#[allow(clippy::unnecessary_wraps)]
 fn compute_max_chunk_bytes(
     option_max_chunk_bytes: Option<usize>,
 ) -> Result<usize, Box<BedErrorPlus>> {
-    let max_chunk_bytes = if let Some(max_chunk_bytes) = option_max_chunk_bytes {
-        max_chunk_bytes
-    // } else if let Ok(num_threads) = env::var("BED_READER_NUM_THREADS") {
-    //     num_threads.parse::<usize>()?
-    // } else if let Ok(num_threads) = env::var("NUM_THREADS") {
-    //     num_threads.parse::<usize>()?
-    } else {
-        8_000_000
-    };
-    Ok(max_chunk_bytes)
+    global_config::resolve_max_chunk_bytes(option_max_chunk_bytes)
 }
 
 impl Index {
@@ -3748,6 +5454,12 @@ impl From<()> for Index {
     }
 }
 
+impl From<&Index> for Index {
+    fn from(index: &Index) -> Index {
+        index.clone()
+    }
+}
+
 // See https://nullderef.com/blog/rust-parameters/
 
 /// Represents options for reading genotype data from a PLINK .bed file.
@@ -3944,6 +5656,37 @@ pub struct ReadOptions<TVal: BedVal> {
     #[builder(default = "true")]
     is_a1_counted: bool,
 
+    /// Sets, per SNP (variant) in the file, if allele 1 is counted -- Overrides
+    /// [`is_a1_counted`](struct.ReadOptionsBuilder.html#method.is_a1_counted) for the SNPs whose mask value differs.
+    ///
+    /// The mask has one entry per SNP in the file, not per SNP selected. It lets a harmonized
+    /// dataset, where only some SNPs need flipping, be read in one pass.
+    ///
+    /// In this example, we count allele 2 for the SNPs indexed by 1 and 3.
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, ReadOptions, sample_bed_file};
+    ///
+    /// let mut bed = Bed::new(sample_bed_file("small.bed")?)?;
+    /// let val = ReadOptions::builder()
+    ///     .count_a1_mask(nd::array![true, false, true, false])
+    ///     .i8()
+    ///     .read(&mut bed)?;
+    ///
+    /// assert_eq!(val, nd::array![[1, 2, -127, 2], [2, 2, -127, 0], [0, 1, 2, 2]]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[builder(default, setter(into, strip_option))]
+    count_a1_mask: Option<nd::Array1<bool>>,
+
+    /// Sets if the output array is transposed, with one row per SNP (variant) and
+    /// one column per individual (sample) -- Default is false.
+    ///
+    /// Also see [`transpose_output`](struct.ReadOptionsBuilder.html#method.transpose_output).
+    #[builder(default = "false")]
+    is_transposed: bool,
+
     /// Number of threads to use (defaults to all processors)
     ///
     /// Can also be set with an environment variable.
@@ -3975,7 +5718,8 @@ pub struct ReadOptions<TVal: BedVal> {
 
     // LATER: Allow this to be set with an environment variable.
     /// Maximum number of concurrent async requests (defaults to 10) --
-    /// Used by [`BedCloud`](struct.BedCloud.html).
+    /// Used by [`BedCloud`](struct.BedCloud.html) and [`ReadStrategy::BatchedPread`](enum.ReadStrategy.html#variant.BatchedPread),
+    /// and, by [`BedSet`](struct.BedSet.html), as the most files processed at once.
     ///
     /// In this example, we read using only request at a time.
     /// ```
@@ -4031,6 +5775,38 @@ pub struct ReadOptions<TVal: BedVal> {
     #[builder(default, setter(strip_option))]
     #[allow(dead_code)]
     max_chunk_bytes: Option<usize>,
+
+    #[builder(default, setter(custom))]
+    score_filters: Vec<(String, f64)>,
+    #[builder(default, setter(custom))]
+    sid_filter: Option<SidFilter>,
+    #[builder(default, setter(custom))]
+    regions: Option<RegionSet>,
+    #[builder(default, setter(custom))]
+    iid_where: Option<IidWhere>,
+    #[builder(default, setter(custom))]
+    progress: Option<Progress>,
+
+    /// How to read the selected SNPs (variants) from a local .bed file (defaults to
+    /// [`ReadStrategy::Sequential`](enum.ReadStrategy.html#variant.Sequential)).
+    ///
+    /// In this example, we read with concurrent positional reads, 100 SNPs at a time.
+    /// ```
+    /// use bed_reader::{Bed, ReadOptions, ReadStrategy, sample_bed_file};
+    ///
+    /// let mut bed = Bed::new(sample_bed_file("some_missing.bed")?)?;
+    /// let val = ReadOptions::builder()
+    ///     .sid_index([5, 0, -1])
+    ///     .read_strategy(ReadStrategy::BatchedPread)
+    ///     .max_concurrent_requests(100)
+    ///     .i8()
+    ///     .read(&mut bed)?;
+    /// assert_eq!(val, ReadOptions::builder().sid_index([5, 0, -1]).i8().read(&mut bed)?);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[builder(default)]
+    read_strategy: ReadStrategy,
 }
 
 impl<TVal: BedVal> ReadOptions<TVal> {
@@ -4165,7 +5941,7 @@ impl<TVal: BedVal> ReadOptions<TVal> {
     /// assert_eq!(read_options.missing_value(), -127);
     ///
     /// let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::new(file_name)?;
+    /// let bed = Bed::new(file_name)?;
     /// let val = bed.read_with_options(&read_options)?;
 
     /// assert_eq_nan(&val, &nd::array![[-127, 0, 1], [-127, 2, 2], [2, 0, 0]]);
@@ -4189,7 +5965,7 @@ impl<TVal: BedVal> ReadOptions<TVal> {
     /// println!("{0:?}", read_options.sid_index()); // Outputs 'Vec([2, 3, 0])'
     ///
     /// let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::new(file_name)?;
+    /// let bed = Bed::new(file_name)?;
     /// let val = bed.read_with_options(&read_options)?;
 
     /// assert_eq_nan(&val, &nd::array![[-127, 0, 1], [-127, 2, 2], [2, 0, 0]]);
@@ -4213,7 +5989,7 @@ impl<TVal: BedVal> ReadOptions<TVal> {
     /// println!("{0:?}", read_options.sid_index()); // Outputs 'Vec([2, 3, 0])'
     ///
     /// let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::new(file_name)?;
+    /// let bed = Bed::new(file_name)?;
     /// let val = bed.read_with_options(&read_options)?;
 
     /// assert_eq_nan(&val, &nd::array![[-127, 0, 1], [-127, 2, 2], [2, 0, 0]]);
@@ -4236,7 +6012,7 @@ impl<TVal: BedVal> ReadOptions<TVal> {
     /// assert_eq!(read_options.is_f(), true);
     ///
     /// let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::new(file_name)?;
+    /// let bed = Bed::new(file_name)?;
     /// let val = bed.read_with_options(&read_options)?;
 
     /// assert_eq_nan(&val, &nd::array![[-127, 0, 1], [-127, 2, 2], [2, 0, 0]]);
@@ -4259,7 +6035,7 @@ impl<TVal: BedVal> ReadOptions<TVal> {
     /// assert_eq!(read_options.is_a1_counted(), true);
     ///
     /// let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::new(file_name)?;
+    /// let bed = Bed::new(file_name)?;
     /// let val = bed.read_with_options(&read_options)?;
 
     /// assert_eq_nan(&val, &nd::array![[-127, 0, 1], [-127, 2, 2], [2, 0, 0]]);
@@ -4270,6 +6046,31 @@ impl<TVal: BedVal> ReadOptions<TVal> {
         self.is_a1_counted
     }
 
+    /// Per SNP (variant) in the file, if allele 1 will be counted (defaults to `None`,
+    /// meaning [`is_a1_counted`](struct.ReadOptions.html#method.is_a1_counted) applies to every SNP).
+    ///
+    /// > See [`ReadOptionsBuilder::count_a1_mask`](struct.ReadOptionsBuilder.html#method.count_a1_mask) for an example.
+    pub fn count_a1_mask(&self) -> Option<&nd::Array1<bool>> {
+        self.count_a1_mask.as_ref()
+    }
+
+    /// If the output array is transposed, with one row per SNP (variant) and
+    /// one column per individual (sample) (defaults to false).
+    ///
+    /// > See [`ReadOptionsBuilder::transpose_output`](struct.ReadOptionsBuilder.html#method.transpose_output) for an example.
+    pub fn is_transposed(&self) -> bool {
+        self.is_transposed
+    }
+
+    // The (row count, column count) of the output array for the given output counts.
+    fn output_dim(&self, iid_count: usize, sid_count: usize) -> (usize, usize) {
+        if self.is_transposed {
+            (sid_count, iid_count)
+        } else {
+            (iid_count, sid_count)
+        }
+    }
+
     /// Number of threads to be used (`None` means set with
     /// [Environment Variables](index.html#environment-variables) or use all processors).
     ///
@@ -4283,7 +6084,7 @@ impl<TVal: BedVal> ReadOptions<TVal> {
     /// assert_eq!(read_options.num_threads(), None);
     ///
     /// let file_name = sample_bed_file("small.bed")?;
-    /// let mut bed = Bed::new(file_name)?;
+    /// let bed = Bed::new(file_name)?;
     /// let val = bed.read_with_options(&read_options)?;
 
     /// assert_eq_nan(&val, &nd::array![[-127, 0, 1], [-127, 2, 2], [2, 0, 0]]);
@@ -4293,11 +6094,39 @@ impl<TVal: BedVal> ReadOptions<TVal> {
     pub fn num_threads(&self) -> Option<usize> {
         self.num_threads
     }
+
+    /// How to read the selected SNPs (variants) from a local .bed file.
+    ///
+    /// > See [`ReadOptionsBuilder::read_strategy`](struct.ReadOptionsBuilder.html#method.read_strategy) for an example.
+    pub fn read_strategy(&self) -> ReadStrategy {
+        self.read_strategy
+    }
+
+    /// Minimum scores that selected SNPs (variants) must have, as (score name, minimum) pairs.
+    ///
+    /// > See [`ReadOptionsBuilder::score_at_least`](struct.ReadOptionsBuilder.html#method.score_at_least) for details.
+    pub fn score_filters(&self) -> &[(String, f64)] {
+        &self.score_filters
+    }
+
+    /// The filter, on per-SNP (per-variant) statistics, that selected SNPs must pass, if any.
+    ///
+    /// > See [`ReadOptionsBuilder::sid_filter`](struct.ReadOptionsBuilder.html#method.sid_filter) for details.
+    pub fn sid_filter(&self) -> Option<&SidFilter> {
+        self.sid_filter.as_ref()
+    }
+
+    /// Regions that selected SNPs (variants) must fall in, if any.
+    ///
+    /// > See [`ReadOptionsBuilder::regions`](struct.ReadOptionsBuilder.html#method.regions) for details.
+    pub fn regions(&self) -> Option<&RegionSet> {
+        self.regions.as_ref()
+    }
 }
 
 impl<TVal: BedVal> ReadOptionsBuilder<TVal> {
     /// > See [`ReadOptions::builder`](struct.ReadOptions.html#method.builder) for details and examples.
-    pub fn read(&self, bed: &mut Bed) -> Result<nd::Array2<TVal>, Box<BedErrorPlus>> {
+    pub fn read(&self, bed: &Bed) -> Result<nd::Array2<TVal>, Box<BedErrorPlus>> {
         let read_options = self.build()?;
         bed.read_with_options(&read_options)
     }
@@ -4372,7 +6201,7 @@ impl<TVal: BedVal> ReadOptionsBuilder<TVal> {
     /// ```
     pub fn read_and_fill(
         &self,
-        bed: &mut Bed,
+        bed: &Bed,
         val: &mut nd::ArrayViewMut2<'_, TVal>, //mutable slices additionally allow to modify elements. But slices cannot grow - they are just a view into some vector.
     ) -> Result<(), Box<BedErrorPlus>> {
         let read_options = self.build()?;
@@ -4502,6 +6331,71 @@ impl<TVal: BedVal> ReadOptionsBuilder<TVal> {
         self.is_a1_counted = Some(false);
         self
     }
+
+    /// Output one row per SNP (variant) and one column per individual (sample),
+    /// that is, the transpose of the usual output.
+    ///
+    /// The values are decoded directly into the transposed array, so no transpose is needed
+    /// afterwards. The array order options, for example, [`c`](struct.ReadOptionsBuilder.html#method.c),
+    /// apply to the transposed array. With `c`, each SNP's values are contiguous in memory,
+    /// which is the fastest to fill.
+    ///
+    /// When filling a preallocated array, the array must have shape (SNP count, individual count).
+    ///
+    /// Also see [`is_transposed`](struct.ReadOptionsBuilder.html#method.is_transposed).
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, ReadOptions, sample_bed_file};
+    ///
+    /// let mut bed = Bed::new(sample_bed_file("small.bed")?)?;
+    /// let val = ReadOptions::builder()
+    ///     .sid_index([0, 3])
+    ///     .transpose_output()
+    ///     .c()
+    ///     .i8()
+    ///     .read(&mut bed)?;
+    /// assert_eq!(val, nd::array![[1, 2, 0], [0, 2, 0]]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn transpose_output(&mut self) -> &mut Self {
+        self.is_transposed = Some(true);
+        self
+    }
+
+    /// Of the SNPs (variants) selected by [`sid_index`](struct.ReadOptionsBuilder.html#method.sid_index),
+    /// keep only those whose named score is at least `min`.
+    ///
+    /// Scores are attached with [`Bed::attach_scores`](struct.Bed.html#method.attach_scores).
+    /// SNPs with a NaN score are never kept. Can be called more than once; a SNP must pass every filter.
+    ///
+    /// # Example:
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, ReadOptions, sample_bed_file};
+    ///
+    /// let mut bed = Bed::new(sample_bed_file("small.bed")?)?;
+    /// bed.attach_scores("info", nd::array![0.95, 0.4, 0.81, 0.99])?;
+    /// bed.attach_scores("call_rate", nd::array![0.99, 0.99, 0.99, 0.5])?;
+    /// let val = ReadOptions::builder()
+    ///     .sid_index([3, 2, 1, 0])
+    ///     .score_at_least("info", 0.8)
+    ///     .score_at_least("call_rate", 0.9)
+    ///     .i8()
+    ///     .read(&mut bed)?;
+    /// assert_eq!(val, nd::array![[-127, 1], [-127, 2], [2, 0]]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[anyinput]
+    pub fn score_at_least(&mut self, name: AnyString, min: f64) -> &mut Self {
+        self.score_filters
+            .get_or_insert_with(Vec::new)
+            .push((name.to_string(), min));
+        self
+    }
 }
 
 impl ReadOptionsBuilder<i8> {
@@ -4596,6 +6490,7 @@ impl ReadOptionsBuilder<f64> {
 /// Construct with [`WriteOptions::builder`](struct.WriteOptions.html#method.builder).
 #[derive(Clone, Debug, Builder)]
 #[builder(build_fn(skip))]
+#[allow(clippy::struct_excessive_bools)]
 pub struct WriteOptions<TVal>
 where
     TVal: BedVal,
@@ -4626,6 +6521,30 @@ where
 
     #[builder(setter(custom), default = "false")]
     skip_bim: bool,
+
+    #[builder(setter(custom), default)]
+    lazy_bim: LazyBim,
+
+    #[builder(default, setter(custom))]
+    dedup_sids: Option<DuplicateSidPolicy>,
+
+    #[builder(default, setter(custom))]
+    tolerance: Option<f64>,
+
+    #[builder(setter(custom), default = "false")]
+    validate: bool,
+
+    #[builder(default, setter(custom))]
+    id_policy: Option<IdPolicy>,
+
+    #[builder(default, setter(custom))]
+    missing_if: Option<MissingIf>,
+
+    #[builder(default, setter(custom))]
+    log: Option<OperationLog>,
+
+    #[builder(default, setter(custom))]
+    progress: Option<Progress>,
 }
 
 impl<TVal> WriteOptions<TVal>
@@ -4686,7 +6605,7 @@ where
     ///
     /// WriteOptions::builder(&output_file2).write(&val)?;
     ///
-    /// let mut bed2 = Bed::new(&output_file2)?;
+    /// let bed2 = Bed::new(&output_file2)?;
     /// println!("{:?}", bed2.chromosome()?); // Outputs ndarray ["0", "0", "0", "0"]
     /// # use bed_reader::BedErrorPlus;
     /// # Ok::<(), Box<BedErrorPlus>>(())
@@ -5062,7 +6981,11 @@ where
     /// # Ok::<(), Box<BedErrorPlus>>(())
     /// ```
     pub fn sid_count(&self) -> usize {
-        self.sid().len()
+        if self.lazy_bim.is_empty() {
+            self.sid().len()
+        } else {
+            self.lazy_bim.sid_count()
+        }
     }
 
     /// Number of individuals (samples) and SNPs (variants)
@@ -5269,6 +7192,41 @@ where
     pub fn skip_bim(&self) -> bool {
         self.skip_bim
     }
+
+    /// The policy for repeated SNP ids (sid), if any.
+    ///
+    /// > See [`WriteOptionsBuilder::dedup_sids`](struct.WriteOptionsBuilder.html#method.dedup_sids) for an example.
+    pub fn dedup_sids(&self) -> Option<DuplicateSidPolicy> {
+        self.dedup_sids
+    }
+
+    /// How far from 0.0, 1.0, or 2.0 a value can be and still be written as that genotype, if at all.
+    ///
+    /// > See [`WriteOptionsBuilder::tolerance`](struct.WriteOptionsBuilder.html#method.tolerance) for an example.
+    pub fn tolerance(&self) -> Option<f64> {
+        self.tolerance
+    }
+
+    /// If true, a bad value is reported with its location.
+    ///
+    /// > See [`WriteOptionsBuilder::validate`](struct.WriteOptionsBuilder.html#method.validate) for an example.
+    pub fn validate(&self) -> bool {
+        self.validate
+    }
+
+    /// The policy for ids that some tools can't read, if any.
+    ///
+    /// > See [`WriteOptionsBuilder::id_policy`](struct.WriteOptionsBuilder.html#method.id_policy) for an example.
+    pub fn id_policy(&self) -> Option<IdPolicy> {
+        self.id_policy
+    }
+
+    /// The log to which the write is reported, if any.
+    ///
+    /// > See [`OperationLog`](struct.OperationLog.html) for an example.
+    pub fn log(&self) -> Option<&OperationLog> {
+        self.log.as_ref()
+    }
 }
 
 impl<TVal> WriteOptionsBuilder<TVal>
@@ -5281,7 +7239,10 @@ where
     pub fn write<S: nd::Data<Elem = TVal>>(
         &mut self,
         val: &nd::ArrayBase<S, nd::Ix2>,
-    ) -> Result<(), Box<BedErrorPlus>> {
+    ) -> Result<(), Box<BedErrorPlus>>
+    where
+        TVal: Into<f64>,
+    {
         let (iid_count, sid_count) = val.dim();
         let write_options = self.build(iid_count, sid_count)?;
         Bed::write_with_options(val, &write_options)?;
@@ -5289,6 +7250,24 @@ where
         Ok(())
     }
 
+    /// Like [`write`](struct.WriteOptionsBuilder.html#method.write), but also returns a
+    /// [`WriteSummary`](struct.WriteSummary.html) with genotype and missing counts, bytes
+    /// written, timing, and the number of values snapped by
+    /// [`tolerance`](struct.WriteOptionsBuilder.html#method.tolerance).
+    ///
+    /// > See [`WriteSummary`](struct.WriteSummary.html) for an example.
+    pub fn write_with_summary<S: nd::Data<Elem = TVal>>(
+        &mut self,
+        val: &nd::ArrayBase<S, nd::Ix2>,
+    ) -> Result<WriteSummary, Box<BedErrorPlus>>
+    where
+        TVal: Into<f64>,
+    {
+        let (iid_count, sid_count) = val.dim();
+        let write_options = self.build(iid_count, sid_count)?;
+        Bed::write_with_summary(val, &write_options)
+    }
+
     /// Set the family id (fid) values for each individual (sample).
     ///
     /// Defaults to zeros.
@@ -5461,7 +7440,7 @@ where
     /// use bed_reader::{Bed, WriteOptions, sample_bed_file};
     /// use ndarray_rand::{rand::prelude::StdRng, rand::SeedableRng, rand_distr::Uniform, RandomExt};
     ///
-    /// let mut bed = Bed::new(sample_bed_file("small.bed")?)?;
+    /// let bed = Bed::new(sample_bed_file("small.bed")?)?;
     /// let metadata = bed.metadata()?;
     /// let shape = bed.dim()?;
     ///
@@ -5558,7 +7537,7 @@ where
     /// use bed_reader::{Bed, WriteOptions, sample_bed_file};
     /// use ndarray_rand::{rand::prelude::StdRng, rand::SeedableRng, rand_distr::Uniform, RandomExt};
     ///
-    /// let mut bed = Bed::new(sample_bed_file("small.bed")?)?;
+    /// let bed = Bed::new(sample_bed_file("small.bed")?)?;
     /// let metadata = bed.metadata()?;
     /// let shape = bed.dim()?;
     ///
@@ -5718,7 +7697,24 @@ where
 
         // unwrap always works because the metadata builder always initializes metadata
         let metadata = self.metadata.as_ref().unwrap();
-        let metadata = metadata.fill(iid_count, sid_count)?;
+        let lazy_bim = self.lazy_bim.clone().unwrap_or_default();
+        let dedup_sids = self.dedup_sids.unwrap_or(None);
+        if dedup_sids == Some(DuplicateSidPolicy::KeepFirst) && !lazy_bim.is_empty() {
+            Err(BedError::KeepFirstWithIter)?;
+        }
+        let tolerance = self.tolerance.unwrap_or(None);
+        if let Some(tolerance) = tolerance {
+            if !(0.0..0.5).contains(&tolerance) {
+                Err(BedError::BadTolerance(tolerance))?;
+            }
+        }
+        let (lazy_bim, metadata) = if lazy_bim.is_empty() {
+            (lazy_bim, metadata.fill(iid_count, sid_count)?)
+        } else {
+            let (lazy_bim, metadata) = lazy_bim.complete(metadata, sid_count)?;
+            (lazy_bim, metadata.fill(iid_count, 0)?)
+        };
+        metadata.check_sid_annotations(sid_count)?;
 
         let write_options = WriteOptions {
             path: path.to_owned(),
@@ -5729,6 +7725,14 @@ where
             missing_value: self.missing_value.unwrap_or_else(|| TVal::missing()),
             skip_fam: self.skip_fam.unwrap_or(false),
             skip_bim: self.skip_bim.unwrap_or(false),
+            lazy_bim,
+            dedup_sids,
+            tolerance,
+            validate: self.validate.unwrap_or(false),
+            id_policy: self.id_policy.unwrap_or(None),
+            missing_if: self.missing_if.clone().unwrap_or(None),
+            log: self.log.clone().unwrap_or(None),
+            progress: self.progress.clone().unwrap_or(None),
 
             metadata,
         };
@@ -5749,6 +7753,14 @@ where
             missing_value: None,
             skip_fam: None,
             skip_bim: None,
+            lazy_bim: None,
+            dedup_sids: None,
+            tolerance: None,
+            validate: None,
+            id_policy: None,
+            missing_if: None,
+            log: None,
+            progress: None,
         }
     }
 }
@@ -5939,7 +7951,7 @@ fn check_counts(
 // Thats quite a safe bet - we checked this for you. ;-)"
 fn compute_field<T: Clone, F: Fn(usize) -> T>(
     field_name: &str,
-    field: &mut Option<Rc<nd::Array1<T>>>,
+    field: &mut Option<Arc<nd::Array1<T>>>,
     count: usize,
     lambda: F,
 ) -> Result<(), Box<BedErrorPlus>> {
@@ -5956,12 +7968,27 @@ fn compute_field<T: Clone, F: Fn(usize) -> T>(
             ))?;
         }
     } else {
-        let array = Rc::new((0..count).map(lambda).collect::<nd::Array1<T>>());
+        let array = Arc::new((0..count).map(lambda).collect::<nd::Array1<T>>());
         *field = Some(array);
     }
     Ok(())
 }
 
+fn resize_field<T: Clone, F: Fn(usize) -> T>(
+    field: &mut Option<Arc<nd::Array1<T>>>,
+    count: usize,
+    lambda: F,
+) {
+    if let Some(array) = field {
+        if array.len() != count {
+            let resized = (0..count)
+                .map(|i| array.get(i).cloned().unwrap_or_else(|| lambda(i)))
+                .collect::<nd::Array1<T>>();
+            *field = Some(Arc::new(resized));
+        }
+    }
+}
+
 impl MetadataBuilder {
     /// Create a [`Metadata`](struct.Metadata.html) from the builder.
     ///
@@ -5977,7 +8004,9 @@ impl MetadataBuilder {
     /// Set the family id (fid) values.
     #[anyinput]
     pub fn fid(&mut self, fid: AnyIter<AnyString>) -> &mut Self {
-        self.fid = Some(Some(Rc::new(fid.map(|s| s.as_ref().to_string()).collect())));
+        self.fid = Some(Some(Arc::new(
+            fid.map(|s| s.as_ref().to_string()).collect(),
+        )));
         self
     }
 
@@ -5995,14 +8024,14 @@ impl MetadataBuilder {
     /// ```
     #[anyinput]
     pub fn iid(&mut self, iid: AnyIter<AnyString>) -> &mut Self {
-        self.iid = Some(Some(Rc::new(iid.map(|s| s.as_ref().to_owned()).collect())));
+        self.iid = Some(Some(Arc::new(iid.map(|s| s.as_ref().to_owned()).collect())));
         self
     }
 
     /// Set the father values.
     #[anyinput]
     pub fn father(&mut self, father: AnyIter<AnyString>) -> &mut Self {
-        self.father = Some(Some(Rc::new(
+        self.father = Some(Some(Arc::new(
             father.map(|s| s.as_ref().to_owned()).collect(),
         )));
         self
@@ -6011,7 +8040,7 @@ impl MetadataBuilder {
     /// Override the mother values.
     #[anyinput]
     pub fn mother(&mut self, mother: AnyIter<AnyString>) -> &mut Self {
-        self.mother = Some(Some(Rc::new(
+        self.mother = Some(Some(Arc::new(
             mother.map(|s| s.as_ref().to_owned()).collect(),
         )));
         self
@@ -6020,14 +8049,14 @@ impl MetadataBuilder {
     /// Override the sex values.
     #[anyinput]
     pub fn sex(&mut self, sex: AnyIter<i32>) -> &mut Self {
-        self.sex = Some(Some(Rc::new(sex.collect())));
+        self.sex = Some(Some(Arc::new(sex.collect())));
         self
     }
 
     /// Override the phenotype values.
     #[anyinput]
     pub fn pheno(&mut self, pheno: AnyIter<AnyString>) -> &mut Self {
-        self.pheno = Some(Some(Rc::new(
+        self.pheno = Some(Some(Arc::new(
             pheno.map(|s| s.as_ref().to_owned()).collect(),
         )));
         self
@@ -6036,7 +8065,7 @@ impl MetadataBuilder {
     /// Override the chromosome values.
     #[anyinput]
     pub fn chromosome(&mut self, chromosome: AnyIter<AnyString>) -> &mut Self {
-        self.chromosome = Some(Some(Rc::new(
+        self.chromosome = Some(Some(Arc::new(
             chromosome.map(|s| s.as_ref().to_owned()).collect(),
         )));
         self
@@ -6056,7 +8085,7 @@ impl MetadataBuilder {
     /// ```
     #[anyinput]
     pub fn sid(&mut self, sid: AnyIter<AnyString>) -> &mut Self {
-        self.sid = Some(Some(Rc::new(
+        self.sid = Some(Some(Arc::new(
             sid.into_iter().map(|s| s.as_ref().to_owned()).collect(),
         )));
         self
@@ -6065,21 +8094,21 @@ impl MetadataBuilder {
     /// Override the centimorgan position values.
     #[anyinput]
     pub fn cm_position(&mut self, cm_position: AnyIter<f32>) -> &mut Self {
-        self.cm_position = Some(Some(Rc::new(cm_position.into_iter().collect())));
+        self.cm_position = Some(Some(Arc::new(cm_position.into_iter().collect())));
         self
     }
 
     /// Override the base-pair position values.
     #[anyinput]
     pub fn bp_position(&mut self, bp_position: AnyIter<i32>) -> &mut Self {
-        self.bp_position = Some(Some(Rc::new(bp_position.into_iter().collect())));
+        self.bp_position = Some(Some(Arc::new(bp_position.into_iter().collect())));
         self
     }
 
     /// Override the allele 1 values.
     #[anyinput]
     pub fn allele_1(&mut self, allele_1: AnyIter<AnyString>) -> &mut Self {
-        self.allele_1 = Some(Some(Rc::new(
+        self.allele_1 = Some(Some(Arc::new(
             allele_1
                 .into_iter()
                 .map(|s| s.as_ref().to_owned())
@@ -6091,7 +8120,7 @@ impl MetadataBuilder {
     /// Override the allele 2 values.
     #[anyinput]
     pub fn allele_2(&mut self, allele_2: AnyIter<AnyString>) -> &mut Self {
-        self.allele_2 = Some(Some(Rc::new(
+        self.allele_2 = Some(Some(Arc::new(
             allele_2
                 .into_iter()
                 .map(|s| s.as_ref().to_owned())
@@ -6100,6 +8129,38 @@ impl MetadataBuilder {
         self
     }
 
+    /// Attach a named text annotation, for example, gene symbols, with one value per SNP (variant).
+    ///
+    /// Replaces any annotation with the same name.
+    ///
+    /// > See [`SidAnnotation`](enum.SidAnnotation.html) for an example.
+    #[anyinput]
+    pub fn sid_annotation_string(
+        &mut self,
+        name: AnyString,
+        values: AnyIter<AnyString>,
+    ) -> &mut Self {
+        let annotation = SidAnnotation::String(values.map(|s| s.as_ref().to_owned()).collect());
+        self.sid_annotations
+            .get_or_insert_with(BTreeMap::new)
+            .insert(name.to_owned(), Arc::new(annotation));
+        self
+    }
+
+    /// Attach a named numeric annotation, for example, QC scores, with one value per SNP (variant).
+    ///
+    /// Replaces any annotation with the same name. Use NaN for missing values.
+    ///
+    /// > See [`SidAnnotation`](enum.SidAnnotation.html) for an example.
+    #[anyinput]
+    pub fn sid_annotation_float(&mut self, name: AnyString, values: AnyIter<f64>) -> &mut Self {
+        let annotation = SidAnnotation::Float(values.collect());
+        self.sid_annotations
+            .get_or_insert_with(BTreeMap::new)
+            .insert(name.to_owned(), Arc::new(annotation));
+        self
+    }
+
     /// Merge metadata from a [`Metadata`](struct.Metadata.html).
     ///
     /// # Example
@@ -6145,6 +8206,11 @@ impl MetadataBuilder {
         set_field(&metadata.bp_position, &mut self.bp_position);
         set_field(&metadata.allele_1, &mut self.allele_1);
         set_field(&metadata.allele_2, &mut self.allele_2);
+        if !metadata.sid_annotations.is_empty() {
+            self.sid_annotations
+                .get_or_insert_with(BTreeMap::new)
+                .extend(metadata.sid_annotations.clone());
+        }
         self
     }
 }
@@ -6181,7 +8247,10 @@ impl Metadata {
                 lazy_or_skip_count(&self.bp_position),
                 lazy_or_skip_count(&self.allele_1),
                 lazy_or_skip_count(&self.allele_2),
-            ],
+            ]
+            .into_iter()
+            .chain(self.sid_annotations.values().map(|a| Some(a.len())))
+            .collect(),
             &mut sid_count,
             "sid",
         )?;
@@ -6320,6 +8389,56 @@ impl Metadata {
         option_rc_as_ref(&self.allele_2)
     }
 
+    /// Read a .fam file into a new [`Metadata`](struct.Metadata.html), without a [`Bed`](struct.Bed.html).
+    ///
+    /// All six .fam fields are read and parsed as [`Bed`](struct.Bed.html) would. The .bim fields
+    /// are `None`. Use [`Metadata::read_fam`](struct.Metadata.html#method.read_fam) to skip fields
+    /// or to fill in only the empty fields of existing metadata.
+    ///
+    /// # Example
+    ///
+    /// Edit sample metadata without touching the genotypes.
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Metadata, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("small.bed");
+    /// WriteOptions::builder(&output_file)
+    ///     .iid(["i1", "i2", "i3"])
+    ///     .write(&nd::array![[1, 0], [2, 0], [0, 1]])?;
+    /// let fam_file = output_file.with_extension("fam");
+    ///
+    /// let metadata = Metadata::from_fam(&fam_file)?;
+    /// assert_eq!(metadata.iid(), Some(&nd::array!["i1", "i2", "i3"].map(|s| s.to_string())));
+    /// assert!(metadata.sid().is_none());
+    ///
+    /// let metadata = Metadata::builder()
+    ///     .metadata(&metadata)
+    ///     .pheno(["case", "control", "case"])
+    ///     .build()?;
+    /// metadata.write_fam(&fam_file)?;
+    /// let pheno = nd::array!["case", "control", "case"].map(|s| s.to_string());
+    /// assert_eq!(Metadata::from_fam(&fam_file)?.pheno(), Some(&pheno));
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[anyinput]
+    pub fn from_fam(path: AnyPath) -> Result<Metadata, Box<BedErrorPlus>> {
+        Ok(Metadata::new().read_fam(path, &HashSet::new())?.0)
+    }
+
+    /// Read a .bim file into a new [`Metadata`](struct.Metadata.html), without a [`Bed`](struct.Bed.html).
+    ///
+    /// All six .bim fields are read and parsed as [`Bed`](struct.Bed.html) would. The .fam fields
+    /// are `None`.
+    ///
+    /// > See [`Metadata::from_fam`](struct.Metadata.html#method.from_fam) for an example.
+    #[anyinput]
+    pub fn from_bim(path: AnyPath) -> Result<Metadata, Box<BedErrorPlus>> {
+        Ok(Metadata::new().read_bim(path, &HashSet::new())?.0)
+    }
+
     /// Create a new [`Metadata`](struct.Metadata.html) by filling in empty fields with a .fam file.
     ///
     /// # Example
@@ -6351,6 +8470,23 @@ impl Metadata {
         path: AnyPath,
         skip_set: &HashSet<MetadataFields>,
     ) -> Result<(Metadata, usize), Box<BedErrorPlus>> {
+        let (metadata, count, _) = self.read_fam_unchecked(path, skip_set, &HashSet::new())?;
+        metadata.check_counts(Some(count), None)?;
+        Ok((metadata, count))
+    }
+
+    // Like read_fam, but doesn't check that the fields agree with the .fam file's line count.
+    #[anyinput]
+    // Fields in raw_set are kept as text rather than parsed; their text is returned separately.
+    #[allow(clippy::type_complexity)]
+    fn read_fam_unchecked(
+        &self,
+        path: AnyPath,
+        skip_set: &HashSet<MetadataFields>,
+        raw_set: &HashSet<MetadataFields>,
+    ) -> Result<(Metadata, usize, HashMap<MetadataFields, nd::Array1<String>>), Box<BedErrorPlus>>
+    {
+        let mut raw_fields = HashMap::new();
         let mut field_vec: Vec<usize> = Vec::new();
 
         if self.fid.is_none() && !skip_set.contains(&MetadataFields::Fid) {
@@ -6378,32 +8514,34 @@ impl Metadata {
 
         // unwraps are safe because we pop once for every push
         if clone.pheno.is_none() && !skip_set.contains(&MetadataFields::Pheno) {
-            clone.pheno = Some(Rc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
+            clone.pheno = Some(Arc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
         }
         if clone.sex.is_none() && !skip_set.contains(&MetadataFields::Sex) {
             let vec = vec_of_vec.pop().unwrap();
-            let array = vec
-                .iter()
-                .map(|s| s.parse::<i32>())
-                .collect::<Result<nd::Array1<i32>, _>>()?;
-            clone.sex = Some(Rc::new(array));
+            if raw_set.contains(&MetadataFields::Sex) {
+                raw_fields.insert(MetadataFields::Sex, nd::Array::from_vec(vec));
+            } else {
+                let array = vec
+                    .iter()
+                    .map(|s| s.parse::<i32>())
+                    .collect::<Result<nd::Array1<i32>, _>>()?;
+                clone.sex = Some(Arc::new(array));
+            }
         }
         if clone.mother.is_none() && !skip_set.contains(&MetadataFields::Mother) {
-            clone.mother = Some(Rc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
+            clone.mother = Some(Arc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
         }
         if clone.father.is_none() && !skip_set.contains(&MetadataFields::Father) {
-            clone.father = Some(Rc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
+            clone.father = Some(Arc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
         }
         if clone.iid.is_none() && !skip_set.contains(&MetadataFields::Iid) {
-            clone.iid = Some(Rc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
+            clone.iid = Some(Arc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
         }
         if clone.fid.is_none() && !skip_set.contains(&MetadataFields::Fid) {
-            clone.fid = Some(Rc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
+            clone.fid = Some(Arc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
         }
 
-        clone.check_counts(Some(count), None)?;
-
-        Ok((clone, count))
+        Ok((clone, count, raw_fields))
     }
 
     /// Create a new [`Metadata`](struct.Metadata.html) by filling in empty
@@ -6469,7 +8607,7 @@ impl Metadata {
 
         // unwraps are safe because we pop once for every push
         if clone.pheno.is_none() && !skip_set.contains(&MetadataFields::Pheno) {
-            clone.pheno = Some(Rc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
+            clone.pheno = Some(Arc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
         }
         if clone.sex.is_none() && !skip_set.contains(&MetadataFields::Sex) {
             let vec = vec_of_vec.pop().unwrap();
@@ -6477,19 +8615,19 @@ impl Metadata {
                 .iter()
                 .map(|s| s.parse::<i32>())
                 .collect::<Result<nd::Array1<i32>, _>>()?;
-            clone.sex = Some(Rc::new(array));
+            clone.sex = Some(Arc::new(array));
         }
         if clone.mother.is_none() && !skip_set.contains(&MetadataFields::Mother) {
-            clone.mother = Some(Rc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
+            clone.mother = Some(Arc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
         }
         if clone.father.is_none() && !skip_set.contains(&MetadataFields::Father) {
-            clone.father = Some(Rc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
+            clone.father = Some(Arc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
         }
         if clone.iid.is_none() && !skip_set.contains(&MetadataFields::Iid) {
-            clone.iid = Some(Rc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
+            clone.iid = Some(Arc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
         }
         if clone.fid.is_none() && !skip_set.contains(&MetadataFields::Fid) {
-            clone.fid = Some(Rc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
+            clone.fid = Some(Arc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
         }
 
         clone.check_counts(Some(count), None)?;
@@ -6528,6 +8666,23 @@ impl Metadata {
         path: AnyPath,
         skip_set: &HashSet<MetadataFields>,
     ) -> Result<(Metadata, usize), Box<BedErrorPlus>> {
+        let (metadata, count, _) = self.read_bim_unchecked(path, skip_set, &HashSet::new())?;
+        metadata.check_counts(None, Some(count))?;
+        Ok((metadata, count))
+    }
+
+    // Like read_bim, but doesn't check that the fields agree with the .bim file's line count.
+    #[anyinput]
+    // Fields in raw_set are kept as text rather than parsed; their text is returned separately.
+    #[allow(clippy::type_complexity)]
+    fn read_bim_unchecked(
+        &self,
+        path: AnyPath,
+        skip_set: &HashSet<MetadataFields>,
+        raw_set: &HashSet<MetadataFields>,
+    ) -> Result<(Metadata, usize, HashMap<MetadataFields, nd::Array1<String>>), Box<BedErrorPlus>>
+    {
+        let mut raw_fields = HashMap::new();
         let mut field_vec: Vec<usize> = Vec::new();
         if self.chromosome.is_none() && !skip_set.contains(&MetadataFields::Chromosome) {
             field_vec.push(0);
@@ -6554,38 +8709,44 @@ impl Metadata {
 
         // unwraps are safe because we pop once for every push
         if clone.allele_2.is_none() && !skip_set.contains(&MetadataFields::Allele2) {
-            clone.allele_2 = Some(Rc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
+            clone.allele_2 = Some(Arc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
         }
         if clone.allele_1.is_none() && !skip_set.contains(&MetadataFields::Allele1) {
-            clone.allele_1 = Some(Rc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
+            clone.allele_1 = Some(Arc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
         }
         if clone.bp_position.is_none() && !skip_set.contains(&MetadataFields::BpPosition) {
             let vec = vec_of_vec.pop().unwrap();
-            let array = vec
-                .iter()
-                .map(|s| s.parse::<i32>())
-                .collect::<Result<nd::Array1<i32>, _>>()?;
-            clone.bp_position = Some(Rc::new(array));
+            if raw_set.contains(&MetadataFields::BpPosition) {
+                raw_fields.insert(MetadataFields::BpPosition, nd::Array::from_vec(vec));
+            } else {
+                let array = vec
+                    .iter()
+                    .map(|s| s.parse::<i32>())
+                    .collect::<Result<nd::Array1<i32>, _>>()?;
+                clone.bp_position = Some(Arc::new(array));
+            }
         }
         if clone.cm_position.is_none() && !skip_set.contains(&MetadataFields::CmPosition) {
             let vec = vec_of_vec.pop().unwrap();
-            let array = vec
-                .iter()
-                .map(|s| s.parse::<f32>())
-                .collect::<Result<nd::Array1<f32>, _>>()?;
-            clone.cm_position = Some(Rc::new(array));
+            if raw_set.contains(&MetadataFields::CmPosition) {
+                raw_fields.insert(MetadataFields::CmPosition, nd::Array::from_vec(vec));
+            } else {
+                let array = vec
+                    .iter()
+                    .map(|s| s.parse::<f32>())
+                    .collect::<Result<nd::Array1<f32>, _>>()?;
+                clone.cm_position = Some(Arc::new(array));
+            }
         }
 
         if clone.sid.is_none() && !skip_set.contains(&MetadataFields::Sid) {
-            clone.sid = Some(Rc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
+            clone.sid = Some(Arc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
         }
         if clone.chromosome.is_none() && !skip_set.contains(&MetadataFields::Chromosome) {
-            clone.chromosome = Some(Rc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
+            clone.chromosome = Some(Arc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
         }
 
-        clone.check_counts(None, Some(count))?;
-
-        Ok((clone, count))
+        Ok((clone, count, raw_fields))
     }
 
     /// Create a new [`Metadata`](struct.Metadata.html) by filling in empty
@@ -6650,10 +8811,10 @@ impl Metadata {
 
         // unwraps are safe because we pop once for every push
         if clone.allele_2.is_none() && !skip_set.contains(&MetadataFields::Allele2) {
-            clone.allele_2 = Some(Rc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
+            clone.allele_2 = Some(Arc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
         }
         if clone.allele_1.is_none() && !skip_set.contains(&MetadataFields::Allele1) {
-            clone.allele_1 = Some(Rc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
+            clone.allele_1 = Some(Arc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
         }
         if clone.bp_position.is_none() && !skip_set.contains(&MetadataFields::BpPosition) {
             let vec = vec_of_vec.pop().unwrap();
@@ -6661,7 +8822,7 @@ impl Metadata {
                 .iter()
                 .map(|s| s.parse::<i32>())
                 .collect::<Result<nd::Array1<i32>, _>>()?;
-            clone.bp_position = Some(Rc::new(array));
+            clone.bp_position = Some(Arc::new(array));
         }
         if clone.cm_position.is_none() && !skip_set.contains(&MetadataFields::CmPosition) {
             let vec = vec_of_vec.pop().unwrap();
@@ -6669,14 +8830,14 @@ impl Metadata {
                 .iter()
                 .map(|s| s.parse::<f32>())
                 .collect::<Result<nd::Array1<f32>, _>>()?;
-            clone.cm_position = Some(Rc::new(array));
+            clone.cm_position = Some(Arc::new(array));
         }
 
         if clone.sid.is_none() && !skip_set.contains(&MetadataFields::Sid) {
-            clone.sid = Some(Rc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
+            clone.sid = Some(Arc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
         }
         if clone.chromosome.is_none() && !skip_set.contains(&MetadataFields::Chromosome) {
-            clone.chromosome = Some(Rc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
+            clone.chromosome = Some(Arc::new(nd::Array::from_vec(vec_of_vec.pop().unwrap())));
         }
 
         clone.check_counts(None, Some(count))?;
@@ -6684,6 +8845,9 @@ impl Metadata {
         Ok((clone, count))
     }
 
+    // Large files are read in blocks. Each block is split into chunks on line boundaries,
+    // the chunks are parsed in parallel, and the results are appended in order. The first
+    // error in file order is returned, just as when reading line by line.
     #[anyinput]
     fn read_fam_or_bim(
         field_vec: &[usize],
@@ -6691,36 +8855,55 @@ impl Metadata {
         path: AnyPath,
     ) -> Result<(Vec<Vec<String>>, usize), Box<BedErrorPlus>> {
         let mut vec_of_vec = vec![vec![]; field_vec.len()];
-
-        let file = File::open(path)?;
-
-        let reader = BufReader::new(file);
         let mut count = 0;
-        for line in reader.lines() {
-            let line = line?;
-            count += 1;
 
-            let fields: Vec<&str> = if is_split_whitespace {
-                line.split_whitespace().collect()
+        let mut file = File::open(path)?;
+        let mut block = Vec::new();
+        let mut pool = None;
+        loop {
+            let read_len = (&mut file)
+                .take(METADATA_BLOCK_BYTES as u64)
+                .read_to_end(&mut block)?;
+            let is_eof = read_len == 0;
+            let end = if is_eof {
+                block.len()
+            } else if let Some(newline_i) = block.iter().rposition(|&b| b == b'\n') {
+                newline_i + 1
             } else {
-                line.split('\t').collect()
+                // No complete line yet, so keep reading
+                continue;
             };
 
-            if fields.len() != 6 {
-                Err(BedError::MetadataFieldCount(
-                    6,
-                    fields.len(),
-                    path_ref_to_string(path),
-                ))?;
+            let chunks = split_at_lines(&block[..end], METADATA_CHUNK_BYTES);
+            let results = if chunks.len() > 1 {
+                if pool.is_none() {
+                    pool = Some(create_pool(compute_num_threads(None)?)?);
+                }
+                // unwrap is safe because the pool was just created
+                pool.as_ref().unwrap().install(|| {
+                    chunks
+                        .par_iter()
+                        .map(|chunk| parse_fam_or_bim(chunk, field_vec, is_split_whitespace, path))
+                        .collect::<Vec<_>>()
+                })
+            } else {
+                chunks
+                    .iter()
+                    .map(|chunk| parse_fam_or_bim(chunk, field_vec, is_split_whitespace, path))
+                    .collect::<Vec<_>>()
+            };
+            for result in results {
+                let (chunk_vec_of_vec, chunk_count) = result?;
+                for (vec, chunk_vec) in vec_of_vec.iter_mut().zip(chunk_vec_of_vec) {
+                    vec.extend(chunk_vec);
+                }
+                count += chunk_count;
             }
 
-            let mut of_interest_count = 0;
-            for (field_index, field) in fields.iter().enumerate() {
-                if field_vec.contains(&field_index) {
-                    vec_of_vec[of_interest_count].push((*field).to_string());
-                    of_interest_count += 1;
-                }
+            if is_eof {
+                break;
             }
+            block.drain(..end);
         }
 
         Ok((vec_of_vec, count))
@@ -6752,7 +8935,7 @@ impl Metadata {
                     Err(BedError::MetadataFieldCount(
                         6,
                         fields.len(),
-                        cloud_file.to_string(),
+                        PathBuf::from(cloud_file.to_string()),
                     ))?;
                 }
 
@@ -6814,17 +8997,28 @@ impl Metadata {
     /// ```
     #[anyinput]
     pub fn write_fam(&self, path: AnyPath) -> Result<(), Box<BedErrorPlus>> {
+        // Check before creating, so a failed write leaves any existing file alone
+        if !self.is_some_fam() {
+            Err(BedError::MetadataMissingForWrite("fam".to_string()))?;
+        }
         let file = File::create(path)?;
         let mut writer = BufWriter::new(file);
+        self.write_fam_to(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    // Write the .fam lines to writer.
+    pub(crate) fn write_fam_to<W: Write>(&self, writer: &mut W) -> Result<(), Box<BedErrorPlus>> {
         let mut result: Result<(), Box<BedErrorPlus>> = Ok(());
 
         if !self.is_some_fam() {
             Err(BedError::MetadataMissingForWrite("fam".to_string()))?;
         }
 
-        // 1st as_ref turns Option<Rc<Array>> into Option<&Rc<Array>>
+        // 1st as_ref turns Option<Arc<Array>> into Option<&Arc<Array>>
         // unwrap always works because we checked that all the fields are present
-        // 2nd as as_ref turns &Rc<Array> into &Array
+        // 2nd as as_ref turns &Arc<Array> into &Array
         nd::azip!((fid in self.fid.as_ref().unwrap().as_ref(),
                    iid in self.iid.as_ref().unwrap().as_ref(),
                    father in self.father.as_ref().unwrap().as_ref(),
@@ -6876,17 +9070,28 @@ impl Metadata {
     /// ```
     #[anyinput]
     pub fn write_bim(&self, path: AnyPath) -> Result<(), Box<BedErrorPlus>> {
+        // Check before creating, so a failed write leaves any existing file alone
+        if !self.is_some_bim() {
+            Err(BedError::MetadataMissingForWrite("bim".to_string()))?;
+        }
         let file = File::create(path)?;
         let mut writer = BufWriter::new(file);
+        self.write_bim_to(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    // Write the .bim lines to writer.
+    pub(crate) fn write_bim_to<W: Write>(&self, writer: &mut W) -> Result<(), Box<BedErrorPlus>> {
         let mut result: Result<(), Box<BedErrorPlus>> = Ok(());
 
         if !self.is_some_bim() {
             Err(BedError::MetadataMissingForWrite("bim".to_string()))?;
         }
 
-        // 1st as_ref turns Option<Rc<Array>> into Option<&Rc<Array>>
+        // 1st as_ref turns Option<Arc<Array>> into Option<&Arc<Array>>
         // unwrap always works because we checked that all the fields are present
-        // 2nd as as_ref turns &Rc<Array> into &Array
+        // 2nd as as_ref turns &Arc<Array> into &Array
         nd::azip!((
             chromosome in self.chromosome.as_ref().unwrap().as_ref(),
             sid in self.sid.as_ref().unwrap().as_ref(),
@@ -6912,6 +9117,56 @@ impl Metadata {
         Ok(())
     }
 
+    /// Write the metadata related to individuals/samples to a .fam file, giving any empty .fam
+    /// field the default values of [`Metadata::fill`](struct.Metadata.html#method.fill).
+    ///
+    /// The number of individuals is taken from the .fam fields that are present. If none are,
+    /// the function returns a [`BedError::MetadataMissingForWrite`](enum.BedError.html#variant.MetadataMissingForWrite)
+    /// error. The .bim fields are ignored.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::Metadata;
+    ///
+    /// let metadata = Metadata::builder().iid(["i1", "i2", "i3"]).build()?;
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("no_bed.fam");
+    /// metadata.write_fam_with_defaults(&output_file)?;
+    ///
+    /// let metadata = Metadata::from_fam(&output_file)?;
+    /// assert_eq!(metadata.fid(), Some(&nd::array!["0", "0", "0"].map(|s| s.to_string())));
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[anyinput]
+    pub fn write_fam_with_defaults(&self, path: AnyPath) -> Result<(), Box<BedErrorPlus>> {
+        let Some(iid_count) = self.check_counts(None, None)?.0 else {
+            Err(BedError::MetadataMissingForWrite("fam".to_string()))?
+        };
+        let mut metadata = self.clone();
+        metadata.fill_fam(iid_count)?;
+        metadata.write_fam(path)
+    }
+
+    /// Write the metadata related to SNPs/variants to a .bim file, giving any empty .bim
+    /// field the default values of [`Metadata::fill`](struct.Metadata.html#method.fill).
+    ///
+    /// The number of SNPs is taken from the .bim fields that are present. If none are,
+    /// the function returns a [`BedError::MetadataMissingForWrite`](enum.BedError.html#variant.MetadataMissingForWrite)
+    /// error. The .fam fields are ignored.
+    ///
+    /// > See [`Metadata::write_fam_with_defaults`](struct.Metadata.html#method.write_fam_with_defaults) for an example.
+    #[anyinput]
+    pub fn write_bim_with_defaults(&self, path: AnyPath) -> Result<(), Box<BedErrorPlus>> {
+        let Some(sid_count) = self.check_counts(None, None)?.1 else {
+            Err(BedError::MetadataMissingForWrite("bim".to_string()))?
+        };
+        let mut metadata = self.clone();
+        metadata.fill_bim(sid_count)?;
+        metadata.write_bim(path)
+    }
+
     /// Create a new [`Metadata`](struct.Metadata.html) by filling in empty fields with default values.
     ///
     /// # Example
@@ -6934,40 +9189,75 @@ impl Metadata {
     /// ```
     pub fn fill(&self, iid_count: usize, sid_count: usize) -> Result<Metadata, Box<BedErrorPlus>> {
         let mut metadata = self.clone();
+        metadata.fill_fam(iid_count)?;
+        metadata.fill_bim(sid_count)?;
+        Ok(metadata)
+    }
 
-        compute_field("fid", &mut metadata.fid, iid_count, |_| "0".to_string())?;
-        compute_field("iid", &mut metadata.iid, iid_count, |i| {
-            format!("iid{}", i + 1)
-        })?;
-        compute_field("father", &mut metadata.father, iid_count, |_| {
-            "0".to_string()
-        })?;
-        compute_field("mother", &mut metadata.mother, iid_count, |_| {
-            "0".to_string()
-        })?;
-        compute_field("sex", &mut metadata.sex, iid_count, |_| 0)?;
-        compute_field("pheno", &mut metadata.pheno, iid_count, |_| "0".to_string())?;
-        compute_field("chromosome", &mut metadata.chromosome, sid_count, |_| {
+    // Give each empty .fam field its default values.
+    fn fill_fam(&mut self, iid_count: usize) -> Result<(), Box<BedErrorPlus>> {
+        compute_field("fid", &mut self.fid, iid_count, |_| "0".to_string())?;
+        compute_field("iid", &mut self.iid, iid_count, |i| format!("iid{}", i + 1))?;
+        compute_field("father", &mut self.father, iid_count, |_| "0".to_string())?;
+        compute_field("mother", &mut self.mother, iid_count, |_| "0".to_string())?;
+        compute_field("sex", &mut self.sex, iid_count, |_| 0)?;
+        compute_field("pheno", &mut self.pheno, iid_count, |_| "0".to_string())?;
+        Ok(())
+    }
+
+    // Give each empty .bim field its default values.
+    fn fill_bim(&mut self, sid_count: usize) -> Result<(), Box<BedErrorPlus>> {
+        compute_field("chromosome", &mut self.chromosome, sid_count, |_| {
             "0".to_string()
         })?;
-        compute_field("sid", &mut metadata.sid, sid_count, |i| {
-            format!("sid{}", i + 1)
-        })?;
-        compute_field("cm_position", &mut metadata.cm_position, sid_count, |_| 0.0)?;
-        compute_field("bp_position", &mut metadata.bp_position, sid_count, |_| 0)?;
-        compute_field("allele_1", &mut metadata.allele_1, sid_count, |_| {
+        compute_field("sid", &mut self.sid, sid_count, |i| format!("sid{}", i + 1))?;
+        compute_field("cm_position", &mut self.cm_position, sid_count, |_| 0.0)?;
+        compute_field("bp_position", &mut self.bp_position, sid_count, |_| 0)?;
+        compute_field("allele_1", &mut self.allele_1, sid_count, |_| {
             "A1".to_string()
         })?;
-        compute_field("allele_2", &mut metadata.allele_2, sid_count, |_| {
+        compute_field("allele_2", &mut self.allele_2, sid_count, |_| {
             "A2".to_string()
         })?;
+        Ok(())
+    }
 
-        Ok(metadata)
+    // Truncate or pad (with fill's defaults) every present individual field to iid_count.
+    fn resize_fam(&self, iid_count: usize) -> Metadata {
+        let mut metadata = self.clone();
+
+        resize_field(&mut metadata.fid, iid_count, |_| "0".to_string());
+        resize_field(&mut metadata.iid, iid_count, |i| format!("iid{}", i + 1));
+        resize_field(&mut metadata.father, iid_count, |_| "0".to_string());
+        resize_field(&mut metadata.mother, iid_count, |_| "0".to_string());
+        resize_field(&mut metadata.sex, iid_count, |_| 0);
+        resize_field(&mut metadata.pheno, iid_count, |_| "0".to_string());
+
+        metadata
+    }
+
+    // Truncate or pad (with fill's defaults) every present SNP field to sid_count.
+    fn resize_bim(&self, sid_count: usize) -> Metadata {
+        let mut metadata = self.clone();
+
+        resize_field(&mut metadata.chromosome, sid_count, |_| "0".to_string());
+        resize_field(&mut metadata.sid, sid_count, |i| format!("sid{}", i + 1));
+        resize_field(&mut metadata.cm_position, sid_count, |_| 0.0);
+        resize_field(&mut metadata.bp_position, sid_count, |_| 0);
+        resize_field(&mut metadata.allele_1, sid_count, |_| "A1".to_string());
+        resize_field(&mut metadata.allele_2, sid_count, |_| "A2".to_string());
+        for annotation in metadata.sid_annotations.values_mut() {
+            if annotation.len() != sid_count {
+                *annotation = Arc::new(annotation.resize(sid_count));
+            }
+        }
+
+        metadata
     }
 
     #[anyinput]
     fn set_fid(&mut self, fid: AnyIter<AnyString>) -> &Self {
-        self.fid = Some(Rc::new(
+        self.fid = Some(Arc::new(
             fid.into_iter().map(|s| s.as_ref().to_owned()).collect(),
         ));
         self
@@ -6975,7 +9265,7 @@ impl Metadata {
 
     #[anyinput]
     fn set_iid(&mut self, iid: AnyIter<AnyString>) -> &Self {
-        self.iid = Some(Rc::new(
+        self.iid = Some(Arc::new(
             iid.into_iter().map(|s| s.as_ref().to_owned()).collect(),
         ));
         self
@@ -6983,76 +9273,78 @@ impl Metadata {
 
     #[anyinput]
     fn set_father(&mut self, father: AnyIter<AnyString>) -> &Self {
-        self.father = Some(Rc::new(father.map(|s| s.as_ref().to_owned()).collect()));
+        self.father = Some(Arc::new(father.map(|s| s.as_ref().to_owned()).collect()));
         self
     }
 
     #[anyinput]
     fn set_mother(&mut self, mother: AnyIter<AnyString>) -> &Self {
-        self.mother = Some(Rc::new(mother.map(|s| s.as_ref().to_owned()).collect()));
+        self.mother = Some(Arc::new(mother.map(|s| s.as_ref().to_owned()).collect()));
         self
     }
 
     #[anyinput]
     fn set_sex(&mut self, sex: AnyIter<i32>) -> &Self {
-        self.sex = Some(Rc::new(sex.collect()));
+        self.sex = Some(Arc::new(sex.collect()));
         self
     }
 
     #[anyinput]
     fn set_pheno(&mut self, pheno: AnyIter<AnyString>) -> &Self {
-        self.pheno = Some(Rc::new(pheno.map(|s| s.as_ref().to_owned()).collect()));
+        self.pheno = Some(Arc::new(pheno.map(|s| s.as_ref().to_owned()).collect()));
         self
     }
 
     #[anyinput]
     fn set_chromosome(&mut self, chromosome: AnyIter<AnyString>) -> &Self {
-        self.chromosome = Some(Rc::new(chromosome.map(|s| s.as_ref().to_owned()).collect()));
+        self.chromosome = Some(Arc::new(
+            chromosome.map(|s| s.as_ref().to_owned()).collect(),
+        ));
         self
     }
 
     #[anyinput]
     fn set_sid(&mut self, sid: AnyIter<AnyString>) -> &Self {
-        self.sid = Some(Rc::new(sid.map(|s| s.as_ref().to_owned()).collect()));
+        self.sid = Some(Arc::new(sid.map(|s| s.as_ref().to_owned()).collect()));
         self
     }
 
     #[anyinput]
     fn set_cm_position(&mut self, cm_position: AnyIter<f32>) -> &Self {
-        self.cm_position = Some(Rc::new(cm_position.into_iter().collect()));
+        self.cm_position = Some(Arc::new(cm_position.into_iter().collect()));
         self
     }
 
     #[anyinput]
     fn set_bp_position(&mut self, bp_position: AnyIter<i32>) -> &Self {
-        self.bp_position = Some(Rc::new(bp_position.into_iter().collect()));
+        self.bp_position = Some(Arc::new(bp_position.into_iter().collect()));
         self
     }
 
     #[anyinput]
     fn set_allele_1(&mut self, allele_1: AnyIter<AnyString>) -> &Self {
-        self.allele_1 = Some(Rc::new(allele_1.map(|s| s.as_ref().to_owned()).collect()));
+        self.allele_1 = Some(Arc::new(allele_1.map(|s| s.as_ref().to_owned()).collect()));
         self
     }
 
     #[anyinput]
     fn set_allele_2(&mut self, allele_2: AnyIter<AnyString>) -> &Self {
-        self.allele_2 = Some(Rc::new(allele_2.map(|s| s.as_ref().to_owned()).collect()));
+        self.allele_2 = Some(Arc::new(allele_2.map(|s| s.as_ref().to_owned()).collect()));
         self
     }
 }
 
 #[allow(clippy::option_option)]
 fn set_field<T>(
-    field1: &Option<Rc<nd::Array1<T>>>,
-    field2: &mut Option<Option<Rc<nd::Array1<T>>>>,
+    field1: &Option<Arc<nd::Array1<T>>>,
+    field2: &mut Option<Option<Arc<nd::Array1<T>>>>,
 ) {
     if let Some(array) = field1 {
         *field2 = Some(Some(array.clone()));
     }
 }
 
-fn option_rc_as_ref<T>(field: &Option<Rc<nd::Array1<T>>>) -> Option<&nd::Array1<T>> {
+fn option_rc_as_ref<T>(field: &Option<Arc<nd::Array1<T>>>) -> Option<&nd::Array1<T>> {
     match field {
         Some(array) => Some(array.as_ref()),
         None => None,
@@ -7085,7 +9377,7 @@ fn matrix_subset_no_alloc<
     // If output is F-order (or in general if iid stride is no more than sid_stride)
     if out_val.stride_of(nd::Axis(0)) <= out_val.stride_of(nd::Axis(1)) {
         // (No error are possible in the par_azip, so don't have to collect and check them)
-        nd::par_azip!((mut out_col in out_val.axis_iter_mut(nd::Axis(1)),
+        par_azip!((mut out_col in out_val.axis_iter_mut(nd::Axis(1)),
                     in_sid_i_pr in sid_index) {
             let in_col = in_val.index_axis(nd::Axis(1), *in_sid_i_pr);
             for did_i in 0..did_count
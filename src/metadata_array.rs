@@ -0,0 +1,221 @@
+use ndarray as nd;
+use std::sync::Arc;
+
+use crate::{BedError, BedErrorPlus, Metadata, MetadataBuilder, MetadataFields};
+
+impl MetadataFields {
+    /// All the fields, .fam fields then .bim fields, in file order.
+    pub const ALL: [MetadataFields; 12] = [
+        MetadataFields::Fid,
+        MetadataFields::Iid,
+        MetadataFields::Father,
+        MetadataFields::Mother,
+        MetadataFields::Sex,
+        MetadataFields::Pheno,
+        MetadataFields::Chromosome,
+        MetadataFields::Sid,
+        MetadataFields::CmPosition,
+        MetadataFields::BpPosition,
+        MetadataFields::Allele1,
+        MetadataFields::Allele2,
+    ];
+
+    /// The field's name, for example, `"bp_position"`, as used by its methods.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            MetadataFields::Fid => "fid",
+            MetadataFields::Iid => "iid",
+            MetadataFields::Father => "father",
+            MetadataFields::Mother => "mother",
+            MetadataFields::Sex => "sex",
+            MetadataFields::Pheno => "pheno",
+            MetadataFields::Chromosome => "chromosome",
+            MetadataFields::Sid => "sid",
+            MetadataFields::CmPosition => "cm_position",
+            MetadataFields::BpPosition => "bp_position",
+            MetadataFields::Allele1 => "allele_1",
+            MetadataFields::Allele2 => "allele_2",
+        }
+    }
+}
+
+/// The values of one metadata field, viewed as the field's type.
+///
+/// Returned by [`Metadata::field`](struct.Metadata.html#method.field) and given to
+/// [`MetadataBuilder::field`](struct.MetadataBuilder.html#method.field), so tools can handle
+/// any field without matching on each one.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{Metadata, MetadataArray, MetadataFields};
+///
+/// let metadata = Metadata::builder()
+///     .iid(["i1", "i2"])
+///     .bp_position([100, 2000])
+///     .build()?;
+/// for field in MetadataFields::ALL {
+///     if let Some(values) = metadata.field(field) {
+///         println!("{}: {:?}", field.name(), values.to_strings());
+///     }
+/// }
+/// assert_eq!(
+///     metadata.field(MetadataFields::BpPosition),
+///     Some(MetadataArray::I32(nd::array![100, 2000].view()))
+/// );
+/// assert_eq!(metadata.field(MetadataFields::Sid), None);
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetadataArray<'a> {
+    /// The values of `fid`, `iid`, `father`, `mother`, `pheno`, `chromosome`, `sid`,
+    /// `allele_1`, or `allele_2`.
+    String(nd::ArrayView1<'a, String>),
+    /// The values of `sex` or `bp_position`.
+    I32(nd::ArrayView1<'a, i32>),
+    /// The values of `cm_position`.
+    F32(nd::ArrayView1<'a, f32>),
+}
+
+impl MetadataArray<'_> {
+    /// The number of values.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        match self {
+            MetadataArray::String(values) => values.len(),
+            MetadataArray::I32(values) => values.len(),
+            MetadataArray::F32(values) => values.len(),
+        }
+    }
+
+    /// True if there are no values.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The values as text, formatted as in .fam and .bim files.
+    ///
+    /// > See [`MetadataArray`](enum.MetadataArray.html) for an example.
+    #[must_use]
+    pub fn to_strings(&self) -> nd::Array1<String> {
+        match self {
+            MetadataArray::String(values) => values.to_owned(),
+            MetadataArray::I32(values) => values.map(ToString::to_string),
+            MetadataArray::F32(values) => values.map(ToString::to_string),
+        }
+    }
+}
+
+impl Metadata {
+    /// The values of a field, if known, whatever its type.
+    ///
+    /// > See [`MetadataArray`](enum.MetadataArray.html) for an example.
+    #[must_use]
+    pub fn field(&self, field: MetadataFields) -> Option<MetadataArray<'_>> {
+        fn string(values: Option<&Arc<nd::Array1<String>>>) -> Option<MetadataArray<'_>> {
+            values.map(|values| MetadataArray::String(values.view()))
+        }
+        match field {
+            MetadataFields::Fid => string(self.fid.as_ref()),
+            MetadataFields::Iid => string(self.iid.as_ref()),
+            MetadataFields::Father => string(self.father.as_ref()),
+            MetadataFields::Mother => string(self.mother.as_ref()),
+            MetadataFields::Sex => self
+                .sex
+                .as_ref()
+                .map(|values| MetadataArray::I32(values.view())),
+            MetadataFields::Pheno => string(self.pheno.as_ref()),
+            MetadataFields::Chromosome => string(self.chromosome.as_ref()),
+            MetadataFields::Sid => string(self.sid.as_ref()),
+            MetadataFields::CmPosition => self
+                .cm_position
+                .as_ref()
+                .map(|values| MetadataArray::F32(values.view())),
+            MetadataFields::BpPosition => self
+                .bp_position
+                .as_ref()
+                .map(|values| MetadataArray::I32(values.view())),
+            MetadataFields::Allele1 => string(self.allele_1.as_ref()),
+            MetadataFields::Allele2 => string(self.allele_2.as_ref()),
+        }
+    }
+}
+
+impl MetadataBuilder {
+    /// Set the values of a field, whatever its type.
+    ///
+    /// Returns a [`BedError::MetadataFieldType`](enum.BedError.html#variant.MetadataFieldType)
+    /// error if the values aren't of the field's type.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Metadata, MetadataArray, MetadataFields};
+    ///
+    /// let other = Metadata::builder().sex([1, 2]).iid(["i1", "i2"]).build()?;
+    /// let mut builder = Metadata::builder();
+    /// for field in [MetadataFields::Iid, MetadataFields::Sex] {
+    ///     // unwrap always works because other has these fields
+    ///     builder.field(field, other.field(field).unwrap())?;
+    /// }
+    /// let chromosome = nd::array!["1".to_string(), "X".to_string()];
+    /// builder.field(MetadataFields::Chromosome, MetadataArray::String(chromosome.view()))?;
+    /// let metadata = builder.build()?;
+    /// assert_eq!(metadata.sex(), Some(&nd::array![1, 2]));
+    /// assert_eq!(metadata.chromosome(), Some(&chromosome));
+    ///
+    /// let sex = nd::array![1, 2];
+    /// assert!(builder.field(MetadataFields::CmPosition, MetadataArray::I32(sex.view())).is_err());
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn field(
+        &mut self,
+        field: MetadataFields,
+        values: MetadataArray<'_>,
+    ) -> Result<&mut Self, Box<BedErrorPlus>> {
+        match (field, values) {
+            (MetadataFields::Fid, MetadataArray::String(values)) => {
+                self.fid = Some(Some(Arc::new(values.to_owned())));
+            }
+            (MetadataFields::Iid, MetadataArray::String(values)) => {
+                self.iid = Some(Some(Arc::new(values.to_owned())));
+            }
+            (MetadataFields::Father, MetadataArray::String(values)) => {
+                self.father = Some(Some(Arc::new(values.to_owned())));
+            }
+            (MetadataFields::Mother, MetadataArray::String(values)) => {
+                self.mother = Some(Some(Arc::new(values.to_owned())));
+            }
+            (MetadataFields::Sex, MetadataArray::I32(values)) => {
+                self.sex = Some(Some(Arc::new(values.to_owned())));
+            }
+            (MetadataFields::Pheno, MetadataArray::String(values)) => {
+                self.pheno = Some(Some(Arc::new(values.to_owned())));
+            }
+            (MetadataFields::Chromosome, MetadataArray::String(values)) => {
+                self.chromosome = Some(Some(Arc::new(values.to_owned())));
+            }
+            (MetadataFields::Sid, MetadataArray::String(values)) => {
+                self.sid = Some(Some(Arc::new(values.to_owned())));
+            }
+            (MetadataFields::CmPosition, MetadataArray::F32(values)) => {
+                self.cm_position = Some(Some(Arc::new(values.to_owned())));
+            }
+            (MetadataFields::BpPosition, MetadataArray::I32(values)) => {
+                self.bp_position = Some(Some(Arc::new(values.to_owned())));
+            }
+            (MetadataFields::Allele1, MetadataArray::String(values)) => {
+                self.allele_1 = Some(Some(Arc::new(values.to_owned())));
+            }
+            (MetadataFields::Allele2, MetadataArray::String(values)) => {
+                self.allele_2 = Some(Some(Arc::new(values.to_owned())));
+            }
+            (field, _) => Err(BedError::MetadataFieldType(field.name().to_string()))?,
+        }
+        Ok(self)
+    }
+}
@@ -0,0 +1,301 @@
+use ndarray as nd;
+use std::cmp::Ordering;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::Metadata;
+
+// The most example differences kept for each field.
+const MAX_DIFF_EXAMPLES: usize = 5;
+
+/// The differences between two [`Metadata`](struct.Metadata.html)s, field by field.
+///
+/// Created by [`Metadata::diff`](struct.Metadata.html#method.diff). Its `Display` output is a
+/// human-readable summary. With the `serde` feature, it can also be serialized, for example,
+/// to JSON for a CI check.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MetadataDiff {
+    fields: Vec<FieldDiff>,
+}
+
+/// How one metadata field differs between two [`Metadata`](struct.Metadata.html)s.
+///
+/// > See [`Metadata::diff`](struct.Metadata.html#method.diff) for an example.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FieldDiff {
+    name: String,
+    count_a: Option<usize>,
+    count_b: Option<usize>,
+    differing_count: usize,
+    examples: Vec<DiffExample>,
+}
+
+/// One differing entry of a metadata field, as shown in a [`FieldDiff`](struct.FieldDiff.html).
+///
+/// A value is `None` when that metadata has no entry at the index.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiffExample {
+    index: usize,
+    a: Option<String>,
+    b: Option<String>,
+}
+
+// Compare one field. A missing field counts as having no entries.
+fn diff_field<T, F>(
+    field: &str,
+    a: Option<&Arc<nd::Array1<T>>>,
+    b: Option<&Arc<nd::Array1<T>>>,
+    is_equal: F,
+) -> Option<FieldDiff>
+where
+    T: ToString,
+    F: Fn(&T, &T) -> bool,
+{
+    let empty = nd::Array1::<T>::from_vec(Vec::new());
+    let a_values = a.map_or(&empty, |a| &**a);
+    let b_values = b.map_or(&empty, |b| &**b);
+
+    let mut differing_count = 0;
+    let mut examples = Vec::new();
+    for index in 0..a_values.len().max(b_values.len()) {
+        let a_value = a_values.get(index);
+        let b_value = b_values.get(index);
+        let is_same = match (a_value, b_value) {
+            (Some(a_value), Some(b_value)) => is_equal(a_value, b_value),
+            _ => false,
+        };
+        if !is_same {
+            differing_count += 1;
+            if examples.len() < MAX_DIFF_EXAMPLES {
+                examples.push(DiffExample {
+                    index,
+                    a: a_value.map(ToString::to_string),
+                    b: b_value.map(ToString::to_string),
+                });
+            }
+        }
+    }
+
+    if differing_count == 0 && a.is_some() == b.is_some() {
+        return None;
+    }
+    Some(FieldDiff {
+        name: field.to_string(),
+        count_a: a.map(|a| a.len()),
+        count_b: b.map(|b| b.len()),
+        differing_count,
+        examples,
+    })
+}
+
+impl Metadata {
+    /// Compare this metadata (a) with another (b), field by field.
+    ///
+    /// For each field that differs, the result gives the number of entries in each metadata
+    /// (or `None` if the field isn't set), the number of differing entries, and up to five
+    /// example differences. Entries are compared by position. NaN centimorgan positions
+    /// are equal to each other.
+    ///
+    /// Use this to check that metadata is unchanged between pipeline stages.
+    ///
+    /// # Example
+    /// ```
+    /// use bed_reader::{Bed, Metadata, sample_bed_file};
+    ///
+    /// let bed = Bed::new(sample_bed_file("small.bed")?)?;
+    /// let metadata_a = bed.metadata()?;
+    /// let metadata_b = Metadata::builder()
+    ///     .iid(["iid1", "iid2", "x3"])
+    ///     .build()?
+    ///     .fill(3, 4)?;
+    /// let diff = metadata_a.diff(&metadata_b);
+    /// println!("{diff}");
+    /// // Outputs:
+    /// // fid: 3 of 3 entries differ (3 vs 3)
+    /// //   [0] "fid1" vs "0"
+    /// // ...
+    /// let iid_diff = diff.field("iid").unwrap();
+    /// assert_eq!(iid_diff.differing_count(), 1);
+    /// assert_eq!(iid_diff.examples()[0].b(), Some("x3"));
+    /// assert!(metadata_a.diff(&metadata_a).is_empty());
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[must_use]
+    pub fn diff(&self, other: &Metadata) -> MetadataDiff {
+        let string_eq = |a: &String, b: &String| a == b;
+        let fields = [
+            diff_field("fid", self.fid.as_ref(), other.fid.as_ref(), string_eq),
+            diff_field("iid", self.iid.as_ref(), other.iid.as_ref(), string_eq),
+            diff_field(
+                "father",
+                self.father.as_ref(),
+                other.father.as_ref(),
+                string_eq,
+            ),
+            diff_field(
+                "mother",
+                self.mother.as_ref(),
+                other.mother.as_ref(),
+                string_eq,
+            ),
+            diff_field("sex", self.sex.as_ref(), other.sex.as_ref(), |a, b| a == b),
+            diff_field(
+                "pheno",
+                self.pheno.as_ref(),
+                other.pheno.as_ref(),
+                string_eq,
+            ),
+            diff_field(
+                "chromosome",
+                self.chromosome.as_ref(),
+                other.chromosome.as_ref(),
+                string_eq,
+            ),
+            diff_field("sid", self.sid.as_ref(), other.sid.as_ref(), string_eq),
+            diff_field(
+                "cm_position",
+                self.cm_position.as_ref(),
+                other.cm_position.as_ref(),
+                |a: &f32, b: &f32| {
+                    a.partial_cmp(b) == Some(Ordering::Equal) || (a.is_nan() && b.is_nan())
+                },
+            ),
+            diff_field(
+                "bp_position",
+                self.bp_position.as_ref(),
+                other.bp_position.as_ref(),
+                |a, b| a == b,
+            ),
+            diff_field(
+                "allele_1",
+                self.allele_1.as_ref(),
+                other.allele_1.as_ref(),
+                string_eq,
+            ),
+            diff_field(
+                "allele_2",
+                self.allele_2.as_ref(),
+                other.allele_2.as_ref(),
+                string_eq,
+            ),
+        ];
+        MetadataDiff {
+            fields: fields.into_iter().flatten().collect(),
+        }
+    }
+}
+
+impl MetadataDiff {
+    /// The fields that differ, in .fam then .bim order.
+    #[must_use]
+    pub fn fields(&self) -> &[FieldDiff] {
+        &self.fields
+    }
+
+    /// The differences for a field, by name (for example, "iid"), or `None` if it doesn't differ.
+    #[must_use]
+    pub fn field(&self, name: &str) -> Option<&FieldDiff> {
+        self.fields
+            .iter()
+            .find(|field_diff| field_diff.name == name)
+    }
+
+    /// True if no fields differ.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
+
+impl FieldDiff {
+    /// The field's name, for example, "iid".
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The number of entries in the first metadata, or `None` if the field isn't set.
+    #[must_use]
+    pub fn count_a(&self) -> Option<usize> {
+        self.count_a
+    }
+
+    /// The number of entries in the second metadata, or `None` if the field isn't set.
+    #[must_use]
+    pub fn count_b(&self) -> Option<usize> {
+        self.count_b
+    }
+
+    /// The number of positions whose entries differ, including positions only one metadata has.
+    #[must_use]
+    pub fn differing_count(&self) -> usize {
+        self.differing_count
+    }
+
+    /// The first few differing entries.
+    #[must_use]
+    pub fn examples(&self) -> &[DiffExample] {
+        &self.examples
+    }
+}
+
+impl DiffExample {
+    /// The position of the entry.
+    #[must_use]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The entry in the first metadata.
+    #[must_use]
+    pub fn a(&self) -> Option<&str> {
+        self.a.as_deref()
+    }
+
+    /// The entry in the second metadata.
+    #[must_use]
+    pub fn b(&self) -> Option<&str> {
+        self.b.as_deref()
+    }
+}
+
+fn fmt_count(count: Option<usize>) -> String {
+    count.map_or_else(|| "unset".to_string(), |count| count.to_string())
+}
+
+fn fmt_value(value: Option<&str>) -> String {
+    value.map_or_else(|| "(none)".to_string(), |value| format!("{value:?}"))
+}
+
+impl fmt::Display for MetadataDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.fields.is_empty() {
+            return writeln!(f, "metadata is the same");
+        }
+        for field_diff in &self.fields {
+            writeln!(
+                f,
+                "{}: {} of {} entries differ ({} vs {})",
+                field_diff.name,
+                field_diff.differing_count,
+                field_diff.count_a.max(field_diff.count_b).unwrap_or(0),
+                fmt_count(field_diff.count_a),
+                fmt_count(field_diff.count_b),
+            )?;
+            for example in &field_diff.examples {
+                writeln!(
+                    f,
+                    "  [{}] {} vs {}",
+                    example.index,
+                    fmt_value(example.a()),
+                    fmt_value(example.b()),
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+use crate::dedup::select_field;
+use crate::{BedError, BedErrorPlus, Index, Metadata};
+
+// The indexes an Index selects from count items, with negative indexes counted from the end.
+fn resolve(
+    index: &Index,
+    count: usize,
+    too_big: fn(isize) -> BedError,
+) -> Result<Vec<usize>, Box<BedErrorPlus>> {
+    index
+        .to_vec(count)?
+        .into_iter()
+        .map(|signed_i| {
+            let resolved = if signed_i < 0 {
+                count.checked_sub(signed_i.unsigned_abs())
+            } else {
+                Some(signed_i.unsigned_abs()).filter(|&i| i < count)
+            };
+            resolved.ok_or_else(|| too_big(signed_i).into())
+        })
+        .collect()
+}
+
+impl Metadata {
+    /// A copy of this metadata with only the individuals (samples) selected by `iid_index`, in
+    /// its order.
+    ///
+    /// `iid_index` can be any [`Index`](enum.Index.html) expression that
+    /// [`ReadOptionsBuilder::iid_index`](struct.ReadOptionsBuilder.html#method.iid_index)
+    /// accepts, for example, a position, a negative position counted from the end, a `Vec` of
+    /// positions, a range, an `s!` slice, or a `bool` array, so the metadata can match
+    /// genotypes read with the same index. The .bim fields are unchanged and fields that are
+    /// `None` stay `None`.
+    ///
+    /// Returns a [`BedError::IidIndexTooBig`](enum.BedError.html#variant.IidIndexTooBig) error
+    /// if a position is out of range.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, ReadOptions, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("filter.bed");
+    /// WriteOptions::builder(&output_file)
+    ///     .iid(["i1", "i2", "i3"])
+    ///     .sid(["s1", "s2", "s3", "s4"])
+    ///     .write(&nd::array![[1, 0, -127, 0], [2, 0, -127, 2], [0, 1, 2, 0]])?;
+    ///
+    /// let mut bed = Bed::new(&output_file)?;
+    /// let (iid_index, sid_index) = (vec![-1, 0], nd::s![1..;2]);
+    /// let val = ReadOptions::builder()
+    ///     .iid_index(&iid_index)
+    ///     .sid_index(sid_index)
+    ///     .i8()
+    ///     .read(&mut bed)?;
+    /// let metadata = bed.metadata()?.filter_iid(&iid_index)?.filter_sid(sid_index)?;
+    /// assert_eq!(val, nd::array![[1, 0], [0, 0]]);
+    /// assert_eq!(metadata.iid(), Some(&nd::array!["i3", "i1"].map(|s| s.to_string())));
+    /// assert_eq!(metadata.sid(), Some(&nd::array!["s2", "s4"].map(|s| s.to_string())));
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn filter_iid(&self, iid_index: impl Into<Index>) -> Result<Metadata, Box<BedErrorPlus>> {
+        let Some(iid_count) = self.check_counts(None, None)?.0 else {
+            return Ok(self.clone());
+        };
+        let iid_index = resolve(&iid_index.into(), iid_count, BedError::IidIndexTooBig)?;
+        let mut metadata = self.clone();
+        metadata.fid = select_field(self.fid.as_ref(), &iid_index);
+        metadata.iid = select_field(self.iid.as_ref(), &iid_index);
+        metadata.father = select_field(self.father.as_ref(), &iid_index);
+        metadata.mother = select_field(self.mother.as_ref(), &iid_index);
+        metadata.sex = select_field(self.sex.as_ref(), &iid_index);
+        metadata.pheno = select_field(self.pheno.as_ref(), &iid_index);
+        Ok(metadata)
+    }
+
+    /// A copy of this metadata with only the SNPs (variants) selected by `sid_index`, in its
+    /// order, including any SNP annotations.
+    ///
+    /// Like [`Metadata::filter_iid`](struct.Metadata.html#method.filter_iid), but for the .bim
+    /// fields. Returns a [`BedError::SidIndexTooBig`](enum.BedError.html#variant.SidIndexTooBig)
+    /// error if a position is out of range.
+    ///
+    /// > See [`Metadata::filter_iid`](struct.Metadata.html#method.filter_iid) for an example.
+    pub fn filter_sid(&self, sid_index: impl Into<Index>) -> Result<Metadata, Box<BedErrorPlus>> {
+        let Some(sid_count) = self.check_counts(None, None)?.1 else {
+            return Ok(self.clone());
+        };
+        let sid_index = resolve(&sid_index.into(), sid_count, BedError::SidIndexTooBig)?;
+        let mut metadata = self.clone();
+        metadata.chromosome = select_field(self.chromosome.as_ref(), &sid_index);
+        metadata.sid = select_field(self.sid.as_ref(), &sid_index);
+        metadata.cm_position = select_field(self.cm_position.as_ref(), &sid_index);
+        metadata.bp_position = select_field(self.bp_position.as_ref(), &sid_index);
+        metadata.allele_1 = select_field(self.allele_1.as_ref(), &sid_index);
+        metadata.allele_2 = select_field(self.allele_2.as_ref(), &sid_index);
+        metadata.sid_annotations = self.select_sid_annotations(&sid_index);
+        Ok(metadata)
+    }
+}
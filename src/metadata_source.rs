@@ -0,0 +1,314 @@
+use anyinput::anyinput;
+use ndarray as nd;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::{count_lines, Bed, BedBuilder, BedErrorPlus, Metadata, MetadataFields};
+
+pub(crate) const FAM_FIELDS: [MetadataFields; 6] = [
+    MetadataFields::Fid,
+    MetadataFields::Iid,
+    MetadataFields::Father,
+    MetadataFields::Mother,
+    MetadataFields::Sex,
+    MetadataFields::Pheno,
+];
+
+const BIM_FIELDS: [MetadataFields; 6] = [
+    MetadataFields::Chromosome,
+    MetadataFields::Sid,
+    MetadataFields::CmPosition,
+    MetadataFields::BpPosition,
+    MetadataFields::Allele1,
+    MetadataFields::Allele2,
+];
+
+/// Where a [`Bed`](struct.Bed.html) finds the individual (sample) and SNP (variant) metadata
+/// usually kept in its .fam and .bim files.
+///
+/// Implement this trait to keep that metadata somewhere else, for example, in a database
+/// or a Parquet file, and give the source to
+/// [`BedBuilder::metadata_source`](struct.BedBuilder.html#method.metadata_source).
+/// [`FileMetadataSource`](struct.FileMetadataSource.html) reads .fam and .bim files.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use std::collections::HashSet;
+/// use bed_reader::{Bed, BedErrorPlus, Metadata, MetadataFields, MetadataSource, WriteOptions};
+///
+/// // A stand-in for a table of samples and variants in a database.
+/// #[derive(Debug)]
+/// struct InMemorySource {
+///     iid: Vec<String>,
+///     sid: Vec<String>,
+/// }
+///
+/// impl MetadataSource for InMemorySource {
+///     fn read_fam(
+///         &self,
+///         skip_set: &HashSet<MetadataFields>,
+///     ) -> Result<(Metadata, usize), Box<BedErrorPlus>> {
+///         let mut builder = Metadata::builder();
+///         if !skip_set.contains(&MetadataFields::Iid) {
+///             builder.iid(&self.iid);
+///         }
+///         Ok((builder.build()?, self.iid.len()))
+///     }
+///
+///     fn read_bim(
+///         &self,
+///         skip_set: &HashSet<MetadataFields>,
+///     ) -> Result<(Metadata, usize), Box<BedErrorPlus>> {
+///         let mut builder = Metadata::builder();
+///         if !skip_set.contains(&MetadataFields::Sid) {
+///             builder.sid(&self.sid);
+///         }
+///         Ok((builder.build()?, self.sid.len()))
+///     }
+/// }
+///
+/// let output_folder = temp_testdir::TempDir::default();
+/// let output_file = output_folder.join("no_text.bed");
+/// WriteOptions::builder(&output_file)
+///     .skip_fam()
+///     .skip_bim()
+///     .write(&nd::array![[0, 1], [2, 0], [1, 1]])?;
+///
+/// let source = InMemorySource {
+///     iid: vec!["a".to_string(), "b".to_string(), "c".to_string()],
+///     sid: vec!["rs1".to_string(), "rs2".to_string()],
+/// };
+/// let bed = Bed::builder(&output_file).metadata_source(source).build()?;
+/// assert_eq!(bed.dim()?, (3, 2));
+/// assert_eq!(bed.iid()?, nd::array!["a", "b", "c"]);
+/// assert_eq!(bed.sid()?, nd::array!["rs1", "rs2"]);
+/// assert_eq!(bed.read::<i8>()?, nd::array![[0, 1], [2, 0], [1, 1]]);
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+pub trait MetadataSource: Debug + Send + Sync {
+    /// Read the .fam fields (`fid`, `iid`, `father`, `mother`, `sex`, and `pheno`), leaving out those
+    /// in `skip_set`, and return them with the number of individuals (samples).
+    ///
+    /// Any field may be left `None`, in which case it gets its usual default value.
+    /// The fields returned must agree with the number returned.
+    fn read_fam(
+        &self,
+        skip_set: &HashSet<MetadataFields>,
+    ) -> Result<(Metadata, usize), Box<BedErrorPlus>>;
+
+    /// Read the .bim fields (`chromosome`, `sid`, `cm_position`, `bp_position`, `allele_1`, and
+    /// `allele_2`), leaving out those in `skip_set`, and return them with the number of SNPs (variants).
+    ///
+    /// Any field may be left `None`, in which case it gets its usual default value.
+    /// The fields returned must agree with the number returned.
+    fn read_bim(
+        &self,
+        skip_set: &HashSet<MetadataFields>,
+    ) -> Result<(Metadata, usize), Box<BedErrorPlus>>;
+
+    /// The number of individuals (samples).
+    ///
+    /// By default, calls [`read_fam`](trait.MetadataSource.html#tymethod.read_fam) with every
+    /// field skipped. Override if the number can be found more cheaply.
+    fn iid_count(&self) -> Result<usize, Box<BedErrorPlus>> {
+        let (_, iid_count) = self.read_fam(&FAM_FIELDS.into_iter().collect())?;
+        Ok(iid_count)
+    }
+
+    /// The number of SNPs (variants).
+    ///
+    /// By default, calls [`read_bim`](trait.MetadataSource.html#tymethod.read_bim) with every
+    /// field skipped. Override if the number can be found more cheaply.
+    fn sid_count(&self) -> Result<usize, Box<BedErrorPlus>> {
+        let (_, sid_count) = self.read_bim(&BIM_FIELDS.into_iter().collect())?;
+        Ok(sid_count)
+    }
+}
+
+/// A [`MetadataSource`](trait.MetadataSource.html) that reads a .fam and a .bim file.
+///
+/// A [`Bed`](struct.Bed.html) without a metadata source reads its .fam and .bim files in the
+/// same way. This source lets those files be chosen, or shared, apart from the .bed file.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{Bed, FileMetadataSource, WriteOptions};
+///
+/// let output_folder = temp_testdir::TempDir::default();
+/// let first_file = output_folder.join("first.bed");
+/// WriteOptions::builder(&first_file)
+///     .iid(["i1", "i2"])
+///     .write(&nd::array![[0, 1], [2, 0]])?;
+/// let second_file = output_folder.join("second.bed");
+/// WriteOptions::builder(&second_file)
+///     .skip_fam()
+///     .skip_bim()
+///     .write(&nd::array![[2, 2], [1, 0]])?;
+///
+/// let source = FileMetadataSource::new(
+///     output_folder.join("first.fam"),
+///     output_folder.join("first.bim"),
+/// );
+/// let bed = Bed::builder(&second_file).metadata_source(source).build()?;
+/// assert_eq!(bed.iid()?, nd::array!["i1", "i2"]);
+/// assert_eq!(bed.read::<i8>()?, nd::array![[2, 2], [1, 0]]);
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileMetadataSource {
+    fam_path: PathBuf,
+    bim_path: PathBuf,
+}
+
+impl FileMetadataSource {
+    /// A source that reads the given .fam and .bim files.
+    ///
+    /// > See [`FileMetadataSource`](struct.FileMetadataSource.html) for an example.
+    #[anyinput]
+    #[must_use]
+    pub fn new(fam_path: AnyPath, bim_path: AnyPath) -> FileMetadataSource {
+        FileMetadataSource {
+            fam_path: fam_path.to_path_buf(),
+            bim_path: bim_path.to_path_buf(),
+        }
+    }
+
+    /// The path of the .fam file.
+    #[must_use]
+    pub fn fam_path(&self) -> &Path {
+        &self.fam_path
+    }
+
+    /// The path of the .bim file.
+    #[must_use]
+    pub fn bim_path(&self) -> &Path {
+        &self.bim_path
+    }
+}
+
+impl MetadataSource for FileMetadataSource {
+    fn read_fam(
+        &self,
+        skip_set: &HashSet<MetadataFields>,
+    ) -> Result<(Metadata, usize), Box<BedErrorPlus>> {
+        Metadata::new().read_fam(&self.fam_path, skip_set)
+    }
+
+    fn read_bim(
+        &self,
+        skip_set: &HashSet<MetadataFields>,
+    ) -> Result<(Metadata, usize), Box<BedErrorPlus>> {
+        Metadata::new().read_bim(&self.bim_path, skip_set)
+    }
+
+    fn iid_count(&self) -> Result<usize, Box<BedErrorPlus>> {
+        count_lines(&self.fam_path)
+    }
+
+    fn sid_count(&self) -> Result<usize, Box<BedErrorPlus>> {
+        count_lines(&self.bim_path)
+    }
+}
+
+impl BedBuilder {
+    /// Find the .fam and .bim metadata in a [`MetadataSource`](trait.MetadataSource.html)
+    /// instead of in the .fam and .bim files next to the .bed file.
+    ///
+    /// The numbers of individuals (samples) and SNPs (variants) also come from the source.
+    /// Metadata given to the builder, for example, with
+    /// [`iid`](struct.BedBuilder.html#method.iid), still overrides the source's.
+    ///
+    /// > See [`MetadataSource`](trait.MetadataSource.html) for an example.
+    #[must_use]
+    pub fn metadata_source<S: MetadataSource + 'static>(mut self, source: S) -> Self {
+        self.metadata_source = Some(Some(Arc::new(source)));
+        self
+    }
+}
+
+impl Bed {
+    // Read the .fam fields not yet known, from the metadata source, if any, else from the .fam file.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn read_fam_fields(
+        &self,
+    ) -> Result<(Metadata, usize, HashMap<MetadataFields, nd::Array1<String>>), Box<BedErrorPlus>>
+    {
+        let skip_set = self.read_skip_set();
+        if let Some(source) = &self.metadata_source {
+            let mut skip_set = skip_set;
+            skip_set.extend(FAM_FIELDS.into_iter().filter(|&field| self.is_known(field)));
+            let (mut metadata, count) = source.read_fam(&skip_set)?;
+            metadata.fill_fam(count)?;
+            return Ok((self.metadata_over(&metadata), count, HashMap::new()));
+        }
+        let fam_path = self.fam_path();
+        self.metadata
+            .read_fam_unchecked(fam_path, &skip_set, &self.raw_set)
+    }
+
+    // Read the .bim fields not yet known, from the metadata source, if any, else from the .bim file.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn read_bim_fields(
+        &self,
+    ) -> Result<(Metadata, usize, HashMap<MetadataFields, nd::Array1<String>>), Box<BedErrorPlus>>
+    {
+        let skip_set = self.read_skip_set();
+        if let Some(source) = &self.metadata_source {
+            let mut skip_set = skip_set;
+            skip_set.extend(BIM_FIELDS.into_iter().filter(|&field| self.is_known(field)));
+            let (mut metadata, count) = source.read_bim(&skip_set)?;
+            metadata.fill_bim(count)?;
+            return Ok((self.metadata_over(&metadata), count, HashMap::new()));
+        }
+        let bim_path = self.bim_path();
+        self.metadata
+            .read_bim_unchecked(bim_path, &skip_set, &self.raw_set)
+    }
+
+    // True if the field's values are already known, so the source needn't supply them.
+    fn is_known(&self, field: MetadataFields) -> bool {
+        let metadata = &self.metadata;
+        match field {
+            MetadataFields::Fid => metadata.fid.is_some(),
+            MetadataFields::Iid => metadata.iid.is_some(),
+            MetadataFields::Father => metadata.father.is_some(),
+            MetadataFields::Mother => metadata.mother.is_some(),
+            MetadataFields::Sex => metadata.sex.is_some(),
+            MetadataFields::Pheno => metadata.pheno.is_some(),
+            MetadataFields::Chromosome => metadata.chromosome.is_some(),
+            MetadataFields::Sid => metadata.sid.is_some(),
+            MetadataFields::CmPosition => metadata.cm_position.is_some(),
+            MetadataFields::BpPosition => metadata.bp_position.is_some(),
+            MetadataFields::Allele1 => metadata.allele_1.is_some(),
+            MetadataFields::Allele2 => metadata.allele_2.is_some(),
+        }
+    }
+
+    // The known metadata, with its empty fields filled from `metadata`.
+    fn metadata_over(&self, metadata: &Metadata) -> Metadata {
+        Metadata::builder()
+            .metadata(metadata)
+            .metadata(&self.metadata)
+            .build_no_file_check()
+            .unwrap() // unwrap is ok because nothing can go wrong
+    }
+
+    // The number of individuals (samples) from the metadata source, if any.
+    pub(crate) fn source_iid_count(&self) -> Option<Result<usize, Box<BedErrorPlus>>> {
+        self.metadata_source
+            .as_ref()
+            .map(|source| source.iid_count())
+    }
+
+    // The number of SNPs (variants) from the metadata source, if any.
+    pub(crate) fn source_sid_count(&self) -> Option<Result<usize, Box<BedErrorPlus>>> {
+        self.metadata_source
+            .as_ref()
+            .map(|source| source.sid_count())
+    }
+}
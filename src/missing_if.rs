@@ -0,0 +1,58 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::{BedVal, WriteOptionsBuilder};
+
+// A user predicate that marks values, such as -9.0 sentinels, as missing.
+#[derive(Clone)]
+pub(crate) struct MissingIf(Arc<dyn Fn(f64) -> bool + Send + Sync>);
+
+impl MissingIf {
+    pub(crate) fn is_missing(&self, value: f64) -> bool {
+        (self.0)(value)
+    }
+}
+
+impl fmt::Debug for MissingIf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MissingIf(..)")
+    }
+}
+
+impl<TVal> WriteOptionsBuilder<TVal>
+where
+    TVal: BedVal,
+{
+    /// Also write as missing every value for which `predicate` returns true.
+    ///
+    /// Some inputs mark missing values with a sentinel, such as -9.0 or 9.0, rather than NaN.
+    /// The predicate is checked, during encoding, before any other test, so no separate pass
+    /// is needed to rewrite sentinels. Values equal to
+    /// [`missing_value`](struct.WriteOptionsBuilder.html#method.missing_value) are still missing.
+    /// Values are given to the predicate as f64.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("sentinels.bed");
+    /// let val = nd::array![[1.0, -9.0], [2.0, 0.0], [f64::NAN, 9.0]];
+    /// WriteOptions::builder(&output_file)
+    ///     .missing_if(|v| v == -9.0 || v == 9.0)
+    ///     .write(&val)?;
+    ///
+    /// let bed = Bed::new(&output_file)?;
+    /// assert_eq!(bed.read::<i8>()?, nd::array![[1, -127], [2, 0], [-127, -127]]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn missing_if<F>(&mut self, predicate: F) -> &mut Self
+    where
+        F: Fn(f64) -> bool + Send + Sync + 'static,
+    {
+        self.missing_if = Some(Some(MissingIf(Arc::new(predicate))));
+        self
+    }
+}
@@ -0,0 +1,152 @@
+use anyinput::anyinput;
+use memmap2::Mmap;
+use ndarray as nd;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::parallel::prelude::*;
+use crate::{
+    check_and_precompute_iid_index, create_pool, format, read_no_alloc, try_div_4, Bed, BedBuilder,
+    BedError, BedErrorPlus, BedVal, TwoBitsToValue, BED_FILE_MAGIC1, BED_FILE_MAGIC2,
+    CB_HEADER_U64, CB_HEADER_USIZE,
+};
+
+impl BedBuilder {
+    /// Read genotype data through a memory mapping of the .bed file.
+    ///
+    /// The .bed file is mapped on the first read and the mapping is kept for later reads, which
+    /// then decode the selected SNPs (variants) straight from memory, without a system call per
+    /// SNP. This helps with dense, repeated, random access, for example, reading thousands of
+    /// scattered SNPs in each round of a permutation test. With mapping on, reads with the
+    /// default [`ReadStrategy::Sequential`](enum.ReadStrategy.html#variant.Sequential) use the
+    /// mapping. A single read can also ask for it with
+    /// [`ReadStrategy::Mmap`](enum.ReadStrategy.html#variant.Mmap). Defaults to `false`.
+    ///
+    /// The .bed file must not be changed, by this or any other process, while mapped.
+    /// See [`watch`](struct.BedBuilder.html#method.watch) to notice changes between reads.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, ReadOptions, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("mapped.bed");
+    /// WriteOptions::builder(&output_file).write(&nd::array![[0, 1, 2], [2, -127, 0]])?;
+    ///
+    /// let mut bed = Bed::builder(&output_file).mmap(true).build()?;
+    /// for _ in 0..3 {
+    ///     let val = ReadOptions::builder().sid_index([2, 0]).i8().read(&mut bed)?;
+    ///     assert_eq!(val, nd::array![[2, 0], [0, 2]]);
+    /// }
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[must_use]
+    pub fn mmap(mut self, is_mmap: bool) -> Self {
+        self.is_mmap = Some(is_mmap);
+        self
+    }
+}
+
+impl Bed {
+    // The memory mapping of the .bed file, made on first use.
+    pub(crate) fn mapping(&self) -> Result<Arc<Mmap>, Box<BedErrorPlus>> {
+        if let Some(mapping) = self.mapping.get() {
+            return Ok(Arc::clone(mapping));
+        }
+        let mapping = Arc::new(map_file(&self.path)?);
+        Ok(Arc::clone(self.mapping.get_or_init(|| mapping)))
+    }
+}
+
+// Map a .bed file into memory.
+pub(crate) fn map_file(path: &Path) -> Result<Mmap, Box<BedErrorPlus>> {
+    let file = File::open(path)?;
+    // SAFETY: The mapping is only read. As documented on BedBuilder::mmap, the .bed file
+    // must not be changed while mapped.
+    Ok(unsafe { Mmap::map(&file)? })
+}
+
+// Like read_no_alloc, but decodes the SNPs from a memory mapping of the .bed file.
+#[allow(clippy::too_many_arguments)]
+#[anyinput]
+pub(crate) fn read_mmap<TVal: BedVal>(
+    path: AnyPath,
+    mapping: &[u8],
+    iid_count: usize,
+    sid_count: usize,
+    is_a1_counted: bool,
+    count_a1_mask: Option<&nd::Array1<bool>>,
+    iid_index: &[isize],
+    sid_index: &[isize],
+    missing_value: TVal,
+    num_threads: usize,
+    val: &mut nd::ArrayViewMut2<'_, TVal>,
+) -> Result<(), Box<BedErrorPlus>> {
+    if mapping.len() < CB_HEADER_USIZE
+        || mapping[0] != BED_FILE_MAGIC1
+        || mapping[1] != BED_FILE_MAGIC2
+    {
+        let file_format = format::detect_format(path)?;
+        Err(file_format.to_error(path, BedError::IllFormed(path.to_path_buf())))?;
+    }
+    if mapping[2] != 1 {
+        return read_no_alloc(
+            path,
+            iid_count,
+            sid_count,
+            is_a1_counted,
+            count_a1_mask,
+            iid_index,
+            sid_index,
+            missing_value,
+            num_threads,
+            val,
+        );
+    }
+
+    let iid_count_div4_u64 = try_div_4(iid_count, sid_count)?;
+    if mapping.len() as u64 != iid_count_div4_u64 * (sid_count as u64) + CB_HEADER_U64 {
+        Err(BedError::IllFormed(path.to_path_buf()))?;
+    }
+
+    create_pool(num_threads)?.install(|| {
+        let (i_div_4_less_start_array, i_mod_4_times_2_array, i_div_4_start, i_div_4_len) =
+            check_and_precompute_iid_index(iid_count, iid_index)?;
+        let two_bits_to_value = TwoBitsToValue::new(is_a1_counted, count_a1_mask, missing_value);
+
+        // "as" and math is safe because of the file-length check
+        let start_list = sid_index
+            .iter()
+            .map(|&in_sid_i_signed| {
+                let in_sid_i = if (0..sid_count as isize).contains(&in_sid_i_signed) {
+                    in_sid_i_signed as u64
+                } else if (-(sid_count as isize)..0).contains(&in_sid_i_signed) {
+                    (sid_count as isize + in_sid_i_signed) as u64
+                } else {
+                    Err(BedError::SidIndexTooBig(in_sid_i_signed))?
+                };
+                Ok((
+                    (in_sid_i * iid_count_div4_u64 + i_div_4_start + CB_HEADER_U64) as usize,
+                    two_bits_to_value.for_sid(in_sid_i as usize),
+                ))
+            })
+            .collect::<Result<Vec<(usize, [TVal; 4])>, Box<BedErrorPlus>>>()?;
+
+        val.axis_iter_mut(nd::Axis(1))
+            .into_par_iter()
+            .zip(start_list.par_iter())
+            .for_each(|(mut col, &(start, from_two_bits_to_value))| {
+                let bytes = &mapping[start..start + i_div_4_len as usize];
+                for out_iid_i in 0..iid_index.len() {
+                    let genotype_byte = (bytes[i_div_4_less_start_array[out_iid_i]]
+                        >> i_mod_4_times_2_array[out_iid_i])
+                        & 0x03;
+                    col[out_iid_i] = from_two_bits_to_value[genotype_byte as usize];
+                }
+            });
+        Ok(())
+    })
+}
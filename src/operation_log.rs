@@ -0,0 +1,202 @@
+use anyinput::anyinput;
+use std::fmt::Write as _;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::{BedErrorPlus, BedVal, WriteOptions, WriteOptionsBuilder, WriteSummary};
+
+/// A human-readable log file, in the style of PLINK's .log files, to which operations
+/// append a report of their inputs, options, counts, warnings, and outcome.
+///
+/// Give it to [`WriteOptionsBuilder::log`](struct.WriteOptionsBuilder.html#method.log), which
+/// also reports writes made with a [`BedWriter`](struct.BedWriter.html). Each operation, whether it
+/// succeeds or fails, appends one report, so several operations can share a log. Clones append
+/// to the same file.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{OperationLog, WriteOptions};
+///
+/// let output_folder = temp_testdir::TempDir::default();
+/// let output_file = output_folder.join("logged.bed");
+/// let log = OperationLog::new(output_folder.join("logged.log"));
+/// WriteOptions::builder(&output_file)
+///     .sex([1, 2, 0])
+///     .log(log.clone())
+///     .write(&nd::array![[0, 1], [2, -127], [1, 1]])?;
+///
+/// let text = std::fs::read_to_string(log.path())?;
+/// assert!(text.contains("3 people (1 male, 1 female, 1 ambiguous) written to"));
+/// assert!(text.contains("2 variants written to"));
+/// assert!(text.contains("Total genotyping rate is 0.833333."));
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperationLog {
+    path: PathBuf,
+}
+
+impl OperationLog {
+    /// A log that appends to the file at `path`, creating it if needed.
+    ///
+    /// > See [`OperationLog`](struct.OperationLog.html) for an example.
+    #[anyinput]
+    #[must_use]
+    pub fn new(path: AnyPath) -> OperationLog {
+        OperationLog {
+            path: path.to_path_buf(),
+        }
+    }
+
+    /// The path of the log file.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Append one operation's report: its name, the options in effect, what it did, and how it ended.
+    fn append_report(
+        &self,
+        operation: &str,
+        options: &[(&str, String)],
+        lines: &[String],
+        outcome: Result<String, &BedErrorPlus>,
+    ) -> Result<(), Box<BedErrorPlus>> {
+        let mut report = String::new();
+        // unwraps always work because writing to a String can't fail
+        writeln!(report, "bed-reader v{}", env!("CARGO_PKG_VERSION")).unwrap();
+        writeln!(report, "Operation: {operation}").unwrap();
+        writeln!(report, "Options in effect:").unwrap();
+        for (name, value) in options {
+            if value.is_empty() {
+                writeln!(report, "  --{name}").unwrap();
+            } else {
+                writeln!(report, "  --{name} {value}").unwrap();
+            }
+        }
+        report.push('\n');
+        for line in lines {
+            writeln!(report, "{line}").unwrap();
+        }
+        match outcome {
+            Ok(done) => writeln!(report, "{done}").unwrap(),
+            Err(e) => writeln!(report, "Error: {e}").unwrap(),
+        }
+        report.push('\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(report.as_bytes())?;
+        Ok(())
+    }
+
+    // Report a write of a .bed file (and its .fam and .bim files).
+    #[allow(clippy::cast_precision_loss)]
+    pub(crate) fn log_write<TVal: BedVal>(
+        &self,
+        write_options: &WriteOptions<TVal>,
+        result: &Result<WriteSummary, Box<BedErrorPlus>>,
+    ) -> Result<(), Box<BedErrorPlus>> {
+        let mut options = vec![("out", write_options.path().display().to_string())];
+        if write_options.skip_fam() {
+            options.push(("skip-fam", String::new()));
+        } else {
+            options.push(("fam", write_options.fam_path().display().to_string()));
+        }
+        if write_options.skip_bim() {
+            options.push(("skip-bim", String::new()));
+        } else {
+            options.push(("bim", write_options.bim_path().display().to_string()));
+        }
+        options.push(("a1-counted", write_options.is_a1_counted().to_string()));
+        options.push((
+            "missing-value",
+            format!("{:?}", write_options.missing_value()),
+        ));
+        if let Some(num_threads) = write_options.num_threads() {
+            options.push(("threads", num_threads.to_string()));
+        }
+        if let Some(policy) = write_options.dedup_sids() {
+            options.push(("dedup-sids", format!("{policy:?}")));
+        }
+        if let Some(tolerance) = write_options.tolerance() {
+            options.push(("tolerance", tolerance.to_string()));
+        }
+        if let Some(policy) = write_options.id_policy() {
+            options.push(("id-policy", format!("{policy:?}")));
+        }
+        if write_options.missing_if.is_some() {
+            options.push(("missing-if", "(predicate)".to_string()));
+        }
+
+        let mut lines = Vec::new();
+        let outcome = match result {
+            Ok(summary) => {
+                if !write_options.skip_fam() {
+                    let sex = write_options.sex();
+                    let male = sex.iter().filter(|&&code| code == 1).count();
+                    let female = sex.iter().filter(|&&code| code == 2).count();
+                    lines.push(format!(
+                        "{} people ({male} male, {female} female, {} ambiguous) written to {}.",
+                        sex.len(),
+                        sex.len() - male - female,
+                        write_options.fam_path().display()
+                    ));
+                }
+                let sid_count = summary.missing_counts().len();
+                if !write_options.skip_bim() {
+                    lines.push(format!(
+                        "{sid_count} variants written to {}.",
+                        write_options.bim_path().display()
+                    ));
+                }
+                let value_count = write_options.iid_count() * sid_count;
+                if value_count > 0 {
+                    let rate = 1.0 - summary.missing_count() as f64 / value_count as f64;
+                    lines.push(format!("Total genotyping rate is {rate:.6}."));
+                }
+                if sid_count < write_options.sid_count() {
+                    lines.push(format!(
+                        "Warning: {} variants with duplicate ids left out.",
+                        write_options.sid_count() - sid_count
+                    ));
+                }
+                if summary.snapped_count() > 0 {
+                    lines.push(format!(
+                        "Warning: {} values snapped to the nearest genotype.",
+                        summary.snapped_count()
+                    ));
+                }
+                Ok(format!(
+                    "Genotypes written to {} ... done.",
+                    write_options.path().display()
+                ))
+            }
+            Err(e) => Err(e.as_ref()),
+        };
+        self.append_report("write", &options, &lines, outcome)
+    }
+}
+
+impl<TVal> WriteOptionsBuilder<TVal>
+where
+    TVal: BedVal,
+{
+    /// Append a PLINK-style report of the write to an [`OperationLog`](struct.OperationLog.html).
+    ///
+    /// The report lists the options in effect, the numbers of people (individuals) and
+    /// variants (SNPs) written, the genotyping rate, and any warnings, such as values snapped by
+    /// [`tolerance`](struct.WriteOptionsBuilder.html#method.tolerance). A failed write is
+    /// reported with its error.
+    ///
+    /// > See [`OperationLog`](struct.OperationLog.html) for an example.
+    pub fn log(&mut self, log: OperationLog) -> &mut Self {
+        self.log = Some(Some(log));
+        self
+    }
+}
@@ -0,0 +1,225 @@
+use anyinput::anyinput;
+use ndarray as nd;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use crate::{Bed, BedError, BedErrorPlus, BedVal, Hold, Metadata, ReadOptions};
+
+/// A trait alias, used internally, to turn Oxford GEN genotype probabilities into i8, f32, or f64 values.
+///
+/// Floats get the expected count of the counted allele (the dosage). `i8` gets the most likely count (a hard call).
+pub trait FromGenProbabilities: BedVal {
+    /// Given the probabilities of zero, one, and two copies of the counted allele,
+    /// return a value, or `missing_value` if all the probabilities are zero.
+    fn from_gen_probabilities(probabilities: [f64; 3], missing_value: Self) -> Self;
+}
+
+#[allow(clippy::float_cmp)]
+fn is_missing_probabilities(probabilities: [f64; 3]) -> bool {
+    probabilities.iter().all(|p| *p == 0.0)
+}
+
+fn gen_dosage(probabilities: [f64; 3]) -> f64 {
+    probabilities[1] + 2.0 * probabilities[2]
+}
+
+impl FromGenProbabilities for f64 {
+    fn from_gen_probabilities(probabilities: [f64; 3], missing_value: Self) -> Self {
+        if is_missing_probabilities(probabilities) {
+            missing_value
+        } else {
+            gen_dosage(probabilities)
+        }
+    }
+}
+
+impl FromGenProbabilities for f32 {
+    #[allow(clippy::cast_possible_truncation)]
+    fn from_gen_probabilities(probabilities: [f64; 3], missing_value: Self) -> Self {
+        if is_missing_probabilities(probabilities) {
+            missing_value
+        } else {
+            gen_dosage(probabilities) as f32
+        }
+    }
+}
+
+impl FromGenProbabilities for i8 {
+    fn from_gen_probabilities(probabilities: [f64; 3], missing_value: Self) -> Self {
+        if is_missing_probabilities(probabilities) {
+            return missing_value;
+        }
+        // On ties, prefer fewer copies of the counted allele
+        let mut best = 0;
+        for count in 1..3 {
+            if probabilities[count] > probabilities[best] {
+                best = count;
+            }
+        }
+        [0, 1, 2][best]
+    }
+}
+
+// Read the fid (ID_1) and iid (ID_2) columns of an Oxford .sample file.
+#[anyinput]
+fn read_sample_file(path: AnyPath) -> Result<(Vec<String>, Vec<String>), Box<BedErrorPlus>> {
+    let mut fid = Vec::new();
+    let mut iid = Vec::new();
+    // The first two lines give the column names and types.
+    for line in BufReader::new(File::open(path)?).lines().skip(2) {
+        let line = line?;
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.is_empty() {
+            continue;
+        }
+        if fields.len() < 3 {
+            Err(BedError::MetadataFieldCount(
+                3,
+                fields.len(),
+                path.to_path_buf(),
+            ))?;
+        }
+        fid.push(fields[0].to_string());
+        iid.push(fields[1].to_string());
+    }
+    Ok((fid, iid))
+}
+
+impl Bed {
+    /// Read genotype data and metadata from an Oxford .gen file and its .sample file.
+    ///
+    /// This lets, for example, older imputation outputs be used without first converting them to .bed.
+    /// Individuals (samples) and SNPs (variants) are selected with the usual [`ReadOptions`](struct.ReadOptions.html)
+    /// index expressions. With f32 or f64 values, the result is the expected count of the
+    /// counted allele (the dosage). With i8 values, it is the most likely count (a hard call).
+    /// Individuals with all-zero probabilities get the missing value.
+    ///
+    /// Each .gen line must have, optionally, a chromosome, then a SNP id, an rsid, a base-pair position,
+    /// two alleles, and three probabilities per individual. The returned [`Metadata`](struct.Metadata.html)
+    /// describes the whole file: fid and iid come from the .sample file, sid from the rsid column.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, ReadOptions, assert_eq_nan};
+    ///
+    /// let temp_out = temp_testdir::TempDir::default();
+    /// let gen_file = temp_out.join("small.gen");
+    /// let sample_file = temp_out.join("small.sample");
+    /// std::fs::write(&sample_file, "ID_1 ID_2 missing\n0 0 0\nfam1 ind1 0\nfam2 ind2 0\n")?;
+    /// std::fs::write(
+    ///     &gen_file,
+    ///     "1 snp1 rs1 100 A G 0 1 0 0.1 0.8 0.1\n1 snp2 rs2 200 T C 0 0 1 0 0 0\n",
+    /// )?;
+    ///
+    /// let read_options = ReadOptions::builder().f64().build()?;
+    /// let (val, metadata) = Bed::read_dosage_from_gen(&gen_file, &sample_file, &read_options)?;
+    /// assert_eq_nan(&val, &nd::array![[1.0, 0.0], [1.0, f64::NAN]]);
+    /// println!("{:?}", metadata.sid()); // Outputs optional ndarray Some(["rs1", "rs2"]...)
+    ///
+    /// let read_options = ReadOptions::builder().sid_index(1).i8().build()?;
+    /// let (val, _) = Bed::read_dosage_from_gen(&gen_file, &sample_file, &read_options)?;
+    /// assert_eq!(val, nd::array![[0], [-127]]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[anyinput]
+    pub fn read_dosage_from_gen<TVal: FromGenProbabilities>(
+        gen_path: AnyPath,
+        sample_path: AnyPath,
+        read_options: &ReadOptions<TVal>,
+    ) -> Result<(nd::Array2<TVal>, Metadata), Box<BedErrorPlus>> {
+        let (fid, iid) = read_sample_file(sample_path)?;
+        let iid_count = iid.len();
+
+        let lines = BufReader::new(File::open(gen_path)?)
+            .lines()
+            .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+            .collect::<Result<Vec<String>, _>>()?;
+        let sid_count = lines.len();
+
+        let mut chromosome = Vec::with_capacity(sid_count);
+        let mut sid = Vec::with_capacity(sid_count);
+        let mut bp_position = Vec::with_capacity(sid_count);
+        let mut allele_1 = Vec::with_capacity(sid_count);
+        let mut allele_2 = Vec::with_capacity(sid_count);
+        let mut probability_start = Vec::with_capacity(sid_count);
+        for line in &lines {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            // With a chromosome column, there are 6 leading fields, otherwise 5.
+            let leading_count = if fields.len() == 6 + 3 * iid_count {
+                chromosome.push(fields[0].to_string());
+                6
+            } else if fields.len() == 5 + 3 * iid_count {
+                chromosome.push("0".to_string());
+                5
+            } else {
+                Err(BedError::MetadataFieldCount(
+                    6 + 3 * iid_count,
+                    fields.len(),
+                    gen_path.to_path_buf(),
+                ))?
+            };
+            sid.push(fields[leading_count - 4].to_string());
+            bp_position.push(fields[leading_count - 3].parse::<i32>()?);
+            allele_1.push(fields[leading_count - 2].to_string());
+            allele_2.push(fields[leading_count - 1].to_string());
+            probability_start.push(leading_count);
+        }
+
+        let iid_hold = Hold::new(&read_options.iid_index, iid_count)?;
+        let iid_index = iid_hold.as_ref();
+        let sid_hold = Hold::new(&read_options.sid_index, sid_count)?;
+        let sid_index = sid_hold.as_ref();
+
+        let shape = nd::ShapeBuilder::set_f(
+            read_options.output_dim(iid_index.len(), sid_index.len()),
+            read_options.is_f,
+        );
+        let mut val = nd::Array2::<TVal>::default(shape);
+        // A transposed output has one row, rather than one column, per SNP.
+        let sid_axis = nd::Axis(usize::from(!read_options.is_transposed));
+        for (&sid_i_signed, mut col) in sid_index.iter().zip(val.axis_iter_mut(sid_axis)) {
+            let sid_i = resolve_index(sid_i_signed, sid_count)
+                .ok_or(BedError::SidIndexTooBig(sid_i_signed))?;
+            let fields: Vec<&str> = lines[sid_i].split_whitespace().collect();
+            let probabilities = &fields[probability_start[sid_i]..];
+            for (&iid_i_signed, out) in iid_index.iter().zip(col.iter_mut()) {
+                let iid_i = resolve_index(iid_i_signed, iid_count)
+                    .ok_or(BedError::IidIndexTooBig(iid_i_signed))?;
+                // GEN gives the probabilities of genotypes AA, AB, and BB where A is allele 1.
+                let p_aa = probabilities[3 * iid_i].parse::<f64>()?;
+                let p_ab = probabilities[3 * iid_i + 1].parse::<f64>()?;
+                let p_bb = probabilities[3 * iid_i + 2].parse::<f64>()?;
+                let by_count = if read_options.is_a1_counted {
+                    [p_bb, p_ab, p_aa]
+                } else {
+                    [p_aa, p_ab, p_bb]
+                };
+                *out = TVal::from_gen_probabilities(by_count, read_options.missing_value);
+            }
+        }
+
+        let metadata = Metadata::builder()
+            .fid(fid)
+            .iid(iid)
+            .chromosome(chromosome)
+            .sid(sid)
+            .bp_position(bp_position)
+            .allele_1(allele_1)
+            .allele_2(allele_2)
+            .build()?;
+
+        Ok((val, metadata))
+    }
+}
+
+// Turn a possibly negative index into a position, or None if out of range.
+pub(crate) fn resolve_index(index: isize, count: usize) -> Option<usize> {
+    if index >= 0 {
+        let index = index.unsigned_abs();
+        (index < count).then_some(index)
+    } else {
+        count.checked_sub(index.unsigned_abs())
+    }
+}
@@ -0,0 +1,223 @@
+use ndarray as nd;
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::{
+    open_and_check, set_up_two_bits_to_value, try_div_4, Bed, BedError, BedErrorPlus, Index,
+    Missing, CB_HEADER_U64,
+};
+
+/// How the bytes returned by [`Bed::read_packed_columns`](struct.Bed.html#method.read_packed_columns)
+/// are laid out, with the tables needed to decode them.
+///
+/// The bytes are the selected SNP (variant) columns of the .bed file, copied as is, one after
+/// another. Column `j` starts at byte `j * bytes_per_column()`. Within a column, individual
+/// (sample) `i` is in byte `i / 4`, at bits `2 * (i % 4)` and `2 * (i % 4) + 1`. Any bits
+/// past the last individual pad the column to a whole byte and should be ignored.
+///
+/// A device kernel can decode with either [`code_values`](struct.PackedLayout.html#method.code_values),
+/// one value per two-bit code, or [`byte_table`](struct.PackedLayout.html#method.byte_table),
+/// four values per byte. [`decode`](struct.PackedLayout.html#method.decode) is a reference CPU
+/// decoder for checking a device's results.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct PackedLayout {
+    iid_count: usize,
+    sid_count: usize,
+    bytes_per_column: usize,
+}
+
+impl PackedLayout {
+    /// Number of individuals (samples) in each column.
+    #[must_use]
+    pub fn iid_count(&self) -> usize {
+        self.iid_count
+    }
+
+    /// Number of SNP (variant) columns.
+    #[must_use]
+    pub fn sid_count(&self) -> usize {
+        self.sid_count
+    }
+
+    /// Number of bytes in each column, including padding.
+    #[must_use]
+    pub fn bytes_per_column(&self) -> usize {
+        self.bytes_per_column
+    }
+
+    /// Total number of bytes, that is, `sid_count() * bytes_per_column()`.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.sid_count * self.bytes_per_column
+    }
+
+    /// True if there are no bytes, because there are no SNPs or no individuals.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The `i8` value of each two-bit code, indexed by the code.
+    ///
+    /// Values count allele 1 if `is_a1_counted` is true, otherwise allele 2. Missing is -127.
+    ///
+    /// # Example
+    /// ```
+    /// use bed_reader::{Bed, format, sample_bed_file};
+    ///
+    /// let mut bed = Bed::new(sample_bed_file("small.bed")?)?;
+    /// let (_, layout) = bed.read_packed_columns(..)?;
+    /// let code_values = layout.code_values(true);
+    /// assert_eq!(code_values, [2, -127, 1, 0]);
+    /// assert_eq!(code_values[format::HETEROZYGOUS as usize], 1);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[must_use]
+    pub fn code_values(&self, is_a1_counted: bool) -> [i8; 4] {
+        set_up_two_bits_to_value(is_a1_counted, <i8 as Missing>::missing())
+    }
+
+    /// The four `i8` values of each possible byte, indexed by the byte, lowest bits first.
+    ///
+    /// Values count allele 1 if `is_a1_counted` is true, otherwise allele 2. Missing is -127.
+    /// The table is 1 KiB, small enough for a GPU's constant or shared memory.
+    #[must_use]
+    pub fn byte_table(&self, is_a1_counted: bool) -> Vec<[i8; 4]> {
+        let code_values = self.code_values(is_a1_counted);
+        (0..=u8::MAX)
+            .map(|byte| {
+                let mut values = [0i8; 4];
+                for (shift, value) in values.iter_mut().enumerate() {
+                    *value = code_values[((byte >> (shift * 2)) & 0x03) as usize];
+                }
+                values
+            })
+            .collect()
+    }
+
+    /// Decode packed bytes on the CPU, as a reference for device decoders.
+    ///
+    /// Returns an (`iid_count`, `sid_count`) array, in Fortran order, that matches
+    /// what [`ReadOptions`](struct.ReadOptions.html) would read as `i8`.
+    ///
+    /// > See [`Bed::read_packed_columns`](struct.Bed.html#method.read_packed_columns) for an example.
+    pub fn decode(
+        &self,
+        packed: &[u8],
+        is_a1_counted: bool,
+    ) -> Result<nd::Array2<i8>, Box<BedErrorPlus>> {
+        if packed.len() != self.len() {
+            Err(BedError::InconsistentCount(
+                "packed byte".to_string(),
+                self.len(),
+                packed.len(),
+            ))?;
+        }
+        let code_values = self.code_values(is_a1_counted);
+        let mut val =
+            nd::Array2::<i8>::zeros(nd::ShapeBuilder::f((self.iid_count, self.sid_count)));
+        if self.is_empty() {
+            return Ok(val);
+        }
+        for (column, mut col) in packed
+            .chunks_exact(self.bytes_per_column)
+            .zip(val.axis_iter_mut(nd::Axis(1)))
+        {
+            for (iid_i, value) in col.iter_mut().enumerate() {
+                let code = (column[iid_i / 4] >> ((iid_i % 4) * 2)) & 0x03;
+                *value = code_values[code as usize];
+            }
+        }
+        Ok(val)
+    }
+}
+
+impl Bed {
+    /// Read the selected SNP (variant) columns as raw, packed two-bit genotypes, for decoding on a GPU or other device.
+    ///
+    /// Packed bytes are a quarter the size of `i8` values, so they are cheaper to upload.
+    /// The returned [`PackedLayout`](struct.PackedLayout.html) describes the bytes and gives
+    /// the tables to decode them, and its [`decode`](struct.PackedLayout.html#method.decode)
+    /// method is a reference CPU decoder. Nothing here depends on a particular device API,
+    /// such as CUDA or wgpu.
+    ///
+    /// See the [Table of Index Expressions](index.html#index-expressions)
+    /// for a list of the supported index expressions. The .bed file must be in the usual
+    /// SNP-major mode.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, ReadOptions, sample_bed_file};
+    ///
+    /// let mut bed = Bed::new(sample_bed_file("small.bed")?)?;
+    /// let (packed, layout) = bed.read_packed_columns([0, 2])?;
+    /// assert_eq!(packed.len(), 2); // 3 individuals fit in 1 byte per SNP
+    /// assert_eq!((layout.iid_count(), layout.sid_count()), (3, 2));
+    ///
+    /// // ... upload `packed` and `layout.byte_table(true)`, decode on the device ...
+    ///
+    /// let val = layout.decode(&packed, true)?;
+    /// assert_eq!(val, nd::array![[1, -127], [2, -127], [0, 2]]);
+    /// assert_eq!(val, ReadOptions::builder().sid_index([0, 2]).i8().read(&mut bed)?);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn read_packed_columns(
+        &mut self,
+        sid_index: impl Into<Index>,
+    ) -> Result<(Vec<u8>, PackedLayout), Box<BedErrorPlus>> {
+        let iid_count = self.iid_count()?;
+        let sid_count = self.sid_count()?;
+        let layout_iid_count = self.layout_iid_count().unwrap_or(iid_count);
+        let layout_sid_count = self.layout_sid_count().unwrap_or(sid_count);
+        let in_sid_i_vec = sid_index
+            .into()
+            .to_vec(sid_count)?
+            .into_iter()
+            .map(|sid_i| {
+                // "as" is safe because counts are checked against the file length
+                if (0..sid_count as isize).contains(&sid_i) {
+                    Ok(sid_i as u64)
+                } else if (-(sid_count as isize)..0).contains(&sid_i) {
+                    Ok((sid_count as isize + sid_i) as u64)
+                } else {
+                    Err(BedError::SidIndexTooBig(sid_i).into())
+                }
+            })
+            .collect::<Result<Vec<u64>, Box<BedErrorPlus>>>()?;
+
+        let (mut buf_reader, bytes_array) = open_and_check(&self.path)?;
+        if bytes_array[2] != 1 {
+            Err(BedError::NotSnpMajor(self.path.clone()))?;
+        }
+
+        let iid_count_div4_u64 = try_div_4(layout_iid_count, layout_sid_count)?;
+        let file_len = buf_reader.get_ref().metadata()?.len();
+        if file_len != iid_count_div4_u64 * (layout_sid_count as u64) + CB_HEADER_U64 {
+            Err(BedError::IllFormed(self.path.clone()))?;
+        }
+
+        // "as" is safe because of the file-length check
+        let bytes_per_column = iid_count_div4_u64 as usize;
+        let mut packed = vec![0u8; bytes_per_column * in_sid_i_vec.len()];
+        if bytes_per_column > 0 {
+            for (&in_sid_i, column) in in_sid_i_vec
+                .iter()
+                .zip(packed.chunks_exact_mut(bytes_per_column))
+            {
+                buf_reader.seek(SeekFrom::Start(
+                    in_sid_i * iid_count_div4_u64 + CB_HEADER_U64,
+                ))?;
+                buf_reader.read_exact(column)?;
+            }
+        }
+
+        let layout = PackedLayout {
+            iid_count,
+            sid_count: in_sid_i_vec.len(),
+            bytes_per_column,
+        };
+        Ok((packed, layout))
+    }
+}
@@ -0,0 +1,237 @@
+use ndarray as nd;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::{
+    open_and_check, read_no_alloc, set_up_two_bits_to_value, try_div_4, Bed, BedError,
+    BedErrorPlus, Missing, CB_HEADER_U64,
+};
+
+// Bytes decoded per read. Kept on the stack so that reading a panel doesn't allocate.
+const PANEL_CHUNK_LEN: usize = 1024;
+
+impl Bed {
+    /// Read genotype data for a small, fixed panel of SNPs (variants), for example, a QC fingerprint panel.
+    ///
+    /// The panel size, `K`, is known at compile time, so the SNP indexes are resolved on the
+    /// stack and the .bed file is opened once and read without multithreading.
+    /// This makes it fast to read the same panel across many files.
+    /// Values are `i8` with allele 1 counted and missing values as -127.
+    ///
+    /// Use [`read_panel_into`](struct.Bed.html#method.read_panel_into) to also reuse the output array.
+    /// Negative indexes count from the end, as with [`Index`](enum.Index.html).
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, sample_bed_file};
+    ///
+    /// let mut bed = Bed::new(sample_bed_file("small.bed")?)?;
+    /// let val = bed.read_panel(&[0, 2, -1])?;
+    /// assert_eq!(val, nd::array![[1, -127, 0], [2, -127, 2], [0, 2, 0]]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn read_panel<const K: usize>(
+        &mut self,
+        sid_index: &[isize; K],
+    ) -> Result<nd::Array2<i8>, Box<BedErrorPlus>> {
+        let iid_count = self.iid_count()?;
+        let mut val = nd::Array2::<i8>::from_elem(
+            nd::ShapeBuilder::f((iid_count, K)),
+            <i8 as Missing>::missing(),
+        );
+        self.read_panel_into(sid_index, &mut val.view_mut())?;
+        Ok(val)
+    }
+
+    /// Read genotype data for a small, fixed panel of SNPs (variants) into a preallocated array.
+    ///
+    /// The array must have shape (`iid_count`, `K`). Reusing one array across many files
+    /// avoids allocating the output for each file. Each call opens the .bed file again. To read
+    /// the same file many times, use a [`PanelReader`](struct.PanelReader.html), from
+    /// [`Bed::panel_reader`](struct.Bed.html#method.panel_reader), which keeps the file open.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, sample_bed_file};
+    ///
+    /// let panel = [0, 2];
+    /// let mut val = nd::Array2::<i8>::zeros((3, panel.len()));
+    /// for file_name in ["small.bed" /* , ... more files with the same individuals */] {
+    ///     let mut bed = Bed::new(sample_bed_file(file_name)?)?;
+    ///     bed.read_panel_into(&panel, &mut val.view_mut())?;
+    ///     println!("{val:?}"); // Outputs ndarray [[1, -127], [2, -127], [0, 2]]
+    /// }
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn read_panel_into<const K: usize>(
+        &mut self,
+        sid_index: &[isize; K],
+        val: &mut nd::ArrayViewMut2<'_, i8>,
+    ) -> Result<(), Box<BedErrorPlus>> {
+        let iid_count = self.iid_count()?;
+        let dim = val.dim();
+        if dim != (iid_count, K) {
+            Err(BedError::InvalidShape(iid_count, K, dim.0, dim.1))?;
+        }
+        match self.panel_reader(sid_index) {
+            Ok(mut panel_reader) => panel_reader.read_into(val),
+            Err(error) if matches!(*error, BedErrorPlus::BedError(BedError::NotSnpMajor(_))) => {
+                // Individual-major files don't store SNPs contiguously, so use the general reader.
+                let sid_count = self.sid_count()?;
+                let iid_index: Vec<isize> = (0..iid_count as isize).collect();
+                read_no_alloc(
+                    &self.path,
+                    self.layout_iid_count().unwrap_or(iid_count),
+                    self.layout_sid_count().unwrap_or(sid_count),
+                    true,
+                    None,
+                    &iid_index,
+                    sid_index,
+                    <i8 as Missing>::missing(),
+                    1,
+                    val,
+                )
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Open a [`PanelReader`](struct.PanelReader.html) that reads a small, fixed panel of SNPs
+    /// (variants) from this .bed file, again and again, through one open file handle.
+    ///
+    /// The SNP indexes are resolved, and the file is opened and checked, once, here. Returns a
+    /// [`BedError::NotSnpMajor`](enum.BedError.html#variant.NotSnpMajor) error if the file is
+    /// individual-major (mode 0).
+    ///
+    /// > See [`PanelReader`](struct.PanelReader.html) for an example.
+    pub fn panel_reader<const K: usize>(
+        &mut self,
+        sid_index: &[isize; K],
+    ) -> Result<PanelReader<K>, Box<BedErrorPlus>> {
+        let iid_count = self.iid_count()?;
+        let sid_count = self.sid_count()?;
+        let layout_iid_count = self.layout_iid_count().unwrap_or(iid_count);
+        let layout_sid_count = self.layout_sid_count().unwrap_or(sid_count);
+
+        // Resolve the indexes on the stack
+        let mut in_sid_i_array = [0u64; K];
+        for (in_sid_i, &in_sid_i_signed) in in_sid_i_array.iter_mut().zip(sid_index.iter()) {
+            // "as" is safe because counts are checked against the file length
+            *in_sid_i = if (0..sid_count as isize).contains(&in_sid_i_signed) {
+                in_sid_i_signed as u64
+            } else if (-(sid_count as isize)..0).contains(&in_sid_i_signed) {
+                (sid_count as isize + in_sid_i_signed) as u64
+            } else {
+                Err(BedError::SidIndexTooBig(in_sid_i_signed))?
+            };
+        }
+
+        let (buf_reader, bytes_array) = open_and_check(&self.path)?;
+        if bytes_array[2] != 1 {
+            Err(BedError::NotSnpMajor(self.path.clone()))?;
+        }
+        let iid_count_div4_u64 = try_div_4(layout_iid_count, layout_sid_count)?;
+        let file = buf_reader.into_inner();
+        let file_len = file.metadata()?.len();
+        if file_len != iid_count_div4_u64 * (layout_sid_count as u64) + CB_HEADER_U64 {
+            Err(BedError::IllFormed(self.path.clone()))?;
+        }
+
+        Ok(PanelReader {
+            file,
+            in_sid_i_array,
+            iid_count,
+            iid_count_div4_u64,
+            from_two_bits_to_value: set_up_two_bits_to_value(true, <i8 as Missing>::missing()),
+        })
+    }
+}
+
+/// Reads a small, fixed panel of SNPs (variants) from one .bed file, again and again, for
+/// example, to serve a QC fingerprint panel.
+///
+/// Made by [`Bed::panel_reader`](struct.Bed.html#method.panel_reader). The reader keeps the
+/// .bed file open, and [`read_into`](struct.PanelReader.html#method.read_into) decodes through a
+/// fixed buffer on the stack into the caller's array, so reads make no heap allocations.
+/// Values are `i8` with allele 1 counted and missing values as -127.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{Bed, WriteOptions};
+///
+/// let output_folder = temp_testdir::TempDir::default();
+/// let output_file = output_folder.join("panel.bed");
+/// WriteOptions::builder(&output_file)
+///     .write(&nd::array![[1, 0, -127, 0], [2, 0, -127, 2], [0, 1, 2, 0]])?;
+///
+/// let mut bed = Bed::new(&output_file)?;
+/// let mut panel_reader = bed.panel_reader(&[0, 2, -1])?;
+/// let mut val = nd::Array2::<i8>::zeros((3, 3));
+/// for _ in 0..2 {
+///     panel_reader.read_into(&mut val.view_mut())?;
+///     assert_eq!(val, nd::array![[1, -127, 0], [2, -127, 2], [0, 2, 0]]);
+/// }
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+#[derive(Debug)]
+pub struct PanelReader<const K: usize> {
+    file: File,
+    in_sid_i_array: [u64; K],
+    iid_count: usize,
+    iid_count_div4_u64: u64,
+    from_two_bits_to_value: [i8; 4],
+}
+
+impl<const K: usize> PanelReader<K> {
+    /// The number of individuals (samples), the number of rows each read fills.
+    #[must_use]
+    pub fn iid_count(&self) -> usize {
+        self.iid_count
+    }
+
+    /// Read the panel into a preallocated array of shape (`iid_count`, `K`), with no heap
+    /// allocation.
+    ///
+    /// > See [`PanelReader`](struct.PanelReader.html) for an example.
+    pub fn read_into(
+        &mut self,
+        val: &mut nd::ArrayViewMut2<'_, i8>,
+    ) -> Result<(), Box<BedErrorPlus>> {
+        let iid_count = self.iid_count;
+        let dim = val.dim();
+        if dim != (iid_count, K) {
+            Err(BedError::InvalidShape(iid_count, K, dim.0, dim.1))?;
+        }
+
+        let byte_count = iid_count.div_ceil(4);
+        let mut chunk = [0u8; PANEL_CHUNK_LEN];
+        for (in_sid_i, mut col) in self
+            .in_sid_i_array
+            .iter()
+            .zip(val.axis_iter_mut(nd::Axis(1)))
+        {
+            self.file.seek(SeekFrom::Start(
+                in_sid_i * self.iid_count_div4_u64 + CB_HEADER_U64,
+            ))?;
+            for chunk_start in (0..byte_count).step_by(PANEL_CHUNK_LEN) {
+                let chunk_len = PANEL_CHUNK_LEN.min(byte_count - chunk_start);
+                self.file.read_exact(&mut chunk[..chunk_len])?;
+                for (byte_i, byte) in chunk[..chunk_len].iter().enumerate() {
+                    let iid_start = (chunk_start + byte_i) * 4;
+                    for iid_i in iid_start..(iid_start + 4).min(iid_count) {
+                        let genotype_byte = (byte >> ((iid_i % 4) * 2)) & 0x03;
+                        col[iid_i] = self.from_two_bits_to_value[genotype_byte as usize];
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
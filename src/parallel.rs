@@ -0,0 +1,106 @@
+// The parallel iterators, thread pools, and `par_azip!` used by the crate. Without the
+// `parallel` feature, sequential stand-ins with the same names are used instead, so the
+// calling code is the same either way.
+
+use crate::BedErrorPlus;
+
+#[cfg(feature = "parallel")]
+pub(crate) use ndarray::par_azip;
+#[cfg(feature = "parallel")]
+use rayon::ThreadPool;
+
+#[cfg(feature = "parallel")]
+pub(crate) mod prelude {
+    pub(crate) use rayon::prelude::*;
+}
+
+#[cfg(feature = "parallel")]
+pub(crate) fn create_pool(num_threads: usize) -> Result<ThreadPool, Box<BedErrorPlus>> {
+    match rayon::ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .build()
+    {
+        Err(e) => Err(Box::new(e.into())),
+        Ok(pool) => Ok(pool),
+    }
+}
+
+#[cfg(not(feature = "parallel"))]
+pub(crate) use sequential::{create_pool, par_azip};
+
+#[cfg(not(feature = "parallel"))]
+pub(crate) mod prelude {
+    pub(crate) use super::sequential::{
+        IntoParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelBridge,
+    };
+}
+
+#[cfg(not(feature = "parallel"))]
+mod sequential {
+    use super::BedErrorPlus;
+
+    // Like ndarray's par_azip!, but on the current thread.
+    macro_rules! par_azip {
+        ($($t:tt)*) => {
+            ndarray::azip!($($t)*)
+        };
+    }
+    pub(crate) use par_azip;
+
+    // Runs everything on the current thread.
+    pub(crate) struct ThreadPool;
+
+    impl ThreadPool {
+        #[allow(clippy::unused_self)]
+        pub(crate) fn install<R>(&self, op: impl FnOnce() -> R) -> R {
+            op()
+        }
+    }
+
+    #[allow(clippy::unnecessary_wraps)]
+    pub(crate) fn create_pool(_num_threads: usize) -> Result<ThreadPool, Box<BedErrorPlus>> {
+        Ok(ThreadPool)
+    }
+
+    pub(crate) trait ParallelBridge: Iterator + Sized {
+        fn par_bridge(self) -> Self {
+            self
+        }
+    }
+    impl<I: Iterator> ParallelBridge for I {}
+
+    pub(crate) trait IntoParallelIterator: IntoIterator + Sized {
+        fn into_par_iter(self) -> Self::IntoIter {
+            self.into_iter()
+        }
+    }
+    impl<I: IntoIterator> IntoParallelIterator for I {}
+
+    pub(crate) trait IntoParallelRefIterator<'data> {
+        type Iter: Iterator;
+        fn par_iter(&'data self) -> Self::Iter;
+    }
+    impl<'data, I: 'data + ?Sized> IntoParallelRefIterator<'data> for I
+    where
+        &'data I: IntoIterator,
+    {
+        type Iter = <&'data I as IntoIterator>::IntoIter;
+        fn par_iter(&'data self) -> Self::Iter {
+            self.into_iter()
+        }
+    }
+
+    pub(crate) trait IntoParallelRefMutIterator<'data> {
+        type Iter: Iterator;
+        fn par_iter_mut(&'data mut self) -> Self::Iter;
+    }
+    impl<'data, I: 'data + ?Sized> IntoParallelRefMutIterator<'data> for I
+    where
+        &'data mut I: IntoIterator,
+    {
+        type Iter = <&'data mut I as IntoIterator>::IntoIter;
+        fn par_iter_mut(&'data mut self) -> Self::Iter {
+            self.into_iter()
+        }
+    }
+}
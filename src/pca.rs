@@ -0,0 +1,350 @@
+//! Principal component analysis (PCA) of genotype data by randomized singular value
+//! decomposition, streaming the .bed file in blocks.
+//!
+//! [`pca`](fn.pca.html) finds the top principal components of the standardized genotype
+//! matrix `X`, with one row per individual (sample) and one column per SNP (variant), without
+//! holding `X` in memory. Each pass through the file multiplies `X`, or its transpose, by a
+//! thin matrix, with [`bed_dot`](../fn.bed_dot.html). The individuals' scores can, for
+//! example, be used as covariates for population structure in a genome-wide association
+//! study (GWAS).
+use derive_builder::Builder;
+use ndarray as nd;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::global_config::resolve_missing_policy;
+use crate::{bed_dot, Bed, BedError, BedErrorPlus, Dist, DotOptions, Index, MissingPolicy};
+
+// The most Jacobi sweeps when finding the eigenvectors of the small, projected matrix.
+const JACOBI_MAX_SWEEPS: usize = 100;
+
+/// Options for [`pca`](fn.pca.html).
+///
+/// Construct with [`PcaOptions::builder`](struct.PcaOptions.html#method.builder).
+#[derive(Debug, Clone, Builder)]
+#[builder(build_fn(error = "Box<BedErrorPlus>"))]
+pub struct PcaOptions {
+    /// The number of principal components to find, as given to
+    /// [`PcaOptions::builder`](struct.PcaOptions.html#method.builder).
+    #[builder(default)]
+    k: usize,
+
+    /// The number of extra random directions sampled, for accuracy (defaults to 10).
+    #[builder(default = "10")]
+    oversample: usize,
+
+    /// The number of power iterations, each two more passes through the file, for accuracy
+    /// when the singular values decay slowly (defaults to 2).
+    #[builder(default = "2")]
+    power_iterations: usize,
+
+    /// The seed of the random directions (defaults to 0). The same seed and inputs always
+    /// give the same components.
+    #[builder(default = "0")]
+    seed: u64,
+
+    /// How each SNP (variant) is scaled after centering (defaults to
+    /// [`Dist::Unit`](../enum.Dist.html#variant.Unit)).
+    #[builder(default = "Dist::Unit")]
+    dist: Dist,
+
+    /// What standardizing does with missing values (defaults to the missing policy of a new
+    /// [`StatsOptions`](../struct.StatsOptions.html)).
+    #[builder(default = "resolve_missing_policy()")]
+    missing_policy: MissingPolicy,
+
+    /// Select which individuals (samples) to analyze -- Defaults to all.
+    ///
+    /// See the [Table of Index Expressions](../index.html#index-expressions)
+    /// for a list of the supported index expressions.
+    #[builder(default = "Index::All")]
+    #[builder(setter(into))]
+    iid_index: Index,
+
+    /// Select which SNPs (variants) to use -- Defaults to all.
+    ///
+    /// See the [Table of Index Expressions](../index.html#index-expressions)
+    /// for a list of the supported index expressions.
+    #[builder(default = "Index::All")]
+    #[builder(setter(into))]
+    sid_index: Index,
+
+    /// Number of threads to use (defaults to all processors)
+    #[builder(default, setter(strip_option))]
+    num_threads: Option<usize>,
+}
+
+impl PcaOptions {
+    /// Options for [`pca`](fn.pca.html), finding `k` principal components.
+    ///
+    /// > See [`pca`](fn.pca.html) for an example.
+    #[must_use]
+    pub fn builder(k: usize) -> PcaOptionsBuilder {
+        let mut builder = PcaOptionsBuilder::default();
+        builder.k(k);
+        builder
+    }
+
+    /// The number of principal components to find.
+    #[must_use]
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// The number of extra random directions sampled.
+    #[must_use]
+    pub fn oversample(&self) -> usize {
+        self.oversample
+    }
+
+    /// The number of power iterations.
+    #[must_use]
+    pub fn power_iterations(&self) -> usize {
+        self.power_iterations
+    }
+
+    /// The seed of the random directions.
+    #[must_use]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// How each SNP (variant) is scaled after centering.
+    #[must_use]
+    pub fn dist(&self) -> Dist {
+        self.dist
+    }
+
+    /// What standardizing does with missing values.
+    #[must_use]
+    pub fn missing_policy(&self) -> MissingPolicy {
+        self.missing_policy
+    }
+
+    /// The individuals (samples) to analyze.
+    #[must_use]
+    pub fn iid_index(&self) -> &Index {
+        &self.iid_index
+    }
+
+    /// The SNPs (variants) to use.
+    #[must_use]
+    pub fn sid_index(&self) -> &Index {
+        &self.sid_index
+    }
+
+    /// The number of threads, if set.
+    #[must_use]
+    pub fn num_threads(&self) -> Option<usize> {
+        self.num_threads
+    }
+
+    // Options for multiplying the same standardized genotype matrix.
+    fn dot_options(&self, is_transposed: bool) -> Result<DotOptions, Box<BedErrorPlus>> {
+        let mut builder = DotOptions::builder();
+        builder
+            .is_transposed(is_transposed)
+            .dist(self.dist)
+            .missing_policy(self.missing_policy)
+            .iid_index(self.iid_index.clone())
+            .sid_index(self.sid_index.clone());
+        if let Some(num_threads) = self.num_threads {
+            builder.num_threads(num_threads);
+        }
+        builder.build()
+    }
+}
+
+/// The principal components found by [`pca`](fn.pca.html).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pca {
+    scores: nd::Array2<f64>,
+    loadings: nd::Array2<f64>,
+    singular_values: nd::Array1<f64>,
+}
+
+impl Pca {
+    /// Each individual's (sample's) score on each component, one row per individual and one
+    /// column per component: the left singular vectors times the singular values, which
+    /// approximate `X` times the loadings.
+    #[must_use]
+    pub fn scores(&self) -> &nd::Array2<f64> {
+        &self.scores
+    }
+
+    /// Each SNP's (variant's) loading on each component, one row per SNP and one column per
+    /// component. The columns are orthonormal.
+    #[must_use]
+    pub fn loadings(&self) -> &nd::Array2<f64> {
+        &self.loadings
+    }
+
+    /// The singular values of `X`, largest first, one per component.
+    #[must_use]
+    pub fn singular_values(&self) -> &nd::Array1<f64> {
+        &self.singular_values
+    }
+}
+
+/// Find the top principal components of the standardized genotype matrix of a
+/// [`Bed`](../struct.Bed.html) by randomized singular value decomposition.
+///
+/// The file is read `2 × power_iterations + 2` times, a block of SNPs (variants) at a time.
+/// Each SNP is standardized, as by [`standardize`](../fn.standardize.html), on every pass.
+/// Components are signed so that each loading vector's entry of largest magnitude is positive.
+/// Returns a [`BedError::BadPcaComponentCount`](../enum.BedError.html#variant.BadPcaComponentCount)
+/// error if `k` is 0 or more than the smaller of the numbers of individuals and SNPs selected.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{pca, Bed, WriteOptions};
+///
+/// // Two populations, with opposite genotypes at every other SNP
+/// let val = nd::Array2::from_shape_fn((8, 40), |(i, j)| match (i < 4, j % 2 == 0) {
+///     (true, true) => 0,
+///     (false, true) => 2,
+///     _ => ((i + j) % 3) as i8,
+/// });
+/// let output_folder = temp_testdir::TempDir::default();
+/// let output_file = output_folder.join("pca.bed");
+/// WriteOptions::builder(&output_file).write(&val)?;
+///
+/// let mut bed = Bed::new(&output_file)?;
+/// let pca = pca::pca(&mut bed, &pca::PcaOptions::builder(2).build()?)?;
+/// assert_eq!(pca.scores().dim(), (8, 2));
+/// assert_eq!(pca.loadings().dim(), (40, 2));
+/// // The first component separates the populations
+/// let first = pca.scores().column(0);
+/// assert!(first.slice(nd::s![..4]).iter().all(|&s| s * first[0] > 0.0));
+/// assert!(first.slice(nd::s![4..]).iter().all(|&s| s * first[0] < 0.0));
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+pub fn pca(bed: &mut Bed, pca_options: &PcaOptions) -> Result<Pca, Box<BedErrorPlus>> {
+    let iid_count = pca_options.iid_index.len(bed.iid_count()?)?;
+    let sid_count = pca_options.sid_index.len(bed.sid_count()?)?;
+    let k = pca_options.k;
+    let max_k = iid_count.min(sid_count);
+    if k == 0 || k > max_k {
+        Err(BedError::BadPcaComponentCount(k, max_k))?;
+    }
+    let l = (k + pca_options.oversample).min(max_k);
+    let x_dot = pca_options.dot_options(false)?;
+    let xt_dot = pca_options.dot_options(true)?;
+
+    // Sample the range of X, then refine it with power iterations
+    let mut rng = ChaCha8Rng::seed_from_u64(pca_options.seed);
+    let omega = nd::Array2::from_shape_simple_fn((sid_count, l), || rng.gen_range(-1.0..1.0));
+    let mut q = orthonormalize(bed_dot(bed, &omega.view(), &x_dot)?);
+    for _ in 0..pca_options.power_iterations {
+        let z = orthonormalize(bed_dot(bed, &q.view(), &xt_dot)?);
+        q = orthonormalize(bed_dot(bed, &z.view(), &x_dot)?);
+    }
+
+    // X ≈ Q Qᵀ X. With Bᵀ = Xᵀ Q, the eigenvectors W of B Bᵀ give X's singular vectors.
+    let bt = bed_dot(bed, &q.view(), &xt_dot)?;
+    let (eigenvalues, w) = symmetric_eigen(bt.t().dot(&bt));
+    let singular_values = eigenvalues.slice(nd::s![..k]).mapv(|v| v.max(0.0).sqrt());
+    let w = w.slice(nd::s![.., ..k]);
+    let mut loadings = bt.dot(&w);
+    for (mut column, &singular_value) in loadings.columns_mut().into_iter().zip(&singular_values) {
+        if singular_value > 0.0 {
+            column /= singular_value;
+        }
+    }
+    let mut scores = q.dot(&w);
+    for (mut column, &singular_value) in scores.columns_mut().into_iter().zip(&singular_values) {
+        column *= singular_value;
+    }
+
+    // Sign each component so its largest loading is positive
+    for (mut loading, mut score) in loadings.columns_mut().into_iter().zip(scores.columns_mut()) {
+        let largest =
+            loading.iter().copied().fold(
+                0.0f64,
+                |largest, v| if v.abs() > largest.abs() { v } else { largest },
+            );
+        if largest < 0.0 {
+            loading.mapv_inplace(|v| -v);
+            score.mapv_inplace(|v| -v);
+        }
+    }
+
+    Ok(Pca {
+        scores,
+        loadings,
+        singular_values,
+    })
+}
+
+// Orthonormalize the columns by modified Gram-Schmidt. Columns that depend on earlier
+// columns become zero.
+fn orthonormalize(mut a: nd::Array2<f64>) -> nd::Array2<f64> {
+    for j in 0..a.ncols() {
+        let original_norm = a.column(j).dot(&a.column(j)).sqrt();
+        for i in 0..j {
+            let projection = a.column(i).dot(&a.column(j));
+            let column_i = a.column(i).to_owned();
+            a.column_mut(j).scaled_add(-projection, &column_i);
+        }
+        let norm = a.column(j).dot(&a.column(j)).sqrt();
+        if norm > 1e-10 * original_norm {
+            a.column_mut(j).mapv_inplace(|v| v / norm);
+        } else {
+            a.column_mut(j).fill(0.0);
+        }
+    }
+    a
+}
+
+// The eigenvalues, largest first, and eigenvectors, as columns, of a small symmetric matrix,
+// by cyclic Jacobi rotations.
+fn symmetric_eigen(mut a: nd::Array2<f64>) -> (nd::Array1<f64>, nd::Array2<f64>) {
+    let size = a.nrows();
+    let mut vectors = nd::Array2::<f64>::eye(size);
+    let scale = a.iter().map(|x| x * x).sum::<f64>();
+    for _ in 0..JACOBI_MAX_SWEEPS {
+        let off_diagonal: f64 = a
+            .indexed_iter()
+            .filter(|((i, j), _)| i != j)
+            .map(|(_, x)| x * x)
+            .sum();
+        if off_diagonal <= 1e-30 * scale {
+            break;
+        }
+        for p in 0..size {
+            for q in p + 1..size {
+                let apq = a[(p, q)];
+                if apq == 0.0 {
+                    continue;
+                }
+                let theta = (a[(q, q)] - a[(p, p)]) / (2.0 * apq);
+                let tan = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let cos = 1.0 / (tan * tan + 1.0).sqrt();
+                let sin = tan * cos;
+                for k in 0..size {
+                    let (akp, akq) = (a[(k, p)], a[(k, q)]);
+                    a[(k, p)] = cos * akp - sin * akq;
+                    a[(k, q)] = sin * akp + cos * akq;
+                }
+                for k in 0..size {
+                    let (apk, aqk) = (a[(p, k)], a[(q, k)]);
+                    a[(p, k)] = cos * apk - sin * aqk;
+                    a[(q, k)] = sin * apk + cos * aqk;
+                }
+                for k in 0..size {
+                    let (vkp, vkq) = (vectors[(k, p)], vectors[(k, q)]);
+                    vectors[(k, p)] = cos * vkp - sin * vkq;
+                    vectors[(k, q)] = sin * vkp + cos * vkq;
+                }
+            }
+        }
+    }
+
+    let mut order: Vec<usize> = (0..size).collect();
+    order.sort_by(|&i, &j| a[(j, j)].total_cmp(&a[(i, i)]));
+    let eigenvalues = order.iter().map(|&i| a[(i, i)]).collect();
+    let eigenvectors = vectors.select(nd::Axis(1), &order);
+    (eigenvalues, eigenvectors)
+}
@@ -0,0 +1,128 @@
+//! Reproducible permutations for permutation-based association testing.
+//!
+//! Each function takes a seed and always returns the same permutation for the same seed and inputs.
+//! The results line up with the crate's metadata arrays: permuted phenotypes have one value per
+//! individual (sample), and permuted indexes can be given to
+//! [`ReadOptionsBuilder::iid_index`](../struct.ReadOptionsBuilder.html#method.iid_index) to permute
+//! genotype rows instead.
+use ndarray as nd;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::collections::HashMap;
+
+use crate::{BedError, BedErrorPlus};
+
+/// Return a random permutation of the individual (sample) indexes `0..iid_count`.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{Bed, ReadOptions, permute, sample_bed_file};
+///
+/// let mut bed = Bed::new(sample_bed_file("small.bed")?)?;
+/// let iid_index = permute::iid_index(bed.iid_count()?, 0);
+/// let val = ReadOptions::builder()
+///     .iid_index(&iid_index)
+///     .i8()
+///     .read(&mut bed)?;
+/// assert_eq!(val.dim(), (3, 4));
+/// assert_eq!(iid_index, permute::iid_index(3, 0)); // same seed, same permutation
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+#[must_use]
+pub fn iid_index(iid_count: usize, seed: u64) -> Vec<isize> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    // "as" is safe because counts fit in isize
+    let mut iid_index: Vec<isize> = (0..iid_count as isize).collect();
+    iid_index.shuffle(&mut rng);
+    iid_index
+}
+
+/// Return a random permutation of the individual (sample) indexes that only exchanges
+/// individuals within the same family.
+///
+/// Individuals are grouped by family id (fid). Position `i` of the result is the index of the
+/// individual whose data moves to position `i`. Families are shuffled in the order they first
+/// appear, so the result depends only on `fid` and `seed`.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::permute;
+///
+/// let fid = nd::array!["f1", "f1", "f2", "f1", "f2"].map(|s| s.to_string());
+/// let iid_index = permute::iid_index_within_family(&fid, 7);
+/// for (i, &from_i) in iid_index.iter().enumerate() {
+///     assert_eq!(fid[i], fid[from_i as usize]);
+/// }
+/// ```
+#[must_use]
+pub fn iid_index_within_family(fid: &nd::Array1<String>, seed: u64) -> Vec<isize> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+
+    let mut family_order: Vec<&str> = Vec::new();
+    let mut families: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (iid_i, family) in fid.iter().enumerate() {
+        families
+            .entry(family.as_str())
+            .or_insert_with(|| {
+                family_order.push(family.as_str());
+                Vec::new()
+            })
+            .push(iid_i);
+    }
+
+    let mut iid_index = vec![0isize; fid.len()];
+    for family in family_order {
+        let members = &families[family];
+        let mut shuffled = members.clone();
+        shuffled.shuffle(&mut rng);
+        for (&to_i, &from_i) in members.iter().zip(shuffled.iter()) {
+            // "as" is safe because counts fit in isize
+            iid_index[to_i] = from_i as isize;
+        }
+    }
+    iid_index
+}
+
+/// Return phenotype values permuted among individuals (samples) of the same family.
+///
+/// `pheno` and `fid` must have one value per individual, for example, from
+/// [`Metadata::pheno`](../struct.Metadata.html#method.pheno) and
+/// [`Metadata::fid`](../struct.Metadata.html#method.fid).
+/// The same seed always gives the same permutation.
+///
+/// > See [`iid_index_within_family`](fn.iid_index_within_family.html) to permute genotype rows instead.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{Bed, permute, sample_bed_file};
+///
+/// let bed = Bed::new(sample_bed_file("small.bed")?)?;
+/// let pheno = bed.pheno()?.clone();
+/// let fid = bed.fid()?.clone();
+/// let permuted = permute::phenotype_within_family(&pheno, &fid, 42)?;
+/// assert_eq!(permuted, permute::phenotype_within_family(&pheno, &fid, 42)?);
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+pub fn phenotype_within_family<T: Clone>(
+    pheno: &nd::Array1<T>,
+    fid: &nd::Array1<String>,
+    seed: u64,
+) -> Result<nd::Array1<T>, Box<BedErrorPlus>> {
+    if pheno.len() != fid.len() {
+        Err(BedError::InconsistentCount(
+            "pheno".to_string(),
+            fid.len(),
+            pheno.len(),
+        ))?;
+    }
+    Ok(iid_index_within_family(fid, seed)
+        .iter()
+        .map(|&from_i| pheno[from_i as usize].clone())
+        .collect())
+}
@@ -0,0 +1,455 @@
+use ndarray as nd;
+use num_traits::{Float, FromPrimitive, ToPrimitive};
+use std::fs;
+use std::sync::Arc;
+
+use crate::{
+    standardize, Bed, BedError, BedErrorPlus, BedVal, Dist, Index, Metadata, ReadOptions,
+    WriteOptionsBuilder, WriteSummary,
+};
+
+// The most genotype values held at once in a pipeline.
+const PIPELINE_CHUNK_VALUES: usize = 8_000_000;
+
+/// A stage of a [`Pipeline`](struct.Pipeline.html), transforming genotype values a block of
+/// SNP (variant) columns at a time.
+///
+/// Implement it to add a transform of your own with
+/// [`Pipeline::stage`](struct.Pipeline.html#method.stage). For a simple transform, see
+/// [`Pipeline::map`](struct.Pipeline.html#method.map).
+pub trait BlockStage<TVal: BedVal> {
+    /// Called once, before any block, with the number of SNPs selected. Check any per-SNP
+    /// settings here. The default does nothing.
+    fn start(&mut self, sid_count: usize) -> Result<(), Box<BedErrorPlus>> {
+        let _ = sid_count;
+        Ok(())
+    }
+
+    /// Transform, in place, a block with one row per individual (sample) and one column per
+    /// SNP, whose first column is SNP `sid_start` of the SNPs selected.
+    fn apply(
+        &mut self,
+        sid_start: usize,
+        block: &mut nd::ArrayViewMut2<'_, TVal>,
+    ) -> Result<(), Box<BedErrorPlus>>;
+
+    /// Update, in place, the .fam and .bim metadata of the selection to match the transform,
+    /// before it is written. The default does nothing.
+    fn metadata(&self, metadata: &mut Metadata) -> Result<(), Box<BedErrorPlus>> {
+        let _ = metadata;
+        Ok(())
+    }
+}
+
+// A stage borrowed, so that its state can be looked at after the pipeline runs.
+impl<TVal: BedVal, S: BlockStage<TVal> + ?Sized> BlockStage<TVal> for &mut S {
+    fn start(&mut self, sid_count: usize) -> Result<(), Box<BedErrorPlus>> {
+        (**self).start(sid_count)
+    }
+
+    fn apply(
+        &mut self,
+        sid_start: usize,
+        block: &mut nd::ArrayViewMut2<'_, TVal>,
+    ) -> Result<(), Box<BedErrorPlus>> {
+        (**self).apply(sid_start, block)
+    }
+
+    fn metadata(&self, metadata: &mut Metadata) -> Result<(), Box<BedErrorPlus>> {
+        (**self).metadata(metadata)
+    }
+}
+
+// Exchange allele 1 and allele 2 of selected SNPs: 0 <-> 2, with 1 and missing unchanged.
+// The genotypes are flipped as they are decoded (see Pipeline::decode_options), so this stage
+// only checks the mask and updates the metadata.
+struct Flip(Vec<bool>);
+
+impl<TVal: BedVal> BlockStage<TVal> for Flip {
+    fn start(&mut self, sid_count: usize) -> Result<(), Box<BedErrorPlus>> {
+        if self.0.len() != sid_count {
+            Err(BedError::BoolArrayVectorWrongLength(
+                sid_count,
+                self.0.len(),
+            ))?;
+        }
+        Ok(())
+    }
+
+    fn apply(
+        &mut self,
+        _sid_start: usize,
+        _block: &mut nd::ArrayViewMut2<'_, TVal>,
+    ) -> Result<(), Box<BedErrorPlus>> {
+        Ok(())
+    }
+
+    fn metadata(&self, metadata: &mut Metadata) -> Result<(), Box<BedErrorPlus>> {
+        if let (Some(allele_1), Some(allele_2)) = (&metadata.allele_1, &metadata.allele_2) {
+            let mut allele_1 = allele_1.as_ref().clone();
+            let mut allele_2 = allele_2.as_ref().clone();
+            for (sid_i, _) in self.0.iter().enumerate().filter(|(_, &flip)| flip) {
+                std::mem::swap(&mut allele_1[sid_i], &mut allele_2[sid_i]);
+            }
+            metadata.allele_1 = Some(Arc::new(allele_1));
+            metadata.allele_2 = Some(Arc::new(allele_2));
+        }
+        Ok(())
+    }
+}
+
+// Center and scale each SNP, as standardize does.
+struct Standardize(Dist);
+
+impl<TVal> BlockStage<TVal> for Standardize
+where
+    TVal: BedVal + Float + ToPrimitive + FromPrimitive,
+{
+    fn apply(
+        &mut self,
+        _sid_start: usize,
+        block: &mut nd::ArrayViewMut2<'_, TVal>,
+    ) -> Result<(), Box<BedErrorPlus>> {
+        standardize(block, self.0)?;
+        Ok(())
+    }
+}
+
+// A user function that transforms each block.
+struct Map<F>(F);
+
+impl<TVal, F> BlockStage<TVal> for Map<F>
+where
+    TVal: BedVal,
+    F: FnMut(usize, &mut nd::ArrayViewMut2<'_, TVal>) -> Result<(), Box<BedErrorPlus>>,
+{
+    fn apply(
+        &mut self,
+        sid_start: usize,
+        block: &mut nd::ArrayViewMut2<'_, TVal>,
+    ) -> Result<(), Box<BedErrorPlus>> {
+        (self.0)(sid_start, block)
+    }
+}
+
+/// A streaming engine that reads and decodes genotype values a block of SNPs (variants) at a
+/// time, gives each block to composable transform stages, and then writes or hands on the
+/// result.
+///
+/// Created by [`Bed::pipeline`](struct.Bed.html#method.pipeline). Stages, such as
+/// [`flip`](struct.Pipeline.html#method.flip) and
+/// [`standardize`](struct.Pipeline.html#method.standardize), run in the order they are added,
+/// on blocks with one row per individual (sample) and one column per SNP. Only one block is in
+/// memory at a time. Finish with [`write`](struct.Pipeline.html#method.write), which encodes the
+/// blocks to a new .bed/.fam/.bim fileset, or with
+/// [`for_each`](struct.Pipeline.html#method.for_each).
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{Bed, ReadOptions, WriteOptions};
+///
+/// let output_folder = temp_testdir::TempDir::default();
+/// let input_file = output_folder.join("input.bed");
+/// WriteOptions::builder(&input_file)
+///     .allele_1(["A", "C", "G"])
+///     .allele_2(["T", "G", "A"])
+///     .write(&nd::array![[0, 1, 2], [2, -127, 0]])?;
+///
+/// // Keep the last two SNPs and flip the alleles of the first of them.
+/// let bed = Bed::new(&input_file)?;
+/// let read_options = ReadOptions::builder().sid_index(1..).i8().build()?;
+/// let output_file = output_folder.join("harmonized.bed");
+/// bed.pipeline(&read_options)?
+///     .flip([true, false])
+///     .write(&WriteOptions::builder(&output_file))?;
+///
+/// let harmonized = Bed::new(&output_file)?;
+/// assert_eq!(harmonized.read::<i8>()?, nd::array![[1, 2], [-127, 0]]);
+/// assert_eq!(harmonized.allele_1()?, nd::array!["G", "G"]);
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+pub struct Pipeline<'a, TVal: BedVal> {
+    bed: &'a Bed,
+    // The read options, with the individuals and SNPs already selected.
+    read_options: ReadOptions<TVal>,
+    iid_index: Vec<isize>,
+    sid_index: Vec<isize>,
+    stages: Vec<Box<dyn BlockStage<TVal> + 'a>>,
+    // The masks given to flip, applied as the genotypes are decoded.
+    flip_masks: Vec<Vec<bool>>,
+    chunk_sids: Option<usize>,
+}
+
+impl<'a, TVal: BedVal> Pipeline<'a, TVal> {
+    /// The number of SNPs (variants) selected, across all blocks.
+    #[must_use]
+    pub fn sid_count(&self) -> usize {
+        self.sid_index.len()
+    }
+
+    /// Add a stage of your own.
+    ///
+    /// > See [`BlockStage`](trait.BlockStage.html).
+    #[must_use]
+    pub fn stage(mut self, stage: impl BlockStage<TVal> + 'a) -> Self {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    /// Add a stage that calls `f` with each block and the position, among the SNPs
+    /// (variants) selected, of its first SNP. `f` may change the block in place.
+    ///
+    /// > See [`Pipeline::for_each`](struct.Pipeline.html#method.for_each) for an example.
+    #[must_use]
+    pub fn map<F>(self, f: F) -> Self
+    where
+        F: FnMut(usize, &mut nd::ArrayViewMut2<'_, TVal>) -> Result<(), Box<BedErrorPlus>> + 'a,
+    {
+        self.stage(Map(f))
+    }
+
+    /// Add a stage that exchanges allele 1 and allele 2 of the SNPs (variants) with `true` in
+    /// `flip_mask`, as when harmonizing to a reference, so that 0 and 2 are exchanged. When
+    /// written, their alleles are exchanged in the .bim metadata, too.
+    ///
+    /// The genotypes are flipped as they are decoded, before any stage runs, so missing
+    /// values stay missing, whatever the missing value.
+    ///
+    /// `flip_mask` has one value per SNP selected. Otherwise, the pipeline returns a
+    /// [`BedError::BoolArrayVectorWrongLength`](enum.BedError.html#variant.BoolArrayVectorWrongLength)
+    /// error before reading. A SNP selected more than once must be flipped in all its
+    /// selections or none, or the pipeline returns a
+    /// [`BedError::InconsistentFlip`](enum.BedError.html#variant.InconsistentFlip) error.
+    ///
+    /// > See [`Pipeline`](struct.Pipeline.html) for an example.
+    #[must_use]
+    pub fn flip(mut self, flip_mask: impl IntoIterator<Item = bool>) -> Self {
+        let flip_mask: Vec<bool> = flip_mask.into_iter().collect();
+        self.flip_masks.push(flip_mask.clone());
+        self.stage(Flip(flip_mask))
+    }
+
+    /// Read and transform `chunk_sids` SNPs (variants) at a time, rather than up to
+    /// 8,000,000 genotype values at a time. If 0, the pipeline returns a
+    /// [`BedError::ZeroChunkSids`](enum.BedError.html#variant.ZeroChunkSids) error.
+    #[must_use]
+    pub fn chunk_sids(mut self, chunk_sids: usize) -> Self {
+        self.chunk_sids = Some(chunk_sids);
+        self
+    }
+
+    /// Run the pipeline, giving `f` each transformed block, in order, and the position, among
+    /// the SNPs (variants) selected, of its first SNP.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, Dist, ReadOptions, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("scan.bed");
+    /// WriteOptions::builder(&output_file)
+    ///     .write(&nd::array![[0, 1, 2], [2, -127, 1], [1, 1, 0]])?;
+    ///
+    /// let bed = Bed::new(&output_file)?;
+    /// let read_options = ReadOptions::builder().f64().build()?;
+    /// let mut norms = vec![0.0; 3];
+    /// bed.pipeline(&read_options)?
+    ///     .chunk_sids(2)
+    ///     .standardize(Dist::Unit)
+    ///     .map(|_sid_start, block| {
+    ///         block.mapv_inplace(|v| v * 2.0);
+    ///         Ok(())
+    ///     })
+    ///     .for_each(|sid_start, block| {
+    ///         for (sid_i, column) in block.columns().into_iter().enumerate() {
+    ///             norms[sid_start + sid_i] = column.dot(&column);
+    ///         }
+    ///         Ok(())
+    ///     })?;
+    /// assert!((norms[0] - 12.0).abs() < 1e-9); // 3 individuals, with variance 4
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn for_each<F>(mut self, f: F) -> Result<(), Box<BedErrorPlus>>
+    where
+        F: FnMut(usize, nd::ArrayView2<'_, TVal>) -> Result<(), Box<BedErrorPlus>>,
+    {
+        self.run(f)
+    }
+
+    /// Run the pipeline, encoding the transformed blocks to a new .bed/.fam/.bim fileset,
+    /// given by `write_options`, through a [`BedWriter`](struct.BedWriter.html).
+    ///
+    /// The .fam and .bim rows of the selection, as updated by the stages, are written for any
+    /// metadata fields not set in `write_options`. The read and write options should agree on
+    /// [`is_a1_counted`](struct.ReadOptionsBuilder.html#method.is_a1_counted) and the missing
+    /// value, as they do by default, and the stages should leave only 0, 1, 2, and missing
+    /// values, unless `write_options` allows others. Returns a
+    /// [`BedError::PipelineOverwritesInput`](enum.BedError.html#variant.PipelineOverwritesInput)
+    /// error if the output .bed file is the input. On error, the output .bed file is removed.
+    ///
+    /// > See [`Pipeline`](struct.Pipeline.html) for an example.
+    pub fn write(
+        mut self,
+        write_options: &WriteOptionsBuilder<TVal>,
+    ) -> Result<WriteSummary, Box<BedErrorPlus>>
+    where
+        TVal: Into<f64>,
+    {
+        if let Some(path) = &write_options.path {
+            if let (Ok(input), Ok(output)) =
+                (fs::canonicalize(&self.bed.path), fs::canonicalize(path))
+            {
+                if input == output {
+                    Err(BedError::PipelineOverwritesInput(path.clone()))?;
+                }
+            }
+        }
+        let mut metadata = self
+            .bed
+            .metadata()?
+            .filter_iid(&self.iid_index)?
+            .filter_sid(&self.sid_index)?;
+        for stage in &self.stages {
+            stage.metadata(&mut metadata)?;
+        }
+
+        let mut writer = write_options.bed_writer(self.iid_index.len())?;
+        let result = writer
+            .fill_metadata(&metadata)
+            .and_then(|()| self.run(|_sid_start, block| writer.write_block(&block)));
+        if result.is_err() {
+            // Clean up the file
+            if let Some(path) = &write_options.path {
+                let _ = fs::remove_file(path);
+            }
+        }
+        result?;
+        writer.finish()
+    }
+
+    // Read, decode, and transform each block, in order, giving it to sink.
+    fn run<F>(&mut self, mut sink: F) -> Result<(), Box<BedErrorPlus>>
+    where
+        F: FnMut(usize, nd::ArrayView2<'_, TVal>) -> Result<(), Box<BedErrorPlus>>,
+    {
+        let sid_count = self.sid_index.len();
+        for stage in &mut self.stages {
+            stage.start(sid_count)?;
+        }
+        let chunk_sids = self
+            .chunk_sids
+            .unwrap_or((PIPELINE_CHUNK_VALUES / self.iid_index.len().max(1)).max(1));
+        let read_options = self.decode_options()?;
+        let mut sid_start = 0;
+        for block in self.bed.iter_sid_chunks(chunk_sids, &read_options)? {
+            let mut block = block?;
+            for stage in &mut self.stages {
+                stage.apply(sid_start, &mut block.view_mut())?;
+            }
+            sink(sid_start, block.view())?;
+            sid_start += block.ncols();
+        }
+        Ok(())
+    }
+
+    // The read options, with the flips folded into count_a1_mask, so that each SNP is
+    // decoded with its own look-up table.
+    fn decode_options(&self) -> Result<ReadOptions<TVal>, Box<BedErrorPlus>> {
+        let mut read_options = self.read_options.clone();
+        if self.flip_masks.is_empty() {
+            return Ok(read_options);
+        }
+        let sid_count = self.bed.sid_count()?;
+        let mut count_a1_mask = read_options
+            .count_a1_mask
+            .take()
+            .unwrap_or_else(|| nd::Array1::from_elem(sid_count, read_options.is_a1_counted));
+        let mut is_flipped: Vec<Option<bool>> = vec![None; sid_count];
+        for (out_sid_i, &in_sid_i) in self.sid_index.iter().enumerate() {
+            // "as" is safe because the index was already checked against sid_count
+            let in_sid_i = if in_sid_i >= 0 {
+                in_sid_i as usize
+            } else {
+                sid_count - ((-in_sid_i) as usize)
+            };
+            let flip = self
+                .flip_masks
+                .iter()
+                .filter(|flip_mask| flip_mask[out_sid_i])
+                .count()
+                % 2
+                == 1;
+            match is_flipped[in_sid_i] {
+                None => {
+                    is_flipped[in_sid_i] = Some(flip);
+                    count_a1_mask[in_sid_i] ^= flip;
+                }
+                Some(was_flipped) if was_flipped != flip => {
+                    Err(BedError::InconsistentFlip(in_sid_i))?;
+                }
+                Some(_) => {}
+            }
+        }
+        read_options.count_a1_mask = Some(count_a1_mask);
+        Ok(read_options)
+    }
+}
+
+impl<TVal> Pipeline<'_, TVal>
+where
+    TVal: BedVal + Float + ToPrimitive + FromPrimitive,
+{
+    /// Add a stage that centers and scales each SNP (variant), as
+    /// [`standardize`](fn.standardize.html) does. Each SNP is in one block, so it is
+    /// standardized over all the individuals (samples) selected.
+    ///
+    /// > See [`Pipeline::for_each`](struct.Pipeline.html#method.for_each) for an example.
+    #[must_use]
+    pub fn standardize(self, dist: Dist) -> Self {
+        self.stage(Standardize(dist))
+    }
+}
+
+impl Bed {
+    /// Start a [`Pipeline`](struct.Pipeline.html) that streams the individuals (samples) and
+    /// SNPs (variants) selected by `read_options` through transform stages, a block of SNPs at
+    /// a time.
+    ///
+    /// The individuals and SNPs are selected once, now, including by predicate, score, and
+    /// region. Blocks always have one row per individual and one column per SNP, whatever
+    /// the output order and orientation options.
+    ///
+    /// > See [`Pipeline`](struct.Pipeline.html) for an example.
+    pub fn pipeline<TVal: BedVal>(
+        &self,
+        read_options: &ReadOptions<TVal>,
+    ) -> Result<Pipeline<'_, TVal>, Box<BedErrorPlus>> {
+        let iid_count = self.iid_count()?;
+        let sid_count = self.sid_count()?;
+        let iid_index = self.filter_iids(iid_count, read_options)?.as_ref().clone();
+        let sid_index = self.filter_sids(sid_count, read_options)?.as_ref().clone();
+
+        let mut read_options = read_options.clone();
+        read_options.iid_index = Index::Vec(iid_index.clone());
+        read_options.sid_index = Index::Vec(sid_index.clone());
+        read_options.iid_where = None;
+        read_options.score_filters = Vec::new();
+        read_options.sid_filter = None;
+        read_options.regions = None;
+        read_options.is_transposed = false;
+
+        Ok(Pipeline {
+            bed: self,
+            read_options,
+            iid_index,
+            sid_index,
+            stages: Vec::new(),
+            flip_masks: Vec::new(),
+            chunk_sids: None,
+        })
+    }
+}
@@ -0,0 +1,377 @@
+use anyinput::anyinput;
+use ndarray as nd;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::{BedError, BedErrorPlus, Metadata, MetadataFields};
+
+// Without a header line, PLINK 2 reads .psam and .pvar files as .fam and .bim files.
+const FAM_COLUMNS: [&str; 6] = ["FID", "IID", "PAT", "MAT", "SEX", "PHENO1"];
+const BIM_COLUMNS: [&str; 6] = ["CHROM", "ID", "CM", "POS", "ALT", "REF"];
+const PSAM_NON_PHENO_COLUMNS: [&str; 6] = ["FID", "IID", "SID", "PAT", "MAT", "SEX"];
+
+// The columns of a PLINK 2 text table, by name, and its line count.
+struct Plink2Table {
+    names: Vec<String>,
+    columns: Vec<Vec<String>>,
+    count: usize,
+}
+
+impl Plink2Table {
+    // Read a .psam or .pvar file. "##" lines are skipped and a "#" line is the header.
+    fn read(path: &Path, headerless_names: &[&str]) -> Result<Plink2Table, Box<BedErrorPlus>> {
+        let mut names: Option<Vec<String>> = None;
+        let mut columns: Vec<Vec<String>> = Vec::new();
+        let mut count = 0;
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            if line.starts_with("##") || line.trim().is_empty() {
+                continue;
+            }
+            if names.is_none() {
+                let header: Vec<String> = if let Some(header) = line.strip_prefix('#') {
+                    header.split_whitespace().map(str::to_string).collect()
+                } else {
+                    headerless_names.iter().map(|s| (*s).to_string()).collect()
+                };
+                columns = vec![Vec::new(); header.len()];
+                names = Some(header);
+                if line.starts_with('#') {
+                    continue;
+                }
+            }
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            if fields.len() != columns.len() {
+                Err(BedError::MetadataFieldCount(
+                    columns.len(),
+                    fields.len(),
+                    path.to_path_buf(),
+                ))?;
+            }
+            for (column, field) in columns.iter_mut().zip(fields) {
+                column.push(field.to_string());
+            }
+            count += 1;
+        }
+        Ok(Plink2Table {
+            names: names.unwrap_or_default(),
+            columns,
+            count,
+        })
+    }
+
+    // Take the values of the named column, if it is present.
+    fn take(&mut self, name: &str) -> Option<Vec<String>> {
+        let column_i = self.names.iter().position(|n| n == name)?;
+        Some(std::mem::take(&mut self.columns[column_i]))
+    }
+
+    fn require(&mut self, name: &str, path: &Path) -> Result<Vec<String>, Box<BedErrorPlus>> {
+        Ok(self
+            .take(name)
+            .ok_or_else(|| BedError::MissingPlink2Column(name.to_string(), path.to_path_buf()))?)
+    }
+}
+
+fn parse_sex(sex: &str) -> Result<i32, Box<BedErrorPlus>> {
+    Ok(match sex {
+        "M" | "m" => 1,
+        "F" | "f" => 2,
+        "NA" => 0,
+        _ => sex.parse::<i32>()?,
+    })
+}
+
+// Store a field's values unless it is already known or skipped.
+fn set_field<T>(
+    field: &mut Option<Arc<nd::Array1<T>>>,
+    field_name: MetadataFields,
+    skip_set: &HashSet<MetadataFields>,
+    values: impl FnOnce() -> Result<Option<Vec<T>>, Box<BedErrorPlus>>,
+) -> Result<(), Box<BedErrorPlus>> {
+    if field.is_none() && !skip_set.contains(&field_name) {
+        if let Some(values) = values()? {
+            *field = Some(Arc::new(nd::Array1::from_vec(values)));
+        }
+    }
+    Ok(())
+}
+
+impl Metadata {
+    /// Create a new [`Metadata`](struct.Metadata.html) by filling in empty fields with a
+    /// PLINK 2 .psam file.
+    ///
+    /// The header line, starting with `#FID` or `#IID`, names the columns; `##` lines before it
+    /// are skipped. IID is required. FID, PAT, MAT, and SEX fill the `fid`, `father`, `mother`,
+    /// and `sex` fields, and the first phenotype column, the `pheno` field. Absent columns leave their
+    /// fields empty. Sex may be 1, 2, M, F, or NA (0). A file without a header is read as a
+    /// .fam file, as PLINK 2 does. Also returns the number of individuals (samples).
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use std::collections::HashSet;
+    /// use bed_reader::Metadata;
+    ///
+    /// let temp_out = temp_testdir::TempDir::default();
+    /// let psam_file = temp_out.join("small.psam");
+    /// std::fs::write(&psam_file, "#IID\tSEX\tHEIGHT\nid1\t2\t1.6\nid2\tNA\t1.8\n")?;
+    ///
+    /// let (metadata, iid_count) = Metadata::new().read_psam(&psam_file, &HashSet::new())?;
+    /// assert_eq!(iid_count, 2);
+    /// assert_eq!(metadata.sex(), Some(&nd::array![2, 0]));
+    /// println!("{0:?}", metadata.pheno()); // Outputs optional ndarray Some(["1.6", "1.8"]...)
+    /// assert!(metadata.fid().is_none());
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[anyinput]
+    pub fn read_psam(
+        &self,
+        path: AnyPath,
+        skip_set: &HashSet<MetadataFields>,
+    ) -> Result<(Metadata, usize), Box<BedErrorPlus>> {
+        let mut table = Plink2Table::read(path, &FAM_COLUMNS)?;
+        let mut metadata = self.clone();
+
+        let iid = table.require("IID", path)?;
+        set_field(&mut metadata.iid, MetadataFields::Iid, skip_set, || {
+            Ok(Some(iid))
+        })?;
+        set_field(&mut metadata.fid, MetadataFields::Fid, skip_set, || {
+            Ok(table.take("FID"))
+        })?;
+        set_field(
+            &mut metadata.father,
+            MetadataFields::Father,
+            skip_set,
+            || Ok(table.take("PAT")),
+        )?;
+        set_field(
+            &mut metadata.mother,
+            MetadataFields::Mother,
+            skip_set,
+            || Ok(table.take("MAT")),
+        )?;
+        set_field(&mut metadata.sex, MetadataFields::Sex, skip_set, || {
+            table
+                .take("SEX")
+                .map(|sex| sex.iter().map(|s| parse_sex(s)).collect())
+                .transpose()
+        })?;
+        let pheno_name = table
+            .names
+            .iter()
+            .find(|name| !PSAM_NON_PHENO_COLUMNS.contains(&name.as_str()))
+            .cloned();
+        set_field(&mut metadata.pheno, MetadataFields::Pheno, skip_set, || {
+            Ok(pheno_name.and_then(|name| table.take(&name)))
+        })?;
+
+        metadata.check_counts(Some(table.count), None)?;
+        Ok((metadata, table.count))
+    }
+
+    /// Create a new [`Metadata`](struct.Metadata.html) by filling in empty fields with a
+    /// PLINK 2 .pvar file.
+    ///
+    /// The header line, starting with `#CHROM`, names the columns; `##` lines before it, such
+    /// as VCF-style INFO definitions, are skipped. CHROM, POS, ID, REF, and ALT are required
+    /// and fill the `chromosome`, `bp_position`, `sid`, `allele_2`, and `allele_1` fields, so
+    /// ALT is allele 1, as in the .bim files PLINK 2 writes. An optional CM column fills
+    /// `cm_position`.
+    /// QUAL, FILTER, INFO, and other columns are ignored. A file without a header is read as a
+    /// .bim file, as PLINK 2 does. Also returns the number of SNPs (variants).
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use std::collections::HashSet;
+    /// use bed_reader::Metadata;
+    ///
+    /// let temp_out = temp_testdir::TempDir::default();
+    /// let pvar_file = temp_out.join("small.pvar");
+    /// std::fs::write(
+    ///     &pvar_file,
+    ///     "##fileformat=VCFv4.2\n#CHROM\tPOS\tID\tREF\tALT\n1\t100\trs1\tA\tG\n2\t200\trs2\tC\tT\n",
+    /// )?;
+    ///
+    /// let (metadata, sid_count) = Metadata::new().read_pvar(&pvar_file, &HashSet::new())?;
+    /// assert_eq!(sid_count, 2);
+    /// assert_eq!(metadata.bp_position(), Some(&nd::array![100, 200]));
+    /// println!("{0:?}", metadata.allele_1()); // Outputs optional ndarray Some(["G", "T"]...)
+    /// assert!(metadata.cm_position().is_none());
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[anyinput]
+    pub fn read_pvar(
+        &self,
+        path: AnyPath,
+        skip_set: &HashSet<MetadataFields>,
+    ) -> Result<(Metadata, usize), Box<BedErrorPlus>> {
+        let mut table = Plink2Table::read(path, &BIM_COLUMNS)?;
+        let mut metadata = self.clone();
+
+        let chromosome = table.require("CHROM", path)?;
+        let bp_position = table.require("POS", path)?;
+        let sid = table.require("ID", path)?;
+        let allele_2 = table.require("REF", path)?;
+        let allele_1 = table.require("ALT", path)?;
+        set_field(
+            &mut metadata.chromosome,
+            MetadataFields::Chromosome,
+            skip_set,
+            || Ok(Some(chromosome)),
+        )?;
+        set_field(
+            &mut metadata.bp_position,
+            MetadataFields::BpPosition,
+            skip_set,
+            || {
+                Ok(Some(
+                    bp_position
+                        .iter()
+                        .map(|s| s.parse())
+                        .collect::<Result<_, _>>()?,
+                ))
+            },
+        )?;
+        set_field(&mut metadata.sid, MetadataFields::Sid, skip_set, || {
+            Ok(Some(sid))
+        })?;
+        set_field(
+            &mut metadata.allele_1,
+            MetadataFields::Allele1,
+            skip_set,
+            || Ok(Some(allele_1)),
+        )?;
+        set_field(
+            &mut metadata.allele_2,
+            MetadataFields::Allele2,
+            skip_set,
+            || Ok(Some(allele_2)),
+        )?;
+        set_field(
+            &mut metadata.cm_position,
+            MetadataFields::CmPosition,
+            skip_set,
+            || {
+                table
+                    .take("CM")
+                    .map(|cm| cm.iter().map(|s| s.parse()).collect())
+                    .transpose()
+                    .map_err(Into::into)
+            },
+        )?;
+
+        metadata.check_counts(None, Some(table.count))?;
+        Ok((metadata, table.count))
+    }
+
+    /// Write the metadata related to individuals/samples to a PLINK 2 .psam file.
+    ///
+    /// The file has a `#FID IID PAT MAT SEX PHENO1` header and tab-separated columns, so
+    /// [`read_psam`](struct.Metadata.html#method.read_psam) gets back the same metadata. Sex 0
+    /// is written as NA. If any of the .fam metadata is not present, the function will return
+    /// an error.
+    ///
+    /// # Example
+    /// ```
+    /// use std::collections::HashSet;
+    /// use bed_reader::Metadata;
+    ///
+    /// let metadata = Metadata::builder().iid(["i1", "i2"]).sex([1, 0]).build()?.fill(2, 0)?;
+    /// let temp_out = temp_testdir::TempDir::default();
+    /// let psam_file = temp_out.join("small.psam");
+    /// metadata.write_psam(&psam_file)?;
+    /// assert_eq!(
+    ///     std::fs::read_to_string(&psam_file)?,
+    ///     "#FID\tIID\tPAT\tMAT\tSEX\tPHENO1\n0\ti1\t0\t0\t1\t0\n0\ti2\t0\t0\tNA\t0\n"
+    /// );
+    /// let (metadata2, _) = Metadata::new().read_psam(&psam_file, &HashSet::new())?;
+    /// assert_eq!(metadata2.sex(), metadata.sex());
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[anyinput]
+    pub fn write_psam(&self, path: AnyPath) -> Result<(), Box<BedErrorPlus>> {
+        if !self.is_some_fam() {
+            Err(BedError::MetadataMissingForWrite("psam".to_string()))?;
+        }
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "#FID\tIID\tPAT\tMAT\tSEX\tPHENO1")?;
+        // unwraps are safe because we checked that all the fields are present
+        for (((((fid, iid), father), mother), sex), pheno) in self
+            .fid()
+            .unwrap()
+            .iter()
+            .zip(self.iid().unwrap())
+            .zip(self.father().unwrap())
+            .zip(self.mother().unwrap())
+            .zip(self.sex().unwrap())
+            .zip(self.pheno().unwrap())
+        {
+            let sex = if *sex == 0 {
+                "NA".to_string()
+            } else {
+                sex.to_string()
+            };
+            writeln!(writer, "{fid}\t{iid}\t{father}\t{mother}\t{sex}\t{pheno}")?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Write the metadata related to SNPs/variants to a PLINK 2 .pvar file.
+    ///
+    /// The file has a `#CHROM POS ID REF ALT CM` header and tab-separated columns, with
+    /// allele 2 as REF and allele 1 as ALT, so
+    /// [`read_pvar`](struct.Metadata.html#method.read_pvar) gets back the same metadata.
+    /// If any of the .bim metadata is not present, the function will return an error.
+    ///
+    /// # Example
+    /// ```
+    /// use bed_reader::Metadata;
+    ///
+    /// let metadata = Metadata::builder().sid(["s1", "s2"]).build()?.fill(0, 2)?;
+    /// let temp_out = temp_testdir::TempDir::default();
+    /// let pvar_file = temp_out.join("small.pvar");
+    /// metadata.write_pvar(&pvar_file)?;
+    /// assert_eq!(
+    ///     std::fs::read_to_string(&pvar_file)?,
+    ///     "#CHROM\tPOS\tID\tREF\tALT\tCM\n0\t0\ts1\tA2\tA1\t0\n0\t0\ts2\tA2\tA1\t0\n"
+    /// );
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[anyinput]
+    pub fn write_pvar(&self, path: AnyPath) -> Result<(), Box<BedErrorPlus>> {
+        if !self.is_some_bim() {
+            Err(BedError::MetadataMissingForWrite("pvar".to_string()))?;
+        }
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "#CHROM\tPOS\tID\tREF\tALT\tCM")?;
+        // unwraps are safe because we checked that all the fields are present
+        for (((((chromosome, bp_position), sid), allele_2), allele_1), cm_position) in self
+            .chromosome()
+            .unwrap()
+            .iter()
+            .zip(self.bp_position().unwrap())
+            .zip(self.sid().unwrap())
+            .zip(self.allele_2().unwrap())
+            .zip(self.allele_1().unwrap())
+            .zip(self.cm_position().unwrap())
+        {
+            writeln!(
+                writer,
+                "{chromosome}\t{bp_position}\t{sid}\t{allele_2}\t{allele_1}\t{cm_position}"
+            )?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
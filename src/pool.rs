@@ -0,0 +1,161 @@
+use anyinput::anyinput;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::{to_metadata_path, Bed, BedErrorPlus};
+
+/// A thread-safe registry of open [`Bed`](struct.Bed.html)s, keyed by canonical path.
+///
+/// Services that open the same files from many request handlers can share one `BedPool`
+/// (for example, in an `Arc` or a `static`). Each [`get`](struct.BedPool.html#method.get) of the
+/// same file returns the same shared handle, so the .fam and .bim files are read at most once.
+///
+/// A cached handle is trusted for the pool's time-to-live (TTL). After that, the next `get` checks
+/// the size and modification time of the .bed, .fam, and .bim files and, if any changed, opens the
+/// file again. Handles returned earlier keep working on the old data.
+pub struct BedPool {
+    ttl: Duration,
+    entries: Mutex<HashMap<PathBuf, PoolEntry>>,
+}
+
+struct PoolEntry {
+    bed: Arc<Bed>,
+    signature: Vec<Option<(u64, SystemTime)>>,
+    checked_at: Instant,
+}
+
+// The size and modification time of the .bed, .fam, and .bim files. Missing files are None.
+fn file_signature(path: &Path) -> Vec<Option<(u64, SystemTime)>> {
+    [
+        path.to_path_buf(),
+        to_metadata_path(path, &None, "fam"),
+        to_metadata_path(path, &None, "bim"),
+    ]
+    .iter()
+    .map(|path| {
+        let metadata = fs::metadata(path).ok()?;
+        Some((metadata.len(), metadata.modified().ok()?))
+    })
+    .collect()
+}
+
+impl BedPool {
+    /// Create an empty pool whose cached handles are trusted for `ttl` before being re-checked.
+    ///
+    /// A `ttl` of zero checks the files on every [`get`](struct.BedPool.html#method.get).
+    ///
+    /// # Example
+    /// ```
+    /// use std::time::Duration;
+    /// use std::sync::Arc;
+    /// use bed_reader::{BedPool, sample_bed_file};
+    ///
+    /// let pool = Arc::new(BedPool::new(Duration::from_secs(5)));
+    /// let file_name = sample_bed_file("small.bed")?;
+    /// let handles: Vec<_> = (0..4)
+    ///     .map(|_| {
+    ///         let pool = Arc::clone(&pool);
+    ///         let file_name = file_name.clone();
+    ///         std::thread::spawn(move || {
+    ///             let bed = pool.get(&file_name).unwrap();
+    ///             let val = bed.read::<i8>().unwrap();
+    ///             val.dim()
+    ///         })
+    ///     })
+    ///     .collect();
+    /// for handle in handles {
+    ///     assert_eq!(handle.join().unwrap(), (3, 4));
+    /// }
+    /// assert_eq!(pool.len(), 1);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[must_use]
+    pub fn new(ttl: Duration) -> Self {
+        BedPool {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Return the shared handle for a .bed file, opening it if it isn't cached or has changed.
+    ///
+    /// The path is canonicalized, so different spellings of the same path share one handle.
+    /// Reads and metadata take `&self`, so the handle can be used from many threads at once.
+    ///
+    /// # Example
+    /// ```
+    /// use std::time::Duration;
+    /// use std::sync::Arc;
+    /// use bed_reader::{BedPool, sample_bed_file};
+    ///
+    /// let pool = BedPool::new(Duration::from_secs(5));
+    /// let file_name = sample_bed_file("small.bed")?;
+    /// let bed = pool.get(&file_name)?;
+    /// println!("{:?}", bed.iid()?); // Outputs ndarray ["iid1", "iid2", "iid3"]
+    /// assert!(Arc::ptr_eq(&bed, &pool.get(&file_name)?));
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[anyinput]
+    pub fn get(&self, path: AnyPath) -> Result<Arc<Bed>, Box<BedErrorPlus>> {
+        let path = fs::canonicalize(path)?;
+        // A panic while holding the lock can't leave the map half-updated, so ignore poisoning.
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if let Some(entry) = entries.get(&path) {
+            if entry.checked_at.elapsed() < self.ttl {
+                return Ok(Arc::clone(&entry.bed));
+            }
+        }
+        let signature = file_signature(&path);
+        if let Some(entry) = entries.get_mut(&path) {
+            if entry.signature == signature {
+                entry.checked_at = Instant::now();
+                return Ok(Arc::clone(&entry.bed));
+            }
+        }
+
+        let bed = Arc::new(Bed::new(&path)?);
+        entries.insert(
+            path,
+            PoolEntry {
+                bed: Arc::clone(&bed),
+                signature,
+                checked_at: Instant::now(),
+            },
+        );
+        Ok(bed)
+    }
+
+    /// Forget the cached handle for a .bed file, if any. Returns true if a handle was removed.
+    #[anyinput]
+    pub fn remove(&self, path: AnyPath) -> bool {
+        let Ok(path) = fs::canonicalize(path) else {
+            return false;
+        };
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&path)
+            .is_some()
+    }
+
+    /// The number of cached handles.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    /// True if no handles are cached.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
@@ -0,0 +1,102 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::{BedVal, ReadOptionsBuilder, WriteOptionsBuilder};
+
+// The most genotype values read between progress reports.
+pub(crate) const PROGRESS_CHUNK_VALUES: usize = 8_000_000;
+
+// A user callback given the SNPs (variants) processed so far and the total SNPs to process.
+#[derive(Clone)]
+pub(crate) struct Progress(Arc<dyn Fn(usize, usize) + Send + Sync>);
+
+impl Progress {
+    pub(crate) fn report(&self, done: usize, total: usize) {
+        (self.0)(done, total);
+    }
+}
+
+impl fmt::Debug for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Progress(..)")
+    }
+}
+
+impl<TVal: BedVal> ReadOptionsBuilder<TVal> {
+    /// Call `progress` with the SNPs (variants) read so far and the total SNPs to read, for
+    /// example, to drive a progress bar on a multi-gigabyte file.
+    ///
+    /// `progress` is called once with 0 before reading and then after each chunk of up to
+    /// 8,000,000 genotype values, ending with the total. Each SNP read is
+    /// `(iid_count + 3) / 4` bytes of the .bed file. Progress is reported by reads of a
+    /// local [`Bed`](struct.Bed.html).
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, ReadOptions, WriteOptions};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("progress.bed");
+    /// WriteOptions::builder(&output_file).write(&nd::Array2::<i8>::zeros((3, 5)))?;
+    ///
+    /// let reports = Arc::new(Mutex::new(Vec::new()));
+    /// let reports_clone = Arc::clone(&reports);
+    /// let mut bed = Bed::new(&output_file)?;
+    /// ReadOptions::builder()
+    ///     .progress(move |done, total| reports_clone.lock().unwrap().push((done, total)))
+    ///     .i8()
+    ///     .read(&mut bed)?;
+    /// assert_eq!(*reports.lock().unwrap(), vec![(0, 5), (5, 5)]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn progress<F>(&mut self, progress: F) -> &mut Self
+    where
+        F: Fn(usize, usize) + Send + Sync + 'static,
+    {
+        self.progress = Some(Some(Progress(Arc::new(progress))));
+        self
+    }
+}
+
+impl<TVal> WriteOptionsBuilder<TVal>
+where
+    TVal: BedVal,
+{
+    /// Call `progress` with the SNPs (variants) written so far and the total SNPs to write,
+    /// for example, to drive a progress bar on a multi-gigabyte file.
+    ///
+    /// `progress` is called once with 0 before writing and then after each SNP is encoded
+    /// and written, in order, or, when writing to the cloud, after each chunk is uploaded.
+    /// Each SNP written is `(iid_count + 3) / 4` bytes of the .bed file. Progress is reported
+    /// by writes of a whole array, such as
+    /// [`WriteOptionsBuilder::write`](struct.WriteOptionsBuilder.html#method.write), but not
+    /// by a [`BedWriter`](struct.BedWriter.html), whose caller gives it each block.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::WriteOptions;
+    /// use std::sync::atomic::{AtomicUsize, Ordering};
+    /// use std::sync::Arc;
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let written = Arc::new(AtomicUsize::new(0));
+    /// let written_clone = Arc::clone(&written);
+    /// WriteOptions::builder(output_folder.join("progress.bed"))
+    ///     .progress(move |done, _total| written_clone.store(done, Ordering::Relaxed))
+    ///     .write(&nd::Array2::<i8>::zeros((3, 5)))?;
+    /// assert_eq!(written.load(Ordering::Relaxed), 5);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn progress<F>(&mut self, progress: F) -> &mut Self
+    where
+        F: Fn(usize, usize) + Send + Sync + 'static,
+    {
+        self.progress = Some(Some(Progress(Arc::new(progress))));
+        self
+    }
+}
@@ -1,11 +1,11 @@
 #![cfg(feature = "extension-module")]
 
-use crate::{BedCloud, CloudFile};
 use crate::{
-    BedError, BedErrorPlus, Dist, _file_ata_piece_internal, create_pool, file_aat_piece,
-    file_ata_piece, file_b_less_aatbx, impute_and_zero_mean_snps, matrix_subset_no_alloc,
-    read_into_f32, read_into_f64, Bed, ReadOptions, WriteOptions,
+    _file_ata_piece_internal, create_pool, file_aat_piece, file_ata_piece, file_b_less_aatbx,
+    impute_and_zero_mean_snps, matrix_subset_no_alloc, read_into_f32, read_into_f64, Bed, BedError,
+    BedErrorPlus, Dist, ReadOptions, WriteOptions,
 };
+use crate::{BedCloud, CloudFile};
 use numpy::{PyArray1, PyArray2, PyArray3};
 use pyo3::{
     exceptions::PyIOError,
@@ -467,7 +467,7 @@ fn bed_reader(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
         let mut val = val.as_array_mut();
         let mut stats = stats.readwrite();
         let mut stats = stats.as_array_mut();
-        let dist = create_dist(beta_not_unit_variance, beta_a, beta_b);
+        let dist = create_dist(beta_not_unit_variance, beta_a, beta_b)?;
         create_pool(num_threads)?.install(|| {
             impute_and_zero_mean_snps(
                 &mut val.view_mut(),
@@ -480,12 +480,18 @@ fn bed_reader(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
         Ok(())
     }
 
-    fn create_dist(beta_not_unit_variance: bool, a: f64, b: f64) -> Dist {
-        if beta_not_unit_variance {
-            Dist::Beta { a, b }
-        } else {
-            Dist::Unit
+    #[cfg_attr(feature = "beta", allow(clippy::unnecessary_wraps))]
+    #[cfg_attr(not(feature = "beta"), allow(unused_variables))]
+    fn create_dist(beta_not_unit_variance: bool, a: f64, b: f64) -> Result<Dist, PyErr> {
+        if !beta_not_unit_variance {
+            return Ok(Dist::Unit);
         }
+        #[cfg(feature = "beta")]
+        return Ok(Dist::Beta { a, b });
+        #[cfg(not(feature = "beta"))]
+        Err(PyValueError::new_err(
+            "standardizing with a beta distribution needs the 'beta' feature",
+        ))
     }
 
     #[pyfn(m)]
@@ -504,7 +510,7 @@ fn bed_reader(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
         let mut val = val.as_array_mut();
         let mut stats = stats.readwrite();
         let mut stats = stats.as_array_mut();
-        let dist = create_dist(beta_not_unit_variance, beta_a, beta_b);
+        let dist = create_dist(beta_not_unit_variance, beta_a, beta_b)?;
 
         create_pool(num_threads)?.install(|| {
             impute_and_zero_mean_snps(
@@ -532,6 +538,11 @@ fn bed_reader(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     ) -> Result<(), PyErr> {
         let mut ata_piece = ata_piece.readwrite();
         let mut ata_piece = ata_piece.as_array_mut();
+        let (nrows, ncols) = ata_piece.dim();
+        log_header(
+            log_frequency,
+            &format!("file_ata_piece: col_start={col_start}, {nrows}x{ncols} output"),
+        );
 
         create_pool(num_threads)?.install(|| {
             file_ata_piece(
@@ -541,7 +552,7 @@ fn bed_reader(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
                 col_count,
                 col_start,
                 &mut ata_piece,
-                log_frequency,
+                &log_progress(log_frequency),
                 read_into_f32,
             )
         })?;
@@ -563,6 +574,11 @@ fn bed_reader(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     ) -> Result<(), PyErr> {
         let mut ata_piece = ata_piece.readwrite();
         let mut ata_piece = ata_piece.as_array_mut();
+        let (nrows, ncols) = ata_piece.dim();
+        log_header(
+            log_frequency,
+            &format!("file_ata_piece: col_start={col_start}, {nrows}x{ncols} output"),
+        );
 
         create_pool(num_threads)?.install(|| {
             file_ata_piece(
@@ -572,7 +588,7 @@ fn bed_reader(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
                 col_count,
                 col_start,
                 &mut ata_piece,
-                log_frequency,
+                &log_progress(log_frequency),
                 read_into_f64,
             )
         })?;
@@ -593,6 +609,11 @@ fn bed_reader(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     ) -> Result<(), PyErr> {
         let mut ata_piece = ata_piece.readwrite();
         let mut ata_piece = ata_piece.as_array_mut();
+        let (nrows, ncols) = ata_piece.dim();
+        log_header(
+            log_frequency,
+            &format!("file_ata_piece: col_start={col_start}, {nrows}x{ncols} output"),
+        );
 
         create_pool(num_threads)?.install(|| {
             _file_ata_piece_internal(
@@ -601,7 +622,7 @@ fn bed_reader(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
                 row_count,
                 col_start,
                 &mut ata_piece,
-                log_frequency,
+                &log_progress(log_frequency),
                 read_into_f64,
             )
         })?;
@@ -623,6 +644,11 @@ fn bed_reader(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     ) -> Result<(), PyErr> {
         let mut aat_piece = aat_piece.readwrite();
         let mut aat_piece = aat_piece.as_array_mut();
+        let (nrows, ncols) = aat_piece.dim();
+        log_header(
+            log_frequency,
+            &format!("file_aat_piece: row_start={row_start}, {nrows}x{ncols} output"),
+        );
 
         create_pool(num_threads)?.install(|| {
             file_aat_piece(
@@ -632,7 +658,7 @@ fn bed_reader(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
                 col_count,
                 row_start,
                 &mut aat_piece,
-                log_frequency,
+                &log_progress(log_frequency),
                 read_into_f32,
             )
         })?;
@@ -654,6 +680,11 @@ fn bed_reader(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     ) -> Result<(), PyErr> {
         let mut aat_piece = aat_piece.readwrite();
         let mut aat_piece = aat_piece.as_array_mut();
+        let (nrows, ncols) = aat_piece.dim();
+        log_header(
+            log_frequency,
+            &format!("file_aat_piece: row_start={row_start}, {nrows}x{ncols} output"),
+        );
 
         create_pool(num_threads)?.install(|| {
             file_aat_piece(
@@ -663,7 +694,7 @@ fn bed_reader(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
                 col_count,
                 row_start,
                 &mut aat_piece,
-                log_frequency,
+                &log_progress(log_frequency),
                 read_into_f64,
             )
         })?;
@@ -690,16 +721,24 @@ fn bed_reader(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
         let mut aatb = aatb.as_array_mut();
         let mut atb = atb.readwrite();
         let mut atb = atb.as_array_mut();
+        let (a_sid_count, b_sid_count) = atb.dim();
+        log_header(
+            log_frequency,
+            &format!(
+                "file_b_less_aatbx: iid_count={iid_count}, {a_sid_count}x{b_sid_count} output"
+            ),
+        );
+        let progress = |a_sid_index: usize, a_sid_count: usize| {
+            if log_frequency > 0 && a_sid_index.is_multiple_of(log_frequency) {
+                println!(
+                    "   working on train_sid_index={a_sid_index} of {a_sid_count} (iid_count={iid_count}, b_sid_count={b_sid_count})"
+                );
+            }
+        };
 
         create_pool(num_threads)?.install(|| {
             file_b_less_aatbx(
-                a_filename,
-                offset,
-                iid_count,
-                &mut b1,
-                &mut aatb,
-                &mut atb,
-                log_frequency,
+                a_filename, offset, iid_count, &mut b1, &mut aatb, &mut atb, &progress,
             )
         })?;
 
@@ -708,4 +747,20 @@ fn bed_reader(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     Ok(())
 }
 
+// Print, if logging, the header line for a call to a low-rank helper.
+fn log_header(log_frequency: usize, header: &str) {
+    if log_frequency > 0 {
+        println!("{header}");
+    }
+}
+
+// Print, every log_frequency steps, the step that a low-rank helper is working on.
+fn log_progress(log_frequency: usize) -> impl Fn(usize, usize) {
+    move |index, count| {
+        if log_frequency > 0 && index.is_multiple_of(log_frequency) {
+            println!("   working on {index} of {count}");
+        }
+    }
+}
+
 // LATER on both rust and python side, when counting bim and fam files, also parse them -- don't read them twice.
@@ -0,0 +1,424 @@
+//! Standard quality-control (QC) metrics of genotype data, computed in parallel, streaming
+//! the .bed file in chunks of SNPs (variants).
+//!
+//! [`hwe_pvalues`](fn.hwe_pvalues.html) gives one value per SNP, in the order of
+//! [`Bed::sid`](../struct.Bed.html#method.sid).
+//! [`per_sample_missing_rate`](fn.per_sample_missing_rate.html) and
+//! [`heterozygosity`](fn.heterozygosity.html) give one value per individual (sample), in the
+//! order of [`Bed::iid`](../struct.Bed.html#method.iid). Each is over all individuals and SNPs
+//! in the file, as PLINK's `--hardy`, `--missing`, and `--het` are, so no export is needed.
+//!
+//! [`Bed::extract_qc`](../struct.Bed.html#method.extract_qc) writes a new fileset of only the
+//! individuals and SNPs that pass a [`QcFilter`](struct.QcFilter.html) of thresholds on these
+//! metrics.
+use derive_builder::Builder;
+use ndarray as nd;
+
+use crate::parallel::par_azip;
+use crate::parallel::prelude::*;
+use crate::{Bed, BedErrorPlus, BedVal, Index, ReadOptions, WriteOptionsBuilder, WriteSummary};
+
+// The most genotype values read at once when computing QC metrics.
+const QC_CHUNK_VALUES: usize = 8_000_000;
+
+/// The p-value of the exact test for Hardy-Weinberg equilibrium (HWE) of each SNP (variant),
+/// over the individuals (samples) with a non-missing value.
+///
+/// The test is the exact test of Wigginton, Cutler, and Abecasis (2005), as used by PLINK. It
+/// gives the probability, given the SNP's allele counts, of a number of heterozygotes as
+/// unlikely as, or less likely than, the number seen. Small p-values suggest genotyping error
+/// or, for example, population structure. A SNP with no calls has a NaN p-value.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{qc, Bed, WriteOptions};
+///
+/// let output_folder = temp_testdir::TempDir::default();
+/// let output_file = output_folder.join("qc.bed");
+/// // The first SNP's genotypes are in HWE. The second SNP has only heterozygotes.
+/// let val = nd::Array2::from_shape_fn((16, 2), |(iid_i, sid_i)| match sid_i {
+///     0 => [0, 1, 1, 2][iid_i % 4],
+///     _ => 1,
+/// });
+/// WriteOptions::builder(&output_file).i8().write(&val)?;
+///
+/// let mut bed = Bed::new(&output_file)?;
+/// let pvalues = qc::hwe_pvalues(&mut bed)?;
+/// assert!(pvalues[0] > 0.5);
+/// assert!(pvalues[1] < 0.001);
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+pub fn hwe_pvalues(bed: &mut Bed) -> Result<nd::Array1<f64>, Box<BedErrorPlus>> {
+    let genotype_counts = genotype_counts(bed, &ReadOptions::builder().i8().build()?)?;
+    Ok(genotype_counts
+        .into_par_iter()
+        .map(|[hom_1, het, hom_2]| hwe_exact(het, hom_1, hom_2))
+        .collect::<Vec<_>>()
+        .into())
+}
+
+/// The fraction of SNPs (variants) with a missing value, for each individual (sample).
+///
+/// With no SNPs, each rate is NaN.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{qc, Bed, WriteOptions};
+///
+/// let output_folder = temp_testdir::TempDir::default();
+/// let output_file = output_folder.join("qc.bed");
+/// WriteOptions::builder(&output_file)
+///     .write(&nd::array![[0, 1, 2, 1], [-127, 1, -127, 2], [1, 1, 1, 1]])?;
+///
+/// let mut bed = Bed::new(&output_file)?;
+/// assert_eq!(qc::per_sample_missing_rate(&mut bed)?, nd::array![0.0, 0.5, 0.0]);
+/// assert_eq!(qc::heterozygosity(&mut bed)?, nd::array![0.5, 0.5, 1.0]);
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+pub fn per_sample_missing_rate(bed: &mut Bed) -> Result<nd::Array1<f64>, Box<BedErrorPlus>> {
+    let sid_count = bed.sid_count()?;
+    let counts = sample_counts(bed, &ReadOptions::builder().i8().build()?)?;
+    // "as" is safe because the counts fit in f64's exact integers
+    #[allow(clippy::cast_precision_loss)]
+    let missing_rate = counts.mapv(|counts| {
+        if sid_count == 0 {
+            f64::NAN
+        } else {
+            counts.missing as f64 / sid_count as f64
+        }
+    });
+    Ok(missing_rate)
+}
+
+/// The observed heterozygosity of each individual (sample): the fraction of its non-missing
+/// values that are heterozygous.
+///
+/// High heterozygosity can flag a contaminated sample, and low heterozygosity, inbreeding.
+/// An individual with no calls has NaN heterozygosity.
+///
+/// > See [`per_sample_missing_rate`](fn.per_sample_missing_rate.html) for an example.
+pub fn heterozygosity(bed: &mut Bed) -> Result<nd::Array1<f64>, Box<BedErrorPlus>> {
+    let sid_count = bed.sid_count()?;
+    let counts = sample_counts(bed, &ReadOptions::builder().i8().build()?)?;
+    // "as" is safe because the counts fit in f64's exact integers
+    #[allow(clippy::cast_precision_loss)]
+    let heterozygosity = counts.mapv(|counts| {
+        let call_count = sid_count - counts.missing;
+        if call_count == 0 {
+            f64::NAN
+        } else {
+            counts.het as f64 / call_count as f64
+        }
+    });
+    Ok(heterozygosity)
+}
+
+/// QC thresholds for [`Bed::extract_qc`](../struct.Bed.html#method.extract_qc), like PLINK's
+/// `--mind`, `--geno`, `--maf`, and `--hwe`.
+///
+/// Construct with [`QcFilter::builder`](struct.QcFilter.html#method.builder). Each threshold is
+/// optional, and unset thresholds don't filter.
+#[derive(Debug, Clone, Builder)]
+#[builder(build_fn(error = "Box<BedErrorPlus>"))]
+pub struct QcFilter {
+    /// Remove individuals (samples) missing more than this fraction of the selected SNPs
+    /// (variants), like `--mind`.
+    #[builder(default, setter(strip_option))]
+    sample_missing_max: Option<f64>,
+
+    /// Remove SNPs (variants) missing more than this fraction of the kept individuals
+    /// (samples), like `--geno`.
+    #[builder(default, setter(strip_option))]
+    missing_max: Option<f64>,
+
+    /// Remove SNPs (variants) whose minor allele frequency is less than this, like `--maf`.
+    #[builder(default, setter(strip_option))]
+    maf_min: Option<f64>,
+
+    /// Remove SNPs (variants) whose Hardy-Weinberg exact test p-value, as from
+    /// [`hwe_pvalues`](fn.hwe_pvalues.html), is less than this, like `--hwe`.
+    #[builder(default, setter(strip_option))]
+    hwe_p_min: Option<f64>,
+}
+
+impl QcFilter {
+    /// QC thresholds for [`Bed::extract_qc`](../struct.Bed.html#method.extract_qc).
+    ///
+    /// > See [`Bed::extract_qc`](../struct.Bed.html#method.extract_qc) for an example.
+    #[must_use]
+    pub fn builder() -> QcFilterBuilder {
+        QcFilterBuilder::default()
+    }
+
+    /// The most missing fraction for a kept individual (sample), if set.
+    #[must_use]
+    pub fn sample_missing_max(&self) -> Option<f64> {
+        self.sample_missing_max
+    }
+
+    /// The most missing fraction for a kept SNP (variant), if set.
+    #[must_use]
+    pub fn missing_max(&self) -> Option<f64> {
+        self.missing_max
+    }
+
+    /// The least minor allele frequency for a kept SNP (variant), if set.
+    #[must_use]
+    pub fn maf_min(&self) -> Option<f64> {
+        self.maf_min
+    }
+
+    /// The least Hardy-Weinberg p-value for a kept SNP (variant), if set.
+    #[must_use]
+    pub fn hwe_p_min(&self) -> Option<f64> {
+        self.hwe_p_min
+    }
+
+    // True if a SNP with these genotype counts, over iid_count individuals, passes.
+    // NaN statistics never pass.
+    #[allow(clippy::cast_precision_loss)] // "as" is safe because the counts fit in f64's exact integers
+    fn is_sid_kept(&self, [hom_1, het, hom_2]: [usize; 3], iid_count: usize) -> bool {
+        let call_count = hom_1 + het + hom_2;
+        let missing_rate = (iid_count - call_count) as f64 / iid_count as f64;
+        let mean = (het + 2 * hom_2) as f64 / call_count as f64;
+        let maf = (mean / 2.0).min(1.0 - mean / 2.0);
+        self.missing_max.is_none_or(|max| missing_rate <= max)
+            && self.maf_min.is_none_or(|min| maf >= min)
+            && self
+                .hwe_p_min
+                .is_none_or(|min| hwe_exact(het, hom_1, hom_2) >= min)
+    }
+
+    // The individuals and SNPs selected by read_options that pass, as read options that select
+    // them by index. As PLINK does, individuals are filtered first, and SNPs are then
+    // filtered on the kept individuals.
+    fn select<TVal: BedVal>(
+        &self,
+        bed: &mut Bed,
+        read_options: &ReadOptions<TVal>,
+    ) -> Result<ReadOptions<TVal>, Box<BedErrorPlus>> {
+        let iid_count = bed.iid_count()?;
+        let sid_count = bed.sid_count()?;
+        let mut iid_index = bed.filter_iids(iid_count, read_options)?.as_ref().clone();
+        let mut sid_index = bed.filter_sids(sid_count, read_options)?.as_ref().clone();
+        let selection = |iid_index: &Vec<isize>, sid_index: &Vec<isize>| {
+            let mut builder = ReadOptions::<i8>::builder();
+            builder
+                .iid_index(iid_index.clone())
+                .sid_index(sid_index.clone());
+            builder.num_threads = Some(read_options.num_threads);
+            builder.build()
+        };
+
+        if let Some(sample_missing_max) = self.sample_missing_max {
+            let counts = sample_counts(bed, &selection(&iid_index, &sid_index)?)?;
+            // "as" is safe because the counts fit in f64's exact integers
+            #[allow(clippy::cast_precision_loss)]
+            let is_kept = |counts: &SampleCounts| {
+                counts.missing as f64 / sid_index.len() as f64 <= sample_missing_max
+            };
+            iid_index = iid_index
+                .iter()
+                .zip(&counts)
+                .filter(|(_, counts)| is_kept(counts))
+                .map(|(&iid_i, _)| iid_i)
+                .collect();
+        }
+        if self.missing_max.is_some() || self.maf_min.is_some() || self.hwe_p_min.is_some() {
+            let counts = genotype_counts(bed, &selection(&iid_index, &sid_index)?)?;
+            sid_index = sid_index
+                .iter()
+                .zip(counts)
+                .filter(|(_, counts)| self.is_sid_kept(*counts, iid_index.len()))
+                .map(|(&sid_i, _)| sid_i)
+                .collect();
+        }
+
+        let mut read_options = read_options.clone();
+        read_options.iid_index = Index::Vec(iid_index);
+        read_options.sid_index = Index::Vec(sid_index);
+        read_options.iid_where = None;
+        read_options.score_filters = Vec::new();
+        read_options.sid_filter = None;
+        read_options.regions = None;
+        Ok(read_options)
+    }
+}
+
+impl Bed {
+    /// Write the individuals (samples) and SNPs (variants) selected by `read_options` that pass
+    /// the QC thresholds of a [`QcFilter`](qc/struct.QcFilter.html) to a new .bed/.fam/.bim
+    /// fileset, like `plink --mind --geno --maf --hwe --make-bed`.
+    ///
+    /// As PLINK does, individuals missing too many of the selected SNPs are removed first, and
+    /// each SNP's missing rate, minor allele frequency, and Hardy-Weinberg p-value are then
+    /// computed over the individuals kept. Each threshold set takes one streaming pass through
+    /// the .bed file before the writing pass of [`Bed::extract`](struct.Bed.html#method.extract),
+    /// which otherwise works as for `extract`, carrying along the .fam and .bim rows that pass.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{qc::QcFilter, Bed, ReadOptions, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let input_file = output_folder.join("raw.bed");
+    /// WriteOptions::builder(&input_file)
+    ///     .sid(["good1", "monomorphic", "often_missing", "all_het", "good2"])
+    ///     .write(&nd::array![
+    ///         [0, 0, 0, 1, 1],
+    ///         [1, 0, 1, 1, 0],
+    ///         [2, 0, -127, 1, 2],
+    ///         [1, 0, -127, 1, 1],
+    ///         [0, 0, 2, 1, 1],
+    ///         [1, 0, 1, 1, 0],
+    ///         [2, 0, -127, 1, 1],
+    ///         [-127, 0, 1, -127, -127] // often missing
+    ///     ])?;
+    ///
+    /// let qc_filter = QcFilter::builder()
+    ///     .sample_missing_max(0.5)
+    ///     .missing_max(0.2)
+    ///     .maf_min(0.05)
+    ///     .hwe_p_min(0.05)
+    ///     .build()?;
+    /// let output_file = output_folder.join("clean.bed");
+    /// let read_options = ReadOptions::builder().i8().build()?;
+    /// Bed::new(&input_file)?.extract_qc(&qc_filter, &read_options, &WriteOptions::builder(&output_file))?;
+    ///
+    /// let clean = Bed::new(&output_file)?;
+    /// assert_eq!(clean.sid()?, nd::array!["good1", "good2"]);
+    /// assert_eq!(clean.iid_count()?, 7);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn extract_qc<TVal: BedVal + Into<f64>>(
+        &mut self,
+        qc_filter: &QcFilter,
+        read_options: &ReadOptions<TVal>,
+        write_options: &WriteOptionsBuilder<TVal>,
+    ) -> Result<WriteSummary, Box<BedErrorPlus>> {
+        let read_options = qc_filter.select(self, read_options)?;
+        self.extract(&read_options, write_options)
+    }
+}
+
+// One individual's numbers of missing and heterozygous values.
+#[derive(Clone, Copy, Default)]
+struct SampleCounts {
+    missing: usize,
+    het: usize,
+}
+
+// For each selected SNP, the number of selected individuals with 0, 1, and 2 copies of
+// allele 1.
+fn genotype_counts(
+    bed: &mut Bed,
+    read_options: &ReadOptions<i8>,
+) -> Result<Vec<[usize; 3]>, Box<BedErrorPlus>> {
+    let iid_count = bed.iid_count()?;
+    let iid_count = bed.filter_iids(iid_count, read_options)?.as_ref().len();
+    let chunk_size = (QC_CHUNK_VALUES / iid_count.max(1)).max(1);
+    let chunks = bed.iter_sid_chunks(chunk_size, read_options)?;
+
+    let mut genotype_counts = Vec::with_capacity(chunks.sid_count());
+    for val in chunks {
+        let val = val?;
+        let column_counts: Vec<[usize; 3]> = val
+            .axis_iter(nd::Axis(1))
+            .into_par_iter()
+            .map(|column| {
+                let mut counts = [0; 3];
+                for &value in column {
+                    if let Ok(value) = usize::try_from(value) {
+                        counts[value] += 1;
+                    }
+                }
+                counts
+            })
+            .collect();
+        genotype_counts.extend(column_counts);
+    }
+    Ok(genotype_counts)
+}
+
+// For each selected individual, its numbers of missing and heterozygous values, over the
+// selected SNPs.
+fn sample_counts(
+    bed: &mut Bed,
+    read_options: &ReadOptions<i8>,
+) -> Result<nd::Array1<SampleCounts>, Box<BedErrorPlus>> {
+    let iid_count = bed.iid_count()?;
+    let iid_count = bed.filter_iids(iid_count, read_options)?.as_ref().len();
+    let chunk_size = (QC_CHUNK_VALUES / iid_count.max(1)).max(1);
+
+    let mut counts = nd::Array1::<SampleCounts>::default(iid_count);
+    for val in bed.iter_sid_chunks(chunk_size, read_options)? {
+        let val = val?;
+        par_azip!((counts in &mut counts, row in val.axis_iter(nd::Axis(0))) {
+            for &value in row {
+                match value {
+                    1 => counts.het += 1,
+                    0 | 2 => {}
+                    _ => counts.missing += 1,
+                }
+            }
+        });
+    }
+    Ok(counts)
+}
+
+// The p-value of the exact test for HWE, given the numbers of heterozygotes and of each
+// homozygote, following Wigginton, Cutler, and Abecasis (2005).
+#[allow(clippy::cast_precision_loss)] // "as" is safe because the counts fit in f64's exact integers
+fn hwe_exact(het: usize, hom_1: usize, hom_2: usize) -> f64 {
+    let genotype_count = het + hom_1 + hom_2;
+    if genotype_count == 0 {
+        return f64::NAN;
+    }
+    let hom_rare = hom_1.min(hom_2);
+    let rare_count = 2 * hom_rare + het;
+
+    // The relative probability of each number of heterozygotes with the same parity as
+    // rare_count, starting from the most likely and working out in both directions
+    let mut het_probs = vec![0.0; rare_count + 1];
+    let mut mid = rare_count * (2 * genotype_count - rare_count) / (2 * genotype_count);
+    if mid % 2 != rare_count % 2 {
+        mid += 1;
+    }
+    het_probs[mid] = 1.0;
+    let mut sum = 1.0;
+
+    let mut cur_het = mid;
+    let mut cur_hom_rare = (rare_count - mid) / 2;
+    let mut cur_hom_common = genotype_count - cur_het - cur_hom_rare;
+    while cur_het >= 2 {
+        het_probs[cur_het - 2] = het_probs[cur_het] * (cur_het * (cur_het - 1)) as f64
+            / (4 * (cur_hom_rare + 1) * (cur_hom_common + 1)) as f64;
+        sum += het_probs[cur_het - 2];
+        cur_het -= 2;
+        cur_hom_rare += 1;
+        cur_hom_common += 1;
+    }
+
+    let mut cur_het = mid;
+    let mut cur_hom_rare = (rare_count - mid) / 2;
+    let mut cur_hom_common = genotype_count - cur_het - cur_hom_rare;
+    while cur_het + 2 <= rare_count {
+        het_probs[cur_het + 2] = het_probs[cur_het] * (4 * cur_hom_rare * cur_hom_common) as f64
+            / ((cur_het + 2) * (cur_het + 1)) as f64;
+        sum += het_probs[cur_het + 2];
+        cur_het += 2;
+        cur_hom_rare -= 1;
+        cur_hom_common -= 1;
+    }
+
+    // Allow for rounding, so that numbers of heterozygotes as likely as the one seen count
+    let het_prob = het_probs[het] * (1.0 + 1e-8);
+    let pvalue: f64 = het_probs.iter().filter(|&&prob| prob <= het_prob).sum();
+    (pvalue / sum).min(1.0)
+}
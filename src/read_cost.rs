@@ -0,0 +1,241 @@
+use std::mem::size_of;
+use std::time::Duration;
+
+use crate::{
+    check_and_precompute_iid_index, coalesce_sid_runs, open_and_check, try_div_4, Bed, BedError,
+    BedErrorPlus, BedVal, ReadOptions, ReadStrategy,
+};
+
+/// An estimate of what a read would cost, made without reading any genotypes.
+///
+/// Created by [`Bed::estimate_read_cost`](struct.Bed.html#method.estimate_read_cost).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadCost {
+    strategy: ReadStrategy,
+    bytes: u64,
+    seeks: usize,
+    dim: (usize, usize),
+    output_bytes: u64,
+}
+
+impl ReadCost {
+    /// The read strategy that the read would use, after any fallback, for example, to
+    /// [`ReadStrategy::Sequential`](enum.ReadStrategy.html#variant.Sequential) for an
+    /// individual-major file.
+    #[must_use]
+    pub fn strategy(&self) -> ReadStrategy {
+        self.strategy
+    }
+
+    /// The number of bytes to read from the .bed file.
+    #[must_use]
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// The number of separate reads, each needing a seek or positional read. A memory-mapped
+    /// read counts none.
+    #[must_use]
+    pub fn seeks(&self) -> usize {
+        self.seeks
+    }
+
+    /// The shape of the output array.
+    #[must_use]
+    pub fn dim(&self) -> (usize, usize) {
+        self.dim
+    }
+
+    /// The bytes of memory for the output array, saturating at `u64::MAX`.
+    #[must_use]
+    pub fn output_bytes(&self) -> u64 {
+        self.output_bytes
+    }
+
+    /// Predict how long the read would take, given a measured throughput, in bytes per second,
+    /// and a measured time per seek, in seconds.
+    ///
+    /// Decoding time isn't included. Returns a
+    /// [`BedError::BadThroughput`](enum.BedError.html#variant.BadThroughput) error if
+    /// `bytes_per_second` isn't positive and finite or `seconds_per_seek` isn't zero or more
+    /// and finite.
+    ///
+    /// > See [`Bed::estimate_read_cost`](struct.Bed.html#method.estimate_read_cost) for an example.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn predicted_time(
+        &self,
+        bytes_per_second: f64,
+        seconds_per_seek: f64,
+    ) -> Result<Duration, Box<BedErrorPlus>> {
+        if !(bytes_per_second.is_finite() && bytes_per_second > 0.0) {
+            Err(BedError::BadThroughput(bytes_per_second))?;
+        }
+        if !(seconds_per_seek.is_finite() && seconds_per_seek >= 0.0) {
+            Err(BedError::BadThroughput(seconds_per_seek))?;
+        }
+        let seconds = self.bytes as f64 / bytes_per_second + self.seeks as f64 * seconds_per_seek;
+        Ok(Duration::try_from_secs_f64(seconds).unwrap_or(Duration::MAX))
+    }
+}
+
+// Resolve an index, with negative positions counted from the end.
+fn resolve(
+    index: &[isize],
+    count: usize,
+    too_big: fn(isize) -> BedError,
+) -> Result<Vec<u64>, Box<BedErrorPlus>> {
+    index
+        .iter()
+        .map(|&signed_i| {
+            let resolved = if signed_i < 0 {
+                count.checked_sub(signed_i.unsigned_abs())
+            } else {
+                Some(signed_i.unsigned_abs()).filter(|&i| i < count)
+            };
+            // "as" is safe because a position in the file fits in u64
+            resolved
+                .map(|i| i as u64)
+                .ok_or_else(|| too_big(signed_i).into())
+        })
+        .collect()
+}
+
+// The bytes read and separate reads needed for the selected columns (SNPs, in the usual
+// SNP-major mode) and rows of a file, following each strategy's own planning.
+fn major_cost(
+    minor_count: usize,
+    major_count: usize,
+    minor_index: &[isize],
+    major_list: &[u64],
+    strategy: ReadStrategy,
+) -> Result<(u64, usize), Box<BedErrorPlus>> {
+    let column_len = try_div_4(minor_count, major_count)?;
+    let (_, _, _, read_len) = check_and_precompute_iid_index(minor_count, minor_index)?;
+    if minor_index.is_empty() || major_list.is_empty() {
+        return Ok((0, 0));
+    }
+
+    // "as" is safe because the number of selected SNPs fits in memory
+    let selected = major_list.len() as u64;
+    Ok(match strategy {
+        ReadStrategy::Sequential => {
+            let runs = coalesce_sid_runs(major_list, column_len, read_len);
+            let bytes = runs
+                .iter()
+                .map(|&(_, count)| (count as u64 - 1) * column_len + read_len)
+                .sum();
+            (bytes, runs.len())
+        }
+        ReadStrategy::BatchedPread => (selected * read_len, major_list.len()),
+        #[cfg(feature = "mmap")]
+        ReadStrategy::Mmap => (selected * read_len, 0),
+        ReadStrategy::Scan => {
+            // unwrap always works because the list isn't empty
+            let first = major_list.iter().min().unwrap();
+            let last = major_list.iter().max().unwrap();
+            ((last - first + 1) * column_len, 1)
+        }
+    })
+}
+
+impl Bed {
+    /// Estimate the cost of reading with `read_options`, without reading any genotypes, so
+    /// that a service can reject or queue an overly expensive query before running it.
+    ///
+    /// The individuals (samples) and SNPs (variants) are selected as the read would select
+    /// them, including by predicate, score, and region, and the estimate follows the planning
+    /// of the read's [`ReadStrategy`](enum.ReadStrategy.html): the bytes to read from the
+    /// .bed file, the number of seeks or positional reads, the output's shape and memory, and,
+    /// through [`ReadCost::predicted_time`](struct.ReadCost.html#method.predicted_time), a
+    /// time predicted from a measured throughput. Only the .bed file's header and, if needed,
+    /// the metadata are read.
+    ///
+    /// Returns the errors that the read would return for bad indexes.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, ReadOptions, ReadStrategy, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("cost.bed");
+    /// WriteOptions::builder(&output_file).write(&nd::Array2::<i8>::zeros((400, 1000)))?;
+    ///
+    /// let mut bed = Bed::new(&output_file)?;
+    /// // 100 bytes per SNP (variant), with adjacent SNPs read together
+    /// let read_options = ReadOptions::builder().sid_index(0..500).f64().build()?;
+    /// let cost = bed.estimate_read_cost(&read_options)?;
+    /// assert_eq!((cost.bytes(), cost.seeks()), (50_000, 1));
+    /// assert_eq!(cost.output_bytes(), 400 * 500 * 8);
+    ///
+    /// // Every 10th SNP, each read on its own
+    /// let read_options = ReadOptions::builder()
+    ///     .sid_index(nd::s![..;10])
+    ///     .read_strategy(ReadStrategy::BatchedPread)
+    ///     .i8()
+    ///     .build()?;
+    /// let cost = bed.estimate_read_cost(&read_options)?;
+    /// assert_eq!((cost.bytes(), cost.seeks()), (10_000, 100));
+    /// let time = cost.predicted_time(1_000_000.0, 0.001)?;
+    /// assert!((time.as_secs_f64() - 0.11).abs() < 1e-9); // 10 ms reading and 100 ms seeking
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn estimate_read_cost<TVal: BedVal>(
+        &mut self,
+        read_options: &ReadOptions<TVal>,
+    ) -> Result<ReadCost, Box<BedErrorPlus>> {
+        let iid_count = self.iid_count()?;
+        let sid_count = self.sid_count()?;
+        let iid_hold = self.filter_iids(iid_count, read_options)?;
+        let iid_index = iid_hold.as_ref();
+        let sid_hold = self.filter_sids(sid_count, read_options)?;
+        let sid_index = sid_hold.as_ref();
+        let layout_iid_count = self.layout_iid_count().unwrap_or(iid_count);
+        let layout_sid_count = self.layout_sid_count().unwrap_or(sid_count);
+
+        // Check the indexes here, so each error names its own axis in either mode.
+        let iid_list = resolve(iid_index, layout_iid_count, BedError::IidIndexTooBig)?;
+        let sid_list = resolve(sid_index, layout_sid_count, BedError::SidIndexTooBig)?;
+
+        let (_, bytes_array) = open_and_check(&self.path)?;
+        let (strategy, (bytes, seeks)) = if bytes_array[2] == 1 {
+            let strategy = match read_options.read_strategy {
+                #[cfg(feature = "mmap")]
+                ReadStrategy::Sequential if self.is_mmap => ReadStrategy::Mmap,
+                strategy => strategy,
+            };
+            let cost = major_cost(
+                layout_iid_count,
+                layout_sid_count,
+                iid_index,
+                &sid_list,
+                strategy,
+            )?;
+            (strategy, cost)
+        } else {
+            // Individual-major files are read sequentially, an individual at a time.
+            let strategy = ReadStrategy::Sequential;
+            let cost = major_cost(
+                layout_sid_count,
+                layout_iid_count,
+                sid_index,
+                &iid_list,
+                strategy,
+            )?;
+            (strategy, cost)
+        };
+
+        let dim = read_options.output_dim(iid_index.len(), sid_index.len());
+        let output_bytes = (dim.0 as u64)
+            .saturating_mul(dim.1 as u64)
+            .saturating_mul(size_of::<TVal>() as u64);
+        Ok(ReadCost {
+            strategy,
+            bytes,
+            seeks,
+            dim,
+            output_bytes,
+        })
+    }
+}
@@ -0,0 +1,251 @@
+use ndarray as nd;
+use std::any::{type_name, Any};
+
+use crate::{
+    BedError, BedErrorPlus, BedVal, Index, RangeAny, ReadOptions, ReadStrategy, Region, RegionSet,
+    SidFilter,
+};
+
+/// A serializable record of a [`ReadOptions`](struct.ReadOptions.html), made by
+/// [`ReadOptions::snapshot`](struct.ReadOptions.html#method.snapshot).
+///
+/// A server can log a snapshot as the exact query it ran, replay it later with
+/// [`ReadOptionsSnapshot::to_read_options`](struct.ReadOptionsSnapshot.html#method.to_read_options),
+/// and, with the `serde` feature, use its JSON as a cache key. The value type, such as `i8`,
+/// is recorded as a tag. Indexes are recorded in one form per kind, so equal selections
+/// written with, for example, a `Vec` or an `ndarray` give equal snapshots. A missing value of
+/// NaN is recorded as `None`, so that it survives JSON.
+///
+/// An [`iid_where`](struct.ReadOptionsBuilder.html#method.iid_where) predicate can't be
+/// serialized, so only its presence is recorded, and a
+/// [`progress`](struct.ReadOptionsBuilder.html#method.progress) callback, which doesn't change
+/// what is read, isn't recorded. Regions are recorded merged, as "chromosome:start-end" strings.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{Bed, ReadOptions, WriteOptions};
+///
+/// let output_folder = temp_testdir::TempDir::default();
+/// let output_file = output_folder.join("snapshot.bed");
+/// WriteOptions::builder(&output_file).write(&nd::array![[0, 1, 2, 0], [2, -127, 1, 1]])?;
+///
+/// let read_options = ReadOptions::builder().sid_index([2, 3, 0]).i8().build()?;
+/// let snapshot = read_options.snapshot()?;
+/// assert_eq!(snapshot.dtype(), "i8");
+///
+/// // Replay the query
+/// let replayed = snapshot.to_read_options::<i8>()?;
+/// let bed = Bed::new(&output_file)?;
+/// assert_eq!(bed.read_with_options(&replayed)?, bed.read_with_options(&read_options)?);
+/// assert!(snapshot.to_read_options::<f64>().is_err());
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(clippy::struct_excessive_bools)]
+pub struct ReadOptionsSnapshot {
+    dtype: String,
+    missing_value: Option<f64>,
+    iid_index: IndexSnapshot,
+    sid_index: IndexSnapshot,
+    is_f: bool,
+    is_a1_counted: bool,
+    count_a1_mask: Option<Vec<bool>>,
+    is_transposed: bool,
+    num_threads: Option<usize>,
+    max_concurrent_requests: Option<usize>,
+    max_chunk_bytes: Option<usize>,
+    score_filters: Vec<(String, f64)>,
+    sid_filter: Option<SidFilter>,
+    regions: Option<Vec<String>>,
+    has_iid_where: bool,
+    read_strategy: ReadStrategy,
+}
+
+/// A serializable record of an [`Index`](enum.Index.html), as kept in a
+/// [`ReadOptionsSnapshot`](struct.ReadOptionsSnapshot.html).
+///
+/// `Vec` and `ndarray` indexes are both recorded as `Vec` or `Bool`, and Rust ranges as `Range`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+// The only unsafe code is in ndarray's s! macro.
+#[cfg_attr(feature = "serde", allow(clippy::unsafe_derive_deserialize))]
+#[allow(missing_docs)]
+pub enum IndexSnapshot {
+    All,
+    One(isize),
+    Vec(Vec<isize>),
+    Bool(Vec<bool>),
+    Slice {
+        start: isize,
+        end: Option<isize>,
+        step: isize,
+    },
+    Range {
+        start: Option<usize>,
+        end: Option<usize>,
+    },
+}
+
+impl IndexSnapshot {
+    fn new(index: &Index) -> Result<IndexSnapshot, Box<BedErrorPlus>> {
+        Ok(match index {
+            Index::All => IndexSnapshot::All,
+            Index::One(one) => IndexSnapshot::One(*one),
+            Index::Vec(vec) => IndexSnapshot::Vec(vec.clone()),
+            Index::NDArray(array) => IndexSnapshot::Vec(array.to_vec()),
+            Index::VecBool(vec_bool) => IndexSnapshot::Bool(vec_bool.clone()),
+            Index::NDArrayBool(array_bool) => IndexSnapshot::Bool(array_bool.to_vec()),
+            Index::NDSliceInfo(slice_info) => match slice_info.as_ref()[0] {
+                nd::SliceInfoElem::Slice { start, end, step } => {
+                    IndexSnapshot::Slice { start, end, step }
+                }
+                nd::SliceInfoElem::Index(index) => IndexSnapshot::Slice {
+                    start: index,
+                    end: Some(index + 1),
+                    step: 1,
+                },
+                nd::SliceInfoElem::NewAxis => Err(BedError::NewAxis)?,
+            },
+            Index::RangeAny(range_any) => IndexSnapshot::Range {
+                start: range_any.start,
+                end: range_any.end,
+            },
+        })
+    }
+
+    fn to_index(&self) -> Index {
+        match self {
+            IndexSnapshot::All => Index::All,
+            IndexSnapshot::One(one) => Index::One(*one),
+            IndexSnapshot::Vec(vec) => Index::Vec(vec.clone()),
+            IndexSnapshot::Bool(vec_bool) => Index::VecBool(vec_bool.clone()),
+            IndexSnapshot::Slice { start, end, step } => {
+                Index::NDSliceInfo(nd::s![nd::Slice::new(*start, *end, *step)])
+            }
+            IndexSnapshot::Range { start, end } => Index::RangeAny(RangeAny {
+                start: *start,
+                end: *end,
+            }),
+        }
+    }
+}
+
+// The value type's own representation of a recorded missing value.
+fn missing_value_from_f64<TVal: BedVal + 'static>(value: Option<f64>) -> TVal {
+    let mut missing_value = TVal::missing();
+    let value = value.unwrap_or(f64::NAN);
+    let any = &mut missing_value as &mut dyn Any;
+    if let Some(missing_value) = any.downcast_mut::<f64>() {
+        *missing_value = value;
+    } else if let Some(missing_value) = any.downcast_mut::<f32>() {
+        // "as" is safe because the value was recorded from an f32
+        #[allow(clippy::cast_possible_truncation)]
+        let value = value as f32;
+        *missing_value = value;
+    } else if let Some(missing_value) = any.downcast_mut::<i8>() {
+        // "as" is safe because the value was recorded from an i8
+        #[allow(clippy::cast_possible_truncation)]
+        let value = value as i8;
+        *missing_value = value;
+    }
+    missing_value
+}
+
+impl<TVal: BedVal> ReadOptions<TVal> {
+    /// A serializable record of these options, for logging, replay, and cache keys.
+    ///
+    /// Returns an error if an index is an `ndarray` slice with a new axis.
+    ///
+    /// > See [`ReadOptionsSnapshot`](struct.ReadOptionsSnapshot.html) for details and an example.
+    pub fn snapshot(&self) -> Result<ReadOptionsSnapshot, Box<BedErrorPlus>>
+    where
+        TVal: Into<f64>,
+    {
+        let missing_value: f64 = self.missing_value.into();
+        Ok(ReadOptionsSnapshot {
+            dtype: type_name::<TVal>().to_string(),
+            missing_value: (!missing_value.is_nan()).then_some(missing_value),
+            iid_index: IndexSnapshot::new(&self.iid_index)?,
+            sid_index: IndexSnapshot::new(&self.sid_index)?,
+            is_f: self.is_f,
+            is_a1_counted: self.is_a1_counted,
+            count_a1_mask: self.count_a1_mask.as_ref().map(nd::Array1::to_vec),
+            is_transposed: self.is_transposed,
+            num_threads: self.num_threads,
+            max_concurrent_requests: self.max_concurrent_requests,
+            max_chunk_bytes: self.max_chunk_bytes,
+            score_filters: self.score_filters.clone(),
+            sid_filter: self.sid_filter.clone(),
+            regions: self.regions.as_ref().map(|regions| {
+                regions
+                    .merged_regions()
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect()
+            }),
+            has_iid_where: self.iid_where.is_some(),
+            read_strategy: self.read_strategy,
+        })
+    }
+}
+
+impl ReadOptionsSnapshot {
+    /// The recorded value type, such as "i8", "f32", or "f64".
+    #[must_use]
+    pub fn dtype(&self) -> &str {
+        &self.dtype
+    }
+
+    /// Rebuild the recorded [`ReadOptions`](struct.ReadOptions.html).
+    ///
+    /// Returns a [`BedError::SnapshotDtypeMismatch`](enum.BedError.html#variant.SnapshotDtypeMismatch)
+    /// error if `TVal` isn't the recorded value type, a
+    /// [`BedError::SnapshotHasIidWhere`](enum.BedError.html#variant.SnapshotHasIidWhere) error if
+    /// the options had an [`iid_where`](struct.ReadOptionsBuilder.html#method.iid_where)
+    /// predicate, and an error if a recorded region can't be parsed.
+    ///
+    /// > See [`ReadOptionsSnapshot`](struct.ReadOptionsSnapshot.html) for an example.
+    pub fn to_read_options<TVal: BedVal + 'static>(
+        &self,
+    ) -> Result<ReadOptions<TVal>, Box<BedErrorPlus>> {
+        if self.dtype != type_name::<TVal>() {
+            Err(BedError::SnapshotDtypeMismatch(
+                self.dtype.clone(),
+                type_name::<TVal>().to_string(),
+            ))?;
+        }
+        if self.has_iid_where {
+            Err(BedError::SnapshotHasIidWhere)?;
+        }
+        let regions = if let Some(regions) = &self.regions {
+            let regions = regions
+                .iter()
+                .map(|region| region.parse::<Region>())
+                .collect::<Result<Vec<_>, _>>()?;
+            Some(RegionSet::from_regions(regions))
+        } else {
+            None
+        };
+
+        let mut builder = ReadOptions::<TVal>::builder();
+        builder
+            .missing_value(missing_value_from_f64(self.missing_value))
+            .iid_index(self.iid_index.to_index())
+            .sid_index(self.sid_index.to_index())
+            .is_f(self.is_f)
+            .is_a1_counted(self.is_a1_counted)
+            .is_transposed(self.is_transposed)
+            .read_strategy(self.read_strategy);
+        builder.count_a1_mask = Some(self.count_a1_mask.clone().map(nd::Array1::from_vec));
+        builder.num_threads = Some(self.num_threads);
+        builder.max_concurrent_requests = Some(self.max_concurrent_requests);
+        builder.max_chunk_bytes = Some(self.max_chunk_bytes);
+        builder.score_filters = Some(self.score_filters.clone());
+        builder.sid_filter = Some(self.sid_filter.clone());
+        builder.regions = Some(regions);
+        builder.build()
+    }
+}
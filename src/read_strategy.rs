@@ -0,0 +1,353 @@
+use anyinput::anyinput;
+use ndarray as nd;
+#[cfg(not(all(feature = "io-uring", target_os = "linux")))]
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+use std::path::PathBuf;
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+use tokio_uring::buf::IoBuf;
+
+use crate::parallel::prelude::*;
+use crate::{
+    check_and_precompute_iid_index, create_pool, open_and_check, read_no_alloc, try_div_4,
+    BedError, BedErrorPlus, BedVal, TwoBitsToValue, CB_HEADER_U64,
+};
+
+/// How [`Bed`](struct.Bed.html) reads the selected SNPs (variants) from a local .bed file.
+///
+/// Set with [`ReadOptionsBuilder::read_strategy`](struct.ReadOptionsBuilder.html#method.read_strategy).
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ReadStrategy {
+    /// Seek to and read each SNP's bytes, one after another, through one buffered reader (default).
+    #[default]
+    Sequential,
+    /// Issue positional reads (pread) of many SNPs at once, in batches of
+    /// [`max_concurrent_requests`](struct.ReadOptionsBuilder.html#method.max_concurrent_requests).
+    ///
+    /// Only the bytes needed for the selected individuals are read, so on file systems with
+    /// transparent compression, such as btrfs or ZFS, only the extents holding those bytes
+    /// are decompressed. Keeping many reads in flight helps on high-latency storage, such as
+    /// network file systems. With the `io-uring` feature on Linux, the reads are submitted
+    /// together through `io_uring`. Otherwise, they are issued from the thread pool.
+    ///
+    /// Individual-major (mode 0) files are read with the sequential strategy.
+    BatchedPread,
+    /// Decode the selected SNPs straight from a memory mapping of the .bed file. The
+    /// mapping is made on the first such read and kept by the [`Bed`](struct.Bed.html).
+    ///
+    /// See [`BedBuilder::mmap`](struct.BedBuilder.html#method.mmap), which also makes reads with the
+    /// default strategy use the mapping. Individual-major (mode 0) files are read with the
+    /// sequential strategy.
+    #[cfg(feature = "mmap")]
+    Mmap,
+    /// Stream the .bed file once, from the first selected SNP to the last, in large blocks,
+    /// keeping only the bytes of the selected individuals.
+    ///
+    /// Reading a few individuals across all, or most, SNPs with the sequential strategy takes
+    /// one small read per SNP. This strategy instead makes one long sequential pass, which is
+    /// much faster on network storage and spinning disks. SNPs between the first and last
+    /// selected are read even if not selected, so prefer another strategy for a few scattered
+    /// SNPs. Individual-major (mode 0) files are read with the sequential strategy.
+    Scan,
+}
+
+// The most bytes of SNPs (variants) read at once by ReadStrategy::Scan.
+const SCAN_BLOCK_BYTES: u64 = 8 * 1024 * 1024;
+
+// Fill the buffer from `pos`, without moving any shared file cursor.
+#[cfg(all(unix, not(all(feature = "io-uring", target_os = "linux"))))]
+fn read_exact_at(file: &File, buf: &mut [u8], pos: u64) -> std::io::Result<()> {
+    std::os::unix::fs::FileExt::read_exact_at(file, buf, pos)
+}
+
+#[cfg(all(windows, not(all(feature = "io-uring", target_os = "linux"))))]
+fn read_exact_at(file: &File, mut buf: &mut [u8], mut pos: u64) -> std::io::Result<()> {
+    while !buf.is_empty() {
+        match std::os::windows::fs::FileExt::seek_read(file, buf, pos)? {
+            0 => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+            n => {
+                buf = &mut buf[n..];
+                pos += n as u64;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Reads (position, length) ranges of a file, batch after batch, from one open file.
+#[cfg(not(all(feature = "io-uring", target_os = "linux")))]
+struct RangeReader {
+    file: File,
+}
+
+#[cfg(not(all(feature = "io-uring", target_os = "linux")))]
+impl RangeReader {
+    fn new(path: &Path) -> Result<RangeReader, Box<BedErrorPlus>> {
+        Ok(RangeReader {
+            file: File::open(path)?,
+        })
+    }
+
+    // Read each range into its own buffer, from the thread pool.
+    fn read_ranges(&self, pos_list: &[u64], len: usize) -> Result<Vec<Vec<u8>>, Box<BedErrorPlus>> {
+        pos_list
+            .par_iter()
+            .map(|&pos| {
+                let mut buf = vec![0u8; len];
+                read_exact_at(&self.file, &mut buf, pos)?;
+                Ok(buf)
+            })
+            .collect()
+    }
+}
+
+// Read `len` bytes from `pos` through io_uring.
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+async fn read_one(file: &tokio_uring::fs::File, pos: u64, len: usize) -> std::io::Result<Vec<u8>> {
+    let mut buf = vec![0u8; len];
+    let mut filled = 0;
+    // A read can return fewer bytes than asked for, so continue until the buffer is full.
+    while filled < len {
+        let (result, slice) = file.read_at(buf.slice(filled..), pos + filled as u64).await;
+        buf = slice.into_inner();
+        match result? {
+            0 => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+            n => filled += n,
+        }
+    }
+    Ok(buf)
+}
+
+// Reads (position, length) ranges of a file, batch after batch, through one io_uring runtime.
+// The runtime is made once per read, because making one costs far more than a batch of reads.
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+struct RangeReader {
+    path: PathBuf,
+    runtime: tokio_uring::Runtime,
+}
+
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+impl RangeReader {
+    fn new(path: &Path) -> Result<RangeReader, Box<BedErrorPlus>> {
+        Ok(RangeReader {
+            path: path.to_path_buf(),
+            runtime: tokio_uring::Runtime::new(&tokio_uring::builder())?,
+        })
+    }
+
+    // Read each range into its own buffer, submitting all reads together.
+    fn read_ranges(&self, pos_list: &[u64], len: usize) -> Result<Vec<Vec<u8>>, Box<BedErrorPlus>> {
+        let buffers = self.runtime.block_on(async {
+            let file = tokio_uring::fs::File::open(&self.path).await?;
+            let buffers = futures_util::future::try_join_all(
+                pos_list.iter().map(|&pos| read_one(&file, pos, len)),
+            )
+            .await;
+            file.close().await?;
+            buffers
+        })?;
+        Ok(buffers)
+    }
+}
+
+// Like read_no_alloc, but reads the SNPs in concurrent batches of positional reads.
+#[allow(clippy::too_many_arguments)]
+#[anyinput]
+pub(crate) fn read_batched_pread<TVal: BedVal>(
+    path: AnyPath,
+    iid_count: usize,
+    sid_count: usize,
+    is_a1_counted: bool,
+    count_a1_mask: Option<&nd::Array1<bool>>,
+    iid_index: &[isize],
+    sid_index: &[isize],
+    missing_value: TVal,
+    num_threads: usize,
+    max_concurrent_requests: usize,
+    val: &mut nd::ArrayViewMut2<'_, TVal>,
+) -> Result<(), Box<BedErrorPlus>> {
+    let (buf_reader, bytes_array) = open_and_check(path)?;
+    if bytes_array[2] != 1 {
+        drop(buf_reader);
+        return read_no_alloc(
+            path,
+            iid_count,
+            sid_count,
+            is_a1_counted,
+            count_a1_mask,
+            iid_index,
+            sid_index,
+            missing_value,
+            num_threads,
+            val,
+        );
+    }
+
+    let iid_count_div4_u64 = try_div_4(iid_count, sid_count)?;
+    let file_len = buf_reader.get_ref().metadata()?.len();
+    drop(buf_reader);
+    if file_len != iid_count_div4_u64 * (sid_count as u64) + CB_HEADER_U64 {
+        Err(BedError::IllFormed(path.to_path_buf()))?;
+    }
+
+    create_pool(num_threads)?.install(|| {
+        let (i_div_4_less_start_array, i_mod_4_times_2_array, i_div_4_start, i_div_4_len) =
+            check_and_precompute_iid_index(iid_count, iid_index)?;
+        let two_bits_to_value = TwoBitsToValue::new(is_a1_counted, count_a1_mask, missing_value);
+
+        // "as" and math is safe because of the file-length check
+        let pos_list = sid_index
+            .iter()
+            .map(|&in_sid_i_signed| {
+                let in_sid_i = if (0..sid_count as isize).contains(&in_sid_i_signed) {
+                    in_sid_i_signed as u64
+                } else if (-(sid_count as isize)..0).contains(&in_sid_i_signed) {
+                    (sid_count as isize + in_sid_i_signed) as u64
+                } else {
+                    Err(BedError::SidIndexTooBig(in_sid_i_signed))?
+                };
+                Ok((
+                    in_sid_i * iid_count_div4_u64 + i_div_4_start + CB_HEADER_U64,
+                    two_bits_to_value.for_sid(in_sid_i as usize),
+                ))
+            })
+            .collect::<Result<Vec<(u64, [TVal; 4])>, Box<BedErrorPlus>>>()?;
+        let (pos_list, table_list): (Vec<u64>, Vec<[TVal; 4]>) = pos_list.into_iter().unzip();
+
+        let range_reader = RangeReader::new(path)?;
+        let batch_len = max_concurrent_requests.max(1);
+        for ((pos_batch, table_batch), mut val_batch) in pos_list
+            .chunks(batch_len)
+            .zip(table_list.chunks(batch_len))
+            .zip(val.axis_chunks_iter_mut(nd::Axis(1), batch_len))
+        {
+            let buffers = range_reader.read_ranges(pos_batch, i_div_4_len as usize)?;
+            val_batch
+                .axis_iter_mut(nd::Axis(1))
+                .into_par_iter()
+                .zip(buffers.par_iter())
+                .zip(table_batch.par_iter())
+                .for_each(|((mut col, bytes), from_two_bits_to_value)| {
+                    for out_iid_i in 0..iid_index.len() {
+                        let genotype_byte = (bytes[i_div_4_less_start_array[out_iid_i]]
+                            >> i_mod_4_times_2_array[out_iid_i])
+                            & 0x03;
+                        col[out_iid_i] = from_two_bits_to_value[genotype_byte as usize];
+                    }
+                });
+        }
+        Ok(())
+    })
+}
+
+// Like read_no_alloc, but reads the SNPs from the first selected to the last in one sequential pass.
+#[allow(clippy::too_many_arguments)]
+#[anyinput]
+pub(crate) fn read_scan<TVal: BedVal>(
+    path: AnyPath,
+    iid_count: usize,
+    sid_count: usize,
+    is_a1_counted: bool,
+    count_a1_mask: Option<&nd::Array1<bool>>,
+    iid_index: &[isize],
+    sid_index: &[isize],
+    missing_value: TVal,
+    num_threads: usize,
+    val: &mut nd::ArrayViewMut2<'_, TVal>,
+) -> Result<(), Box<BedErrorPlus>> {
+    let (mut buf_reader, bytes_array) = open_and_check(path)?;
+    if bytes_array[2] != 1 {
+        drop(buf_reader);
+        return read_no_alloc(
+            path,
+            iid_count,
+            sid_count,
+            is_a1_counted,
+            count_a1_mask,
+            iid_index,
+            sid_index,
+            missing_value,
+            num_threads,
+            val,
+        );
+    }
+
+    let iid_count_div4_u64 = try_div_4(iid_count, sid_count)?;
+    let file_len = buf_reader.get_ref().metadata()?.len();
+    if file_len != iid_count_div4_u64 * (sid_count as u64) + CB_HEADER_U64 {
+        Err(BedError::IllFormed(path.to_path_buf()))?;
+    }
+
+    create_pool(num_threads)?.install(|| {
+        let (i_div_4_less_start_array, i_mod_4_times_2_array, i_div_4_start, i_div_4_len) =
+            check_and_precompute_iid_index(iid_count, iid_index)?;
+        let two_bits_to_value = TwoBitsToValue::new(is_a1_counted, count_a1_mask, missing_value);
+
+        // Pair each SNP in the file with the output column wanting it, in file order.
+        // "as" and math is safe because of the file-length check
+        let mut in_out_list = sid_index
+            .iter()
+            .enumerate()
+            .map(|(out_sid_i, &in_sid_i_signed)| {
+                let in_sid_i = if (0..sid_count as isize).contains(&in_sid_i_signed) {
+                    in_sid_i_signed as u64
+                } else if (-(sid_count as isize)..0).contains(&in_sid_i_signed) {
+                    (sid_count as isize + in_sid_i_signed) as u64
+                } else {
+                    Err(BedError::SidIndexTooBig(in_sid_i_signed))?
+                };
+                Ok((in_sid_i, out_sid_i))
+            })
+            .collect::<Result<Vec<(u64, usize)>, Box<BedErrorPlus>>>()?;
+        in_out_list.sort_unstable();
+        let (Some(&(first_sid_i, _)), Some(&(last_sid_i, _))) =
+            (in_out_list.first(), in_out_list.last())
+        else {
+            return Ok(());
+        };
+
+        let block_sid_count = (SCAN_BLOCK_BYTES / iid_count_div4_u64.max(1)).max(1);
+        let mut block = Vec::new();
+        buf_reader.seek(SeekFrom::Start(
+            first_sid_i * iid_count_div4_u64 + CB_HEADER_U64,
+        ))?;
+        let mut block_start = first_sid_i;
+        let mut pair_start = 0;
+        while block_start <= last_sid_i {
+            let block_end = (block_start + block_sid_count).min(last_sid_i + 1);
+            block.resize(((block_end - block_start) * iid_count_div4_u64) as usize, 0);
+            buf_reader.read_exact(&mut block)?;
+
+            let pair_end = pair_start
+                + in_out_list[pair_start..].partition_point(|&(in_sid_i, _)| in_sid_i < block_end);
+            let columns = in_out_list[pair_start..pair_end]
+                .par_iter()
+                .map(|&(in_sid_i, _)| {
+                    let start =
+                        ((in_sid_i - block_start) * iid_count_div4_u64 + i_div_4_start) as usize;
+                    let bytes = &block[start..start + i_div_4_len as usize];
+                    let from_two_bits_to_value = two_bits_to_value.for_sid(in_sid_i as usize);
+                    (0..iid_index.len())
+                        .map(|out_iid_i| {
+                            let genotype_byte = (bytes[i_div_4_less_start_array[out_iid_i]]
+                                >> i_mod_4_times_2_array[out_iid_i])
+                                & 0x03;
+                            from_two_bits_to_value[genotype_byte as usize]
+                        })
+                        .collect::<Vec<TVal>>()
+                })
+                .collect::<Vec<_>>();
+            for (&(_, out_sid_i), column) in in_out_list[pair_start..pair_end].iter().zip(columns) {
+                val.column_mut(out_sid_i)
+                    .assign(&nd::ArrayView1::from(&column));
+            }
+
+            pair_start = pair_end;
+            block_start = block_end;
+        }
+        Ok(())
+    })
+}
@@ -0,0 +1,152 @@
+use anyinput::anyinput;
+use std::fs::{self, File};
+use std::io::{BufWriter, Read, Write};
+use std::sync::Arc;
+
+use crate::append::temp_path;
+use crate::{
+    bed_path_from_prefix, format, open_and_check, try_div_4, Bed, BedError, BedErrorPlus,
+    CB_HEADER_U64,
+};
+
+// For each byte, the byte with every homozygous code swapped (00 <-> 11).
+// Missing (01) and heterozygous (10) codes are unchanged.
+fn swap_table() -> [u8; 256] {
+    let mut table = [0u8; 256];
+    for (byte, swapped) in table.iter_mut().enumerate() {
+        for shift in (0..8).step_by(2) {
+            // "as" is safe because the index is less than 256
+            let code = (byte as u8 >> shift) & 0b11;
+            let new_code = match code {
+                format::HOM_ALLELE_1 => format::HOM_ALLELE_2,
+                format::HOM_ALLELE_2 => format::HOM_ALLELE_1,
+                _ => code,
+            };
+            *swapped |= new_code << shift;
+        }
+    }
+    table
+}
+
+/// Rewrite a .bed fileset with the allele orientation of selected SNPs (variants) swapped, for
+/// files written with allele 1 and allele 2 exchanged.
+///
+/// For each SNP with `true` in `flip_mask`, the packed genotype bytes are remapped through a
+/// table that exchanges the two homozygous codes, without decoding to values, and allele 1
+/// and allele 2 are exchanged in the .bim file. Other SNPs are copied unchanged, as is the .fam
+/// file. Filesets are given by their .bed path, with or without the `.bed` extension. The output
+/// may be the input: the new files are written to temporary files and then renamed.
+///
+/// Returns an error if the .bed file is not SNP-major or if `flip_mask` doesn't have one
+/// value per SNP.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{Bed, WriteOptions, reencode_allele_orientation};
+///
+/// let output_folder = temp_testdir::TempDir::default();
+/// let in_file = output_folder.join("swapped.bed");
+/// WriteOptions::builder(&in_file)
+///     .allele_1(["A", "C", "G"])
+///     .allele_2(["T", "G", "A"])
+///     .write(&nd::array![[0, 1, 2], [2, -127, 0]])?;
+///
+/// let out_file = output_folder.join("fixed");
+/// reencode_allele_orientation(&in_file, &out_file, [true, true, false])?;
+///
+/// let bed = Bed::new(out_file.with_extension("bed"))?;
+/// assert_eq!(bed.read::<i8>()?, nd::array![[2, 1, 2], [0, -127, 0]]);
+/// assert_eq!(bed.allele_1()?, nd::array!["T", "G", "G"]);
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+#[anyinput]
+pub fn reencode_allele_orientation(
+    in_prefix: AnyPath,
+    out_prefix: AnyPath,
+    flip_mask: AnyIter<bool>,
+) -> Result<(), Box<BedErrorPlus>> {
+    let flip_mask = flip_mask.collect::<Vec<bool>>();
+    let bed = Bed::new(bed_path_from_prefix(in_prefix))?;
+    let (iid_count, sid_count) = bed.dim()?;
+    if flip_mask.len() != sid_count {
+        Err(BedError::BoolArrayVectorWrongLength(
+            sid_count,
+            flip_mask.len(),
+        ))?;
+    }
+
+    let (buf_reader, bytes_array) = open_and_check(bed.path())?;
+    if bytes_array[2] != 1 {
+        Err(BedError::NotSnpMajor(bed.path().to_path_buf()))?;
+    }
+    let iid_count_div4_u64 = try_div_4(iid_count, sid_count)?;
+    if buf_reader.get_ref().metadata()?.len()
+        != iid_count_div4_u64 * (sid_count as u64) + CB_HEADER_U64
+    {
+        Err(BedError::IllFormed(bed.path().to_path_buf()))?;
+    }
+
+    // The last byte of each column may hold padding bits, which are kept as they are.
+    let last_byte_mask: u8 = match iid_count % 4 {
+        0 => 0xFF,
+        remainder => (1u8 << (2 * remainder)) - 1,
+    };
+    let table = swap_table();
+
+    let mut metadata = bed.metadata()?;
+    let (allele_1, allele_2) = (bed.allele_1()?.clone(), bed.allele_2()?.clone());
+    let mut new_allele_1 = allele_1.clone();
+    let mut new_allele_2 = allele_2.clone();
+    for (sid_i, &is_flipped) in flip_mask.iter().enumerate() {
+        if is_flipped {
+            new_allele_1[sid_i].clone_from(&allele_2[sid_i]);
+            new_allele_2[sid_i].clone_from(&allele_1[sid_i]);
+        }
+    }
+    metadata.allele_1 = Some(Arc::new(new_allele_1));
+    metadata.allele_2 = Some(Arc::new(new_allele_2));
+
+    let out_bed_path = bed_path_from_prefix(out_prefix);
+    let out_bim_path = out_bed_path.with_extension("bim");
+    let out_fam_path = out_bed_path.with_extension("fam");
+    let temp_bed_path = temp_path(&out_bed_path);
+    let temp_bim_path = temp_path(&out_bim_path);
+
+    let result = (|| -> Result<(), Box<BedErrorPlus>> {
+        let mut reader = buf_reader;
+        let mut writer = BufWriter::new(File::create(&temp_bed_path)?);
+        writer.write_all(&bytes_array)?;
+        // "as" is safe because of the file-length check
+        let mut column = vec![0u8; iid_count_div4_u64 as usize];
+        for &is_flipped in &flip_mask {
+            reader.read_exact(&mut column)?;
+            if is_flipped {
+                for byte in &mut column {
+                    *byte = table[*byte as usize];
+                }
+                if let Some(last) = column.last_mut() {
+                    let original = table[*last as usize];
+                    *last = (*last & last_byte_mask) | (original & !last_byte_mask);
+                }
+            }
+            writer.write_all(&column)?;
+        }
+        writer.flush()?;
+        metadata.write_bim(&temp_bim_path)?;
+
+        if bed.fam_path() != out_fam_path {
+            fs::copy(bed.fam_path(), &out_fam_path)?;
+        }
+        fs::rename(&temp_bed_path, &out_bed_path)?;
+        fs::rename(&temp_bim_path, &out_bim_path)?;
+        Ok(())
+    })();
+    if result.is_err() {
+        // Clean up the files
+        let _ = fs::remove_file(&temp_bed_path);
+        let _ = fs::remove_file(&temp_bim_path);
+    }
+    result
+}
@@ -0,0 +1,379 @@
+use anyinput::anyinput;
+use ndarray as nd;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::str::FromStr;
+
+use crate::{BedError, BedErrorPlus, BedVal, ChromosomeScheme, ReadOptionsBuilder};
+
+/// A set of genomic regions, for example, the genes of a panel, used to select SNPs (variants) by position.
+///
+/// Read regions from a genomic BED file (not a PLINK .bed file) with
+/// [`RegionSet::from_bed_file`](struct.RegionSet.html#method.from_bed_file) and
+/// select the SNPs that fall in them with
+/// [`ReadOptionsBuilder::regions`](struct.ReadOptionsBuilder.html#method.regions).
+///
+/// Regions are indexed by chromosome as sorted, merged intervals, so each SNP is
+/// looked up with a binary search. Chromosome names match whatever their scheme, so "chr1"
+/// matches "1" and "chrX" matches "23".
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegionSet {
+    // By normalized chromosome, disjoint, sorted 0-based half-open intervals.
+    intervals: BTreeMap<String, Vec<(u64, u64)>>,
+    region_count: usize,
+}
+
+impl RegionSet {
+    /// Read regions from a genomic BED file.
+    ///
+    /// Each line gives a chromosome, a 0-based start, an end (exclusive), and, optionally,
+    /// other fields, which are ignored. Fields are separated by tabs or spaces. Blank lines and
+    /// `#`, `track`, and `browser` header lines are skipped.
+    ///
+    /// Returns a [`BedError::BadRegion`](enum.BedError.html#variant.BadRegion) error for a line without
+    /// three fields, with a start or end that is not a non-negative whole number, or with an end before its start.
+    ///
+    /// # Example
+    /// ```
+    /// use bed_reader::RegionSet;
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let regions_file = output_folder.join("panel.bed");
+    /// std::fs::write(&regions_file, "track name=panel\nchr1\t99\t200\tGENE1\nchr5\t0\t10\n")?;
+    ///
+    /// let regions = RegionSet::from_bed_file(&regions_file)?;
+    /// assert_eq!(regions.region_count(), 2);
+    /// assert!(regions.contains("1", 100));
+    /// assert!(!regions.contains("1", 99));
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[anyinput]
+    pub fn from_bed_file(path: AnyPath) -> Result<RegionSet, Box<BedErrorPlus>> {
+        let mut intervals: BTreeMap<String, Vec<(u64, u64)>> = BTreeMap::new();
+        let mut region_count = 0;
+        for (line_i, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty()
+                || line.starts_with('#')
+                || line.starts_with("track")
+                || line.starts_with("browser")
+            {
+                continue;
+            }
+            let bad_region = || BedError::BadRegion(line_i + 1, path.to_path_buf());
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 3 {
+                Err(bad_region())?;
+            }
+            let start = fields[1].parse::<u64>().map_err(|_| bad_region())?;
+            let end = fields[2].parse::<u64>().map_err(|_| bad_region())?;
+            if end < start {
+                Err(bad_region())?;
+            }
+            intervals
+                .entry(ChromosomeScheme::EnsemblNumeric.normalize(fields[0]))
+                .or_default()
+                .push((start, end));
+            region_count += 1;
+        }
+
+        for chromosome_intervals in intervals.values_mut() {
+            merge_intervals(chromosome_intervals);
+        }
+
+        Ok(RegionSet {
+            intervals,
+            region_count,
+        })
+    }
+
+    /// Create a set from regions, such as those parsed from "2:100000-250000".
+    ///
+    /// # Example
+    /// ```
+    /// use bed_reader::{Region, RegionSet};
+    ///
+    /// let regions = RegionSet::from_regions([
+    ///     "2:100000-250000".parse::<Region>()?,
+    ///     Region::new("chrX", 5, 10)?,
+    /// ]);
+    /// assert_eq!(regions.region_count(), 2);
+    /// assert!(regions.contains("2", 250000));
+    /// assert!(!regions.contains("2", 250001));
+    /// assert!(regions.contains("23", 5));
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn from_regions<I: IntoIterator<Item = Region>>(regions: I) -> RegionSet {
+        let mut region_set = RegionSet::default();
+        for region in regions {
+            region_set.insert(&region);
+        }
+        region_set
+    }
+
+    /// Add a region to the set, merging it with any regions it overlaps.
+    ///
+    /// > See [`RegionSet::from_regions`](struct.RegionSet.html#method.from_regions) for an example.
+    pub fn insert(&mut self, region: &Region) {
+        let chromosome_intervals = self
+            .intervals
+            .entry(ChromosomeScheme::EnsemblNumeric.normalize(&region.chromosome))
+            .or_default();
+        // 1-based, inclusive to 0-based, half-open
+        chromosome_intervals.push((region.start - 1, region.end));
+        merge_intervals(chromosome_intervals);
+        self.region_count += 1;
+    }
+
+    /// The number of regions read, before overlapping regions are merged.
+    #[must_use]
+    pub fn region_count(&self) -> usize {
+        self.region_count
+    }
+
+    /// True if a SNP (variant) at the given chromosome and 1-based base-pair position is in a region.
+    ///
+    /// > See [`RegionSet::from_bed_file`](struct.RegionSet.html#method.from_bed_file) for an example.
+    #[must_use]
+    pub fn contains(&self, chromosome: &str, bp_position: i32) -> bool {
+        let Ok(position) = u64::try_from(bp_position) else {
+            return false;
+        };
+        if position == 0 {
+            return false;
+        }
+        let Some(intervals) = self
+            .intervals
+            .get(&ChromosomeScheme::EnsemblNumeric.normalize(chromosome))
+        else {
+            return false;
+        };
+        // The 0-based position must be in the last interval that starts at or before it.
+        let position = position - 1;
+        let after = intervals.partition_point(|&(start, _)| start <= position);
+        after > 0 && position < intervals[after - 1].1
+    }
+
+    // The set's merged regions, 1-based and inclusive, skipping any that are empty.
+    pub(crate) fn merged_regions(&self) -> Vec<Region> {
+        self.intervals
+            .iter()
+            .flat_map(|(chromosome, intervals)| {
+                intervals
+                    .iter()
+                    .filter(|&&(start, end)| start < end)
+                    .map(|&(start, end)| Region {
+                        chromosome: chromosome.clone(),
+                        start: start + 1,
+                        end,
+                    })
+            })
+            .collect()
+    }
+
+    // Of the selected SNPs, keep, in order, those in a region.
+    pub(crate) fn filter(
+        &self,
+        sid_index: &[isize],
+        sid_count: usize,
+        chromosome: &nd::Array1<String>,
+        bp_position: &nd::Array1<i32>,
+    ) -> Result<Vec<isize>, Box<BedErrorPlus>> {
+        let lower_sid_count = -(sid_count as isize);
+        let upper_sid_count: isize = (sid_count as isize) - 1;
+        let mut kept = Vec::new();
+        for &sid_i_signed in sid_index {
+            let sid_i = if (0..=upper_sid_count).contains(&sid_i_signed) {
+                sid_i_signed as usize
+            } else if (lower_sid_count..=-1).contains(&sid_i_signed) {
+                sid_count - ((-sid_i_signed) as usize)
+            } else {
+                Err(BedError::SidIndexTooBig(sid_i_signed))?
+            };
+            if self.contains(&chromosome[sid_i], bp_position[sid_i]) {
+                kept.push(sid_i_signed);
+            }
+        }
+        Ok(kept)
+    }
+}
+
+// Sort intervals and merge those that overlap or touch.
+fn merge_intervals(intervals: &mut Vec<(u64, u64)>) {
+    intervals.sort_unstable();
+    let mut merged: Vec<(u64, u64)> = Vec::with_capacity(intervals.len());
+    for &(start, end) in intervals.iter() {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    *intervals = merged;
+}
+
+/// A genomic region: a chromosome and a range of 1-based base-pair positions, both ends included.
+///
+/// Parse a region written as "chromosome:start-end", for example, "2:100000-250000" or
+/// "chr2:100,000-250,000", or create one with [`Region::new`](struct.Region.html#method.new).
+/// Select the SNPs (variants) in regions with
+/// [`ReadOptionsBuilder::region`](struct.ReadOptionsBuilder.html#method.region) or, via a
+/// [`RegionSet`](struct.RegionSet.html), with
+/// [`ReadOptionsBuilder::regions`](struct.ReadOptionsBuilder.html#method.regions).
+///
+/// # Example
+/// ```
+/// use bed_reader::Region;
+///
+/// let region: Region = "chr2:100,000-250,000".parse()?;
+/// assert_eq!(region, Region::new("chr2", 100_000, 250_000)?);
+/// assert_eq!(region.chromosome(), "chr2");
+/// assert_eq!((region.start(), region.end()), (100_000, 250_000));
+/// assert_eq!(region.to_string(), "chr2:100000-250000");
+/// assert!("2:250000-100000".parse::<Region>().is_err());
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Region {
+    chromosome: String,
+    start: u64,
+    end: u64,
+}
+
+impl Region {
+    /// Create a region from a chromosome and its first and last 1-based base-pair positions.
+    ///
+    /// Returns a [`BedError::BadRegionString`](enum.BedError.html#variant.BadRegionString) error
+    /// if the chromosome is empty, the start is 0, or the end is before the start.
+    ///
+    /// > See [`Region`](struct.Region.html) for an example.
+    pub fn new(chromosome: &str, start: u64, end: u64) -> Result<Region, Box<BedErrorPlus>> {
+        if chromosome.is_empty() || start == 0 || end < start {
+            Err(BedError::BadRegionString(format!(
+                "{chromosome}:{start}-{end}"
+            )))?;
+        }
+        Ok(Region {
+            chromosome: chromosome.to_string(),
+            start,
+            end,
+        })
+    }
+
+    /// The region's chromosome, as given.
+    #[must_use]
+    pub fn chromosome(&self) -> &str {
+        &self.chromosome
+    }
+
+    /// The region's first 1-based base-pair position.
+    #[must_use]
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// The region's last 1-based base-pair position, included in the region.
+    #[must_use]
+    pub fn end(&self) -> u64 {
+        self.end
+    }
+}
+
+impl FromStr for Region {
+    type Err = Box<BedErrorPlus>;
+
+    fn from_str(region: &str) -> Result<Self, Self::Err> {
+        let bad_region = || BedError::BadRegionString(region.to_string());
+        let (chromosome, range) = region.trim().rsplit_once(':').ok_or_else(bad_region)?;
+        let (start, end) = range.split_once('-').ok_or_else(bad_region)?;
+        let parse = |position: &str| position.replace(',', "").parse::<u64>();
+        let start = parse(start).map_err(|_| bad_region())?;
+        let end = parse(end).map_err(|_| bad_region())?;
+        Region::new(chromosome, start, end).map_err(|_| bad_region().into())
+    }
+}
+
+impl std::fmt::Display for Region {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}-{}", self.chromosome, self.start, self.end)
+    }
+}
+
+impl<TVal: BedVal> ReadOptionsBuilder<TVal> {
+    /// Of the SNPs (variants) selected by [`sid_index`](struct.ReadOptionsBuilder.html#method.sid_index),
+    /// keep only those whose chromosome and base-pair position fall in one of the regions.
+    ///
+    /// SNPs keep their selected order. This extracts, for example, the SNPs of a gene panel in one call.
+    ///
+    /// # Example:
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, ReadOptions, RegionSet, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("positions.bed");
+    /// WriteOptions::builder(&output_file)
+    ///     .chromosome(["1", "1", "1", "2"])
+    ///     .bp_position([100, 250, 300, 100])
+    ///     .write(&nd::array![[0, 1, 2, 0], [2, 1, 0, -127]])?;
+    ///
+    /// let regions_file = output_folder.join("panel.bed");
+    /// std::fs::write(&regions_file, "chr1\t90\t260\nchr2\t99\t100\n")?;
+    /// let regions = RegionSet::from_bed_file(&regions_file)?;
+    ///
+    /// let mut bed = Bed::new(&output_file)?;
+    /// let val = ReadOptions::builder().regions(&regions).i8().read(&mut bed)?;
+    /// assert_eq!(val, nd::array![[0, 1, 0], [2, 1, -127]]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn regions(&mut self, regions: &RegionSet) -> &mut Self {
+        self.regions = Some(Some(regions.clone()));
+        self
+    }
+
+    /// Of the SNPs (variants) selected by [`sid_index`](struct.ReadOptionsBuilder.html#method.sid_index),
+    /// keep only those in a region written as "chromosome:start-end", for example, "2:100000-250000".
+    ///
+    /// Positions are 1-based and both ends are included. Call this more than once to keep the
+    /// SNPs in any of several regions. The regions are added to any set by
+    /// [`regions`](struct.ReadOptionsBuilder.html#method.regions). Returns a
+    /// [`BedError::BadRegionString`](enum.BedError.html#variant.BadRegionString) error for an
+    /// ill-formed region.
+    ///
+    /// > Also see [`Region`](struct.Region.html), for regions built from their parts.
+    ///
+    /// # Example:
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, ReadOptions, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("positions.bed");
+    /// WriteOptions::builder(&output_file)
+    ///     .chromosome(["1", "1", "2", "2"])
+    ///     .bp_position([100, 250, 100_000, 250_001])
+    ///     .write(&nd::array![[0, 1, 2, 0], [2, 1, 0, -127]])?;
+    ///
+    /// let mut bed = Bed::new(&output_file)?;
+    /// let val = ReadOptions::builder()
+    ///     .region("chr1:1-100")?
+    ///     .region("2:100,000-250,000")?
+    ///     .i8()
+    ///     .read(&mut bed)?;
+    /// assert_eq!(val, nd::array![[0, 2], [2, 0]]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn region(&mut self, region: &str) -> Result<&mut Self, Box<BedErrorPlus>> {
+        let region = region.parse::<Region>()?;
+        let mut regions = self.regions.clone().flatten().unwrap_or_default();
+        regions.insert(&region);
+        self.regions = Some(Some(regions));
+        Ok(self)
+    }
+}
@@ -0,0 +1,169 @@
+use anyinput::anyinput;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::{BedErrorPlus, Metadata};
+#[cfg(feature = "xlsx")]
+use {crate::BedError, rust_xlsxwriter::Workbook};
+
+/// File formats supported by [`Metadata::to_report`](struct.Metadata.html#method.to_report).
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum ReportFormat {
+    /// Tab-separated values with columns `section`, `key`, and `count`.
+    Tsv,
+    /// An Excel workbook with one worksheet per section. Requires the `xlsx` feature.
+    #[cfg(feature = "xlsx")]
+    Xlsx,
+}
+
+/// One section of a metadata report, for example, SNP counts per chromosome.
+struct ReportSection {
+    name: &'static str,
+    rows: Vec<(String, usize)>,
+}
+
+// Count values, keeping the order in which each value is first seen.
+fn count_in_order<'a>(values: impl Iterator<Item = &'a str>) -> Vec<(String, usize)> {
+    let mut position: HashMap<&str, usize> = HashMap::new();
+    let mut rows: Vec<(String, usize)> = Vec::new();
+    for value in values {
+        if let Some(&i) = position.get(value) {
+            rows[i].1 += 1;
+        } else {
+            position.insert(value, rows.len());
+            rows.push((value.to_string(), 1));
+        }
+    }
+    rows
+}
+
+impl Metadata {
+    fn report_sections(&self) -> Vec<ReportSection> {
+        let mut summary = Vec::new();
+        let (iid_count, sid_count) = self.check_counts(None, None).unwrap_or((None, None));
+        if let Some(iid_count) = iid_count {
+            summary.push(("iid_count".to_string(), iid_count));
+        }
+        if let Some(sid_count) = sid_count {
+            summary.push(("sid_count".to_string(), sid_count));
+        }
+
+        let mut sections = vec![ReportSection {
+            name: "summary",
+            rows: summary,
+        }];
+
+        if let Some(chromosome) = self.chromosome() {
+            sections.push(ReportSection {
+                name: "chromosome",
+                rows: count_in_order(chromosome.iter().map(String::as_str)),
+            });
+        }
+        if let (Some(allele_1), Some(allele_2)) = (self.allele_1(), self.allele_2()) {
+            let pairs: Vec<String> = allele_1
+                .iter()
+                .zip(allele_2.iter())
+                .map(|(a1, a2)| format!("{a1}/{a2}"))
+                .collect();
+            sections.push(ReportSection {
+                name: "allele",
+                rows: count_in_order(pairs.iter().map(String::as_str)),
+            });
+        }
+        if let Some(sex) = self.sex() {
+            let sex: Vec<&str> = sex
+                .iter()
+                .map(|code| match code {
+                    1 => "male",
+                    2 => "female",
+                    _ => "unknown",
+                })
+                .collect();
+            sections.push(ReportSection {
+                name: "sex",
+                rows: count_in_order(sex.into_iter()),
+            });
+        }
+        sections
+    }
+
+    /// Write a summary of this metadata to a report file, for example, for data delivery documentation.
+    ///
+    /// The report is generated only from the fields already present in the
+    /// [`Metadata`](struct.Metadata.html); no .fam or .bim file is read. Fields that are
+    /// `None` are left out. The sections are:
+    ///  * `summary` -- the number of individuals (samples) and SNPs (variants)
+    ///  * `chromosome` -- the number of SNPs on each chromosome
+    ///  * `allele` -- the number of SNPs with each "allele 1/allele 2" pair
+    ///  * `sex` -- the number of individuals that are male, female, or unknown
+    ///
+    /// With [`ReportFormat::Tsv`](enum.ReportFormat.html#variant.Tsv), each line holds a section, a key,
+    /// and a count. With `ReportFormat::Xlsx` (requires the `xlsx` feature), each section is a worksheet.
+    ///
+    /// # Example
+    /// ```
+    /// use bed_reader::{Bed, ReportFormat, sample_bed_file};
+    ///
+    /// let bed = Bed::new(sample_bed_file("small.bed")?)?;
+    /// let metadata = bed.metadata()?;
+    ///
+    /// let temp_out = temp_testdir::TempDir::default();
+    /// let report_file = temp_out.join("small_report.tsv");
+    /// metadata.to_report(&report_file, ReportFormat::Tsv)?;
+    ///
+    /// let report = std::fs::read_to_string(&report_file)?;
+    /// assert!(report.contains("chromosome\t1\t2"));
+    /// assert!(report.contains("sex\tfemale\t1"));
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[anyinput]
+    pub fn to_report(&self, path: AnyPath, format: ReportFormat) -> Result<(), Box<BedErrorPlus>> {
+        let sections = self.report_sections();
+        match format {
+            ReportFormat::Tsv => write_tsv_report(path, &sections),
+            #[cfg(feature = "xlsx")]
+            ReportFormat::Xlsx => write_xlsx_report(path, &sections),
+        }
+    }
+}
+
+#[anyinput]
+fn write_tsv_report(path: AnyPath, sections: &[ReportSection]) -> Result<(), Box<BedErrorPlus>> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "section\tkey\tcount")?;
+    for section in sections {
+        for (key, count) in &section.rows {
+            writeln!(writer, "{}\t{key}\t{count}", section.name)?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(feature = "xlsx")]
+#[allow(clippy::cast_precision_loss)]
+#[anyinput]
+fn write_xlsx_report(path: AnyPath, sections: &[ReportSection]) -> Result<(), Box<BedErrorPlus>> {
+    let to_bed_error = |e: rust_xlsxwriter::XlsxError| BedError::ReportWrite(e.to_string());
+
+    let mut workbook = Workbook::new();
+    for section in sections {
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name(section.name).map_err(to_bed_error)?;
+        worksheet.write_string(0, 0, "key").map_err(to_bed_error)?;
+        worksheet
+            .write_string(0, 1, "count")
+            .map_err(to_bed_error)?;
+        for (row, (key, count)) in section.rows.iter().enumerate() {
+            let row = (row + 1) as u32;
+            worksheet.write_string(row, 0, key).map_err(to_bed_error)?;
+            worksheet
+                .write_number(row, 1, *count as f64)
+                .map_err(to_bed_error)?;
+        }
+    }
+    workbook.save(path).map_err(to_bed_error)?;
+    Ok(())
+}
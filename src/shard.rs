@@ -0,0 +1,217 @@
+use ndarray as nd;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::{
+    Bed, BedError, BedErrorPlus, Metadata, BED_FILE_MAGIC1, BED_FILE_MAGIC2, CB_HEADER_U64,
+};
+
+// Split sid_count SNPs into shard_count contiguous ranges whose sizes differ by at most one.
+fn shard_ranges(sid_count: usize, shard_count: usize) -> Vec<Range<usize>> {
+    let (base, extra) = (sid_count / shard_count, sid_count % shard_count);
+    let mut start = 0;
+    (0..shard_count)
+        .map(|shard_i| {
+            let end = start + base + usize::from(shard_i < extra);
+            let range = start..end;
+            start = end;
+            range
+        })
+        .collect()
+}
+
+fn slice_field<T: Clone>(
+    field: Option<&Arc<nd::Array1<T>>>,
+    range: &Range<usize>,
+) -> Option<Arc<nd::Array1<T>>> {
+    field.map(|array| Arc::new(array.slice(nd::s![range.clone()]).to_owned()))
+}
+
+// Quote a string for JSON.
+fn json_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => {
+                // unwrap always works because writing to a String can't fail
+                write!(quoted, "\\u{:04x}", c as u32).unwrap();
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn json_path(path: &Path) -> String {
+    json_string(&path.to_string_lossy())
+}
+
+impl Bed {
+    /// Split the dataset into `shard_count` .bed/.fam/.bim filesets of contiguous SNPs (variants),
+    /// for example, to give each job of a cluster its own part.
+    ///
+    /// `path_template` gives each shard's .bed path, with `{}` replaced by the shard's 0-based
+    /// index. The .fam and .bim files go next to it. Shard sizes differ by at most one SNP and every
+    /// shard has all the individuals (samples). A manifest, describing the split, is written to the
+    /// template with `{}` replaced by `manifest` and the extension `.json`. It gives, for each shard,
+    /// its files, its range of SNP indexes (`sid_start` inclusive, `sid_end` exclusive),
+    /// and its first and last sid.
+    ///
+    /// Genotypes aren't decoded. Each shard's SNP columns are copied as bytes from the .bed file,
+    /// which must be in the usual SNP-major mode. Returns the shards' .bed paths. On error,
+    /// the files already written are removed.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, ReadOptions, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("all.bed");
+    /// let val = nd::array![[0, 1, 2, 0, 1], [2, 1, 0, -127, 0]];
+    /// WriteOptions::builder(&output_file).write(&val)?;
+    ///
+    /// let mut bed = Bed::new(&output_file)?;
+    /// let template = output_folder.join("part_{}.bed");
+    /// let shard_paths = bed.shard_by_sid(2, template.to_str().unwrap())?;
+    /// assert_eq!(shard_paths[1], output_folder.join("part_1.bed"));
+    ///
+    /// let shard = Bed::new(&shard_paths[1])?;
+    /// assert_eq!(shard.sid()?, nd::array!["sid4", "sid5"]);
+    /// assert_eq!(shard.read::<i8>()?, nd::array![[0, 1], [-127, 0]]);
+    /// assert!(output_folder.join("part_manifest.json").exists());
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn shard_by_sid(
+        &mut self,
+        shard_count: usize,
+        path_template: &str,
+    ) -> Result<Vec<PathBuf>, Box<BedErrorPlus>> {
+        if !path_template.contains("{}") {
+            Err(BedError::BadShardTemplate(path_template.to_string()))?;
+        }
+        let sid_count = self.sid_count()?;
+        if shard_count == 0 || shard_count > sid_count {
+            Err(BedError::BadShardCount(shard_count, sid_count))?;
+        }
+
+        let bed_paths: Vec<PathBuf> = (0..shard_count)
+            .map(|shard_i| PathBuf::from(path_template.replace("{}", &shard_i.to_string())))
+            .collect();
+        let manifest_path =
+            PathBuf::from(path_template.replace("{}", "manifest")).with_extension("json");
+
+        let result = self.write_shards(&bed_paths, &manifest_path);
+        if result.is_err() {
+            // Clean up the files
+            for bed_path in &bed_paths {
+                let _ = fs::remove_file(bed_path);
+                let _ = fs::remove_file(bed_path.with_extension("fam"));
+                let _ = fs::remove_file(bed_path.with_extension("bim"));
+            }
+            let _ = fs::remove_file(&manifest_path);
+        }
+        result.map(|()| bed_paths)
+    }
+
+    fn write_shards(
+        &mut self,
+        bed_paths: &[PathBuf],
+        manifest_path: &Path,
+    ) -> Result<(), Box<BedErrorPlus>> {
+        let iid_count = self.iid_count()?;
+        let sid_count = self.sid_count()?;
+        // Copied columns must hold exactly the individuals and SNPs of the metadata.
+        if let Some(layout_iid_count) = self.layout_iid_count().filter(|&n| n != iid_count) {
+            Err(BedError::InconsistentCount(
+                "iid".to_string(),
+                iid_count,
+                layout_iid_count,
+            ))?;
+        }
+        if let Some(layout_sid_count) = self.layout_sid_count().filter(|&n| n != sid_count) {
+            Err(BedError::InconsistentCount(
+                "sid".to_string(),
+                sid_count,
+                layout_sid_count,
+            ))?;
+        }
+        let metadata = self.metadata()?.fill(iid_count, sid_count)?;
+        let (mut buf_reader, bytes_per_column, _) = self.open_packed_columns()?;
+        let ranges = shard_ranges(sid_count, bed_paths.len());
+
+        for (shard_i, (bed_path, range)) in bed_paths.iter().zip(&ranges).enumerate() {
+            let mut writer = BufWriter::new(File::create(bed_path)?);
+            writer.write_all(&[BED_FILE_MAGIC1, BED_FILE_MAGIC2, 0x01])?;
+            buf_reader.seek(SeekFrom::Start(
+                CB_HEADER_U64 + (range.start * bytes_per_column) as u64,
+            ))?;
+            let byte_count = (range.len() * bytes_per_column) as u64;
+            if io::copy(&mut buf_reader.by_ref().take(byte_count), &mut writer)? != byte_count {
+                Err(BedError::IllFormed(self.path.clone()))?;
+            }
+            writer.flush()?;
+
+            let fam_path = bed_path.with_extension("fam");
+            if shard_i == 0 {
+                metadata.write_fam(&fam_path)?;
+            } else {
+                fs::copy(bed_paths[0].with_extension("fam"), &fam_path)?;
+            }
+
+            let mut bim = Metadata::new();
+            bim.chromosome = slice_field(metadata.chromosome.as_ref(), range);
+            bim.sid = slice_field(metadata.sid.as_ref(), range);
+            bim.cm_position = slice_field(metadata.cm_position.as_ref(), range);
+            bim.bp_position = slice_field(metadata.bp_position.as_ref(), range);
+            bim.allele_1 = slice_field(metadata.allele_1.as_ref(), range);
+            bim.allele_2 = slice_field(metadata.allele_2.as_ref(), range);
+            bim.write_bim(bed_path.with_extension("bim"))?;
+        }
+
+        // unwrap always works because fill made every field
+        let sid = metadata.sid.as_ref().unwrap();
+        let mut writer = BufWriter::new(File::create(manifest_path)?);
+        writeln!(writer, "{{")?;
+        writeln!(writer, "  \"source\": {},", json_path(&self.path))?;
+        writeln!(writer, "  \"iid_count\": {iid_count},")?;
+        writeln!(writer, "  \"sid_count\": {sid_count},")?;
+        writeln!(writer, "  \"shard_count\": {},", bed_paths.len())?;
+        writeln!(writer, "  \"shards\": [")?;
+        for (shard_i, (bed_path, range)) in bed_paths.iter().zip(&ranges).enumerate() {
+            let separator = if shard_i + 1 < bed_paths.len() {
+                ","
+            } else {
+                ""
+            };
+            writeln!(
+                writer,
+                "    {{\"index\": {shard_i}, \"bed\": {}, \"fam\": {}, \"bim\": {}, \
+                 \"sid_start\": {}, \"sid_end\": {}, \"first_sid\": {}, \"last_sid\": {}}}{separator}",
+                json_path(bed_path),
+                json_path(&bed_path.with_extension("fam")),
+                json_path(&bed_path.with_extension("bim")),
+                range.start,
+                range.end,
+                json_string(&sid[range.start]),
+                json_string(&sid[range.end - 1]),
+            )?;
+        }
+        writeln!(writer, "  ]")?;
+        writeln!(writer, "}}")?;
+        writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,291 @@
+use anyinput::anyinput;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::cache::{file_stamp, FileStamp};
+use crate::{bed_path_from_prefix, format, Bed, BedError, BedErrorPlus};
+
+// A .bedshare file starts with these bytes and then the format version.
+const SHARED_MAGIC: [u8; 8] = *b"BEDSHARE";
+const SHARED_VERSION: u32 = 1;
+
+/// What is needed to read a .bed file, saved once in a `.bedshare` sidecar file so that many
+/// processes can open the file without reading its .fam and .bim files.
+///
+/// It holds the counts, the mode, and the runs of SNPs (variants) on each chromosome, in .bim
+/// order, from which each process can find, for example, the SNPs of the chromosome it reads.
+/// It also records the sizes and modification times of the .bed, .fam, and .bim files, so a
+/// sidecar that no longer matches its files is never used.
+///
+/// Save with [`Bed::write_shared_index`](struct.Bed.html#method.write_shared_index). Load with
+/// [`SharedIndex::load`](struct.SharedIndex.html#method.load) or open a
+/// [`Bed`](struct.Bed.html) from it with [`Bed::open_shared`](struct.Bed.html#method.open_shared).
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{Bed, ReadOptions, SharedIndex, WriteOptions};
+///
+/// let output_folder = temp_testdir::TempDir::default();
+/// let prefix = output_folder.join("farm");
+/// let val = nd::array![[1, 0, -127, 0], [2, 0, -127, 2], [0, 1, 2, 0]];
+/// WriteOptions::builder(prefix.with_extension("bed"))
+///     .chromosome(["1", "1", "2", "2"])
+///     .write(&val)?;
+/// Bed::new(prefix.with_extension("bed"))?.write_shared_index()?;
+///
+/// // In each process, for example, one per chromosome
+/// let index = SharedIndex::load(&prefix)?;
+/// let sid_range = index.sid_ranges("2")[0].clone();
+/// assert_eq!(sid_range, 2..4);
+/// let mut bed = Bed::open_shared(&prefix)?;
+/// let chromosome_2 = ReadOptions::builder().sid_index(sid_range).i8().read(&mut bed)?;
+/// assert_eq!(chromosome_2, nd::array![[-127, 0], [-127, 2], [2, 0]]);
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SharedIndex {
+    mode: format::Mode,
+    iid_count: usize,
+    sid_count: usize,
+    chromosome_runs: Vec<(String, Range<usize>)>,
+}
+
+// The magic bytes, the version, and the stamps of the .bed, .fam, and .bim files.
+fn shared_header(stamps: [FileStamp; 3]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&SHARED_MAGIC);
+    bytes.extend_from_slice(&SHARED_VERSION.to_le_bytes());
+    for (len, secs, nanos) in stamps {
+        bytes.extend_from_slice(&len.to_le_bytes());
+        bytes.extend_from_slice(&secs.to_le_bytes());
+        bytes.extend_from_slice(&nanos.to_le_bytes());
+    }
+    bytes
+}
+
+// The stamps of a fileset's .bed, .fam, and .bim files.
+fn fileset_stamps(bed_path: &Path) -> Result<[FileStamp; 3], Box<BedErrorPlus>> {
+    Ok([
+        file_stamp(bed_path)?,
+        file_stamp(&bed_path.with_extension("fam"))?,
+        file_stamp(&bed_path.with_extension("bim"))?,
+    ])
+}
+
+// Reads little-endian values from the front of a byte slice.
+struct Cursor<'a>(&'a [u8]);
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+        let (front, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(front)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn usize(&mut self) -> Option<usize> {
+        // "as" is safe because the crate requires a 64-bit target
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?) as usize)
+    }
+}
+
+impl SharedIndex {
+    fn to_bytes(&self, stamps: [FileStamp; 3]) -> Vec<u8> {
+        let mut bytes = shared_header(stamps);
+        bytes.push(self.mode.to_byte());
+        // "as" is safe because the crate requires a 64-bit target
+        bytes.extend_from_slice(&(self.iid_count as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.sid_count as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.chromosome_runs.len() as u64).to_le_bytes());
+        for (chromosome, sid_range) in &self.chromosome_runs {
+            bytes.extend_from_slice(&(sid_range.start as u64).to_le_bytes());
+            bytes.extend_from_slice(&(sid_range.end as u64).to_le_bytes());
+            bytes.extend_from_slice(&(chromosome.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(chromosome.as_bytes());
+        }
+        bytes
+    }
+
+    // Returns None if the bytes aren't a shared index, of this version, for files with these stamps.
+    fn from_bytes(bytes: &[u8], stamps: [FileStamp; 3]) -> Option<SharedIndex> {
+        let header = shared_header(stamps);
+        let mut cursor = Cursor(bytes);
+        if cursor.take(header.len())? != header {
+            return None;
+        }
+        let mode = format::Mode::from_byte(cursor.take(1)?[0])?;
+        let iid_count = cursor.usize()?;
+        let sid_count = cursor.usize()?;
+        let run_count = cursor.usize()?;
+        let mut chromosome_runs = Vec::new();
+        for _ in 0..run_count {
+            let sid_range = cursor.usize()?..cursor.usize()?;
+            let name_len = cursor.u32()? as usize;
+            let chromosome = std::str::from_utf8(cursor.take(name_len)?).ok()?;
+            chromosome_runs.push((chromosome.to_string(), sid_range));
+        }
+        if !cursor.0.is_empty() {
+            return None;
+        }
+        Some(SharedIndex {
+            mode,
+            iid_count,
+            sid_count,
+            chromosome_runs,
+        })
+    }
+
+    /// Load the shared index of the fileset with this prefix, for example, `data/farm` for
+    /// `data/farm.bed`, from its `.bedshare` sidecar file.
+    ///
+    /// Only the sidecar file and the sizes and modification times of the .bed, .fam, and .bim
+    /// files are read. Returns a [`BedError::StaleSharedIndex`](enum.BedError.html#variant.StaleSharedIndex)
+    /// error if the sidecar file is missing, unreadable, or doesn't match the files.
+    ///
+    /// > See [`SharedIndex`](struct.SharedIndex.html) for an example.
+    #[anyinput]
+    pub fn load(prefix: AnyPath) -> Result<SharedIndex, Box<BedErrorPlus>> {
+        let bed_path = bed_path_from_prefix(prefix);
+        let stamps = fileset_stamps(&bed_path)?;
+        let shared_path = bed_path.with_extension("bedshare");
+        fs::read(&shared_path)
+            .ok()
+            .and_then(|bytes| SharedIndex::from_bytes(&bytes, stamps))
+            .ok_or_else(|| BedError::StaleSharedIndex(shared_path).into())
+    }
+
+    /// The order of the genotype data in the .bed file.
+    #[must_use]
+    pub fn mode(&self) -> format::Mode {
+        self.mode
+    }
+
+    /// Number of individuals (samples).
+    #[must_use]
+    pub fn iid_count(&self) -> usize {
+        self.iid_count
+    }
+
+    /// Number of SNPs (variants).
+    #[must_use]
+    pub fn sid_count(&self) -> usize {
+        self.sid_count
+    }
+
+    /// The number of bytes in each column of the .bed file.
+    #[must_use]
+    pub fn column_len(&self) -> u64 {
+        match self.mode {
+            format::Mode::SnpMajor => format::bytes_per_column(self.iid_count),
+            format::Mode::IndividualMajor => format::bytes_per_column(self.sid_count),
+        }
+    }
+
+    /// The byte offset in the .bed file of a column: a SNP (variant) in
+    /// [`format::Mode::SnpMajor`](format/enum.Mode.html#variant.SnpMajor), otherwise an
+    /// individual (sample).
+    #[must_use]
+    pub fn column_offset(&self, column_index: usize) -> u64 {
+        // "as" is safe because the crate requires a 64-bit target
+        format::HEADER_LEN + column_index as u64 * self.column_len()
+    }
+
+    /// Each run of consecutive SNPs (variants) on one chromosome, in .bim order.
+    #[must_use]
+    pub fn chromosome_runs(&self) -> &[(String, Range<usize>)] {
+        &self.chromosome_runs
+    }
+
+    /// The ranges of SNP (variant) indexes on a chromosome, one per run, in .bim order.
+    ///
+    /// A sorted .bim file has at most one range per chromosome. The result is empty if the
+    /// chromosome has no SNPs.
+    ///
+    /// > See [`SharedIndex`](struct.SharedIndex.html) for an example.
+    #[must_use]
+    pub fn sid_ranges(&self, chromosome: &str) -> Vec<Range<usize>> {
+        self.chromosome_runs
+            .iter()
+            .filter(|(run_chromosome, _)| run_chromosome == chromosome)
+            .map(|(_, sid_range)| sid_range.clone())
+            .collect()
+    }
+}
+
+impl Bed {
+    /// The path of the `.bedshare` sidecar file, next to the .bed file.
+    #[must_use]
+    pub fn shared_index_path(&self) -> PathBuf {
+        self.path.with_extension("bedshare")
+    }
+
+    /// Find and save the [`SharedIndex`](struct.SharedIndex.html) of this .bed file in its
+    /// `.bedshare` sidecar file, for processes that open it with
+    /// [`Bed::open_shared`](struct.Bed.html#method.open_shared).
+    ///
+    /// The .fam and .bim files must be next to the .bed file, with the same name. The sidecar
+    /// file is written to a temporary file and then renamed, so other processes see either
+    /// the old or the new sidecar file, never a partial one.
+    ///
+    /// > See [`SharedIndex`](struct.SharedIndex.html) for an example.
+    pub fn write_shared_index(&self) -> Result<SharedIndex, Box<BedErrorPlus>> {
+        let stamps = fileset_stamps(&self.path)?;
+        let mode = format::read_mode(&self.path)?;
+        let (iid_count, sid_count) = self.dim()?;
+        let mut chromosome_runs: Vec<(String, Range<usize>)> = Vec::new();
+        for (sid_i, chromosome) in self.chromosome()?.iter().enumerate() {
+            match chromosome_runs.last_mut() {
+                Some((run_chromosome, sid_range)) if run_chromosome == chromosome => {
+                    sid_range.end = sid_i + 1;
+                }
+                _ => chromosome_runs.push((chromosome.clone(), sid_i..sid_i + 1)),
+            }
+        }
+        let shared_index = SharedIndex {
+            mode,
+            iid_count,
+            sid_count,
+            chromosome_runs,
+        };
+
+        let shared_path = self.shared_index_path();
+        let temp_path = self
+            .path
+            .with_extension(format!("bedshare.{}.tmp", std::process::id()));
+        fs::write(&temp_path, shared_index.to_bytes(stamps))?;
+        if let Err(error) = fs::rename(&temp_path, &shared_path) {
+            let _ = fs::remove_file(&temp_path);
+            Err(error)?;
+        }
+        Ok(shared_index)
+    }
+
+    /// Open the fileset with this prefix, for example, `data/farm` for `data/farm.bed`, using
+    /// only its [`SharedIndex`](struct.SharedIndex.html) sidecar file for counts.
+    ///
+    /// Meant for read farms, for example, one process per chromosome: every process opens the
+    /// same, immutable counts, without reading the .fam and .bim files, and gets a
+    /// [`BedError::StaleSharedIndex`](enum.BedError.html#variant.StaleSharedIndex) error,
+    /// rather than inconsistent results, if the files have changed since the sidecar was saved.
+    /// Metadata, such as [`Bed::iid`](struct.Bed.html#method.iid), is still read from the
+    /// .fam and .bim files if asked for.
+    ///
+    /// > See [`SharedIndex`](struct.SharedIndex.html) for an example.
+    #[anyinput]
+    pub fn open_shared(prefix: AnyPath) -> Result<Bed, Box<BedErrorPlus>> {
+        let shared_index = SharedIndex::load(prefix)?;
+        Bed::builder(bed_path_from_prefix(prefix))
+            .iid_count(shared_index.iid_count)
+            .sid_count(shared_index.sid_count)
+            .build()
+    }
+}
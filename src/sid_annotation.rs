@@ -0,0 +1,350 @@
+use anyinput::anyinput;
+use ndarray as nd;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::{Bed, BedError, BedErrorPlus, Metadata};
+
+// The extension, replacing ".bed", of the sidecar file that holds SNP annotations.
+pub(crate) const SID_ANNOTATIONS_EXTENSION: &str = "sid_annotations.tsv";
+
+/// A named, per-SNP (variant) annotation, for example, gene symbols or QC flags.
+///
+/// Attach annotations with [`MetadataBuilder::sid_annotation_string`](struct.MetadataBuilder.html#method.sid_annotation_string)
+/// and [`MetadataBuilder::sid_annotation_float`](struct.MetadataBuilder.html#method.sid_annotation_float).
+/// When written, they go to a `.sid_annotations.tsv` sidecar file next to the .bed file,
+/// and are reloaded by [`Bed::sid_annotation`](struct.Bed.html#method.sid_annotation)
+/// and [`Bed::metadata`](struct.Bed.html#method.metadata).
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{Bed, Metadata, SidAnnotation, WriteOptions};
+///
+/// let output_folder = temp_testdir::TempDir::default();
+/// let output_file = output_folder.join("annotated.bed");
+/// let metadata = Metadata::builder()
+///     .sid_annotation_string("gene", ["BRCA1", "", "TP53", "APOE"])
+///     .sid_annotation_float("qc_score", [0.9, 0.4, f64::NAN, 1.0])
+///     .build()?;
+/// let val = nd::array![[1, 0, -127, 0], [2, 0, -127, 2], [0, 1, 2, 0]];
+/// WriteOptions::builder(&output_file)
+///     .metadata(&metadata)
+///     .write(&val)?;
+///
+/// let bed = Bed::new(&output_file)?;
+/// let gene = bed.sid_annotation("gene")?.unwrap();
+/// assert_eq!(gene.as_string().unwrap(), nd::array!["BRCA1", "", "TP53", "APOE"]);
+/// let qc_score = bed.sid_annotation("qc_score")?.and_then(SidAnnotation::as_float);
+/// assert_eq!(qc_score.unwrap()[1], 0.4);
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub enum SidAnnotation {
+    /// Text values. Written with tabs, newlines, and backslashes escaped.
+    String(nd::Array1<String>),
+    /// Numeric values. NaN is missing.
+    Float(nd::Array1<f64>),
+}
+
+impl SidAnnotation {
+    /// The number of SNPs (variants) annotated.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        match self {
+            SidAnnotation::String(values) => values.len(),
+            SidAnnotation::Float(values) => values.len(),
+        }
+    }
+
+    /// True if no SNPs (variants) are annotated.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The values, if this is a text annotation.
+    #[must_use]
+    pub fn as_string(&self) -> Option<&nd::Array1<String>> {
+        match self {
+            SidAnnotation::String(values) => Some(values),
+            SidAnnotation::Float(_) => None,
+        }
+    }
+
+    /// The values, if this is a numeric annotation.
+    #[must_use]
+    pub fn as_float(&self) -> Option<&nd::Array1<f64>> {
+        match self {
+            SidAnnotation::String(_) => None,
+            SidAnnotation::Float(values) => Some(values),
+        }
+    }
+
+    pub(crate) fn select(&self, sid_index: &[usize]) -> SidAnnotation {
+        match self {
+            SidAnnotation::String(values) => {
+                SidAnnotation::String(values.select(nd::Axis(0), sid_index))
+            }
+            SidAnnotation::Float(values) => {
+                SidAnnotation::Float(values.select(nd::Axis(0), sid_index))
+            }
+        }
+    }
+
+    // Truncate, or pad with empty strings or NaN, to sid_count.
+    pub(crate) fn resize(&self, sid_count: usize) -> SidAnnotation {
+        match self {
+            SidAnnotation::String(values) => SidAnnotation::String(
+                (0..sid_count)
+                    .map(|i| values.get(i).cloned().unwrap_or_default())
+                    .collect(),
+            ),
+            SidAnnotation::Float(values) => SidAnnotation::Float(
+                (0..sid_count)
+                    .map(|i| values.get(i).copied().unwrap_or(f64::NAN))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            SidAnnotation::String(_) => "string",
+            SidAnnotation::Float(_) => "float",
+        }
+    }
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('t') => unescaped.push('\t'),
+                Some('n') => unescaped.push('\n'),
+                Some('r') => unescaped.push('\r'),
+                Some(other) => unescaped.push(other),
+                None => unescaped.push('\\'),
+            }
+        } else {
+            unescaped.push(c);
+        }
+    }
+    unescaped
+}
+
+impl Metadata {
+    /// Write the SNP annotations to a tab-separated sidecar file.
+    ///
+    /// The header gives each annotation's name and type, for example, `gene:string`.
+    /// Each following line holds the annotations of one SNP, in .bim file order.
+    /// [`WriteOptions`](struct.WriteOptions.html) calls this automatically when the
+    /// metadata has annotations.
+    ///
+    /// > See [`SidAnnotation`](enum.SidAnnotation.html) for an example.
+    #[anyinput]
+    pub fn write_sid_annotations(&self, path: AnyPath) -> Result<(), Box<BedErrorPlus>> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_sid_annotations_to(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    // Write the sidecar's header and lines to writer.
+    pub(crate) fn write_sid_annotations_to<W: Write>(
+        &self,
+        writer: &mut W,
+    ) -> Result<(), Box<BedErrorPlus>> {
+        let header = self
+            .sid_annotations
+            .iter()
+            .map(|(name, annotation)| format!("{}:{}", escape(name), annotation.type_name()))
+            .collect::<Vec<_>>();
+        writeln!(writer, "{}", header.join("\t"))?;
+
+        let sid_count = self.sid_annotations.values().next().map_or(0, |a| a.len());
+        for sid_i in 0..sid_count {
+            for (annotation_i, annotation) in self.sid_annotations.values().enumerate() {
+                if annotation_i > 0 {
+                    write!(writer, "\t")?;
+                }
+                match annotation.as_ref() {
+                    SidAnnotation::String(values) => write!(writer, "{}", escape(&values[sid_i]))?,
+                    SidAnnotation::Float(values) => write!(writer, "{}", values[sid_i])?,
+                }
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    /// Create a new [`Metadata`](struct.Metadata.html) by adding the SNP annotations in a sidecar file
+    /// written by [`Metadata::write_sid_annotations`](struct.Metadata.html#method.write_sid_annotations).
+    ///
+    /// Annotations already in this metadata are kept. Also returns the number of SNPs annotated.
+    #[anyinput]
+    pub fn read_sid_annotations(
+        &self,
+        path: AnyPath,
+    ) -> Result<(Metadata, usize), Box<BedErrorPlus>> {
+        let mut lines = BufReader::new(File::open(path)?).lines();
+        let header = lines.next().transpose()?.unwrap_or_default();
+
+        let mut columns = Vec::new();
+        if !header.is_empty() {
+            for field in header.split('\t') {
+                let (name, type_name) = field.rsplit_once(':').ok_or_else(|| {
+                    BedError::BadSidAnnotationHeader(field.to_string(), path.to_path_buf())
+                })?;
+                let is_float = match type_name {
+                    "string" => false,
+                    "float" => true,
+                    _ => Err(BedError::BadSidAnnotationHeader(
+                        field.to_string(),
+                        path.to_path_buf(),
+                    ))?,
+                };
+                columns.push((unescape(name), is_float, Vec::<String>::new()));
+            }
+        }
+
+        let mut count = 0;
+        for line in lines {
+            let line = line?;
+            let fields = line.split('\t').collect::<Vec<_>>();
+            if fields.len() != columns.len() {
+                Err(BedError::MetadataFieldCount(
+                    columns.len(),
+                    fields.len(),
+                    path.to_path_buf(),
+                ))?;
+            }
+            for (column, field) in columns.iter_mut().zip(fields) {
+                column.2.push(field.to_string());
+            }
+            count += 1;
+        }
+
+        let mut metadata = self.clone();
+        for (name, is_float, values) in columns {
+            if metadata.sid_annotations.contains_key(&name) {
+                continue;
+            }
+            let annotation = if is_float {
+                SidAnnotation::Float(
+                    values
+                        .iter()
+                        .map(|value| match value.as_str() {
+                            "NA" => Ok(f64::NAN),
+                            _ => value.parse::<f64>(),
+                        })
+                        .collect::<Result<_, _>>()?,
+                )
+            } else {
+                SidAnnotation::String(values.iter().map(|value| unescape(value)).collect())
+            };
+            metadata.sid_annotations.insert(name, Arc::new(annotation));
+        }
+        metadata.check_counts(None, Some(count))?;
+        Ok((metadata, count))
+    }
+
+    /// The names of the SNP annotations, in sorted order.
+    pub fn sid_annotation_names(&self) -> impl Iterator<Item = &str> {
+        self.sid_annotations.keys().map(String::as_str)
+    }
+
+    /// Optional SNP annotation with the given name
+    ///
+    /// > See [`SidAnnotation`](enum.SidAnnotation.html) for an example.
+    #[must_use]
+    pub fn sid_annotation(&self, name: &str) -> Option<&SidAnnotation> {
+        self.sid_annotations.get(name).map(AsRef::as_ref)
+    }
+
+    // Check that every SNP annotation has sid_count values.
+    pub(crate) fn check_sid_annotations(&self, sid_count: usize) -> Result<(), Box<BedErrorPlus>> {
+        for (name, annotation) in &self.sid_annotations {
+            if annotation.len() != sid_count {
+                Err(BedError::InconsistentCount(
+                    name.clone(),
+                    annotation.len(),
+                    sid_count,
+                ))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn select_sid_annotations(
+        &self,
+        sid_index: &[usize],
+    ) -> BTreeMap<String, Arc<SidAnnotation>> {
+        self.sid_annotations
+            .iter()
+            .map(|(name, annotation)| (name.clone(), Arc::new(annotation.select(sid_index))))
+            .collect()
+    }
+}
+
+impl Bed {
+    /// SNP annotation with the given name, or `None` if there is no such annotation.
+    ///
+    /// Annotations are those given with
+    /// [`BedBuilder::metadata`](struct.BedBuilder.html#method.metadata) plus, if it exists,
+    /// those in the `.sid_annotations.tsv` sidecar file next to the .bed file. The sidecar file
+    /// is read the first time annotations are needed and its number of lines is checked
+    /// against the number of SNPs.
+    ///
+    /// > See [`SidAnnotation`](enum.SidAnnotation.html) for an example.
+    pub fn sid_annotation(&self, name: &str) -> Result<Option<&SidAnnotation>, Box<BedErrorPlus>> {
+        Ok(self.sid_annotated()?.sid_annotation(name))
+    }
+
+    pub(crate) fn sid_annotations_path(&self) -> PathBuf {
+        self.path.with_extension(SID_ANNOTATIONS_EXTENSION)
+    }
+
+    // The metadata with the annotations of the sidecar file, if it exists, read on first use.
+    pub(crate) fn sid_annotated(&self) -> Result<&Metadata, Box<BedErrorPlus>> {
+        if let Some(metadata) = self.sid_annotated.get() {
+            return Ok(metadata);
+        }
+        let path = self.sid_annotations_path();
+        let metadata = if path.exists() {
+            let (metadata, count) = self.metadata.read_sid_annotations(&path)?;
+            let sid_count = self.sid_count()?;
+            if count != sid_count {
+                Err(BedError::InconsistentCount(
+                    "sid".to_string(),
+                    sid_count,
+                    count,
+                ))?;
+            }
+            metadata
+        } else {
+            self.metadata.clone()
+        };
+        Ok(self.sid_annotated.get_or_init(|| metadata))
+    }
+}
@@ -0,0 +1,113 @@
+use ndarray as nd;
+
+use crate::{Bed, BedError, BedErrorPlus, BedVal, Index, ReadOptions};
+
+/// An iterator of genotype data, a chunk of SNPs (variants) at a time.
+///
+/// Created by [`Bed::iter_sid_chunks`](struct.Bed.html#method.iter_sid_chunks).
+/// Iteration stops after the first error.
+pub struct SidChunks<'a, TVal: BedVal> {
+    bed: &'a Bed,
+    // The read options, with the individuals and SNPs already selected.
+    read_options: ReadOptions<TVal>,
+    sid_index: Vec<isize>,
+    chunk_size: usize,
+    next_sid: usize,
+}
+
+impl<TVal: BedVal> SidChunks<'_, TVal> {
+    /// The number of SNPs (variants) selected, across all chunks.
+    #[must_use]
+    pub fn sid_count(&self) -> usize {
+        self.sid_index.len()
+    }
+}
+
+impl<TVal: BedVal> Iterator for SidChunks<'_, TVal> {
+    type Item = Result<nd::Array2<TVal>, Box<BedErrorPlus>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next_sid >= self.sid_index.len() {
+            return None;
+        }
+        let start = self.next_sid;
+        let end = (start + self.chunk_size).min(self.sid_index.len());
+        let mut read_options = self.read_options.clone();
+        read_options.sid_index = Index::Vec(self.sid_index[start..end].to_vec());
+        let result = self.bed.read_with_options(&read_options);
+        self.next_sid = if result.is_ok() {
+            end
+        } else {
+            self.sid_index.len()
+        };
+        Some(result)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let chunk_count = (self.sid_index.len() - self.next_sid).div_ceil(self.chunk_size);
+        (chunk_count, Some(chunk_count))
+    }
+}
+
+impl<TVal: BedVal> ExactSizeIterator for SidChunks<'_, TVal> {}
+
+impl Bed {
+    /// Read genotype data lazily, `chunk_size` SNPs (variants) at a time.
+    ///
+    /// Each item is the same as reading, with `read_options`, just that chunk's SNPs, so
+    /// the individual (sample) selection, missing value, allele-1 counting, order, and other
+    /// options are respected. SNPs are selected, by index, score, or region, once, when
+    /// the iterator is made, and then split into chunks in order. Only one chunk is in memory
+    /// at a time, which allows genome-wide scans of files too big to read at once.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, ReadOptions, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("scan.bed");
+    /// WriteOptions::builder(&output_file)
+    ///     .write(&nd::array![[1, 0, -127, 0, 2], [2, 0, -127, 2, 1], [0, 1, 2, 0, 1]])?;
+    ///
+    /// let bed = Bed::new(&output_file)?;
+    /// let read_options = ReadOptions::builder().iid_index([0, 2]).f64().build()?;
+    /// let mut total = 0.0;
+    /// for chunk in bed.iter_sid_chunks(2, &read_options)? {
+    ///     let chunk = chunk?;
+    ///     assert_eq!(chunk.nrows(), 2); // 2 individuals and up to 2 SNPs
+    ///     total += chunk.iter().filter(|v| !v.is_nan()).sum::<f64>();
+    /// }
+    /// assert_eq!(total, 7.0);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn iter_sid_chunks<TVal: BedVal>(
+        &self,
+        chunk_size: usize,
+        read_options: &ReadOptions<TVal>,
+    ) -> Result<SidChunks<'_, TVal>, Box<BedErrorPlus>> {
+        if chunk_size == 0 {
+            Err(BedError::ZeroChunkSids)?;
+        }
+        let iid_count = self.iid_count()?;
+        let sid_count = self.sid_count()?;
+        let iid_index = self.filter_iids(iid_count, read_options)?.as_ref().clone();
+        let sid_index = self.filter_sids(sid_count, read_options)?.as_ref().clone();
+
+        let mut read_options = read_options.clone();
+        read_options.iid_index = Index::Vec(iid_index);
+        read_options.iid_where = None;
+        read_options.score_filters = Vec::new();
+        read_options.sid_filter = None;
+        read_options.regions = None;
+
+        Ok(SidChunks {
+            bed: self,
+            read_options,
+            sid_index,
+            chunk_size,
+            next_sid: 0,
+        })
+    }
+}
@@ -0,0 +1,228 @@
+use ndarray as nd;
+use std::sync::Arc;
+
+use crate::parallel::prelude::*;
+use crate::{Bed, BedError, BedErrorPlus, BedVal, Index, ReadOptions, ReadOptionsBuilder};
+
+// The most genotype values read at once when computing the statistics SNP filters consult.
+const SID_FILTER_CHUNK_VALUES: usize = 8_000_000;
+
+/// A filter on per-SNP (per-variant) statistics -- minor allele frequency, call rate, and
+/// allele dosage variance -- for selecting SNPs with
+/// [`ReadOptionsBuilder::sid_filter`](struct.ReadOptionsBuilder.html#method.sid_filter).
+///
+/// Build a filter from conditions, such as
+/// [`SidFilter::maf_at_least`](struct.SidFilter.html#method.maf_at_least), joined with
+/// [`and`](struct.SidFilter.html#method.and). The statistics are computed over all
+/// individuals (samples) in the file, in one pass, the first time a
+/// [`Bed`](struct.Bed.html) needs them, and are then kept with it. A SNP with a NaN
+/// statistic, for example, one with no calls, never passes a condition on it.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{Bed, ReadOptions, SidFilter, WriteOptions};
+///
+/// let output_folder = temp_testdir::TempDir::default();
+/// let output_file = output_folder.join("filter.bed");
+/// WriteOptions::builder(&output_file)
+///     .write(&nd::array![[0, 0, 2], [1, 0, -127], [2, 0, 2], [1, 1, 1]])?;
+///
+/// let mut bed = Bed::new(&output_file)?;
+/// let sid_filter = SidFilter::maf_at_least(0.1).and(SidFilter::call_rate_at_least(0.9));
+/// let val = ReadOptions::builder()
+///     .sid_filter(sid_filter.clone())
+///     .i8()
+///     .read(&mut bed)?;
+/// assert_eq!(val, nd::array![[0, 0], [1, 0], [2, 0], [1, 1]]);
+///
+/// // The filter compiles down to a boolean index
+/// let index = sid_filter.and(SidFilter::variance_at_least(0.2)).to_index(&mut bed)?;
+/// let val = ReadOptions::builder().sid_index(index).i8().read(&mut bed)?;
+/// assert_eq!(val, nd::array![[0], [1], [2], [1]]);
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SidFilter {
+    conditions: Vec<SidCondition>,
+}
+
+// A minimum on one statistic.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum SidCondition {
+    Maf(f64),
+    CallRate(f64),
+    Variance(f64),
+}
+
+// The per-SNP statistics that SNP filters consult, over all individuals in the file.
+#[derive(Debug)]
+pub(crate) struct SidFilterStats {
+    maf: nd::Array1<f64>,
+    call_rate: nd::Array1<f64>,
+    variance: nd::Array1<f64>,
+}
+
+impl SidFilter {
+    /// Keep SNPs (variants) whose minor allele frequency (0.0 to 0.5) is at least `min`.
+    #[must_use]
+    pub fn maf_at_least(min: f64) -> SidFilter {
+        SidFilter {
+            conditions: vec![SidCondition::Maf(min)],
+        }
+    }
+
+    /// Keep SNPs (variants) whose call rate, the fraction of individuals (samples) with a
+    /// non-missing value, is at least `min`.
+    #[must_use]
+    pub fn call_rate_at_least(min: f64) -> SidFilter {
+        SidFilter {
+            conditions: vec![SidCondition::CallRate(min)],
+        }
+    }
+
+    /// Keep SNPs (variants) whose allele dosage variance, the population variance of the
+    /// non-missing allele counts (0.0 to 1.0), is at least `min`.
+    #[must_use]
+    pub fn variance_at_least(min: f64) -> SidFilter {
+        SidFilter {
+            conditions: vec![SidCondition::Variance(min)],
+        }
+    }
+
+    /// Keep SNPs (variants) that pass both this filter and `other`.
+    #[must_use]
+    pub fn and(mut self, other: SidFilter) -> SidFilter {
+        self.conditions.extend(other.conditions);
+        self
+    }
+
+    /// The boolean [`Index`](enum.Index.html), with one entry per SNP (variant) in the file,
+    /// of the SNPs that pass this filter.
+    ///
+    /// > See [`SidFilter`](struct.SidFilter.html) for an example.
+    pub fn to_index(&self, bed: &Bed) -> Result<Index, Box<BedErrorPlus>> {
+        Ok(Index::NDArrayBool(self.mask(bed.sid_filter_stats()?)))
+    }
+
+    // For each SNP, true if it passes every condition. NaN statistics never pass.
+    pub(crate) fn mask(&self, stats: &SidFilterStats) -> nd::Array1<bool> {
+        nd::Array1::from_shape_fn(stats.maf.len(), |sid_i| {
+            self.conditions.iter().all(|condition| match *condition {
+                SidCondition::Maf(min) => stats.maf[sid_i] >= min,
+                SidCondition::CallRate(min) => stats.call_rate[sid_i] >= min,
+                SidCondition::Variance(min) => stats.variance[sid_i] >= min,
+            })
+        })
+    }
+}
+
+impl SidFilterStats {
+    // Compute the statistics over all individuals and SNPs, in one streaming pass.
+    fn compute(bed: &Bed) -> Result<SidFilterStats, Box<BedErrorPlus>> {
+        let iid_count = bed.iid_count()?;
+        let read_options = ReadOptions::builder().i8().build()?;
+        let chunk_size = (SID_FILTER_CHUNK_VALUES / iid_count.max(1)).max(1);
+
+        // For each SNP, the number of calls, and the sums of the counts and squared counts
+        let mut sums: Vec<(u64, u64, u64)> = Vec::with_capacity(bed.sid_count()?);
+        for val in bed.iter_sid_chunks(chunk_size, &read_options)? {
+            let val = val?;
+            let column_sums: Vec<(u64, u64, u64)> = val
+                .axis_iter(nd::Axis(1))
+                .into_par_iter()
+                .map(|column| {
+                    column
+                        .iter()
+                        .fold((0, 0, 0), |(count, sum, square_sum), &value| {
+                            match u64::try_from(value) {
+                                Ok(value) => (count + 1, sum + value, square_sum + value * value),
+                                Err(_) => (count, sum, square_sum), // missing
+                            }
+                        })
+                })
+                .collect();
+            sums.extend(column_sums);
+        }
+
+        let sid_count = sums.len();
+        let mut stats = SidFilterStats {
+            maf: nd::Array1::from_elem(sid_count, f64::NAN),
+            call_rate: nd::Array1::from_elem(sid_count, f64::NAN),
+            variance: nd::Array1::from_elem(sid_count, f64::NAN),
+        };
+        // "as" is safe because the counts and sums fit in f64's exact integers
+        #[allow(clippy::cast_precision_loss)]
+        for (sid_i, &(count, sum, square_sum)) in sums.iter().enumerate() {
+            if iid_count > 0 {
+                stats.call_rate[sid_i] = count as f64 / iid_count as f64;
+            }
+            if count > 0 {
+                let mean = sum as f64 / count as f64;
+                stats.maf[sid_i] = (mean / 2.0).min(1.0 - mean / 2.0);
+                // In integers, so that a constant SNP has a variance of exactly 0
+                stats.variance[sid_i] =
+                    (count * square_sum - sum * sum) as f64 / (count * count) as f64;
+            }
+        }
+        Ok(stats)
+    }
+}
+
+// Keep only the SNPs in sid_index whose entry in mask is true.
+pub(crate) fn filter_by_mask(
+    sid_index: &[isize],
+    sid_count: usize,
+    mask: &nd::Array1<bool>,
+) -> Result<Vec<isize>, Box<BedErrorPlus>> {
+    let lower_sid_count = -(sid_count as isize);
+    let upper_sid_count: isize = (sid_count as isize) - 1;
+    let mut kept = Vec::new();
+    for &sid_i_signed in sid_index {
+        let sid_i = if (0..=upper_sid_count).contains(&sid_i_signed) {
+            sid_i_signed as usize
+        } else if (lower_sid_count..=-1).contains(&sid_i_signed) {
+            sid_count - ((-sid_i_signed) as usize)
+        } else {
+            Err(BedError::SidIndexTooBig(sid_i_signed))?
+        };
+        if mask[sid_i] {
+            kept.push(sid_i_signed);
+        }
+    }
+    Ok(kept)
+}
+
+impl Bed {
+    // The statistics SNP filters consult, computed on first use.
+    pub(crate) fn sid_filter_stats(&self) -> Result<&SidFilterStats, Box<BedErrorPlus>> {
+        if let Some(stats) = self.sid_filter_stats.get() {
+            return Ok(stats);
+        }
+        let stats = Arc::new(SidFilterStats::compute(self)?);
+        Ok(self.sid_filter_stats.get_or_init(|| stats))
+    }
+}
+
+impl<TVal: BedVal> ReadOptionsBuilder<TVal> {
+    /// Of the SNPs (variants) selected by [`sid_index`](struct.ReadOptionsBuilder.html#method.sid_index),
+    /// keep only those that pass a [`SidFilter`](struct.SidFilter.html) on their minor allele
+    /// frequency, call rate, or allele dosage variance.
+    ///
+    /// The statistics are computed over all individuals (samples) in the file, the first time
+    /// they are needed, and are then kept with the [`Bed`](struct.Bed.html). Can be called more
+    /// than once; a SNP must pass every filter. Only supported when reading a local `Bed`.
+    ///
+    /// > See [`SidFilter`](struct.SidFilter.html) for an example.
+    pub fn sid_filter(&mut self, sid_filter: SidFilter) -> &mut Self {
+        let sid_filter = match self.sid_filter.clone().flatten() {
+            Some(previous) => previous.and(sid_filter),
+            None => sid_filter,
+        };
+        self.sid_filter = Some(Some(sid_filter));
+        self
+    }
+}
@@ -0,0 +1,274 @@
+use anyinput::anyinput;
+use ndarray as nd;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+#[cfg(feature = "parquet")]
+use {
+    arrow_array::{ArrayRef, Float64Array, RecordBatch, StringArray},
+    arrow_schema::{DataType, Field, Schema, SchemaRef},
+    parquet::arrow::ArrowWriter,
+    std::sync::Arc,
+};
+
+use crate::{Bed, BedError, BedErrorPlus};
+
+// Rows buffered before being written as one Parquet record batch.
+#[cfg(feature = "parquet")]
+const PARQUET_BATCH_ROWS: usize = 65_536;
+
+/// File formats supported by [`SidecarWriter`](struct.SidecarWriter.html).
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum SidecarFormat {
+    /// Tab-separated values with a header line. SNPs without a record have `NA` values.
+    Tsv,
+    /// A Parquet file with a `sid` column and one nullable `Float64` column per value.
+    /// SNPs without a record have null values. Requires the `parquet` feature.
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+enum Sink {
+    Tsv(BufWriter<File>),
+    #[cfg(feature = "parquet")]
+    Parquet {
+        writer: Box<ArrowWriter<File>>,
+        schema: SchemaRef,
+        sid: Vec<String>,
+        columns: Vec<Vec<Option<f64>>>,
+    },
+}
+
+impl Sink {
+    // Write one row. A row without values has missing values in each of the columns.
+    fn write_row(
+        &mut self,
+        sid: &str,
+        values: Option<&[f64]>,
+        column_count: usize,
+    ) -> Result<(), Box<BedErrorPlus>> {
+        match self {
+            Sink::Tsv(writer) => {
+                write!(writer, "{sid}")?;
+                if let Some(values) = values {
+                    for value in values {
+                        write!(writer, "\t{value}")?;
+                    }
+                } else {
+                    for _ in 0..column_count {
+                        write!(writer, "\tNA")?;
+                    }
+                }
+                writeln!(writer)?;
+            }
+            #[cfg(feature = "parquet")]
+            Sink::Parquet {
+                sid: batch_sid,
+                columns,
+                ..
+            } => {
+                batch_sid.push(sid.to_string());
+                for (column_i, column) in columns.iter_mut().enumerate() {
+                    column.push(values.map(|values| values[column_i]));
+                }
+                if batch_sid.len() >= PARQUET_BATCH_ROWS {
+                    self.write_batch()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "parquet")]
+    fn write_batch(&mut self) -> Result<(), Box<BedErrorPlus>> {
+        if let Sink::Parquet {
+            writer,
+            schema,
+            sid,
+            columns,
+        } = self
+        {
+            let mut arrays: Vec<ArrayRef> = Vec::with_capacity(columns.len() + 1);
+            arrays.push(Arc::new(StringArray::from(std::mem::take(sid))));
+            for column in columns.iter_mut() {
+                arrays.push(Arc::new(Float64Array::from(std::mem::take(column))));
+            }
+            writer.write(&RecordBatch::try_new(schema.clone(), arrays)?)?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(), Box<BedErrorPlus>> {
+        match self {
+            Sink::Tsv(ref mut writer) => writer.flush()?,
+            #[cfg(feature = "parquet")]
+            Sink::Parquet { .. } => {
+                self.write_batch()?;
+                if let Sink::Parquet { writer, .. } = self {
+                    writer.close()?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes per-SNP (per-variant) records, such as association results, to a sidecar file
+/// whose rows are in the same order as the .bim file.
+///
+/// Records can be given in any order, by sid or by SNP index, for example, from a parallel
+/// scan of the SNPs. Each row is written as soon as it and every row before it are known, so
+/// records given in .bim order are not held in memory. [`finish`](struct.SidecarWriter.html#method.finish)
+/// writes the rest, giving missing values to any SNP without a record, so the sidecar
+/// file always has exactly one row per SNP.
+///
+/// # Example
+/// ```
+/// use bed_reader::{Bed, ReadOptions, SidecarFormat, SidecarWriter, sample_bed_file};
+///
+/// let mut bed = Bed::new(sample_bed_file("small.bed")?)?;
+/// let output_folder = temp_testdir::TempDir::default();
+/// let output_file = output_folder.join("small.assoc.tsv");
+/// let mut writer = SidecarWriter::new(&mut bed, &output_file, ["mean", "called"], SidecarFormat::Tsv)?;
+///
+/// let val = ReadOptions::builder().f64().read(&mut bed)?;
+/// let columns: Vec<_> = val.columns().into_iter().collect();
+/// for (sid_index, column) in columns.iter().enumerate().rev() {
+///     let called: Vec<f64> = column.iter().copied().filter(|v| !v.is_nan()).collect();
+///     if called.len() >= 2 { // skip SNPs with too few calls
+///         let mean = called.iter().sum::<f64>() / called.len() as f64;
+///         writer.record_at(sid_index, &[mean, called.len() as f64])?;
+///     }
+/// }
+/// writer.finish()?;
+///
+/// let sidecar = std::fs::read_to_string(&output_file)?;
+/// assert_eq!(sidecar, "sid\tmean\tcalled\nsid1\t1\t3\nsid2\t0.3333333333333333\t3\nsid3\tNA\tNA\nsid4\t0.6666666666666666\t3\n");
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+pub struct SidecarWriter {
+    sid: nd::Array1<String>,
+    index_of_sid: HashMap<String, usize>,
+    duplicate_sids: HashSet<String>,
+    column_count: usize,
+    // Records given but not yet written, by SNP index
+    pending: BTreeMap<usize, Vec<f64>>,
+    // Every row before this one has been written
+    next_index: usize,
+    sink: Sink,
+}
+
+impl SidecarWriter {
+    /// Create a sidecar file for the SNPs (variants) of a [`Bed`](struct.Bed.html), with the
+    /// given value column names.
+    ///
+    /// > See [`SidecarWriter`](struct.SidecarWriter.html) for an example.
+    #[anyinput]
+    pub fn new(
+        bed: &mut Bed,
+        path: AnyPath,
+        column_names: AnyIter<AnyString>,
+        format: SidecarFormat,
+    ) -> Result<SidecarWriter, Box<BedErrorPlus>> {
+        let sid = bed.sid()?.clone();
+        let column_names: Vec<String> =
+            column_names.map(|name| name.as_ref().to_string()).collect();
+
+        let mut index_of_sid = HashMap::with_capacity(sid.len());
+        let mut duplicate_sids = HashSet::new();
+        for (sid_index, sid) in sid.iter().enumerate() {
+            if index_of_sid.insert(sid.clone(), sid_index).is_some() {
+                duplicate_sids.insert(sid.clone());
+            }
+        }
+
+        let file = File::create(path)?;
+        let sink = match format {
+            SidecarFormat::Tsv => {
+                let mut writer = BufWriter::new(file);
+                writeln!(writer, "sid\t{}", column_names.join("\t"))?;
+                Sink::Tsv(writer)
+            }
+            #[cfg(feature = "parquet")]
+            SidecarFormat::Parquet => {
+                let mut fields = vec![Field::new("sid", DataType::Utf8, false)];
+                fields.extend(
+                    column_names
+                        .iter()
+                        .map(|name| Field::new(name.as_str(), DataType::Float64, true)),
+                );
+                let schema = Arc::new(Schema::new(fields));
+                Sink::Parquet {
+                    writer: Box::new(ArrowWriter::try_new(file, schema.clone(), None)?),
+                    schema,
+                    sid: Vec::new(),
+                    columns: vec![Vec::new(); column_names.len()],
+                }
+            }
+        };
+
+        Ok(SidecarWriter {
+            sid,
+            index_of_sid,
+            duplicate_sids,
+            column_count: column_names.len(),
+            pending: BTreeMap::new(),
+            next_index: 0,
+            sink,
+        })
+    }
+
+    /// Give the values of the SNP (variant) with this sid.
+    ///
+    /// Returns an error if no SNP, or more than one SNP, has this sid,
+    /// or if the SNP already has a record.
+    pub fn record(&mut self, sid: &str, values: &[f64]) -> Result<(), Box<BedErrorPlus>> {
+        if self.duplicate_sids.contains(sid) {
+            Err(BedError::DuplicateSid(sid.to_string()))?;
+        }
+        let Some(&sid_index) = self.index_of_sid.get(sid) else {
+            Err(BedError::UnknownSid(sid.to_string()))?
+        };
+        self.record_at(sid_index, values)
+    }
+
+    /// Give the values of the SNP (variant) at this index, that is, line of the .bim file.
+    ///
+    /// Returns an error if the SNP already has a record.
+    ///
+    /// > See [`SidecarWriter`](struct.SidecarWriter.html) for an example.
+    pub fn record_at(&mut self, sid_index: usize, values: &[f64]) -> Result<(), Box<BedErrorPlus>> {
+        if sid_index >= self.sid.len() {
+            Err(BedError::SidIndexTooBig(sid_index as isize))?;
+        }
+        if values.len() != self.column_count {
+            Err(BedError::InconsistentCount(
+                "value".to_string(),
+                self.column_count,
+                values.len(),
+            ))?;
+        }
+        if sid_index < self.next_index || self.pending.contains_key(&sid_index) {
+            Err(BedError::SidAlreadyRecorded(self.sid[sid_index].clone()))?;
+        }
+
+        self.pending.insert(sid_index, values.to_vec());
+        while let Some(values) = self.pending.remove(&self.next_index) {
+            self.sink
+                .write_row(&self.sid[self.next_index], Some(&values), self.column_count)?;
+            self.next_index += 1;
+        }
+        Ok(())
+    }
+
+    /// Write the remaining rows, with missing values for SNPs without a record, and close the file.
+    pub fn finish(mut self) -> Result<(), Box<BedErrorPlus>> {
+        for sid_index in self.next_index..self.sid.len() {
+            let values = self.pending.remove(&sid_index);
+            self.sink
+                .write_row(&self.sid[sid_index], values.as_deref(), self.column_count)?;
+        }
+        self.sink.finish()
+    }
+}
@@ -0,0 +1,306 @@
+use ndarray as nd;
+use std::collections::{BTreeSet, HashMap};
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::parallel::prelude::*;
+use crate::{Bed, BedError, BedErrorPlus, ChromosomeScheme, Index, ReadOptions};
+
+// The most genotype values read at once when sketching.
+const SKETCH_CHUNK_VALUES: usize = 8_000_000;
+
+// One SNP (variant) in this many, chosen by hashing its position and alleles, is sketched.
+const SKETCH_THINNING: u64 = 16;
+
+// The bins per band when matching sketches by locality-sensitive hashing.
+const SKETCH_BAND_BINS: usize = 4;
+
+// A bin that no genotype hashed to.
+const EMPTY_BIN: u64 = u64::MAX;
+
+/// Per-individual (per-sample) genotype sketches, for matching the same individuals across
+/// datasets with [`match_samples`](fn.match_samples.html).
+///
+/// Made by [`Bed::sample_sketches`](struct.Bed.html#method.sample_sketches).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SampleSketches {
+    iid: nd::Array1<String>,
+    sketches: nd::Array2<u64>,
+    sid_count: usize,
+}
+
+impl SampleSketches {
+    /// The individual id of each individual (sample) sketched.
+    #[must_use]
+    pub fn iid(&self) -> &nd::Array1<String> {
+        &self.iid
+    }
+
+    /// The sketches, one row of bins per individual (sample).
+    #[must_use]
+    pub fn sketches(&self) -> &nd::Array2<u64> {
+        &self.sketches
+    }
+
+    /// The number of SNPs (variants) sketched, after thinning.
+    #[must_use]
+    pub fn sid_count(&self) -> usize {
+        self.sid_count
+    }
+
+    /// The number of bins in each sketch.
+    #[must_use]
+    pub fn k(&self) -> usize {
+        self.sketches.ncols()
+    }
+}
+
+/// The best match, in a second [`SampleSketches`](struct.SampleSketches.html), for an
+/// individual (sample) of a first, as found by [`match_samples`](fn.match_samples.html).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SampleMatch {
+    index_a: usize,
+    index_b: usize,
+    similarity: f64,
+}
+
+impl SampleMatch {
+    /// The index of the individual (sample) in the first sketches.
+    #[must_use]
+    pub fn index_a(&self) -> usize {
+        self.index_a
+    }
+
+    /// The index of its best match in the second sketches.
+    #[must_use]
+    pub fn index_b(&self) -> usize {
+        self.index_b
+    }
+
+    /// The estimated Jaccard similarity of their genotypes, from 0.0 to 1.0, with 1.0 for
+    /// the same genotypes.
+    #[must_use]
+    pub fn similarity(&self) -> f64 {
+        self.similarity
+    }
+}
+
+// The SNP's key, from its position and alleles, in an order that doesn't depend on which
+// allele is counted, and whether its allele 1 sorts first.
+fn sid_key(chromosome: &str, bp_position: i32, allele_1: &str, allele_2: &str) -> (u64, bool) {
+    let is_a1_first = allele_1 <= allele_2;
+    let (first, second) = if is_a1_first {
+        (allele_1, allele_2)
+    } else {
+        (allele_2, allele_1)
+    };
+    let key = format!(
+        "{}:{bp_position}:{first}:{second}",
+        ChromosomeScheme::EnsemblNumeric.normalize(chromosome)
+    );
+    (xxh3_64(key.as_bytes()), is_a1_first)
+}
+
+// The estimated Jaccard similarity of two sketches, over the bins not empty in both.
+fn sketch_similarity(a: nd::ArrayView1<'_, u64>, b: nd::ArrayView1<'_, u64>) -> f64 {
+    let mut same_count = 0usize;
+    let mut used_count = 0usize;
+    for (&a_bin, &b_bin) in a.iter().zip(b.iter()) {
+        if a_bin == EMPTY_BIN && b_bin == EMPTY_BIN {
+            continue;
+        }
+        used_count += 1;
+        if a_bin == b_bin {
+            same_count += 1;
+        }
+    }
+    if used_count == 0 {
+        return 0.0;
+    }
+    // "as" is safe because the counts are at most the number of bins
+    #[allow(clippy::cast_precision_loss)]
+    let similarity = same_count as f64 / used_count as f64;
+    similarity
+}
+
+impl Bed {
+    /// Sketch the genotypes of each individual (sample) in `k` bins, for fast matching of the
+    /// same individuals across datasets with [`match_samples`](fn.match_samples.html),
+    /// without comparing every pair of individuals over every SNP (variant).
+    ///
+    /// One SNP in 16, chosen by hashing its chromosome, base-pair position, and alleles, is
+    /// read, so datasets choose the same SNPs without coordinating. Each individual's non-missing
+    /// genotypes at those SNPs, counted in an allele order that doesn't depend on the file,
+    /// are hashed, and each bin keeps the smallest hash that falls in it (one-permutation
+    /// min-hashing). The sketches of two individuals agree in a fraction of bins that estimates
+    /// the Jaccard similarity of their genotypes. Larger `k` gives closer estimates. Returns a
+    /// [`BedError::ZeroSketchBins`](enum.BedError.html#variant.ZeroSketchBins) error if `k` is 0.
+    ///
+    /// > See [`match_samples`](fn.match_samples.html) for an example.
+    pub fn sample_sketches(&self, k: usize) -> Result<SampleSketches, Box<BedErrorPlus>> {
+        if k == 0 {
+            Err(BedError::ZeroSketchBins)?;
+        }
+        let iid = self.iid()?.clone();
+        let chromosome = self.chromosome()?.clone();
+        let bp_position = self.bp_position()?.clone();
+        let allele_1 = self.allele_1()?.clone();
+        let allele_2 = self.allele_2()?.clone();
+
+        // For each thinned SNP, the hash of each genotype, counting the allele that sorts first
+        let mut sid_index = Vec::new();
+        let mut genotype_hashes: Vec<[u64; 3]> = Vec::new();
+        for sid_i in 0..chromosome.len() {
+            let (key, is_a1_first) = sid_key(
+                &chromosome[sid_i],
+                bp_position[sid_i],
+                &allele_1[sid_i],
+                &allele_2[sid_i],
+            );
+            if key % SKETCH_THINNING != 0 {
+                continue;
+            }
+            let hash = |count: u8| xxh3_64(&[&key.to_le_bytes()[..], &[count]].concat());
+            sid_index.push(sid_i as isize);
+            genotype_hashes.push(if is_a1_first {
+                [hash(0), hash(1), hash(2)]
+            } else {
+                [hash(2), hash(1), hash(0)]
+            });
+        }
+
+        let iid_count = iid.len();
+        let mut sketches = nd::Array2::<u64>::from_elem((iid_count, k), EMPTY_BIN);
+        let read_options = ReadOptions::builder()
+            .sid_index(Index::Vec(sid_index))
+            .i8()
+            .build()?;
+        let chunk_size = (SKETCH_CHUNK_VALUES / iid_count.max(1)).max(1);
+        let mut sid_start = 0;
+        for val in self.iter_sid_chunks(chunk_size, &read_options)? {
+            let val = val?;
+            let chunk_hashes = &genotype_hashes[sid_start..sid_start + val.ncols()];
+            sketches
+                .axis_iter_mut(nd::Axis(0))
+                .into_par_iter()
+                .zip(val.axis_iter(nd::Axis(0)))
+                .for_each(|(mut sketch, row)| {
+                    for (&value, hashes) in row.iter().zip(chunk_hashes) {
+                        let Ok(count) = usize::try_from(value) else {
+                            continue; // missing
+                        };
+                        let hash = hashes[count];
+                        // "as" is safe because the bin is less than k
+                        #[allow(clippy::cast_possible_truncation)]
+                        let bin = ((u128::from(hash) * k as u128) >> 64) as usize;
+                        sketch[bin] = sketch[bin].min(hash);
+                    }
+                });
+            sid_start += val.ncols();
+        }
+
+        Ok(SampleSketches {
+            iid,
+            sketches,
+            sid_count: genotype_hashes.len(),
+        })
+    }
+}
+
+/// For each individual (sample) of `sketches_a`, find its best match in `sketches_b`, for
+/// example, to resolve the identities of individuals across two datasets.
+///
+/// Candidates are found by locality-sensitive hashing: each sketch is split into bands of 4
+/// bins, and individuals with a band in common are compared. Individuals with no candidate
+/// get no match. Matches are in the order of `sketches_a`, and a match's
+/// [`similarity`](struct.SampleMatch.html#method.similarity) should be checked against a
+/// threshold, as unrelated individuals share many genotypes. Returns a
+/// [`BedError::SketchBinsMismatch`](enum.BedError.html#variant.SketchBinsMismatch) error if the
+/// sketches have different numbers of bins.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{match_samples, Bed, WriteOptions};
+///
+/// let output_folder = temp_testdir::TempDir::default();
+/// let val = nd::Array2::from_shape_fn((6, 2000), |(i, j)| {
+///     ((i * 2000 + j) as u64 * 2_654_435_761 % 4_294_967_291 % 3) as i8
+/// });
+/// let file_a = output_folder.join("a.bed");
+/// WriteOptions::builder(&file_a).bp_position(1..=2000).write(&val)?;
+/// // The same individuals, in another order
+/// let file_b = output_folder.join("b.bed");
+/// let order = [3, 0, 5, 1, 4, 2];
+/// WriteOptions::builder(&file_b)
+///     .bp_position(1..=2000)
+///     .write(&val.select(nd::Axis(0), &order))?;
+///
+/// let sketches_a = Bed::new(&file_a)?.sample_sketches(64)?;
+/// let sketches_b = Bed::new(&file_b)?.sample_sketches(64)?;
+/// let matches = match_samples(&sketches_a, &sketches_b)?;
+/// assert_eq!(matches.len(), 6);
+/// for sample_match in matches {
+///     assert_eq!(order[sample_match.index_b()], sample_match.index_a());
+///     assert_eq!(sample_match.similarity(), 1.0);
+/// }
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+pub fn match_samples(
+    sketches_a: &SampleSketches,
+    sketches_b: &SampleSketches,
+) -> Result<Vec<SampleMatch>, Box<BedErrorPlus>> {
+    let k = sketches_a.k();
+    if sketches_b.k() != k {
+        Err(BedError::SketchBinsMismatch(k, sketches_b.k()))?;
+    }
+
+    // For each band, the individuals of b by their bins in the band
+    let band_count = k.div_ceil(SKETCH_BAND_BINS);
+    let band_range =
+        |band_i: usize| band_i * SKETCH_BAND_BINS..((band_i + 1) * SKETCH_BAND_BINS).min(k);
+    let mut buckets: Vec<HashMap<&[u64], Vec<usize>>> = vec![HashMap::new(); band_count];
+    for (index_b, sketch) in sketches_b.sketches.axis_iter(nd::Axis(0)).enumerate() {
+        // unwrap always works because rows of a standard-layout array are contiguous
+        let sketch = sketch.to_slice().unwrap();
+        for (band_i, band_buckets) in buckets.iter_mut().enumerate() {
+            let band = &sketch[band_range(band_i)];
+            if band.iter().all(|&bin| bin == EMPTY_BIN) {
+                continue;
+            }
+            band_buckets.entry(band).or_default().push(index_b);
+        }
+    }
+
+    let matches: Vec<Option<SampleMatch>> = sketches_a
+        .sketches
+        .axis_iter(nd::Axis(0))
+        .into_par_iter()
+        .enumerate()
+        .map(|(index_a, sketch)| {
+            let mut candidates = BTreeSet::new();
+            for (band_i, band_buckets) in buckets.iter().enumerate() {
+                // unwrap always works because rows of a standard-layout array are contiguous
+                let band = &sketch.as_slice().unwrap()[band_range(band_i)];
+                if let Some(indexes_b) = band_buckets.get(band) {
+                    candidates.extend(indexes_b);
+                }
+            }
+            candidates
+                .into_iter()
+                .map(|&index_b| SampleMatch {
+                    index_a,
+                    index_b,
+                    similarity: sketch_similarity(sketch, sketches_b.sketches.row(index_b)),
+                })
+                .reduce(|best, other| {
+                    if other.similarity > best.similarity {
+                        other
+                    } else {
+                        best
+                    }
+                })
+        })
+        .collect();
+    Ok(matches.into_iter().flatten().collect())
+}
@@ -0,0 +1,256 @@
+use ndarray as nd;
+
+use crate::parallel::prelude::*;
+use crate::stats_options::deterministic_sum;
+use crate::{
+    Bed, BedErrorPlus, BedVal, MissingPolicy, ReadOptions, ReadOptionsBuilder, StatsOptions,
+};
+
+// The most genotype values read at once when computing statistics.
+const STATS_CHUNK_VALUES: usize = 8_000_000;
+
+/// Per-SNP (per-variant) statistics, computed in one streaming pass by
+/// [`Bed::stats`](struct.Bed.html#method.stats).
+///
+/// Each array has one entry per SNP read. Statistics are of the counted allele, allele 1 by
+/// default, over the non-missing values of the individuals (samples) read. A SNP with no
+/// calls has a NaN mean and minor allele frequency.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnpStats {
+    mean: nd::Array1<f64>,
+    maf: nd::Array1<f64>,
+    missing_rate: nd::Array1<f64>,
+    call_count: nd::Array1<usize>,
+}
+
+impl SnpStats {
+    /// The mean count of the counted allele (0.0 to 2.0). Half of this is the allele's frequency.
+    #[must_use]
+    pub fn mean(&self) -> &nd::Array1<f64> {
+        &self.mean
+    }
+
+    /// The minor allele frequency (0.0 to 0.5).
+    #[must_use]
+    pub fn maf(&self) -> &nd::Array1<f64> {
+        &self.maf
+    }
+
+    /// The fraction of individuals with a missing value, or NaN if no individuals were read.
+    #[must_use]
+    pub fn missing_rate(&self) -> &nd::Array1<f64> {
+        &self.missing_rate
+    }
+
+    /// The number of individuals with a non-missing value.
+    #[must_use]
+    pub fn call_count(&self) -> &nd::Array1<usize> {
+        &self.call_count
+    }
+
+    /// The number of SNPs (variants).
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.call_count.len()
+    }
+
+    /// True if there are no SNPs (variants).
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.call_count.is_empty()
+    }
+}
+
+impl SnpStats {
+    // Gather statistics computed in parts, each with the output positions of its SNPs.
+    pub(crate) fn gather<'a, I>(parts: I, sid_count: usize) -> SnpStats
+    where
+        I: IntoIterator<Item = (&'a [usize], SnpStats)>,
+    {
+        let mut stats = SnpStats {
+            mean: nd::Array1::zeros(sid_count),
+            maf: nd::Array1::zeros(sid_count),
+            missing_rate: nd::Array1::zeros(sid_count),
+            call_count: nd::Array1::zeros(sid_count),
+        };
+        for (out_list, part) in parts {
+            for (part_i, &out_sid_i) in out_list.iter().enumerate() {
+                stats.mean[out_sid_i] = part.mean[part_i];
+                stats.maf[out_sid_i] = part.maf[part_i];
+                stats.missing_rate[out_sid_i] = part.missing_rate[part_i];
+                stats.call_count[out_sid_i] = part.call_count[part_i];
+            }
+        }
+        stats
+    }
+}
+
+impl Bed {
+    /// Compute per-SNP (per-variant) mean, minor allele frequency, missing rate, and call count
+    /// over all individuals (samples), in one streaming pass.
+    ///
+    /// SNPs are read in chunks, so the full genotype matrix is never held in memory.
+    /// To choose individuals, SNPs, or the counted allele, see
+    /// [`ReadOptionsBuilder::stats`](struct.ReadOptionsBuilder.html#method.stats).
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("stats.bed");
+    /// WriteOptions::builder(&output_file)
+    ///     .write(&nd::array![[1, 0, -127], [2, 0, -127], [0, 1, 2], [2, 0, -127]])?;
+    ///
+    /// let bed = Bed::new(&output_file)?;
+    /// let stats = bed.stats()?;
+    /// assert_eq!(stats.mean(), nd::array![1.25, 0.25, 2.0]);
+    /// assert_eq!(stats.maf(), nd::array![0.375, 0.125, 0.0]);
+    /// assert_eq!(stats.missing_rate(), nd::array![0.0, 0.0, 0.75]);
+    /// assert_eq!(stats.call_count(), nd::array![4, 4, 1]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn stats(&self) -> Result<SnpStats, Box<BedErrorPlus>> {
+        self.stats_with_options(&ReadOptions::<f64>::builder().build()?)
+    }
+
+    /// Like [`Bed::stats`](struct.Bed.html#method.stats), but over the individuals (samples) and
+    /// SNPs (variants) selected by `read_options`, counting the allele it counts.
+    ///
+    /// > See [`ReadOptionsBuilder::stats`](struct.ReadOptionsBuilder.html#method.stats) for an example.
+    pub fn stats_with_options<TVal: BedVal + Into<f64>>(
+        &self,
+        read_options: &ReadOptions<TVal>,
+    ) -> Result<SnpStats, Box<BedErrorPlus>> {
+        self.snp_stats(read_options, StatsOptions::new())
+    }
+
+    // Compute the statistics, summing and handling missing values as stats_options says.
+    pub(crate) fn snp_stats<TVal: BedVal + Into<f64>>(
+        &self,
+        read_options: &ReadOptions<TVal>,
+        stats_options: StatsOptions,
+    ) -> Result<SnpStats, Box<BedErrorPlus>> {
+        let is_deterministic = stats_options.is_deterministic();
+        let mut read_options = read_options.clone();
+        read_options.is_transposed = false;
+        let missing_value = read_options.missing_value;
+        #[allow(clippy::eq_op)]
+        let use_nan = missing_value != missing_value; // generic NAN test
+
+        let iid_count = self.iid_count()?;
+        let iid_count = self.filter_iids(iid_count, &read_options)?.as_ref().len();
+        let chunk_size = (STATS_CHUNK_VALUES / iid_count.max(1)).max(1);
+        let chunks = self.iter_sid_chunks(chunk_size, &read_options)?;
+
+        let sid_count = chunks.sid_count();
+        let mut sum = Vec::with_capacity(sid_count);
+        let mut call_count = Vec::with_capacity(sid_count);
+        for val in chunks {
+            let val = val?;
+            let column_sums: Vec<(f64, usize)> = val
+                .axis_iter(nd::Axis(1))
+                .into_par_iter()
+                .map(|column| {
+                    // value != value is a generic NAN check
+                    #[allow(clippy::eq_op)]
+                    let is_missing = |value: TVal| {
+                        (use_nan && value != value) || (!use_nan && value == missing_value)
+                    };
+                    if is_deterministic {
+                        let called: Vec<f64> = column
+                            .iter()
+                            .filter(|&&value| !is_missing(value))
+                            .map(|&value| value.into())
+                            .collect();
+                        (deterministic_sum(&called), called.len())
+                    } else {
+                        column.iter().fold((0.0, 0usize), |(sum, count), &value| {
+                            if is_missing(value) {
+                                (sum, count)
+                            } else {
+                                (sum + value.into(), count + 1)
+                            }
+                        })
+                    }
+                })
+                .collect();
+            for (column_sum, column_count) in column_sums {
+                sum.push(column_sum);
+                call_count.push(column_count);
+            }
+        }
+
+        let call_count = nd::Array1::from_vec(call_count);
+        #[allow(clippy::cast_precision_loss)]
+        let mean: nd::Array1<f64> = sum
+            .iter()
+            .zip(&call_count)
+            .map(|(&sum, &count)| {
+                let is_propagated =
+                    stats_options.missing_policy() == MissingPolicy::Propagate && count < iid_count;
+                if count == 0 || is_propagated {
+                    f64::NAN
+                } else {
+                    sum / count as f64
+                }
+            })
+            .collect();
+        let maf = mean.mapv(|mean| (mean / 2.0).min(1.0 - mean / 2.0));
+        #[allow(clippy::cast_precision_loss)]
+        let missing_rate = call_count.mapv(|count| {
+            if iid_count == 0 {
+                f64::NAN
+            } else {
+                (iid_count - count) as f64 / iid_count as f64
+            }
+        });
+        Ok(SnpStats {
+            mean,
+            maf,
+            missing_rate,
+            call_count,
+        })
+    }
+}
+
+impl<TVal: BedVal> ReadOptionsBuilder<TVal> {
+    /// Compute per-SNP (per-variant) statistics over the individuals (samples) and SNPs selected,
+    /// in one streaming pass. See [`SnpStats`](struct.SnpStats.html).
+    ///
+    /// The counted allele follows [`is_a1_counted`](struct.ReadOptionsBuilder.html#method.is_a1_counted)
+    /// and [`count_a1_mask`](struct.ReadOptionsBuilder.html#method.count_a1_mask).
+    /// Options that shape the output array, such as its order, don't matter.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, ReadOptions, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("stats.bed");
+    /// WriteOptions::builder(&output_file)
+    ///     .write(&nd::array![[1, 0, -127], [2, 0, -127], [0, 1, 2], [2, 0, -127]])?;
+    ///
+    /// let bed = Bed::new(&output_file)?;
+    /// let stats = ReadOptions::builder()
+    ///     .iid_index([0, 2])
+    ///     .sid_index([2, 0])
+    ///     .is_a1_counted(false)
+    ///     .i8()
+    ///     .stats(&bed)?;
+    /// assert_eq!(stats.mean(), nd::array![0.0, 1.5]);
+    /// assert_eq!(stats.call_count(), nd::array![1, 2]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn stats(&self, bed: &Bed) -> Result<SnpStats, Box<BedErrorPlus>>
+    where
+        TVal: Into<f64>,
+    {
+        let read_options = self.build()?;
+        bed.stats_with_options(&read_options)
+    }
+}
@@ -0,0 +1,104 @@
+use ndarray as nd;
+use num_traits::{Float, FromPrimitive, ToPrimitive};
+use std::fmt::Debug;
+
+use crate::{impute_and_zero_mean_snps, BedError, BedErrorPlus, Dist};
+
+/// Standardize genotype values in place, one SNP (variant) column at a time, and return each
+/// SNP's mean and standard deviation.
+///
+/// Each SNP is centered on its mean over the individuals (samples) with a value and then scaled
+/// as given by `dist`: to unit variance, with [`Dist::Unit`](enum.Dist.html#variant.Unit), or by a
+/// Beta density at the SNP's minor allele frequency, with
+/// [`Dist::Beta`](enum.Dist.html#variant.Beta). Missing (NaN) values become 0.0, the mean.
+/// A SNP with only one observed value becomes all 0.0 and gets an infinite standard deviation.
+///
+/// The returned array has one row per SNP: its mean and then its standard deviation.
+/// Give it to [`standardize_with_stats`](fn.standardize_with_stats.html) to standardize other
+/// individuals, for example, a test set, in the same way.
+///
+/// Returns a [`BedError::NoIndividuals`](enum.BedError.html#variant.NoIndividuals) error if
+/// a SNP has no values and, with `Dist::Beta`, a
+/// [`BedError::IllegalSnpMean`](enum.BedError.html#variant.IllegalSnpMean) error if a SNP's
+/// mean isn't from 0.0 to 2.0.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{standardize, Dist};
+///
+/// let mut val = nd::array![[0.0, 1.0], [2.0, f64::NAN], [1.0, 1.0]];
+/// let stats = standardize(&mut val, Dist::Unit)?;
+/// assert_eq!(stats.column(0), nd::array![1.0, 1.0]);
+/// assert!((val[(1, 0)] - 1.5f64.sqrt()).abs() < 1e-12);
+/// assert_eq!(val.column(1), nd::array![0.0, 0.0, 0.0]);
+/// assert!(stats[(1, 1)].is_infinite());
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+pub fn standardize<T, S>(
+    val: &mut nd::ArrayBase<S, nd::Ix2>,
+    dist: Dist,
+) -> Result<nd::Array2<T>, Box<BedErrorPlus>>
+where
+    T: Default + Copy + Debug + Sync + Send + Float + ToPrimitive + FromPrimitive,
+    S: nd::DataMut<Elem = T>,
+{
+    let mut stats = nd::Array2::<T>::zeros((val.dim().1, 2));
+    impute_and_zero_mean_snps(
+        &mut val.view_mut(),
+        &dist,
+        true,
+        false,
+        &mut stats.view_mut(),
+    )?;
+    Ok(stats)
+}
+
+/// Standardize genotype values in place with the means and standard deviations from
+/// [`standardize`](fn.standardize.html).
+///
+/// `stats` has one row per SNP (variant) column of `val`: its mean and then its standard
+/// deviation. Missing (NaN) values become 0.0.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{standardize, standardize_with_stats, Dist};
+///
+/// let mut train = nd::array![[0.0, 1.0], [2.0, 0.0], [1.0, 2.0], [1.0, 1.0]];
+/// let stats = standardize(&mut train, Dist::Unit)?;
+///
+/// let mut test = nd::array![[1.0, f64::NAN], [2.0, 1.0]];
+/// standardize_with_stats(&mut test, Dist::Unit, &stats)?;
+/// assert_eq!(test.column(1), nd::array![0.0, 0.0]);
+/// assert!((test[(1, 0)] - 1.0 / stats[(0, 1)]).abs() < 1e-12);
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+pub fn standardize_with_stats<T, S>(
+    val: &mut nd::ArrayBase<S, nd::Ix2>,
+    dist: Dist,
+    stats: &nd::Array2<T>,
+) -> Result<(), Box<BedErrorPlus>>
+where
+    T: Default + Copy + Debug + Sync + Send + Float + ToPrimitive + FromPrimitive,
+    S: nd::DataMut<Elem = T>,
+{
+    let sid_count = val.dim().1;
+    if stats.dim() != (sid_count, 2) {
+        Err(BedError::InconsistentCount(
+            "sid".to_string(),
+            sid_count,
+            stats.nrows(),
+        ))?;
+    }
+    let mut stats = stats.clone();
+    impute_and_zero_mean_snps(
+        &mut val.view_mut(),
+        &dist,
+        true,
+        true,
+        &mut stats.view_mut(),
+    )
+}
@@ -0,0 +1,320 @@
+use anyinput::anyinput;
+use ndarray as nd;
+use num_traits::{Float, FromPrimitive, ToPrimitive};
+use std::fmt::Debug;
+
+use crate::global_config::resolve_missing_policy;
+use crate::parallel::prelude::*;
+use crate::{
+    standardize, standardize_with_stats, Agg, Bed, BedError, BedErrorPlus, BedVal, Dist,
+    ReadOptions, SnpStats,
+};
+
+// The number of values summed, with compensation, before their sum joins the pairwise sum.
+const SUM_BLOCK_LEN: usize = 1024;
+
+/// What the aggregating methods of [`StatsOptions`](struct.StatsOptions.html) do with missing values.
+///
+/// | Method | `Skip` (default) | `MeanImpute` | `Propagate` |
+/// |---|---|---|---|
+/// | [`stats`](struct.StatsOptions.html#method.stats) | Mean and MAF over called values | Same as `Skip` | Mean and MAF are NaN for a SNP with any missing value |
+/// | [`standardize`](struct.StatsOptions.html#method.standardize) | Stats over called values; missing values become 0.0, the mean | Same as `Skip` | A SNP with any missing value gets NaN stats and all NaN values |
+/// | [`aggregate_by`](struct.StatsOptions.html#method.aggregate_by) | Sums and means over the group's called values | Missing values count as the SNP's mean over all called values | A group with any missing value gets NaN |
+///
+/// Missing rates and call counts always count the missing values.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{Agg, Bed, MissingPolicy, StatsOptions, WriteOptions};
+///
+/// let output_folder = temp_testdir::TempDir::default();
+/// let output_file = output_folder.join("missing.bed");
+/// WriteOptions::builder(&output_file).write(&nd::array![[2, 0], [-127, 0], [0, 1]])?;
+/// let mut bed = Bed::new(&output_file)?;
+///
+/// let labels = ["a", "a", "b"];
+/// for (missing_policy, a_count) in [
+///     (MissingPolicy::Skip, 2.0),
+///     (MissingPolicy::MeanImpute, 3.0), // the missing value counts as the mean, 1.0
+/// ] {
+///     let stats_options = StatsOptions::new().with_missing_policy(missing_policy);
+///     let (_, val) = stats_options.aggregate_by(&mut bed, labels, Agg::AlleleCount)?;
+///     assert_eq!(val.column(0), nd::array![a_count, 0.0]);
+/// }
+///
+/// let stats_options = StatsOptions::new().with_missing_policy(MissingPolicy::Propagate);
+/// let (_, val) = stats_options.aggregate_by(&mut bed, labels, Agg::AlleleCount)?;
+/// assert!(val[(0, 0)].is_nan());
+/// assert_eq!(val[(1, 0)], 0.0);
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash, Default)]
+pub enum MissingPolicy {
+    /// Leave missing values out (default).
+    #[default]
+    Skip,
+    /// Treat each missing value as its SNP's (variant's) mean over its called values.
+    MeanImpute,
+    /// Make any result that a missing value touches NaN.
+    Propagate,
+}
+
+/// How per-SNP (per-variant) statistics, such as means and standard deviations, are summed.
+///
+/// By default, each SNP's values are added one after another, which is fast. With
+/// [`StatsOptions::deterministic`](struct.StatsOptions.html#method.deterministic), they are
+/// instead summed in `f64` with compensated (Kahan) summation over fixed-size blocks, in a
+/// fixed order, and the block sums are then added pairwise. The results depend only on the
+/// values, not on the value type (f32 or f64), the array's memory order, how SNPs are read in
+/// chunks, or the number of threads, and are more accurate for many individuals (samples).
+/// Use it when QC metrics or standardized values must be bit-identical across machines
+/// and settings.
+///
+/// The options also choose what is done with missing values. See
+/// [`MissingPolicy`](enum.MissingPolicy.html).
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{Dist, StatsOptions};
+///
+/// let mut val64 = nd::array![[0.0, 1.0], [2.0, f64::NAN], [1.0, 1.0]];
+/// let mut val32 = val64.mapv(|v| v as f32);
+/// let stats_options = StatsOptions::deterministic();
+/// let stats64 = stats_options.standardize(&mut val64, Dist::Unit)?;
+/// let stats32 = stats_options.standardize(&mut val32, Dist::Unit)?;
+/// assert_eq!(stats32, stats64.mapv(|v| v as f32));
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatsOptions {
+    is_deterministic: bool,
+    missing_policy: MissingPolicy,
+}
+
+impl Default for StatsOptions {
+    fn default() -> Self {
+        StatsOptions {
+            is_deterministic: false,
+            missing_policy: resolve_missing_policy(),
+        }
+    }
+}
+
+impl StatsOptions {
+    /// Sum values one after another, the default, with the missing policy of the installed
+    /// [`GlobalConfig`](struct.GlobalConfig.html), or else
+    /// [`MissingPolicy::Skip`](enum.MissingPolicy.html#variant.Skip).
+    #[must_use]
+    pub fn new() -> StatsOptions {
+        StatsOptions::default()
+    }
+
+    /// Sum values in a fixed, compensated, blocked order, so results are reproducible.
+    ///
+    /// > See [`StatsOptions`](struct.StatsOptions.html) for details and an example.
+    #[must_use]
+    pub fn deterministic() -> StatsOptions {
+        StatsOptions {
+            is_deterministic: true,
+            ..StatsOptions::default()
+        }
+    }
+
+    /// True if values are summed deterministically.
+    #[must_use]
+    pub fn is_deterministic(&self) -> bool {
+        self.is_deterministic
+    }
+
+    /// These options, but with missing values handled as `missing_policy` says.
+    ///
+    /// > See [`MissingPolicy`](enum.MissingPolicy.html) for details and an example.
+    #[must_use]
+    pub fn with_missing_policy(self, missing_policy: MissingPolicy) -> StatsOptions {
+        StatsOptions {
+            missing_policy,
+            ..self
+        }
+    }
+
+    /// What is done with missing values.
+    #[must_use]
+    pub fn missing_policy(&self) -> MissingPolicy {
+        self.missing_policy
+    }
+
+    /// Like [`Bed::stats_with_options`](struct.Bed.html#method.stats_with_options), but summing
+    /// as these options say.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, ReadOptions, StatsOptions, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("stats.bed");
+    /// WriteOptions::builder(&output_file).write(&nd::array![[1, 0], [2, -127], [0, 1]])?;
+    ///
+    /// let bed = Bed::new(&output_file)?;
+    /// let read_options = ReadOptions::builder().f32().build()?;
+    /// let stats = StatsOptions::deterministic().stats(&bed, &read_options)?;
+    /// assert_eq!(stats.mean(), &nd::array![1.0, 0.5]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn stats<TVal: BedVal + Into<f64>>(
+        &self,
+        bed: &Bed,
+        read_options: &ReadOptions<TVal>,
+    ) -> Result<SnpStats, Box<BedErrorPlus>> {
+        bed.snp_stats(read_options, *self)
+    }
+
+    /// Like [`standardize`](fn.standardize.html), but summing as these options say.
+    ///
+    /// With deterministic summation, each SNP's standard deviation is found from the sum of
+    /// squared differences from its mean.
+    ///
+    /// > See [`StatsOptions`](struct.StatsOptions.html) for an example.
+    pub fn standardize<T, S>(
+        &self,
+        val: &mut nd::ArrayBase<S, nd::Ix2>,
+        dist: Dist,
+    ) -> Result<nd::Array2<T>, Box<BedErrorPlus>>
+    where
+        T: Default + Copy + Debug + Sync + Send + Float + ToPrimitive + FromPrimitive,
+        S: nd::DataMut<Elem = T>,
+    {
+        if self.missing_policy == MissingPolicy::Propagate {
+            return self.standardize_propagating(val, dist);
+        }
+        if !self.is_deterministic {
+            return standardize(val, dist);
+        }
+        let stats = deterministic_stats(&val.view(), dist)?;
+        standardize_with_stats(val, dist, &stats)?;
+        Ok(stats)
+    }
+
+    // Standardize the SNPs without missing values; make the others, and their stats, all NaN.
+    fn standardize_propagating<T, S>(
+        self,
+        val: &mut nd::ArrayBase<S, nd::Ix2>,
+        dist: Dist,
+    ) -> Result<nd::Array2<T>, Box<BedErrorPlus>>
+    where
+        T: Default + Copy + Debug + Sync + Send + Float + ToPrimitive + FromPrimitive,
+        S: nd::DataMut<Elem = T>,
+    {
+        let complete: Vec<usize> = val
+            .columns()
+            .into_iter()
+            .enumerate()
+            .filter(|(_, column)| !column.iter().any(|value| value.is_nan()))
+            .map(|(sid_i, _)| sid_i)
+            .collect();
+        let mut complete_val = val.select(nd::Axis(1), &complete);
+        let complete_stats = self
+            .with_missing_policy(MissingPolicy::Skip)
+            .standardize(&mut complete_val, dist)?;
+
+        let mut stats = nd::Array2::<T>::from_elem((val.ncols(), 2), T::nan());
+        val.fill(T::nan());
+        for (complete_i, &sid_i) in complete.iter().enumerate() {
+            val.column_mut(sid_i)
+                .assign(&complete_val.column(complete_i));
+            stats.row_mut(sid_i).assign(&complete_stats.row(complete_i));
+        }
+        Ok(stats)
+    }
+
+    /// Like [`Bed::aggregate_by`](struct.Bed.html#method.aggregate_by), but handling missing
+    /// values as these options say. Summation isn't affected by
+    /// [`StatsOptions::deterministic`](struct.StatsOptions.html#method.deterministic).
+    ///
+    /// > See [`MissingPolicy`](enum.MissingPolicy.html) for an example.
+    #[anyinput]
+    pub fn aggregate_by(
+        &self,
+        bed: &mut Bed,
+        group_labels: AnyIter<AnyString>,
+        agg: Agg,
+    ) -> Result<(nd::Array1<String>, nd::Array2<f64>), Box<BedErrorPlus>> {
+        bed.aggregate(group_labels, agg, self.missing_policy)
+    }
+}
+
+// Find each SNP's mean and standard deviation, summing deterministically in f64.
+fn deterministic_stats<T>(
+    val: &nd::ArrayView2<'_, T>,
+    dist: Dist,
+) -> Result<nd::Array2<T>, Box<BedErrorPlus>>
+where
+    T: Default + Copy + Debug + Sync + Send + Float + ToPrimitive + FromPrimitive,
+{
+    let rows = val
+        .axis_iter(nd::Axis(1))
+        .into_par_iter()
+        .map(|column| {
+            let called: Vec<f64> = column
+                .iter()
+                .filter(|v| !v.is_nan())
+                .map(|v| v.to_f64().unwrap_or(f64::NAN))
+                .collect();
+            if called.is_empty() {
+                Err(BedError::NoIndividuals)?;
+            }
+            #[allow(clippy::cast_precision_loss)]
+            let count = called.len() as f64;
+            let mean = deterministic_sum(&called) / count;
+            if mean.is_nan() || (dist.is_beta() && !(0.0..=2.0).contains(&mean)) {
+                Err(BedError::IllegalSnpMean)?;
+            }
+            let squares: Vec<f64> = called.iter().map(|v| (v - mean) * (v - mean)).collect();
+            let mut std = (deterministic_sum(&squares) / count).sqrt();
+            if std.is_nan() || std <= 0.0 {
+                // All the values are the same
+                std = f64::INFINITY;
+            }
+            Ok([mean, std])
+        })
+        .collect::<Result<Vec<[f64; 2]>, Box<BedErrorPlus>>>()?;
+
+    let mut stats = nd::Array2::<T>::zeros((rows.len(), 2));
+    for (mut stats_row, row) in stats.axis_iter_mut(nd::Axis(0)).zip(rows) {
+        for (stat, value) in stats_row.iter_mut().zip(row) {
+            *stat = T::from_f64(value).ok_or(BedError::CannotConvertBetaToFromF64)?;
+        }
+    }
+    Ok(stats)
+}
+
+// Sum in a fixed order: with Kahan compensation within blocks, then pairwise over the blocks.
+pub(crate) fn deterministic_sum(values: &[f64]) -> f64 {
+    let block_sums: Vec<f64> = values
+        .chunks(SUM_BLOCK_LEN)
+        .map(|block| {
+            let (mut sum, mut compensation) = (0.0, 0.0);
+            for &value in block {
+                let y = value - compensation;
+                let t = sum + y;
+                compensation = (t - sum) - y;
+                sum = t;
+            }
+            sum
+        })
+        .collect();
+    pairwise_sum(&block_sums)
+}
+
+fn pairwise_sum(values: &[f64]) -> f64 {
+    match values.len() {
+        0 => 0.0,
+        1 => values[0],
+        len => pairwise_sum(&values[..len / 2]) + pairwise_sum(&values[len / 2..]),
+    }
+}
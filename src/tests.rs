@@ -6,6 +6,8 @@ use crate::assert_eq_nan;
 #[cfg(test)]
 use crate::assert_error_variant;
 #[cfg(test)]
+use crate::coalesce_sid_runs;
+#[cfg(test)]
 use crate::file_aat_piece;
 #[cfg(test)]
 use crate::file_ata_piece;
@@ -38,7 +40,7 @@ use crate::WriteOptions;
 #[cfg(test)]
 use crate::{impute_and_zero_mean_snps, matrix_subset_no_alloc};
 #[cfg(test)]
-use crate::{internal_read_no_alloc, read_no_alloc, BedError, BedErrorPlus};
+use crate::{internal_read_no_alloc, read_no_alloc, BedError, BedErrorPlus, TwoBitsToValue};
 #[cfg(test)]
 use anyinput::anyinput;
 #[cfg(test)]
@@ -107,7 +109,7 @@ fn reference_val_i8(is_a1_counted: bool) -> nd::Array2<i8> {
 #[test]
 fn read_test() {
     let file = sample_bed_file("plink_sim_10s_100v_10pmiss.bed").unwrap();
-    let mut bed = Bed::new(file).unwrap();
+    let bed = Bed::new(file).unwrap();
     assert!(bed.iid_count().unwrap() == 10);
     assert!(bed.sid_count().unwrap() == 100);
     let val: nd::Array2<i8> = bed.read().unwrap();
@@ -115,7 +117,7 @@ fn read_test() {
     let mean_ = val_f64.mean().unwrap();
     assert!((mean_ - -13.142).abs() < 1e-8); // really shouldn't do mean on data where -127 represents missing
 
-    let mut bed2 = Bed::new(sample_bed_file("small_too_short.bed").unwrap()).unwrap();
+    let bed2 = Bed::new(sample_bed_file("small_too_short.bed").unwrap()).unwrap();
     let result = bed2.read::<i8>();
     assert_error_variant!(result, BedErrorPlus::BedError(BedError::IllFormed(_)));
 }
@@ -249,10 +251,9 @@ fn index() {
         "ignore",
         usize::MAX,
         usize::MAX,
-        true,
+        TwoBitsToValue::new(true, None, f64::NAN),
         &[isize::MAX - 1],
         &[isize::MAX - 1],
-        f64::NAN,
         &mut ignore_val.view_mut(),
     );
     assert_error_variant!(
@@ -412,43 +413,46 @@ fn fill_in() {
         );
         assert_error_variant!(result, BedErrorPlus::BedError(BedError::NoIndividuals));
 
-        let mut bed = Bed::builder(&filename).build().unwrap();
-        let mut val = ReadOptions::builder()
-            .is_f(*output_is_orderf_ptr)
-            .f64()
-            .read(&mut bed)
+        #[cfg(feature = "beta")]
+        {
+            let mut bed = Bed::builder(&filename).build().unwrap();
+            let mut val = ReadOptions::builder()
+                .is_f(*output_is_orderf_ptr)
+                .f64()
+                .read(&mut bed)
+                .unwrap();
+            let result = impute_and_zero_mean_snps(
+                &mut val.view_mut(),
+                &Dist::Beta { a: -10.0, b: 0.0 },
+                true,
+                false,
+                &mut stats.view_mut(),
+            );
+            assert_error_variant!(
+                result,
+                BedErrorPlus::BedError(BedError::CannotCreateBetaDist(_, _))
+            );
+
+            nd::Array2::fill(&mut val, 3.0);
+            let result = impute_and_zero_mean_snps(
+                &mut val.view_mut(),
+                &Dist::Beta { a: 0.5, b: 0.5 },
+                true,
+                false,
+                &mut stats.view_mut(),
+            );
+            assert_error_variant!(result, BedErrorPlus::BedError(BedError::IllegalSnpMean));
+
+            nd::Array2::fill(&mut val, 1.0);
+            impute_and_zero_mean_snps(
+                &mut val.view_mut(),
+                &Dist::Beta { a: 0.5, b: 0.5 },
+                true,
+                false,
+                &mut stats.view_mut(),
+            )
             .unwrap();
-        let result = impute_and_zero_mean_snps(
-            &mut val.view_mut(),
-            &Dist::Beta { a: -10.0, b: 0.0 },
-            true,
-            false,
-            &mut stats.view_mut(),
-        );
-        assert_error_variant!(
-            result,
-            BedErrorPlus::BedError(BedError::CannotCreateBetaDist(_, _))
-        );
-
-        nd::Array2::fill(&mut val, 3.0);
-        let result = impute_and_zero_mean_snps(
-            &mut val.view_mut(),
-            &Dist::Beta { a: 0.5, b: 0.5 },
-            true,
-            false,
-            &mut stats.view_mut(),
-        );
-        assert_error_variant!(result, BedErrorPlus::BedError(BedError::IllegalSnpMean));
-
-        nd::Array2::fill(&mut val, 1.0);
-        impute_and_zero_mean_snps(
-            &mut val.view_mut(),
-            &Dist::Beta { a: 0.5, b: 0.5 },
-            true,
-            false,
-            &mut stats.view_mut(),
-        )
-        .unwrap();
+        }
     }
 }
 
@@ -484,6 +488,7 @@ fn div_4() {
     );
 }
 
+#[cfg(feature = "beta")]
 #[test]
 fn standardize_beta() {
     for output_is_orderf_ptr in &[true, false] {
@@ -523,6 +528,7 @@ fn read_errors() {
         iid_count,
         sid_count,
         true,
+        None,
         &iid_index,
         &sid_index,
         f64::NAN,
@@ -536,6 +542,7 @@ fn read_errors() {
         iid_count,
         sid_count,
         true,
+        None,
         &iid_index,
         &sid_index,
         f64::NAN,
@@ -549,6 +556,7 @@ fn read_errors() {
         iid_count,
         sid_count,
         true,
+        None,
         &iid_index,
         &sid_index,
         f64::NAN,
@@ -561,7 +569,7 @@ fn read_errors() {
 #[test]
 fn read_modes() -> Result<(), Box<BedErrorPlus>> {
     let filename = sample_bed_file("small.bed")?;
-    let mut bed = Bed::new(filename)?;
+    let bed = Bed::new(filename)?;
     let iid_count_s1 = bed.iid_count()?;
     let sid_count_s1 = bed.sid_count()?;
 
@@ -569,7 +577,7 @@ fn read_modes() -> Result<(), Box<BedErrorPlus>> {
     bed.read_and_fill(&mut val_small_mode_1.view_mut())?;
 
     let bed_fam_bim = sample_files(["small_too_short.bed", "small.fam", "small.bim"])?;
-    let mut bed_too_short = Bed::builder(&bed_fam_bim[0])
+    let bed_too_short = Bed::builder(&bed_fam_bim[0])
         .fam_path(&bed_fam_bim[1])
         .bim_path(&bed_fam_bim[2])
         .build()?;
@@ -577,12 +585,12 @@ fn read_modes() -> Result<(), Box<BedErrorPlus>> {
     assert_error_variant!(result, BedErrorPlus::BedError(BedError::IllFormed(_)));
 
     let mut val_small_mode_0 = nd::Array2::<i8>::default((sid_count_s1, iid_count_s1));
-    let mut bed_mode0 = Bed::new(sample_bed_file("smallmode0.bed")?)?;
+    let bed_mode0 = Bed::new(sample_bed_file("smallmode0.bed")?)?;
     bed_mode0.read_and_fill(&mut val_small_mode_0.view_mut())?;
     assert_eq!(val_small_mode_0.t(), val_small_mode_1);
 
     let bed_fam_bim = sample_files(["smallmodebad.bed", "small.fam", "small.bim"])?;
-    let mut bed_small_mode_bad = Bed::builder(&bed_fam_bim[0])
+    let bed_small_mode_bad = Bed::builder(&bed_fam_bim[0])
         .fam_path(&bed_fam_bim[1])
         .bim_path(&bed_fam_bim[2])
         .build()?;
@@ -595,7 +603,7 @@ fn read_modes() -> Result<(), Box<BedErrorPlus>> {
 #[test]
 fn zeros() -> Result<(), Box<BedErrorPlus>> {
     let filename = sample_bed_file("some_missing.bed")?;
-    let mut bed = Bed::new(&filename).unwrap();
+    let bed = Bed::new(&filename).unwrap();
     let iid_count = bed.iid_count().unwrap();
     let sid_count = bed.sid_count().unwrap();
     let iid_index_full = (0..iid_count).collect::<Vec<usize>>();
@@ -709,7 +717,7 @@ fn file_ata(
             sid_count,
             sid_start,
             &mut ata_piece.view_mut(),
-            sid_range_len,
+            &|_, _| {},
             read_into_f64,
         )?;
         insert_piece(
@@ -757,7 +765,7 @@ fn file_b_less_aatbx_medium() {
         &mut b1.view_mut(),
         &mut aatb.view_mut(),
         &mut atb.view_mut(),
-        10,
+        &|_, _| {},
     )
     .unwrap();
 
@@ -819,7 +827,7 @@ fn file_aat(
             sid_count,
             iid_start,
             &mut aat_piece.view_mut(),
-            iid_range_len,
+            &|_, _| {},
             read_into_f64,
         )?;
         println!("piece:\n{aat_piece:?}");
@@ -907,12 +915,12 @@ fn index_len_is_empty() -> Result<(), Box<BedErrorPlus>> {
 #[test]
 fn test_sample_file() -> Result<(), Box<BedErrorPlus>> {
     let filename = sample_bed_file("small.bed")?;
-    let mut bed = Bed::new(filename)?;
+    let bed = Bed::new(filename)?;
     println!("{}", bed.iid_count()?);
     println!("{}", bed.sid_count()?);
 
     let deb_maf_mib = sample_files(["small.deb", "small.maf", "small.mib"])?;
-    let mut bed = Bed::builder(&deb_maf_mib[0])
+    let bed = Bed::builder(&deb_maf_mib[0])
         .fam_path(&deb_maf_mib[1])
         .bim_path(&deb_maf_mib[2])
         .build()?;
@@ -1151,3 +1159,27 @@ fn another_bed_read_example() -> Result<(), Box<BedErrorPlus>> {
     println!("{:?}", val.dim());
     Ok(())
 }
+
+#[test]
+fn coalesce_runs() {
+    // Adjacent, increasing SNPs join; repeats, reversals, and jumps start new runs
+    assert_eq!(
+        coalesce_sid_runs(&[3, 4, 5, 5, 9, 8, 9, 10], 100, 100),
+        vec![(0, 3), (3, 1), (4, 1), (5, 3)]
+    );
+    assert_eq!(coalesce_sid_runs(&[], 100, 100), vec![]);
+
+    // Runs don't join SNPs whose needed bytes are far apart ...
+    assert_eq!(
+        coalesce_sid_runs(&[0, 1, 2], 10_000, 1),
+        vec![(0, 1), (1, 1), (2, 1)]
+    );
+    assert_eq!(coalesce_sid_runs(&[0, 1, 2], 4097, 1), vec![(0, 3)]);
+
+    // ... or grow too long
+    let in_sid_list = (0..3000).collect::<Vec<u64>>();
+    assert_eq!(
+        coalesce_sid_runs(&in_sid_list, 4096, 4096),
+        vec![(0, 2048), (2048, 952)]
+    );
+}
@@ -0,0 +1,51 @@
+use crate::{BedVal, WriteOptionsBuilder};
+
+// If the value is within the tolerance of 0, 1, or 2, return that genotype.
+pub(crate) fn snap(value: f64, tolerance: f64) -> Option<u8> {
+    let rounded = value.round();
+    if (value - rounded).abs() > tolerance {
+        return None;
+    }
+    match rounded {
+        // "as" is safe because the value is 0.0, 1.0, or 2.0
+        0.0 | 1.0 | 2.0 => Some(rounded as u8),
+        _ => None,
+    }
+}
+
+impl<TVal> WriteOptionsBuilder<TVal>
+where
+    TVal: BedVal,
+{
+    /// Accept floating-point values within `tolerance` of 0.0, 1.0, or 2.0, writing them as that genotype.
+    ///
+    /// Without a tolerance, any value other than exactly 0, 1, 2, or missing returns a
+    /// [`BedError::BadValue`](enum.BedError.html#variant.BadValue) error. Upstream float math
+    /// can leave values such as 1.0000001. The tolerance must be at least 0.0 and less than 0.5.
+    /// Use [`write_with_summary`](struct.WriteOptionsBuilder.html#method.write_with_summary)
+    /// to learn how many values were snapped.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("snapped.bed");
+    /// let val = nd::array![[1.0000001, 0.0], [2.0, 1e-9], [f64::NAN, 0.9999999]];
+    /// let summary = WriteOptions::builder(&output_file)
+    ///     .tolerance(1e-6)
+    ///     .write_with_summary(&val)?;
+    /// assert_eq!(summary.snapped_count(), 3);
+    /// assert_eq!(summary.snapped_counts(), nd::array![1, 2]);
+    ///
+    /// let bed = Bed::new(&output_file)?;
+    /// assert_eq!(bed.read::<i8>()?, nd::array![[1, 0], [2, 0], [-127, 1]]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn tolerance(&mut self, tolerance: f64) -> &mut Self {
+        self.tolerance = Some(Some(tolerance));
+        self
+    }
+}
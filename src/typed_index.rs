@@ -0,0 +1,105 @@
+use crate::{BedVal, Index, ReadOptionsBuilder};
+
+/// An [`Index`](enum.Index.html) of individuals (samples), typed so that it can't be given where
+/// SNPs (variants) are indexed.
+///
+/// Give it to [`ReadOptionsBuilder::typed_iid_index`](struct.ReadOptionsBuilder.html#method.typed_iid_index).
+/// Unlike an untyped index, it can't be passed to
+/// [`typed_sid_index`](struct.ReadOptionsBuilder.html#method.typed_sid_index); that is a compile error.
+/// Create one from anything that converts into an `Index`, for example, a `Vec<isize>`, a
+/// range, or an `s!` slice.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::{Bed, IidIndex, ReadOptions, SidIndex, WriteOptions};
+///
+/// let output_folder = temp_testdir::TempDir::default();
+/// let output_file = output_folder.join("typed.bed");
+/// let val = nd::array![[1, 0, -127, 0], [2, 0, -127, 2], [0, 1, 2, 0]];
+/// WriteOptions::builder(&output_file).write(&val)?;
+///
+/// let cases = IidIndex::new(vec![0, 2]);
+/// let snps = SidIndex::new(1..3);
+/// let mut bed = Bed::new(&output_file)?;
+/// let val = ReadOptions::builder()
+///     .typed_iid_index(cases)
+///     .typed_sid_index(snps)
+///     .i8()
+///     .read(&mut bed)?;
+/// assert_eq!(val, nd::array![[0, -127], [1, 2]]);
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+///
+/// Mixing them up doesn't compile:
+/// ```compile_fail
+/// use bed_reader::{IidIndex, ReadOptions};
+///
+/// ReadOptions::<i8>::builder().typed_sid_index(IidIndex::new(vec![0, 2]));
+/// ```
+#[derive(Debug, Clone)]
+pub struct IidIndex(pub Index);
+
+/// An [`Index`](enum.Index.html) of SNPs (variants), typed so that it can't be given where
+/// individuals (samples) are indexed.
+///
+/// Give it to [`ReadOptionsBuilder::typed_sid_index`](struct.ReadOptionsBuilder.html#method.typed_sid_index).
+///
+/// > See [`IidIndex`](struct.IidIndex.html) for details and an example.
+#[derive(Debug, Clone)]
+pub struct SidIndex(pub Index);
+
+impl IidIndex {
+    /// Create an index of individuals (samples) from anything that converts into an `Index`.
+    ///
+    /// > See [`IidIndex`](struct.IidIndex.html) for an example.
+    pub fn new<I: Into<Index>>(index: I) -> IidIndex {
+        IidIndex(index.into())
+    }
+
+    /// The untyped index.
+    #[must_use]
+    pub fn index(&self) -> &Index {
+        &self.0
+    }
+}
+
+impl SidIndex {
+    /// Create an index of SNPs (variants) from anything that converts into an `Index`.
+    ///
+    /// > See [`IidIndex`](struct.IidIndex.html) for an example.
+    pub fn new<I: Into<Index>>(index: I) -> SidIndex {
+        SidIndex(index.into())
+    }
+
+    /// The untyped index.
+    #[must_use]
+    pub fn index(&self) -> &Index {
+        &self.0
+    }
+}
+
+impl<TVal: BedVal> ReadOptionsBuilder<TVal> {
+    /// Set the individuals (samples) to read with an [`IidIndex`](struct.IidIndex.html).
+    ///
+    /// Like [`iid_index`](struct.ReadOptionsBuilder.html#method.iid_index), but only accepts an
+    /// index of individuals, so an index of SNPs (variants) given by mistake is a compile error.
+    ///
+    /// > See [`IidIndex`](struct.IidIndex.html) for an example.
+    pub fn typed_iid_index(&mut self, iid_index: IidIndex) -> &mut Self {
+        self.iid_index = Some(iid_index.0);
+        self
+    }
+
+    /// Set the SNPs (variants) to read with a [`SidIndex`](struct.SidIndex.html).
+    ///
+    /// Like [`sid_index`](struct.ReadOptionsBuilder.html#method.sid_index), but only accepts an
+    /// index of SNPs, so an index of individuals (samples) given by mistake is a compile error.
+    ///
+    /// > See [`IidIndex`](struct.IidIndex.html) for an example.
+    pub fn typed_sid_index(&mut self, sid_index: SidIndex) -> &mut Self {
+        self.sid_index = Some(sid_index.0);
+        self
+    }
+}
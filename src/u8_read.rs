@@ -0,0 +1,113 @@
+use ndarray as nd;
+
+use crate::{Bed, BedErrorPlus, BedVal, ReadOptions};
+
+// The same selection and layout options, reading i8 values with -127 for missing.
+fn to_i8_options<TVal: BedVal>(read_options: &ReadOptions<TVal>) -> ReadOptions<i8> {
+    ReadOptions {
+        missing_value: -127,
+        iid_index: read_options.iid_index.clone(),
+        sid_index: read_options.sid_index.clone(),
+        is_f: read_options.is_f,
+        is_a1_counted: read_options.is_a1_counted,
+        count_a1_mask: read_options.count_a1_mask.clone(),
+        is_transposed: read_options.is_transposed,
+        num_threads: read_options.num_threads,
+        max_concurrent_requests: read_options.max_concurrent_requests,
+        max_chunk_bytes: read_options.max_chunk_bytes,
+        score_filters: read_options.score_filters.clone(),
+        sid_filter: read_options.sid_filter.clone(),
+        regions: read_options.regions.clone(),
+        iid_where: read_options.iid_where.clone(),
+        progress: read_options.progress.clone(),
+        read_strategy: read_options.read_strategy,
+    }
+}
+
+impl Bed {
+    /// Read all genotype data as unsigned counts, 0, 1, or 2, with `missing_value` for missing.
+    ///
+    /// > Also see [`Bed::read_u8_with_options`](struct.Bed.html#method.read_u8_with_options),
+    /// > which supports options, and [`Bed::read_missing_mask`](struct.Bed.html#method.read_missing_mask).
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("counts.bed");
+    /// WriteOptions::builder(&output_file).write(&nd::array![[1, 0, -127], [2, 0, 1]])?;
+    ///
+    /// let bed = Bed::new(&output_file)?;
+    /// assert_eq!(bed.read_u8(255)?, nd::array![[1, 0, 255], [2, 0, 1]]);
+    /// assert_eq!(
+    ///     bed.read_missing_mask()?,
+    ///     nd::array![[false, false, true], [false, false, false]]
+    /// );
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn read_u8(&self, missing_value: u8) -> Result<nd::Array2<u8>, Box<BedErrorPlus>> {
+        let read_options = ReadOptions::<i8>::builder().build()?;
+        self.read_u8_with_options(&read_options, missing_value)
+    }
+
+    /// Read genotype data, with options, as unsigned counts, 0, 1, or 2, with `missing_value`
+    /// for missing.
+    ///
+    /// The options, for example, which individuals (samples) and SNPs (variants) to read and
+    /// in what memory order, are as for
+    /// [`Bed::read_with_options`](struct.Bed.html#method.read_with_options), but the options'
+    /// value type and missing value are ignored. The values are read as i8 and then converted.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, ReadOptions, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("counts.bed");
+    /// WriteOptions::builder(&output_file).write(&nd::array![[1, 0, -127], [2, 0, 1]])?;
+    ///
+    /// let bed = Bed::new(&output_file)?;
+    /// let read_options = ReadOptions::builder().sid_index([0, 2]).count_a2().f64().build()?;
+    /// let val = bed.read_u8_with_options(&read_options, 9)?;
+    /// assert_eq!(val, nd::array![[1, 9], [0, 1]]);
+    /// let mask = bed.read_missing_mask_with_options(&read_options)?;
+    /// assert_eq!(mask, nd::array![[false, true], [false, false]]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn read_u8_with_options<TVal: BedVal>(
+        &self,
+        read_options: &ReadOptions<TVal>,
+        missing_value: u8,
+    ) -> Result<nd::Array2<u8>, Box<BedErrorPlus>> {
+        let val = self.read_with_options(&to_i8_options(read_options))?;
+        // Missing, -127, is the only negative value
+        Ok(val.mapv(|value| u8::try_from(value).unwrap_or(missing_value)))
+    }
+
+    /// Read, for all genotype data, true where a value is missing.
+    ///
+    /// > See [`Bed::read_u8`](struct.Bed.html#method.read_u8) for an example.
+    pub fn read_missing_mask(&self) -> Result<nd::Array2<bool>, Box<BedErrorPlus>> {
+        let read_options = ReadOptions::<i8>::builder().build()?;
+        self.read_missing_mask_with_options(&read_options)
+    }
+
+    /// Read, with options, true where a value is missing.
+    ///
+    /// The options are as for [`Bed::read_u8_with_options`](struct.Bed.html#method.read_u8_with_options),
+    /// so the same options give a mask that lines up with values read with them.
+    ///
+    /// > See [`Bed::read_u8_with_options`](struct.Bed.html#method.read_u8_with_options) for an example.
+    pub fn read_missing_mask_with_options<TVal: BedVal>(
+        &self,
+        read_options: &ReadOptions<TVal>,
+    ) -> Result<nd::Array2<bool>, Box<BedErrorPlus>> {
+        let val = self.read_with_options(&to_i8_options(read_options))?;
+        Ok(val.mapv(|value| value < 0))
+    }
+}
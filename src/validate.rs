@@ -0,0 +1,122 @@
+use ndarray as nd;
+use std::io;
+
+use crate::{
+    compute_num_threads, try_div_4, write_columns, BedError, BedErrorPlus, BedVal, WriteOptions,
+    WriteOptionsBuilder,
+};
+
+impl<TVal> WriteOptionsBuilder<TVal>
+where
+    TVal: BedVal,
+{
+    /// Report a bad value with its location.
+    ///
+    /// By default, a value other than 0, 1, 2, or missing returns a
+    /// [`BedError::BadValue`](enum.BedError.html#variant.BadValue) error, which names only the
+    /// file. With `validate(true)`, it instead returns a
+    /// [`BedError::BadValueAt`](enum.BedError.html#variant.BadValueAt) error with the
+    /// individual (sample) index, SNP (variant) index, and value of the first bad value, in
+    /// SNP order. A [`BedWriter`](struct.BedWriter.html) counts SNPs over all its blocks.
+    /// Either way, the .bed file is removed. Also see
+    /// [`WriteOptions::validate_val`](struct.WriteOptions.html#method.validate_val), which checks
+    /// values without writing a file.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::WriteOptions;
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("validated.bed");
+    /// let val = nd::array![[1.0, 0.0], [2.0, 1.5], [f64::NAN, 3.0]];
+    /// let result = WriteOptions::builder(&output_file).validate(true).write(&val);
+    /// let message = result.unwrap_err().to_string();
+    /// assert!(message.contains("value 1.5 at iid index 1, sid index 1"));
+    /// assert!(!output_file.exists());
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn validate(&mut self, validate: bool) -> &mut Self {
+        self.validate = Some(validate);
+        self
+    }
+}
+
+impl<TVal> WriteOptions<TVal>
+where
+    TVal: BedVal,
+{
+    /// Check that values can be written with these options, without writing a file.
+    ///
+    /// Values are checked as when writing, with
+    /// [`missing_value`](struct.WriteOptionsBuilder.html#method.missing_value),
+    /// [`missing_if`](struct.WriteOptionsBuilder.html#method.missing_if), and
+    /// [`tolerance`](struct.WriteOptionsBuilder.html#method.tolerance). Returns a
+    /// [`BedError::BadValueAt`](enum.BedError.html#variant.BadValueAt) error with the location
+    /// of the first bad value, in SNP (variant) order, whether or not
+    /// [`validate`](struct.WriteOptionsBuilder.html#method.validate) is set.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{BedError, BedErrorPlus, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("checked.bed");
+    /// let val = nd::array![[1, 0, 2], [2, -127, 3]];
+    /// let write_options = WriteOptions::builder(&output_file).i8().build(2, 3)?;
+    ///
+    /// match write_options.validate_val(&val) {
+    ///     Err(e) => match *e {
+    ///         BedErrorPlus::BedError(BedError::BadValueAt(iid_index, sid_index, value, _)) => {
+    ///             assert_eq!((iid_index, sid_index, value.as_str()), (1, 2, "3"));
+    ///         }
+    ///         _ => panic!("unexpected error"),
+    ///     },
+    ///     Ok(()) => panic!("expected an error"),
+    /// }
+    /// assert!(!output_file.exists());
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn validate_val<S: nd::Data<Elem = TVal>>(
+        &self,
+        val: &nd::ArrayBase<S, nd::Ix2>,
+    ) -> Result<(), Box<BedErrorPlus>>
+    where
+        TVal: Into<f64>,
+    {
+        let (iid_count, sid_count) = val.dim();
+        if iid_count != self.iid_count() {
+            Err(BedError::InconsistentCount(
+                "iid".into(),
+                self.iid_count(),
+                iid_count,
+            ))?;
+        }
+        if sid_count != self.sid_count() {
+            Err(BedError::InconsistentCount(
+                "sid".into(),
+                self.sid_count(),
+                sid_count,
+            ))?;
+        }
+
+        // Encode, as when writing, but discard the bytes.
+        write_columns(
+            &mut io::sink(),
+            &self.path,
+            try_div_4(iid_count, sid_count)?,
+            val,
+            self.is_a1_counted,
+            self.missing_value,
+            self.missing_if.as_ref(),
+            self.tolerance,
+            true,
+            0,
+            compute_num_threads(self.num_threads)?,
+            None,
+        )?;
+        Ok(())
+    }
+}
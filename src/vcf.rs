@@ -0,0 +1,265 @@
+use anyinput::anyinput;
+#[cfg(feature = "gzip")]
+use flate2::read::MultiGzDecoder;
+use ndarray as nd;
+use ndarray::ShapeBuilder;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Lines, Read};
+use std::path::Path;
+
+use crate::parallel::prelude::*;
+use crate::{
+    Bed, BedError, BedErrorPlus, BedWriter, Metadata, MetadataBuilder, WriteOptionsBuilder,
+    WriteSummary,
+};
+
+// The most genotype values converted at once.
+const VCF_CHUNK_VALUES: usize = 8_000_000;
+
+// One biallelic VCF record: its .bim fields and, per sample, its count of ALT alleles.
+struct VcfRecord {
+    chromosome: String,
+    sid: String,
+    bp_position: i32,
+    allele_1: String,
+    allele_2: String,
+    values: Vec<i8>,
+}
+
+// Open a VCF file, decompressing it if it is gzipped or bgzipped.
+fn open_vcf(path: &Path) -> Result<Box<dyn BufRead>, Box<BedErrorPlus>> {
+    let mut magic = [0u8; 2];
+    let magic_len = File::open(path)?.read(&mut magic)?;
+    let file = File::open(path)?;
+    if magic_len != 2 || magic != [0x1f, 0x8b] {
+        return Ok(Box::new(BufReader::new(file)));
+    }
+    // bgzip files are gzip files of many members
+    #[cfg(feature = "gzip")]
+    return Ok(Box::new(BufReader::new(MultiGzDecoder::new(file))));
+    #[cfg(not(feature = "gzip"))]
+    Err(BedError::GzipFeatureNeeded(path.to_path_buf()))?
+}
+
+// Count the ALT alleles of a GT value, for example, "0|1", or return None if any are missing.
+// A haploid call counts as homozygous.
+fn parse_gt(gt: &str) -> Result<Option<i8>, ()> {
+    let mut count = 0i8;
+    let mut ploidy = 0;
+    for allele in gt.split(['/', '|']) {
+        match allele {
+            "0" => {}
+            "1" => count += 1,
+            "." => return Ok(None),
+            _ => return Err(()),
+        }
+        ploidy += 1;
+    }
+    match ploidy {
+        1 => Ok(Some(count * 2)),
+        2 => Ok(Some(count)),
+        _ => Err(()),
+    }
+}
+
+// Parse one data line, or return None if it isn't biallelic.
+fn parse_record(line: &str, iid_count: usize, missing_value: i8) -> Result<Option<VcfRecord>, ()> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    if fields.len() != 9 + iid_count && !(iid_count == 0 && fields.len() == 8) {
+        return Err(());
+    }
+    let (chromosome, bp_position, id, reference, alternate) =
+        (fields[0], fields[1], fields[2], fields[3], fields[4]);
+    if alternate.contains(',') {
+        return Ok(None);
+    }
+    let bp_position = bp_position.parse::<i32>().map_err(|_| ())?;
+    let sid = if id == "." {
+        format!("{chromosome}:{bp_position}")
+    } else {
+        id.to_string()
+    };
+
+    let gt_index = fields
+        .get(8)
+        .and_then(|format| format.split(':').position(|key| key == "GT"));
+    let values = fields[9.min(fields.len())..]
+        .iter()
+        .map(|sample| {
+            let gt = gt_index.and_then(|gt_index| sample.split(':').nth(gt_index));
+            match gt {
+                Some(gt) => Ok(parse_gt(gt)?.unwrap_or(missing_value)),
+                None => Ok(missing_value),
+            }
+        })
+        .collect::<Result<Vec<i8>, ()>>()?;
+
+    Ok(Some(VcfRecord {
+        chromosome: chromosome.to_string(),
+        sid,
+        bp_position,
+        allele_1: alternate.to_string(),
+        allele_2: reference.to_string(),
+        values,
+    }))
+}
+
+impl Bed {
+    /// Convert a VCF file, plain or gzipped (including bgzipped), into .bed, .fam, and .bim files.
+    ///
+    /// The VCF is streamed: lines are read, and their GT fields converted in parallel, a block of
+    /// SNPs (variants) at a time, and each block is written with a
+    /// [`BedWriter`](struct.BedWriter.html) created from `write_options_builder`.
+    /// Each value is the number of ALT alleles: 0, 1, 2, or missing if any allele of the call
+    /// is `.` or there is no GT field. A haploid call counts as homozygous. Multiallelic records
+    /// are skipped.
+    ///
+    /// The VCF gives the `iid` (the sample names), `chromosome`, `bp_position`, `sid` (ID, or
+    /// `CHROM:POS` if `.`), `allele_1` (ALT), and `allele_2` (REF) metadata, as PLINK's
+    /// `--vcf` does. Metadata set in `write_options_builder` is kept instead. Returns the
+    /// [`WriteSummary`](struct.WriteSummary.html) of the .bed file written.
+    ///
+    /// Returns a [`BedError::IllFormedVcf`](enum.BedError.html#variant.IllFormedVcf) error,
+    /// and removes the .bed file, if a line can't be converted.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let vcf_file = output_folder.join("small.vcf");
+    /// std::fs::write(
+    ///     &vcf_file,
+    ///     "##fileformat=VCFv4.2\n\
+    ///      #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\ts1\ts2\n\
+    ///      1\t100\trs1\tA\tG\t.\tPASS\t.\tGT:DP\t0/1:9\t1|1:7\n\
+    ///      1\t200\t.\tC\tT\t.\tPASS\t.\tGT\t./.\t0/0\n",
+    /// )?;
+    ///
+    /// let output_file = output_folder.join("small.bed");
+    /// Bed::from_vcf(&vcf_file, &WriteOptions::builder(&output_file))?;
+    ///
+    /// let bed = Bed::new(&output_file)?;
+    /// assert_eq!(bed.read::<i8>()?, nd::array![[1, -127], [2, 0]]);
+    /// assert_eq!(bed.iid()?, nd::array!["s1", "s2"]);
+    /// assert_eq!(bed.sid()?, nd::array!["rs1", "1:200"]);
+    /// assert_eq!(bed.allele_1()?, nd::array!["G", "T"]);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[anyinput]
+    pub fn from_vcf(
+        vcf_path: AnyPath,
+        write_options_builder: &WriteOptionsBuilder<i8>,
+    ) -> Result<WriteSummary, Box<BedErrorPlus>> {
+        let mut lines = open_vcf(vcf_path)?.lines();
+        let mut line_number = 0;
+        let iid = loop {
+            line_number += 1;
+            let Some(line) = lines.next().transpose()? else {
+                Err(BedError::IllFormedVcf(line_number, vcf_path.to_path_buf()))?
+            };
+            if line.starts_with("##") {
+                continue;
+            }
+            if !line.starts_with("#CHROM") {
+                Err(BedError::IllFormedVcf(line_number, vcf_path.to_path_buf()))?;
+            }
+            break line
+                .split('\t')
+                .skip(9)
+                .map(str::to_string)
+                .collect::<Vec<_>>();
+        };
+        let iid_count = iid.len();
+
+        // The values written are ALT, that is, allele 1, counts.
+        let mut write_options_builder = write_options_builder.clone();
+        write_options_builder.count_a1();
+        let missing_value = write_options_builder.missing_value.unwrap_or(-127);
+        let mut writer = write_options_builder.bed_writer(iid_count)?;
+
+        let result = convert_records(
+            &mut lines,
+            line_number,
+            &mut writer,
+            missing_value,
+            vcf_path,
+        )
+        .and_then(|mut metadata_builder| {
+            writer.fill_metadata(&metadata_builder.iid(iid).build_no_file_check()?)
+        });
+        if let Err(e) = result {
+            // Clean up the file
+            drop(writer);
+            // unwrap always works because bed_writer needs a path
+            let _ = fs::remove_file(write_options_builder.path.as_ref().unwrap());
+            return Err(e);
+        }
+        writer.finish()
+    }
+}
+
+// Convert the VCF's data lines, a chunk at a time, and return the .bim metadata.
+fn convert_records(
+    lines: &mut Lines<Box<dyn BufRead>>,
+    mut line_number: usize,
+    writer: &mut BedWriter<i8>,
+    missing_value: i8,
+    path: &Path,
+) -> Result<MetadataBuilder, Box<BedErrorPlus>> {
+    let iid_count = writer.iid_count();
+    let mut chromosome = Vec::new();
+    let mut sid = Vec::new();
+    let mut bp_position = Vec::new();
+    let mut allele_1 = Vec::new();
+    let mut allele_2 = Vec::new();
+    let chunk_len = (VCF_CHUNK_VALUES / iid_count.max(1)).max(1);
+    let mut chunk: Vec<(usize, String)> = Vec::with_capacity(chunk_len);
+    loop {
+        chunk.clear();
+        for line in lines.by_ref() {
+            line_number += 1;
+            let line = line?;
+            if !line.is_empty() {
+                chunk.push((line_number, line));
+                if chunk.len() == chunk_len {
+                    break;
+                }
+            }
+        }
+        if chunk.is_empty() {
+            break;
+        }
+
+        let records = chunk
+            .par_iter()
+            .map(|(line_number, line)| {
+                parse_record(line, iid_count, missing_value)
+                    .map_err(|()| BedError::IllFormedVcf(*line_number, path.to_path_buf()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let records = records.into_iter().flatten().collect::<Vec<_>>();
+
+        let mut val = nd::Array2::<i8>::zeros((iid_count, records.len()).f());
+        for (mut column, record) in val.axis_iter_mut(nd::Axis(1)).zip(records) {
+            column.assign(&nd::ArrayView1::from(&record.values));
+            chromosome.push(record.chromosome);
+            sid.push(record.sid);
+            bp_position.push(record.bp_position);
+            allele_1.push(record.allele_1);
+            allele_2.push(record.allele_2);
+        }
+        writer.write_block(&val)?;
+    }
+
+    let mut metadata_builder = Metadata::builder();
+    metadata_builder
+        .chromosome(chromosome)
+        .sid(sid)
+        .bp_position(bp_position)
+        .allele_1(allele_1)
+        .allele_2(allele_2);
+    Ok(metadata_builder)
+}
@@ -0,0 +1,225 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::SystemTime;
+
+use crate::{Bed, BedBuilder, BedError, BedErrorPlus};
+
+/// What a watched [`Bed`](struct.Bed.html) does when its .bed, .fam, or .bim file changes.
+///
+/// Set with [`BedBuilder::on_stale`](struct.BedBuilder.html#method.on_stale).
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash, Default)]
+pub enum StalePolicy {
+    /// Forget the counts and metadata read so far, so they are read again from
+    /// the changed files (default).
+    #[default]
+    Refresh,
+    /// Return a [`BedError::StaleFile`](enum.BedError.html#variant.StaleFile) error.
+    Error,
+}
+
+// A file's length and modification time, or None if it doesn't exist.
+type FileStamp = Option<(u64, SystemTime)>;
+
+fn file_stamp(path: &Path) -> Result<FileStamp, Box<BedErrorPlus>> {
+    match fs::metadata(path) {
+        Ok(metadata) => Ok(Some((metadata.len(), metadata.modified()?))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// The files of a watched Bed, as they were when last checked, the Bed as built, and, once
+// the files change, the refreshed Bed that reads and accessors use from then on.
+#[derive(Debug, Clone)]
+pub(crate) struct Watch {
+    stamps: Vec<(PathBuf, FileStamp)>,
+    fresh: Box<Bed>,
+    next: OnceLock<Box<Bed>>,
+}
+
+impl Watch {
+    pub(crate) fn new(bed: &Bed) -> Result<Watch, Box<BedErrorPlus>> {
+        let fresh = Box::new(bed.clone());
+        let stamps = [bed.path.clone(), bed.fam_path(), bed.bim_path()]
+            .into_iter()
+            .map(|path| {
+                let stamp = file_stamp(&path)?;
+                Ok((path, stamp))
+            })
+            .collect::<Result<Vec<_>, Box<BedErrorPlus>>>()?;
+        Ok(Watch {
+            stamps,
+            fresh,
+            next: OnceLock::new(),
+        })
+    }
+
+    // The first file that has changed, if any.
+    fn changed_path(&self) -> Result<Option<&PathBuf>, Box<BedErrorPlus>> {
+        for (path, stamp) in &self.stamps {
+            if file_stamp(path)? != *stamp {
+                return Ok(Some(path));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl BedBuilder {
+    /// Check, before each read, whether the .bed, .fam, or .bim file has changed since
+    /// it was opened.
+    ///
+    /// A long-running server may keep a [`Bed`](struct.Bed.html) open while its files are
+    /// rewritten. With watching on, each read first compares the files' lengths and modification
+    /// times with those seen when the `Bed` was built (or last refreshed). On a change, by default,
+    /// the read reads the counts and metadata again, once, and later reads and accessors, such as
+    /// [`iid`](struct.Bed.html#method.iid), use them. See
+    /// [`Bed::refresh`](struct.Bed.html#method.refresh) and
+    /// [`on_stale`](struct.BedBuilder.html#method.on_stale) for the alternative. Defaults to `false`.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("live.bed");
+    /// WriteOptions::builder(&output_file).write(&nd::array![[0, 1], [2, 0]])?;
+    ///
+    /// let bed = Bed::builder(&output_file).watch(true).build()?;
+    /// assert_eq!(bed.read::<i8>()?.dim(), (2, 2));
+    ///
+    /// // The files are rewritten with another individual
+    /// WriteOptions::builder(&output_file).write(&nd::array![[0, 1], [2, 0], [1, 1]])?;
+    /// assert_eq!(bed.read::<i8>()?.dim(), (3, 2));
+    /// assert_eq!(bed.iid_count()?, 3);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[must_use]
+    pub fn watch(mut self, is_watched: bool) -> Self {
+        self.is_watched = Some(is_watched);
+        self
+    }
+
+    /// Set what a watched [`Bed`](struct.Bed.html) does when its files change.
+    ///
+    /// By default, [`StalePolicy::Refresh`](enum.StalePolicy.html#variant.Refresh) reads the
+    /// counts and metadata again. Setting a policy also turns on
+    /// [`watch`](struct.BedBuilder.html#method.watch).
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, StalePolicy, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("live.bed");
+    /// WriteOptions::builder(&output_file).write(&nd::array![[0, 1], [2, 0]])?;
+    ///
+    /// let bed = Bed::builder(&output_file).on_stale(StalePolicy::Error).build()?;
+    /// WriteOptions::builder(&output_file).write(&nd::array![[0, 1], [2, 0], [1, 1]])?;
+    /// assert!(bed.read::<i8>().is_err());
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    #[must_use]
+    pub fn on_stale(mut self, policy: StalePolicy) -> Self {
+        self.is_watched = Some(true);
+        self.stale_policy = Some(policy);
+        self
+    }
+}
+
+impl Bed {
+    /// If this `Bed` is watched and its files have changed, forget the counts and metadata
+    /// read so far, so that they are read again from the changed files. Returns `true` if
+    /// this `Bed` changed. Attached scores are kept.
+    ///
+    /// Reads take `&self`, so, with the default
+    /// [`StalePolicy::Refresh`](enum.StalePolicy.html#variant.Refresh), a read of changed files
+    /// stores the refreshed counts and metadata alongside the old ones, which references
+    /// returned earlier may still use. Later reads and accessors use the refreshed ones. Call
+    /// `refresh` to check the files without reading and to drop the old counts and metadata.
+    /// Ignores the stale policy.
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray as nd;
+    /// use bed_reader::{Bed, WriteOptions};
+    ///
+    /// let output_folder = temp_testdir::TempDir::default();
+    /// let output_file = output_folder.join("live.bed");
+    /// WriteOptions::builder(&output_file).write(&nd::array![[0, 1], [2, 0]])?;
+    ///
+    /// let mut bed = Bed::builder(&output_file).watch(true).build()?;
+    /// assert_eq!(bed.iid_count()?, 2);
+    ///
+    /// // The files are rewritten with another individual
+    /// WriteOptions::builder(&output_file).write(&nd::array![[0, 1], [2, 0], [1, 1]])?;
+    /// assert!(bed.refresh()?);
+    /// assert_eq!(bed.iid_count()?, 3);
+    /// # use bed_reader::BedErrorPlus;
+    /// # Ok::<(), Box<BedErrorPlus>>(())
+    /// ```
+    pub fn refresh(&mut self) -> Result<bool, Box<BedErrorPlus>> {
+        let is_settled = self.settle();
+        let Some(watch) = &self.watch else {
+            return Ok(is_settled);
+        };
+        if watch.changed_path()?.is_none() {
+            return Ok(is_settled);
+        }
+        *self = self.refreshed()?;
+        Ok(true)
+    }
+
+    // The Bed refreshed when this watched Bed's files last changed, if they have.
+    pub(crate) fn latest(&self) -> Option<&Bed> {
+        let mut latest = self.watch.as_ref()?.next.get()?;
+        while let Some(next) = latest.watch.as_ref().and_then(|watch| watch.next.get()) {
+            latest = next;
+        }
+        Some(latest)
+    }
+
+    // Make the latest refreshed Bed, if any, this Bed. Returns true if there was one.
+    pub(crate) fn settle(&mut self) -> bool {
+        let mut is_settled = false;
+        while let Some(next) = self.watch.as_mut().and_then(|watch| watch.next.take()) {
+            *self = *next;
+            is_settled = true;
+        }
+        is_settled
+    }
+
+    // If watched, before a read, handle any change to the files. With StalePolicy::Refresh,
+    // returns the latest refreshed Bed to read from, refreshing it, once, if the files have
+    // changed again.
+    pub(crate) fn check_watch(&self) -> Result<Option<&Bed>, Box<BedErrorPlus>> {
+        let current = self.latest().unwrap_or(self);
+        let Some(watch) = &current.watch else {
+            return Ok(None);
+        };
+        let Some(changed_path) = watch.changed_path()? else {
+            return Ok(self.latest());
+        };
+        match self.stale_policy {
+            StalePolicy::Error => Err(BedError::StaleFile(changed_path.clone()))?,
+            StalePolicy::Refresh => {
+                let fresh = current.refreshed()?;
+                Ok(Some(watch.next.get_or_init(|| Box::new(fresh))))
+            }
+        }
+    }
+
+    // The Bed as built, with the scores attached since, watching the files as they are now.
+    fn refreshed(&self) -> Result<Bed, Box<BedErrorPlus>> {
+        // unwrap always works because only watched Beds are refreshed
+        let mut fresh = (*self.watch.as_ref().unwrap().fresh).clone();
+        fresh.scores.clone_from(&self.scores);
+        fresh.watch = Some(Watch::new(&fresh)?);
+        Ok(fresh)
+    }
+}
@@ -0,0 +1,107 @@
+use ndarray as nd;
+use std::time::Duration;
+
+/// What happened while writing a .bed file, returned by
+/// [`WriteOptionsBuilder::write_with_summary`](struct.WriteOptionsBuilder.html#method.write_with_summary)
+/// and [`Bed::write_with_summary`](struct.Bed.html#method.write_with_summary).
+///
+/// Genotype counts are of the values given, before any
+/// [`count_a2`](struct.WriteOptionsBuilder.html#method.count_a2) re-encoding.
+/// Timings cover the .bed file only, not the .fam and .bim files.
+///
+/// # Example
+/// ```
+/// use ndarray as nd;
+/// use bed_reader::WriteOptions;
+///
+/// let output_folder = temp_testdir::TempDir::default();
+/// let output_file = output_folder.join("summary.bed");
+/// let val = nd::array![[1, 0, -127, 0], [2, 0, -127, 2], [0, 1, 2, 0]];
+/// let summary = WriteOptions::builder(&output_file).write_with_summary(&val)?;
+///
+/// assert_eq!(summary.genotype_counts(), [5, 2, 3]);
+/// assert_eq!(summary.missing_count(), 2);
+/// assert_eq!(summary.missing_counts(), nd::array![0, 0, 2, 0]);
+/// assert_eq!(summary.bytes_written(), 7);
+/// println!("{:.0} SNPs per second", summary.sids_per_second());
+/// # use bed_reader::BedErrorPlus;
+/// # Ok::<(), Box<BedErrorPlus>>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct WriteSummary {
+    // Per SNP, the number of 0, 1, 2, and missing values
+    value_counts: nd::Array2<usize>,
+    snapped_counts: nd::Array1<usize>,
+    bytes_written: u64,
+    elapsed: Duration,
+}
+
+impl WriteSummary {
+    pub(crate) fn new(
+        value_counts: nd::Array2<usize>,
+        snapped_counts: nd::Array1<usize>,
+        bytes_written: u64,
+        elapsed: Duration,
+    ) -> Self {
+        Self {
+            value_counts,
+            snapped_counts,
+            bytes_written,
+            elapsed,
+        }
+    }
+
+    /// The number of values, over all SNPs (variants), snapped to 0, 1, or 2 because they
+    /// were within the [`tolerance`](struct.WriteOptionsBuilder.html#method.tolerance).
+    #[must_use]
+    pub fn snapped_count(&self) -> usize {
+        self.snapped_counts.sum()
+    }
+
+    /// The number of values snapped in each SNP (variant) written.
+    #[must_use]
+    pub fn snapped_counts(&self) -> &nd::Array1<usize> {
+        &self.snapped_counts
+    }
+
+    /// The number of 0, 1, and 2 values written, over all SNPs (variants).
+    ///
+    /// Snapped values count as the genotype they were snapped to.
+    #[must_use]
+    pub fn genotype_counts(&self) -> [usize; 3] {
+        [0, 1, 2].map(|genotype_i| self.value_counts.column(genotype_i).sum())
+    }
+
+    /// The number of missing values written, over all SNPs (variants).
+    #[must_use]
+    pub fn missing_count(&self) -> usize {
+        self.value_counts.column(3).sum()
+    }
+
+    /// The number of missing values written in each SNP (variant).
+    #[must_use]
+    pub fn missing_counts(&self) -> nd::ArrayView1<'_, usize> {
+        self.value_counts.column(3)
+    }
+
+    /// The number of bytes written to the .bed file, including its header.
+    #[must_use]
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// The time taken to write the .bed file.
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// The number of SNPs (variants) written per second.
+    ///
+    /// Not finite if the write took no measurable time.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn sids_per_second(&self) -> f64 {
+        self.snapped_counts.len() as f64 / self.elapsed.as_secs_f64()
+    }
+}
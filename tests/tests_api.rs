@@ -1,28 +1,92 @@
 use bed_reader::allclose;
 use bed_reader::assert_eq_nan;
 use bed_reader::assert_error_variant;
+use bed_reader::bed_dot;
+#[cfg(feature = "rand")]
+use bed_reader::folds;
+use bed_reader::format;
+#[cfg(feature = "checksum")]
+use bed_reader::match_samples;
+#[cfg(feature = "rand")]
+use bed_reader::pca;
+#[cfg(feature = "rand")]
+use bed_reader::permute;
+use bed_reader::qc;
+use bed_reader::reencode_allele_orientation;
 use bed_reader::sample_bed_file;
 use bed_reader::sample_file;
 use bed_reader::sample_files;
+use bed_reader::standardize;
+use bed_reader::standardize_with_stats;
+use bed_reader::to_i8_checked;
+use bed_reader::Agg;
 use bed_reader::Bed;
+#[cfg(feature = "checksum")]
+use bed_reader::BedCache;
 use bed_reader::BedError;
 use bed_reader::BedErrorPlus;
+use bed_reader::BedPool;
+use bed_reader::BedSource;
+use bed_reader::BedWriter;
+use bed_reader::BlockStage;
+use bed_reader::ChromosomeScheme;
+#[cfg(feature = "checksum")]
+use bed_reader::ColumnChecksums;
+use bed_reader::CountMismatchPolicy;
+use bed_reader::Dist;
+use bed_reader::DotOptions;
+use bed_reader::DuplicateIidPolicy;
+use bed_reader::DuplicateSidPolicy;
+use bed_reader::FeatureEncoding;
+use bed_reader::FeatureMissing;
+use bed_reader::FeatureOptions;
+use bed_reader::FieldDiff;
+use bed_reader::FieldMode;
+use bed_reader::FileMetadataSource;
+use bed_reader::GrmOptions;
+use bed_reader::GwasDataset;
+use bed_reader::IdPolicy;
+use bed_reader::IidIndex;
+use bed_reader::ImportSorter;
+use bed_reader::InternedStrings;
 use bed_reader::Metadata;
+use bed_reader::MetadataArray;
+#[cfg(feature = "serde")]
+use bed_reader::MetadataDiff;
 use bed_reader::MetadataFields;
+use bed_reader::MetadataSource;
+use bed_reader::MissingPolicy;
+use bed_reader::OperationLog;
+use bed_reader::OutOfRangePolicy;
 use bed_reader::ReadOptions;
+#[cfg(feature = "serde")]
+use bed_reader::ReadOptionsSnapshot;
+use bed_reader::ReadStrategy;
+use bed_reader::Region;
+use bed_reader::RegionSet;
+use bed_reader::ReportFormat;
+use bed_reader::SharedIndex;
+use bed_reader::SidAnnotation;
+use bed_reader::SidFilter;
+use bed_reader::SidIndex;
 use bed_reader::SliceInfo1;
+use bed_reader::SnpStats;
+use bed_reader::StalePolicy;
+use bed_reader::StatsOptions;
 use bed_reader::WriteOptions;
 use ndarray as nd;
 use ndarray::s;
 use ndarray_rand::{rand::prelude::StdRng, rand::SeedableRng, rand_distr::Uniform, RandomExt};
 use std::collections::HashSet;
 use std::panic::catch_unwind;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use temp_testdir::TempDir;
 
 #[test]
 fn rusty_bed1() -> Result<(), Box<BedErrorPlus>> {
     let file = sample_bed_file("plink_sim_10s_100v_10pmiss.bed")?;
-    let mut bed = Bed::new(&file)?;
+    let bed = Bed::new(&file)?;
     let val = bed.read::<i8>()?;
     let mean = val.mapv(|elem| elem as f64).mean().unwrap();
     assert!(mean == -13.142); // really shouldn't do mean on data where -127 represents missing
@@ -110,7 +174,7 @@ fn bad_header() -> Result<(), Box<BedErrorPlus>> {
 #[test]
 fn doc_test_test() -> Result<(), Box<BedErrorPlus>> {
     let file_name = sample_bed_file("small.bed")?;
-    let mut bed = Bed::new(file_name)?;
+    let bed = Bed::new(file_name)?;
     let val = bed.read::<f64>()?;
     assert_eq_nan(
         &val,
@@ -160,7 +224,7 @@ fn open_examples() -> Result<(), Box<BedErrorPlus>> {
     //     >>> del bed  # optional: delete bed object
 
     let file_name = sample_bed_file("small.bed")?;
-    let mut bed = Bed::new(&file_name)?;
+    let bed = Bed::new(&file_name)?;
     println!("{:?}", bed.iid()?);
     println!("{:?}", bed.sid()?);
     println!("{:?}", bed.read::<f64>()?);
@@ -201,7 +265,7 @@ fn open_examples() -> Result<(), Box<BedErrorPlus>> {
     //     >>> print(bed.sid) # same as before
     //     ['sid1' 'sid2' 'sid3' 'sid4']
 
-    let mut bed = Bed::builder(&file_name)
+    let bed = Bed::builder(&file_name)
         .iid(["sample1", "sample2", "sample3"])
         .build()?;
     println!("{:?}", bed.iid()?);
@@ -237,7 +301,7 @@ fn open_examples() -> Result<(), Box<BedErrorPlus>> {
     //      [ 2.  0. nan  2.]
     //      [ 0.  1.  2.  0.]]
 
-    let mut bed = Bed::builder(&file_name).iid_count(3).sid_count(4).build()?;
+    let bed = Bed::builder(&file_name).iid_count(3).sid_count(4).build()?;
     println!("{:?}", bed.read::<f64>()?);
 
     //  [[1.0, 0.0, NaN, 0.0],
@@ -254,7 +318,7 @@ fn open_examples() -> Result<(), Box<BedErrorPlus>> {
     //     >>> print(bed.allele_2)   # not read and not offered
     //     None
 
-    let mut bed = Bed::builder(&file_name).skip_allele_2().build()?;
+    let bed = Bed::builder(&file_name).skip_allele_2().build()?;
     println!("{:?}", bed.iid()?);
 
     let result = bed.allele_2();
@@ -272,18 +336,18 @@ fn metadata_etc() -> Result<(), Box<BedErrorPlus>> {
     // >>> with open_bed(file_name) as bed:
     // ...     print(bed.sex)
     // [1 2 0]
-    let mut bed = Bed::new(sample_bed_file("small.bed")?)?;
+    let bed = Bed::new(sample_bed_file("small.bed")?)?;
     println!("{:?}", bed.sex()?);
     // [1, 2, 0], shape=[3], strides=[1], layout=CFcf (0xf), const ndim=1
 
-    let mut bed = Bed::new(sample_bed_file("small.bed")?)?;
+    let bed = Bed::new(sample_bed_file("small.bed")?)?;
     println!("{:?}", bed.cm_position()?);
     // [100.4, 2000.5, 4000.7, 7000.9], shape=[4], strides=[1], layout=CFcf (0xf), const ndim=1
 
     println!("{:?}", bed.bp_position()?);
     // [1, 100, 1000, 1004], shape=[4], strides=[1], layout=CFcf (0xf), const ndim=1
 
-    let mut bed = Bed::new(sample_bed_file("small.bed")?)?;
+    let bed = Bed::new(sample_bed_file("small.bed")?)?;
     println!("{:?}", bed.fid()?);
     // ["fid1", "fid1", "fid2"], shape=[3], strides=[1], layout=CFcf (0xf), const ndim=1
 
@@ -298,7 +362,7 @@ fn metadata_etc() -> Result<(), Box<BedErrorPlus>> {
 
 #[test]
 fn hello_father() -> Result<(), Box<BedErrorPlus>> {
-    let mut bed = Bed::builder(sample_bed_file("small.bed")?)
+    let bed = Bed::builder(sample_bed_file("small.bed")?)
         .father(["f1", "f2", "f3"])
         .skip_mother()
         .build()?;
@@ -325,7 +389,7 @@ fn num_threads() -> Result<(), Box<BedErrorPlus>> {
 #[test]
 fn fam_and_bim() -> Result<(), Box<BedErrorPlus>> {
     let deb_maf_mib = sample_files(["small.deb", "small.maf", "small.mib"])?;
-    let mut bed = Bed::builder(&deb_maf_mib[0])
+    let bed = Bed::builder(&deb_maf_mib[0])
         .fam_path(&deb_maf_mib[1])
         .bim_path(&deb_maf_mib[2])
         .build()?;
@@ -357,7 +421,7 @@ fn readme_examples() -> Result<(), Box<BedErrorPlus>> {
     // >>> del bed
 
     let file_name = sample_bed_file("small.bed")?;
-    let mut bed = Bed::new(file_name)?;
+    let bed = Bed::new(file_name)?;
     let val = bed.read::<f64>()?;
     println!("{val:?}");
     // [[1.0, 0.0, NaN, 0.0],
@@ -405,7 +469,7 @@ fn readme_examples() -> Result<(), Box<BedErrorPlus>> {
     let unique = bed3.chromosome()?.iter().collect::<HashSet<_>>();
     println!("{unique:?}");
     // let is_5 = bed3.chromosome()?.map(|elem| elem == "5");
-    let is_5 = nd::Zip::from(bed3.chromosome()?).par_map_collect(|elem| elem == "5");
+    let is_5 = nd::Zip::from(bed3.chromosome()?).map_collect(|elem| elem == "5");
     let val3 = ReadOptions::builder()
         .sid_index(is_5)
         .f64()
@@ -482,7 +546,7 @@ fn write_docs() -> Result<(), Box<BedErrorPlus>> {
     let output_file2 = output_folder.join("small2.bed");
     let val = nd::array![[1, 0, -127, 0], [2, 0, -127, 2], [0, 1, 2, 0]];
     Bed::write(&val, &output_file2)?;
-    let mut bed2 = Bed::new(&output_file2)?;
+    let bed2 = Bed::new(&output_file2)?;
     println!("{:?}", bed2.chromosome()?);
     // ["0", "0", "0", "0"], shape=[4], strides=[1], layout=CFcf (0xf), const ndim=1
 
@@ -496,7 +560,7 @@ fn read_write() -> Result<(), Box<BedErrorPlus>> {
     // properties = bed.properties
 
     let file_name = sample_bed_file("small.bed")?;
-    let mut bed = Bed::new(file_name)?;
+    let bed = Bed::new(file_name)?;
     let val = bed.read::<f64>()?;
     let metadata = bed.metadata()?;
     println!("{metadata:?}");
@@ -533,7 +597,7 @@ fn read_write() -> Result<(), Box<BedErrorPlus>> {
     // with open_bed(output_file, fam_filepath=fam_file, bim_filepath=bim_file) as deb:
     // val2 = deb.read()
     // properties2 = deb.properties
-    let mut deb = Bed::builder(&output_file)
+    let deb = Bed::builder(&output_file)
         .fam_path(&fam_file)
         .bim_path(&bim_file)
         .build()?;
@@ -606,7 +670,7 @@ fn nd_slice() -> Result<(), Box<BedErrorPlus>> {
 
 #[test]
 fn skip_coverage() -> Result<(), Box<BedErrorPlus>> {
-    let mut bed = Bed::builder(sample_bed_file("small.bed")?)
+    let bed = Bed::builder(sample_bed_file("small.bed")?)
         .skip_fid()
         .skip_iid()
         .skip_father()
@@ -628,7 +692,7 @@ fn skip_coverage() -> Result<(), Box<BedErrorPlus>> {
 #[test]
 fn into_iter() -> Result<(), Box<BedErrorPlus>> {
     let file_name = sample_bed_file("small.bed")?;
-    let mut bed = Bed::builder(file_name)
+    let bed = Bed::builder(file_name)
         .fid(["sample1", "sample2", "sample3"])
         .iid(["sample1", "sample2", "sample3"])
         .father(["sample1", "sample2", "sample3"])
@@ -708,7 +772,7 @@ fn counts_and_files() -> Result<(), Box<BedErrorPlus>> {
     let file_name = sample_bed_file("small.bed")?;
 
     // We give the wrong number for iid_count and then expect an error
-    let mut bed = Bed::builder(&file_name)
+    let bed = Bed::builder(&file_name)
         .iid(["i1", "i2", "i3", "i4"])
         .build()?;
     let iid_count = bed.iid_count()?;
@@ -727,7 +791,7 @@ fn counts_and_files() -> Result<(), Box<BedErrorPlus>> {
         BedErrorPlus::BedError(BedError::InconsistentCount(_, _, _))
     );
 
-    let mut bed = Bed::builder(&file_name)
+    let bed = Bed::builder(&file_name)
         .bim_path(sample_file("small.bad_bim")?)
         .build()?;
 
@@ -737,23 +801,23 @@ fn counts_and_files() -> Result<(), Box<BedErrorPlus>> {
     );
 
     // We give the wrong number for iid_count and then expect an error
-    let mut bed = Bed::builder(&file_name).iid_count(4).build()?;
+    let bed = Bed::builder(&file_name).iid_count(4).build()?;
     assert_eq!(bed.iid_count()?, 4);
     assert_error_variant!(
         bed.iid(),
         BedErrorPlus::BedError(BedError::InconsistentCount(_, _, _))
     );
 
-    let mut bed = Bed::builder(&file_name)
+    let bed = Bed::builder(&file_name)
         .bim_path(sample_file("small.bim")?)
         .build()?;
     assert_eq!(bed.iid_count()?, 3);
     assert_eq!(bed.sid_count()?, 4);
-    let mut bed = Bed::new(&file_name)?;
+    let bed = Bed::new(&file_name)?;
     assert_eq!(bed.iid_count()?, 3);
     assert_eq!(bed.sid_count()?, 4);
 
-    let mut bed = Bed::builder(&file_name).build()?;
+    let bed = Bed::builder(&file_name).build()?;
     let _ = bed.iid()?;
     let _ = bed.iid_count()?;
 
@@ -798,7 +862,7 @@ fn i8_etc() -> Result<(), Box<BedErrorPlus>> {
 #[test]
 fn fill() -> Result<(), Box<BedErrorPlus>> {
     let file_name = sample_bed_file("small.bed")?;
-    let mut bed = Bed::new(file_name)?;
+    let bed = Bed::new(file_name)?;
     let read_options = ReadOptions::builder()
         .f()
         .i8()
@@ -896,7 +960,7 @@ fn read_options_builder() -> Result<(), Box<BedErrorPlus>> {
 #[test]
 fn bed_builder() -> Result<(), Box<BedErrorPlus>> {
     let file_name = sample_bed_file("small.bed")?;
-    let mut bed = Bed::builder(&file_name).build()?;
+    let bed = Bed::builder(&file_name).build()?;
     println!("{:?}", bed.iid()?);
     println!("{:?}", bed.sid()?);
     let val = bed.read::<f64>()?;
@@ -915,13 +979,13 @@ fn bed_builder() -> Result<(), Box<BedErrorPlus>> {
 
     assert_eq_nan(&val, &nd::array![[f64::NAN], [f64::NAN], [2.0]]);
 
-    let mut bed = Bed::builder(&file_name)
+    let bed = Bed::builder(&file_name)
         .iid(["sample1", "sample2", "sample3"])
         .build()?;
     println!("{:?}", bed.iid()?); // replaced
     println!("{:?}", bed.sid()?); // same as before
 
-    let mut bed = Bed::builder(&file_name).iid_count(3).sid_count(4).build()?;
+    let bed = Bed::builder(&file_name).iid_count(3).sid_count(4).build()?;
     let val = bed.read::<f64>()?;
     assert_eq_nan(
         &val,
@@ -932,7 +996,7 @@ fn bed_builder() -> Result<(), Box<BedErrorPlus>> {
         ],
     );
 
-    let mut bed = Bed::builder(&file_name)
+    let bed = Bed::builder(&file_name)
         .skip_father()
         .skip_mother()
         .skip_sex()
@@ -1320,15 +1384,15 @@ fn set_metadata() -> Result<(), Box<BedErrorPlus>> {
         .iid(["iid1", "iid2", "iid3"])
         .sid(["sid1", "sid2", "sid3", "sid4"])
         .build()?;
-    let mut bed = Bed::builder(&file_name).metadata(&metadata).build()?;
+    let bed = Bed::builder(&file_name).metadata(&metadata).build()?;
     let metadata2 = bed.metadata()?;
     println!("{metadata2:?}");
 
-    let mut bed = Bed::new(&file_name)?;
+    let bed = Bed::new(&file_name)?;
     let metadata = bed.metadata()?;
     println!("{metadata:?}");
 
-    let mut bed = Bed::builder(&file_name).metadata(&metadata).build()?;
+    let bed = Bed::builder(&file_name).metadata(&metadata).build()?;
     let metadata2 = bed.metadata()?;
     println!("{metadata2:?}");
 
@@ -1338,7 +1402,7 @@ fn set_metadata() -> Result<(), Box<BedErrorPlus>> {
 #[test]
 fn metadata_print() -> Result<(), Box<BedErrorPlus>> {
     let file_name = sample_bed_file("small.bed")?;
-    let mut bed = Bed::new(file_name)?;
+    let bed = Bed::new(file_name)?;
 
     let fid = bed.fid()?;
     println!("{fid:?}"); // Outputs ndarray ["fid1", "fid1", "fid2"]
@@ -1560,7 +1624,7 @@ fn metadata_use() -> Result<(), Box<BedErrorPlus>> {
     // Extract metadata from a file.
     // Create a random file with the same metadata.
 
-    let mut bed = Bed::new(sample_bed_file("small.bed")?)?;
+    let bed = Bed::new(sample_bed_file("small.bed")?)?;
     let metadata = bed.metadata()?;
     let shape = bed.dim()?;
 
@@ -1631,7 +1695,7 @@ fn struct_play() -> Result<(), Box<BedErrorPlus>> {
 #[test]
 fn metadata_bed() -> Result<(), Box<BedErrorPlus>> {
     let file_name = sample_bed_file("small.bed")?;
-    let mut bed = Bed::new(file_name)?;
+    let bed = Bed::new(file_name)?;
     let metadata = bed.metadata()?;
     println!("{0:?}", metadata.iid()); // Outputs Some(["iid1", "iid2", "iid3"] ...)
     println!("{0:?}", metadata.sid()); // Outputs Some(["sid1", "sid2", "sid3", "sid4"] ...)
@@ -1650,7 +1714,7 @@ where
     let file_name = sample_bed_file("toydata.5chrom.bed")?;
 
     let result1 = catch_unwind(|| {
-        let mut bed = Bed::new(&file_name).unwrap();
+        let bed = Bed::new(&file_name).unwrap();
         let all: Vec<isize> = (0..(bed.iid_count().unwrap() as isize)).collect();
         let mut bed = Bed::new(&file_name).unwrap();
         let iid_index: &[isize] = &all[range_thing.clone()];
@@ -1695,7 +1759,7 @@ fn rt3(
     let file_name = sample_bed_file("toydata.5chrom.bed").unwrap();
 
     let result3 = catch_unwind(|| {
-        let mut bed = Bed::new(file_name).unwrap();
+        let bed = Bed::new(file_name).unwrap();
         range_thing.len(bed.iid_count().unwrap()).unwrap()
     });
     match result3 {
@@ -1711,7 +1775,7 @@ fn nds1(
     let file_name = sample_bed_file("toydata.5chrom.bed")?;
 
     let result1 = catch_unwind(|| {
-        let mut bed = Bed::new(&file_name).unwrap();
+        let bed = Bed::new(&file_name).unwrap();
         let all: nd::Array1<isize> = (0..(bed.iid_count().unwrap() as isize)).collect();
         let mut bed = Bed::new(&file_name).unwrap();
         let iid_index = &all.slice(&range_thing);
@@ -1770,7 +1834,7 @@ fn read_and_fill_with_options() -> Result<(), Box<BedErrorPlus>> {
     use ndarray as nd;
     // Read the SNPs indexed by 2.
     let file_name = sample_bed_file("small.bed")?;
-    let mut bed = Bed::new(file_name)?;
+    let bed = Bed::new(file_name)?;
     let read_options = ReadOptions::builder().sid_index(2).build()?;
     let mut val = nd::Array2::<f64>::default((3, 1));
     bed.read_and_fill_with_options(&mut val.view_mut(), &read_options)?;
@@ -1811,7 +1875,7 @@ fn bed_builder_metadata() -> Result<(), Box<BedErrorPlus>> {
         .iid(["i1", "i2", "i3"])
         .sid(["s1", "s2", "s3", "s4"])
         .build()?;
-    let mut bed = Bed::builder(&file_name)
+    let bed = Bed::builder(&file_name)
         .fid(["f1", "f2", "f3"])
         .iid(["x1", "x2", "x3"])
         .metadata(&metadata)
@@ -1821,7 +1885,7 @@ fn bed_builder_metadata() -> Result<(), Box<BedErrorPlus>> {
     println!("{0:?}", bed.sid()?); // Outputs ndarray ["s1", "s2", "s3", "s4"]
     println!("{0:?}", bed.chromosome()?); // Outputs ndarray ["1", "1", "5", "Y"]
 
-    let mut bed = Bed::builder(file_name)
+    let bed = Bed::builder(file_name)
         .skip_fid()
         .fid(["f1", "f2", "f3"])
         .iid(["x1", "x2", "x3"])
@@ -1962,7 +2026,7 @@ fn read_options_properties() -> Result<(), Box<BedErrorPlus>> {
     assert_eq!(read_options.num_threads(), None);
 
     let file_name = sample_bed_file("small.bed")?;
-    let mut bed = Bed::new(file_name)?;
+    let bed = Bed::new(file_name)?;
     let val = bed.read_with_options(&read_options)?;
 
     assert_eq_nan(&val, &nd::array![[-127, 0, 1], [-127, 2, 2], [2, 0, 0]]);
@@ -2052,7 +2116,7 @@ fn bed_inconsistent_count() -> Result<(), Box<BedErrorPlus>> {
     );
 
     // Bed: file vs file:
-    let mut bed = Bed::builder(sample_bed_file("small.bed")?)
+    let bed = Bed::builder(sample_bed_file("small.bed")?)
         .fam_path(sample_file("small.fam_bad")?)
         .skip_iid()
         .skip_father()
@@ -2077,7 +2141,7 @@ fn bed_inconsistent_count() -> Result<(), Box<BedErrorPlus>> {
     );
 
     // Bed: iid vs file
-    let mut bed = Bed::builder(sample_bed_file("small.bed")?)
+    let bed = Bed::builder(sample_bed_file("small.bed")?)
         .iid(["i1", "i2", "i3", "i4"])
         .build()?;
     let result = bed.fid();
@@ -2193,7 +2257,7 @@ fn write_options_examples() -> Result<(), Box<BedErrorPlus>> {
 #[test]
 fn parsing_metadata() -> Result<(), Box<BedErrorPlus>> {
     let bed_fam_bim = sample_files(["small.bed", "small.fam", "small.bim_bad_positions.bim"])?;
-    let mut bed = Bed::builder(&bed_fam_bim[0])
+    let bed = Bed::builder(&bed_fam_bim[0])
         .bim_path(&bed_fam_bim[2])
         .build()?;
     let result = bed.cm_position();
@@ -2242,3 +2306,6223 @@ fn metadata_iid_sid2() -> Result<(), Box<BedErrorPlus>> {
 
     Ok(())
 }
+
+#[test]
+fn metadata_report() -> Result<(), Box<BedErrorPlus>> {
+    let metadata = Metadata::builder()
+        .sex([1, 2, 0])
+        .chromosome(["1", "1", "5", "Y"])
+        .allele_1(["A", "T", "A", "T"])
+        .allele_2(["A", "C", "C", "C"])
+        .build()?;
+
+    let output_folder = TempDir::default();
+    let report_file = output_folder.join("report.tsv");
+    metadata.to_report(&report_file, ReportFormat::Tsv)?;
+
+    let report = std::fs::read_to_string(&report_file)?;
+    let lines: Vec<&str> = report.lines().collect();
+    assert_eq!(
+        lines,
+        [
+            "section\tkey\tcount",
+            "summary\tiid_count\t3",
+            "summary\tsid_count\t4",
+            "chromosome\t1\t2",
+            "chromosome\t5\t1",
+            "chromosome\tY\t1",
+            "allele\tA/A\t1",
+            "allele\tT/C\t2",
+            "allele\tA/C\t1",
+            "sex\tmale\t1",
+            "sex\tfemale\t1",
+            "sex\tunknown\t1",
+        ]
+    );
+
+    // Sections for missing fields are left out.
+    let metadata = Metadata::builder().iid(["i1", "i2"]).build()?;
+    metadata.to_report(&report_file, ReportFormat::Tsv)?;
+    let report = std::fs::read_to_string(&report_file)?;
+    assert_eq!(report, "section\tkey\tcount\nsummary\tiid_count\t2\n");
+
+    #[cfg(feature = "xlsx")]
+    {
+        let xlsx_file = output_folder.join("report.xlsx");
+        Metadata::new().to_report(&xlsx_file, ReportFormat::Xlsx)?;
+        assert!(xlsx_file.exists());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn count_mismatch_policy() -> Result<(), Box<BedErrorPlus>> {
+    let file_name = sample_bed_file("small.bed")?;
+    let output_folder = TempDir::default();
+
+    // A .bim file with one SNP too few and a .fam file with one individual too many.
+    let short_bim = output_folder.join("short.bim");
+    std::fs::write(
+        &short_bim,
+        "1\tsid1\t100.4\t1\tA\tA\n1\tsid2\t2000.5\t100\tT\tC\n5\tsid3\t4000.7\t1000\tA\tC\n",
+    )?;
+    let long_fam = output_folder.join("long.fam");
+    let mut fam = std::fs::read_to_string(sample_file("small.fam")?)?;
+    fam.push_str("fid3 iid4 0 0 1 red\n");
+    std::fs::write(&long_fam, fam)?;
+
+    // By default, a mismatch is an error.
+    let bed = Bed::builder(&file_name)
+        .bim_path(&short_bim)
+        .sid_count(4)
+        .build()?;
+    let result = bed.sid();
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::InconsistentCount(_, _, _))
+    );
+
+    // TruncateToMin exposes only the SNPs present in both.
+    let bed = Bed::builder(&file_name)
+        .bim_path(&short_bim)
+        .fam_path(&long_fam)
+        .iid_count(3)
+        .sid_count(4)
+        .count_mismatch_policy(CountMismatchPolicy::TruncateToMin)
+        .build()?;
+    assert_eq!(bed.sid()?.to_vec(), ["sid1", "sid2", "sid3"]);
+    assert_eq!(bed.iid()?.to_vec(), ["iid1", "iid2", "iid3"]);
+    assert_eq!(bed.dim()?, (3, 3));
+    let val = bed.read::<i8>()?;
+    assert_eq!(val, nd::array![[1, 0, -127], [2, 0, -127], [0, 1, 2]]);
+    assert_eq!(
+        bed.count_mismatch_notes(),
+        [
+            "sid: expected 4, found 3 in .bim file; using 3",
+            "iid: expected 3, found 4 in .fam file; using 3"
+        ]
+    );
+
+    // PadMetadata keeps the expected count and fills in defaults.
+    let bed = Bed::builder(&file_name)
+        .bim_path(&short_bim)
+        .sid_count(4)
+        .count_mismatch_policy(CountMismatchPolicy::PadMetadata)
+        .build()?;
+    assert_eq!(bed.sid()?.to_vec(), ["sid1", "sid2", "sid3", "sid4"]);
+    assert_eq!(bed.chromosome()?.to_vec(), ["1", "1", "5", "0"]);
+    assert_eq!(bed.allele_1()?.to_vec(), ["A", "T", "A", "A1"]);
+    assert_eq!(bed.read::<i8>()?.dim(), (3, 4));
+    assert_eq!(bed.count_mismatch_notes().len(), 1);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "checksum")]
+fn column_checksums() -> Result<(), Box<BedErrorPlus>> {
+    let bed = Bed::new(sample_bed_file("small.bed")?)?;
+    let older = bed.column_checksums()?;
+    assert_eq!(older.sid().to_vec(), ["sid1", "sid2", "sid3", "sid4"]);
+    assert_eq!(older.checksum().len(), 4);
+
+    // Round trip through a sidecar file.
+    let output_folder = TempDir::default();
+    let checksum_file = output_folder.join("small.bed.xxh3");
+    older.to_file(&checksum_file)?;
+    assert_eq!(ColumnChecksums::from_file(&checksum_file)?, older);
+
+    // Change a genotype in sid2 and replace sid4 with sid5.
+    let mut val = bed.read::<i8>()?;
+    val[[1, 1]] = 2;
+    let output_file = output_folder.join("small2.bed");
+    WriteOptions::builder(&output_file)
+        .sid(["sid1", "sid2", "sid3", "sid5"])
+        .write(&val)?;
+    let newer = Bed::new(&output_file)?.column_checksums()?;
+
+    let diff = older.diff(&newer);
+    assert_eq!(diff.added(), ["sid5"]);
+    assert_eq!(diff.removed(), ["sid4"]);
+    assert_eq!(diff.changed(), ["sid2"]);
+    assert!(!diff.is_empty());
+    assert!(newer.diff(&newer).is_empty());
+
+    // Ill-formed sidecar files are reported.
+    std::fs::write(&checksum_file, "sid1\n")?;
+    let result = ColumnChecksums::from_file(&checksum_file);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::MetadataFieldCount(_, _, _))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn read_panel() -> Result<(), Box<BedErrorPlus>> {
+    // Matches the general reader, including on an individual-major (mode 0) file.
+    for file_name in ["some_missing.bed", "smallmode0.bed"] {
+        let mut bed = Bed::new(sample_bed_file(file_name)?)?;
+        let panel = [0, 2, -1, 1];
+        let val = bed.read_panel(&panel)?;
+        let expected = ReadOptions::builder()
+            .sid_index(panel.as_slice())
+            .i8()
+            .read(&mut bed)?;
+        assert_eq!(val, expected);
+    }
+
+    let mut bed = Bed::new(sample_bed_file("small.bed")?)?;
+    let result = bed.read_panel(&[0, 4]);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::SidIndexTooBig(4)));
+
+    let mut val = nd::Array2::<i8>::zeros((3, 3));
+    let result = bed.read_panel_into(&[0, 1], &mut val.view_mut());
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::InvalidShape(3, 2, 3, 3))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn panel_reader() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("panel_reader.bed");
+    let val = nd::array![
+        [0, 1, 2, -127],
+        [1, -127, 0, 2],
+        [2, 2, 1, 0],
+        [-127, 0, 1, 1],
+        [1, 1, 0, 2]
+    ];
+    WriteOptions::builder(&output_file).i8().write(&val)?;
+
+    // One reader, one output array, many reads, each matching the general reader.
+    let mut bed = Bed::new(&output_file)?;
+    let panel = [3, 0, -2];
+    let expected = ReadOptions::builder()
+        .sid_index(panel.as_slice())
+        .i8()
+        .read(&mut bed)?;
+    let mut panel_reader = bed.panel_reader(&panel)?;
+    assert_eq!(panel_reader.iid_count(), 5);
+    let mut out = nd::Array2::<i8>::zeros((5, 3));
+    for _ in 0..3 {
+        out.fill(0);
+        panel_reader.read_into(&mut out.view_mut())?;
+        assert_eq!(out, expected);
+    }
+
+    let mut out = nd::Array2::<i8>::zeros((4, 3));
+    let result = panel_reader.read_into(&mut out.view_mut());
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::InvalidShape(5, 3, 4, 3))
+    );
+
+    let result = bed.panel_reader(&[0, -5]);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::SidIndexTooBig(-5)));
+
+    Ok(())
+}
+
+#[test]
+fn score_filters() -> Result<(), Box<BedErrorPlus>> {
+    let mut bed = Bed::new(sample_bed_file("small.bed")?)?;
+    bed.attach_scores("info", nd::array![0.95, 0.4, 0.81, f64::NAN])?;
+    assert_eq!(bed.scores("info").unwrap().len(), 4);
+    assert!(bed.scores("maf").is_none());
+
+    let val = ReadOptions::builder()
+        .score_at_least("info", 0.8)
+        .i8()
+        .read(&mut bed)?;
+    assert_eq!(val, nd::array![[1, -127], [2, -127], [0, 2]]);
+
+    // Filters apply after sid_index, keeping its order.
+    let read_options = ReadOptions::builder()
+        .sid_index([-2, 1, 0])
+        .score_at_least("info", 0.5)
+        .f64()
+        .build()?;
+    assert_eq!(read_options.score_filters(), [("info".to_string(), 0.5)]);
+    let mut val = nd::Array2::<f64>::zeros((3, 2));
+    bed.read_and_fill_with_options(&mut val.view_mut(), &read_options)?;
+    assert_eq_nan(
+        &val,
+        &nd::array![[f64::NAN, 1.0], [f64::NAN, 2.0], [2.0, 0.0]],
+    );
+
+    let result = ReadOptions::builder()
+        .score_at_least("maf", 0.01)
+        .i8()
+        .read(&mut bed);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::UnknownScore(_)));
+
+    let result = bed.attach_scores("short", nd::array![0.9, 0.9]);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::InconsistentCount(_, 4, 2))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn write_lazy_bim() -> Result<(), Box<BedErrorPlus>> {
+    let val = nd::array![[1, 0, -127, 0], [2, 0, -127, 2], [0, 1, 2, 0]];
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("lazy.bed");
+
+    // Lazy fields mix with array fields and defaults.
+    let write_options = WriteOptions::builder(&output_file)
+        .iid(["i1", "i2", "i3"])
+        .chromosome(["1", "1", "5", "Y"])
+        .sid_iter((1..=4).map(|i| format!("rs{i}")))
+        .cm_position_iter([0.5f32, 1.0, 1.5, 2.0])
+        .allele_1_iter(["A", "T", "A", "T"].map(String::from))
+        .build(3, 4)?;
+    assert_eq!(write_options.sid().len(), 0);
+    assert_eq!(write_options.iid().to_vec(), ["i1", "i2", "i3"]);
+    Bed::write_with_options(&val, &write_options)?;
+
+    let bed = Bed::new(&output_file)?;
+    assert_eq!(bed.sid()?.to_vec(), ["rs1", "rs2", "rs3", "rs4"]);
+    assert_eq!(bed.chromosome()?.to_vec(), ["1", "1", "5", "Y"]);
+    assert_eq!(bed.cm_position()?.to_vec(), [0.5, 1.0, 1.5, 2.0]);
+    assert_eq!(bed.bp_position()?.to_vec(), [0, 0, 0, 0]);
+    assert_eq!(bed.allele_1()?.to_vec(), ["A", "T", "A", "T"]);
+    assert_eq!(bed.allele_2()?.to_vec(), ["A2", "A2", "A2", "A2"]);
+    assert_eq!(bed.iid()?.to_vec(), ["i1", "i2", "i3"]);
+    assert_eq!(bed.read::<i8>()?, val);
+
+    // The iterator is used up by the first write.
+    let result = Bed::write_with_options(&val, &write_options);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::InconsistentCount(_, 4, 0))
+    );
+    assert!(!output_file.with_extension("bim").exists());
+
+    // Iterators must produce exactly one value per SNP.
+    let result = WriteOptions::builder(&output_file)
+        .bp_position_iter(0..5)
+        .write(&val);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::InconsistentCount(_, 4, 5))
+    );
+    let result = WriteOptions::builder(&output_file)
+        .allele_2_iter(["C".to_string()])
+        .write(&val);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::InconsistentCount(_, 4, 1))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn write_interned() -> Result<(), Box<BedErrorPlus>> {
+    let val = nd::array![[1, 0, -127, 0], [2, 0, -127, 2], [0, 1, 2, 0]];
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("interned.bed");
+
+    // Each distinct value is stored once
+    let chromosome_strs = nd::array!["1", "1", "22", "1"];
+    let chromosome = InternedStrings::new(&chromosome_strs)?;
+    assert_eq!((chromosome.len(), chromosome.distinct_count()), (4, 2));
+    assert_eq!(
+        chromosome.iter().collect::<Vec<_>>(),
+        chromosome_strs.to_vec()
+    );
+    assert_eq!(chromosome.get(2), Some("22"));
+    assert_eq!(chromosome.get(4), None);
+    assert!(InternedStrings::new(Vec::<String>::new())?.is_empty());
+
+    // Interned fields mix with array fields and defaults
+    WriteOptions::builder(&output_file)
+        .chromosome_interned(&chromosome)
+        .allele_1_interned(&InternedStrings::new(["A", "C", "A", "A"])?)
+        .bp_position([10, 20, 30, 40])
+        .write(&val)?;
+    let bed = Bed::new(&output_file)?;
+    assert_eq!(bed.chromosome()?, chromosome.to_array());
+    assert_eq!(bed.allele_1()?.to_vec(), ["A", "C", "A", "A"]);
+    assert_eq!(bed.allele_2()?.to_vec(), ["A2", "A2", "A2", "A2"]);
+    assert_eq!(bed.bp_position()?.to_vec(), [10, 20, 30, 40]);
+    assert_eq!(bed.read::<i8>()?, val);
+
+    // Columns must have exactly one value per SNP
+    let result = WriteOptions::builder(&output_file)
+        .allele_2_interned(&InternedStrings::new(["C"; 5])?)
+        .write(&val);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::InconsistentCount(_, 4, 5))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn normalize_chromosomes() -> Result<(), Box<BedErrorPlus>> {
+    let metadata = Metadata::builder()
+        .chromosome([
+            "chr1",
+            "01",
+            "1",
+            "chrX",
+            "23",
+            "MT",
+            "chrM",
+            "0",
+            "GL000192.1",
+        ])
+        .build()?;
+
+    let (ensembl, mapping) = metadata.normalize_chromosomes(ChromosomeScheme::EnsemblNumeric);
+    assert_eq!(
+        ensembl.chromosome().unwrap().to_vec(),
+        ["1", "1", "1", "X", "X", "MT", "MT", "0", "GL000192.1"]
+    );
+    assert_eq!(
+        mapping,
+        [
+            ("chr1".to_string(), "1".to_string()),
+            ("01".to_string(), "1".to_string()),
+            ("chrX".to_string(), "X".to_string()),
+            ("23".to_string(), "X".to_string()),
+            ("chrM".to_string(), "MT".to_string()),
+        ]
+    );
+
+    let (ucsc, mapping) = ensembl.normalize_chromosomes(ChromosomeScheme::UcscChrPrefix);
+    assert_eq!(
+        ucsc.chromosome().unwrap().to_vec(),
+        [
+            "chr1",
+            "chr1",
+            "chr1",
+            "chrX",
+            "chrX",
+            "chrM",
+            "chrM",
+            "0",
+            "GL000192.1"
+        ]
+    );
+    assert_eq!(mapping.len(), 3);
+
+    // Other fields are untouched and missing chromosomes are fine.
+    let metadata = Metadata::builder().iid(["i1"]).build()?;
+    let (same, mapping) = metadata.normalize_chromosomes(ChromosomeScheme::UcscChrPrefix);
+    assert_eq!(same, metadata);
+    assert!(mapping.is_empty());
+
+    assert!(ChromosomeScheme::same_chromosome("chrY", "24"));
+    assert!(ChromosomeScheme::same_chromosome("CHR2", "02"));
+    assert!(!ChromosomeScheme::same_chromosome("chrX", "XY"));
+
+    Ok(())
+}
+
+#[test]
+fn read_dosage_from_gen() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let sample_file = output_folder.join("toy.sample");
+    std::fs::write(
+        &sample_file,
+        "ID_1 ID_2 missing sex\n0 0 0 D\nf1 i1 0 1\nf2 i2 0 2\nf3 i3 0 1\n",
+    )?;
+    // Old-style lines without a chromosome column
+    let gen_file = output_folder.join("toy.gen");
+    std::fs::write(
+        &gen_file,
+        "s1 rs1 10 A G 1 0 0 0 1 0 0 0 1\n\
+         s2 rs2 20 C T 0.2 0.6 0.2 0 0 0 0.5 0.5 0\n\
+         s3 rs3 30 G A 0 0 1 0 0 1 1 0 0\n",
+    )?;
+
+    let read_options = ReadOptions::builder().f32().build()?;
+    let (val, metadata) = Bed::read_dosage_from_gen(&gen_file, &sample_file, &read_options)?;
+    assert_eq_nan(
+        &val,
+        &nd::array![[2.0, 1.0, 0.0], [1.0, f32::NAN, 0.0], [0.0, 1.5, 2.0]],
+    );
+    assert_eq!(metadata.fid().unwrap().to_vec(), ["f1", "f2", "f3"]);
+    assert_eq!(metadata.iid().unwrap().to_vec(), ["i1", "i2", "i3"]);
+    assert_eq!(metadata.sid().unwrap().to_vec(), ["rs1", "rs2", "rs3"]);
+    assert_eq!(metadata.chromosome().unwrap().to_vec(), ["0", "0", "0"]);
+    assert_eq!(metadata.bp_position().unwrap().to_vec(), [10, 20, 30]);
+    assert_eq!(metadata.allele_2().unwrap().to_vec(), ["G", "T", "A"]);
+
+    // Selection, allele 2 counting, and hard calls
+    let read_options = ReadOptions::builder()
+        .iid_index([2, 0])
+        .sid_index([-2, -1])
+        .count_a2()
+        .i8()
+        .build()?;
+    let (val, _) = Bed::read_dosage_from_gen(&gen_file, &sample_file, &read_options)?;
+    assert_eq!(val, nd::array![[0, 0], [1, 2]]);
+
+    // Lines must match the number of individuals
+    std::fs::write(&gen_file, "1 s1 rs1 10 A G 1 0 0\n")?;
+    let result = Bed::read_dosage_from_gen(&gen_file, &sample_file, &read_options);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::MetadataFieldCount(15, 9, _))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn bed_pool() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("pooled.bed");
+    let val = nd::array![[1i8, 0, -127, 0], [2, 0, -127, 2], [0, 1, 2, 0]];
+    WriteOptions::builder(&output_file).write(&val)?;
+
+    // Spellings of the same path share a handle, across threads
+    let pool = BedPool::new(Duration::ZERO);
+    let bed = pool.get(&output_file)?;
+    let other_spelling = output_folder.join(".").join("pooled.bed");
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            let other = pool.get(&other_spelling).unwrap();
+            assert!(Arc::ptr_eq(&bed, &other));
+            assert_eq!(other.read::<i8>().unwrap(), val);
+        });
+    });
+    assert_eq!(pool.len(), 1);
+
+    // A changed file gets a new handle; the old handle keeps the old data
+    WriteOptions::builder(&output_file).write(&val.slice(s![.., ..2]))?;
+    let changed = pool.get(&output_file)?;
+    assert!(!Arc::ptr_eq(&bed, &changed));
+    assert_eq!(changed.sid_count()?, 2);
+    assert_eq!(bed.sid_count()?, 4);
+
+    // Within the TTL, the cached handle is returned without checking the files
+    let pool = BedPool::new(Duration::from_secs(3600));
+    let cached = pool.get(&output_file)?;
+    WriteOptions::builder(&output_file).write(&val)?;
+    assert!(Arc::ptr_eq(&cached, &pool.get(&output_file)?));
+    assert!(pool.remove(&output_file));
+    assert!(pool.is_empty());
+
+    let result = pool.get(output_folder.join("missing.bed"));
+    assert_error_variant!(result, BedErrorPlus::IOError(_));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "rand")]
+fn permute_within_family() -> Result<(), Box<BedErrorPlus>> {
+    let fid = nd::array!["f1", "f2", "f1", "f3", "f2", "f1", "f2"].map(|s| s.to_string());
+    let pheno = nd::array![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0];
+
+    let permuted = permute::phenotype_within_family(&pheno, &fid, 1)?;
+    assert_eq!(permuted, permute::phenotype_within_family(&pheno, &fid, 1)?);
+    // Values stay within their family, and a singleton family never changes
+    for (i, value) in permuted.iter().enumerate() {
+        let from_i = pheno.iter().position(|v| v == value).unwrap();
+        assert_eq!(fid[i], fid[from_i]);
+    }
+    assert_eq!(permuted[3], 4.0);
+    // Over several seeds, some value moves
+    assert!((0..10)
+        .any(|seed| { permute::phenotype_within_family(&pheno, &fid, seed).unwrap() != pheno }));
+
+    // Genotype rows can be permuted the same way
+    let mut bed = Bed::new(sample_bed_file("small.bed")?)?;
+    let iid_index = permute::iid_index(bed.iid_count()?, 3);
+    let mut sorted = iid_index.clone();
+    sorted.sort_unstable();
+    assert_eq!(sorted, [0, 1, 2]);
+    let val = ReadOptions::builder()
+        .iid_index(&iid_index)
+        .i8()
+        .read(&mut bed)?;
+    let all = bed.read::<i8>()?;
+    for (row_i, &from_i) in iid_index.iter().enumerate() {
+        assert_eq!(val.row(row_i), all.row(from_i as usize));
+    }
+
+    let result = permute::phenotype_within_family(&pheno.slice(s![..3]).to_owned(), &fid, 1);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::InconsistentCount(_, 7, 3))
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "rand")]
+fn stratified_folds() -> Result<(), Box<BedErrorPlus>> {
+    let labels = nd::array!["a", "a", "a", "a", "a", "a", "b", "b", "b", "b"];
+    let fold_list = folds::stratified_kfold(3, &labels, 5)?;
+    assert_eq!(fold_list, folds::stratified_kfold(3, &labels, 5)?);
+    let mut tested = vec![0; labels.len()];
+    for fold in &fold_list {
+        // Sorted, disjoint, and covering every individual
+        assert!(fold.test().windows(2).all(|w| w[0] < w[1]));
+        assert!(fold.train().windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(fold.train().len() + fold.test().len(), labels.len());
+        assert!(fold.test().iter().all(|i| !fold.train().contains(i)));
+        for &i in fold.test() {
+            tested[i as usize] += 1;
+        }
+        // Sizes and label counts differ by at most one
+        assert!((3..=4).contains(&fold.test().len()));
+        let b_count = fold
+            .test()
+            .iter()
+            .filter(|&&i| labels[i as usize] == "b")
+            .count();
+        assert!((1..=2).contains(&b_count));
+    }
+    assert!(tested.iter().all(|&count| count == 1));
+
+    // Families stay together
+    let fid = nd::array!["f1", "f1", "f1", "f2", "f2", "f3", "f4", "f5", "f5", "f6"]
+        .map(|s| s.to_string());
+    let fold_list = folds::stratified_kfold_by_family(2, &labels, &fid, 5)?;
+    let mut tested = vec![0; labels.len()];
+    for fold in &fold_list {
+        for &i in fold.test() {
+            tested[i as usize] += 1;
+            assert!(fold
+                .train()
+                .iter()
+                .all(|&j| fid[j as usize] != fid[i as usize]));
+        }
+        assert!((4..=6).contains(&fold.test().len()));
+    }
+    assert!(tested.iter().all(|&count| count == 1));
+
+    // The folds' indexes read genotypes
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("folds.bed");
+    let val = nd::Array2::<i8>::from_shape_fn((10, 2), |(i, j)| ((i + j) % 3) as i8);
+    WriteOptions::builder(&output_file).write(&val)?;
+    let mut bed = Bed::new(&output_file)?;
+    let test = ReadOptions::builder()
+        .iid_index(fold_list[0].test())
+        .i8()
+        .read(&mut bed)?;
+    for (row_i, &iid_i) in fold_list[0].test().iter().enumerate() {
+        assert_eq!(test.row(row_i), val.row(iid_i as usize));
+    }
+
+    for k in [0, 1, 11] {
+        let result = folds::stratified_kfold(k, &labels, 0);
+        assert_error_variant!(
+            result,
+            BedErrorPlus::BedError(BedError::BadFoldCount(_, 10))
+        );
+    }
+    let result = folds::stratified_kfold_by_family(7, &labels, &fid, 0);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::BadFoldCount(7, 6)));
+    let result = folds::stratified_kfold_by_family(2, &labels.slice(s![..3]).to_owned(), &fid, 0);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::InconsistentCount(_, 3, 10))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn read_shared() -> Result<(), Box<BedErrorPlus>> {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Bed>();
+
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("shared.bed");
+    let val = nd::Array2::<i8>::from_shape_fn((5, 8), |(i, j)| ((i * 3 + j) % 4) as i8 - 1)
+        .mapv(|v| if v < 0 { -127 } else { v });
+    WriteOptions::builder(&output_file)
+        .sex([1, 2, 1, 2, 1])
+        .chromosome(["1", "1", "1", "1", "2", "2", "2", "2"])
+        .bp_position([1, 2, 3, 4, 1, 2, 3, 4])
+        .write(&val)?;
+
+    // Counts and metadata load on first use, through &self, and are kept
+    let bed = Bed::new(&output_file)?;
+    assert_eq!(bed.iid_count()?, 5);
+    assert_eq!(bed.sid_count()?, 8);
+    assert_eq!(bed.dim()?, (5, 8));
+    assert_eq!(bed.read::<i8>()?, val);
+    let iid = bed.iid()?;
+    assert_eq!(bed.sex()?, nd::array![1, 2, 1, 2, 1]);
+    assert!(std::ptr::eq(iid, bed.iid()?));
+    assert_eq!(bed.metadata()?.sex(), Some(&nd::array![1, 2, 1, 2, 1]));
+
+    // Many threads read from one Bed
+    let bed = Arc::new(Bed::new(&output_file)?);
+    let handles: Vec<_> = (0..8)
+        .map(|sid_i| {
+            let bed = Arc::clone(&bed);
+            std::thread::spawn(move || {
+                let read_options = ReadOptions::builder()
+                    .sid_index(sid_i)
+                    .iid_where(|row| row.sex == 2)
+                    .i8()
+                    .build()?;
+                bed.read_with_options(&read_options)
+            })
+        })
+        .collect();
+    for (sid_i, handle) in handles.into_iter().enumerate() {
+        let column = handle.join().unwrap()?;
+        assert_eq!(
+            column.column(0),
+            val.select(nd::Axis(0), &[1, 3]).column(sid_i)
+        );
+    }
+
+    // Filters that need metadata load it through &self, too
+    let read_options = ReadOptions::builder()
+        .iid_where(|row| row.sex == 2)
+        .region("2:2-3")?
+        .i8()
+        .build()?;
+    let bed = Bed::new(&output_file)?;
+    assert_eq!(
+        bed.read_with_options(&read_options)?,
+        val.select(nd::Axis(0), &[1, 3]).slice(s![.., 5..7])
+    );
+
+    // Memory-mapped reads are shared, too
+    #[cfg(feature = "mmap")]
+    {
+        let bed = Arc::new(Bed::builder(&output_file).mmap(true).build()?);
+        assert_eq!(bed.read::<i8>()?, val);
+        assert_eq!(bed.read::<i8>()?, val);
+    }
+
+    // Skipped metadata can't be used
+    let bed = Bed::builder(&output_file).skip_sex().build()?;
+    let result = bed.read_with_options(&read_options);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::CannotUseSkippedMetadata(_))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn typed_indexes() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("typed.bed");
+    let val = nd::array![[1, 0, -127, 0], [2, 0, -127, 2], [0, 1, 2, 0]];
+    WriteOptions::builder(&output_file).write(&val)?;
+    let mut bed = Bed::new(&output_file)?;
+
+    // Typed indexes select the same values as untyped ones
+    let iid_index = IidIndex::new(s![..;2]);
+    let sid_index = SidIndex::new(vec![-1, 0]);
+    assert_eq!(iid_index.index().len(3)?, 2);
+    assert_eq!(sid_index.index().to_vec(4)?, [-1, 0]);
+    let typed = ReadOptions::builder()
+        .typed_iid_index(iid_index)
+        .typed_sid_index(sid_index.clone())
+        .i8()
+        .read(&mut bed)?;
+    let untyped = ReadOptions::builder()
+        .iid_index(s![..;2])
+        .sid_index(sid_index.0)
+        .i8()
+        .read(&mut bed)?;
+    assert_eq!(typed, untyped);
+    assert_eq!(typed, nd::array![[0, 1], [0, 0]]);
+
+    // The last setter wins, typed or not
+    let val_all_iids = ReadOptions::builder()
+        .typed_iid_index(IidIndex::new(0..1))
+        .iid_index(..)
+        .i8()
+        .read(&mut bed)?;
+    assert_eq!(val_all_iids, val);
+
+    Ok(())
+}
+
+#[test]
+fn positional_drift() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("drift.bed");
+    // SNPs 6 and 7 have many missing values; SNP 7 has no calls at all
+    let val = nd::array![
+        [0, 1, 2, 0, 1, 2, -127, -127, 0, 1],
+        [1, 1, 0, 2, 0, 1, -127, -127, 1, 0],
+        [2, 0, 1, 1, 2, 0, 0, -127, 2, 2],
+    ];
+    WriteOptions::builder(&output_file).i8().write(&val)?;
+
+    let mut bed = Bed::new(&output_file)?;
+    let report = bed.positional_drift_report(3)?;
+    assert_eq!(report.window(), 3);
+    assert_eq!(report.len(), 8);
+    let expected = [
+        0.0,
+        0.0,
+        0.0,
+        0.0,
+        2.0 / 9.0,
+        5.0 / 9.0,
+        5.0 / 9.0,
+        1.0 / 3.0,
+    ];
+    for (missing_rate, expected) in report.missing_rate().iter().zip(expected) {
+        assert!((missing_rate - expected).abs() < 1e-12);
+    }
+    assert!((report.overall_missing_rate() - 1.0 / 6.0).abs() < 1e-12);
+    assert_eq!(report.flagged(0.3, 1.0), vec![5, 6]);
+    assert!(report.flagged(1.0, 1.0).is_empty());
+    assert_eq!(report.flagged(0.0, 0.0).len(), 8);
+
+    // MAF skips SNPs with no calls
+    let maf = bed.stats()?.maf().clone();
+    assert!((report.maf()[7] - (maf[8] + maf[9]) / 2.0).abs() < 1e-12);
+    let overall_maf = maf.iter().filter(|maf| !maf.is_nan()).sum::<f64>() / 9.0;
+    assert!((report.overall_maf() - overall_maf).abs() < 1e-12);
+    let report = bed.positional_drift_report(1)?;
+    assert!(report.maf()[7].is_nan());
+    assert_eq!(report.flagged(0.4, 1.0), vec![6, 7]);
+
+    for window in [0, 11] {
+        let result = bed.positional_drift_report(window);
+        assert_error_variant!(
+            result,
+            BedErrorPlus::BedError(BedError::BadDriftWindow(_, 10))
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn ffi_read() -> Result<(), Box<BedErrorPlus>> {
+    use bed_reader::ffi::*;
+    use std::ffi::{CStr, CString};
+
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("ffi.bed");
+    let val = nd::array![[1, 0, -127, 0], [2, 0, -127, 2], [0, 1, 2, 0]];
+    WriteOptions::builder(&output_file).i8().write(&val)?;
+    let path = CString::new(output_file.to_str().unwrap()).unwrap();
+
+    unsafe {
+        let mut bed = std::ptr::null_mut();
+        assert_eq!(bed_reader_open(path.as_ptr(), &mut bed), BED_READER_OK);
+        let (mut iid_count, mut sid_count) = (0, 0);
+        assert_eq!(bed_reader_iid_count(bed, &mut iid_count), BED_READER_OK);
+        assert_eq!(bed_reader_sid_count(bed, &mut sid_count), BED_READER_OK);
+        assert_eq!((iid_count, sid_count), (3, 4));
+
+        // Row-major and column-major reads of a range
+        let mut out = [0i8; 6];
+        let status = bed_reader_read_i8(bed, 1, 3, 1, 4, false, 0, out.as_mut_ptr(), 6);
+        assert_eq!(status, BED_READER_OK);
+        assert_eq!(out, [0, -127, 2, 1, 2, 0]);
+        let mut out = [0.0f64; 6];
+        let status = bed_reader_read_f64(bed, 1, 3, 1, 4, true, 1, out.as_mut_ptr(), 6);
+        assert_eq!(status, BED_READER_OK);
+        let out = nd::Array2::from_shape_vec(nd::ShapeBuilder::f((2, 3)), out.to_vec()).unwrap();
+        assert_eq_nan(&out, &nd::array![[0.0, f64::NAN, 2.0], [1.0, 2.0, 0.0]]);
+        let mut out = [0.0f32; 12];
+        let status = bed_reader_read_f32(bed, 0, 3, 0, 4, false, 0, out.as_mut_ptr(), 12);
+        assert_eq!(status, BED_READER_OK);
+        assert_eq!(out[4], 2.0);
+
+        // Failures are reported through the last error
+        let mut out = [0i8; 5];
+        let status = bed_reader_read_i8(bed, 1, 3, 1, 4, false, 0, out.as_mut_ptr(), 5);
+        assert_eq!(status, BED_READER_ERROR);
+        let needed = bed_reader_last_error(std::ptr::null_mut(), 0);
+        let mut message = vec![0; needed];
+        assert_eq!(bed_reader_last_error(message.as_mut_ptr(), needed), needed);
+        let message = CStr::from_ptr(message.as_ptr()).to_str().unwrap();
+        assert_eq!(message, "The buffer must hold 6 values, but holds 5");
+        let mut short = [1; 4];
+        bed_reader_last_error(short.as_mut_ptr(), 4);
+        assert_eq!(CStr::from_ptr(short.as_ptr()).to_str().unwrap(), "The");
+
+        let status = bed_reader_read_i8(bed, 1, 4, 1, 4, false, 0, out.as_mut_ptr(), 9);
+        assert_eq!(status, BED_READER_ERROR);
+        // A range too large to address is an error, not an overflow
+        let status = bed_reader_read_i8(bed, 0, usize::MAX, 0, 3, false, 0, out.as_mut_ptr(), 5);
+        assert_eq!(status, BED_READER_ERROR);
+        let mut message = vec![0; 100];
+        bed_reader_last_error(message.as_mut_ptr(), 100);
+        let message = CStr::from_ptr(message.as_ptr()).to_str().unwrap();
+        assert_eq!(
+            message,
+            format!("A buffer of {} by 3 values is too large", usize::MAX)
+        );
+        let status = bed_reader_iid_count(std::ptr::null_mut(), &mut iid_count);
+        assert_eq!(status, BED_READER_ERROR);
+        bed_reader_close(bed);
+        bed_reader_close(std::ptr::null_mut());
+
+        let missing = CString::new("no_such_file.bed").unwrap();
+        let mut bed = std::ptr::null_mut();
+        assert_eq!(
+            bed_reader_open(missing.as_ptr(), &mut bed),
+            BED_READER_ERROR
+        );
+        assert!(bed.is_null());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn shared_index() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let prefix = output_folder.join("farm");
+    let bed_path = prefix.with_extension("bed");
+    let val = nd::array![
+        [1, 0, -127, 0, 2, 1],
+        [2, 0, -127, 2, 0, 1],
+        [0, 1, 2, 0, 1, -127],
+        [1, 1, 1, 1, 1, 1],
+        [0, 0, 0, 2, 2, 2],
+    ];
+    WriteOptions::builder(&bed_path)
+        .chromosome(["1", "1", "2", "2", "2", "1"])
+        .write(&val)?;
+
+    let result = Bed::open_shared(&prefix);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::StaleSharedIndex(_))
+    );
+
+    let bed = Bed::new(&bed_path)?;
+    let written = bed.write_shared_index()?;
+    assert!(bed.shared_index_path().exists());
+    let index = SharedIndex::load(&prefix)?;
+    assert_eq!(index, written);
+    assert_eq!(SharedIndex::load(&bed_path)?, index);
+    assert_eq!((index.iid_count(), index.sid_count()), (5, 6));
+    assert_eq!(index.mode(), format::Mode::SnpMajor);
+    assert_eq!(index.column_len(), 2);
+    assert_eq!(index.column_offset(4), 11);
+    assert_eq!(index.sid_ranges("1"), vec![0..2, 5..6]);
+    assert_eq!(index.sid_ranges("2"), vec![2..5]);
+    assert!(index.sid_ranges("3").is_empty());
+    assert_eq!(index.chromosome_runs().len(), 3);
+
+    // One reader per chromosome, each with its own Bed
+    let handles: Vec<_> = ["1", "2"]
+        .into_iter()
+        .map(|chromosome| {
+            let prefix = prefix.clone();
+            std::thread::spawn(move || -> Result<_, Box<BedErrorPlus>> {
+                let sid_index: Vec<isize> = SharedIndex::load(&prefix)?
+                    .sid_ranges(chromosome)
+                    .into_iter()
+                    .flatten()
+                    .map(|sid_i| sid_i as isize)
+                    .collect();
+                let mut bed = Bed::open_shared(&prefix)?;
+                let val = ReadOptions::builder()
+                    .sid_index(&sid_index)
+                    .i8()
+                    .read(&mut bed)?;
+                Ok((sid_index, val))
+            })
+        })
+        .collect();
+    for handle in handles {
+        let (sid_index, chromosome_val) = handle.join().unwrap()?;
+        let sid_index: Vec<usize> = sid_index.iter().map(|&sid_i| sid_i as usize).collect();
+        assert_eq!(chromosome_val, val.select(nd::Axis(1), &sid_index));
+    }
+
+    // Changed files make the shared index stale
+    std::fs::write(prefix.with_extension("fam"), "f1 i1 0 0 0 -9\n")?;
+    let result = Bed::open_shared(&prefix);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::StaleSharedIndex(_))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn metadata_files_without_bed() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let fam_file = output_folder.join("only.fam");
+    let bim_file = output_folder.join("only.bim");
+
+    // Write each file from partial metadata, with defaults for the rest
+    let metadata = Metadata::builder()
+        .iid(["i1", "i2"])
+        .sex([1, 2])
+        .sid(["s1", "s2", "s3"])
+        .bp_position([10, 20, 30])
+        .build()?;
+    metadata.write_fam_with_defaults(&fam_file)?;
+    metadata.write_bim_with_defaults(&bim_file)?;
+    let result = metadata.write_fam(&fam_file);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::MetadataMissingForWrite(_))
+    );
+
+    let fam = Metadata::from_fam(&fam_file)?;
+    assert_eq!(
+        fam.iid(),
+        Some(&nd::array!["i1".to_string(), "i2".to_string()])
+    );
+    assert_eq!(fam.sex(), Some(&nd::array![1, 2]));
+    assert_eq!(
+        fam.father(),
+        Some(&nd::array!["0".to_string(), "0".to_string()])
+    );
+    assert!(fam.sid().is_none());
+    let bim = Metadata::from_bim(&bim_file)?;
+    assert_eq!(bim.bp_position(), Some(&nd::array![10, 20, 30]));
+    assert_eq!(bim.allele_1().unwrap()[2], "A1");
+    assert!(bim.iid().is_none());
+
+    // Round trip, unchanged
+    let both = Metadata::builder().metadata(&fam).metadata(&bim).build()?;
+    assert_eq!(both, metadata.fill(2, 3)?);
+    fam.write_fam(&fam_file)?;
+    assert_eq!(Metadata::from_fam(&fam_file)?, fam);
+
+    for metadata in [Metadata::new(), fam] {
+        let result = metadata.write_bim_with_defaults(&bim_file);
+        assert_error_variant!(
+            result,
+            BedErrorPlus::BedError(BedError::MetadataMissingForWrite(_))
+        );
+    }
+    let result = Metadata::new().write_fam_with_defaults(&fam_file);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::MetadataMissingForWrite(_))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn metadata_filter() -> Result<(), Box<BedErrorPlus>> {
+    use bed_reader::Index;
+
+    let metadata = Metadata::builder()
+        .iid(["i1", "i2", "i3"])
+        .sex([1, 2, 0])
+        .sid(["s1", "s2", "s3", "s4"])
+        .bp_position([10, 20, 30, 40])
+        .sid_annotation_string("gene", ["g1", "g2", "g3", "g4"])
+        .build()?;
+
+    let filtered = metadata.filter_iid([true, false, true])?;
+    assert_eq!(
+        filtered.iid(),
+        Some(&nd::array!["i1".to_string(), "i3".to_string()])
+    );
+    assert_eq!(filtered.sex(), Some(&nd::array![1, 0]));
+    assert!(filtered.fid().is_none());
+    assert_eq!(filtered.sid(), metadata.sid());
+
+    let filtered = metadata.filter_sid(s![..;-2])?;
+    assert_eq!(filtered.bp_position(), Some(&nd::array![40, 20]));
+    let gene = filtered
+        .sid_annotation("gene")
+        .unwrap()
+        .as_string()
+        .unwrap();
+    assert_eq!(gene, nd::array!["g4".to_string(), "g2".to_string()]);
+    assert_eq!(filtered.iid(), metadata.iid());
+
+    // The same Index filters genotypes and metadata alike
+    let index = Index::from(1..3);
+    assert_eq!(metadata.filter_sid(&index)?, metadata.filter_sid(1..3)?);
+    assert_eq!(metadata.filter_iid(..)?, metadata);
+    assert_eq!(metadata.filter_iid(-1)?.iid().unwrap()[0], "i3");
+    assert_eq!(
+        metadata
+            .filter_iid(Vec::<isize>::new())?
+            .iid()
+            .unwrap()
+            .len(),
+        0
+    );
+    assert_eq!(Metadata::new().filter_iid(5)?, Metadata::new());
+
+    let result = metadata.filter_iid(3);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::IidIndexTooBig(3)));
+    let result = metadata.filter_sid(vec![0, -5]);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::SidIndexTooBig(-5)));
+    let result = metadata.filter_iid([true, false]);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::BoolArrayVectorWrongLength(3, 2))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn read_large_bim() -> Result<(), Box<BedErrorPlus>> {
+    // Big enough to be split into chunks and parsed in parallel
+    let sid_count = 100_000;
+    let line = |i: usize| format!("{}\tsnp{i}\t{}.5\t{}\tA\tG", i % 22 + 1, i % 7, i * 10);
+    let mut text = (0..sid_count).map(line).collect::<Vec<_>>().join("\r\n");
+    text.push('\n');
+    let output_folder = TempDir::default();
+    let bim_file = output_folder.join("large.bim");
+    std::fs::write(&bim_file, &text)?;
+
+    let skip_set = HashSet::<MetadataFields>::new();
+    let (metadata, count) = Metadata::new().read_bim(&bim_file, &skip_set)?;
+    assert_eq!(count, sid_count);
+    let sid = metadata.sid().unwrap();
+    assert_eq!(sid.len(), sid_count);
+    assert!(sid.iter().enumerate().all(|(i, s)| *s == format!("snp{i}")));
+    assert_eq!(metadata.bp_position().unwrap()[sid_count - 1], 999_990);
+    assert_eq!(metadata.allele_2().unwrap()[sid_count - 1], "G");
+
+    // The first bad line, in file order, decides the error
+    let mut lines: Vec<String> = (0..sid_count).map(line).collect();
+    lines[sid_count - 10] = "1\tsnp\t0\t0\tA".to_string();
+    lines[sid_count / 2] = "1\tsnp\t0\t0\tA\tG\tC".to_string();
+    std::fs::write(&bim_file, lines.join("\n"))?;
+    let result = Metadata::new().read_bim(&bim_file, &skip_set);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::MetadataFieldCount(6, 7, _))
+    );
+
+    let mut bytes = text.into_bytes();
+    let bad_i = bytes.len() * 3 / 4;
+    bytes[bad_i] = 0xFF;
+    std::fs::write(&bim_file, bytes)?;
+    let result = Metadata::new().read_bim(&bim_file, &skip_set);
+    assert_error_variant!(result, BedErrorPlus::IOError(_));
+
+    Ok(())
+}
+
+#[test]
+fn append_samples() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("append.bed");
+    let bed = Bed::new(sample_bed_file("small.bed")?)?;
+    let metadata = bed.metadata()?;
+    let val = bed.read::<f32>()?;
+    WriteOptions::builder(&output_file)
+        .metadata(&metadata)
+        .write(&val)?;
+    let bim_before = std::fs::read(output_folder.join("append.bim"))?;
+
+    // Two new individuals cross a byte boundary (3 + 2 = 5 individuals)
+    let new_val = nd::array![[0.0, 2.0, 1.0, f32::NAN], [1.0, 1.0, 0.0, 2.0]];
+    let new_metadata = Metadata::builder()
+        .iid(["new1", "new2"])
+        .sex([1, 2])
+        .sid(metadata.sid().unwrap().iter())
+        .build()?;
+    Bed::append_samples(&output_file, &new_val, &new_metadata)?;
+
+    let appended = Bed::new(&output_file)?;
+    assert_eq!(appended.dim()?, (5, 4));
+    assert_eq_nan(
+        &appended.read::<f32>()?,
+        &nd::concatenate(nd::Axis(0), &[val.view(), new_val.view()]).unwrap(),
+    );
+    assert_eq!(
+        appended.iid()?.to_vec(),
+        ["iid1", "iid2", "iid3", "new1", "new2"]
+    );
+    assert_eq!(appended.fid()?.to_vec()[3..], ["0", "0"]);
+    assert_eq!(appended.sex()?.to_vec()[3..], [1, 2]);
+    assert_eq!(std::fs::read(output_folder.join("append.bim"))?, bim_before);
+    assert!(!output_folder.join("append.bed.append_tmp").exists());
+    assert!(!output_folder.join("append.bed.append_bak").exists());
+
+    // .bim fields given must match, and the new data must have one column per SNP
+    let other_sid = Metadata::builder().sid(["a", "b", "c", "d"]).build()?;
+    let result = Bed::append_samples(&output_file, &new_val, &other_sid);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::BimMismatch(_, _)));
+    let result = Bed::append_samples(&output_file, &new_val.slice(s![.., ..3]), &Metadata::new());
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::InconsistentCount(_, 4, 3))
+    );
+    assert_eq!(Bed::new(&output_file)?.dim()?, (5, 4));
+
+    Ok(())
+}
+
+#[test]
+fn format_layout() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    for iid_count in [0, 1, 3, 4, 5, 8, 9] {
+        for sid_count in [0, 1, 7] {
+            let output_file = output_folder.join(format!("f{iid_count}_{sid_count}.bed"));
+            let val = nd::Array2::<i8>::zeros((iid_count, sid_count));
+            Bed::write(&val, &output_file)?;
+            assert_eq!(format::read_mode(&output_file)?, format::Mode::SnpMajor);
+            assert_eq!(
+                format::expected_file_len((iid_count, sid_count), format::Mode::SnpMajor)?,
+                std::fs::metadata(&output_file)?.len()
+            );
+            let header = std::fs::read(&output_file)?[..3].to_vec();
+            assert_eq!(header, format::Mode::SnpMajor.header());
+        }
+    }
+
+    let file_name = sample_bed_file("smallmode0.bed")?;
+    let mode = format::read_mode(&file_name)?;
+    assert_eq!(mode, format::Mode::IndividualMajor);
+    let dim = Bed::new(&file_name)?.dim()?;
+    assert_eq!(
+        format::expected_file_len(dim, mode)?,
+        std::fs::metadata(&file_name)?.len()
+    );
+
+    assert_eq!(format::Mode::from_byte(2), None);
+    let result = format::expected_file_len((usize::MAX, usize::MAX), format::Mode::SnpMajor);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::IndexesTooBigForFiles(_, _))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn metadata_diff() -> Result<(), Box<BedErrorPlus>> {
+    let bed = Bed::new(sample_bed_file("small.bed")?)?;
+    let metadata_a = bed.metadata()?;
+    assert!(metadata_a.diff(&metadata_a).is_empty());
+
+    let metadata_b = Metadata::builder()
+        .iid(["iid1", "x2", "x3", "iid4"])
+        .sid(["sid1", "sid2", "sid3", "sid4"])
+        .build()?;
+    let diff = metadata_a.diff(&metadata_b);
+    let names: Vec<&str> = diff.fields().iter().map(FieldDiff::name).collect();
+    // sid is the same; the other unset fields differ
+    assert_eq!(
+        names,
+        [
+            "fid",
+            "iid",
+            "father",
+            "mother",
+            "sex",
+            "pheno",
+            "chromosome",
+            "cm_position",
+            "bp_position",
+            "allele_1",
+            "allele_2"
+        ]
+    );
+    let iid_diff = diff.field("iid").unwrap();
+    assert_eq!((iid_diff.count_a(), iid_diff.count_b()), (Some(3), Some(4)));
+    assert_eq!(iid_diff.differing_count(), 3);
+    let examples: Vec<_> = iid_diff
+        .examples()
+        .iter()
+        .map(|e| (e.index(), e.a(), e.b()))
+        .collect();
+    assert_eq!(
+        examples,
+        [
+            (1, Some("iid2"), Some("x2")),
+            (2, Some("iid3"), Some("x3")),
+            (3, None, Some("iid4"))
+        ]
+    );
+    let fid_diff = diff.field("fid").unwrap();
+    assert_eq!((fid_diff.count_a(), fid_diff.count_b()), (Some(3), None));
+    assert!(diff
+        .to_string()
+        .contains("iid: 3 of 4 entries differ (3 vs 4)"));
+
+    // Examples are capped, and NaN positions match
+    let cm_a = Metadata::builder().cm_position([f32::NAN; 10]).build()?;
+    assert!(cm_a.diff(&cm_a).is_empty());
+    let cm_b = Metadata::builder().cm_position([0.0; 10]).build()?;
+    let cm_diff = cm_a.diff(&cm_b);
+    assert_eq!(cm_diff.field("cm_position").unwrap().differing_count(), 10);
+    assert_eq!(cm_diff.field("cm_position").unwrap().examples().len(), 5);
+
+    #[cfg(feature = "serde")]
+    {
+        let json = serde_json::to_string(&diff).unwrap();
+        assert!(json.contains(r#""name":"iid""#));
+        let round_trip: MetadataDiff = serde_json::from_str(&json).unwrap();
+        assert_eq!(round_trip, diff);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn transpose_output() -> Result<(), Box<BedErrorPlus>> {
+    let mut bed = Bed::new(sample_bed_file("some_missing.bed")?)?;
+    let iid_index = [5isize, 0, -1, 3];
+    let sid_index = s![10..40;3];
+    let expected = ReadOptions::builder()
+        .iid_index(iid_index)
+        .sid_index(sid_index)
+        .f32()
+        .read(&mut bed)?;
+
+    for is_f in [true, false] {
+        let val = ReadOptions::builder()
+            .iid_index(iid_index)
+            .sid_index(sid_index)
+            .transpose_output()
+            .is_f(is_f)
+            .f32()
+            .read(&mut bed)?;
+        assert_eq!(val.is_standard_layout(), !is_f);
+        assert_eq_nan(&val, &expected.t().to_owned());
+    }
+
+    // Mode 0 files, too
+    let mut bed = Bed::new(sample_bed_file("smallmode0.bed")?)?;
+    let val = ReadOptions::builder()
+        .transpose_output()
+        .i8()
+        .read(&mut bed)?;
+    assert_eq!(val, bed.read::<i8>()?.t());
+
+    // A preallocated array must have the transposed shape
+    let mut bed = Bed::new(sample_bed_file("small.bed")?)?;
+    let mut val = nd::Array2::<i8>::zeros((4, 3));
+    ReadOptions::builder()
+        .transpose_output()
+        .read_and_fill(&mut bed, &mut val.view_mut())?;
+    assert_eq!(val, bed.read::<i8>()?.t());
+    let mut val = nd::Array2::<i8>::zeros((3, 4));
+    let result = ReadOptions::builder()
+        .transpose_output()
+        .read_and_fill(&mut bed, &mut val.view_mut());
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::InvalidShape(4, 3, 3, 4))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn aggregate_by() -> Result<(), Box<BedErrorPlus>> {
+    let mut bed = Bed::new(sample_bed_file("some_missing.bed")?)?;
+    let val = bed.read::<f64>()?;
+    let group_labels: Vec<String> = (0..bed.iid_count()?)
+        .map(|i| ["x", "y", "z"][i % 3].to_string())
+        .collect();
+
+    let (groups, mean) = bed.aggregate_by(&group_labels, Agg::MeanDosage)?;
+    let (_, count) = bed.aggregate_by(&group_labels, Agg::AlleleCount)?;
+    assert_eq!(groups.to_vec(), ["x", "y", "z"]);
+    assert_eq!(mean.dim(), (3, bed.sid_count()?));
+    for (group_i, group) in groups.iter().enumerate() {
+        let rows: Vec<usize> = (0..group_labels.len())
+            .filter(|&i| group_labels[i] == *group)
+            .collect();
+        let group_val = val.select(nd::Axis(0), &rows);
+        for (sid_i, column) in group_val.columns().into_iter().enumerate() {
+            let called: Vec<f64> = column.iter().copied().filter(|v| !v.is_nan()).collect();
+            let total: f64 = called.iter().sum();
+            assert_eq!(count[[group_i, sid_i]], total);
+            if called.is_empty() {
+                assert!(mean[[group_i, sid_i]].is_nan());
+            } else {
+                assert!((mean[[group_i, sid_i]] - total / called.len() as f64).abs() < 1e-12);
+            }
+        }
+    }
+
+    let result = bed.aggregate_by(["a", "b"], Agg::AlleleCount);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::InconsistentCount(_, 100, 2))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn zero_sized_dimensions() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    for (iid_count, sid_count) in [(0, 0), (0, 3), (3, 0)] {
+        let output_file = output_folder.join(format!("empty_{iid_count}_{sid_count}.bed"));
+        let val = nd::Array2::<i8>::zeros((iid_count, sid_count));
+        WriteOptions::builder(&output_file).write(&val)?;
+        assert_eq!(
+            std::fs::metadata(&output_file)?.len(),
+            format::expected_file_len((iid_count, sid_count), format::Mode::SnpMajor)?
+        );
+
+        let mut bed = Bed::new(&output_file)?;
+        assert_eq!(bed.dim()?, (iid_count, sid_count));
+        assert_eq!(bed.read::<i8>()?, val);
+        assert_eq!(bed.read::<f64>()?.dim(), (iid_count, sid_count));
+        let val_t = ReadOptions::builder()
+            .transpose_output()
+            .i8()
+            .read(&mut bed)?;
+        assert_eq!(val_t.dim(), (sid_count, iid_count));
+        let val_some = ReadOptions::builder()
+            .iid_index(..)
+            .sid_index(nd::s![..;2])
+            .f32()
+            .read(&mut bed)?;
+        assert_eq!(val_some.dim(), (iid_count, sid_count.div_ceil(2)));
+        assert_eq!(bed.read_panel(&[])?.dim(), (iid_count, 0));
+
+        let metadata = bed.metadata()?;
+        assert_eq!(metadata.iid().map(nd::ArrayBase::len), Some(iid_count));
+        assert_eq!(metadata.sid().map(nd::ArrayBase::len), Some(sid_count));
+        assert!(metadata.diff(&metadata).is_empty());
+        #[cfg(feature = "checksum")]
+        assert_eq!(bed.column_checksums()?.checksum().len(), sid_count);
+
+        let (groups, aggregated) = bed.aggregate_by(vec!["a"; iid_count], Agg::MeanDosage)?;
+        assert_eq!(aggregated.dim(), (groups.len(), sid_count));
+
+        let new_val = nd::Array2::<i8>::ones((2, sid_count));
+        Bed::append_samples(&output_file, &new_val, &Metadata::new())?;
+        let bed = Bed::new(&output_file)?;
+        assert_eq!(bed.dim()?, (iid_count + 2, sid_count));
+        assert_eq!(bed.read::<i8>()?.slice(nd::s![iid_count.., ..]), new_val);
+    }
+
+    // Individual-major (mode 0) files with no data
+    for (iid_count, sid_count) in [(0, 0), (0, 3), (3, 0)] {
+        let output_file = output_folder.join(format!("mode0_{iid_count}_{sid_count}.bed"));
+        std::fs::write(&output_file, format::Mode::IndividualMajor.header())?;
+        let bed = Bed::builder(&output_file)
+            .iid_count(iid_count)
+            .sid_count(sid_count)
+            .build()?;
+        assert_eq!(bed.read::<i8>()?.dim(), (iid_count, sid_count));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn dedup_sids() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("dedup.bed");
+    let val = nd::array![[1i8, 0, -127, 0], [2, 0, -127, 2], [0, 1, 2, 0]];
+    let sid = ["rs1", "rs1_2", "rs1", "rs1"];
+
+    // Without a policy, sids are written as given
+    WriteOptions::builder(&output_file).sid(sid).write(&val)?;
+    assert_eq!(Bed::new(&output_file)?.sid()?.to_vec(), sid);
+
+    WriteOptions::builder(&output_file)
+        .sid(sid)
+        .bp_position([10, 20, 30, 40])
+        .dedup_sids(DuplicateSidPolicy::KeepFirst)
+        .write(&val)?;
+    let bed = Bed::new(&output_file)?;
+    assert_eq!(bed.sid()?.to_vec(), ["rs1", "rs1_2"]);
+    assert_eq!(bed.bp_position()?.to_vec(), [10, 20]);
+    assert_eq!(bed.read::<i8>()?, val.slice(nd::s![.., ..2]));
+
+    WriteOptions::builder(&output_file)
+        .sid(sid)
+        .dedup_sids(DuplicateSidPolicy::RenameWithSuffix)
+        .write(&val)?;
+    let bed = Bed::new(&output_file)?;
+    assert_eq!(bed.sid()?.to_vec(), ["rs1", "rs1_2", "rs1_3", "rs1_4"]);
+    assert_eq!(bed.read::<i8>()?, val);
+
+    let error_file = output_folder.join("error.bed");
+    let result = WriteOptions::builder(&error_file)
+        .sid(sid)
+        .dedup_sids(DuplicateSidPolicy::Error)
+        .write(&val);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::DuplicateSid(_)));
+    assert!(!error_file.exists());
+
+    // Unique sids are unchanged
+    WriteOptions::builder(&output_file)
+        .sid(["a", "b", "c", "d"])
+        .dedup_sids(DuplicateSidPolicy::Error)
+        .write(&val)?;
+    assert_eq!(
+        Bed::new(&output_file)?.sid()?.to_vec(),
+        ["a", "b", "c", "d"]
+    );
+
+    // Streamed sids
+    WriteOptions::builder(&output_file)
+        .sid_iter(sid.map(String::from))
+        .dedup_sids(DuplicateSidPolicy::RenameWithSuffix)
+        .write(&val)?;
+    assert_eq!(
+        Bed::new(&output_file)?.sid()?.to_vec(),
+        ["rs1", "rs1_2", "rs1_3", "rs1_4"]
+    );
+    let result = WriteOptions::builder(&error_file)
+        .sid_iter(sid.map(String::from))
+        .dedup_sids(DuplicateSidPolicy::Error)
+        .write(&val);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::DuplicateSid(_)));
+    assert!(!error_file.with_extension("bim").exists());
+    let result = WriteOptions::builder(&error_file)
+        .sid_iter(sid.map(String::from))
+        .dedup_sids(DuplicateSidPolicy::KeepFirst)
+        .write(&val);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::KeepFirstWithIter));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "checksum")]
+fn use_cache() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("small.bed");
+    let bed = Bed::new(sample_bed_file("small.bed")?)?;
+    WriteOptions::builder(&output_file)
+        .metadata(&bed.metadata()?)
+        .write(&bed.read::<i8>()?)?;
+
+    // Without use_cache, no sidecar file is written
+    let bed = Bed::new(&output_file)?;
+    assert_eq!(bed.dim()?, (3, 4));
+    assert!(!bed.cache_path().exists());
+
+    let bed = Bed::builder(&output_file).use_cache(true).build()?;
+    assert_eq!(bed.dim()?, (3, 4));
+    let cache_path = bed.cache_path();
+    assert_eq!(cache_path, output_folder.join("small.bedcache"));
+    let cache: BedCache = bed.cache()?;
+    assert_eq!((cache.iid_count(), cache.sid_count()), (3, 4));
+    assert_ne!(cache.fam_digest(), cache.bim_digest());
+
+    // An up-to-date sidecar file is trusted, so an edited count shows through
+    let mut bytes = std::fs::read(&cache_path)?;
+    let iid_count_start = bytes.len() - 32;
+    bytes[iid_count_start..iid_count_start + 8].copy_from_slice(&7u64.to_le_bytes());
+    std::fs::write(&cache_path, &bytes)?;
+    let bed = Bed::builder(&output_file).use_cache(true).build()?;
+    assert_eq!(bed.iid_count()?, 7);
+
+    // A changed .fam file makes the sidecar file out of date
+    let fam_path = output_file.with_extension("fam");
+    let mut fam = std::fs::read_to_string(&fam_path)?;
+    fam.push_str("fid4 iid4 0 0 0 0\n");
+    std::fs::write(&fam_path, fam)?;
+    let bed = Bed::builder(&output_file).use_cache(true).build()?;
+    assert_eq!(bed.dim()?, (4, 4));
+    let new_cache = bed.cache()?;
+    assert_ne!(new_cache.fam_digest(), cache.fam_digest());
+    assert_eq!(new_cache.bim_digest(), cache.bim_digest());
+
+    // An unreadable sidecar file is rebuilt
+    std::fs::write(&cache_path, b"not a cache")?;
+    let bed = Bed::builder(&output_file).use_cache(true).build()?;
+    assert_eq!(bed.dim()?, (4, 4));
+    assert_eq!(bed.cache()?, new_cache);
+
+    // Counts given to the builder take precedence
+    let bed = Bed::builder(&output_file)
+        .use_cache(true)
+        .iid_count(4)
+        .build()?;
+    assert_eq!(bed.dim()?, (4, 4));
+
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn non_utf8_path() -> Result<(), Box<BedErrorPlus>> {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join(OsStr::from_bytes(b"small_\xFF.bed"));
+    assert!(output_file.to_str().is_none());
+    let bed = Bed::new(sample_bed_file("small.bed")?)?;
+    let val = bed.read::<i8>()?;
+    WriteOptions::builder(&output_file)
+        .metadata(&bed.metadata()?)
+        .write(&val)?;
+
+    #[cfg(feature = "checksum")]
+    let bed = Bed::builder(&output_file).use_cache(true).build()?;
+    #[cfg(not(feature = "checksum"))]
+    let bed = Bed::new(&output_file)?;
+    assert_eq!(bed.read::<i8>()?, val);
+    assert_eq!(bed.iid()?.len(), 3);
+    assert_eq!(bed.fam_path(), output_file.with_extension("fam"));
+
+    // Errors keep the path, unchanged
+    std::fs::write(&output_file, [0u8; 3])?;
+    let result = Bed::new(&output_file);
+    match result.map_err(|e| *e) {
+        Err(BedErrorPlus::BedError(BedError::IllFormed(path))) => assert_eq!(path, output_file),
+        other => panic!("expected IllFormed, got {other:?}"),
+    }
+    let result = Bed::write(&nd::array![[3i8]], &output_file);
+    match result.map_err(|e| *e) {
+        Err(BedErrorPlus::BedError(BedError::BadValue(path))) => assert_eq!(path, output_file),
+        other => panic!("expected BadValue, got {other:?}"),
+    }
+
+    Ok(())
+}
+
+#[cfg(windows)]
+#[test]
+fn windows_long_path() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    // canonicalize gives a verbatim (\\?\) path, which may exceed MAX_PATH
+    let mut long_folder = output_folder.canonicalize()?;
+    for _ in 0..8 {
+        long_folder.push("a".repeat(40));
+    }
+    std::fs::create_dir_all(&long_folder)?;
+    let output_file = long_folder.join("small.bed");
+    assert!(output_file.as_os_str().len() > 260);
+
+    let mut bed = Bed::new(sample_bed_file("small.bed")?)?;
+    let val = bed.read::<i8>()?;
+    WriteOptions::builder(&output_file)
+        .metadata(&bed.metadata()?)
+        .write(&val)?;
+    let mut bed = Bed::builder(&output_file).use_cache(true).build()?;
+    assert_eq!(bed.read::<i8>()?, val);
+    assert_eq!(bed.sid()?.len(), 4);
+
+    Ok(())
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn to_record_batches() -> Result<(), Box<BedErrorPlus>> {
+    use arrow_array::{Array, Int8Array, StringArray};
+    use bed_reader::{ArrowLayout, ArrowOptions};
+
+    let mut bed = Bed::new(sample_bed_file("some_missing.bed")?)?;
+    let val = ReadOptions::builder().sid_index(5..9).i8().read(&mut bed)?;
+    let iid = bed.iid()?.clone();
+    let sid = bed.sid()?.slice(nd::s![5..9]).to_owned();
+
+    // Wide: one row per individual
+    let arrow_options = ArrowOptions::builder().sid_index(5..9).build()?;
+    let batches = bed
+        .to_record_batches(30, &arrow_options)?
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(
+        batches.iter().map(|b| b.num_rows()).collect::<Vec<_>>(),
+        [30, 30, 30, 10]
+    );
+    let mut row = 0;
+    for batch in &batches {
+        assert_eq!(batch.num_columns(), 5);
+        assert_eq!(batch.schema().field(2).name(), &sid[1]);
+        let iid_column = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        for batch_row in 0..batch.num_rows() {
+            assert_eq!(iid_column.value(batch_row), iid[row]);
+            for sid_i in 0..4 {
+                let column = batch
+                    .column(sid_i + 1)
+                    .as_any()
+                    .downcast_ref::<Int8Array>()
+                    .unwrap();
+                let expected = val[(row, sid_i)];
+                if expected == -127 {
+                    assert!(column.is_null(batch_row));
+                } else {
+                    assert_eq!(column.value(batch_row), expected);
+                }
+            }
+            row += 1;
+        }
+    }
+    assert_eq!(row, 100);
+
+    // Long: one row per genotype, SNP by SNP, with batches that span SNPs
+    let arrow_options = ArrowOptions::builder()
+        .layout(ArrowLayout::Long)
+        .sid_index(5..9)
+        .build()?;
+    let mut row = 0;
+    for batch in bed.to_record_batches(70, &arrow_options)? {
+        let batch = batch?;
+        let columns: Vec<_> = (0..2)
+            .map(|i| {
+                batch
+                    .column(i)
+                    .as_any()
+                    .downcast_ref::<StringArray>()
+                    .unwrap()
+            })
+            .collect();
+        let value = batch
+            .column(2)
+            .as_any()
+            .downcast_ref::<Int8Array>()
+            .unwrap();
+        for batch_row in 0..batch.num_rows() {
+            let (sid_i, iid_i) = (row / 100, row % 100);
+            assert_eq!(columns[0].value(batch_row), sid[sid_i]);
+            assert_eq!(columns[1].value(batch_row), iid[iid_i]);
+            assert_eq!(value.is_null(batch_row), val[(iid_i, sid_i)] == -127);
+            if !value.is_null(batch_row) {
+                assert_eq!(value.value(batch_row), val[(iid_i, sid_i)]);
+            }
+            row += 1;
+        }
+    }
+    assert_eq!(row, 400);
+
+    let result = bed.to_record_batches(0, &arrow_options);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::ZeroBatchRows));
+    let arrow_options = ArrowOptions::builder().sid_index([10_000]).build()?;
+    let result = bed.to_record_batches(10, &arrow_options);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::SidIndexTooBig(10_000))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn sidecar_writer() -> Result<(), Box<BedErrorPlus>> {
+    use bed_reader::{SidecarFormat, SidecarWriter};
+
+    let output_folder = TempDir::default();
+    let mut bed = Bed::new(sample_bed_file("small.bed")?)?;
+
+    // Records out of order, by index and by sid; one SNP never recorded
+    let output_file = output_folder.join("small.tsv");
+    let mut writer = SidecarWriter::new(&mut bed, &output_file, ["beta", "p"], SidecarFormat::Tsv)?;
+    writer.record_at(3, &[0.5, 0.01])?;
+    writer.record("sid1", &[-1.5, 0.25])?;
+    writer.record_at(1, &[f64::NAN, 1.0])?;
+    assert_error_variant!(
+        writer.record("sid2", &[0.0, 0.0]),
+        BedErrorPlus::BedError(BedError::SidAlreadyRecorded(_))
+    );
+    assert_error_variant!(
+        writer.record_at(3, &[0.0, 0.0]),
+        BedErrorPlus::BedError(BedError::SidAlreadyRecorded(_))
+    );
+    assert_error_variant!(
+        writer.record("no_such_sid", &[0.0, 0.0]),
+        BedErrorPlus::BedError(BedError::UnknownSid(_))
+    );
+    assert_error_variant!(
+        writer.record_at(4, &[0.0, 0.0]),
+        BedErrorPlus::BedError(BedError::SidIndexTooBig(4))
+    );
+    assert_error_variant!(
+        writer.record_at(2, &[0.0]),
+        BedErrorPlus::BedError(BedError::InconsistentCount(_, 2, 1))
+    );
+    writer.finish()?;
+    assert_eq!(
+        std::fs::read_to_string(&output_file)?,
+        "sid\tbeta\tp\nsid1\t-1.5\t0.25\nsid2\tNaN\t1\nsid3\tNA\tNA\nsid4\t0.5\t0.01\n"
+    );
+
+    // Repeated sids can only be recorded by index
+    let dup_file = output_folder.join("dup.bed");
+    WriteOptions::builder(&dup_file)
+        .sid(["rs1", "rs2", "rs1"])
+        .write(&nd::array![[0i8, 1, 2]])?;
+    let mut dup_bed = Bed::new(&dup_file)?;
+    let output_file = output_folder.join("dup.tsv");
+    let mut writer = SidecarWriter::new(&mut dup_bed, &output_file, ["p"], SidecarFormat::Tsv)?;
+    assert_error_variant!(
+        writer.record("rs1", &[0.5]),
+        BedErrorPlus::BedError(BedError::DuplicateSid(_))
+    );
+    writer.record("rs2", &[0.5])?;
+    writer.record_at(2, &[0.25])?;
+    writer.finish()?;
+    assert_eq!(
+        std::fs::read_to_string(&output_file)?,
+        "sid\tp\nrs1\tNA\nrs2\t0.5\nrs1\t0.25\n"
+    );
+
+    #[cfg(feature = "parquet")]
+    {
+        use arrow_array::{Array, Float64Array, StringArray};
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let output_file = output_folder.join("small.parquet");
+        let mut writer =
+            SidecarWriter::new(&mut bed, &output_file, ["beta"], SidecarFormat::Parquet)?;
+        writer.record_at(2, &[2.0])?;
+        writer.record_at(0, &[1.0])?;
+        writer.finish()?;
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(std::fs::File::open(&output_file)?)?
+            .build()?;
+        let batches = reader.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(batches.len(), 1);
+        let batch = &batches[0];
+        let sid = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .unwrap();
+        assert_eq!(
+            sid.iter().map(Option::unwrap).collect::<Vec<_>>(),
+            ["sid1", "sid2", "sid3", "sid4"]
+        );
+        let beta = batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .unwrap();
+        assert_eq!(
+            beta.iter().collect::<Vec<_>>(),
+            [Some(1.0), None, Some(2.0), None]
+        );
+        assert!(beta.is_null(1));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn read_packed_columns() -> Result<(), Box<BedErrorPlus>> {
+    let mut bed = Bed::new(sample_bed_file("some_missing.bed")?)?;
+    let sid_index = [5, -1, 0, 5];
+    let (packed, layout) = bed.read_packed_columns(sid_index)?;
+    assert_eq!(layout.iid_count(), 100);
+    assert_eq!(layout.sid_count(), 4);
+    assert_eq!(layout.bytes_per_column(), 25);
+    assert_eq!(packed.len(), layout.len());
+
+    for is_a1_counted in [true, false] {
+        let expected = ReadOptions::builder()
+            .sid_index(sid_index)
+            .is_a1_counted(is_a1_counted)
+            .i8()
+            .read(&mut bed)?;
+        assert_eq!(layout.decode(&packed, is_a1_counted)?, expected);
+
+        // Decoding with the byte table, as a device kernel might, gives the same values
+        let byte_table = layout.byte_table(is_a1_counted);
+        assert_eq!(byte_table.len(), 256);
+        for (sid_i, column) in packed.chunks_exact(layout.bytes_per_column()).enumerate() {
+            for iid_i in 0..layout.iid_count() {
+                let value = byte_table[column[iid_i / 4] as usize][iid_i % 4];
+                assert_eq!(value, expected[(iid_i, sid_i)]);
+            }
+        }
+    }
+
+    // An iid count that isn't a multiple of 4 leaves padding bits
+    let mut bed = Bed::new(sample_bed_file("small.bed")?)?;
+    let (packed, layout) = bed.read_packed_columns(..)?;
+    assert_eq!((packed.len(), layout.bytes_per_column()), (4, 1));
+    assert_eq!(layout.decode(&packed, true)?, bed.read::<i8>()?);
+
+    let (packed, layout) = bed.read_packed_columns(s![..0])?;
+    assert!(packed.is_empty() && layout.is_empty());
+    assert_eq!(layout.decode(&packed, true)?.dim(), (3, 0));
+
+    assert_error_variant!(
+        bed.read_packed_columns([4]),
+        BedErrorPlus::BedError(BedError::SidIndexTooBig(4))
+    );
+    assert_error_variant!(
+        layout.decode(&[0u8], true),
+        BedErrorPlus::BedError(BedError::InconsistentCount(_, 0, 1))
+    );
+    let mut bed = Bed::new(sample_bed_file("smallmode0.bed")?)?;
+    assert_error_variant!(
+        bed.read_packed_columns(..),
+        BedErrorPlus::BedError(BedError::NotSnpMajor(_))
+    );
+    Ok(())
+}
+
+#[test]
+fn read_strategy() -> Result<(), Box<BedErrorPlus>> {
+    use bed_reader::{Index, ReadStrategy};
+
+    let mut bed = Bed::new(sample_bed_file("some_missing.bed")?)?;
+    assert_eq!(
+        ReadOptions::<i8>::builder().build()?.read_strategy(),
+        ReadStrategy::Sequential
+    );
+    for (iid_index, sid_index) in [
+        (Index::All, Index::All),
+        (Index::from([3, -1, 0]), Index::from(s![..;7])),
+        (Index::from(10..12), Index::from([99, 0, 99, -100])),
+        (Index::from(s![..0]), Index::from(5..9)),
+        (Index::All, Index::from(s![..0])),
+    ] {
+        let expected = ReadOptions::builder()
+            .iid_index(iid_index.clone())
+            .sid_index(sid_index.clone())
+            .f32()
+            .read(&mut bed)?;
+        for max_concurrent_requests in [1, 3, 1000] {
+            let val = ReadOptions::builder()
+                .iid_index(iid_index.clone())
+                .sid_index(sid_index.clone())
+                .read_strategy(ReadStrategy::BatchedPread)
+                .max_concurrent_requests(max_concurrent_requests)
+                .f32()
+                .read(&mut bed)?;
+            assert!(allclose(&val.view(), &expected.view(), 0.0, true));
+        }
+    }
+
+    let result = ReadOptions::builder()
+        .sid_index(100)
+        .read_strategy(ReadStrategy::BatchedPread)
+        .i8()
+        .read(&mut bed);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::SidIndexTooBig(100))
+    );
+
+    // Individual-major files fall back to reading sequentially
+    let mut bed = Bed::new(sample_bed_file("smallmode0.bed")?)?;
+    let val = ReadOptions::builder()
+        .read_strategy(ReadStrategy::BatchedPread)
+        .i8()
+        .read(&mut bed)?;
+    assert_eq!(val, bed.read::<i8>()?);
+
+    Ok(())
+}
+
+#[test]
+fn write_tolerance() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("tolerance.bed");
+    let val = nd::array![
+        [1.0000001f32, 0.0, 2.0],
+        [2.0, -1e-7, f32::NAN],
+        [0.0, 1.0, 1.9999999]
+    ];
+
+    // Without a tolerance, only exact values are accepted
+    let result = WriteOptions::builder(&output_file).write(&val);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::BadValue(_)));
+    assert!(!output_file.exists());
+
+    let summary = WriteOptions::builder(&output_file)
+        .tolerance(1e-6)
+        .count_a2()
+        .write_with_summary(&val)?;
+    assert_eq!(summary.snapped_count(), 3);
+    assert_eq!(summary.snapped_counts(), nd::array![1, 1, 1]);
+    let mut bed = Bed::new(&output_file)?;
+    let expected = nd::array![[1, 0, 2], [2, 0, -127], [0, 1, 2]];
+    assert_eq!(
+        ReadOptions::builder().count_a2().i8().read(&mut bed)?,
+        expected
+    );
+
+    // Exact values aren't counted as snapped
+    let summary = WriteOptions::builder(&output_file)
+        .missing_value(-127.0)
+        .tolerance(1e-6)
+        .write_with_summary(&expected.mapv(f64::from))?;
+    assert_eq!(summary.snapped_count(), 0);
+
+    // Values too far from a genotype, or near an integer that isn't a genotype, still fail
+    for bad in [1.01, 3.0000001, -0.9999999] {
+        let result = WriteOptions::builder(&output_file)
+            .tolerance(1e-6)
+            .write(&nd::array![[bad, 0.0]]);
+        assert_error_variant!(result, BedErrorPlus::BedError(BedError::BadValue(_)));
+    }
+
+    // With a non-NaN missing value, values are snapped only to 0, 1, or 2
+    let summary = WriteOptions::builder(&output_file)
+        .missing_value(-127.0)
+        .tolerance(0.25)
+        .write_with_summary(&nd::array![[-127.0, 0.8], [1.2, 2.1]])?;
+    assert_eq!(summary.snapped_counts(), nd::array![1, 2]);
+    assert_eq!(
+        Bed::new(&output_file)?.read::<i8>()?,
+        nd::array![[-127, 1], [1, 2]]
+    );
+
+    // Snapped counts follow the SNPs written
+    let summary = WriteOptions::builder(&output_file)
+        .sid(["s1", "s1", "s2"])
+        .dedup_sids(DuplicateSidPolicy::KeepFirst)
+        .tolerance(1e-6)
+        .write_with_summary(&val)?;
+    assert_eq!(summary.snapped_counts(), nd::array![1, 1]);
+
+    for bad_tolerance in [-0.1, 0.5, f64::NAN] {
+        let result = WriteOptions::builder(&output_file)
+            .tolerance(bad_tolerance)
+            .write(&val);
+        assert_error_variant!(result, BedErrorPlus::BedError(BedError::BadTolerance(_)));
+    }
+    assert_eq!(
+        WriteOptions::builder(&output_file)
+            .f32()
+            .tolerance(1e-6)
+            .build(3, 3)?
+            .tolerance(),
+        Some(1e-6)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn on_duplicate_iid() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("dup_iid.bed");
+    WriteOptions::builder(&output_file)
+        .fid(["f1", "f1", "f2", "f1", "f1", "f2"])
+        .iid(["i1", "i1_2", "i1", "i1", "i1", "i1"])
+        .write(&nd::array![[0i8], [1], [2], [0], [1], [2]])?;
+
+    // Without a policy, nothing is checked
+    let bed = Bed::new(&output_file)?;
+    assert!(bed.duplicate_iids().is_empty());
+
+    let result = Bed::builder(&output_file)
+        .on_duplicate_iid(DuplicateIidPolicy::Error)
+        .build();
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::DuplicateIid(_, _)));
+
+    let bed = Bed::builder(&output_file)
+        .on_duplicate_iid(DuplicateIidPolicy::Warn)
+        .build()?;
+    let expected_duplicates = [
+        ("f1".to_string(), "i1".to_string()),
+        ("f2".to_string(), "i1".to_string()),
+    ];
+    assert_eq!(bed.duplicate_iids(), expected_duplicates);
+    assert_eq!(bed.iid()?, nd::array!["i1", "i1_2", "i1", "i1", "i1", "i1"]);
+
+    // "i1_2" is already in use in family f1, so the repeats there become "i1_3" and "i1_4"
+    let bed = Bed::builder(&output_file)
+        .on_duplicate_iid(DuplicateIidPolicy::MakeUnique)
+        .build()?;
+    assert_eq!(bed.duplicate_iids(), expected_duplicates);
+    assert_eq!(
+        bed.iid()?,
+        nd::array!["i1", "i1_2", "i1", "i1_3", "i1_4", "i1_2"]
+    );
+    assert_eq!(bed.read::<i8>()?, nd::array![[0], [1], [2], [0], [1], [2]]);
+
+    // Ids given to the builder are checked, too
+    let bed = Bed::builder(&output_file)
+        .iid(["a", "b", "c", "d", "e", "f"])
+        .on_duplicate_iid(DuplicateIidPolicy::Error)
+        .build()?;
+    assert!(bed.duplicate_iids().is_empty());
+    let result = Bed::builder(&output_file)
+        .fid(["f"; 6])
+        .iid(["a", "b", "c", "d", "e", "a"])
+        .on_duplicate_iid(DuplicateIidPolicy::Error)
+        .build();
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::DuplicateIid(_, _)));
+
+    Ok(())
+}
+
+#[test]
+fn site_frequency_spectrum() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("sfs.bed");
+    let val = nd::array![
+        [0i8, 2, 1, 0, 2, -127],
+        [1, 2, 0, 0, 2, 0],
+        [0, 1, 1, 0, 2, 0],
+        [2, 2, 0, 0, 2, 1]
+    ];
+    WriteOptions::builder(&output_file)
+        .chromosome(["1", "1", "2", "1", "2", "2"])
+        .write(&val)?;
+
+    // Allele 1 counts are [3, 7, 2, 0, 8, missing] out of 8
+    let bed = Bed::new(&output_file)?;
+    let (sfs, skipped) = bed.site_frequency_spectrum(false)?;
+    assert_eq!(sfs, nd::array![1, 0, 1, 1, 0, 0, 0, 1, 1]);
+    assert_eq!(skipped, 1);
+    let (sfs, skipped) = bed.site_frequency_spectrum(true)?;
+    assert_eq!(sfs, nd::array![2, 1, 1, 1, 0]);
+    // Every SNP is either in the spectrum or counted as left out
+    assert_eq!(sfs.sum() + skipped, 6);
+
+    let (chromosomes, sfs, skipped) = bed.site_frequency_spectrum_by_chromosome(false)?;
+    assert_eq!(chromosomes, nd::array!["1".to_string(), "2".to_string()]);
+    assert_eq!(
+        sfs,
+        nd::array![[1, 0, 0, 1, 0, 0, 0, 1, 0], [0, 0, 1, 0, 0, 0, 0, 0, 1]]
+    );
+    assert_eq!(skipped, nd::array![0, 1]);
+    let (_, sfs, _) = bed.site_frequency_spectrum_by_chromosome(true)?;
+    assert_eq!(sfs, nd::array![[1, 1, 0, 1, 0], [1, 0, 1, 0, 0]]);
+
+    // Zero SNPs
+    let output_file = output_folder.join("sfs_empty.bed");
+    WriteOptions::builder(&output_file).write(&nd::Array2::<i8>::zeros((4, 0)))?;
+    let bed = Bed::new(&output_file)?;
+    assert_eq!(
+        bed.site_frequency_spectrum(true)?,
+        (nd::array![0, 0, 0, 0, 0], 0)
+    );
+    let (chromosomes, sfs, skipped) = bed.site_frequency_spectrum_by_chromosome(true)?;
+    assert!(chromosomes.is_empty());
+    assert_eq!(sfs.dim(), (0, 5));
+    assert!(skipped.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn count_a1_mask() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("count_a1_mask.bed");
+    let val_in = nd::array![[0i8, 2, 1, -127], [1, 2, 0, 0], [2, 1, 2, 0]];
+    WriteOptions::builder(&output_file).write(&val_in)?;
+    let mut bed = Bed::new(&output_file)?;
+
+    let mask = nd::array![true, false, false, true];
+    let val = ReadOptions::builder()
+        .count_a1_mask(mask.clone())
+        .i8()
+        .read(&mut bed)?;
+    assert_eq!(val, nd::array![[0, 0, 1, -127], [1, 0, 2, 0], [2, 1, 0, 0]]);
+
+    // The mask overrides count_a2, and is indexed by SNP in the file
+    let val = ReadOptions::builder()
+        .sid_index([-2, 0])
+        .count_a2()
+        .count_a1_mask(mask.clone())
+        .transpose_output()
+        .f64()
+        .read(&mut bed)?;
+    assert_eq!(val, nd::array![[1.0, 2.0, 0.0], [0.0, 1.0, 2.0]]);
+
+    // Missing values stay missing, even when the missing value is 0 or 2, with every
+    // read strategy
+    let expected = nd::array![[0, 2, 1, 2], [1, 2, 0, 2], [2, 1, 2, 2]];
+    for read_strategy in [
+        ReadStrategy::Sequential,
+        ReadStrategy::BatchedPread,
+        #[cfg(feature = "mmap")]
+        ReadStrategy::Mmap,
+        ReadStrategy::Scan,
+    ] {
+        let val = ReadOptions::builder()
+            .count_a1_mask(nd::array![true, true, true, false])
+            .missing_value(2)
+            .read_strategy(read_strategy)
+            .i8()
+            .read(&bed)?;
+        assert_eq!(val, expected);
+    }
+
+    let result = ReadOptions::builder()
+        .count_a1_mask(vec![true, false])
+        .i8()
+        .read(&mut bed);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::BoolArrayVectorWrongLength(4, 2))
+    );
+
+    // The same genotypes in an individual-major (mode 0) file, one byte per individual
+    let mut bytes = format::Mode::IndividualMajor.header().to_vec();
+    for row in val_in.rows() {
+        let mut byte = 0u8;
+        for (sid_i, &value) in row.iter().enumerate() {
+            let two_bits: u8 = match value {
+                0 => 3,
+                1 => 2,
+                2 => 0,
+                _ => 1,
+            };
+            byte |= two_bits << (2 * sid_i);
+        }
+        bytes.push(byte);
+    }
+    std::fs::write(&output_file, bytes)?;
+    let bed = Bed::new(&output_file)?;
+    let val = ReadOptions::builder()
+        .count_a1_mask(nd::array![true, true, true, false])
+        .missing_value(2)
+        .i8()
+        .read(&bed)?;
+    assert_eq!(val, expected);
+    let val = ReadOptions::builder()
+        .iid_index([-1, 0])
+        .sid_index([3, 1])
+        .count_a1_mask(mask.clone())
+        .i8()
+        .read(&bed)?;
+    assert_eq!(val, nd::array![[0, 1], [-127, 0]]);
+
+    Ok(())
+}
+
+#[test]
+fn write_summary() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("write_summary.bed");
+    let val = nd::array![
+        [0.0, 2.0, f64::NAN],
+        [1.0, 2.0000001, f64::NAN],
+        [2.0, 0.0, 1.0],
+        [0.0, 0.0, 1.0],
+        [1.0, 0.0, 1.0]
+    ];
+    let summary = WriteOptions::builder(&output_file)
+        .count_a2()
+        .tolerance(1e-6)
+        .write_with_summary(&val)?;
+    assert_eq!(summary.genotype_counts(), [5, 5, 3]);
+    assert_eq!(summary.missing_count(), 2);
+    assert_eq!(summary.missing_counts(), nd::array![0, 0, 2]);
+    assert_eq!(summary.snapped_counts(), nd::array![0, 1, 0]);
+    assert_eq!(summary.bytes_written(), 3 + 2 * 3);
+    assert_eq!(
+        summary.bytes_written(),
+        std::fs::metadata(&output_file)?.len()
+    );
+
+    // Zero SNPs
+    let summary =
+        WriteOptions::builder(&output_file).write_with_summary(&nd::Array2::<i8>::zeros((5, 0)))?;
+    assert_eq!(summary.genotype_counts(), [0, 0, 0]);
+    assert_eq!(summary.missing_counts().len(), 0);
+    assert_eq!(summary.bytes_written(), 3);
+
+    Ok(())
+}
+
+#[test]
+fn import_sorter() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("import_sorter.bed");
+
+    // Spill runs of 2 SNPs, with mixed chromosome names and a tie in position
+    let mut sorter = ImportSorter::new(&output_file, 5, 2)?;
+    let snps = [
+        ("chrX", 10, "x1"),
+        ("2", 5, "b1"),
+        ("chr1", 300, "a3"),
+        ("1", 20, "a1"),
+        ("scaffold9", 1, "s1"),
+        ("10", 7, "c1"),
+        ("1", 300, "a4"),
+        ("01", 100, "a2"),
+    ];
+    for (snp_i, (chromosome, bp_position, sid)) in snps.iter().enumerate() {
+        let mut val = nd::Array1::<f32>::from_elem(5, (snp_i % 3) as f32);
+        val[snp_i % 5] = f32::NAN;
+        sorter.push(chromosome, *bp_position, sid, "A", "C", &val)?;
+    }
+    sorter.finish(
+        &Metadata::builder()
+            .iid(["i1", "i2", "i3", "i4", "i5"])
+            .build()?,
+    )?;
+    assert!(output_folder.read_dir()?.all(|entry| !entry
+        .unwrap()
+        .file_name()
+        .to_string_lossy()
+        .contains("sort_run")));
+
+    let bed = Bed::new(&output_file)?;
+    assert_eq!(bed.iid()?, nd::array!["i1", "i2", "i3", "i4", "i5"]);
+    assert_eq!(
+        bed.sid()?,
+        nd::array!["a1", "a2", "a3", "a4", "b1", "c1", "x1", "s1"]
+    );
+    assert_eq!(
+        bed.chromosome()?,
+        nd::array!["1", "01", "chr1", "1", "2", "10", "chrX", "scaffold9"]
+    );
+    let val = bed.read::<i8>()?;
+    // Columns in sorted order came from pushes 3, 7, 2, 6, 1, 5, 0, 4
+    for (col_i, &snp_i) in [3usize, 7, 2, 6, 1, 5, 0, 4].iter().enumerate() {
+        let mut expected = nd::Array1::<i8>::from_elem(5, (snp_i % 3) as i8);
+        expected[snp_i % 5] = -127;
+        assert_eq!(val.column(col_i), expected);
+    }
+
+    // Bad values and wrong lengths are errors
+    let mut sorter = ImportSorter::new(&output_file, 3, 10)?;
+    let result = sorter.push("1", 1, "s1", "A", "C", &nd::array![0, 3, 1]);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::BadValue(_)));
+    let result = sorter.push("1", 1, "s1", "A", "C", &nd::array![0, 1]);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::InconsistentCount(_, 3, 2))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn peek_dims() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("peek.bed");
+    let val = nd::Array2::<i8>::from_elem((5, 7), 1);
+    WriteOptions::builder(&output_file).write(&val)?;
+
+    // The .bim file isn't needed
+    std::fs::remove_file(output_file.with_extension("bim"))?;
+    let bed = Bed::new(&output_file)?;
+    assert_eq!(bed.peek_dims()?, (5, 7));
+    let bed = Bed::builder(&output_file).iid_count(8).build()?;
+    assert_eq!(bed.peek_dims()?, (8, 7));
+
+    // A known sid_count is checked
+    let bed = Bed::builder(&output_file).sid_count(7).build()?;
+    assert_eq!(bed.peek_dims()?, (5, 7));
+    let bed = Bed::builder(&output_file).sid_count(6).build()?;
+    let result = bed.peek_dims();
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::InconsistentCount(_, 6, 7))
+    );
+
+    // The length must be a whole number of columns
+    let bed = Bed::builder(&output_file).iid_count(9).build()?;
+    let result = bed.peek_dims();
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::IllFormed(_)));
+
+    Ok(())
+}
+
+#[test]
+fn sid_annotations() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("annotated.bed");
+    let val = nd::array![[0i8, 1, 2, 0], [2, 1, 0, -127]];
+    let gene = ["BRCA1", "tab\there", "new\nline", "back\\slash"];
+    let metadata = Metadata::builder()
+        .sid(["s1", "s2", "s3", "s1"])
+        .sid_annotation_string("gene", gene)
+        .sid_annotation_float("qc score", [0.5, f64::NAN, 1.0, -2.5])
+        .build()?;
+    WriteOptions::builder(&output_file)
+        .metadata(&metadata)
+        .write(&val)?;
+    assert!(output_file.with_extension("sid_annotations.tsv").exists());
+
+    // Annotations round-trip, including escaped characters and NaN
+    let bed = Bed::new(&output_file)?;
+    assert_eq!(
+        bed.sid_annotation("gene")?
+            .and_then(SidAnnotation::as_string),
+        Some(&nd::array!["BRCA1", "tab\there", "new\nline", "back\\slash"].map(|s| s.to_string()))
+    );
+    let qc_score = bed.sid_annotation("qc score")?.unwrap().as_float().unwrap();
+    assert!(qc_score[1].is_nan());
+    assert_eq!(
+        qc_score.select(nd::Axis(0), &[0, 2, 3]),
+        nd::array![0.5, 1.0, -2.5]
+    );
+    assert!(bed.sid_annotation("unknown")?.is_none());
+    let metadata = bed.metadata()?;
+    assert_eq!(
+        metadata.sid_annotation_names().collect::<Vec<_>>(),
+        ["gene", "qc score"]
+    );
+
+    // Annotations given when opening take precedence over the sidecar file
+    let bed = Bed::builder(&output_file)
+        .metadata(
+            &Metadata::builder()
+                .sid_annotation_float("gene", [1.0, 2.0, 3.0, 4.0])
+                .build()?,
+        )
+        .build()?;
+    assert!(bed.sid_annotation("gene")?.unwrap().as_float().is_some());
+
+    // Dropping duplicate SNPs also drops their annotations
+    WriteOptions::builder(&output_file)
+        .metadata(&metadata)
+        .dedup_sids(DuplicateSidPolicy::KeepFirst)
+        .write(&val)?;
+    let bed = Bed::new(&output_file)?;
+    assert_eq!(bed.sid_annotation("gene")?.unwrap().len(), 3);
+
+    // Annotations must have one value per SNP
+    let result = Metadata::builder()
+        .sid(["s1", "s2"])
+        .sid_annotation_float("qc", [0.5])
+        .build();
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::InconsistentCount(_, 2, 1))
+    );
+    let result = WriteOptions::builder(&output_file)
+        .metadata(
+            &Metadata::builder()
+                .sid_annotation_float("qc", [0.5])
+                .build()?,
+        )
+        .write(&val);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::InconsistentCount(_, 1, 4))
+    );
+
+    // A sidecar file with the wrong number of lines is an error
+    WriteOptions::builder(&output_file).write(&val)?;
+    std::fs::write(
+        output_file.with_extension("sid_annotations.tsv"),
+        "gene:string\nA\nB\n",
+    )?;
+    let bed = Bed::new(&output_file)?;
+    let result = bed.sid_annotation("gene");
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::InconsistentCount(_, 4, 2))
+    );
+    std::fs::write(
+        output_file.with_extension("sid_annotations.tsv"),
+        "gene\nA\nB\nC\nD\n",
+    )?;
+    let bed = Bed::new(&output_file)?;
+    let result = bed.metadata();
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::BadSidAnnotationHeader(_, _))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn to_i8_checked_policies() -> Result<(), Box<BedErrorPlus>> {
+    let val = nd::array![
+        [0.0, 0.9999, f64::NAN, -7.0],
+        [2.4, f64::INFINITY, 1.5, f64::NEG_INFINITY]
+    ];
+
+    let (val_i8, report) = to_i8_checked(&val.view(), OutOfRangePolicy::Saturate)?;
+    assert_eq!(val_i8, nd::array![[0, 1, -127, 0], [2, 2, 2, 0]]);
+    assert_eq!(report.missing_count(), 1);
+    assert_eq!(report.rounded_count(), 3);
+    assert_eq!(report.out_of_range_count(), 3);
+    assert_eq!(
+        report.out_of_range_examples(),
+        [
+            (0, 3, -7.0),
+            (1, 1, f64::INFINITY),
+            (1, 3, f64::NEG_INFINITY)
+        ]
+    );
+
+    let (val_i8, report) = to_i8_checked(&val.view(), OutOfRangePolicy::Missing)?;
+    assert_eq!(val_i8, nd::array![[0, 1, -127, -127], [2, -127, 2, -127]]);
+    assert_eq!(report.out_of_range_count(), 3);
+
+    let result = to_i8_checked(&val.view(), OutOfRangePolicy::Error);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::OutOfRangeValue(_, 0, 3))
+    );
+
+    // Works on non-standard layouts
+    let (val_i8, _) = to_i8_checked(&val.t(), OutOfRangePolicy::Saturate)?;
+    assert_eq!(val_i8, nd::array![[0, 2], [1, 2], [-127, 2], [0, 0]]);
+
+    Ok(())
+}
+
+#[test]
+fn gwas_dataset() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let prefix = output_folder.join("study.v1");
+    let val = nd::array![[0i8, 1, 2], [2, 1, 0], [1, 1, 1], [0, 0, 2], [2, 2, 2]];
+    WriteOptions::builder(output_folder.join("study.v1.bed"))
+        .fid(["f1", "f1", "f2", "f2", "f3"])
+        .iid(["i1", "i2", "i1", "i2", "i1"])
+        .write(&val)?;
+    let pheno_path = output_folder.join("study.pheno");
+    std::fs::write(
+        &pheno_path,
+        "f3 i1 1 0.5\nf1 i1 0 -9\nf1 i2 1 0.25\n\nf2 i2 0 0.75\n",
+    )?;
+    let covar_path = output_folder.join("study.covar");
+    std::fs::write(
+        &covar_path,
+        "FID IID age\nf1 i2 40\nf2 i2 NA\nf3 i1 50\nf2 i1 60\n",
+    )?;
+
+    // A prefix with a dot, and without covariates
+    let gwas = GwasDataset::open_without_covariates(&prefix, &pheno_path)?;
+    assert_eq!(gwas.pheno_names(), ["pheno1", "pheno2"]);
+    assert_eq!(gwas.iid_index(), [1, 3, 4]);
+    assert_eq!(gwas.covariates().dim(), (3, 0));
+
+    let gwas = GwasDataset::open(prefix.with_extension("v1.bed"), &pheno_path, &covar_path)?;
+    assert_eq!(gwas.iid_count(), 2);
+    assert_eq!(gwas.sid_count()?, 3);
+    assert_eq!(gwas.fid(), nd::array!["f1", "f3"]);
+    assert_eq!(gwas.iid(), nd::array!["i2", "i1"]);
+    assert_eq!(gwas.covar_names(), ["age"]);
+    assert_eq!(gwas.phenos(), nd::array![[1.0, 0.25], [1.0, 0.5]]);
+    assert_eq!(gwas.pheno("pheno2"), Some(nd::array![0.25, 0.5].view()));
+    assert!(gwas.pheno("unknown").is_none());
+    assert_eq!(gwas.covariates(), nd::array![[40.0], [50.0]]);
+    assert_eq!(gwas.read::<i8>(1..)?, nd::array![[1, 0], [2, 2]]);
+    assert_eq!(gwas.read_subset::<i8>(&[1], ..)?, nd::array![[2, 2, 2]]);
+    let result = gwas.read_subset::<i8>(&[2], ..);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::IidIndexTooBig(2)));
+
+    // Splits are reproducible, sorted, and cover every individual once
+    #[cfg(feature = "rand")]
+    {
+        let (train, test) = gwas.train_test_split(0.5, 3)?;
+        assert_eq!(
+            (train.clone(), test.clone()),
+            gwas.train_test_split(0.5, 3)?
+        );
+        let mut all = [train, test].concat();
+        all.sort_unstable();
+        assert_eq!(all, [0, 1]);
+        assert_eq!(gwas.train_test_split(0.0, 3)?.1.len(), 0);
+        let result = gwas.train_test_split(1.5, 3);
+        assert_error_variant!(result, BedErrorPlus::BedError(BedError::BadTestFraction(_)));
+    }
+
+    // Every line needs the same number of values
+    std::fs::write(&pheno_path, "f1 i1 1 2\nf1 i2 1\n")?;
+    let result = GwasDataset::open_without_covariates(&prefix, &pheno_path);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::MetadataFieldCount(4, 3, _))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn reencode_allele_orientation_in_place() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("swapped.bed");
+    let val = nd::array![
+        [0i8, 1, 2, -127],
+        [2, 0, 1, 0],
+        [1, 2, -127, 2],
+        [0, 0, 0, 1],
+        [2, -127, 2, 0]
+    ];
+    WriteOptions::builder(&output_file)
+        .allele_1(["A", "C", "G", "T"])
+        .allele_2(["C", "G", "T", "A"])
+        .write(&val)?;
+    let fam_before = std::fs::read(output_file.with_extension("fam"))?;
+
+    // The output may be the input, given without the .bed extension
+    let flip_mask = nd::array![false, true, true, false];
+    reencode_allele_orientation(
+        output_file.with_extension(""),
+        &output_file,
+        flip_mask.iter().copied(),
+    )?;
+
+    let bed = Bed::new(&output_file)?;
+    let mut expected = val.clone();
+    for mut column in expected.slice_mut(nd::s![.., 1..3]).columns_mut() {
+        column.map_inplace(|v| {
+            if *v != -127 {
+                *v = 2 - *v;
+            }
+        });
+    }
+    assert_eq!(bed.read::<i8>()?, expected);
+    assert_eq!(bed.allele_1()?, nd::array!["A", "G", "T", "T"]);
+    assert_eq!(bed.allele_2()?, nd::array!["C", "C", "G", "A"]);
+    assert_eq!(
+        std::fs::read(output_file.with_extension("fam"))?,
+        fam_before
+    );
+    assert!(!output_folder.join("swapped.bed.append_tmp").exists());
+
+    // Flipping again restores the original bytes, including padding
+    let bytes = std::fs::read(&output_file)?;
+    reencode_allele_orientation(&output_file, &output_file, flip_mask.iter().copied())?;
+    reencode_allele_orientation(&output_file, &output_file, flip_mask.iter().copied())?;
+    assert_eq!(std::fs::read(&output_file)?, bytes);
+
+    let result = reencode_allele_orientation(&output_file, &output_file, [true]);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::BoolArrayVectorWrongLength(4, 1))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn id_policy() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let val = nd::array![[0, 1], [2, 0], [1, -127]];
+
+    // Escape keeps distinct ids distinct, even ids that look escaped.
+    let output_file = output_folder.join("escape.bed");
+    WriteOptions::builder(&output_file)
+        .fid(["fam 1", "fam#1", "fam%201"])
+        .iid(["ann", "\"bob\"", "čapek"])
+        .father(["0", "dad's", "0"])
+        .sid(["rs1", "rs 1"])
+        .id_policy(IdPolicy::Escape)
+        .write(&val)?;
+    let bed = Bed::new(&output_file)?;
+    assert_eq!(bed.fid()?, nd::array!["fam%201", "fam%231", "fam%25201"]);
+    assert_eq!(bed.iid()?, nd::array!["ann", "%22bob%22", "%C4%8Dapek"]);
+    assert_eq!(bed.father()?, nd::array!["0", "dad%27s", "0"]);
+    assert_eq!(bed.sid()?, nd::array!["rs1", "rs%201"]);
+    assert_eq!(bed.read::<i8>()?, val);
+
+    // Escape also applies to sids given as iterators.
+    let output_file = output_folder.join("escape_lazy.bed");
+    WriteOptions::builder(&output_file)
+        .sid_iter(["rs#1", "rs2"].into_iter().map(String::from))
+        .id_policy(IdPolicy::Escape)
+        .write(&val)?;
+    let bed = Bed::new(&output_file)?;
+    assert_eq!(bed.sid()?, nd::array!["rs%231", "rs2"]);
+
+    // Strict rejects unusual ids before writing anything.
+    let output_file = output_folder.join("strict.bed");
+    let result = WriteOptions::builder(&output_file)
+        .iid(["ann", "bob", "čapek"])
+        .id_policy(IdPolicy::Strict)
+        .write(&val);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::UnsafeId(_, _)));
+    assert!(!output_file.exists());
+
+    let result = WriteOptions::builder(&output_file)
+        .sid(["rs1", ""])
+        .id_policy(IdPolicy::Strict)
+        .write(&val);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::UnsafeId(_, _)));
+
+    WriteOptions::builder(&output_file)
+        .iid(["ann", "bob", "carl"])
+        .id_policy(IdPolicy::Strict)
+        .write(&val)?;
+    assert_eq!(
+        Bed::new(&output_file)?.iid()?,
+        nd::array!["ann", "bob", "carl"]
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "gzip")]
+fn to_beagle_gl() -> Result<(), Box<BedErrorPlus>> {
+    use std::io::Read;
+
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("beagle.bed");
+    WriteOptions::builder(&output_file)
+        .iid(["i1", "i2", "i3"])
+        .sid(["s1", "s2"])
+        .allele_1(["A", "C"])
+        .allele_2(["G", "T"])
+        .write(&nd::array![[0, 2], [1, -127], [2, 0]])?;
+    let mut bed = Bed::new(&output_file)?;
+
+    let beagle_file = output_folder.join("beagle.beagle.gz");
+    bed.to_beagle_gl(&beagle_file, 0.1)?;
+    let mut text = String::new();
+    flate2::read::GzDecoder::new(std::fs::File::open(&beagle_file)?).read_to_string(&mut text)?;
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(
+        lines[0],
+        "marker\tallele1\tallele2\ti1\ti1\ti1\ti2\ti2\ti2\ti3\ti3\ti3"
+    );
+    assert_eq!(
+        lines[1],
+        "s1\tA\tG\t0.050000\t0.050000\t0.900000\t0.050000\t0.900000\t0.050000\t0.900000\t0.050000\t0.050000"
+    );
+    assert_eq!(
+        lines[2],
+        "s2\tC\tT\t0.900000\t0.050000\t0.050000\t0.333333\t0.333333\t0.333333\t0.050000\t0.050000\t0.900000"
+    );
+
+    bed.to_beagle_gl(&beagle_file, 0.0)?;
+    let mut text = String::new();
+    flate2::read::GzDecoder::new(std::fs::File::open(&beagle_file)?).read_to_string(&mut text)?;
+    assert!(text
+        .lines()
+        .nth(1)
+        .unwrap()
+        .ends_with("\t1.000000\t0.000000\t0.000000"));
+
+    let bad_file = output_folder.join("bad.beagle.gz");
+    for error_rate in [-0.1, 1.0, f64::NAN] {
+        let result = bed.to_beagle_gl(&bad_file, error_rate);
+        assert_error_variant!(result, BedErrorPlus::BedError(BedError::BadErrorRate(_)));
+    }
+    assert!(!bad_file.exists());
+
+    Ok(())
+}
+
+#[test]
+fn regions() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("regions.bed");
+    WriteOptions::builder(&output_file)
+        .chromosome(["1", "1", "1", "X", "2"])
+        .bp_position([100, 201, 300, 50, 100])
+        .write(&nd::array![[0, 1, 2, 0, 1], [2, 1, 0, -127, 0]])?;
+
+    // Overlapping regions are merged; "chr" prefixes and "23" match
+    let regions_file = output_folder.join("panel.bed");
+    std::fs::write(
+        &regions_file,
+        "# panel\nbrowser position chr1\nchr1\t99\t150\tA\nchr1 120 201 B\n\n23\t0\t50\n",
+    )?;
+    let regions = RegionSet::from_bed_file(&regions_file)?;
+    assert_eq!(regions.region_count(), 3);
+    assert!(regions.contains("1", 100) && regions.contains("1", 201));
+    assert!(!regions.contains("1", 99) && !regions.contains("1", 202));
+    assert!(regions.contains("chrX", 50) && !regions.contains("X", 51));
+    assert!(!regions.contains("2", 100) && !regions.contains("1", 0));
+
+    let mut bed = Bed::new(&output_file)?;
+    let val = ReadOptions::builder()
+        .regions(&regions)
+        .i8()
+        .read(&mut bed)?;
+    assert_eq!(val, nd::array![[0, 1, 0], [2, 1, -127]]);
+
+    // Regions filter the sid index, keeping its order, and work with score filters
+    bed.attach_scores("info", nd::array![0.9, 0.1, 0.9, 0.9, 0.9])?;
+    let val = ReadOptions::builder()
+        .sid_index([3, -5, 1, 2])
+        .regions(&regions)
+        .score_at_least("info", 0.5)
+        .i8()
+        .read(&mut bed)?;
+    assert_eq!(val, nd::array![[0, 0], [-127, 2]]);
+
+    let empty_file = output_folder.join("empty.bed");
+    std::fs::write(&empty_file, "")?;
+    let val = ReadOptions::builder()
+        .regions(&RegionSet::from_bed_file(&empty_file)?)
+        .i8()
+        .read(&mut bed)?;
+    assert_eq!(val.dim(), (2, 0));
+
+    for bad in [
+        "chr1\t10\n",
+        "chr1\t-1\t10\n",
+        "chr1\t20\t10\n",
+        "chr1\ta\t10\n",
+    ] {
+        std::fs::write(&regions_file, format!("chr1\t1\t2\n{bad}"))?;
+        let result = RegionSet::from_bed_file(&regions_file);
+        assert_error_variant!(result, BedErrorPlus::BedError(BedError::BadRegion(2, _)));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn region_strings() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("region_strings.bed");
+    WriteOptions::builder(&output_file)
+        .chromosome(["1", "1", "1", "X", "2"])
+        .bp_position([100, 201, 300, 50, 100])
+        .write(&nd::array![[0, 1, 2, 0, 1], [2, 1, 0, -127, 0]])?;
+
+    // Positions are 1-based and both ends are included
+    let region: Region = "chr1:100-201".parse()?;
+    assert_eq!(region, Region::new("chr1", 100, 201)?);
+    assert_eq!(region.to_string(), "chr1:100-201");
+    let regions = RegionSet::from_regions([region, "23:1,000-2,000".parse()?]);
+    assert!(regions.contains("1", 100) && regions.contains("1", 201));
+    assert!(!regions.contains("1", 99) && !regions.contains("1", 202));
+    assert!(regions.contains("X", 1000) && !regions.contains("X", 50));
+
+    // Several regions, and regions added to a set, keep the SNPs in any of them
+    let mut bed = Bed::new(&output_file)?;
+    let val = ReadOptions::builder()
+        .region("1:100-200")?
+        .region("chrX:50-50")?
+        .i8()
+        .read(&mut bed)?;
+    assert_eq!(val, nd::array![[0, 0], [2, -127]]);
+    let val = ReadOptions::builder()
+        .regions(&regions)
+        .region("2:1-100")?
+        .sid_index([4, 0, 3])
+        .i8()
+        .read(&mut bed)?;
+    assert_eq!(val, nd::array![[1, 0], [0, 2]]);
+
+    for bad in [
+        "1", "1:100", "1:a-200", ":1-2", "1:0-10", "1:20-10", "1:-5-10",
+    ] {
+        let result = ReadOptions::<i8>::builder().region(bad).map(|_| ());
+        assert_error_variant!(result, BedErrorPlus::BedError(BedError::BadRegionString(_)));
+    }
+    assert_error_variant!(
+        Region::new("1", 20, 10),
+        BedErrorPlus::BedError(BedError::BadRegionString(_))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn missing_if() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+
+    // Sentinels become missing and are counted as missing in the summary
+    let output_file = output_folder.join("sentinel.bed");
+    let val = nd::array![[0.0, -9.0, 2.0], [-9.0, 1.0, f64::NAN], [1.0, 2.0, -9.0]];
+    let summary = WriteOptions::builder(&output_file)
+        .missing_if(|v| v == -9.0)
+        .write_with_summary(&val)?;
+    assert_eq!(summary.missing_counts(), nd::array![1, 1, 2]);
+    let bed = Bed::new(&output_file)?;
+    assert_eq!(
+        bed.read::<i8>()?,
+        nd::array![[0, -127, 2], [-127, 1, -127], [1, 2, -127]]
+    );
+
+    // The predicate is checked first, so it can mark even 0, 1, or 2 as missing,
+    // and it works with i8 values and a tolerance
+    let output_file = output_folder.join("sentinel_i8.bed");
+    WriteOptions::builder(&output_file)
+        .missing_if(|v| v == 2.0 || v == 9.0)
+        .write(&nd::array![[0i8, 9], [2, 1]])?;
+    assert_eq!(
+        Bed::new(&output_file)?.read::<i8>()?,
+        nd::array![[0, -127], [-127, 1]]
+    );
+
+    let output_file = output_folder.join("sentinel_tolerance.bed");
+    WriteOptions::builder(&output_file)
+        .missing_if(|v| v < 0.0)
+        .tolerance(0.01)
+        .write(&nd::array![[0.999, -1.0], [-9.0, 2.0]])?;
+    assert_eq!(
+        Bed::new(&output_file)?.read::<i8>()?,
+        nd::array![[1, -127], [-127, 2]]
+    );
+
+    // Without the predicate, a sentinel is a bad value
+    let result = WriteOptions::builder(output_folder.join("bad.bed")).write(&val);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::BadValue(_)));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "checksum")]
+fn chunk_hashes() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let mut val = nd::array![[0, 1, 2, 0, 1], [2, 1, 0, -127, 0], [1, 1, 1, 2, 2]];
+    let output_file = output_folder.join("v1.bed");
+    WriteOptions::builder(&output_file).write(&val)?;
+    let bed = Bed::new(&output_file)?;
+
+    let v1 = bed.chunk_hashes(2)?;
+    assert_eq!(v1.chunk_sids(), 2);
+    assert_eq!(v1.chunk_hashes().len(), 3);
+    assert_eq!(Bed::new(&output_file)?.chunk_hashes(2)?, v1);
+
+    // One chunk's root is its hash; chunk size changes the hashes
+    let whole = bed.chunk_hashes(10)?;
+    assert_eq!(whole.chunk_hashes().len(), 1);
+    assert_eq!(whole.root(), whole.chunk_hashes()[0]);
+    assert_ne!(whole.root(), v1.root());
+    let single = bed.chunk_hashes(1)?;
+    assert_eq!(single.chunk_hashes().len(), 5);
+
+    // Only a changed chunk changes
+    val[[2, 2]] = 0;
+    let output_file = output_folder.join("v2.bed");
+    WriteOptions::builder(&output_file).write(&val)?;
+    let v2 = Bed::new(&output_file)?.chunk_hashes(2)?;
+    assert_eq!(v1.chunk_hashes()[0], v2.chunk_hashes()[0]);
+    assert_ne!(v1.chunk_hashes()[1], v2.chunk_hashes()[1]);
+    assert_eq!(v1.chunk_hashes()[2], v2.chunk_hashes()[2]);
+    assert_ne!(v1.root(), v2.root());
+
+    // With no SNPs, there are no chunks but there is a root
+    let output_file = output_folder.join("empty.bed");
+    WriteOptions::builder(&output_file).write(&nd::Array2::<i8>::zeros((3, 0)))?;
+    let empty = Bed::new(&output_file)?.chunk_hashes(2)?;
+    assert!(empty.chunk_hashes().is_empty());
+
+    let result = bed.chunk_hashes(0);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::ZeroChunkSids));
+
+    Ok(())
+}
+
+#[test]
+fn iid_where() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("iid_where.bed");
+    WriteOptions::builder(&output_file)
+        .fid(["CASE1", "CTRL1", "CASE2", "CASE3"])
+        .iid(["a", "b", "c", "d"])
+        .father(["0", "0", "a", "0"])
+        .sex([2, 2, 1, 2])
+        .pheno(["1", "0", "1", "1"])
+        .write(&nd::array![[0, 1], [2, 1], [1, 0], [0, -127]])?;
+    let mut bed = Bed::new(&output_file)?;
+
+    let val = ReadOptions::builder()
+        .iid_where(|row| row.sex == 2 && row.fid.starts_with("CASE"))
+        .i8()
+        .read(&mut bed)?;
+    assert_eq!(val, nd::array![[0, 1], [0, -127]]);
+
+    // The predicate filters the iid index, keeping its order
+    let val = ReadOptions::builder()
+        .iid_index([3, -2, 0, 1])
+        .iid_where(|row| row.pheno == "1" && row.iid != "a")
+        .sid_index(0)
+        .i8()
+        .read(&mut bed)?;
+    assert_eq!(val, nd::array![[0], [1]]);
+
+    let val = ReadOptions::builder()
+        .iid_where(|row| row.father != "0")
+        .f64()
+        .transpose_output()
+        .read(&mut bed)?;
+    assert_eq!(val, nd::array![[1.0], [0.0]]);
+
+    let val = ReadOptions::builder()
+        .iid_where(|_| false)
+        .i8()
+        .read(&mut bed)?;
+    assert_eq!(val.dim(), (0, 2));
+
+    let result = ReadOptions::builder()
+        .iid_index(4)
+        .iid_where(|_| true)
+        .i8()
+        .read(&mut bed);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::IidIndexTooBig(4)));
+
+    Ok(())
+}
+
+#[test]
+fn detect_format() -> Result<(), Box<BedErrorPlus>> {
+    use bed_reader::format::{detect_format, FileFormat, Mode};
+
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("detect.bed");
+    WriteOptions::builder(&output_file).write(&nd::array![[0, 1], [2, 0]])?;
+    assert_eq!(
+        detect_format(&output_file)?,
+        FileFormat::Bed(Mode::SnpMajor)
+    );
+    let bytes = std::fs::read(&output_file)?;
+
+    // A PLINK 2 mode byte gives a dedicated error that suggests plink2
+    let mut pgen_bytes = bytes.clone();
+    pgen_bytes[2] = 0x10;
+    std::fs::write(&output_file, &pgen_bytes)?;
+    assert_eq!(detect_format(&output_file)?, FileFormat::Pgen);
+    let result = Bed::new(&output_file)?.read::<i8>();
+    match result {
+        Err(e) => match *e {
+            BedErrorPlus::BedError(BedError::UnsupportedFormat(FileFormat::Pgen, ref path)) => {
+                assert_eq!(path, &output_file);
+                assert!(e.to_string().contains("plink2 --pfile"));
+            }
+            _ => panic!("expected UnsupportedFormat, got {e:?}"),
+        },
+        Ok(_) => panic!("expected an error"),
+    }
+
+    // An unknown mode byte is still BadMode and other unknown bytes are still IllFormed
+    let mut bad_mode_bytes = bytes.clone();
+    bad_mode_bytes[2] = 0x30;
+    std::fs::write(&output_file, &bad_mode_bytes)?;
+    assert_eq!(detect_format(&output_file)?, FileFormat::Unknown);
+    let result = Bed::new(&output_file)?.read::<i8>();
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::BadMode(_)));
+    std::fs::write(&output_file, b"not a bed file")?;
+    let result = Bed::new(&output_file);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::IllFormed(_)));
+
+    // Other formats
+    std::fs::write(&output_file, [0x1F, 0x8B, 0x08, 0x00])?;
+    assert_eq!(detect_format(&output_file)?, FileFormat::Gzip);
+    let result = Bed::new(&output_file);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::UnsupportedFormat(FileFormat::Gzip, _))
+    );
+    std::fs::write(&output_file, "##fileformat=VCFv4.3\n")?;
+    assert_eq!(detect_format(&output_file)?, FileFormat::Vcf);
+    std::fs::write(&output_file, b"BCF\x02\x02")?;
+    assert_eq!(detect_format(&output_file)?, FileFormat::Bcf);
+    let mut bgen_bytes = vec![0u8; 16];
+    bgen_bytes.extend_from_slice(b"bgen");
+    std::fs::write(&output_file, &bgen_bytes)?;
+    assert_eq!(detect_format(&output_file)?, FileFormat::Bgen);
+    std::fs::write(&output_file, [])?;
+    assert_eq!(detect_format(&output_file)?, FileFormat::Unknown);
+
+    Ok(())
+}
+
+#[test]
+fn metadata_loaded() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("loaded.bed");
+    WriteOptions::builder(&output_file)
+        .iid(["i1", "i2", "i3"])
+        .sid(["s1", "s2"])
+        .write(&nd::array![[0, 1], [2, 0], [1, 1]])?;
+
+    let bed = Bed::builder(&output_file).chromosome(["5", "6"]).build()?;
+    let loaded = bed.metadata_loaded();
+    assert!(loaded.iid().is_none() && loaded.sid().is_none());
+    assert_eq!(
+        loaded.chromosome(),
+        Some(&nd::array!["5", "6"].map(|s| s.to_string()))
+    );
+
+    // Reading one .fam field loads them all, but none from the .bim
+    assert_eq!(bed.iid_count()?, 3);
+    bed.father()?;
+    let loaded = bed.metadata_loaded();
+    assert!(loaded.iid().is_some() && loaded.sex().is_some());
+    assert!(loaded.sid().is_none() && loaded.bp_position().is_none());
+
+    // No file IO: works even after the files are gone
+    std::fs::remove_file(output_file.with_extension("bim"))?;
+    assert!(bed.metadata_loaded().sid().is_none());
+    let result = bed.metadata();
+    assert_error_variant!(result, BedErrorPlus::IOError(_));
+
+    Ok(())
+}
+
+#[test]
+fn field_mode() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("field_mode.bed");
+    WriteOptions::builder(&output_file)
+        .sex([1, 2, 0])
+        .cm_position([0.5, 1.25])
+        .bp_position([100, 2000])
+        .write(&nd::array![[0, 1], [2, 0], [1, 1]])?;
+
+    // Raw fields are kept as text until their typed array is asked for
+    let mut bed = Bed::builder(&output_file)
+        .field_mode(MetadataFields::Sex, FieldMode::Raw)
+        .field_mode(MetadataFields::BpPosition, FieldMode::Raw)
+        .field_mode(MetadataFields::Sid, FieldMode::Raw)
+        .build()?;
+    assert_eq!(bed.iid_count()?, 3);
+    assert_eq!(
+        bed.raw_field(MetadataFields::Sex)?,
+        nd::array!["1", "2", "0"]
+    );
+    assert!(bed.metadata_loaded().sex().is_none());
+    assert_eq!(bed.sex()?, nd::array![1, 2, 0]);
+    assert_eq!(bed.cm_position()?, nd::array![0.5, 1.25]);
+    assert!(bed.metadata_loaded().bp_position().is_none());
+    assert_eq!(
+        bed.raw_field(MetadataFields::BpPosition)?,
+        nd::array!["100", "2000"]
+    );
+    assert_eq!(bed.bp_position()?, nd::array![100, 2000]);
+    assert_eq!(
+        bed.raw_field(MetadataFields::Sid)?,
+        nd::array!["sid1", "sid2"]
+    );
+    let result = bed.raw_field(MetadataFields::CmPosition);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::FieldNotRaw(_)));
+
+    // Skip is like skip_*, and the last mode set wins
+    let mut bed = Bed::builder(&output_file)
+        .field_mode(MetadataFields::CmPosition, FieldMode::Skip)
+        .field_mode(MetadataFields::BpPosition, FieldMode::Raw)
+        .field_mode(MetadataFields::BpPosition, FieldMode::Typed)
+        .build()?;
+    let result = bed.cm_position();
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::CannotUseSkippedMetadata(_))
+    );
+    assert_eq!(bed.bp_position()?, nd::array![100, 2000]);
+    let result = bed.raw_field(MetadataFields::BpPosition);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::FieldNotRaw(_)));
+
+    // Raw text is made from values given to the builder
+    let mut bed = Bed::builder(&output_file)
+        .bp_position([7, 8])
+        .field_mode(MetadataFields::BpPosition, FieldMode::Raw)
+        .build()?;
+    assert_eq!(
+        bed.raw_field(MetadataFields::BpPosition)?,
+        nd::array!["7", "8"]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn shard_by_sid() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("all.bed");
+    let val = nd::array![
+        [0i8, 1, 2, 0, 1, 2, 0],
+        [2, 1, 0, -127, 0, 1, 2],
+        [1, 1, 1, 2, -127, 0, 0],
+        [0, 0, 2, 2, 1, 1, 0],
+        [2, -127, 0, 1, 1, 0, 2]
+    ];
+    WriteOptions::builder(&output_file)
+        .iid(["i1", "i2", "i3", "i4", "i5"])
+        .chromosome(["1", "1", "1", "2", "2", "3", "3"])
+        .bp_position([10, 20, 30, 40, 50, 60, 70])
+        .write(&val)?;
+
+    let mut bed = Bed::new(&output_file)?;
+    let template = output_folder.join("shards").join("part{}.bed");
+    std::fs::create_dir(output_folder.join("shards"))?;
+    let shard_paths = bed.shard_by_sid(3, template.to_str().unwrap())?;
+    assert_eq!(shard_paths.len(), 3);
+
+    // Sizes differ by at most one and the shards rejoin to the whole
+    let mut sid_start = 0;
+    for (shard_path, sid_len) in shard_paths.iter().zip([3, 2, 2]) {
+        let shard = Bed::new(shard_path)?;
+        assert_eq!(shard.iid()?, bed.iid()?);
+        assert_eq!(shard.dim()?, (5, sid_len));
+        let range = sid_start..sid_start + sid_len;
+        assert_eq!(shard.read::<i8>()?, val.slice(nd::s![.., range.clone()]));
+        assert_eq!(shard.sid()?, bed.sid()?.slice(nd::s![range.clone()]));
+        assert_eq!(
+            shard.bp_position()?,
+            bed.bp_position()?.slice(nd::s![range])
+        );
+        sid_start += sid_len;
+    }
+
+    let manifest = std::fs::read_to_string(output_folder.join("shards").join("partmanifest.json"))?;
+    assert!(manifest.contains("\"shard_count\": 3,"));
+    assert!(manifest.contains(
+        "\"sid_start\": 3, \"sid_end\": 5, \"first_sid\": \"sid4\", \"last_sid\": \"sid5\""
+    ));
+
+    let result = bed.shard_by_sid(8, template.to_str().unwrap());
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::BadShardCount(8, 7))
+    );
+    let result = bed.shard_by_sid(2, "part.bed");
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::BadShardTemplate(_))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn iter_sid_chunks() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("chunks.bed");
+    let val = nd::array![
+        [0i8, 1, 2, 0, 1, 2, 0],
+        [2, 1, 0, -127, 0, 1, 2],
+        [1, 1, 1, 2, -127, 0, 0]
+    ];
+    WriteOptions::builder(&output_file)
+        .bp_position([10, 20, 30, 40, 50, 60, 70])
+        .write(&val)?;
+    let bed = Bed::new(&output_file)?;
+
+    // Chunks rejoin to a single read with the same options
+    let read_options = ReadOptions::builder()
+        .iid_index([2, 0])
+        .sid_index(1..)
+        .is_a1_counted(false)
+        .missing_value(-1)
+        .i8()
+        .build()?;
+    let expected = bed.read_with_options(&read_options)?;
+    let chunks = bed.iter_sid_chunks(4, &read_options)?;
+    assert_eq!(chunks.sid_count(), 6);
+    assert_eq!(chunks.len(), 2);
+    let chunks = chunks.collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(chunks[0].dim(), (2, 4));
+    assert_eq!(chunks[1].dim(), (2, 2));
+    let views: Vec<_> = chunks.iter().map(nd::ArrayBase::view).collect();
+    assert_eq!(nd::concatenate(nd::Axis(1), &views).unwrap(), expected);
+
+    // SNPs selected by region are split into chunks, too
+    let regions_file = output_folder.join("panel.bed");
+    std::fs::write(&regions_file, "0\t15\t45\n0\t65\t70\n")?;
+    let regions = RegionSet::from_bed_file(&regions_file)?;
+    let read_options = ReadOptions::builder().regions(&regions).i8().build()?;
+    let chunks = bed
+        .iter_sid_chunks(2, &read_options)?
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(chunks.len(), 2);
+    assert_eq!(chunks[1], val.select(nd::Axis(1), &[3, 6]));
+
+    let result = bed.iter_sid_chunks(0, &read_options);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::ZeroChunkSids));
+
+    Ok(())
+}
+
+#[test]
+fn read_features() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("features.bed");
+    let val = nd::array![[0i8, 1, 2], [2, -127, 1], [1, 0, 0]];
+    WriteOptions::builder(&output_file).write(&val)?;
+    let mut bed = Bed::new(&output_file)?;
+
+    let options = FeatureOptions::builder().iid_index([2, 0]).build()?;
+    let features = bed.read_features::<f64>(&options)?;
+    assert_eq!(
+        features,
+        nd::array![
+            [0.0, 1.0, 0.0, 1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]
+        ]
+    );
+
+    // Missing genotypes, by default, are missing in each of their columns
+    let options = FeatureOptions::builder()
+        .encoding(FeatureEncoding::AddDom)
+        .sid_index([1, 2])
+        .build()?;
+    let features = bed.read_features::<f32>(&options)?;
+    assert_eq!(features.dim(), (3, 4));
+    assert!(features[(1, 0)].is_nan() && features[(1, 1)].is_nan());
+    assert_eq!(features.row(0), nd::array![1.0, 1.0, 2.0, 0.0]);
+
+    // Counting allele 2 swaps homozygotes
+    let options = FeatureOptions::builder()
+        .encoding(FeatureEncoding::AddDom)
+        .missing(FeatureMissing::Zero)
+        .is_a1_counted(false)
+        .build()?;
+    let features = bed.read_features::<i8>(&options)?;
+    assert_eq!(features.row(1), nd::array![0, 0, 0, 0, 1, 1]);
+
+    let options = FeatureOptions::builder()
+        .missing(FeatureMissing::Error)
+        .build()?;
+    let result = bed.read_features::<f64>(&options);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::MissingFeatureGenotype(1, 1))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn watch() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("live.bed");
+    WriteOptions::builder(&output_file)
+        .iid(["i1", "i2"])
+        .write(&nd::array![[0, 1], [2, 0]])?;
+
+    let mut watched = Bed::builder(&output_file).watch(true).build()?;
+    let mut strict = Bed::builder(&output_file)
+        .on_stale(StalePolicy::Error)
+        .build()?;
+    let mut unwatched = Bed::new(&output_file)?;
+    for bed in [&mut watched, &mut strict, &mut unwatched] {
+        assert_eq!(bed.iid()?, nd::array!["i1", "i2"]);
+        assert_eq!(bed.read::<i8>()?, nd::array![[0, 1], [2, 0]]);
+    }
+    watched.attach_scores("info", nd::array![0.9, 0.1])?;
+
+    // Rewrite the files with new individuals and values
+    WriteOptions::builder(&output_file)
+        .iid(["j1", "j2", "j3"])
+        .write(&nd::array![[1, 1], [0, 2], [2, 2]])?;
+
+    // Reads, through &self, refresh the counts and metadata, and accessors then agree
+    let old_iid = watched.iid()?;
+    assert_eq!(watched.read::<i8>()?, nd::array![[1, 1], [0, 2], [2, 2]]);
+    assert_eq!(watched.iid()?, nd::array!["j1", "j2", "j3"]);
+    assert_eq!(watched.dim()?, (3, 2));
+    assert_eq!(old_iid, nd::array!["i1", "i2"]);
+    // The refreshed metadata is kept, not read again for each read
+    let refreshed_iid: *const _ = watched.iid()?;
+    watched.read::<i8>()?;
+    assert!(std::ptr::eq(refreshed_iid, watched.iid()?));
+    let read_options = ReadOptions::builder()
+        .score_at_least("info", 0.5)
+        .i8()
+        .build()?;
+    assert_eq!(
+        watched.read_with_options(&read_options)?,
+        nd::array![[1], [0], [2]]
+    );
+    assert!(watched.refresh()?);
+    assert!(!watched.refresh()?);
+    assert_eq!(watched.iid()?, nd::array!["j1", "j2", "j3"]);
+    // Attached scores survive the refresh
+    assert_eq!(watched.scores("info"), Some(&nd::array![0.9, 0.1]));
+    let result = strict.read::<i8>();
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::StaleFile(_)));
+    // Without watching, the old counts are kept
+    assert_eq!(unwatched.read::<i8>()?, nd::array![[1, 1], [0, 2]]);
+
+    // Kept scores that no longer fit the SNPs are an error
+    WriteOptions::builder(&output_file).write(&nd::array![[1, 1, 0], [0, 2, 0]])?;
+    let result = watched.read_with_options(&read_options);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::InconsistentCount(_, 3, 2))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn max_dims() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("dims.bed");
+    WriteOptions::builder(&output_file).write(&nd::array![[0, 1, 2], [2, 0, 1]])?;
+
+    let bed = Bed::builder(&output_file).max_dims(2, 3).build()?;
+    assert_eq!(bed.dim()?, (2, 3));
+    assert_eq!(bed.read::<i8>()?.dim(), (2, 3));
+
+    // Found by counting lines
+    let bed = Bed::builder(&output_file).max_dims(1, 3).build()?;
+    let result = bed.iid_count();
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::TooManyForMaxDims(_, 2, 1))
+    );
+    let result = bed.read::<i8>();
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::TooManyForMaxDims(_, 2, 1))
+    );
+
+    // Checked before the .bim file is read
+    let bed = Bed::builder(&output_file).max_dims(2, 2).build()?;
+    let result = bed.sid();
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::TooManyForMaxDims(_, 3, 2))
+    );
+
+    // Given counts and metadata are checked when built
+    let result = Bed::builder(&output_file)
+        .iid(["a", "b"])
+        .max_dims(1, 3)
+        .build();
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::TooManyForMaxDims(_, 2, 1))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn bed_writer() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let val = nd::array![[0.0, 1.0, 2.0, f64::NAN, 0.0], [2.0, 0.0, 1.0, 1.0, 0.0]];
+
+    // Blocks of any size give the same files as writing all at once
+    let whole_file = output_folder.join("whole.bed");
+    let whole_summary = WriteOptions::builder(&whole_file)
+        .sid(["s1", "s2", "s3", "s4", "s5"])
+        .write_with_summary(&val)?;
+    let block_file = output_folder.join("block.bed");
+    let mut writer = WriteOptions::builder(&block_file)
+        .sid(["s1", "s2", "s3", "s4", "s5"])
+        .bed_writer(2)?;
+    writer.write_block(&val.slice(nd::s![.., ..2]))?;
+    writer.write_block(&val.slice(nd::s![.., 2..2]))?;
+    writer.write_block(&val.slice(nd::s![.., 2..]))?;
+    assert_eq!(writer.sid_count(), 5);
+    let block_summary = writer.finish()?;
+    assert_eq!(
+        block_summary.genotype_counts(),
+        whole_summary.genotype_counts()
+    );
+    assert_eq!(
+        block_summary.missing_counts(),
+        whole_summary.missing_counts()
+    );
+    assert_eq!(block_summary.bytes_written(), whole_summary.bytes_written());
+    for extension in ["bed", "fam", "bim"] {
+        assert_eq!(
+            std::fs::read(block_file.with_extension(extension))?,
+            std::fs::read(whole_file.with_extension(extension))?
+        );
+    }
+
+    // A bad block writes nothing, and the writer can go on
+    let output_file = output_folder.join("bad.bed");
+    let mut writer = BedWriter::new(&output_file, 2)?;
+    writer.write_block(&nd::array![[0i8], [1]])?;
+    let result = writer.write_block(&nd::array![[2i8, 3], [0, 0]]);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::BadValue(_)));
+    let result = writer.write_block(&nd::array![[2i8], [0], [1]]);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::InconsistentCount(_, 2, 3))
+    );
+    writer.write_block(&nd::array![[2i8], [0]])?;
+    writer.finish()?;
+    let bed = Bed::new(&output_file)?;
+    assert_eq!(bed.read::<i8>()?, nd::array![[0, 2], [1, 0]]);
+
+    // Metadata that doesn't match the SNPs written fails at finish and removes the .bed file
+    let output_file = output_folder.join("short.bed");
+    let mut writer = WriteOptions::builder(&output_file)
+        .sid(["s1"])
+        .bed_writer(2)?;
+    writer.write_block(&nd::array![[0i8, 1], [1, 2]])?;
+    assert!(writer.finish().is_err());
+    assert!(!output_file.exists());
+
+    let result = WriteOptions::<i8>::builder(&output_file)
+        .dedup_sids(DuplicateSidPolicy::Error)
+        .bed_writer(2);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::StreamingDedupSids));
+
+    Ok(())
+}
+
+#[test]
+fn sid_counts() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    // Every iid count from 0 to 9, to cover each size of the final, partial byte
+    for iid_count in 0..10usize {
+        let val = nd::Array2::from_shape_fn((iid_count, 3), |(iid_i, sid_i)| {
+            [0i8, 1, 2, -127][(iid_i * (sid_i + 1) + sid_i) % 4]
+        });
+        let output_file = output_folder.join(format!("counts{iid_count}.bed"));
+        WriteOptions::builder(&output_file).write(&val)?;
+
+        let bed = Bed::new(&output_file)?;
+        for (sid_i, column) in val.columns().into_iter().enumerate() {
+            let mut expected = [0u64; 4];
+            for &value in column {
+                expected[if value < 0 { 3 } else { value as usize }] += 1;
+            }
+            assert_eq!(bed.sid_counts(sid_i)?, expected);
+        }
+        let result = bed.sid_counts(3);
+        assert_error_variant!(result, BedErrorPlus::BedError(BedError::SidIndexTooBig(3)));
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "mmap")]
+fn mmap() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("mapped.bed");
+    let val = nd::Array2::from_shape_fn((11, 7), |(iid_i, sid_i)| {
+        [0i8, 1, 2, -127][(iid_i * 3 + sid_i * 5) % 4]
+    });
+    WriteOptions::builder(&output_file).write(&val)?;
+
+    // Mapped reads match sequential reads, including for scattered and negative indexes
+    let mut bed = Bed::new(&output_file)?;
+    let mut mapped_bed = Bed::builder(&output_file).mmap(true).build()?;
+    for _ in 0..2 {
+        let read_options = ReadOptions::builder()
+            .iid_index([10, 0, -2, 5])
+            .sid_index([6, -7, 3, 3])
+            .f32()
+            .build()?;
+        let expected = bed.read_with_options(&read_options)?;
+        assert_eq_nan(&mapped_bed.read_with_options(&read_options)?, &expected);
+        assert_eq_nan(
+            &ReadOptions::builder()
+                .iid_index([10, 0, -2, 5])
+                .sid_index([6, -7, 3, 3])
+                .read_strategy(ReadStrategy::Mmap)
+                .f32()
+                .read(&mut bed)?,
+            &expected,
+        );
+    }
+    assert_eq!(mapped_bed.read::<i8>()?, val);
+    assert_eq!(
+        ReadOptions::builder()
+            .is_a1_counted(false)
+            .i8()
+            .read(&mut mapped_bed)?,
+        ReadOptions::builder()
+            .is_a1_counted(false)
+            .i8()
+            .read(&mut bed)?
+    );
+
+    let result = ReadOptions::builder()
+        .sid_index(7)
+        .i8()
+        .read(&mut mapped_bed);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::SidIndexTooBig(7)));
+    let result = ReadOptions::builder()
+        .iid_index(11)
+        .i8()
+        .read(&mut mapped_bed);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::IidIndexTooBig(11)));
+
+    // A short .bed file is reported, not read past
+    let short_file = output_folder.join("short.bed");
+    let mut bytes = std::fs::read(&output_file)?;
+    bytes.pop();
+    std::fs::write(&short_file, bytes)?;
+    std::fs::copy(
+        output_file.with_extension("fam"),
+        short_file.with_extension("fam"),
+    )?;
+    std::fs::copy(
+        output_file.with_extension("bim"),
+        short_file.with_extension("bim"),
+    )?;
+    let bed = Bed::builder(&short_file).mmap(true).build()?;
+    let result = bed.read::<i8>();
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::IllFormed(_)));
+
+    Ok(())
+}
+
+#[test]
+fn operation_log() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let log = OperationLog::new(output_folder.join("run.log"));
+
+    let output_file = output_folder.join("first.bed");
+    WriteOptions::builder(&output_file)
+        .sid(["s1", "s1", "s2"])
+        .dedup_sids(DuplicateSidPolicy::KeepFirst)
+        .tolerance(0.01)
+        .log(log.clone())
+        .write(&nd::array![[0.0, 1.0, 2.001], [f64::NAN, 2.0, 1.0]])?;
+
+    // A failed write is reported, too
+    let result = WriteOptions::builder(output_folder.join("bad.bed"))
+        .log(log.clone())
+        .write(&nd::array![[0, 3]]);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::BadValue(_)));
+
+    // And so are writes made a block at a time
+    let mut writer = WriteOptions::builder(output_folder.join("blocks.bed"))
+        .skip_fam()
+        .log(log.clone())
+        .bed_writer(1)?;
+    writer.write_block(&nd::array![[0i8, 1]])?;
+    writer.finish()?;
+
+    let text = std::fs::read_to_string(log.path())?;
+    assert_eq!(text.matches("Operation: write").count(), 3);
+
+    assert!(text.contains("  --dedup-sids KeepFirst\n"));
+    assert!(text.contains("  --tolerance 0.01\n"));
+    assert!(text.contains("2 people (0 male, 0 female, 2 ambiguous) written to"));
+    assert!(text.contains("2 variants written to"));
+    assert!(text.contains("Total genotyping rate is 0.750000."));
+    assert!(text.contains("Warning: 1 variants with duplicate ids left out."));
+    assert!(text.contains("Warning: 1 values snapped to the nearest genotype."));
+    assert!(text.contains("Error: "));
+    assert!(text.contains("  --skip-fam\n"));
+    assert!(text.contains("Total genotyping rate is 1.000000."));
+
+    Ok(())
+}
+
+#[test]
+fn snp_stats() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("stats.bed");
+    let val = nd::Array2::from_shape_fn((9, 6), |(iid_i, sid_i)| {
+        [0i8, 1, 2, -127, 2][(iid_i * (sid_i + 2) + sid_i) % 5]
+    });
+    WriteOptions::builder(&output_file).write(&val)?;
+
+    // Matches a full read and reduction
+    let bed = Bed::new(&output_file)?;
+    let stats = bed.stats()?;
+    assert_eq!(stats.len(), 6);
+    for (sid_i, column) in val.columns().into_iter().enumerate() {
+        let called: Vec<f64> = column
+            .iter()
+            .filter(|&&v| v >= 0)
+            .map(|&v| f64::from(v))
+            .collect();
+        assert_eq!(stats.call_count()[sid_i], called.len());
+        if called.is_empty() {
+            assert!(stats.mean()[sid_i].is_nan());
+            continue;
+        }
+        let mean = called.iter().sum::<f64>() / called.len() as f64;
+        assert!((stats.mean()[sid_i] - mean).abs() < 1e-12);
+        assert!((stats.maf()[sid_i] - (mean / 2.0).min(1.0 - mean / 2.0)).abs() < 1e-12);
+        let missing_rate = (9 - called.len()) as f64 / 9.0;
+        assert!((stats.missing_rate()[sid_i] - missing_rate).abs() < 1e-12);
+    }
+
+    // Selections, the counted allele, and the output order are respected or ignored as documented
+    let stats = ReadOptions::builder()
+        .sid_index([4, 1])
+        .is_a1_counted(false)
+        .c()
+        .f64()
+        .stats(&bed)?;
+    let full = bed.stats()?;
+    assert_eq!(
+        stats.call_count(),
+        nd::array![full.call_count()[4], full.call_count()[1]]
+    );
+    assert!((stats.mean()[0] - (2.0 - full.mean()[4])).abs() < 1e-12);
+    assert!((stats.maf()[1] - full.maf()[1]).abs() < 1e-12);
+
+    // A SNP with no calls
+    let output_file = output_folder.join("uncalled.bed");
+    WriteOptions::builder(&output_file).write(&nd::array![[-127i8, 0], [-127, 1]])?;
+    let stats: SnpStats = Bed::new(&output_file)?.stats()?;
+    assert!(stats.mean()[0].is_nan() && stats.maf()[0].is_nan());
+    assert_eq!(stats.missing_rate(), nd::array![1.0, 0.0]);
+    assert_eq!(stats.call_count(), nd::array![0, 2]);
+
+    Ok(())
+}
+
+#[derive(Debug)]
+struct TableSource {
+    iid: Vec<String>,
+    sid: Vec<String>,
+    skip_sets: std::sync::Mutex<Vec<HashSet<MetadataFields>>>,
+}
+
+impl MetadataSource for TableSource {
+    fn read_fam(
+        &self,
+        skip_set: &HashSet<MetadataFields>,
+    ) -> Result<(Metadata, usize), Box<BedErrorPlus>> {
+        self.skip_sets.lock().unwrap().push(skip_set.clone());
+        let mut builder = Metadata::builder();
+        if !skip_set.contains(&MetadataFields::Iid) {
+            builder.iid(&self.iid);
+        }
+        if !skip_set.contains(&MetadataFields::Sex) {
+            builder.sex(vec![2; self.iid.len()]);
+        }
+        Ok((builder.build()?, self.iid.len()))
+    }
+
+    fn read_bim(
+        &self,
+        skip_set: &HashSet<MetadataFields>,
+    ) -> Result<(Metadata, usize), Box<BedErrorPlus>> {
+        self.skip_sets.lock().unwrap().push(skip_set.clone());
+        let mut builder = Metadata::builder();
+        if !skip_set.contains(&MetadataFields::Sid) {
+            builder.sid(&self.sid);
+        }
+        Ok((builder.build()?, self.sid.len()))
+    }
+}
+
+#[test]
+fn metadata_source() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("source.bed");
+    let val = nd::array![[0i8, 1, 2], [2, -127, 0]];
+    WriteOptions::builder(&output_file)
+        .skip_fam()
+        .skip_bim()
+        .write(&val)?;
+    let table_source = || TableSource {
+        iid: vec!["a".to_string(), "b".to_string()],
+        sid: vec!["s1".to_string(), "s2".to_string(), "s3".to_string()],
+        skip_sets: std::sync::Mutex::new(Vec::new()),
+    };
+
+    // Counts, metadata, and reads, with no .fam or .bim file
+    let bed = Bed::builder(&output_file)
+        .metadata_source(table_source())
+        .build()?;
+    assert_eq!(bed.dim()?, (2, 3));
+    assert_eq!(bed.iid()?, nd::array!["a", "b"]);
+    assert_eq!(bed.sex()?, nd::array![2, 2]);
+    assert_eq!(bed.sid()?, nd::array!["s1", "s2", "s3"]);
+    assert_eq!(bed.chromosome()?, nd::array!["0", "0", "0"]);
+    assert_eq!(bed.read::<i8>()?, val);
+
+    // Metadata given to the builder overrides the source's and isn't asked for
+    let source = Arc::new(table_source());
+    #[derive(Debug)]
+    struct Shared(Arc<TableSource>);
+    impl MetadataSource for Shared {
+        fn read_fam(
+            &self,
+            skip_set: &HashSet<MetadataFields>,
+        ) -> Result<(Metadata, usize), Box<BedErrorPlus>> {
+            self.0.read_fam(skip_set)
+        }
+        fn read_bim(
+            &self,
+            skip_set: &HashSet<MetadataFields>,
+        ) -> Result<(Metadata, usize), Box<BedErrorPlus>> {
+            self.0.read_bim(skip_set)
+        }
+    }
+    let bed = Bed::builder(&output_file)
+        .iid(["x", "y"])
+        .skip_pheno()
+        .metadata_source(Shared(source.clone()))
+        .build()?;
+    assert_eq!(bed.iid()?, nd::array!["x", "y"]);
+    assert_eq!(bed.sex()?, nd::array![2, 2]);
+    let skip_sets = source.skip_sets.lock().unwrap();
+    assert_eq!(skip_sets.len(), 1);
+    assert!(skip_sets[0].contains(&MetadataFields::Iid));
+    assert!(skip_sets[0].contains(&MetadataFields::Pheno));
+    assert!(!skip_sets[0].contains(&MetadataFields::Sex));
+    drop(skip_sets);
+
+    // A count that disagrees with the source's
+    let bed = Bed::builder(&output_file)
+        .iid(["x", "y", "z"])
+        .metadata_source(table_source())
+        .build()?;
+    assert_error_variant!(
+        bed.sex(),
+        BedErrorPlus::BedError(BedError::InconsistentCount(_, 2, 3))
+    );
+
+    // The files-based source
+    let first_file = output_folder.join("first.bed");
+    WriteOptions::builder(&first_file)
+        .iid(["i1", "i2"])
+        .sid(["r1", "r2", "r3"])
+        .write(&val)?;
+    let source = FileMetadataSource::new(
+        output_folder.join("first.fam"),
+        output_folder.join("first.bim"),
+    );
+    assert_eq!(source.iid_count()?, 2);
+    assert_eq!(source.sid_count()?, 3);
+    let bed = Bed::builder(&output_file).metadata_source(source).build()?;
+    assert_eq!(bed.dim()?, (2, 3));
+    assert_eq!(bed.iid()?, nd::array!["i1", "i2"]);
+    assert_eq!(bed.sid()?, nd::array!["r1", "r2", "r3"]);
+
+    Ok(())
+}
+
+#[derive(Debug)]
+struct MemorySource {
+    bytes: bytes::Bytes,
+    requests: std::sync::Mutex<Vec<Vec<std::ops::Range<usize>>>>,
+}
+
+impl BedSource for MemorySource {
+    fn size(&self) -> Result<usize, Box<BedErrorPlus>> {
+        Ok(self.bytes.len())
+    }
+
+    fn read_ranges(
+        &self,
+        ranges: &[std::ops::Range<usize>],
+    ) -> Result<Vec<bytes::Bytes>, Box<BedErrorPlus>> {
+        self.requests.lock().unwrap().push(ranges.to_vec());
+        Ok(ranges
+            .iter()
+            .map(|range| self.bytes.slice(range.clone()))
+            .collect())
+    }
+}
+
+#[test]
+fn bed_source() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let val = nd::array![[0i8, 1, 2, -127], [2, -127, 0, 1], [1, 1, 0, 2]];
+    let memory_source = |file: &std::path::Path| -> Result<MemorySource, Box<BedErrorPlus>> {
+        Ok(MemorySource {
+            bytes: std::fs::read(file)?.into(),
+            requests: std::sync::Mutex::new(Vec::new()),
+        })
+    };
+
+    // SNP-major and individual-major files read the same as locally, with selections
+    let snp_major_file = output_folder.join("snp.bed");
+    WriteOptions::builder(&snp_major_file)
+        .sid(["s1", "s2", "s3", "s4"])
+        .write(&val)?;
+    let iid_major_file = output_folder.join("iid.bed");
+    Bed::new(&snp_major_file)?.rewrite_as_individual_major(&iid_major_file)?;
+    for output_file in [snp_major_file, iid_major_file] {
+        let local = Bed::new(&output_file)?;
+        let bed = Bed::builder(&output_file)
+            .bed_source(memory_source(&output_file)?)
+            .build()?;
+        assert_eq!(bed.dim()?, (3, 4));
+        assert_eq!(bed.sid()?, nd::array!["s1", "s2", "s3", "s4"]);
+        assert_eq!(bed.read::<i8>()?, val);
+        let read_options = ReadOptions::builder()
+            .iid_index([2, 0])
+            .sid_index(s![..;-2])
+            .is_a1_counted(false)
+            .f64()
+            .build()?;
+        assert_eq_nan(
+            &bed.read_with_options(&read_options)?,
+            &local.read_with_options(&read_options)?,
+        );
+    }
+
+    // Only the selected SNPs' bytes are fetched
+    let output_file = output_folder.join("snp.bed");
+    let source = std::sync::Arc::new(memory_source(&output_file)?);
+    #[derive(Debug)]
+    struct Shared(std::sync::Arc<MemorySource>);
+    impl BedSource for Shared {
+        fn size(&self) -> Result<usize, Box<BedErrorPlus>> {
+            self.0.size()
+        }
+        fn read_ranges(
+            &self,
+            ranges: &[std::ops::Range<usize>],
+        ) -> Result<Vec<bytes::Bytes>, Box<BedErrorPlus>> {
+            self.0.read_ranges(ranges)
+        }
+    }
+    let bed = Bed::builder(&output_file)
+        .bed_source(Shared(source.clone()))
+        .build()?;
+    ReadOptions::builder().sid_index([1, 3]).i8().read(&bed)?;
+    assert_eq!(
+        source.requests.lock().unwrap().last().unwrap(),
+        &vec![4..5, 6..7]
+    );
+
+    // A source that isn't a .bed file, or returns short ranges
+    let bad_source = MemorySource {
+        bytes: bytes::Bytes::from_static(b"not a bed file"),
+        requests: std::sync::Mutex::new(Vec::new()),
+    };
+    assert_error_variant!(
+        Bed::builder(&output_file).bed_source(bad_source).build(),
+        BedErrorPlus::BedError(BedError::IllFormed(_))
+    );
+    let mut bytes = std::fs::read(&output_file)?;
+    bytes.pop();
+    let short_source = MemorySource {
+        bytes: bytes.into(),
+        requests: std::sync::Mutex::new(Vec::new()),
+    };
+    let bed = Bed::builder(&output_file)
+        .bed_source(short_source)
+        .build()?;
+    assert_error_variant!(
+        bed.read::<i8>(),
+        BedErrorPlus::BedError(BedError::IllFormed(_))
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn builder_url() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("url.bed");
+    let val = nd::array![[0i8, 1, 2], [2, -127, 0]];
+    WriteOptions::builder(&output_file)
+        .iid(["i1", "i2"])
+        .sid(["s1", "s2", "s3"])
+        .write(&val)?;
+
+    let url = cloud_file::abs_path_to_url_string(&output_file)?;
+    let bed = Bed::builder_url(&url)?.build()?;
+    assert_eq!(bed.dim()?, (2, 3));
+    assert_eq!(bed.iid()?, nd::array!["i1", "i2"]);
+    assert_eq!(bed.sid()?, nd::array!["s1", "s2", "s3"]);
+    assert_eq!(bed.read::<i8>()?, val);
+    let val2 = ReadOptions::builder()
+        .iid_index(1)
+        .sid_index([2, 0])
+        .i8()
+        .read(&bed)?;
+    assert_eq!(val2, nd::array![[0, 2]]);
+
+    // Metadata given to the builder still overrides the URL's
+    let bed = Bed::builder_url(&url)?.iid(["x", "y"]).build()?;
+    assert_eq!(bed.iid()?, nd::array!["x", "y"]);
+
+    Ok(())
+}
+
+#[test]
+fn standardize_public() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("standardize.bed");
+    let val = nd::array![
+        [0i8, 1, 2, 1],
+        [2, -127, 0, 1],
+        [1, 2, 0, 1],
+        [1, 0, -127, 1],
+        [0, 1, 2, 1]
+    ];
+    WriteOptions::builder(&output_file).write(&val)?;
+
+    // C- and F-order give the same unit-variance result
+    let mut bed = Bed::new(&output_file)?;
+    let mut val_f = ReadOptions::builder().f().f64().read(&mut bed)?;
+    let mut val_c = ReadOptions::builder().c().f64().read(&mut bed)?;
+    let stats_f = standardize(&mut val_f, Dist::Unit)?;
+    let stats_c = standardize(&mut val_c, Dist::Unit)?;
+    assert!(allclose(&val_f.view(), &val_c.view(), 1e-12, false));
+    // The last SNP's standard deviation is infinite
+    assert!(allclose(
+        &stats_f.slice(s![..3, ..]),
+        &stats_c.slice(s![..3, ..]),
+        1e-12,
+        false
+    ));
+    // Each SNP has mean 0.0 and, over its observed values, unit variance
+    for (sid_i, column) in val_f.axis_iter(nd::Axis(1)).enumerate().take(3) {
+        let observed = val.column(sid_i).iter().filter(|&&v| v != -127).count() as f64;
+        assert!(column.sum().abs() < 1e-12, "sid {sid_i}");
+        assert!(
+            (column.mapv(|v| v * v).sum() - observed).abs() < 1e-9,
+            "sid {sid_i}"
+        );
+    }
+    assert_eq!(val_f.column(3), nd::array![0.0, 0.0, 0.0, 0.0, 0.0]);
+    assert!(stats_f[(3, 1)].is_infinite());
+
+    // Stats from one set standardize another the same way
+    let mut again = ReadOptions::builder().f64().read(&mut bed)?;
+    standardize_with_stats(&mut again, Dist::Unit, &stats_f)?;
+    assert!(allclose(&again.view(), &val_f.view(), 1e-12, false));
+    let mut fewer = ReadOptions::builder().sid_index(..2).f64().read(&mut bed)?;
+    let result = standardize_with_stats(&mut fewer, Dist::Unit, &stats_f);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::InconsistentCount(_, 2, 4))
+    );
+
+    // f32 and Beta weighting
+    #[cfg(feature = "beta")]
+    {
+        let mut val32 = ReadOptions::builder().f32().read(&mut bed)?;
+        let stats32 = standardize(&mut val32, Dist::Beta { a: 1.0, b: 25.0 })?;
+        assert!((f64::from(stats32[(0, 0)]) - stats_f[(0, 0)]).abs() < 1e-6);
+    }
+
+    // A SNP with no values
+    let mut missing = nd::Array2::<f64>::from_elem((3, 1), f64::NAN);
+    let result = standardize(&mut missing, Dist::Unit);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::NoIndividuals));
+
+    Ok(())
+}
+
+#[test]
+fn read_strategy_scan() -> Result<(), Box<BedErrorPlus>> {
+    use bed_reader::Index;
+
+    // More than one 8 MB block of SNPs
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("scan.bed");
+    let (iid_count, sid_count) = (40_001, 900);
+    let val =
+        nd::Array2::<i8>::from_shape_fn((iid_count, sid_count), |(iid_i, sid_i)| {
+            match (iid_i * 7 + sid_i * 13) % 5 {
+                4 => -127,
+                code => (code % 3) as i8,
+            }
+        });
+    WriteOptions::builder(&output_file).write(&val)?;
+
+    let mut bed = Bed::new(&output_file)?;
+    for (iid_index, sid_index) in [
+        (Index::from([3, -1, 0, 40_000]), Index::All),
+        (Index::from(s![5..9]), Index::from([899, 0, 450, 0, -1])),
+        (Index::from(s![..;1000]), Index::from(s![100..700;3])),
+        (Index::from(s![..0]), Index::from(5..9)),
+        (Index::All, Index::from(s![..0])),
+    ] {
+        let expected = ReadOptions::builder()
+            .iid_index(iid_index.clone())
+            .sid_index(sid_index.clone())
+            .f32()
+            .read(&mut bed)?;
+        for is_f in [true, false] {
+            let val = ReadOptions::builder()
+                .iid_index(iid_index.clone())
+                .sid_index(sid_index.clone())
+                .read_strategy(ReadStrategy::Scan)
+                .is_f(is_f)
+                .f32()
+                .read(&mut bed)?;
+            assert!(allclose(&val.view(), &expected.view(), 0.0, true));
+        }
+    }
+
+    let result = ReadOptions::builder()
+        .sid_index(900)
+        .read_strategy(ReadStrategy::Scan)
+        .i8()
+        .read(&mut bed);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::SidIndexTooBig(900))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn rewrite_as_individual_major() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("snp_major.bed");
+    let val =
+        nd::Array2::<i8>::from_shape_fn((7, 5), |(iid_i, sid_i)| match (iid_i * 3 + sid_i) % 4 {
+            3 => -127,
+            code => code as i8,
+        });
+    WriteOptions::builder(&output_file)
+        .sid(["s1", "s2", "s3", "s4", "s5"])
+        .bp_position([10, 20, 30, 40, 50])
+        .write(&val)?;
+    let mut bed = Bed::new(&output_file)?;
+
+    let iid_major_file = output_folder.join("iid_major.bed");
+    bed.rewrite_as_individual_major(&iid_major_file)?;
+    assert_eq!(
+        format::read_mode(&iid_major_file)?,
+        format::Mode::IndividualMajor
+    );
+    assert_eq!(
+        std::fs::metadata(&iid_major_file)?.len(),
+        format::expected_file_len((7, 5), format::Mode::IndividualMajor)?
+    );
+    let mut iid_major = Bed::new(&iid_major_file)?;
+    assert_eq!(iid_major.read::<i8>()?, val);
+    assert_eq!(iid_major.metadata()?, bed.metadata()?);
+    let val_a2 = ReadOptions::builder()
+        .iid_index([6, 0])
+        .sid_index(1..4)
+        .count_a2()
+        .f32()
+        .read(&mut iid_major)?;
+    let expected = ReadOptions::builder()
+        .iid_index([6, 0])
+        .sid_index(1..4)
+        .count_a2()
+        .f32()
+        .read(&mut bed)?;
+    assert!(allclose(&val_a2.view(), &expected.view(), 0.0, true));
+
+    // An individual-major file can be rewritten, too
+    let again_file = output_folder.join("again.bed");
+    iid_major.rewrite_as_individual_major(&again_file)?;
+    assert_eq!(Bed::new(&again_file)?.read::<i8>()?, val);
+
+    // On error, no file is left
+    let missing_dir_file = output_folder.join("no_such_dir").join("bad.bed");
+    assert!(bed.rewrite_as_individual_major(&missing_dir_file).is_err());
+    assert!(!missing_dir_file.exists());
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "mmap")]
+fn read_coalesced_runs() -> Result<(), Box<BedErrorPlus>> {
+    use bed_reader::Index;
+
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("runs.bed");
+    let val = nd::Array2::<i8>::from_shape_fn((1001, 60), |(iid_i, sid_i)| {
+        match (iid_i * 5 + sid_i * 11) % 4 {
+            3 => -127,
+            code => code as i8,
+        }
+    });
+    WriteOptions::builder(&output_file).write(&val)?;
+
+    // Reads of runs of adjacent SNPs match reads from a memory mapping
+    let mut bed = Bed::new(&output_file)?;
+    for (iid_index, sid_index) in [
+        (Index::All, Index::All),
+        (Index::from(s![10..900]), Index::from(s![5..40])),
+        (
+            Index::from([1000, 0, 3]),
+            Index::from([7, 8, 9, 9, 10, 2, 1, -1, -2]),
+        ),
+        (Index::from(s![..0]), Index::from(5..9)),
+    ] {
+        let expected = ReadOptions::builder()
+            .iid_index(iid_index.clone())
+            .sid_index(sid_index.clone())
+            .read_strategy(ReadStrategy::Mmap)
+            .i8()
+            .read(&mut bed)?;
+        let val = ReadOptions::builder()
+            .iid_index(iid_index)
+            .sid_index(sid_index)
+            .i8()
+            .read(&mut bed)?;
+        assert_eq!(val, expected);
+    }
+    assert_eq!(bed.read::<i8>()?, val);
+
+    Ok(())
+}
+
+#[test]
+fn bed_set() -> Result<(), Box<BedErrorPlus>> {
+    use bed_reader::BedSet;
+
+    let output_folder = TempDir::default();
+    let val =
+        nd::Array2::<i8>::from_shape_fn((5, 9), |(iid_i, sid_i)| match (iid_i * 2 + sid_i) % 4 {
+            3 => -127,
+            code => code as i8,
+        });
+    let mut paths = Vec::new();
+    for (chromosome, range) in [("1", 0..4), ("2", 4..5), ("3", 5..9)] {
+        let path = output_folder.join(format!("chr{chromosome}.bed"));
+        WriteOptions::builder(&path)
+            .iid(["a", "b", "c", "d", "e"])
+            .chromosome(vec![chromosome; range.len()])
+            .sid(range.clone().map(|sid_i| format!("s{sid_i}")))
+            .write(&val.slice(s![.., range]))?;
+        paths.push(path);
+    }
+
+    let bed_set = BedSet::new(&paths)?;
+    assert_eq!(bed_set.dim(), (5, 9));
+    assert_eq!(bed_set.beds().len(), 3);
+    assert_eq!(bed_set.paths().count(), 3);
+    assert_eq!(bed_set.iid()?, nd::array!["a", "b", "c", "d", "e"]);
+    assert_eq!(bed_set.sid()?[4], "s4");
+    assert_eq!(bed_set.chromosome()?[8], "3");
+    assert_eq!(bed_set.metadata().allele_1().unwrap().len(), 9);
+    assert_eq!(bed_set.read::<i8>()?, val);
+
+    // SNP indexes across files, with the usual output options
+    let expected = val.select(nd::Axis(1), &[8, 0, 4, 3, 5, 8]);
+    let read = ReadOptions::builder()
+        .sid_index([8, 0, 4, -6, 5, -1])
+        .iid_index([4, 0, 1])
+        .c()
+        .f32()
+        .read_set(&bed_set)?;
+    assert!(allclose(
+        &read.view(),
+        &expected
+            .select(nd::Axis(0), &[4, 0, 1])
+            .mapv(|v| if v == -127 { f32::NAN } else { f32::from(v) })
+            .view(),
+        0.0,
+        true
+    ));
+    let read = ReadOptions::builder()
+        .sid_index(3..6)
+        .is_transposed(true)
+        .i8()
+        .read_set(&bed_set)?;
+    assert_eq!(read, val.slice(s![.., 3..6]).t());
+    let read = ReadOptions::builder()
+        .sid_index(s![..0])
+        .i8()
+        .read_set(&bed_set)?;
+    assert_eq!(read.dim(), (5, 0));
+
+    // A count_a1_mask covers all the files' SNPs
+    let mask = nd::Array1::from_shape_fn(9, |sid_i| sid_i % 2 == 0);
+    let read = ReadOptions::builder()
+        .count_a1_mask(mask.clone())
+        .i8()
+        .read_set(&bed_set)?;
+    for sid_i in 0..9 {
+        let mut bed = Bed::new(&paths[usize::from(sid_i >= 4) + usize::from(sid_i >= 5)])?;
+        let file_start = [0, 0, 0, 0, 4, 5, 5, 5, 5][sid_i];
+        let column = ReadOptions::builder()
+            .sid_index((sid_i - file_start) as isize)
+            .is_a1_counted(mask[sid_i])
+            .i8()
+            .read(&mut bed)?;
+        assert_eq!(read.column(sid_i), column.column(0));
+    }
+
+    // Errors
+    let result = ReadOptions::builder().sid_index(9).i8().read_set(&bed_set);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::SidIndexTooBig(9)));
+    let result = ReadOptions::builder()
+        .score_at_least("info", 0.5)
+        .i8()
+        .read_set(&bed_set);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::UnsupportedBedSetOption(_))
+    );
+    let result = BedSet::new(Vec::<std::path::PathBuf>::new());
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::EmptyBedSet));
+    let other_path = output_folder.join("other.bed");
+    WriteOptions::builder(&other_path)
+        .iid(["a", "b", "c", "d", "x"])
+        .write(&val)?;
+    let result = BedSet::new([&paths[0], &other_path]);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::BedSetFamMismatch(_, _))
+    );
+
+    // Skipped metadata: counts come from the files, and the skipped fields are errors
+    let skipping = |path: &std::path::PathBuf| {
+        Bed::builder(path)
+            .skip_iid()
+            .skip_fid()
+            .skip_chromosome()
+            .build()
+    };
+    let bed_set = BedSet::from_beds(paths.iter().map(skipping).collect::<Result<_, _>>()?)?;
+    assert_eq!(bed_set.dim(), (5, 9));
+    assert_eq!(bed_set.read::<i8>()?, val);
+    assert_error_variant!(
+        bed_set.iid(),
+        BedErrorPlus::BedError(BedError::CannotUseSkippedMetadata(_))
+    );
+    assert_error_variant!(
+        bed_set.chromosome(),
+        BedErrorPlus::BedError(BedError::CannotUseSkippedMetadata(_))
+    );
+    assert_eq!(bed_set.sid()?[4], "s4");
+    let fewer_path = output_folder.join("fewer.bed");
+    WriteOptions::builder(&fewer_path).write(&val.slice(s![..4, ..]))?;
+    let result = BedSet::from_beds(vec![skipping(&paths[0])?, skipping(&fewer_path)?]);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::BedSetFamMismatch(_, _))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn ld_with() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("ld_with.bed");
+    let val = nd::Array2::<i8>::from_shape_fn((50, 12), |(iid_i, sid_i)| {
+        match (iid_i * 7 + iid_i * iid_i * sid_i + sid_i / 3) % 11 {
+            10 => -127,
+            code => (code % 3) as i8,
+        }
+    });
+    let chromosome = ["1", "1", "1", "1", "1", "2", "2", "2", "2", "2", "2", "3"];
+    WriteOptions::builder(&output_file)
+        .chromosome(chromosome)
+        .write(&val)?;
+    let mut bed = Bed::new(&output_file)?;
+
+    let expected_r2 = |sid_a: usize, sid_b: usize| {
+        let pairs: Vec<(f64, f64)> = val
+            .column(sid_a)
+            .iter()
+            .zip(val.column(sid_b))
+            .filter(|(&a, &b)| a >= 0 && b >= 0)
+            .map(|(&a, &b)| (f64::from(a), f64::from(b)))
+            .collect();
+        let n = pairs.len() as f64;
+        let mean_a = pairs.iter().map(|p| p.0).sum::<f64>() / n;
+        let mean_b = pairs.iter().map(|p| p.1).sum::<f64>() / n;
+        let cov: f64 = pairs.iter().map(|p| (p.0 - mean_a) * (p.1 - mean_b)).sum();
+        let var_a: f64 = pairs.iter().map(|p| (p.0 - mean_a).powi(2)).sum();
+        let var_b: f64 = pairs.iter().map(|p| (p.1 - mean_b).powi(2)).sum();
+        cov * cov / (var_a * var_b)
+    };
+
+    for (target_sid, window, expected_sid_index) in [
+        (6, 2, 5..9),
+        (6, 100, 5..11),
+        (2, 1, 1..4),
+        (4, 3, 1..5),
+        (11, 5, 11..12),
+        (7, 0, 7..8),
+    ] {
+        let (sid_index, r2) = bed.ld_with(target_sid, window)?;
+        assert_eq!(sid_index, nd::Array1::from_iter(expected_sid_index));
+        for (&sid_i, &r2) in sid_index.iter().zip(&r2) {
+            let expected = expected_r2(target_sid, sid_i);
+            assert!(
+                (r2 - expected).abs() < 1e-12 || (r2.is_nan() && expected.is_nan()),
+                "{target_sid} {sid_i} {r2} {expected}"
+            );
+        }
+        assert!((r2[target_sid - sid_index[0]] - 1.0).abs() < 1e-12);
+    }
+
+    // A SNP with no variation has NaN r²
+    let output_file = output_folder.join("ld_with_constant.bed");
+    WriteOptions::builder(&output_file).write(&nd::array![[0, 1], [2, 1], [1, -127]])?;
+    let (_, r2) = Bed::new(&output_file)?.ld_with(0, 1)?;
+    assert_eq!(r2[0], 1.0);
+    assert!(r2[1].is_nan());
+
+    let result = bed.ld_with(12, 1);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::SidIndexTooBig(12)));
+
+    Ok(())
+}
+
+// Write a BGEN layout 2 file of diploid, two-allele SNPs. Each individual's two probabilities
+// are P(11) and P(12) or, if phased, each haplotype's P(allele 1). None is missing.
+#[cfg(feature = "gzip")]
+fn write_bgen(
+    path: &std::path::Path,
+    compression: u32,
+    iid: Option<&[&str]>,
+    snps: &[(&str, &str, u32, Vec<Option<[f64; 2]>>)],
+    is_phased: bool,
+    bits: u32,
+) -> Result<(), Box<BedErrorPlus>> {
+    use std::io::Write;
+
+    let iid_count = snps[0].3.len();
+    let mut bytes = vec![];
+    bytes.extend(20u32.to_le_bytes());
+    bytes.extend(20u32.to_le_bytes());
+    bytes.extend((snps.len() as u32).to_le_bytes());
+    bytes.extend((iid_count as u32).to_le_bytes());
+    bytes.extend(b"bgen");
+    bytes.extend((compression | 2 << 2 | u32::from(iid.is_some()) << 31).to_le_bytes());
+    if let Some(iid) = iid {
+        let mut block = vec![];
+        block.extend((iid.len() as u32).to_le_bytes());
+        for iid in iid {
+            block.extend((iid.len() as u16).to_le_bytes());
+            block.extend(iid.as_bytes());
+        }
+        bytes.extend((block.len() as u32 + 4).to_le_bytes());
+        bytes.extend(block);
+        let offset = bytes.len() as u32 - 4;
+        bytes[0..4].copy_from_slice(&offset.to_le_bytes());
+    }
+    for (sid, chromosome, bp_position, probabilities) in snps {
+        for text in [&format!("v_{sid}"), *sid, *chromosome] {
+            bytes.extend((text.len() as u16).to_le_bytes());
+            bytes.extend(text.as_bytes());
+        }
+        bytes.extend(bp_position.to_le_bytes());
+        bytes.extend(2u16.to_le_bytes());
+        for allele in ["A", "G"] {
+            bytes.extend(1u32.to_le_bytes());
+            bytes.extend(allele.as_bytes());
+        }
+
+        let mut data = vec![];
+        data.extend((iid_count as u32).to_le_bytes());
+        data.extend(2u16.to_le_bytes());
+        data.extend([2, 2]);
+        data.extend(
+            probabilities
+                .iter()
+                .map(|p| if p.is_some() { 2u8 } else { 0x82 }),
+        );
+        data.extend([u8::from(is_phased), bits as u8]);
+        let mut packed = vec![0u8; (iid_count * 2 * bits as usize).div_ceil(8)];
+        let max_value = ((1u64 << bits) - 1) as f64;
+        for (value_i, p) in probabilities
+            .iter()
+            .flat_map(|p| p.unwrap_or([0.0, 0.0]))
+            .enumerate()
+        {
+            let value = (p * max_value).round() as u64;
+            for bit_i in 0..bits as usize {
+                let bit = value_i * bits as usize + bit_i;
+                packed[bit / 8] |= (((value >> bit_i) & 1) as u8) << (bit % 8);
+            }
+        }
+        data.extend(packed);
+
+        match compression {
+            0 => {
+                bytes.extend((data.len() as u32).to_le_bytes());
+                bytes.extend(data);
+            }
+            1 => {
+                let mut encoder =
+                    flate2::write::ZlibEncoder::new(vec![], flate2::Compression::default());
+                encoder.write_all(&data)?;
+                let compressed = encoder.finish()?;
+                bytes.extend((compressed.len() as u32 + 4).to_le_bytes());
+                bytes.extend((data.len() as u32).to_le_bytes());
+                bytes.extend(compressed);
+            }
+            #[cfg(feature = "zstd")]
+            2 => {
+                let compressed = zstd::encode_all(data.as_slice(), 0)?;
+                bytes.extend((compressed.len() as u32 + 4).to_le_bytes());
+                bytes.extend((data.len() as u32).to_le_bytes());
+                bytes.extend(compressed);
+            }
+            _ => panic!("unknown compression {compression}"),
+        }
+    }
+    std::fs::write(path, bytes)?;
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "gzip")]
+fn read_dosage_from_bgen() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let val = nd::Array2::<i8>::from_shape_fn((7, 5), |(iid_i, sid_i)| {
+        match (iid_i * 3 + sid_i * 5) % 7 {
+            6 => -127,
+            code => (code % 3) as i8,
+        }
+    });
+    let iid = ["i0", "i1", "i2", "i3", "i4", "i5", "i6"];
+    // Hard calls as probabilities, unphased and phased
+    let snps = |is_phased: bool| -> Vec<(&str, &str, u32, Vec<Option<[f64; 2]>>)> {
+        let sid = ["rs0", "rs1", "rs2", "rs3", "rs4"];
+        sid.iter()
+            .enumerate()
+            .map(|(sid_i, sid)| {
+                let probabilities = val
+                    .column(sid_i)
+                    .iter()
+                    .map(|&v| match (v, is_phased) {
+                        (-127, _) => None,
+                        (2, _) => Some([1.0, if is_phased { 1.0 } else { 0.0 }]),
+                        (1, _) => Some([
+                            if is_phased { 1.0 } else { 0.0 },
+                            if is_phased { 0.0 } else { 1.0 },
+                        ]),
+                        _ => Some([0.0, 0.0]),
+                    })
+                    .collect();
+                (
+                    *sid,
+                    if sid_i < 3 { "1" } else { "2" },
+                    100 * sid_i as u32 + 1,
+                    probabilities,
+                )
+            })
+            .collect()
+    };
+    let compressions = [
+        0,
+        1,
+        #[cfg(feature = "zstd")]
+        2,
+    ];
+
+    for compression in compressions {
+        for is_phased in [false, true] {
+            let bgen_file = output_folder.join(format!("hard_{compression}_{is_phased}.bgen"));
+            write_bgen(
+                &bgen_file,
+                compression,
+                Some(&iid),
+                &snps(is_phased),
+                is_phased,
+                8,
+            )?;
+
+            let read_options = ReadOptions::builder().i8().build()?;
+            let (read, metadata) = Bed::read_dosage_from_bgen(&bgen_file, &read_options)?;
+            assert_eq!(read, val);
+            assert_eq!(
+                metadata.iid().unwrap(),
+                &nd::Array1::from_iter(iid.map(String::from))
+            );
+            assert_eq!(metadata.sid().unwrap()[4], "rs4");
+            assert_eq!(metadata.chromosome().unwrap()[3], "2");
+            assert_eq!(metadata.bp_position().unwrap()[2], 201);
+            assert_eq!(metadata.allele_1().unwrap()[0], "A");
+            assert_eq!(metadata.allele_2().unwrap()[0], "G");
+
+            let read_options = ReadOptions::builder()
+                .iid_index([-1, 0, 3])
+                .sid_index(s![1..;2])
+                .count_a2()
+                .c()
+                .f64()
+                .build()?;
+            let (read, _) = Bed::read_dosage_from_bgen(&bgen_file, &read_options)?;
+            let expected = val
+                .select(nd::Axis(0), &[6, 0, 3])
+                .select(nd::Axis(1), &[1, 3])
+                .mapv(|v| {
+                    if v == -127 {
+                        f64::NAN
+                    } else {
+                        2.0 - f64::from(v)
+                    }
+                });
+            assert_eq_nan(&read, &expected);
+            assert!(read.is_standard_layout());
+        }
+    }
+
+    // Dosages of unphased probabilities, without sample identifiers
+    let bgen_file = output_folder.join("dosage.bgen");
+    let probabilities = vec![Some([0.2, 0.5]), Some([0.0, 0.25]), None, Some([0.9, 0.1])];
+    write_bgen(
+        &bgen_file,
+        1,
+        None,
+        &[("rs0", "1", 1, probabilities)],
+        false,
+        16,
+    )?;
+    let read_options = ReadOptions::builder().f32().is_transposed(true).build()?;
+    let (read, metadata) = Bed::read_dosage_from_bgen(&bgen_file, &read_options)?;
+    assert!(metadata.iid().is_none());
+    assert!(allclose(
+        &read.view(),
+        &nd::array![[0.9, 0.25, f32::NAN, 1.9]].view(),
+        1e-4,
+        true
+    ));
+
+    // Errors
+    let result = Bed::read_dosage_from_bgen(
+        &bgen_file,
+        &ReadOptions::builder().sid_index(1).f64().build()?,
+    );
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::SidIndexTooBig(1)));
+    let mut bytes = std::fs::read(&bgen_file)?;
+    bytes[20] = 1 << 2; // Layout 1
+    std::fs::write(&bgen_file, &bytes)?;
+    let result = Bed::read_dosage_from_bgen(&bgen_file, &ReadOptions::builder().f64().build()?);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::UnsupportedBgen(_, _))
+    );
+    bytes[16] = b'x';
+    std::fs::write(&bgen_file, &bytes)?;
+    let result = Bed::read_dosage_from_bgen(&bgen_file, &ReadOptions::builder().f64().build()?);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::IllFormedBgen(_, _))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn psam_pvar_round_trip() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let metadata = Metadata::builder()
+        .fid(["f1", "f1", "f2"])
+        .iid(["i1", "i2", "i3"])
+        .father(["0", "i1", "0"])
+        .mother(["0", "0", "i2"])
+        .sex([1, 2, 0])
+        .pheno(["1.5", "-9", "2"])
+        .chromosome(["1", "X", "MT", "22"])
+        .sid(["rs1", "rs2", "rs3", "rs4"])
+        .cm_position([0.0, 1.25, 3.5, 100.0])
+        .bp_position([10, 20, 30, 2_000_000])
+        .allele_1(["G", "T", "AC,AG", "C"])
+        .allele_2(["A", "C", "A", "T"])
+        .build()?;
+    let psam_file = output_folder.join("round_trip.psam");
+    let pvar_file = output_folder.join("round_trip.pvar");
+    metadata.write_psam(&psam_file)?;
+    metadata.write_pvar(&pvar_file)?;
+
+    let skip_set = HashSet::new();
+    let (read_metadata, iid_count) = Metadata::new().read_psam(&psam_file, &skip_set)?;
+    let (read_metadata, sid_count) = read_metadata.read_pvar(&pvar_file, &skip_set)?;
+    assert_eq!((iid_count, sid_count), (3, 4));
+    assert_eq!(read_metadata, metadata);
+
+    // Known and skipped fields are kept
+    let known = Metadata::builder().iid(["a", "b", "c"]).build()?;
+    let skip_set = HashSet::from([MetadataFields::Pheno, MetadataFields::CmPosition]);
+    let (read_metadata, _) = known.read_psam(&psam_file, &skip_set)?;
+    let (read_metadata, _) = read_metadata.read_pvar(&pvar_file, &skip_set)?;
+    assert_eq!(read_metadata.iid(), known.iid());
+    assert_eq!(read_metadata.fid(), metadata.fid());
+    assert!(read_metadata.pheno().is_none());
+    assert!(read_metadata.cm_position().is_none());
+
+    // PLINK 2 style files, with comments, extra columns, and letter sexes
+    std::fs::write(
+        &psam_file,
+        "#IID\tSID\tSEX\tPHENO1\tPHENO2\nid1\ts1\tM\t1\t5\nid2\ts1\tF\t2\t6\n",
+    )?;
+    std::fs::write(
+        &pvar_file,
+        "##fileformat=VCFv4.2\n##INFO=<ID=AF,Number=A,Type=Float>\n\
+         #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n\
+         1\t100\trs1\tA\tG\t.\tPASS\tAF=0.1\n",
+    )?;
+    let (read_metadata, _) = Metadata::new().read_psam(&psam_file, &HashSet::new())?;
+    let (read_metadata, _) = read_metadata.read_pvar(&pvar_file, &HashSet::new())?;
+    assert_eq!(read_metadata.sex(), Some(&nd::array![1, 2]));
+    assert_eq!(read_metadata.pheno().unwrap(), nd::array!["1", "2"]);
+    assert!(read_metadata.fid().is_none());
+    assert!(read_metadata.father().is_none());
+    assert_eq!(read_metadata.allele_1().unwrap(), nd::array!["G"]);
+    assert!(read_metadata.cm_position().is_none());
+    let filled = read_metadata.fill(2, 1)?;
+    assert_eq!(filled.fid().unwrap(), nd::array!["0", "0"]);
+
+    // Without headers, the files are read as .fam and .bim files
+    std::fs::write(&psam_file, "f1 i1 0 0 2 1\nf2 i2 0 0 1 -9\n")?;
+    std::fs::write(&pvar_file, "1\trs1\t0.5\t100\tG\tA\n")?;
+    let (read_metadata, _) = Metadata::new().read_psam(&psam_file, &HashSet::new())?;
+    let (read_metadata, _) = read_metadata.read_pvar(&pvar_file, &HashSet::new())?;
+    assert_eq!(read_metadata.fid().unwrap(), nd::array!["f1", "f2"]);
+    assert_eq!(read_metadata.sex(), Some(&nd::array![2, 1]));
+    assert_eq!(read_metadata.cm_position(), Some(&nd::array![0.5]));
+    assert_eq!(read_metadata.allele_2().unwrap(), nd::array!["A"]);
+
+    // Errors
+    std::fs::write(&pvar_file, "#CHROM\tPOS\tID\tREF\n1\t100\trs1\tA\n")?;
+    let result = Metadata::new().read_pvar(&pvar_file, &HashSet::new());
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::MissingPlink2Column(_, _))
+    );
+    std::fs::write(&psam_file, "#IID\tSEX\nid1\t1\nid2\n")?;
+    let result = Metadata::new().read_psam(&psam_file, &HashSet::new());
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::MetadataFieldCount(2, 1, _))
+    );
+    let result = Metadata::builder()
+        .iid(["i1"])
+        .build()?
+        .write_psam(&psam_file);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::MetadataMissingForWrite(_))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn from_vcf() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let header = "##fileformat=VCFv4.2\n##contig=<ID=1>\n\
+                  #CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\ta\tb\tc\n";
+    let records = "1\t10\trs1\tA\tG\t.\tPASS\t.\tGT\t0/0\t0/1\t1/1\n\
+                   1\t20\trs2\tA\tG,T\t.\tPASS\t.\tGT\t0/0\t0/1\t1/2\n\
+                   1\t30\t.\tC\tT\t50\tPASS\tAF=0.5\tDP:GT\t3:1|0\t4:.|.\t5:0|1\n\
+                   X\t40\trs4\tG\tA\t.\t.\t.\tGT\t1\t0\t.\n\
+                   X\t50\trs5\tT\tC\t.\t.\t.\tDP\t3\t4\t5\n";
+    let expected = nd::array![[0, 1, 2, -127], [1, -127, 0, -127], [2, 1, -127, -127]];
+
+    // Plain and bgzipped, that is, gzipped in several members
+    let vcf_file = output_folder.join("small.vcf");
+    std::fs::write(&vcf_file, format!("{header}{records}"))?;
+    #[cfg(feature = "gzip")]
+    let vcf_gz_file = output_folder.join("small.vcf.gz");
+    #[cfg(feature = "gzip")]
+    {
+        use std::io::Write;
+
+        let mut bytes = Vec::new();
+        for text in [header, records] {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(text.as_bytes())?;
+            bytes.extend(encoder.finish()?);
+        }
+        std::fs::write(&vcf_gz_file, bytes)?;
+    }
+
+    for (name, path) in [
+        ("plain", &vcf_file),
+        #[cfg(feature = "gzip")]
+        ("gz", &vcf_gz_file),
+    ] {
+        let output_file = output_folder.join(format!("{name}.bed"));
+        let summary = Bed::from_vcf(path, &WriteOptions::builder(&output_file))?;
+        assert_eq!(summary.genotype_counts(), [2, 3, 2]);
+        let bed = Bed::new(&output_file)?;
+        assert_eq!(bed.read::<i8>()?, expected);
+        assert_eq!(bed.iid()?, nd::array!["a", "b", "c"]);
+        assert_eq!(bed.fid()?, nd::array!["0", "0", "0"]);
+        assert_eq!(bed.sid()?, nd::array!["rs1", "1:30", "rs4", "rs5"]);
+        assert_eq!(bed.chromosome()?, nd::array!["1", "1", "X", "X"]);
+        assert_eq!(bed.bp_position()?, nd::array![10, 30, 40, 50]);
+        assert_eq!(bed.allele_1()?, nd::array!["G", "T", "A", "C"]);
+        assert_eq!(bed.allele_2()?, nd::array!["A", "C", "G", "T"]);
+    }
+
+    // Metadata in the write options is kept
+    let output_file = output_folder.join("options.bed");
+    Bed::from_vcf(
+        &vcf_file,
+        &WriteOptions::builder(&output_file)
+            .fid(["f1", "f2", "f3"])
+            .sid(["s1", "s2", "s3", "s4"])
+            .missing_value(-1),
+    )?;
+    let bed = Bed::new(&output_file)?;
+    assert_eq!(bed.fid()?, nd::array!["f1", "f2", "f3"]);
+    assert_eq!(bed.iid()?, nd::array!["a", "b", "c"]);
+    assert_eq!(bed.sid()?, nd::array!["s1", "s2", "s3", "s4"]);
+    assert_eq!(bed.read::<i8>()?, expected);
+
+    // Errors
+    let bad_file = output_folder.join("bad.vcf");
+    std::fs::write(
+        &bad_file,
+        format!("{header}{records}1\t60\trs6\tA\tG\t.\t.\t.\tGT\t0/3\t0/0\t0/0\n"),
+    )?;
+    let output_file = output_folder.join("bad.bed");
+    let result = Bed::from_vcf(&bad_file, &WriteOptions::builder(&output_file));
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::IllFormedVcf(9, _)));
+    assert!(!output_file.exists());
+    std::fs::write(&bad_file, records)?;
+    let result = Bed::from_vcf(&bad_file, &WriteOptions::builder(&output_file));
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::IllFormedVcf(1, _)));
+
+    Ok(())
+}
+
+#[test]
+fn stats_options_deterministic() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("deterministic.bed");
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut val =
+        nd::Array::random_using((3000, 5), Uniform::from(0..3), &mut rng).mapv(|v| v as i8);
+    val.slice_mut(s![..100, 1]).fill(-127);
+    WriteOptions::builder(&output_file).write(&val)?;
+    let mut bed = Bed::new(&output_file)?;
+
+    assert!(!StatsOptions::new().is_deterministic());
+    assert!(StatsOptions::deterministic().is_deterministic());
+    let stats_options = StatsOptions::deterministic();
+
+    // The stats don't depend on the number of threads or the value type
+    let stats = stats_options.stats(
+        &mut bed,
+        &ReadOptions::builder().num_threads(1).f64().build()?,
+    )?;
+    for read_options in [
+        ReadOptions::builder().num_threads(4).f64().build()?,
+        ReadOptions::builder().c().f64().build()?,
+    ] {
+        assert_eq!(stats, stats_options.stats(&bed, &read_options)?);
+    }
+    let stats_f32 = stats_options.stats(&bed, &ReadOptions::builder().f32().build()?)?;
+    assert_eq!(stats.mean(), stats_f32.mean());
+    // ... and agree with the default summation
+    let stats_default = bed.stats()?;
+    let mean_diff = stats.mean() - stats_default.mean();
+    assert!(mean_diff.iter().all(|diff| diff.abs() < 1e-12));
+    assert_eq!(stats.call_count(), stats_default.call_count());
+
+    // Standardizing f32 and f64 values, in C- or F-order, gives the same stats
+    let mut val64 = ReadOptions::builder().f().f64().read(&mut bed)?;
+    let mut val64_c = ReadOptions::builder().c().f64().read(&mut bed)?;
+    let mut val32 = ReadOptions::builder().f32().read(&mut bed)?;
+    let stats64 = stats_options.standardize(&mut val64, Dist::Unit)?;
+    assert_eq!(
+        stats64,
+        stats_options.standardize(&mut val64_c, Dist::Unit)?
+    );
+    assert_eq!(val64, val64_c);
+    let stats32 = stats_options.standardize(&mut val32, Dist::Unit)?;
+    assert_eq!(stats32, stats64.mapv(|v| v as f32));
+    assert_eq!(stats64.column(0), stats.mean());
+
+    // ... which agree with the default summation
+    let mut val_default = ReadOptions::builder().f64().read(&mut bed)?;
+    let stats_default = StatsOptions::new().standardize(&mut val_default, Dist::Unit)?;
+    assert!(allclose(
+        &stats64.view(),
+        &stats_default.view(),
+        1e-9,
+        false
+    ));
+    assert!(allclose(&val64.view(), &val_default.view(), 1e-9, false));
+
+    // Errors
+    let mut val = nd::array![[f64::NAN, 1.0], [f64::NAN, 2.0]];
+    let result = stats_options.standardize(&mut val, Dist::Unit);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::NoIndividuals));
+
+    Ok(())
+}
+
+#[test]
+fn missing_policy() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("missing_policy.bed");
+    let val = nd::array![
+        [2, 0, 1, 2],
+        [-127, 0, 1, 2],
+        [0, 1, -127, 2],
+        [1, -127, -127, 0]
+    ];
+    WriteOptions::builder(&output_file).write(&val)?;
+    let mut bed = Bed::new(&output_file)?;
+    let read_options = ReadOptions::builder().f64().build()?;
+
+    assert_eq!(StatsOptions::new().missing_policy(), MissingPolicy::Skip);
+    let skip = StatsOptions::new();
+    let impute = StatsOptions::new().with_missing_policy(MissingPolicy::MeanImpute);
+    let propagate = StatsOptions::deterministic().with_missing_policy(MissingPolicy::Propagate);
+    assert!(propagate.is_deterministic());
+
+    // Stats
+    let stats = skip.stats(&bed, &read_options)?;
+    assert_eq!(stats, bed.stats()?);
+    assert_eq!(stats, impute.stats(&bed, &read_options)?);
+    let propagated = propagate.stats(&bed, &read_options)?;
+    assert!(propagated.mean().iter().take(3).all(|mean| mean.is_nan()));
+    assert_eq!(propagated.mean()[3], 1.5);
+    assert_eq!(propagated.call_count(), stats.call_count());
+    assert_eq!(propagated.missing_rate(), stats.missing_rate());
+
+    // Aggregation
+    let labels = ["a", "a", "b", "b"];
+    let (_, skipped) = skip.aggregate_by(&mut bed, labels, Agg::AlleleCount)?;
+    assert_eq!(skipped, bed.aggregate_by(labels, Agg::AlleleCount)?.1);
+    assert_eq!(
+        skipped,
+        nd::array![[2.0, 0.0, 2.0, 4.0], [1.0, 1.0, 0.0, 2.0]]
+    );
+    let (_, imputed) = impute.aggregate_by(&mut bed, labels, Agg::AlleleCount)?;
+    assert!(allclose(
+        &imputed.view(),
+        &nd::array![[3.0, 0.0, 2.0, 4.0], [1.0, 4.0 / 3.0, 2.0, 2.0]].view(),
+        1e-12,
+        false
+    ));
+    let (_, imputed) = impute.aggregate_by(&mut bed, labels, Agg::MeanDosage)?;
+    assert_eq!(imputed.column(0), nd::array![1.5, 0.5]);
+    let (_, propagated) = propagate.aggregate_by(&mut bed, labels, Agg::MeanDosage)?;
+    assert_eq_nan(
+        &propagated,
+        &nd::array![[f64::NAN, 0.0, 1.0, 2.0], [0.5, f64::NAN, f64::NAN, 1.0]],
+    );
+
+    // Standardization
+    let mut val_skip = bed.read::<f64>()?;
+    let mut val_impute = val_skip.clone();
+    let mut val_propagate = val_skip.clone();
+    let mut val_complete = val_skip.slice(s![.., 3..]).to_owned();
+    let stats_skip = skip.standardize(&mut val_skip, Dist::Unit)?;
+    assert_eq!(stats_skip, impute.standardize(&mut val_impute, Dist::Unit)?);
+    assert_eq!(val_skip, val_impute);
+    let stats_propagate = propagate.standardize(&mut val_propagate, Dist::Unit)?;
+    let stats_complete = propagate.standardize(&mut val_complete, Dist::Unit)?;
+    assert!(stats_propagate
+        .slice(s![..3, ..])
+        .iter()
+        .all(|v| v.is_nan()));
+    assert!(val_propagate.slice(s![.., ..3]).iter().all(|v| v.is_nan()));
+    assert_eq!(stats_propagate.slice(s![3.., ..]), stats_complete);
+    assert_eq!(val_propagate.slice(s![.., 3..]), val_complete);
+
+    Ok(())
+}
+
+#[test]
+fn write_validate() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("validate.bed");
+    let val = nd::array![[0.0, 1.0, 2.0], [1.0, f64::NAN, 2.4], [2.0, 7.0, 0.0]];
+
+    // By default, only the file is named
+    let result = WriteOptions::builder(&output_file).write(&val);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::BadValue(_)));
+    assert!(!output_file.exists());
+
+    // The first bad value, in SNP order, is reported
+    let result = WriteOptions::builder(&output_file)
+        .validate(true)
+        .write(&val);
+    match result {
+        Err(e) => match *e {
+            BedErrorPlus::BedError(BedError::BadValueAt(iid_index, sid_index, value, path)) => {
+                assert_eq!((iid_index, sid_index, value.as_str()), (2, 1, "7.0"));
+                assert_eq!(path, output_file);
+            }
+            _ => panic!("unexpected error {e}"),
+        },
+        Ok(()) => panic!("expected an error"),
+    }
+    assert!(!output_file.exists());
+
+    // A BedWriter counts SNPs over its blocks
+    let mut writer = WriteOptions::builder(&output_file)
+        .validate(true)
+        .bed_writer(3)?;
+    writer.write_block(&nd::array![[0.0], [1.0], [2.0]])?;
+    let result = writer.write_block(&val.slice(s![.., 1..]));
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::BadValueAt(2, 1, _, _))
+    );
+    let result = writer.write_block(&val.slice(s![.., 2..]));
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::BadValueAt(1, 1, _, _))
+    );
+    writer.finish()?;
+
+    // Values can be checked before writing, with the write options
+    let write_options = WriteOptions::builder(&output_file).build(3, 3)?;
+    assert!(write_options.validate_val(&val.slice(s![.., ..1])).is_err());
+    let result = write_options.validate_val(&val);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::BadValueAt(2, 1, _, _))
+    );
+    let write_options = WriteOptions::builder(&output_file)
+        .missing_if(|v| v == 7.0)
+        .tolerance(0.49)
+        .build(3, 3)?;
+    write_options.validate_val(&val)?;
+    assert!(!write_options.validate());
+    let result = write_options.validate_val(&val.slice(s![..2, ..]));
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::InconsistentCount(_, 3, 2))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn read_u8_and_missing_mask() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("u8.bed");
+    let val = nd::array![[1i8, 0, -127, 0], [2, 0, -127, 2], [0, 1, 2, -127]];
+    WriteOptions::builder(&output_file).write(&val)?;
+    let bed = Bed::new(&output_file)?;
+
+    let val_u8 = bed.read_u8(u8::MAX)?;
+    assert_eq!(val_u8, val.mapv(|v| if v < 0 { u8::MAX } else { v as u8 }));
+    assert_eq!(bed.read_missing_mask()?, val.mapv(|v| v == -127));
+
+    // The options' missing value is ignored, and the memory order and selection are kept
+    for is_f in [false, true] {
+        let read_options = ReadOptions::builder()
+            .iid_index([2, 0])
+            .sid_index(1..)
+            .is_f(is_f)
+            .missing_value(1)
+            .i8()
+            .build()?;
+        let expected = val
+            .select(nd::Axis(0), &[2, 0])
+            .slice(s![.., 1..])
+            .to_owned();
+        let val_u8 = bed.read_u8_with_options(&read_options, 3)?;
+        assert_eq!(val_u8.is_standard_layout(), !is_f);
+        assert_eq!(val_u8, expected.mapv(|v| if v < 0 { 3 } else { v as u8 }));
+        let mask = bed.read_missing_mask_with_options(&read_options)?;
+        assert_eq!(mask.is_standard_layout(), !is_f);
+        assert_eq!(mask, expected.mapv(|v| v == -127));
+    }
+
+    // Options for other value types give the same counts and mask
+    let read_options = ReadOptions::builder().is_transposed(true).f32().build()?;
+    assert_eq!(
+        bed.read_u8_with_options(&read_options, 9)?,
+        bed.read_u8(9)?.reversed_axes()
+    );
+    assert_eq!(
+        bed.read_missing_mask_with_options(&read_options)?,
+        bed.read_missing_mask()?.reversed_axes()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn metadata_field() -> Result<(), Box<BedErrorPlus>> {
+    let metadata = Metadata::builder()
+        .iid(["i1", "i2"])
+        .sid(["s1", "s2", "s3"])
+        .build()?
+        .fill(2, 3)?;
+
+    // Every field can be copied without knowing its type
+    let mut builder = Metadata::builder();
+    for field in MetadataFields::ALL {
+        let values = metadata.field(field).unwrap();
+        let expected_len = if MetadataFields::ALL[..6].contains(&field) {
+            2
+        } else {
+            3
+        };
+        assert_eq!(values.len(), expected_len, "{}", field.name());
+        assert_eq!(values.to_strings().len(), expected_len);
+        builder.field(field, values)?;
+    }
+    assert_eq!(builder.build()?, metadata);
+
+    assert_eq!(
+        metadata
+            .field(MetadataFields::CmPosition)
+            .unwrap()
+            .to_strings(),
+        nd::array!["0", "0", "0"]
+    );
+    assert!(Metadata::new().field(MetadataFields::Sex).is_none());
+    assert_eq!(MetadataFields::Allele1.name(), "allele_1");
+
+    let sid = nd::array!["a".to_string()];
+    let result = builder.field(MetadataFields::Sex, MetadataArray::String(sid.view()));
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::MetadataFieldType(_))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn genotype_calls() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("genotype_calls.bed");
+    WriteOptions::builder(&output_file)
+        .allele_1(["A", "G", "ATT"])
+        .allele_2(["C", "T", "A"])
+        .write(&nd::array![[0, 2, -127], [1, -127, 2], [2, 0, 1]])?;
+
+    let bed = Bed::new(&output_file)?;
+    let calls = bed.genotype_calls(..)?;
+    assert_eq!(
+        calls,
+        nd::array![
+            ["CC", "GG", "--"],
+            ["AC", "--", "ATT/ATT"],
+            ["AA", "TT", "ATT/A"]
+        ]
+    );
+
+    // Negative indexes count from the end and keep the alleles with their SNPs
+    let calls = bed.genotype_calls([-1, 0])?;
+    assert_eq!(
+        calls,
+        nd::array![["--", "CC"], ["ATT/ATT", "AC"], ["ATT/A", "AA"]]
+    );
+
+    let result = bed.genotype_calls(3);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::SidIndexTooBig(_)));
+
+    Ok(())
+}
+
+#[test]
+fn extract() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let input_file = output_folder.join("extract_in.bed");
+    let val = nd::array![
+        [0, 1, 2, 0, 1],
+        [2, -127, 0, 1, 1],
+        [1, 1, 1, 2, 0],
+        [0, 2, -127, 0, 2]
+    ];
+    WriteOptions::builder(&input_file)
+        .iid(["i1", "i2", "i3", "i4"])
+        .sex([1, 2, 1, 2])
+        .chromosome(["1", "1", "2", "2", "2"])
+        .bp_position([10, 20, 10, 20, 30])
+        .allele_1(["A", "C", "G", "T", "A"])
+        .write(&val)?;
+    let mut bed = Bed::new(&input_file)?;
+
+    // Select with a predicate and a region
+    let read_options = ReadOptions::builder()
+        .iid_where(|row| row.sex == 2)
+        .region("2:15-30")?
+        .i8()
+        .build()?;
+    let output_file = output_folder.join("extract_out.bed");
+    let summary = bed.extract(&read_options, &WriteOptions::builder(&output_file))?;
+    assert_eq!(summary.genotype_counts(), [1, 2, 1]);
+
+    let subset = Bed::new(&output_file)?;
+    assert_eq!(subset.read::<i8>()?, bed.read_with_options(&read_options)?);
+    assert_eq!(subset.iid()?, nd::array!["i2", "i4"]);
+    assert_eq!(subset.sex()?, nd::array![2, 2]);
+    assert_eq!(subset.bp_position()?, nd::array![20, 30]);
+    assert_eq!(subset.allele_1()?, nd::array!["T", "A"]);
+
+    // Metadata given in the write options is kept
+    let read_options = ReadOptions::builder().sid_index([-1, 0]).i8().build()?;
+    let write_options = WriteOptions::builder(&output_file).sid(["last", "first"]);
+    bed.extract(&read_options, &write_options)?;
+    let subset = Bed::new(&output_file)?;
+    assert_eq!(subset.sid()?, nd::array!["last", "first"]);
+    assert_eq!(subset.chromosome()?, nd::array!["2", "1"]);
+    assert_eq!(subset.read::<i8>()?, val.select(nd::Axis(1), &[4, 0]));
+
+    // The input can't be overwritten and a bad index leaves no output
+    let result = bed.extract(&read_options, &WriteOptions::builder(&input_file));
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::ExtractOverwritesInput(_))
+    );
+    let bad_file = output_folder.join("extract_bad.bed");
+    let read_options = ReadOptions::builder().iid_index(4).i8().build()?;
+    let result = bed.extract(&read_options, &WriteOptions::builder(&bad_file));
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::IidIndexTooBig(_)));
+    assert!(!bad_file.exists());
+    assert_eq!(bed.read::<i8>()?, val);
+
+    Ok(())
+}
+
+#[test]
+fn concat() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let val = nd::array![[0, 1, 2, -127], [2, 0, 1, 1], [1, 1, 0, 2]];
+    let write_part = |name: &str, iid: &[&str], sid: &[&str], val: nd::ArrayView2<i8>| {
+        let path = output_folder.join(name);
+        WriteOptions::builder(&path)
+            .iid(iid)
+            .sid(sid)
+            .write(&val)
+            .map(|()| path)
+    };
+
+    // Split by SNPs and join again
+    let left = write_part(
+        "left.bed",
+        &["a", "b", "c"],
+        &["s1"],
+        val.slice(s![.., ..1]),
+    )?;
+    let right = write_part(
+        "right.bed",
+        &["a", "b", "c"],
+        &["s2", "s3", "s4"],
+        val.slice(s![.., 1..]),
+    )?;
+    let mut beds = vec![Bed::new(&left)?, Bed::new(&right)?];
+    let output_file = output_folder.join("by_sid.bed");
+    let summary = Bed::concat_sid(&mut beds, &WriteOptions::<i8>::builder(&output_file))?;
+    assert_eq!(summary.genotype_counts(), [3, 5, 3]);
+    let bed = Bed::new(&output_file)?;
+    assert_eq!(bed.read::<i8>()?, val);
+    assert_eq!(bed.sid()?, nd::array!["s1", "s2", "s3", "s4"]);
+
+    // Split by individuals and join again, counting allele 2 and with NaN as missing
+    let top = write_part(
+        "top.bed",
+        &["a"],
+        &["s1", "s2", "s3", "s4"],
+        val.slice(s![..1, ..]),
+    )?;
+    let bottom = write_part(
+        "bottom.bed",
+        &["b", "c"],
+        &["s1", "s2", "s3", "s4"],
+        val.slice(s![1.., ..]),
+    )?;
+    let mut beds = vec![Bed::new(&top)?, Bed::new(&bottom)?];
+    let output_file = output_folder.join("by_iid.bed");
+    Bed::concat_iid(
+        &mut beds,
+        WriteOptions::<f64>::builder(&output_file).count_a2(),
+    )?;
+    let bed = Bed::new(&output_file)?;
+    assert_eq!(bed.read::<i8>()?, val);
+    assert_eq!(bed.iid()?, nd::array!["a", "b", "c"]);
+
+    // Mismatched metadata, no files, or an input as output
+    let other = write_part(
+        "other.bed",
+        &["a", "b", "x"],
+        &["s1"],
+        val.slice(s![.., ..1]),
+    )?;
+    let mut beds = vec![Bed::new(&left)?, Bed::new(&other)?];
+    let result = Bed::concat_sid(&mut beds, &WriteOptions::<i8>::builder(&output_file));
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::BedSetFamMismatch(_, _))
+    );
+    let result = Bed::concat_iid(&mut [], &WriteOptions::<i8>::builder(&output_file));
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::EmptyConcat));
+    let mut beds = vec![Bed::new(&left)?, Bed::new(&right)?];
+    let result = Bed::concat_iid(&mut beds, &WriteOptions::<i8>::builder(&output_file));
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::BimMetadataMismatch(_, _))
+    );
+    let result = Bed::concat_sid(&mut beds, &WriteOptions::<i8>::builder(&right));
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::ConcatOverwritesInput(_))
+    );
+    assert_eq!(Bed::new(&right)?.read::<i8>()?, val.slice(s![.., 1..]));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "checksum")]
+fn copy_fileset() -> Result<(), Box<BedErrorPlus>> {
+    use bed_reader::{copy_fileset, CopyOptions};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let output_folder = TempDir::default();
+    let source = output_folder.join("copy_source.bed");
+    let val = nd::array![[0, 1, 2, -127], [2, 0, 1, 1], [1, 1, 0, 2]];
+    WriteOptions::builder(&source).write(&val)?;
+
+    // Copy with the default options, given prefixes without the extension
+    let summary = copy_fileset(
+        output_folder.join("copy_source"),
+        output_folder.join("copy_target"),
+        &CopyOptions::default(),
+    )?;
+    assert!(summary.is_verified());
+    let total_len: u64 = ["bed", "fam", "bim"]
+        .iter()
+        .map(|extension| {
+            std::fs::metadata(source.with_extension(extension))
+                .unwrap()
+                .len()
+        })
+        .sum();
+    assert_eq!(summary.bytes(), total_len);
+    let target = output_folder.join("copy_target.bed");
+    for extension in ["bed", "fam", "bim"] {
+        assert_eq!(
+            std::fs::read(source.with_extension(extension))?,
+            std::fs::read(target.with_extension(extension))?
+        );
+    }
+    assert_eq!(Bed::new(&target)?.read::<i8>()?, val);
+    assert!(!output_folder.join("copy_target.bed.append_tmp").exists());
+
+    // Progress is reported and verifying can be turned off
+    let calls = Arc::new(AtomicUsize::new(0));
+    let calls_clone = Arc::clone(&calls);
+    let copy_options = CopyOptions::builder()
+        .verify(false)
+        .progress(move |bytes, total| {
+            assert!(bytes <= total);
+            calls_clone.fetch_add(1, Ordering::Relaxed);
+        })
+        .build()?;
+    let unverified = copy_fileset(&source, &target, &copy_options)?;
+    assert!(!unverified.is_verified());
+    assert_eq!(unverified.bed_checksum(), summary.bed_checksum());
+    assert_ne!(unverified.fam_checksum(), unverified.bim_checksum());
+    assert_eq!(calls.load(Ordering::Relaxed), 3);
+
+    // A fileset can't be copied onto itself, and a bad source leaves the target as it was
+    let result = copy_fileset(&source, &source, &CopyOptions::default());
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::CopyOntoSource(_)));
+    let bytes = std::fs::read(&source)?;
+    std::fs::write(&source, &bytes[..bytes.len() - 1])?;
+    let result = copy_fileset(&source, &target, &CopyOptions::default());
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::IllFormed(_)));
+    assert_eq!(Bed::new(&target)?.read::<i8>()?, val);
+
+    Ok(())
+}
+
+#[test]
+fn estimate_read_cost() -> Result<(), Box<BedErrorPlus>> {
+    use bed_reader::ReadStrategy;
+
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("read_cost.bed");
+    // 10 bytes per SNP (variant)
+    WriteOptions::builder(&output_file)
+        .chromosome(vec!["1"; 100])
+        .bp_position((1..=100).collect::<Vec<i32>>())
+        .write(&nd::Array2::<i8>::zeros((40, 100)))?;
+    let mut bed = Bed::new(&output_file)?;
+
+    // The first 8 individuals need only 2 bytes of each SNP, so scattered SNPs are read apart
+    let read_options = ReadOptions::builder()
+        .iid_index(0..8)
+        .sid_index([90, 10, 11, 12, -1])
+        .f32()
+        .build()?;
+    let cost = bed.estimate_read_cost(&read_options)?;
+    assert_eq!(cost.strategy(), ReadStrategy::Sequential);
+    assert_eq!(cost.dim(), (8, 5));
+    assert_eq!(cost.output_bytes(), 8 * 5 * 4);
+    assert_eq!((cost.bytes(), cost.seeks()), (2 + 22 + 2, 3));
+
+    // Each strategy plans its own reads
+    let strategy_cost = |bed: &mut Bed, strategy| -> Result<_, Box<BedErrorPlus>> {
+        let read_options = ReadOptions::builder()
+            .iid_index(0..8)
+            .sid_index([90, 10, 11, 12, -1])
+            .read_strategy(strategy)
+            .i8()
+            .build()?;
+        let cost = bed.estimate_read_cost(&read_options)?;
+        Ok((cost.bytes(), cost.seeks()))
+    };
+    assert_eq!(
+        strategy_cost(&mut bed, ReadStrategy::BatchedPread)?,
+        (10, 5)
+    );
+    #[cfg(feature = "mmap")]
+    assert_eq!(strategy_cost(&mut bed, ReadStrategy::Mmap)?, (10, 0));
+    assert_eq!(strategy_cost(&mut bed, ReadStrategy::Scan)?, (900, 1));
+
+    // Regions select as in a read, and the estimate matches the output shape
+    let read_options = ReadOptions::builder().region("1:21-30")?.i8().build()?;
+    let cost = bed.estimate_read_cost(&read_options)?;
+    assert_eq!(cost.dim(), bed.read_with_options(&read_options)?.dim());
+    assert_eq!((cost.bytes(), cost.seeks()), (100, 1));
+
+    // Individual-major files are read an individual at a time
+    let individual_major = output_folder.join("read_cost_individual_major.bed");
+    bed.rewrite_as_individual_major(&individual_major)?;
+    let mut bed = Bed::new(&individual_major)?;
+    let read_options = ReadOptions::builder()
+        .iid_index([0, 2])
+        .read_strategy(ReadStrategy::Scan)
+        .i8()
+        .build()?;
+    let cost = bed.estimate_read_cost(&read_options)?;
+    assert_eq!(cost.strategy(), ReadStrategy::Sequential);
+    assert_eq!((cost.bytes(), cost.seeks()), (25 + 25, 2));
+
+    // Bad indexes and throughputs are errors
+    let read_options = ReadOptions::builder().sid_index(100).i8().build()?;
+    let result = bed.estimate_read_cost(&read_options);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::SidIndexTooBig(_)));
+    let result = cost.predicted_time(0.0, 0.0);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::BadThroughput(_)));
+
+    Ok(())
+}
+
+#[test]
+fn progress() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("progress.bed");
+
+    // Writes report each SNP, in order
+    let val = nd::Array2::from_shape_fn((5, 40), |(i, j)| ((i + j) % 3) as i8);
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let reports_clone = Arc::clone(&reports);
+    WriteOptions::builder(&output_file)
+        .progress(move |done, total| reports_clone.lock().unwrap().push((done, total)))
+        .write(&val)?;
+    let expected: Vec<_> = (0..=40).map(|done| (done, 40)).collect();
+    assert_eq!(*reports.lock().unwrap(), expected);
+
+    // Reads report each chunk, with every strategy, and read the same values
+    let bed = Bed::new(&output_file)?;
+    for read_strategy in [
+        ReadStrategy::Sequential,
+        ReadStrategy::BatchedPread,
+        #[cfg(feature = "mmap")]
+        ReadStrategy::Mmap,
+        ReadStrategy::Scan,
+    ] {
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let reports_clone = Arc::clone(&reports);
+        let read_options = ReadOptions::builder()
+            .sid_index([3, 1, -1])
+            .read_strategy(read_strategy)
+            .progress(move |done, total| reports_clone.lock().unwrap().push((done, total)))
+            .c()
+            .i8()
+            .build()?;
+        let read_val = bed.read_with_options(&read_options)?;
+        assert_eq!(read_val, val.select(nd::Axis(1), &[3, 1, 39]));
+        assert_eq!(*reports.lock().unwrap(), vec![(0, 3), (3, 3)]);
+    }
+
+    // Big reads are split into chunks, also when transposed
+    let output_file = output_folder.join("progress_big.bed");
+    let mut bytes = vec![0x6c, 0x1b, 0x01];
+    bytes.resize(3 + 3 * 1_000_000, 0b1111_1111); // 4,000,000 individuals by 3 SNPs, all 0
+    std::fs::write(&output_file, bytes).unwrap();
+    let mut bed = Bed::builder(&output_file)
+        .iid_count(4_000_000)
+        .sid_count(3)
+        .build()?;
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let reports_clone = Arc::clone(&reports);
+    let read_val = ReadOptions::builder()
+        .transpose_output()
+        .progress(move |done, total| reports_clone.lock().unwrap().push((done, total)))
+        .i8()
+        .read(&mut bed)?;
+    assert_eq!(read_val.dim(), (3, 4_000_000));
+    assert!(read_val.iter().all(|&v| v == 0));
+    assert_eq!(*reports.lock().unwrap(), vec![(0, 3), (2, 3), (3, 3)]);
+
+    Ok(())
+}
+
+// A stage that counts the non-missing values it sees.
+struct CountObserved(usize);
+
+impl BlockStage<f64> for CountObserved {
+    fn apply(
+        &mut self,
+        _sid_start: usize,
+        block: &mut nd::ArrayViewMut2<'_, f64>,
+    ) -> Result<(), Box<BedErrorPlus>> {
+        self.0 += block.iter().filter(|v| !v.is_nan()).count();
+        Ok(())
+    }
+}
+
+#[test]
+fn pipeline() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let input_file = output_folder.join("pipeline.bed");
+    let val = nd::array![[0, 1, 2, 0, 2], [2, -127, 0, 1, 1], [1, 1, 0, 2, 0]];
+    WriteOptions::builder(&input_file)
+        .iid(["i1", "i2", "i3"])
+        .allele_1(["A", "C", "G", "T", "A"])
+        .allele_2(["T", "G", "A", "C", "G"])
+        .write(&val)?;
+    let bed = Bed::new(&input_file)?;
+
+    // Select, flip, and write, a block at a time, with the metadata following
+    let read_options = ReadOptions::builder()
+        .iid_index([2, 0])
+        .sid_index([4, 0, 2])
+        .transpose_output()
+        .i8()
+        .build()?;
+    let output_file = output_folder.join("pipeline_out.bed");
+    let summary = bed
+        .pipeline(&read_options)?
+        .chunk_sids(2)
+        .flip([true, false, true])
+        .write(&WriteOptions::builder(&output_file))?;
+    assert_eq!(summary.genotype_counts(), [3, 1, 2]);
+    let out = Bed::new(&output_file)?;
+    assert_eq!(out.read::<i8>()?, nd::array![[2, 1, 2], [0, 0, 0]]);
+    assert_eq!(out.iid()?, nd::array!["i3", "i1"]);
+    assert_eq!(out.allele_1()?, nd::array!["G", "A", "A"]);
+    assert_eq!(out.allele_2()?, nd::array!["A", "T", "G"]);
+
+    // Flipping leaves missing values missing, even when the missing value is 0 or 2
+    let read_options = ReadOptions::builder()
+        .sid_index([1, 2])
+        .missing_value(2)
+        .i8()
+        .build()?;
+    let mut flipped = Vec::new();
+    bed.pipeline(&read_options)?
+        .flip([true, false])
+        .for_each(|_sid_start, block| {
+            flipped.push(block.to_owned());
+            Ok(())
+        })?;
+    assert_eq!(flipped, vec![nd::array![[1, 2], [2, 0], [1, 0]]]);
+
+    // Stages run in order, and the blocks of a standardizing pipeline match a full read
+    let read_options = ReadOptions::builder().f64().build()?;
+    let mut expected = bed.read_with_options(&read_options)?;
+    standardize(&mut expected, Dist::Unit)?;
+    let mut counter = CountObserved(0);
+    let mut actual = nd::Array2::<f64>::zeros((3, 5));
+    let mut starts = Vec::new();
+    bed.pipeline(&read_options)?
+        .chunk_sids(2)
+        .stage(&mut counter)
+        .standardize(Dist::Unit)
+        .map(|sid_start, _block| {
+            starts.push(sid_start);
+            Ok(())
+        })
+        .for_each(|sid_start, block| {
+            actual
+                .slice_mut(s![.., sid_start..sid_start + block.ncols()])
+                .assign(&block);
+            Ok(())
+        })?;
+    assert_eq!(counter.0, 14);
+    assert_eq!(starts, vec![0, 2, 4]);
+    assert!(allclose(&actual.view(), &expected.view(), 1e-12, false));
+
+    // Bad flip masks, zero chunks, and writing over the input are errors
+    let result = bed
+        .pipeline(&read_options)?
+        .flip([true])
+        .for_each(|_, _| Ok(()));
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::BoolArrayVectorWrongLength(5, 1))
+    );
+    let read_options_twice = ReadOptions::builder().sid_index([0, 0]).i8().build()?;
+    let result = bed
+        .pipeline(&read_options_twice)?
+        .flip([true, false])
+        .for_each(|_, _| Ok(()));
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::InconsistentFlip(0))
+    );
+    let result = bed
+        .pipeline(&read_options)?
+        .chunk_sids(0)
+        .for_each(|_, _| Ok(()));
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::ZeroChunkSids));
+    let read_options = ReadOptions::builder().i8().build()?;
+    let result = bed
+        .pipeline(&read_options)?
+        .write(&WriteOptions::builder(&input_file));
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::PipelineOverwritesInput(_))
+    );
+    assert_eq!(bed.read::<i8>()?, val);
+
+    Ok(())
+}
+
+#[test]
+fn grm() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("grm.bed");
+    let val = nd::array![
+        [0, 1, 2, 0, 2],
+        [2, -127, 0, 1, 1],
+        [1, 1, 0, 2, 0],
+        [0, 2, 1, 1, 2],
+        [1, 0, 1, -127, 0]
+    ];
+    WriteOptions::builder(&output_file).write(&val)?;
+    let bed = Bed::new(&output_file)?;
+
+    // Matches Z Zᵀ / M computed all at once, with missing values mean imputed
+    let grm_options = GrmOptions::builder()
+        .iid_index([4, 0, 1, 3])
+        .num_threads(2)
+        .build()?;
+    let grm = bed.grm(&grm_options)?;
+    let read_options = ReadOptions::builder()
+        .iid_index([4, 0, 1, 3])
+        .f64()
+        .build()?;
+    let mut z = bed.read_with_options(&read_options)?;
+    StatsOptions::new()
+        .with_missing_policy(MissingPolicy::Skip)
+        .standardize(&mut z, Dist::Unit)?;
+    let expected = z.dot(&z.t()) / 5.0;
+    assert!(allclose(&grm.view(), &expected.view(), 1e-12, false));
+
+    // Writing a band at a time gives the same values
+    let grm_file = output_folder.join("grm.bin");
+    let grm_options = GrmOptions::builder()
+        .iid_index([4, 0, 1, 3])
+        .band_iids(3)
+        .build()?;
+    bed.grm_to_file(&grm_file, &grm_options)?;
+    let values: Vec<f64> = std::fs::read(&grm_file)?
+        .chunks_exact(8)
+        .map(|bytes| f64::from_le_bytes(bytes.try_into().unwrap()))
+        .collect();
+    assert_eq!(nd::Array2::from_shape_vec((4, 4), values).unwrap(), grm);
+
+    // Missing values can make the GRM NaN
+    let grm_options = GrmOptions::builder()
+        .missing_policy(MissingPolicy::Propagate)
+        .build()?;
+    assert!(bed.grm(&grm_options)?.iter().all(|v| v.is_nan()));
+    let grm_options = GrmOptions::builder()
+        .missing_policy(MissingPolicy::Propagate)
+        .sid_index([0, 2, 4])
+        .build()?;
+    assert!(bed.grm(&grm_options)?.iter().all(|v| v.is_finite()));
+
+    // Bands of no individuals are errors, and leave no file
+    let grm_options = GrmOptions::builder().band_iids(0).build()?;
+    let grm_file = output_folder.join("grm_bad.bin");
+    let result = bed.grm_to_file(&grm_file, &grm_options);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::ZeroBandIids));
+    assert!(!grm_file.exists());
+
+    Ok(())
+}
+
+#[test]
+fn read_options_snapshot() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("snapshot.bed");
+    let val = nd::Array2::from_shape_fn((6, 50), |(i, j)| [0i8, 1, 2, -127][(i + j) % 4]);
+    WriteOptions::builder(&output_file)
+        .chromosome((0..50).map(|j| if j < 30 { "1" } else { "2" }))
+        .bp_position((0..50).map(|j| j * 100 + 1))
+        .write(&val)?;
+    let bed = Bed::new(&output_file)?;
+    let regions = RegionSet::from_regions(["1:1-1000000".parse::<Region>()?]);
+    let read_options = ReadOptions::builder()
+        .iid_index(nd::array![5isize, 0, -1, 3])
+        .sid_index(s![10..40;3])
+        .regions(&regions)
+        .missing_value(-1.0)
+        .read_strategy(ReadStrategy::Scan)
+        .num_threads(2)
+        .f32()
+        .build()?;
+    let snapshot = read_options.snapshot()?;
+    assert_eq!(snapshot.dtype(), "f32");
+    let replayed = snapshot.to_read_options::<f32>()?;
+    assert_eq!(replayed.snapshot()?, snapshot);
+    assert_eq_nan(
+        &bed.read_with_options(&replayed)?,
+        &bed.read_with_options(&read_options)?,
+    );
+    assert_eq!(replayed.missing_value(), -1.0);
+
+    // Equal selections, written differently, give equal snapshots
+    let vec_snapshot = ReadOptions::builder()
+        .iid_index(vec![5isize, 0, -1, 3])
+        .f64()
+        .build()?
+        .snapshot()?;
+    let array_snapshot = ReadOptions::builder()
+        .iid_index(nd::array![5isize, 0, -1, 3])
+        .f64()
+        .build()?
+        .snapshot()?;
+    assert_eq!(vec_snapshot, array_snapshot);
+    assert!(vec_snapshot
+        .to_read_options::<f64>()?
+        .missing_value()
+        .is_nan());
+
+    // The dtype must match, and a predicate can't be replayed
+    let result = snapshot.to_read_options::<f64>();
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::SnapshotDtypeMismatch(_, _))
+    );
+    let snapshot = ReadOptions::builder()
+        .iid_where(|row| row.sex == 1)
+        .i8()
+        .build()?
+        .snapshot()?;
+    let result = snapshot.to_read_options::<i8>();
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::SnapshotHasIidWhere)
+    );
+
+    #[cfg(feature = "serde")]
+    {
+        let json = serde_json::to_string(&vec_snapshot).unwrap();
+        assert!(json.contains(r#""dtype":"f64""#));
+        assert!(json.contains(r#""missing_value":null"#));
+        assert_eq!(json, serde_json::to_string(&array_snapshot).unwrap());
+        let round_trip: ReadOptionsSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(round_trip, vec_snapshot);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn bed_set_stats() -> Result<(), Box<BedErrorPlus>> {
+    use bed_reader::BedSet;
+
+    let output_folder = TempDir::default();
+    let val =
+        nd::Array2::<i8>::from_shape_fn((7, 23), |(iid_i, sid_i)| match (iid_i * 3 + sid_i) % 5 {
+            4 => -127,
+            code => (code % 3) as i8,
+        });
+    let whole_file = output_folder.join("whole.bed");
+    WriteOptions::builder(&whole_file).write(&val)?;
+    let whole = Bed::new(&whole_file)?;
+    let mut paths = Vec::new();
+    for chromosome in 1..=22 {
+        let range = chromosome - 1..if chromosome == 22 { 23 } else { chromosome };
+        let path = output_folder.join(format!("chr{chromosome}.bed"));
+        WriteOptions::builder(&path).write(&val.slice(s![.., range]))?;
+        paths.push(path);
+    }
+
+    // Genome-wide statistics match those of one file with all the SNPs
+    let bed_set = BedSet::new(&paths)?;
+    assert_eq!(bed_set.stats()?, whole.stats()?);
+    for max_concurrent_requests in [1, 4] {
+        let read_options = ReadOptions::builder()
+            .iid_index([6, 0, 2, 3])
+            .sid_index([22, 0, -2, 7, 7])
+            .is_a1_counted(false)
+            .max_concurrent_requests(max_concurrent_requests)
+            .i8()
+            .build()?;
+        let stats_options =
+            StatsOptions::deterministic().with_missing_policy(MissingPolicy::Propagate);
+        let set_stats = bed_set.stats_with_options(&read_options, stats_options)?;
+        let expected = stats_options.stats(&whole, &read_options)?;
+        assert_eq!(set_stats.call_count(), expected.call_count());
+        assert!(set_stats.mean()[1].is_nan());
+        assert_eq!(
+            set_stats.mean().mapv(f64::to_bits),
+            expected.mean().mapv(f64::to_bits)
+        );
+    }
+
+    // Options that select SNPs by metadata aren't supported
+    let read_options = ReadOptions::builder()
+        .score_at_least("r2", 0.5)
+        .f64()
+        .build()?;
+    let result = bed_set.stats_with_options(&read_options, StatsOptions::new());
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::UnsupportedBedSetOption(_))
+    );
+
+    Ok(())
+}
+
+#[test]
+fn bed_dot_products() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("dot.bed");
+    let val = nd::Array2::<i8>::from_shape_fn((9, 13), |(iid_i, sid_i)| {
+        match (iid_i * 5 + sid_i * 3) % 7 {
+            6 => -127,
+            code => (code % 3) as i8,
+        }
+    });
+    WriteOptions::builder(&output_file).write(&val)?;
+    let mut bed = Bed::new(&output_file)?;
+
+    // Matches products with the dense, standardized genotype matrix
+    let iid_index = [8isize, 0, 3, 4, 1];
+    let sid_index = [12isize, 0, 5, 6, 2, 9, 1];
+    let mut x = ReadOptions::builder()
+        .iid_index(iid_index)
+        .sid_index(sid_index)
+        .f64()
+        .read(&mut bed)?;
+    // Beta weighting needs the beta feature
+    #[cfg(feature = "beta")]
+    let dist = Dist::Beta { a: 1.0, b: 25.0 };
+    #[cfg(not(feature = "beta"))]
+    let dist = Dist::Unit;
+    StatsOptions::new().standardize(&mut x, dist)?;
+    let b = nd::Array2::from_shape_fn((7, 2), |(i, j)| (i * 2 + j) as f64 - 3.5);
+    let bt = nd::Array2::from_shape_fn((5, 3), |(i, j)| (i + j * 4) as f64 * 0.25);
+    for num_threads in [1, 3] {
+        let dot_options = DotOptions::builder()
+            .iid_index(iid_index)
+            .sid_index(sid_index)
+            .dist(dist)
+            .num_threads(num_threads)
+            .build()?;
+        let xb = bed_dot(&mut bed, &b.view(), &dot_options)?;
+        assert!(allclose(&xb.view(), &x.dot(&b).view(), 1e-9, false));
+
+        let dot_options = DotOptions::builder()
+            .iid_index(iid_index)
+            .sid_index(sid_index)
+            .dist(dist)
+            .num_threads(num_threads)
+            .is_transposed(true)
+            .build()?;
+        let xtb = bed_dot(&mut bed, &bt.view(), &dot_options)?;
+        assert!(allclose(&xtb.view(), &x.t().dot(&bt).view(), 1e-9, false));
+    }
+
+    // Without standardizing, missing values propagate
+    let dot_options = DotOptions::builder()
+        .is_standardized(false)
+        .is_transposed(true)
+        .build()?;
+    let counts = bed_dot(&mut bed, &nd::Array2::ones((9, 1)).view(), &dot_options)?;
+    for (sid_i, column) in val.columns().into_iter().enumerate() {
+        if column.iter().any(|&v| v == -127) {
+            assert!(counts[(sid_i, 0)].is_nan());
+        } else {
+            let sum: i32 = column.iter().map(|&v| i32::from(v)).sum();
+            assert_eq!(counts[(sid_i, 0)], f64::from(sum));
+        }
+    }
+
+    // b must have one row per SNP, or, if transposed, per individual
+    let result = bed_dot(&mut bed, &b.view(), &dot_options);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::DotShapeMismatch(7, 9))
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "checksum")]
+fn sample_sketches() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let (iid_count, sid_count) = (20, 4000);
+    let val = nd::Array2::from_shape_fn((iid_count, sid_count), |(iid_i, sid_i)| {
+        let code = (iid_i * sid_count + sid_i) as u64 * 2_654_435_761 % 4_294_967_291;
+        if code % 50 == 0 {
+            -127
+        } else {
+            (code % 3) as i8
+        }
+    });
+    let file_a = output_folder.join("a.bed");
+    WriteOptions::builder(&file_a)
+        .chromosome(vec!["1"; sid_count])
+        .bp_position((1..=sid_count as i32).map(|bp| bp * 10))
+        .allele_1(vec!["A"; sid_count])
+        .allele_2(vec!["G"; sid_count])
+        .write(&val)?;
+
+    // Dataset b: other chromosome names, alleles swapped, 5 of a's individuals, a new
+    // individual, and every tenth SNP dropped
+    let order = [7usize, 2, 19, 11, 0];
+    let kept: Vec<usize> = (0..sid_count).filter(|sid_i| sid_i % 10 != 0).collect();
+    let mut val_b = val.select(nd::Axis(0), &order).select(nd::Axis(1), &kept);
+    val_b.mapv_inplace(|v| if v == -127 { v } else { 2 - v });
+    let new_row = nd::Array2::from_shape_fn((1, kept.len()), |(_, sid_i)| (sid_i % 3) as i8);
+    let val_b = nd::concatenate![nd::Axis(0), val_b, new_row];
+    let file_b = output_folder.join("b.bed");
+    WriteOptions::builder(&file_b)
+        .chromosome(vec!["chr1"; kept.len()])
+        .bp_position(kept.iter().map(|&sid_i| (sid_i as i32 + 1) * 10))
+        .allele_1(vec!["G"; kept.len()])
+        .allele_2(vec!["A"; kept.len()])
+        .write(&val_b)?;
+
+    let sketches_a = Bed::new(&file_a)?.sample_sketches(128)?;
+    let sketches_b = Bed::new(&file_b)?.sample_sketches(128)?;
+    assert_eq!(sketches_a.k(), 128);
+    assert_eq!(sketches_a.sketches().dim(), (20, 128));
+    assert!(sketches_a.sid_count() > 150 && sketches_a.sid_count() < 350);
+    assert!(sketches_b.sid_count() < sketches_a.sid_count());
+
+    // Each of b's individuals from a is matched to itself
+    let matches = match_samples(&sketches_b, &sketches_a)?;
+    for (index_b, &index_a) in order.iter().enumerate() {
+        let sample_match = matches
+            .iter()
+            .find(|sample_match| sample_match.index_a() == index_b)
+            .unwrap();
+        assert_eq!(sample_match.index_b(), index_a);
+        assert!(sample_match.similarity() > 0.8);
+    }
+    if let Some(sample_match) = matches.iter().find(|m| m.index_a() == 5) {
+        assert!(sample_match.similarity() < 0.8);
+    }
+
+    // Sketches must have the same number of bins, and at least one
+    let sketches_c = Bed::new(&file_b)?.sample_sketches(64)?;
+    let result = match_samples(&sketches_a, &sketches_c);
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::SketchBinsMismatch(128, 64))
+    );
+    let result = Bed::new(&file_a)?.sample_sketches(0);
+    assert_error_variant!(result, BedErrorPlus::BedError(BedError::ZeroSketchBins));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "rand")]
+fn randomized_pca() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("pca.bed");
+    let (iid_count, sid_count) = (30, 200);
+    let val = nd::Array2::from_shape_fn((iid_count, sid_count), |(iid_i, sid_i)| {
+        let code = (iid_i * sid_count + sid_i) as u64 * 2_654_435_761 % 4_294_967_291;
+        // Three populations, differing at a third of the SNPs
+        if sid_i % 3 == 0 {
+            ((iid_i % 3 + code as usize % 2) % 3) as i8
+        } else if code % 40 == 0 {
+            -127
+        } else {
+            (code % 3) as i8
+        }
+    });
+    WriteOptions::builder(&output_file).write(&val)?;
+    let mut bed = Bed::new(&output_file)?;
+
+    let iid_index = 2..28;
+    let sid_index = s![..;2];
+    let mut x = ReadOptions::builder()
+        .iid_index(iid_index.clone())
+        .sid_index(sid_index)
+        .f64()
+        .read(&mut bed)?;
+    StatsOptions::new().standardize(&mut x, Dist::Unit)?;
+
+    let pca_options = pca::PcaOptions::builder(3)
+        .iid_index(iid_index.clone())
+        .sid_index(sid_index)
+        .num_threads(2)
+        .build()?;
+    let pca = pca::pca(&mut bed, &pca_options)?;
+    assert_eq!(pca.scores().dim(), (26, 3));
+    assert_eq!(pca.loadings().dim(), (100, 3));
+    assert_eq!(pca, pca::pca(&mut bed, &pca_options)?); // same seed, same components
+
+    // Loadings are orthonormal, scores are about X times the loadings, and each loading is
+    // about an eigenvector of Xᵀ X with the squared singular value
+    let identity = pca.loadings().t().dot(pca.loadings());
+    assert!(allclose(
+        &identity.view(),
+        &nd::Array2::<f64>::eye(3).view(),
+        1e-9,
+        false
+    ));
+    assert!(allclose(
+        &pca.scores().view(),
+        &x.dot(pca.loadings()).view(),
+        0.05,
+        false
+    ));
+    let singular_values = pca.singular_values();
+    assert!(singular_values[0] >= singular_values[1] && singular_values[1] >= singular_values[2]);
+    let xtx = x.t().dot(&x);
+    for component in 0..2 {
+        let loading = pca.loadings().column(component);
+        let residual = &xtx.dot(&loading) - &(&loading * singular_values[component].powi(2));
+        let relative = residual.dot(&residual).sqrt() / singular_values[component].powi(2);
+        assert!(relative < 1e-3, "{relative}");
+    }
+
+    // With every component, the squared singular values sum to the squared norm of X
+    let pca_options = pca::PcaOptions::builder(26)
+        .iid_index(iid_index)
+        .sid_index(sid_index)
+        .build()?;
+    let full = pca::pca(&mut bed, &pca_options)?;
+    let total: f64 = full.singular_values().iter().map(|s| s * s).sum();
+    assert!((total - x.iter().map(|v| v * v).sum::<f64>()).abs() < 1e-6 * total);
+
+    // k must be between 1 and the smaller dimension
+    for k in [0, 31] {
+        let result = pca::pca(&mut bed, &pca::PcaOptions::builder(k).build()?);
+        assert_error_variant!(
+            result,
+            BedErrorPlus::BedError(BedError::BadPcaComponentCount(_, 30))
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn sid_filter() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("sid_filter.bed");
+    let val = nd::Array2::from_shape_fn((20, 40), |(i, j)| match (i * 7 + j * 3) % (j % 5 + 2) {
+        0 if j % 3 == 0 => -127,
+        0 => 2,
+        1 => 1,
+        _ => 0,
+    });
+    WriteOptions::builder(&output_file).i8().write(&val)?;
+
+    // The expected statistics, from the values
+    let calls = |j: usize| {
+        val.column(j)
+            .iter()
+            .filter(|&&v| v >= 0)
+            .map(|&v| f64::from(v))
+            .collect::<Vec<_>>()
+    };
+    let passes = |j: usize, min_maf: f64, min_call_rate: f64, min_variance: f64| {
+        let calls = calls(j);
+        if calls.is_empty() {
+            return false;
+        }
+        let count = calls.len() as f64;
+        let mean = calls.iter().sum::<f64>() / count;
+        let variance = calls.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / count;
+        (mean / 2.0).min(1.0 - mean / 2.0) >= min_maf
+            && count / 20.0 >= min_call_rate
+            && variance >= min_variance - 1e-12
+    };
+
+    let mut bed = Bed::new(&output_file)?;
+    let sid_filter = SidFilter::maf_at_least(0.2)
+        .and(SidFilter::call_rate_at_least(0.9))
+        .and(SidFilter::variance_at_least(0.3));
+    let expected: Vec<isize> = (0..40)
+        .filter(|&j| passes(j, 0.2, 0.9, 0.3))
+        .map(|j| j as isize)
+        .collect();
+    assert!(!expected.is_empty() && expected.len() < 40);
+
+    // Filters given separately combine, and apply after the sid index
+    let read_options = ReadOptions::builder()
+        .sid_index(10..)
+        .sid_filter(SidFilter::maf_at_least(0.2))
+        .sid_filter(SidFilter::call_rate_at_least(0.9).and(SidFilter::variance_at_least(0.3)))
+        .i8()
+        .build()?;
+    assert_eq!(read_options.sid_filter(), Some(&sid_filter));
+    let expected_tail: Vec<isize> = expected.iter().copied().filter(|&j| j >= 10).collect();
+    assert_eq!(
+        bed.read_with_options(&read_options)?,
+        val.select(
+            nd::Axis(1),
+            &expected_tail
+                .iter()
+                .map(|&j| j as usize)
+                .collect::<Vec<_>>()
+        )
+    );
+
+    // The filter compiles down to a boolean index
+    let bed_reader::Index::NDArrayBool(mask) = sid_filter.to_index(&mut bed)? else {
+        panic!("expected a boolean index");
+    };
+    let mask_sids: Vec<isize> = (0..40).filter(|&j| mask[j]).map(|j| j as isize).collect();
+    assert_eq!(mask_sids, expected);
+
+    // Reads through &self, for example, from an Arc, compute the statistics on first use
+    let shared_bed = Arc::new(Bed::new(&output_file)?);
+    assert_eq!(
+        shared_bed.read_with_options(&read_options)?,
+        bed.read_with_options(&read_options)?
+    );
+
+    // The filter is kept in snapshots
+    let replayed = read_options.snapshot()?.to_read_options::<i8>()?;
+    assert_eq!(replayed.sid_filter(), Some(&sid_filter));
+
+    Ok(())
+}
+
+#[test]
+fn qc_metrics() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("qc.bed");
+    let val = nd::Array2::from_shape_fn((24, 30), |(i, j)| {
+        let value = (i * i * 3 + j * 7 + i * j) % (j % 4 + 3);
+        if (i + 2 * j) % 11 == 0 {
+            -127
+        } else {
+            [0, 1, 2, 1, 0, 0, 2][value] as i8
+        }
+    });
+    WriteOptions::builder(&output_file).i8().write(&val)?;
+    let mut bed = Bed::new(&output_file)?;
+
+    // The HWE p-value, by summing the probabilities, from the multinomial formula, of every
+    // number of heterozygotes as unlikely as the one seen
+    let ln_factorial = |n: usize| (1..=n).map(|k| (k as f64).ln()).sum::<f64>();
+    let hwe_brute = |column: nd::ArrayView1<i8>| {
+        let counts = [0, 1, 2].map(|g| column.iter().filter(|&&v| v == g).count());
+        let n = counts.iter().sum::<usize>();
+        let rare = (2 * counts[0] + counts[1]).min(2 * counts[2] + counts[1]);
+        let prob = |het: usize| {
+            let hom_rare = (rare - het) / 2;
+            let hom_common = n - het - hom_rare;
+            (ln_factorial(n)
+                - ln_factorial(het)
+                - ln_factorial(hom_rare)
+                - ln_factorial(hom_common)
+                + het as f64 * 2f64.ln()
+                + ln_factorial(rare)
+                + ln_factorial(2 * n - rare)
+                - ln_factorial(2 * n))
+            .exp()
+        };
+        let seen = prob(counts[1]);
+        (rare % 2..=rare)
+            .step_by(2)
+            .map(prob)
+            .filter(|&p| p <= seen * (1.0 + 1e-8))
+            .sum::<f64>()
+            .min(1.0)
+    };
+    let pvalues = qc::hwe_pvalues(&mut bed)?;
+    assert_eq!(pvalues.len(), 30);
+    for (j, column) in val.axis_iter(nd::Axis(1)).enumerate() {
+        let expected = hwe_brute(column);
+        assert!(
+            (pvalues[j] - expected).abs() < 1e-9,
+            "{j}: {} {expected}",
+            pvalues[j]
+        );
+    }
+    assert!(pvalues.iter().any(|&p| p < 0.05) && pvalues.iter().any(|&p| p > 0.5));
+
+    // The per-sample metrics, in iid order
+    let missing_rate = qc::per_sample_missing_rate(&mut bed)?;
+    let heterozygosity = qc::heterozygosity(&mut bed)?;
+    for (i, row) in val.axis_iter(nd::Axis(0)).enumerate() {
+        let missing = row.iter().filter(|&&v| v == -127).count();
+        let het = row.iter().filter(|&&v| v == 1).count();
+        assert_eq!(missing_rate[i], missing as f64 / 30.0);
+        assert_eq!(heterozygosity[i], het as f64 / (30 - missing) as f64);
+    }
+
+    // With no calls, NaN
+    let output_file = output_folder.join("qc_missing.bed");
+    WriteOptions::builder(&output_file).write(&nd::array![[-127, 0], [-127, 1]])?;
+    let mut bed = Bed::new(&output_file)?;
+    let pvalues = qc::hwe_pvalues(&mut bed)?;
+    assert!(pvalues[0].is_nan() && pvalues[1] == 1.0);
+    let output_file = output_folder.join("qc_missing_iid.bed");
+    WriteOptions::builder(&output_file).write(&nd::array![[-127, -127], [2, 1]])?;
+    let mut bed = Bed::new(&output_file)?;
+    let heterozygosity = qc::heterozygosity(&mut bed)?;
+    assert!(heterozygosity[0].is_nan() && heterozygosity[1] == 0.5);
+
+    Ok(())
+}
+
+#[test]
+fn extract_qc() -> Result<(), Box<BedErrorPlus>> {
+    let output_folder = TempDir::default();
+    let input_file = output_folder.join("raw.bed");
+    let val = nd::Array2::from_shape_fn((30, 40), |(i, j)| {
+        if (i * 5 + j * 3) % (i % 6 + 7) == 0 {
+            -127
+        } else {
+            [0i8, 1, 2, 1, 1, 0][(i * i + j * (j % 5 + 1)) % (j % 4 + 3)]
+        }
+    });
+    let iid: Vec<String> = (0..30).map(|i| format!("iid{i}")).collect();
+    let sid: Vec<String> = (0..40).map(|j| format!("sid{j}")).collect();
+    WriteOptions::builder(&input_file)
+        .iid(&iid)
+        .sid(&sid)
+        .write(&val)?;
+    let mut bed = Bed::new(&input_file)?;
+    let read_options = ReadOptions::builder().sid_index(5..).i8().build()?;
+    let qc_filter = qc::QcFilter::builder()
+        .sample_missing_max(0.1)
+        .missing_max(0.05)
+        .maf_min(0.2)
+        .hwe_p_min(0.01)
+        .build()?;
+    assert_eq!(qc_filter.maf_min(), Some(0.2));
+
+    // Expected: individuals first, over the selected SNPs, then SNPs over those kept
+    let kept_iids: Vec<usize> = (0..30)
+        .filter(|&i| {
+            let missing = (5..40).filter(|&j| val[(i, j)] == -127).count();
+            missing as f64 / 35.0 <= 0.1
+        })
+        .collect();
+    assert!(kept_iids.len() > 5 && kept_iids.len() < 30);
+    let middle_file = output_folder.join("middle.bed");
+    let middle_options = ReadOptions::builder()
+        .iid_index(kept_iids.iter().map(|&i| i as isize).collect::<Vec<_>>())
+        .sid_index(5..)
+        .i8()
+        .build()?;
+    bed.extract(&middle_options, &WriteOptions::builder(&middle_file))?;
+    let mut middle = Bed::new(&middle_file)?;
+    let stats = middle.stats()?;
+    let pvalues = qc::hwe_pvalues(&mut middle)?;
+    let kept_sids: Vec<usize> = (0..35)
+        .filter(|&j| stats.missing_rate()[j] <= 0.05 && stats.maf()[j] >= 0.2 && pvalues[j] >= 0.01)
+        .collect();
+    assert!(!kept_sids.is_empty() && kept_sids.len() < 35);
+
+    let output_file = output_folder.join("clean.bed");
+    bed.extract_qc(
+        &qc_filter,
+        &read_options,
+        &WriteOptions::builder(&output_file),
+    )?;
+    let clean = Bed::new(&output_file)?;
+    let expected_iid: Vec<String> = kept_iids.iter().map(|&i| iid[i].clone()).collect();
+    let expected_sid: Vec<String> = kept_sids.iter().map(|&j| sid[j + 5].clone()).collect();
+    assert_eq!(clean.iid()?.to_vec(), expected_iid);
+    assert_eq!(clean.sid()?.to_vec(), expected_sid);
+    let expected_val = val.select(nd::Axis(0), &kept_iids).select(
+        nd::Axis(1),
+        &kept_sids.iter().map(|&j| j + 5).collect::<Vec<_>>(),
+    );
+    assert_eq!(clean.read::<i8>()?, expected_val);
+
+    // With no thresholds, everything selected is kept
+    let output_file = output_folder.join("all.bed");
+    let qc_filter = qc::QcFilter::builder().build()?;
+    bed.extract_qc(
+        &qc_filter,
+        &read_options,
+        &WriteOptions::builder(&output_file),
+    )?;
+    assert_eq!(
+        Bed::new(&output_file)?.read::<i8>()?,
+        val.slice(nd::s![.., 5..])
+    );
+
+    Ok(())
+}
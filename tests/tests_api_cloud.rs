@@ -2451,3 +2451,70 @@ async fn doc_test_cloud_1() -> Result<(), Box<BedErrorPlus>> {
     println!("{:?}", bed_cloud.sid().await?); // Outputs ndarray ["sid1", "sid2", "sid3", "sid4"]
     Ok(())
 }
+
+#[tokio::test]
+async fn write_cloud() -> Result<(), Box<BedErrorPlus>> {
+    use bed_reader::{Bed, DuplicateSidPolicy, WriteOptions};
+
+    let output_folder = temp_testdir::TempDir::default();
+    let output_file = output_folder.join("cloud.bed");
+    let url = abs_path_to_url_string(&output_file)?;
+    let val = nd::array![
+        [1.0, 0.0, f64::NAN, 0.0],
+        [2.0, 0.0, 1.0, 2.0],
+        [0.0, 1.0, 2.0, 0.0]
+    ];
+    let summary = WriteOptions::builder(&url)
+        .iid(["i1", "i2", "i3"])
+        .sid(["s1", "s2", "s3", "s4"])
+        .write_cloud_with_options(&val, EMPTY_OPTIONS)
+        .await?;
+    assert_eq!(summary.bytes_written(), 7);
+    assert_eq!(summary.genotype_counts(), [5, 3, 3]);
+
+    // The fileset is the same as one written locally
+    let local_file = output_folder.join("local.bed");
+    WriteOptions::builder(&local_file)
+        .iid(["i1", "i2", "i3"])
+        .sid(["s1", "s2", "s3", "s4"])
+        .write(&val)?;
+    for extension in ["bed", "fam", "bim"] {
+        assert_eq!(
+            std::fs::read(output_file.with_extension(extension))?,
+            std::fs::read(local_file.with_extension(extension))?
+        );
+    }
+    let mut bed_cloud = BedCloud::new(&url).await?;
+    assert_eq_nan(&bed_cloud.read::<f64>().await?, &val);
+
+    // Options, such as dedup_sids, apply
+    let output_file = output_folder.join("dedup.bed");
+    let url = abs_path_to_url_string(&output_file)?;
+    WriteOptions::builder(&url)
+        .sid(["s1", "s2", "s1", "s4"])
+        .dedup_sids(DuplicateSidPolicy::KeepFirst)
+        .skip_fam()
+        .write_cloud(&val)
+        .await?;
+    assert!(!output_file.with_extension("fam").exists());
+    let mut bed = Bed::builder(&output_file).iid_count(3).build()?;
+    assert_eq!(bed.sid()?, nd::array!["s1", "s2", "s4"]);
+    assert_eq_nan(&bed.read::<f64>()?, &val.select(nd::Axis(1), &[0, 1, 3]));
+
+    // On a bad value, nothing is uploaded
+    let output_file = output_folder.join("bad.bed");
+    let url = abs_path_to_url_string(&output_file)?;
+    let result = WriteOptions::builder(&url)
+        .validate(true)
+        .write_cloud(&nd::array![[0, 1], [2, 3]])
+        .await;
+    assert_error_variant!(
+        result,
+        BedErrorPlus::BedError(BedError::BadValueAt(1, 1, _, _))
+    );
+    assert!(!output_file.exists());
+    // No staged upload is left behind
+    assert_eq!(std::fs::read_dir(&*output_folder)?.count(), 8);
+
+    Ok(())
+}
@@ -0,0 +1,77 @@
+// GlobalConfig changes process-wide defaults, so its tests run in their own test binary, one
+// after another in a single test, rather than beside the tests in tests_api.rs.
+
+use bed_reader::assert_error_variant;
+use bed_reader::Bed;
+use bed_reader::BedErrorPlus;
+use bed_reader::GlobalConfig;
+use bed_reader::MissingPolicy;
+use bed_reader::ReadOptions;
+use bed_reader::StatsOptions;
+use bed_reader::WriteOptions;
+use ndarray as nd;
+use temp_testdir::TempDir;
+
+#[test]
+fn global_config() -> Result<(), Box<BedErrorPlus>> {
+    std::env::remove_var("BED_READER_NUM_THREADS");
+    std::env::remove_var("NUM_THREADS");
+    std::env::remove_var("BED_READER_MAX_CONCURRENT_REQUESTS");
+    std::env::remove_var("BED_READER_MAX_CHUNK_BYTES");
+
+    // Built-in defaults
+    assert_eq!(GlobalConfig::current(), GlobalConfig::new());
+    let effective = GlobalConfig::effective()?;
+    assert_eq!(effective.num_threads(), Some(0));
+    assert_eq!(effective.max_concurrent_requests(), Some(10));
+    assert_eq!(effective.max_chunk_bytes(), Some(8_000_000));
+    assert_eq!(effective.missing_policy(), Some(MissingPolicy::Skip));
+
+    // Environment variables, in order of priority
+    std::env::set_var("NUM_THREADS", "3");
+    std::env::set_var("BED_READER_MAX_CHUNK_BYTES", "1000");
+    assert_eq!(GlobalConfig::effective()?.num_threads(), Some(3));
+    std::env::set_var("BED_READER_NUM_THREADS", "2");
+    let from_env = GlobalConfig::from_env()?;
+    assert_eq!(from_env.num_threads(), Some(2));
+    assert_eq!(from_env.max_chunk_bytes(), Some(1000));
+    assert_eq!(from_env.max_concurrent_requests(), None);
+
+    // An installed configuration overrides the environment, and a call's options override both
+    let previous = GlobalConfig::new()
+        .with_num_threads(1)
+        .with_missing_policy(MissingPolicy::Propagate)
+        .install();
+    assert_eq!(previous, GlobalConfig::new());
+    let effective = GlobalConfig::effective()?;
+    assert_eq!(effective.num_threads(), Some(1));
+    assert_eq!(effective.max_chunk_bytes(), Some(1000));
+
+    let output_folder = TempDir::default();
+    let output_file = output_folder.join("global_config.bed");
+    WriteOptions::builder(&output_file).write(&nd::array![[0, 1], [2, -127], [1, 1]])?;
+    let bed = Bed::new(&output_file)?;
+    assert_eq!(
+        StatsOptions::new().missing_policy(),
+        MissingPolicy::Propagate
+    );
+    let stats = bed.stats()?;
+    assert!(stats.mean()[1].is_nan());
+    let read_options = ReadOptions::builder().f64().build()?;
+    let stats = StatsOptions::new()
+        .with_missing_policy(MissingPolicy::Skip)
+        .stats(&bed, &read_options)?;
+    assert_eq!(stats.mean()[1], 1.0);
+
+    // A bad environment variable is an error only where it's needed
+    GlobalConfig::new().install();
+    std::env::set_var("BED_READER_NUM_THREADS", "many");
+    let result = bed.read::<i8>();
+    assert_error_variant!(result, BedErrorPlus::ParseIntError(_));
+    GlobalConfig::new().with_num_threads(0).install();
+    assert_eq!(bed.read::<i8>()?.dim(), (3, 2));
+    std::env::remove_var("BED_READER_NUM_THREADS");
+    GlobalConfig::new().install();
+
+    Ok(())
+}